@@ -2,6 +2,7 @@ use std::env;
 
 fn main() {
     prep_net();
+    select_slider_lookup();
     build_dependencies();
     generate_bindings();
 }
@@ -21,6 +22,20 @@ fn prep_net() {
     std::fs::copy(net_path, "viridithas.nnue.zst").unwrap();
 }
 
+/// Slider attacks are looked up with `pext` only if the `pext` feature asks for it and
+/// the target has BMI2. It's opt-in rather than following BMI2 alone, because AMD's
+/// Zen 1 and Zen 2 have BMI2 but microcode `pext`, running it far slower than a magic
+/// lookup, so generic x86-64-v3 and `target-cpu=native` builds keep the magics.
+fn select_slider_lookup() {
+    println!("cargo::rustc-check-cfg=cfg(pext)");
+    let x86_64 = env::var("CARGO_CFG_TARGET_ARCH").is_ok_and(|arch| arch == "x86_64");
+    let bmi2 = env::var("CARGO_CFG_TARGET_FEATURE")
+        .is_ok_and(|features| features.split(',').any(|feature| feature == "bmi2"));
+    if env::var_os("CARGO_FEATURE_PEXT").is_some() && x86_64 && bmi2 {
+        println!("cargo::rustc-cfg=pext");
+    }
+}
+
 fn build_dependencies() {
     #[cfg(feature = "syzygy")]
     build_fathom();
@@ -0,0 +1,354 @@
+//! Building Polyglot books from collections of games.
+//!
+//! Games are read either from PGN, or from EPD files in which consecutive
+//! positions one legal move apart are taken to be successive positions of one
+//! game, as in text dumps of self-play data. Each book move is weighted by the
+//! points scored with it, so moves that are played often and score well are
+//! played most often from the book.
+
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+};
+
+use anyhow::{Context, bail};
+
+use crate::chess::{
+    board::Board,
+    chessmove::Move,
+    fen::Fen,
+    pgn::{self, Token},
+    piece::Colour,
+};
+
+use super::{ENTRY_SIZE, encode_move, polyglot_key};
+
+/// The result of a game.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    WhiteWin,
+    Draw,
+    BlackWin,
+}
+
+impl Outcome {
+    /// Parse a result, as a PGN result token or as White's score.
+    fn parse(text: &str) -> Option<Self> {
+        match text.trim_matches(|c| matches!(c, '[' | ']' | '"')) {
+            "1-0" | "1.0" => Some(Self::WhiteWin),
+            "1/2-1/2" | "0.5" => Some(Self::Draw),
+            "0-1" | "0.0" => Some(Self::BlackWin),
+            _ => None,
+        }
+    }
+
+    /// Points scored by `colour`, counting a win as two and a draw as one.
+    const fn half_points(self, colour: Colour) -> u32 {
+        match (self, colour) {
+            (Self::WhiteWin, Colour::White) | (Self::BlackWin, Colour::Black) => 2,
+            (Self::Draw, _) => 1,
+            _ => 0,
+        }
+    }
+}
+
+/// A game read from a corpus.
+#[derive(Debug)]
+pub struct Game {
+    pub start: Board,
+    pub moves: Vec<Move>,
+    pub outcome: Option<Outcome>,
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+struct MoveStats {
+    games: u32,
+    half_points: u32,
+}
+
+/// Accumulates move statistics from games, and writes them out as a book.
+#[derive(Debug)]
+pub struct BookBuilder {
+    max_ply: usize,
+    stats: HashMap<(u64, u16), MoveStats>,
+}
+
+impl BookBuilder {
+    /// Create a builder that records moves made before game ply `max_ply`.
+    pub fn new(max_ply: usize) -> Self {
+        Self {
+            max_ply,
+            stats: HashMap::new(),
+        }
+    }
+
+    /// Record the moves of a game. Games without a known result count each move as a draw,
+    /// so that they contribute frequency but not score.
+    pub fn add_game(&mut self, game: &Game) {
+        let mut board = game.start.clone();
+        for &m in &game.moves {
+            if board.ply() >= self.max_ply {
+                break;
+            }
+            let stats = self
+                .stats
+                .entry((polyglot_key(&board), encode_move(m)))
+                .or_default();
+            stats.games += 1;
+            stats.half_points += game.outcome.map_or(1, |o| o.half_points(board.turn()));
+            board.make_move_simple(m);
+        }
+    }
+
+    /// The book entries as (key, move, weight), sorted by key and then by descending weight.
+    /// Moves played in fewer than `min_games` games, or that never scored, are left out.
+    /// Weights are scaled down if needed to fit the format.
+    pub fn entries(&self, min_games: u32) -> Vec<(u64, u16, u16)> {
+        let kept = self
+            .stats
+            .iter()
+            .filter(|(_, s)| s.games >= min_games && s.half_points > 0)
+            .collect::<Vec<_>>();
+        let max = kept.iter().map(|(_, s)| s.half_points).max().unwrap_or(0);
+        let scale = f64::from(u16::MAX) / f64::from(max.max(u32::from(u16::MAX)));
+        let mut entries = kept
+            .into_iter()
+            .map(|(&(key, mv), s)| {
+                #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+                let weight = ((f64::from(s.half_points) * scale) as u16).max(1);
+                (key, mv, weight)
+            })
+            .collect::<Vec<_>>();
+        entries.sort_unstable_by_key(|&(key, mv, weight)| (key, std::cmp::Reverse(weight), mv));
+        entries
+    }
+
+    /// Write the book in Polyglot format, returning the number of entries written.
+    pub fn write(&self, mut out: impl Write, min_games: u32) -> std::io::Result<usize> {
+        let entries = self.entries(min_games);
+        for &(key, mv, weight) in &entries {
+            let mut entry = [0; ENTRY_SIZE];
+            entry[..8].copy_from_slice(&key.to_be_bytes());
+            entry[8..10].copy_from_slice(&mv.to_be_bytes());
+            entry[10..12].copy_from_slice(&weight.to_be_bytes());
+            out.write_all(&entry)?;
+        }
+        Ok(entries.len())
+    }
+}
+
+/// Read the games in a PGN file. Comments, variations, and annotations are skipped,
+/// a game is cut short at the first move that can't be parsed, and a game whose
+/// `FEN` tag can't be read is dropped.
+pub fn parse_pgn(text: &str) -> Vec<Game> {
+    pgn::split_games(text)
+        .into_iter()
+        .filter_map(|game| {
+            let start = game.start_position().ok()?;
+            let moves = parse_movetext(&start, &game.movetext);
+            Some(Game {
+                start,
+                moves,
+                outcome: game.tag("Result").and_then(Outcome::parse),
+            })
+        })
+        .collect()
+}
+
+/// Parse the moves of a game's movetext, starting from `start`.
+fn parse_movetext(start: &Board, movetext: &str) -> Vec<Move> {
+    let mut board = start.clone();
+    let mut moves = Vec::new();
+    for token in pgn::mainline(movetext) {
+        match token {
+            Token::Move(san) => {
+                let Ok(m) = board.parse_san(san) else {
+                    break;
+                };
+                moves.push(m);
+                board.make_move_simple(m);
+            }
+            Token::Comment(_) => {}
+            Token::Result(_) => break,
+        }
+    }
+    moves
+}
+
+/// Read the games in an EPD file, where each line holds a position and,
+/// optionally, the game result as a final `1-0` / `1.0`-style token.
+/// Runs of positions that are one legal move apart are joined into games.
+pub fn parse_epd(text: &str) -> Vec<Game> {
+    let mut games = Vec::<Game>::new();
+    let mut last: Option<Board> = None;
+    for line in text.lines() {
+        let Ok(parsed) = Fen::parse_relaxed(line) else {
+            continue;
+        };
+        let mut board = Board::startpos();
+        board.set_from_fen(&parsed);
+        let outcome = line
+            .rsplit(|c: char| c.is_whitespace() || c == '|')
+            .find(|t| !t.is_empty())
+            .and_then(Outcome::parse);
+
+        let connecting_move = last.as_ref().and_then(|prev| {
+            prev.legal_moves_iter().find(|&m| {
+                let mut after = prev.clone();
+                after.make_move_simple(m);
+                after.state.keys.zobrist == board.state.keys.zobrist
+            })
+        });
+        match (connecting_move, games.last_mut()) {
+            (Some(m), Some(game)) => {
+                game.moves.push(m);
+                game.outcome = game.outcome.or(outcome);
+            }
+            _ => games.push(Game {
+                start: board.clone(),
+                moves: Vec::new(),
+                outcome,
+            }),
+        }
+        last = Some(board);
+    }
+    games.retain(|g| !g.moves.is_empty());
+    games
+}
+
+/// Build a Polyglot book from the games in `input`, a PGN or EPD file, and write it to `output`.
+pub fn make_book(
+    input: &Path,
+    output: &Path,
+    max_ply: usize,
+    min_games: u32,
+) -> anyhow::Result<()> {
+    if output.try_exists()? {
+        bail!("Output file already exists.");
+    }
+    let text = std::fs::read_to_string(input)
+        .with_context(|| format!("Failed to read {}", input.display()))?;
+    let is_pgn = input
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("pgn"));
+    let games = if is_pgn {
+        parse_pgn(&text)
+    } else {
+        parse_epd(&text)
+    };
+
+    let mut builder = BookBuilder::new(max_ply);
+    for game in &games {
+        builder.add_game(game);
+    }
+
+    let file = File::create(output).with_context(|| "Failed to create output file")?;
+    let mut writer = BufWriter::new(file);
+    let written = builder
+        .write(&mut writer, min_games)
+        .with_context(|| "Failed to write book")?;
+    writer
+        .flush()
+        .with_context(|| "Failed to flush output file")?;
+    println!(
+        "Wrote {written} entries from {} games to {}",
+        games.len(),
+        output.display()
+    );
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::book::PolyglotBook;
+
+    const PGN: &str = r#"[Event "one"]
+[Result "1-0"]
+
+1. e4 {best by test} e5 (1... c5 2. Nf3) 2. Nf3! Nc6 3. Bb5 $1 a6 1-0
+
+[Event "two"]
+[Result "0-1"]
+
+1.e4 c5 2. Nf3 d6 0-1
+
+[Event "three"]
+[Result "1/2-1/2"]
+
+1. d4 d5 2. Zz9 Nf6 1/2-1/2
+"#;
+
+    #[test]
+    fn pgn_games() {
+        let games = parse_pgn(PGN);
+        assert_eq!(games.len(), 3);
+        assert_eq!(games[0].moves.len(), 6);
+        assert_eq!(games[0].outcome, Some(Outcome::WhiteWin));
+        assert_eq!(games[1].moves.len(), 4);
+        assert_eq!(games[1].outcome, Some(Outcome::BlackWin));
+        // the bad move ends the game.
+        assert_eq!(games[2].moves.len(), 2);
+    }
+
+    #[test]
+    fn pgn_chess960_start() {
+        let pgn = r#"[Variant "Chess960"]
+[FEN "bbqnnrkr/pppppppp/8/8/8/8/PPPPPPPP/BBQNNRKR w KQkq - 0 1"]
+[SetUp "1"]
+
+1. e4 e5 *
+"#;
+        let games = parse_pgn(pgn);
+        assert_eq!(games.len(), 1);
+        assert_eq!(games[0].start.rules(), crate::chess::board::Rules::Chess960);
+        assert_eq!(games[0].moves.len(), 2);
+    }
+
+    #[test]
+    fn built_book_round_trips() {
+        let mut builder = BookBuilder::new(2);
+        for game in parse_pgn(PGN) {
+            builder.add_game(&game);
+        }
+        let mut bytes = Vec::new();
+        assert_eq!(builder.write(&mut bytes, 1).unwrap(), 4);
+        let book = PolyglotBook::from_bytes(&bytes).unwrap();
+
+        let start = Board::startpos();
+        let e4 = start.parse_uci("e2e4").unwrap();
+        let d4 = start.parse_uci("d2d4").unwrap();
+        // e4 scored a win and a loss, d4 a draw.
+        let moves = book.moves(&start);
+        assert_eq!(moves.len(), 2);
+        assert_eq!(moves[0], (e4, 2));
+        assert_eq!(moves[1], (d4, 1));
+
+        // after 1. e4, black's c5 won and e5 lost, so only c5 is kept.
+        let mut board = start;
+        board.make_move_simple(e4);
+        let c5 = board.parse_uci("c7c5").unwrap();
+        assert_eq!(book.moves(&board), vec![(c5, 2)]);
+
+        // moves from beyond the ply limit aren't recorded.
+        board.make_move_simple(c5);
+        assert!(book.moves(&board).is_empty());
+    }
+
+    #[test]
+    fn epd_games() {
+        let epd = "\
+rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1 | 10 | 0.5
+rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1 | 20 | 0.5
+rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 2 | 15 | 0.5
+4k3/8/8/8/8/8/8/3QK3 w - - 0 1 | 900 | 1.0
+";
+        let games = parse_epd(epd);
+        assert_eq!(games.len(), 1);
+        assert_eq!(games[0].moves.len(), 2);
+        assert_eq!(games[0].outcome, Some(Outcome::Draw));
+    }
+}
@@ -0,0 +1,284 @@
+//! Opening books in the Polyglot `.bin` format.
+//!
+//! A Polyglot book is a sequence of 16-byte big-endian entries, sorted by key:
+//! a 64-bit position key, a 16-bit move, a 16-bit weight, and 32 bits of learning
+//! data that we ignore. Positions are keyed with Polyglot's own Zobrist scheme,
+//! which is unrelated to the engine's, so we compute those keys separately.
+
+pub mod builder;
+mod random;
+
+use std::path::Path;
+
+use crate::{
+    chess::{
+        board::{Board, movegen::pawn_attacks_by},
+        chessmove::Move,
+        piece::{Colour, PieceType},
+        squareset::SquareSet,
+        types::Square,
+    },
+    errors::BookError,
+    rng::XorShiftState,
+};
+
+use self::random::POLYGLOT_RANDOM;
+
+/// The default value of the `BookDepth` option.
+pub const DEFAULT_BOOK_DEPTH: usize = 20;
+/// The largest value of the `BookDepth` option.
+pub const MAX_BOOK_DEPTH: usize = 1000;
+
+const ENTRY_SIZE: usize = 16;
+const CASTLE_OFFSET: usize = 768;
+const EP_OFFSET: usize = 772;
+const TURN_OFFSET: usize = 780;
+
+/// A single book entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct BookEntry {
+    key: u64,
+    mv: u16,
+    weight: u16,
+}
+
+/// An opening book loaded into memory.
+#[derive(Debug, Default)]
+pub struct PolyglotBook {
+    entries: Vec<BookEntry>,
+}
+
+impl PolyglotBook {
+    /// Read a Polyglot book from `path`.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, BookError> {
+        let bytes = std::fs::read(path).map_err(|e| BookError::Io(e.to_string()))?;
+        Self::from_bytes(&bytes)
+    }
+
+    /// Parse a Polyglot book from its raw contents.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, BookError> {
+        if !bytes.len().is_multiple_of(ENTRY_SIZE) {
+            return Err(BookError::BadLength(bytes.len()));
+        }
+        let mut entries = bytes
+            .chunks_exact(ENTRY_SIZE)
+            .map(|chunk| {
+                let (key, rest) = chunk.split_at(8);
+                let (mv, rest) = rest.split_at(2);
+                let (weight, _learn) = rest.split_at(2);
+                BookEntry {
+                    key: u64::from_be_bytes(key.try_into().unwrap()),
+                    mv: u16::from_be_bytes(mv.try_into().unwrap()),
+                    weight: u16::from_be_bytes(weight.try_into().unwrap()),
+                }
+            })
+            .collect::<Vec<_>>();
+        // books are meant to be sorted already, but don't rely on it.
+        entries.sort_by_key(|e| e.key);
+        Ok(Self { entries })
+    }
+
+    /// The number of entries in the book.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// The legal book moves for `board`, with their weights.
+    pub fn moves(&self, board: &Board) -> Vec<(Move, u16)> {
+        let key = polyglot_key(board);
+        let start = self.entries.partition_point(|e| e.key < key);
+        self.entries[start..]
+            .iter()
+            .take_while(|e| e.key == key)
+            .filter_map(|e| decode_move(board, e.mv).map(|m| (m, e.weight)))
+            .collect()
+    }
+
+    /// Pick a book move for `board`, at random in proportion to the entry weights.
+    /// Entries with zero weight are never played.
+    pub fn probe(&self, board: &Board, rng: &mut XorShiftState) -> Option<Move> {
+        let moves = self.moves(board);
+        let total = moves.iter().map(|&(_, w)| u64::from(w)).sum::<u64>();
+        if total == 0 {
+            return None;
+        }
+        let mut pick = rng.next_below(total);
+        for (m, w) in moves {
+            if pick < u64::from(w) {
+                return Some(m);
+            }
+            pick -= u64::from(w);
+        }
+        None
+    }
+}
+
+/// Compute the Polyglot key of the position on `board`.
+pub fn polyglot_key(board: &Board) -> u64 {
+    let mut key = 0;
+    for sq in Square::all() {
+        if let Some(piece) = board.mailbox[sq] {
+            // polyglot orders the colours black-first.
+            let kind =
+                2 * piece.piece_type() as usize + usize::from(piece.colour() == Colour::White);
+            key ^= POLYGLOT_RANDOM[64 * kind + sq as usize];
+        }
+    }
+
+    let rights = board.castling_rights().hashkey_index();
+    for i in 0..4 {
+        if rights & (1 << i) != 0 {
+            key ^= POLYGLOT_RANDOM[CASTLE_OFFSET + i];
+        }
+    }
+
+    // the en passant file is only hashed if a pawn is placed to make the capture.
+    if let Some(ep) = board.ep_sq() {
+        let us = board.turn();
+        let capturers = pawn_attacks_by(SquareSet::from_square(ep), !us) & board.pawns(us);
+        if capturers != SquareSet::EMPTY {
+            key ^= POLYGLOT_RANDOM[EP_OFFSET + ep.file() as usize];
+        }
+    }
+
+    if board.turn() == Colour::White {
+        key ^= POLYGLOT_RANDOM[TURN_OFFSET];
+    }
+
+    key
+}
+
+/// Encode a move in Polyglot's format.
+fn encode_move(m: Move) -> u16 {
+    let promotion = match m.promotion_type() {
+        Some(PieceType::Knight) => 1,
+        Some(PieceType::Bishop) => 2,
+        Some(PieceType::Rook) => 3,
+        Some(PieceType::Queen) => 4,
+        _ => 0,
+    };
+    (promotion << 12) | ((m.from() as u16) << 6) | m.to() as u16
+}
+
+/// Find the legal move on `board` encoded by a Polyglot move.
+/// Polyglot encodes castling as the king capturing its own rook, as we do.
+fn decode_move(board: &Board, raw: u16) -> Option<Move> {
+    #![allow(clippy::cast_possible_truncation)]
+    let to = Square::new((raw & 0o77) as u8)?;
+    let from = Square::new(((raw >> 6) & 0o77) as u8)?;
+    let promotion = match (raw >> 12) & 0b111 {
+        0 => None,
+        1 => Some(PieceType::Knight),
+        2 => Some(PieceType::Bishop),
+        3 => Some(PieceType::Rook),
+        4 => Some(PieceType::Queen),
+        _ => return None,
+    };
+    board
+        .legal_moves_iter()
+        .find(|m| m.from() == from && m.to() == to && m.promotion_type() == promotion)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // test vectors from the Polyglot book format specification.
+    const KEYS: [(&str, u64); 9] = [
+        (
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            0x463B_9618_1691_FC9C,
+        ),
+        (
+            "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1",
+            0x823C_9B50_FD11_4196,
+        ),
+        (
+            "rnbqkbnr/ppp1pppp/8/3p4/4P3/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 2",
+            0x0756_B944_61C5_0FB0,
+        ),
+        (
+            "rnbqkbnr/ppp1pppp/8/3pP3/8/8/PPPP1PPP/RNBQKBNR b KQkq - 0 2",
+            0x662F_AFB9_65DB_29D4,
+        ),
+        (
+            "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+            0x22A4_8B5A_8E47_FF78,
+        ),
+        (
+            "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPPKPPP/RNBQ1BNR b kq - 0 3",
+            0x652A_607C_A3F2_42C1,
+        ),
+        (
+            "rnbq1bnr/ppp1pkpp/8/3pPp2/8/8/PPPPKPPP/RNBQ1BNR w - - 0 4",
+            0x00FD_D303_C946_BDD9,
+        ),
+        (
+            "rnbqkbnr/p1pppppp/8/8/PpP4P/8/1P1PPPP1/RNBQKBNR b KQkq c3 0 3",
+            0x3C81_23EA_7B06_7637,
+        ),
+        (
+            "rnbqkbnr/p1pppppp/8/8/P6P/R1p5/1P1PPPP1/1NBQKBNR b Kkq - 0 4",
+            0x5C3F_9B82_9B27_9560,
+        ),
+    ];
+
+    fn entry(key: u64, mv: u16, weight: u16) -> [u8; ENTRY_SIZE] {
+        let mut out = [0; ENTRY_SIZE];
+        out[..8].copy_from_slice(&key.to_be_bytes());
+        out[8..10].copy_from_slice(&mv.to_be_bytes());
+        out[10..12].copy_from_slice(&weight.to_be_bytes());
+        out
+    }
+
+    const fn encode(from: Square, to: Square) -> u16 {
+        ((from as u16) << 6) | to as u16
+    }
+
+    #[test]
+    fn polyglot_keys() {
+        for (fen, key) in KEYS {
+            let board = Board::from_fen(fen).unwrap();
+            assert_eq!(polyglot_key(&board), key, "{fen}");
+        }
+    }
+
+    #[test]
+    fn weighted_probe() {
+        let start = Board::startpos();
+        let key = polyglot_key(&start);
+        let mut bytes = Vec::new();
+        // deliberately out of order, with an entry for another position in between.
+        bytes.extend(entry(key, encode(Square::D2, Square::D4), 1));
+        bytes.extend(entry(0, encode(Square::A2, Square::A3), 100));
+        bytes.extend(entry(key, encode(Square::E2, Square::E4), 3));
+        bytes.extend(entry(key, encode(Square::B1, Square::C3), 0));
+        let book = PolyglotBook::from_bytes(&bytes).unwrap();
+        assert_eq!(book.len(), 4);
+        assert_eq!(book.moves(&start).len(), 3);
+
+        let mut rng = XorShiftState::with_seed(1);
+        let picks = (0..400)
+            .map(|_| book.probe(&start, &mut rng).unwrap())
+            .collect::<Vec<_>>();
+        let e4 = start.parse_uci("e2e4").unwrap();
+        let d4 = start.parse_uci("d2d4").unwrap();
+        let e4_count = picks.iter().filter(|&&m| m == e4).count();
+        assert!(picks.iter().all(|&m| m == e4 || m == d4));
+        assert!((250..350).contains(&e4_count), "e4 played {e4_count} times");
+
+        assert_eq!(
+            PolyglotBook::from_bytes(&bytes[..20]).unwrap_err(),
+            BookError::BadLength(20)
+        );
+    }
+
+    #[test]
+    fn castling_is_decoded() {
+        let board = Board::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+        let m = decode_move(&board, encode(Square::E1, Square::H1)).unwrap();
+        assert!(m.is_castle());
+        let m = decode_move(&board, encode(Square::E8, Square::A8));
+        assert_eq!(m, None, "black can't castle on white's turn");
+    }
+}
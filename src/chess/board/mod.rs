@@ -1,6 +1,8 @@
+//! The [`Board`], which holds a position and its history, and makes and unmakes moves.
+
 pub mod movegen;
 mod san;
-pub mod validation;
+pub(crate) mod validation;
 
 use std::fmt::{self, Debug, Display, Formatter, Write};
 
@@ -9,13 +11,14 @@ use movegen::{MAX_POSITION_MOVES, RAY_BETWEEN, RAY_FULL};
 
 use crate::{
     chess::{
-        board::movegen::{MoveList, diag_attacks, orth_attacks, pawn_attacks, pawn_attacks_by},
+        board::movegen::{diag_attacks, orth_attacks, pawn_attacks, pawn_attacks_by},
         chessmove::{Move, MoveFlags},
         fen::Fen,
         piece::{Black, Col, Colour, Piece, PieceType, White},
+        piecelayout::Threats,
         quick::Quick,
         squareset::SquareSet,
-        types::{CastlingRights, CheckState, File, Rank, Square, State},
+        types::{CastlingRights, CheckState, File, Rank, Square, State, Undo},
     },
     cuckoo,
     errors::MoveParseError,
@@ -25,18 +28,35 @@ use crate::{
         threat_updates::{self, Add, Sub},
     },
     search::pv::PVariation,
+    util::MAX_DEPTH,
 };
 
+/// The castling rules in play.
 #[derive(PartialEq, Eq, Clone, Copy, Debug)]
 pub enum Rules {
+    /// Standard chess, with castling written as the king's two-square move.
     Classical,
+    /// Chess960, with castling written as the king capturing its own rook.
     Chess960,
 }
 
+/// How castling rights are written out in a FEN string.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum CastlingNotation {
+    /// X-FEN: `KQkq`, naming the outermost rook on each side of the king, and
+    /// falling back to the rook's file when another rook stands further out.
+    XFen,
+    /// Shredder-FEN: always the rook's file, as in `HAha`.
+    Shredder,
+}
+
+/// A chess position, along with the history of positions that led to it.
 #[derive(PartialEq, Eq, Clone)]
 pub struct Board {
     /// Copyable state for the board.
     pub(crate) state: State,
+    /// The piece on each square, to accelerate `Board::piece_at()`.
+    pub(crate) mailbox: [Option<Piece>; 64],
     /// The side to move.
     side: Colour,
     /// The number of half moves made since the start of the game.
@@ -45,14 +65,14 @@ pub struct Board {
     height: usize,
     /// Is this a Chess960 game?
     rules: Rules,
-    /// Stack of previous board states.
-    history: Vec<State>,
+    /// Stack of previous board states, with the moves made from them.
+    history: Vec<Undo>,
 }
 
 impl Debug for Board {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("Board")
-            .field("piece_array", &self.state.mailbox)
+            .field("piece_array", &self.mailbox)
             .field("side", &self.side)
             .field("ep_sq", &self.state.ep_square)
             .field("fifty_move_counter", &self.state.fifty_move_counter)
@@ -67,14 +87,18 @@ impl Debug for Board {
 }
 
 impl Board {
+    /// The FEN of the standard starting position.
     pub const STARTING_FEN: &'static str =
         "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+    /// The FEN of the standard starting position, with Shredder-FEN castling rights.
     pub const STARTING_FEN_960: &'static str =
         "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w AHah - 0 1";
 
+    /// An empty board, playing under `rules`.
     pub fn empty(rules: Rules) -> Self {
         let mut out = Self {
             state: State::default(),
+            mailbox: [None; 64],
             side: Colour::White,
             height: 0,
             ply: 0,
@@ -85,95 +109,236 @@ impl Board {
         out
     }
 
+    /// The en passant square, if the last move was a double pawn push.
     pub const fn ep_sq(&self) -> Option<Square> {
         self.state.ep_square
     }
 
-    pub fn history(&self) -> &[State] {
+    /// The states of the positions before this one, and the moves made from them, oldest first.
+    pub fn history(&self) -> &[Undo] {
         &self.history
     }
 
+    /// Mutable access to the en passant square.
     #[cfg(feature = "datagen")]
     pub fn ep_sq_mut(&mut self) -> &mut Option<Square> {
         &mut self.state.ep_square
     }
 
+    /// Mutable access to the side to move.
     #[cfg(feature = "datagen")]
     pub fn turn_mut(&mut self) -> &mut Colour {
         &mut self.side
     }
 
+    /// Mutable access to the fifty-move counter.
     #[cfg(feature = "datagen")]
     pub fn halfmove_clock_mut(&mut self) -> &mut u8 {
         &mut self.state.fifty_move_counter
     }
 
+    /// Set the full move number, keeping the side to move.
     #[cfg(feature = "datagen")]
     pub fn set_fullmove_clock(&mut self, fullmove_clock: u16) {
         self.ply = (fullmove_clock as usize - 1) * 2 + usize::from(self.side == Colour::Black);
     }
 
+    /// The number of half moves made since the start of the game.
     pub const fn ply(&self) -> usize {
         self.ply
     }
 
+    /// Whether the side to move is in check.
     pub fn in_check(&self) -> bool {
         self.state.threats.checkers != SquareSet::EMPTY
     }
 
+    /// Does `mv` put the opponent in check? This finds direct checks, discovered checks,
+    /// and checks given by the rook in castling, without making the move.
+    /// To distinguish check from checkmate, use [`Self::gives`] instead.
     pub fn gives_check(&self, mv: Move) -> bool {
-        let moved_piece = mv
+        use PieceType::{Bishop, King, Knight, Pawn, Queen, Rook};
+
+        let bbs = &self.state.bbs;
+        let us = self.side;
+        let their_king = (bbs.pieces[King] & bbs.colours[!us]).first().unwrap();
+        let from = mv.from();
+        let mut to = mv.to();
+        let moved = mv
             .promotion_type()
-            .unwrap_or_else(|| self.state.mailbox[mv.from()].unwrap().piece_type());
-        self.state.threats.tellers[moved_piece].contains_square(mv.to())
+            .unwrap_or_else(|| self.mailbox[from].unwrap().piece_type());
+
+        // knights and pawns can only give check directly, and their attacks don't
+        // depend on occupancy, so the precomputed checking squares are exact.
+        if matches!(moved, Pawn | Knight) && self.state.threats.tellers[moved].contains_square(to) {
+            return true;
+        }
+
+        let ours = bbs.colours[us];
+        let mut occupied = bbs.occupied() ^ from.as_set();
+        let mut diag = (bbs.pieces[Bishop] | bbs.pieces[Queen]) & ours & !from.as_set();
+        let mut orth = (bbs.pieces[Rook] | bbs.pieces[Queen]) & ours & !from.as_set();
+        if mv.is_castle() {
+            // castling is encoded as king-captures-rook.
+            let rook_from = to;
+            let (king_to, rook_to) = if rook_from > from {
+                (Square::G1.relative_to(us), Square::F1.relative_to(us))
+            } else {
+                (Square::C1.relative_to(us), Square::D1.relative_to(us))
+            };
+            occupied = occupied.remove_square(rook_from).add_square(rook_to);
+            orth = orth.remove_square(rook_from).add_square(rook_to);
+            to = king_to;
+        } else if mv.is_ep() {
+            let captured = to
+                .backward(us)
+                .expect("en-passant move without a valid en-passant square");
+            occupied = occupied.remove_square(captured);
+        }
+        occupied = occupied.add_square(to);
+        if matches!(moved, Bishop | Queen) {
+            diag = diag.add_square(to);
+        }
+        if matches!(moved, Rook | Queen) {
+            orth = orth.add_square(to);
+        }
+
+        // this covers both a slider moving into check and one uncovered behind the moved piece.
+        diag_attacks(their_king, occupied) & diag != SquareSet::EMPTY
+            || orth_attacks(their_king, occupied) & orth != SquareSet::EMPTY
     }
 
-    pub fn zero_height(&mut self) {
+    /// Make this position the root of a search tree. Room is made in the history for a
+    /// search's worth of moves, so that making moves in the search never reallocates it.
+    pub(crate) fn zero_height(&mut self) {
         self.height = 0;
+        self.history.reserve(MAX_DEPTH);
     }
 
+    /// The number of moves made since the root of the search tree.
     pub const fn height(&self) -> usize {
         self.height
     }
 
+    /// The castling rules in play.
     pub const fn rules(&self) -> Rules {
         self.rules
     }
 
     #[deprecated(note = "don’t break this invariant!")]
     #[allow(unused)]
-    pub fn rules_mut(&mut self) -> &mut Rules {
+    pub(crate) fn rules_mut(&mut self) -> &mut Rules {
         &mut self.rules
     }
 
+    /// The side to move.
     pub const fn turn(&self) -> Colour {
         self.side
     }
 
+    /// Which rooks each side can castle with.
     pub const fn castling_rights(&self) -> CastlingRights {
         self.state.castle_perm
     }
 
+    /// The pieces of `colour` that are pinned to their king.
+    pub fn pinned(&self, colour: Colour) -> SquareSet {
+        self.state.pinned[colour]
+    }
+
+    /// The set of squares occupied by pawns of the given colour.
+    ///
+    /// ```
+    /// use viridithas::chess::{board::Board, piece::Colour};
+    ///
+    /// let board = Board::startpos();
+    /// assert_eq!(board.pawns(Colour::White).count(), 8);
+    /// ```
+    pub fn pawns(&self, colour: Colour) -> SquareSet {
+        self.state.bbs.pieces[PieceType::Pawn] & self.state.bbs.colours[colour]
+    }
+
+    /// The set of squares occupied by knights of the given colour.
+    pub fn knights(&self, colour: Colour) -> SquareSet {
+        self.state.bbs.pieces[PieceType::Knight] & self.state.bbs.colours[colour]
+    }
+
+    /// The set of squares occupied by bishops of the given colour.
+    pub fn bishops(&self, colour: Colour) -> SquareSet {
+        self.state.bbs.pieces[PieceType::Bishop] & self.state.bbs.colours[colour]
+    }
+
+    /// The set of squares occupied by rooks of the given colour.
+    pub fn rooks(&self, colour: Colour) -> SquareSet {
+        self.state.bbs.pieces[PieceType::Rook] & self.state.bbs.colours[colour]
+    }
+
+    /// The set of squares occupied by queens of the given colour.
+    pub fn queens(&self, colour: Colour) -> SquareSet {
+        self.state.bbs.pieces[PieceType::Queen] & self.state.bbs.colours[colour]
+    }
+
+    /// The set of squares occupied by the king of the given colour.
+    pub fn king(&self, colour: Colour) -> SquareSet {
+        self.state.bbs.pieces[PieceType::King] & self.state.bbs.colours[colour]
+    }
+
+    /// The set of squares occupied by passed pawns of the given colour.
+    pub fn passed_pawns(&self, colour: Colour) -> SquareSet {
+        self.pawns(colour)
+            .iter()
+            .filter(|&sq| self.is_passed_pawn(sq))
+            .fold(SquareSet::EMPTY, SquareSet::add_square)
+    }
+
+    /// Whether the piece on `sq` is a passed pawn: a pawn with no enemy pawns ahead of it
+    /// on its own file or either adjacent file.
+    pub fn is_passed_pawn(&self, sq: Square) -> bool {
+        let Some(piece) = self.mailbox[sq] else {
+            return false;
+        };
+        if piece.piece_type() != PieceType::Pawn {
+            return false;
+        }
+        let colour = piece.colour();
+        movegen::PASSED_PAWN_MASKS[colour][sq] & self.pawns(!colour) == SquareSet::EMPTY
+    }
+
+    /// The set of squares occupied by any piece.
+    pub fn occupied(&self) -> SquareSet {
+        self.state.bbs.occupied()
+    }
+
+    /// The set of squares occupied by pieces of the given colour.
+    pub fn occupied_co(&self, colour: Colour) -> SquareSet {
+        self.state.bbs.colours[colour]
+    }
+
+    /// Mutable access to the castling rights, for setting up positions directly.
     #[cfg(feature = "datagen")]
     pub fn castling_rights_mut(&mut self) -> &mut CastlingRights {
         &mut self.state.castle_perm
     }
 
+    /// Recompute the hash keys after the position has been edited directly.
     #[cfg(feature = "datagen")]
     pub fn regenerate_zobrist(&mut self) {
         self.state.keys = self.state.generate_pos_keys(self.side);
     }
 
+    /// Recompute the threat bitboard after the pieces have been edited directly.
     #[cfg(feature = "datagen")]
     pub fn regenerate_threats(&mut self) {
         self.state.threats = self.state.bbs.generate_threats(self.side);
     }
 
     // NOTE: Mutable operations like this are basically awful and should be removed or made private.
+    /// Clear the board, keeping the rules.
     pub fn reset(&mut self) {
         // preserve ruleset across resets
         let rules = self.rules;
         self.state = State::default();
+        self.mailbox = [None; 64];
         self.side = Colour::White;
         self.height = 0;
         self.ply = 0;
@@ -182,6 +347,7 @@ impl Board {
     }
 
     // NOTE: Mutable operations like this are basically awful and should be removed or made private.
+    /// Set up the Chess960 starting position with the given Scharnagl number, from 0 to 959.
     pub fn set_frc_idx(&mut self, scharnagl: usize) {
         #![allow(clippy::cast_possible_truncation)]
         assert!(scharnagl < 960, "scharnagl index out of range");
@@ -230,6 +396,8 @@ impl Board {
     }
 
     // NOTE: Mutable operations like this are basically awful and should be removed or made private.
+    /// Set up the Double Chess960 starting position with the given index, from 0 to 960 * 960 - 1.
+    /// White's back rank is given by `scharnagl % 960`, and Black's by `scharnagl / 960`.
     pub fn set_dfrc_idx(&mut self, scharnagl: usize) {
         #![allow(clippy::cast_possible_truncation)]
         assert!(scharnagl < 960 * 960, "double scharnagl index out of range");
@@ -287,6 +455,7 @@ impl Board {
         ];
     }
 
+    /// The back rank of the Chess960 starting position with the given Scharnagl number.
     pub fn get_scharnagl_backrank(scharnagl: usize) -> [PieceType; 8] {
         // White's starting array can be derived from its number N (0 ... 959) as follows (https://en.wikipedia.org/wiki/Fischer_random_chess_numbering_scheme#Direct_derivation):
         // A. Divide N by 4, yielding quotient N2 and remainder B1. Place a Bishop upon the bright square corresponding to B1 (0=b, 1=d, 2=f, 3=h).
@@ -363,13 +532,14 @@ impl Board {
     }
 
     // NOTE: Mutable operations like this are basically awful and should be removed or made private.
+    /// Set up the position given by a parsed FEN, keeping the rules.
     pub fn set_from_fen(&mut self, fen: &Fen) {
         self.reset();
 
         self.state.bbs = fen.board;
 
         for sq in Square::all() {
-            self.state.mailbox[sq] = fen.board.piece_at(sq);
+            self.mailbox[sq] = fen.board.piece_at(sq);
         }
 
         self.side = fen.turn;
@@ -411,6 +581,7 @@ impl Board {
     }
 
     // NOTE: Mutable operations like this are basically awful and should be removed or made private.
+    /// Set up the starting position, in the Chess960 form if the rules call for it.
     pub fn set_startpos(&mut self) {
         let starting_fen = match self.rules {
             Rules::Classical => Self::STARTING_FEN,
@@ -420,13 +591,13 @@ impl Board {
         self.set_from_fen(&fen);
     }
 
-    pub fn set_from_quick(&mut self, quick: &Quick) {
+    pub(crate) fn set_from_quick(&mut self, quick: &Quick) {
         self.reset();
 
         self.state.bbs = quick.board;
 
         for sq in Square::all() {
-            self.state.mailbox[sq] = quick.board.piece_at(sq);
+            self.mailbox[sq] = quick.board.piece_at(sq);
         }
 
         self.side = quick.turn;
@@ -443,13 +614,15 @@ impl Board {
         ];
     }
 
+    /// The standard starting position.
     pub fn startpos() -> Self {
         let mut out = Self::empty(Rules::Classical);
         out.set_startpos();
         out
     }
 
-    #[cfg(test)]
+    /// Parse a position from a FEN string, leniently. The rules are Chess960 if the castling
+    /// rights can only be given in Shredder-FEN or X-FEN, and standard chess otherwise.
     pub fn from_fen(fen: &str) -> Result<Self, crate::errors::FenParseError> {
         let parsed = Fen::parse_relaxed(fen)?;
         // interpret rights to generate mode:
@@ -463,21 +636,107 @@ impl Board {
         Ok(out)
     }
 
+    /// The FEN string for this position, with castling rights in the given notation.
+    /// The [`Display`] implementation uses Shredder-FEN for Chess960 games, and X-FEN otherwise.
+    pub fn fen(&self, notation: CastlingNotation) -> String {
+        let mut out = String::new();
+        self.write_fen(&mut out, notation)
+            .expect("writing to a String can't fail");
+        out
+    }
+
+    fn write_fen(&self, f: &mut impl Write, notation: CastlingNotation) -> fmt::Result {
+        let mut counter = 0;
+        for rank in Rank::all().rev() {
+            for file in File::all() {
+                let sq = Square::from_rank_file(rank, file);
+                let piece = self.mailbox[sq];
+                if let Some(piece) = piece {
+                    if counter != 0 {
+                        write!(f, "{counter}")?;
+                    }
+                    counter = 0;
+                    write!(f, "{piece}")?;
+                } else {
+                    counter += 1;
+                }
+            }
+            if counter != 0 {
+                write!(f, "{counter}")?;
+            }
+            counter = 0;
+            if rank != Rank::One {
+                write!(f, "/")?;
+            }
+        }
+
+        match self.side {
+            Colour::White => write!(f, " w")?,
+            Colour::Black => write!(f, " b")?,
+        }
+        write!(f, " ")?;
+        let perm = self.state.castle_perm;
+        if perm == CastlingRights::default() {
+            write!(f, "-")?;
+        } else {
+            for colour in Colour::all() {
+                let back_rank = [SquareSet::RANK_1, SquareSet::RANK_8][colour];
+                let rooks =
+                    self.state.bbs.piece_bb(Piece::new(colour, PieceType::Rook)) & back_rank;
+                for (file, kingside) in [
+                    (perm.kingside(colour), true),
+                    (perm.queenside(colour), false),
+                ] {
+                    let Some(file) = file else {
+                        continue;
+                    };
+                    // in X-FEN, K and Q stand for the outermost rook on that side.
+                    let outermost = if kingside {
+                        rooks.last()
+                    } else {
+                        rooks.first()
+                    };
+                    let ch = if notation == CastlingNotation::XFen
+                        && outermost.map(Square::file) == Some(file)
+                    {
+                        if kingside { 'K' } else { 'Q' }
+                    } else {
+                        char::from(b'A' + file as u8)
+                    };
+                    if colour == Colour::White {
+                        write!(f, "{ch}")?;
+                    } else {
+                        write!(f, "{}", ch.to_ascii_lowercase())?;
+                    }
+                }
+            }
+        }
+        if let Some(ep_sq) = self.state.ep_square {
+            write!(f, " {ep_sq}")?;
+        } else {
+            write!(f, " -")?;
+        }
+        write!(f, " {}", self.state.fifty_move_counter)?;
+        write!(f, " {}", self.ply / 2 + 1)?;
+
+        Ok(())
+    }
+
+    /// The Chess960 starting position with the given Scharnagl number, from 0 to 959.
     pub fn from_frc_idx(scharnagl: usize) -> Self {
         let mut out = Self::empty(Rules::Chess960);
         out.set_frc_idx(scharnagl);
         out
     }
 
-    #[cfg(test)]
-    pub fn from_dfrc_idx(scharnagl: usize) -> Self {
+    pub(crate) fn from_dfrc_idx(scharnagl: usize) -> Self {
         let mut out = Self::empty(Rules::Chess960);
         out.set_dfrc_idx(scharnagl);
         out
     }
 
     #[cfg(test)]
-    pub fn from_quick(record: &str) -> Result<Self, crate::errors::QuickParseError> {
+    pub(crate) fn from_quick(record: &str) -> Result<Self, crate::errors::QuickParseError> {
         let parsed = Quick::parse(record.trim_ascii())?;
         // interpret rights to generate mode:
         let rules = if parsed.rights.is_nonclassical() {
@@ -490,6 +749,13 @@ impl Board {
         Ok(out)
     }
 
+    /// The pieces of both colours that attack `sq`, as if the board's occupancy were `occupied`.
+    /// Sliders see through any square missing from `occupied`, which lets exchange evaluation
+    /// uncover x-ray attackers as pieces are traded off.
+    pub fn attackers_to(&self, sq: Square, occupied: SquareSet) -> SquareSet {
+        self.state.bbs.all_attackers_to_sq(sq, occupied)
+    }
+
     /// Determines if `sq` is attacked by `side`
     pub fn sq_attacked(&self, sq: Square, side: Colour) -> bool {
         match side {
@@ -498,6 +764,7 @@ impl Board {
         }
     }
 
+    /// Whether `sq` is attacked by a piece of colour `C`.
     pub fn sq_attacked_by<C: Col>(&self, sq: Square) -> bool {
         // we remove this check because the board actually *can*
         // be in an inconsistent state when we call this, as it's
@@ -524,8 +791,8 @@ impl Board {
         let from = m.from();
         let to = m.to();
 
-        let moved_piece = self.state.mailbox[from];
-        let captured_piece = self.state.mailbox[to];
+        let moved_piece = self.mailbox[from];
+        let captured_piece = self.mailbox[to];
 
         let Some(moved_piece) = moved_piece else {
             return false;
@@ -562,7 +829,7 @@ impl Board {
                 let Some(one_forward) = from.pawn_push(self.side) else {
                     return false;
                 };
-                return self.state.mailbox[one_forward].is_none()
+                return self.mailbox[one_forward].is_none()
                     && Some(to) == one_forward.pawn_push(self.side);
             } else if captured_piece.is_none() {
                 return Some(to) == from.pawn_push(self.side);
@@ -587,6 +854,8 @@ impl Board {
         movegen::attacks_by_type(moved_piece, from, self.state.bbs.occupied()).contains_square(to)
     }
 
+    /// Whether `m` is a castling move that the castling rights allow, with nothing in the way
+    /// and no attacked squares on the king's path. `m` must be a castling move.
     pub fn is_pseudo_legal_castling(&self, m: Move) -> bool {
         // illegal if:
         // - we're not moving the king
@@ -596,7 +865,7 @@ impl Board {
         // - there are pieces between the king and the rook
         // - the king passes through a square that is attacked by the opponent
         // - the king ends up in check (not checked here)
-        let Some(moved) = self.state.mailbox[m.from()] else {
+        let Some(moved) = self.mailbox[m.from()] else {
             return false;
         };
         if moved.piece_type() != PieceType::King {
@@ -697,7 +966,7 @@ impl Board {
                     == SquareSet::EMPTY;
         }
 
-        let moving = self.state.mailbox[from].unwrap();
+        let moving = self.mailbox[from].unwrap();
 
         if moving.piece_type() == PieceType::King {
             let without_king = bbs.occupied() ^ our_king_bb;
@@ -735,6 +1004,7 @@ impl Board {
         (RAY_BETWEEN[king][checker] | self.state.threats.checkers).contains_square(to)
     }
 
+    /// Whether any of `squares` is attacked by a piece of colour `by`.
     pub fn any_attacked(&self, squares: SquareSet, by: Colour) -> bool {
         if by == self.side.flip() {
             squares & self.state.threats.all != SquareSet::EMPTY
@@ -748,12 +1018,18 @@ impl Board {
         }
     }
 
+    /// Put `piece` on `sq`. This doesn't update the hash keys or threats.
     pub fn add_piece(&mut self, sq: Square, piece: Piece) {
         self.state.bbs.set_piece_at(sq, piece);
-        self.state.mailbox[sq] = Some(piece);
+        self.mailbox[sq] = Some(piece);
     }
 
     /// Determines whether this move would be a capture in the current position.
+    ///
+    /// This includes en passant, where the target square is empty, and
+    /// excludes castling, where the target square holds a friendly rook.
+    /// Code that needs the captured piece itself (e.g. to index capture
+    /// history or to value the capture) must still special-case en passant.
     pub fn is_capture(&self, m: Move) -> bool {
         if m.is_castle() {
             return false;
@@ -761,7 +1037,7 @@ impl Board {
         if m.is_ep() {
             return true;
         }
-        self.state.mailbox[m.to()].is_some()
+        self.mailbox[m.to()].is_some()
     }
 
     /// Determines whether this move would be tactical in the current position.
@@ -769,26 +1045,69 @@ impl Board {
         m.is_promo() || self.is_capture(m)
     }
 
+    /// Make a legal move, without updating any neural network state.
     pub fn make_move_simple(&mut self, m: Move) {
         self.make_move_base(m, &mut UpdateBuffer::default());
     }
 
+    /// Makes a move, returning its SAN as computed in the position before the move.
+    /// Returns `None`, leaving the board untouched, if the move is illegal.
+    ///
+    /// This doesn't update NNUE state, so it's suitable for replaying games of any length.
+    pub fn make_move_logging(&mut self, m: Move) -> Option<String> {
+        let san = self.san(m)?.to_string();
+        self.make_move_simple(m);
+        Some(san)
+    }
+
+    /// The position after a legal move, made without recording anything needed to take it
+    /// back. The new position has no history, so it can't see repetitions of earlier ones.
+    ///
+    /// This is cheaper than making and unmaking the move where neither is needed, as in
+    /// perft, or when replaying a game only to look at each position.
+    pub fn make_move_new(&self, m: Move) -> Self {
+        let mut next = Self {
+            state: self.state.clone(),
+            mailbox: self.mailbox,
+            side: self.side,
+            ply: self.ply,
+            height: self.height,
+            rules: self.rules,
+            history: Vec::new(),
+        };
+        next.apply_move(m, &mut UpdateBuffer::default());
+        next
+    }
+
+    pub(crate) fn make_move_base(&mut self, m: Move, update_buffer: &mut UpdateBuffer) {
+        let captured = if m.is_castle() {
+            None
+        } else {
+            self.mailbox[m.to()]
+        };
+        self.history.push(Undo {
+            state: self.state.clone(),
+            mv: Some(m),
+            captured,
+        });
+        self.apply_move(m, update_buffer);
+    }
+
+    /// Make a legal move on the board, without recording it in the history.
     #[allow(clippy::cognitive_complexity, clippy::too_many_lines)]
-    pub fn make_move_base(&mut self, m: Move, update_buffer: &mut UpdateBuffer) {
+    fn apply_move(&mut self, m: Move, update_buffer: &mut UpdateBuffer) {
         debug_assert!(self.is_pseudo_legal(m));
         debug_assert!(self.is_legal(m));
 
         #[cfg(debug_assertions)]
         self.check_validity();
 
-        self.history.push(self.state.clone());
-
         let from = m.from();
         let mut to = m.to();
         let castle = m.is_castle();
         let side = self.side;
-        let piece = self.state.mailbox[from].unwrap();
-        let captured = if castle { None } else { self.state.mailbox[to] };
+        let piece = self.mailbox[from].unwrap();
+        let captured = if castle { None } else { self.mailbox[to] };
         let mut castling_perm = self.state.castle_perm;
 
         // from, to, and piece are valid unless this is a castling move,
@@ -804,14 +1123,14 @@ impl Board {
         }
 
         if m.is_ep() {
-            let clear_at = match side {
-                Colour::White => to.sub(8),
-                Colour::Black => to.add(8),
-            }
-            .unwrap();
+            // the ep square is always on the third or sixth rank (see check_validity),
+            // so the captured pawn one rank behind it is always on the board.
+            let clear_at = to
+                .backward(side)
+                .expect("en-passant target square on the edge of the board");
             let to_clear = Piece::new(side.flip(), PieceType::Pawn);
             threat_updates::on_change::<Sub>(&mut update_buffer.aux, self, to_clear, clear_at);
-            self.state.mailbox[clear_at] = None;
+            self.mailbox[clear_at] = None;
             self.state.bbs.clear_piece_at(clear_at, to_clear);
             update_buffer.psqt.clear_piece(clear_at, to_clear);
         } else if castle {
@@ -847,7 +1166,7 @@ impl Board {
                 .promotion_type()
                 .map_or(piece, |promo| Piece::new(side, promo));
             threat_updates::on_mutate(&mut update_buffer.aux, self, captured, new_piece_at_to, to);
-            self.state.mailbox[to] = Some(new_piece_at_to);
+            self.mailbox[to] = Some(new_piece_at_to);
             self.state.bbs.clear_piece_at(to, captured);
             update_buffer.psqt.clear_piece(to, captured);
         }
@@ -864,13 +1183,16 @@ impl Board {
                     & self.state.bbs.colours[side.flip()]
                     != SquareSet::EMPTY
             {
-                if side == Colour::White {
-                    self.state.ep_square = from.add(8);
-                    debug_assert!(self.state.ep_square.unwrap().rank() == Rank::Three);
-                } else {
-                    self.state.ep_square = from.sub(8);
-                    debug_assert!(self.state.ep_square.unwrap().rank() == Rank::Six);
-                }
+                // a double pawn push starts on the second rank, so the skipped-over
+                // square is always on the board.
+                self.state.ep_square = from.forward(side);
+                debug_assert_eq!(
+                    self.state.ep_square.map(Square::rank),
+                    Some(match side {
+                        Colour::White => Rank::Three,
+                        Colour::Black => Rank::Six,
+                    })
+                );
             }
         }
         if let Some(ep_sq) = self.state.ep_square {
@@ -882,10 +1204,10 @@ impl Board {
             debug_assert!(promo_piece.piece_type().legal_promo());
             self.state.bbs.clear_piece_at(from, piece);
             self.state.bbs.set_piece_at(to, promo_piece);
-            self.state.mailbox[from] = None;
+            self.mailbox[from] = None;
             if captured.is_none() {
                 // if we’re not capturing, we can call the fused move path.
-                self.state.mailbox[to] = Some(promo_piece);
+                self.mailbox[to] = Some(promo_piece);
                 threat_updates::on_move(&mut update_buffer.aux, self, piece, from, promo_piece, to);
             } else {
                 threat_updates::on_change::<Sub>(&mut update_buffer.aux, self, piece, from);
@@ -901,24 +1223,24 @@ impl Board {
                 Square::D1.relative_to(side)
             };
             let rook = Piece::new(side, PieceType::Rook);
-            self.state.mailbox[from] = None;
+            self.mailbox[from] = None;
             threat_updates::on_change::<Sub>(&mut update_buffer.aux, self, piece, from);
-            self.state.mailbox[rook_from] = None;
+            self.mailbox[rook_from] = None;
             threat_updates::on_change::<Sub>(&mut update_buffer.aux, self, rook, rook_from);
-            self.state.mailbox[to] = Some(piece);
+            self.mailbox[to] = Some(piece);
             threat_updates::on_change::<Add>(&mut update_buffer.aux, self, piece, to);
-            self.state.mailbox[rook_to] = Some(rook);
+            self.mailbox[rook_to] = Some(rook);
             threat_updates::on_change::<Add>(&mut update_buffer.aux, self, rook, rook_to);
         } else if captured.is_some() {
             self.state.bbs.move_piece(from, to, piece);
             // update mailbox and compute threats for the moving piece
-            self.state.mailbox[from] = None;
+            self.mailbox[from] = None;
             threat_updates::on_change::<Sub>(&mut update_buffer.aux, self, piece, from);
         } else {
             self.state.bbs.move_piece(from, to, piece);
             // update mailbox and compute threats for the moving piece
-            self.state.mailbox[from] = None;
-            self.state.mailbox[to] = Some(piece);
+            self.mailbox[from] = None;
+            self.mailbox[to] = Some(piece);
             threat_updates::on_move(&mut update_buffer.aux, self, piece, from, piece, to);
         }
 
@@ -927,28 +1249,7 @@ impl Board {
         // hash out the castling to insert it again after updating rights.
         self.state.keys.zobrist ^= CASTLE_KEYS[castling_perm.hashkey_index()];
         // update castling rights
-        if piece == Piece::WR && from.rank() == Rank::One {
-            if Some(from.file()) == castling_perm.kingside(Colour::White) {
-                castling_perm.clear_side::<true, White>();
-            } else if Some(from.file()) == castling_perm.queenside(Colour::White) {
-                castling_perm.clear_side::<false, White>();
-            }
-        } else if piece == Piece::BR && from.rank() == Rank::Eight {
-            if Some(from.file()) == castling_perm.kingside(Colour::Black) {
-                castling_perm.clear_side::<true, Black>();
-            } else if Some(from.file()) == castling_perm.queenside(Colour::Black) {
-                castling_perm.clear_side::<false, Black>();
-            }
-        } else if piece == Piece::WK {
-            castling_perm.clear::<White>();
-        } else if piece == Piece::BK {
-            castling_perm.clear::<Black>();
-        }
-        if to.rank() == Rank::One {
-            castling_perm.remove::<White>(to.file());
-        } else if to.rank() == Rank::Eight {
-            castling_perm.remove::<Black>(to.file());
-        }
+        castling_perm = castling_rights_after(castling_perm, piece, from, to);
         self.state.keys.zobrist ^= CASTLE_KEYS[castling_perm.hashkey_index()];
         self.state.castle_perm = castling_perm;
 
@@ -992,7 +1293,7 @@ impl Board {
         self.ply += 1;
         self.height += 1;
 
-        self.state.threats = self.state.bbs.generate_threats(self.side);
+        self.state.threats = self.next_threats();
         self.state.pinned = [
             self.state.bbs.generate_pinned(Colour::White),
             self.state.bbs.generate_pinned(Colour::Black),
@@ -1002,7 +1303,7 @@ impl Board {
         self.check_validity();
     }
 
-    pub fn unmake_move_base(&mut self) {
+    pub(crate) fn unmake_move_base(&mut self) {
         // we remove this check because the board actually *can*
         // be in an inconsistent state when we call this, as we
         // may be unmaking a move that was determined to be
@@ -1014,18 +1315,76 @@ impl Board {
         self.height -= 1;
         self.ply -= 1;
         self.side = self.side.flip();
-        self.state = self.history.pop().expect("No move to unmake!");
+        let undo = self.history.pop().expect("No move to unmake!");
+        self.state = undo.state;
+        if let Some(m) = undo.mv {
+            self.unmake_mailbox(m, undo.captured);
+        }
 
         #[cfg(debug_assertions)]
         self.check_validity();
     }
 
+    /// The threats against the side to move, just after a move. They're updated from those
+    /// of two plies ago, which were against the same side, when the history reaches back
+    /// that far.
+    fn next_threats(&self) -> Threats {
+        self.history.len().checked_sub(2).map_or_else(
+            || self.state.bbs.generate_threats(self.side),
+            |i| {
+                let earlier = &self.history[i].state;
+                self.state
+                    .bbs
+                    .update_threats(self.side, &earlier.bbs, &earlier.threats)
+            },
+        )
+    }
+
+    /// Take `m`, made by the side to move, back out of the mailbox. `captured` is the piece
+    /// it captured, unless it was taken en passant, and the castling rights must be those
+    /// from before the move.
+    fn unmake_mailbox(&mut self, m: Move, captured: Option<Piece>) {
+        let side = self.side;
+        let from = m.from();
+        let to = m.to();
+        if m.is_castle() {
+            // castling is encoded as king-captures-rook.
+            let (king_to, rook_to) = if Some(to.file()) == self.state.castle_perm.kingside(side) {
+                (Square::G1.relative_to(side), Square::F1.relative_to(side))
+            } else {
+                (Square::C1.relative_to(side), Square::D1.relative_to(side))
+            };
+            self.mailbox[king_to] = None;
+            self.mailbox[rook_to] = None;
+            self.mailbox[from] = Some(Piece::new(side, PieceType::King));
+            self.mailbox[to] = Some(Piece::new(side, PieceType::Rook));
+            return;
+        }
+        self.mailbox[from] = if m.is_promo() {
+            Some(Piece::new(side, PieceType::Pawn))
+        } else {
+            self.mailbox[to]
+        };
+        self.mailbox[to] = captured;
+        if m.is_ep() {
+            let captured_at = to
+                .backward(side)
+                .expect("en-passant target square on the edge of the board");
+            self.mailbox[captured_at] = Some(Piece::new(side.flip(), PieceType::Pawn));
+        }
+    }
+
+    /// Pass the turn to the other side. The side to move must not be in check.
     pub fn make_nullmove(&mut self) {
         #[cfg(debug_assertions)]
         self.check_validity();
         debug_assert!(!self.in_check());
 
-        self.history.push(self.state.clone());
+        self.history.push(Undo {
+            state: self.state.clone(),
+            mv: None,
+            captured: None,
+        });
 
         let mut key = self.state.keys.zobrist;
         if let Some(ep_sq) = self.state.ep_square {
@@ -1039,12 +1398,13 @@ impl Board {
         self.ply += 1;
         self.height += 1;
 
-        self.state.threats = self.state.bbs.generate_threats(self.side);
+        self.state.threats = self.next_threats();
 
         #[cfg(debug_assertions)]
         self.check_validity();
     }
 
+    /// Take back a null move made with [`Board::make_nullmove`].
     pub fn unmake_nullmove(&mut self) {
         #[cfg(debug_assertions)]
         self.check_validity();
@@ -1059,7 +1419,7 @@ impl Board {
             keys,
             pinned,
             ..
-        } = self.history.last().expect("No move to unmake!");
+        } = &self.history.last().expect("No move to unmake!").state;
 
         self.state.ep_square = *ep_square;
         self.state.threats = *threats;
@@ -1072,8 +1432,8 @@ impl Board {
         self.check_validity();
     }
 
-    pub fn make_move_nnue(&mut self, m: Move, nnue: &mut NNUEState) {
-        let piece = self.state.mailbox[m.from()].unwrap();
+    pub(crate) fn make_move_nnue(&mut self, m: Move, nnue: &mut NNUEState) {
+        let piece = self.mailbox[m.from()].unwrap();
 
         let update_buffer = &mut nnue.updates[nnue.current_acc];
 
@@ -1099,26 +1459,31 @@ impl Board {
         nnue.current_acc += 1;
     }
 
-    pub fn unmake_move_nnue(&mut self, nnue: &mut NNUEState) {
+    pub(crate) fn unmake_move_nnue(&mut self, nnue: &mut NNUEState) {
         self.unmake_move_base();
         nnue.current_acc -= 1;
     }
 
+    /// Make a legal move, updating `nnue` to match.
     pub fn make_move(&mut self, m: Move, nnue: &mut NNUEState) {
         self.make_move_nnue(m, nnue);
     }
 
+    /// Take back the last move, and the update to `nnue` that came with it.
     pub fn unmake_move(&mut self, nnue: &mut NNUEState) {
         self.unmake_move_nnue(nnue);
     }
 
-    /// Makes a guess about the new position key after a move.
-    /// This is a cheap estimate, and will fail for special moves such as castling.
+    /// Makes a guess about the new position key after a move, including the
+    /// halfmove-clock component used by the transposition table.
+    /// This is a cheap estimate for prefetching: it is wrong for castling and
+    /// en passant, and ignores changes to castling rights and the ep square.
+    /// Use [`Board::key_after_exact`] where correctness matters.
     pub fn key_after(&self, m: Move) -> u64 {
         let src = m.from();
         let tgt = m.to();
-        let piece = self.state.mailbox[src].unwrap();
-        let captured = self.state.mailbox[tgt];
+        let piece = self.mailbox[src].unwrap();
+        let captured = self.mailbox[tgt];
         let is_pawn = piece.piece_type() == PieceType::Pawn;
         let src_piece = piece;
         let dst_piece = m
@@ -1144,6 +1509,67 @@ impl Board {
         new_key ^ HM_CLOCK_KEYS[new_hmc as usize]
     }
 
+    /// Computes the exact position key after a move, as [`Board::make_move`]
+    /// would leave it. Handles castling, promotions, en passant, and changes
+    /// to castling rights and the ep square. Unlike [`Board::key_after`], the
+    /// halfmove clock is not folded in.
+    pub fn key_after_exact(&self, m: Move) -> u64 {
+        let side = self.side;
+        let from = m.from();
+        let mut to = m.to();
+        let piece = self.mailbox[from].unwrap();
+
+        let mut new_key = self.state.keys.zobrist ^ SIDE_KEY;
+
+        if m.is_castle() {
+            // castling is encoded as king-captures-rook.
+            let rook_from = to;
+            let rook_to = if Some(to.file()) == self.state.castle_perm.kingside(side) {
+                to = Square::G1.relative_to(side);
+                Square::F1.relative_to(side)
+            } else {
+                to = Square::C1.relative_to(side);
+                Square::D1.relative_to(side)
+            };
+            let rook = Piece::new(side, PieceType::Rook);
+            new_key ^= PIECE_KEYS[piece][from] ^ PIECE_KEYS[piece][to];
+            new_key ^= PIECE_KEYS[rook][rook_from] ^ PIECE_KEYS[rook][rook_to];
+        } else {
+            let dst_piece = m
+                .promotion_type()
+                .map_or(piece, |promo| Piece::new(side, promo));
+            new_key ^= PIECE_KEYS[piece][from] ^ PIECE_KEYS[dst_piece][to];
+            if m.is_ep() {
+                let captured_at = to
+                    .backward(side)
+                    .expect("en-passant target square on the edge of the board");
+                new_key ^= PIECE_KEYS[Piece::new(side.flip(), PieceType::Pawn)][captured_at];
+            } else if let Some(captured) = self.mailbox[to] {
+                new_key ^= PIECE_KEYS[captured][to];
+            }
+        }
+
+        if let Some(ep_sq) = self.state.ep_square {
+            new_key ^= EP_KEYS[ep_sq];
+        }
+        // mirror make_move: the ep square is only set if an enemy pawn could capture.
+        if piece.piece_type() == PieceType::Pawn
+            && m.is_double_pawn_push_ranks()
+            && (m.to().as_set().west_one() | m.to().as_set().east_one())
+                & self.state.bbs.pieces[PieceType::Pawn]
+                & self.state.bbs.colours[side.flip()]
+                != SquareSet::EMPTY
+            && let Some(ep_sq) = from.forward(side)
+        {
+            new_key ^= EP_KEYS[ep_sq];
+        }
+
+        let castle_perm = castling_rights_after(self.state.castle_perm, piece, from, to);
+        new_key ^= CASTLE_KEYS[self.state.castle_perm.hashkey_index()];
+        new_key ^ CASTLE_KEYS[castle_perm.hashkey_index()]
+    }
+
+    /// The Zobrist key of the position after a null move.
     pub fn key_after_null_move(&self) -> u64 {
         self.state.keys.zobrist ^ SIDE_KEY
     }
@@ -1183,8 +1609,7 @@ impl Board {
             File::from_index(san_bytes[2] - b'a').ok_or(Unknown)?,
         );
 
-        self.legal_moves()
-            .into_iter()
+        self.legal_moves_iter()
             .find(|&m| {
                 let m_to = if self.rules == Rules::Classical && m.is_castle() {
                     // if we're in normal UCI mode, we'll rework our castling moves into the
@@ -1208,39 +1633,18 @@ impl Board {
             .ok_or_else(|| IllegalMove(uci.to_string()))
     }
 
+    /// Whether `m` gives check or checkmate. This plays the move out and generates replies,
+    /// so it's only used for notation - search should use [`Self::gives_check`].
     pub fn gives(&self, m: Move) -> CheckState {
-        debug_assert!(self.is_pseudo_legal(m));
-        debug_assert!(self.is_legal(m));
-        let Self {
-            state,
-            side,
-            ply,
-            height,
-            ..
-        } = self;
-        let mut playout = Self {
-            state: state.clone(),
-            side: *side,
-            ply: *ply,
-            height: *height,
-            rules: self.rules,
-            history: Vec::new(),
-        };
-        playout.make_move_simple(m);
+        let playout = self.make_move_new(m);
         let gives_check = playout.in_check();
         if gives_check {
-            let mut ml = MoveList::new();
-            playout.generate_moves(&mut ml);
-            for &m in ml.iter_moves() {
-                if !playout.is_legal(m) {
-                    continue;
-                }
-                // we found a legal move, so m does not give checkmate.
-                return CheckState::Check;
-            }
-            // we didn't return, so there were no legal moves,
-            // so m gives checkmate.
-            return CheckState::Checkmate;
+            // if there are no legal replies, m gives checkmate.
+            return if playout.legal_moves_iter().next().is_none() {
+                CheckState::Checkmate
+            } else {
+                CheckState::Check
+            };
         }
         CheckState::None
     }
@@ -1260,7 +1664,7 @@ impl Board {
             .skip(3)
             .step_by(2)
         {
-            if u.keys.zobrist == self.state.keys.zobrist {
+            if u.state.keys.zobrist == self.state.keys.zobrist {
                 // in-tree, can twofold:
                 if dist_back < self.height {
                     return true;
@@ -1280,6 +1684,7 @@ impl Board {
         (self.state.fifty_move_counter >= 100 || self.is_repetition()) && self.height != 0
     }
 
+    /// The moves of `pv` in SAN, numbered and separated by spaces.
     pub fn pv_san(&self, pv: &PVariation) -> Result<String, fmt::Error> {
         let mut playout = self.clone();
         let mut out = String::new();
@@ -1291,23 +1696,17 @@ impl Board {
         Ok(out)
     }
 
+    /// All the legal moves in this position.
     pub fn legal_moves(&self) -> ArrayVec<Move, MAX_POSITION_MOVES> {
-        let mut legal_moves = ArrayVec::new();
-        let mut move_list = MoveList::new();
-        self.generate_moves(&mut move_list);
-        for &m in move_list.iter_moves() {
-            if self.is_legal(m) {
-                legal_moves.push(m);
-            }
-        }
-        legal_moves
+        self.legal_moves_iter().collect()
     }
 
+    /// The number of half moves since the last capture or pawn move.
     pub const fn fifty_move_counter(&self) -> u8 {
         self.state.fifty_move_counter
     }
 
-    #[cfg(any(feature = "datagen", test))]
+    /// Whether `C` has too little material to ever checkmate.
     pub fn has_insufficient_material<C: Col>(&self) -> bool {
         use PieceType::{Bishop, King, Knight, Pawn, Queen, Rook};
 
@@ -1342,11 +1741,14 @@ impl Board {
         true
     }
 
+    /// The number of the full move being played, starting at 1.
     #[cfg(feature = "datagen")]
     pub const fn full_move_number(&self) -> usize {
         self.ply / 2 + 1
     }
 
+    /// Whether the side to move has a move that repeats an earlier position, using the cuckoo
+    /// tables. Only earlier positions after the root count, unless they repeat a second time.
     pub fn has_game_cycle(&self, ply: usize) -> bool {
         let end = std::cmp::min(self.fifty_move_counter() as usize, self.history.len());
 
@@ -1354,7 +1756,7 @@ impl Board {
             return false;
         }
 
-        let old_key = |i: usize| self.history[self.history.len() - i].keys.zobrist;
+        let old_key = |i: usize| self.history[self.history.len() - i].state.keys.zobrist;
 
         let occ = self.state.bbs.occupied();
         let original_key = self.state.keys.zobrist;
@@ -1390,9 +1792,9 @@ impl Board {
                     return true;
                 }
 
-                let mut piece = self.state.mailbox[mv.from()];
+                let mut piece = self.mailbox[mv.from()];
                 if piece.is_none() {
-                    piece = self.state.mailbox[mv.to()];
+                    piece = self.mailbox[mv.to()];
                 }
 
                 return piece.unwrap().colour() == self.side;
@@ -1402,44 +1804,71 @@ impl Board {
         false
     }
 
-    #[cfg(any(feature = "datagen", test))]
+    /// Whether neither side has enough material to ever checkmate.
     pub fn is_insufficient_material(&self) -> bool {
         self.has_insufficient_material::<White>() && self.has_insufficient_material::<Black>()
     }
 
-    #[cfg(any(feature = "datagen", test))]
-    pub fn outcome(&self) -> Option<GameOutcome> {
-        use DrawType::{FiftyMoves, InsufficientMaterial, Repetition, Stalemate};
-        use GameOutcome::{BlackWin, Draw, WhiteWin};
-        if self.state.fifty_move_counter >= 100 {
-            return Some(Draw(FiftyMoves));
-        }
+    /// Is the game drawn by the fifty-move rule?
+    ///
+    /// Unlike the check in [`Board::is_draw`], this is rules-exact: a
+    /// checkmate delivered on the hundredth half-move wins the game.
+    pub fn is_fifty_move_draw(&self) -> bool {
+        self.state.fifty_move_counter >= 100
+            && !(self.in_check() && self.legal_moves_iter().next().is_none())
+    }
+
+    /// How many times the current position has occurred in the game,
+    /// including this occurrence.
+    pub fn repetition_count(&self) -> usize {
         let mut reps = 1;
         for undo in self.history.iter().rev().skip(1).step_by(2) {
-            if undo.keys.zobrist == self.state.keys.zobrist {
+            if undo.state.keys.zobrist == self.state.keys.zobrist {
                 reps += 1;
-                if reps == 3 {
-                    return Some(Draw(Repetition));
-                }
             }
             // optimisation: if the fifty move counter was zeroed, then any prior positions will not be repetitions.
-            if undo.fifty_move_counter == 0 {
+            if undo.state.fifty_move_counter == 0 {
                 break;
             }
         }
+        reps
+    }
+
+    /// Is the game drawn automatically under FIDE rules, without either
+    /// player needing to claim it?
+    ///
+    /// This covers the seventy-five-move rule and fivefold repetition, as
+    /// opposed to the claimable fifty-move and threefold thresholds used by
+    /// [`Board::outcome`]. A checkmate delivered on the final half-move
+    /// still wins the game.
+    pub fn is_automatic_draw(&self) -> Option<DrawType> {
+        if self.state.fifty_move_counter >= 150
+            && !(self.in_check() && self.legal_moves_iter().next().is_none())
+        {
+            return Some(DrawType::SeventyFiveMoves);
+        }
+        if self.repetition_count() >= 5 {
+            return Some(DrawType::FivefoldRepetition);
+        }
+        None
+    }
+
+    /// The result of the game, if it has ended by checkmate, stalemate, or insufficient
+    /// material, or either player could claim a draw by the fifty-move rule or threefold
+    /// repetition. Draws that need no claim are reported by [`Board::is_automatic_draw`].
+    pub fn outcome(&self) -> Option<GameOutcome> {
+        use DrawType::{FiftyMoves, InsufficientMaterial, Repetition, Stalemate};
+        use GameOutcome::{BlackWin, Draw, WhiteWin};
+        if self.is_fifty_move_draw() {
+            return Some(Draw(FiftyMoves));
+        }
+        if self.repetition_count() >= 3 {
+            return Some(Draw(Repetition));
+        }
         if self.is_insufficient_material() {
             return Some(Draw(InsufficientMaterial));
         }
-        let mut move_list = MoveList::new();
-        self.generate_moves(&mut move_list);
-        let mut legal_moves = false;
-        for &m in move_list.iter_moves() {
-            if self.is_legal(m) {
-                legal_moves = true;
-                break;
-            }
-        }
-        if legal_moves {
+        if self.legal_moves_iter().next().is_some() {
             None
         } else if self.in_check() {
             match self.side {
@@ -1451,46 +1880,133 @@ impl Board {
         }
     }
 
+    /// Make a random legal move, preferring moves whose static exchange
+    /// evaluation clears `see_threshold`. If none is found in a few attempts,
+    /// any legal move is made. Returns `None` if there are no legal moves.
+    ///
+    /// The choice depends only on the state of `rng`, so a seeded generator
+    /// gives a reproducible sequence of moves.
+    #[cfg(any(feature = "datagen", test))]
+    pub fn make_random_move_seeded(
+        &mut self,
+        rng: &mut crate::rng::XorShiftState,
+        conf: &crate::search::parameters::Config,
+        see_threshold: i32,
+    ) -> Option<Move> {
+        #![allow(clippy::cast_possible_truncation)]
+        /// Number of attempts to find a good random move before just picking any random move.
+        const ATTEMPTS: usize = 8;
+        let legal_moves = self.legal_moves();
+        if legal_moves.is_empty() {
+            return None;
+        }
+        let mut choose = || legal_moves[rng.next_below(legal_moves.len() as u64) as usize];
+        let m = (0..ATTEMPTS)
+            .map(|_| choose())
+            .find(|&m| crate::search::static_exchange_eval(self, conf, m, see_threshold))
+            .unwrap_or_else(choose);
+        debug_assert!(self.is_legal(m));
+        self.make_move_simple(m);
+        Some(m)
+    }
+
     #[cfg(debug_assertions)]
-    pub fn assert_mated(&self) {
+    pub(crate) fn assert_mated(&self) {
         assert!(self.in_check());
-        let mut move_list = MoveList::new();
+        let mut move_list = movegen::MoveList::new();
         self.generate_moves(&mut move_list);
-        for &mv in move_list.iter_moves() {
-            assert!(!self.is_legal(mv));
-        }
+        assert!(move_list.is_empty());
     }
 }
 
+/// The castling rights after `piece` moves from `from` to `to`, where `to` is
+/// the king's destination square for castling moves.
+fn castling_rights_after(
+    mut castling_perm: CastlingRights,
+    piece: Piece,
+    from: Square,
+    to: Square,
+) -> CastlingRights {
+    if piece == Piece::WR && from.rank() == Rank::One {
+        if Some(from.file()) == castling_perm.kingside(Colour::White) {
+            castling_perm.clear_side::<true, White>();
+        } else if Some(from.file()) == castling_perm.queenside(Colour::White) {
+            castling_perm.clear_side::<false, White>();
+        }
+    } else if piece == Piece::BR && from.rank() == Rank::Eight {
+        if Some(from.file()) == castling_perm.kingside(Colour::Black) {
+            castling_perm.clear_side::<true, Black>();
+        } else if Some(from.file()) == castling_perm.queenside(Colour::Black) {
+            castling_perm.clear_side::<false, Black>();
+        }
+    } else if piece == Piece::WK {
+        castling_perm.clear::<White>();
+    } else if piece == Piece::BK {
+        castling_perm.clear::<Black>();
+    }
+    if to.rank() == Rank::One {
+        castling_perm.remove::<White>(to.file());
+    } else if to.rank() == Rank::Eight {
+        castling_perm.remove::<Black>(to.file());
+    }
+    castling_perm
+}
+
+/// The result of a finished game.
 #[allow(dead_code)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum GameOutcome {
+    /// Black won.
     BlackWin(WinType),
+    /// The game was drawn.
     Draw(DrawType),
+    /// White won.
     WhiteWin(WinType),
 }
 
+/// How a game was won.
 #[allow(dead_code)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum WinType {
+    /// By checkmate.
     Mate,
+    /// By reaching a tablebase win.
     TB,
+    /// By adjudication on the evaluation.
     Adjudication,
+    /// By the loser resigning.
+    Resignation,
+    /// By the loser running out of time.
+    TimeForfeit,
 }
 
+/// How a game was drawn.
 #[allow(dead_code)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum DrawType {
+    /// By reaching a tablebase draw.
     TB,
+    /// By the fifty-move rule.
     FiftyMoves,
+    /// By threefold repetition.
     Repetition,
+    /// By the seventy-five-move rule.
+    SeventyFiveMoves,
+    /// By fivefold repetition.
+    FivefoldRepetition,
+    /// By stalemate.
     Stalemate,
+    /// Because neither side can checkmate.
     InsufficientMaterial,
+    /// By adjudication on the evaluation.
     Adjudication,
+    /// By the players agreeing to a draw.
+    Agreement,
 }
 
 #[cfg(feature = "datagen")]
 impl GameOutcome {
+    /// The outcome packed into a byte, as written by the marlinformat packer.
     pub const fn as_packed_u8(self) -> u8 {
         // 0 for black win, 1 for draw, 2 for white win
         match self {
@@ -1503,60 +2019,11 @@ impl GameOutcome {
 
 impl Display for Board {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), std::fmt::Error> {
-        let mut counter = 0;
-        for rank in Rank::all().rev() {
-            for file in File::all() {
-                let sq = Square::from_rank_file(rank, file);
-                let piece = self.state.mailbox[sq];
-                if let Some(piece) = piece {
-                    if counter != 0 {
-                        write!(f, "{counter}")?;
-                    }
-                    counter = 0;
-                    write!(f, "{piece}")?;
-                } else {
-                    counter += 1;
-                }
-            }
-            if counter != 0 {
-                write!(f, "{counter}")?;
-            }
-            counter = 0;
-            if rank != Rank::One {
-                write!(f, "/")?;
-            }
-        }
-
-        match self.side {
-            Colour::White => write!(f, " w")?,
-            Colour::Black => write!(f, " b")?,
-        }
-        write!(f, " ")?;
-        if self.state.castle_perm == CastlingRights::default() {
-            write!(f, "-")?;
-        } else {
-            for (_, ch) in [
-                self.state.castle_perm.kingside(Colour::White),
-                self.state.castle_perm.queenside(Colour::White),
-                self.state.castle_perm.kingside(Colour::Black),
-                self.state.castle_perm.queenside(Colour::Black),
-            ]
-            .into_iter()
-            .zip("KQkq".chars())
-            .filter(|(m, _)| m.is_some())
-            {
-                write!(f, "{ch}")?;
-            }
-        }
-        if let Some(ep_sq) = self.state.ep_square {
-            write!(f, " {ep_sq}")?;
-        } else {
-            write!(f, " -")?;
-        }
-        write!(f, " {}", self.state.fifty_move_counter)?;
-        write!(f, " {}", self.ply / 2 + 1)?;
-
-        Ok(())
+        let notation = match self.rules {
+            Rules::Classical => CastlingNotation::XFen,
+            Rules::Chess960 => CastlingNotation::Shredder,
+        };
+        self.write_fen(f, notation)
     }
 }
 
@@ -1566,7 +2033,7 @@ impl std::fmt::UpperHex for Board {
             write!(f, "{} ", rank as u8 + 1)?;
             for file in File::all() {
                 let sq = Square::from_rank_file(rank, file);
-                if let Some(piece) = self.state.mailbox[sq] {
+                if let Some(piece) = self.mailbox[sq] {
                     write!(f, "{piece} ")?;
                 } else {
                     write!(f, ". ")?;
@@ -1630,6 +2097,52 @@ mod tests {
         assert_eq!(insufficient_material_knights.outcome(), None);
     }
 
+    #[test]
+    fn no_legal_move_outcomes() {
+        use super::{DrawType, GameOutcome, WinType};
+
+        // smothered mate: the knight check can't be blocked or captured.
+        let smothered = Board::from_fen("6rk/5Npp/8/8/8/8/8/6K1 b - - 0 1").unwrap();
+        assert!(smothered.in_check());
+        assert_eq!(
+            smothered.outcome(),
+            Some(GameOutcome::WhiteWin(WinType::Mate))
+        );
+
+        // stalemate where black's only non-king piece is pinned along a line it can't move on.
+        let pinned_stalemate = Board::from_fen("R5bk/8/6K1/8/8/8/8/8 b - - 0 1").unwrap();
+        assert!(!pinned_stalemate.in_check());
+        assert_eq!(
+            pinned_stalemate.outcome(),
+            Some(GameOutcome::Draw(DrawType::Stalemate))
+        );
+
+        // stalemate where the only pseudo-legal move is an en-passant capture
+        // that would expose the king along the fifth rank.
+        let mut ep_stalemate = Board::from_fen("7k/ppp5/1n6/KP5r/8/8/2n5/8 b - - 0 1").unwrap();
+        ep_stalemate.make_move_simple(Move::new(Square::C7, Square::C5));
+        assert_eq!(ep_stalemate.ep_sq(), Some(Square::C6));
+        assert!(!ep_stalemate.in_check());
+        assert_eq!(
+            ep_stalemate.outcome(),
+            Some(GameOutcome::Draw(DrawType::Stalemate))
+        );
+
+        // a checking pawn that can be taken en-passant is not mate.
+        let mut ep_escape = Board::from_fen("k7/3p4/2p5/4PP2/3PKP2/3PPP2/8/8 b - - 0 1").unwrap();
+        ep_escape.make_move_simple(Move::new(Square::D7, Square::D5));
+        assert!(ep_escape.in_check());
+        assert_eq!(
+            ep_escape.legal_moves().as_slice(),
+            &[Move::new_with_flags(
+                Square::E5,
+                Square::D6,
+                MoveFlags::EnPassant
+            )]
+        );
+        assert_eq!(ep_escape.outcome(), None);
+    }
+
     #[test]
     #[cfg_attr(miri, ignore)] // too slow.
     fn fen_round_trip() {
@@ -1650,6 +2163,43 @@ mod tests {
         }
     }
 
+    #[test]
+    fn frc_fen_round_trip() {
+        use super::{Board, Rules};
+        use crate::chess::fen::Fen;
+
+        let suite = std::fs::read_to_string("assets/epds/frcperftsuite.epd").unwrap();
+        let mut board = Board::empty(Rules::Chess960);
+        for line in suite.lines() {
+            let fen = line.split_once(';').unwrap().0.trim();
+            board.set_from_fen(&Fen::parse(fen).unwrap());
+            assert_eq!(board.to_string(), fen);
+        }
+    }
+
+    #[test]
+    fn castling_notation() {
+        use super::{Board, CastlingNotation};
+
+        let board = Board::from_fen("1r2k1r1/8/8/8/8/8/8/R3KR1R w FAgb - 0 1").unwrap();
+        // the inner white rook isn't the outermost, so X-FEN needs its file.
+        assert_eq!(
+            board.fen(CastlingNotation::XFen),
+            "1r2k1r1/8/8/8/8/8/8/R3KR1R w FQkq - 0 1"
+        );
+        assert_eq!(
+            board.fen(CastlingNotation::Shredder),
+            "1r2k1r1/8/8/8/8/8/8/R3KR1R w FAgb - 0 1"
+        );
+        assert_eq!(board.to_string(), board.fen(CastlingNotation::Shredder));
+        let board = Board::startpos();
+        assert_eq!(board.to_string(), Board::STARTING_FEN);
+        assert_eq!(
+            board.fen(CastlingNotation::Shredder),
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w HAha - 0 1"
+        );
+    }
+
     #[test]
     fn scharnagl_backrank_works() {
         use super::Board;
@@ -1754,6 +2304,63 @@ mod tests {
         assert_eq!(board.state.keys.zobrist, key);
     }
 
+    #[test]
+    fn key_after_exact_works_for_special_moves() {
+        use super::Board;
+        use crate::chess::chessmove::{Move, MoveFlags};
+        use crate::chess::types::Square;
+        let cases = [
+            // promotion with capture, losing black the kingside castle.
+            (
+                "r3k2r/6P1/8/8/8/8/8/R3K2R w KQkq - 0 1",
+                Move::new_with_promo(
+                    Square::G7,
+                    Square::H8,
+                    crate::chess::piece::PieceType::Queen,
+                ),
+            ),
+            // kingside castle.
+            (
+                "r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1",
+                Move::new_with_flags(Square::E1, Square::H1, MoveFlags::Castle),
+            ),
+            // en passant capture.
+            (
+                "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+                Move::new_with_flags(Square::E5, Square::F6, MoveFlags::EnPassant),
+            ),
+        ];
+        for (fen, mv) in cases {
+            let mut board = Board::from_fen(fen).unwrap();
+            assert!(board.is_legal(mv), "{fen} {mv:?}");
+            let key = board.key_after_exact(mv);
+            board.make_move_simple(mv);
+            assert_eq!(board.state.keys.zobrist, key, "{fen} {mv:?}");
+        }
+    }
+
+    #[test]
+    fn key_after_exact_matches_every_legal_move() {
+        use super::Board;
+        let fens = [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+        ];
+        for fen in fens {
+            let board = Board::from_fen(fen).unwrap();
+            for mv in board.legal_moves() {
+                let mut after = board.clone();
+                after.make_move_simple(mv);
+                assert_eq!(
+                    after.state.keys.zobrist,
+                    board.key_after_exact(mv),
+                    "{fen} {mv:?}"
+                );
+            }
+        }
+    }
+
     #[test]
     fn ep_square_edge_case() {
         use super::Board;
@@ -1854,4 +2461,254 @@ mod tests {
 
         board.make_move_simple(castle_move);
     }
+
+    #[test]
+    fn piece_set_accessors() {
+        use crate::chess::{piece::Colour, squareset::SquareSet};
+
+        let board = Board::startpos();
+        assert_eq!(board.pawns(Colour::White).count(), 8);
+        assert_eq!(board.knights(Colour::Black).count(), 2);
+        assert_eq!(
+            board.queens(Colour::White),
+            SquareSet::from_square(Square::D1)
+        );
+        assert_eq!(
+            board.king(Colour::Black),
+            SquareSet::from_square(Square::E8)
+        );
+        assert_eq!(board.occupied().count(), 32);
+        assert_eq!(
+            board.occupied_co(Colour::White) | board.occupied_co(Colour::Black),
+            board.occupied()
+        );
+
+        let board = Board::from_fen("4k3/8/8/3p4/4P3/8/8/4K3 w - - 0 1").unwrap();
+        assert_eq!(
+            board.pawns(Colour::White),
+            SquareSet::from_square(Square::E4)
+        );
+        assert_eq!(board.rooks(Colour::White), SquareSet::EMPTY);
+        assert_eq!(board.bishops(Colour::Black), SquareSet::EMPTY);
+    }
+
+    #[test]
+    fn en_passant_is_capture() {
+        let mut board = Board::from_fen("4k3/3p4/8/4P3/8/8/8/4K3 b - - 0 1").unwrap();
+        board.make_move_simple(board.parse_uci("d7d5").unwrap());
+        let ep = board.parse_uci("e5d6").unwrap();
+        assert!(ep.is_ep());
+        assert!(board.is_capture(ep));
+        assert!(board.is_tactical(ep));
+        let push = board.parse_uci("e5e6").unwrap();
+        assert!(!board.is_capture(push));
+    }
+
+    #[test]
+    fn fifty_move_draw_respects_mate() {
+        use super::{GameOutcome, WinType};
+
+        let quiet = Board::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 b - - 100 60").unwrap();
+        assert!(quiet.is_fifty_move_draw());
+
+        let mut mated = Board::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 99 60").unwrap();
+        assert!(!mated.is_fifty_move_draw());
+        mated.make_move_simple(Move::new(Square::A1, Square::A8));
+        assert_eq!(mated.fifty_move_counter(), 100);
+        assert!(!mated.is_fifty_move_draw());
+        assert_eq!(mated.outcome(), Some(GameOutcome::WhiteWin(WinType::Mate)));
+    }
+
+    #[test]
+    fn automatic_draws() {
+        use super::DrawType;
+
+        // fivefold repetition: shuffle the knights back to the start four times.
+        let mut board = Board::startpos();
+        let shuffle = ["g1f3", "g8f6", "f3g1", "f6g8"];
+        for cycle in 1..=4 {
+            assert_eq!(board.is_automatic_draw(), None);
+            for uci in shuffle {
+                board.make_move_simple(board.parse_uci(uci).unwrap());
+            }
+            assert_eq!(board.repetition_count(), cycle + 1);
+            if cycle == 2 {
+                // threefold is claimable, but not automatic.
+                assert!(board.outcome().is_some());
+                assert_eq!(board.is_automatic_draw(), None);
+            }
+        }
+        assert_eq!(
+            board.is_automatic_draw(),
+            Some(DrawType::FivefoldRepetition)
+        );
+
+        // seventy-five-move rule.
+        let mut board = Board::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 100 60").unwrap();
+        assert!(board.is_fifty_move_draw());
+        assert_eq!(board.is_automatic_draw(), None);
+        board.state.fifty_move_counter = 149;
+        board.make_move_simple(Move::new(Square::G1, Square::F1));
+        assert_eq!(board.fifty_move_counter(), 150);
+        assert_eq!(board.is_automatic_draw(), Some(DrawType::SeventyFiveMoves));
+
+        // ...unless the final move mates.
+        let mut mated = Board::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 60").unwrap();
+        mated.state.fifty_move_counter = 149;
+        mated.make_move_simple(Move::new(Square::A1, Square::A8));
+        assert_eq!(mated.fifty_move_counter(), 150);
+        assert_eq!(mated.is_automatic_draw(), None);
+    }
+
+    #[test]
+    fn make_move_logging_records_san() {
+        let mut board = Board::startpos();
+        let mut log = Vec::new();
+        for uci in [
+            "e2e4", "e7e5", "g1f3", "b8c6", "f1b5", "g8f6", "e1g1", "f6e4",
+        ] {
+            let m = board.parse_uci(uci).unwrap();
+            log.push(board.make_move_logging(m).unwrap());
+        }
+        assert_eq!(log, ["e4", "e5", "Nf3", "Nc6", "Bb5", "Nf6", "O-O", "Nxe4"]);
+
+        // illegal moves are rejected without touching the board.
+        let before = board.clone();
+        assert_eq!(
+            board.make_move_logging(Move::new(Square::A1, Square::A8)),
+            None
+        );
+        assert!(board == before);
+    }
+
+    #[test]
+    fn passed_pawns() {
+        use crate::chess::{piece::Colour, squareset::SquareSet};
+
+        // the a-pawn is passed; the e-pawn is blockaded by the e5 pawn, and
+        // the black pawn on d6 stops the c-pawn.
+        let board = Board::from_fen("4k3/8/3p4/4p3/2P1P3/8/P7/4K3 w - - 0 1").unwrap();
+        assert!(board.is_passed_pawn(Square::A2));
+        assert!(!board.is_passed_pawn(Square::E4));
+        assert!(!board.is_passed_pawn(Square::C4));
+        assert!(!board.is_passed_pawn(Square::E1));
+        assert!(!board.is_passed_pawn(Square::H4));
+        assert_eq!(
+            board.passed_pawns(Colour::White),
+            SquareSet::from_square(Square::A2)
+        );
+        assert_eq!(board.passed_pawns(Colour::Black), SquareSet::EMPTY);
+
+        let board = Board::from_fen("4k3/8/8/1p6/8/8/7P/4K3 w - - 0 1").unwrap();
+        assert_eq!(
+            board.passed_pawns(Colour::Black),
+            SquareSet::from_square(Square::B5)
+        );
+        assert_eq!(
+            board.passed_pawns(Colour::White),
+            SquareSet::from_square(Square::H2)
+        );
+    }
+
+    #[test]
+    fn seeded_random_moves_are_reproducible() {
+        use crate::{rng::XorShiftState, search::parameters::Config};
+
+        let conf = Config::default();
+        let play = |seed| {
+            let mut rng = XorShiftState::with_seed(seed);
+            let mut board = Board::startpos();
+            (0..16)
+                .map_while(|_| board.make_random_move_seeded(&mut rng, &conf, -1000))
+                .collect::<Vec<_>>()
+        };
+        let a = play(42);
+        assert_eq!(a.len(), 16);
+        assert_eq!(a, play(42));
+        assert_ne!(a, play(43));
+    }
+
+    #[test]
+    fn attackers_to_both_colours_and_x_rays() {
+        use crate::chess::squareset::SquareSet;
+
+        let board = Board::from_fen("4k3/8/8/3r1n2/8/2PR4/8/3QK3 w - - 0 1").unwrap();
+        let occupied = board.state.bbs.occupied();
+        let direct = [Square::D5, Square::F5, Square::C3, Square::D3]
+            .into_iter()
+            .fold(SquareSet::EMPTY, SquareSet::add_square);
+        assert_eq!(board.attackers_to(Square::D4, occupied), direct);
+        // lifting the rook off d3 uncovers the queen behind it.
+        let x_ray = board.attackers_to(Square::D4, occupied.remove_square(Square::D3));
+        assert_eq!(x_ray, direct.add_square(Square::D1));
+    }
+
+    #[test]
+    fn gives_check_agrees_with_playout() {
+        use crate::{bench, chess::types::CheckState};
+
+        let special = [
+            // discovered check from the rook behind the knight.
+            "4k3/8/8/8/4N3/8/8/4RK2 w - - 0 1",
+            // promotion with check along the back rank.
+            "4k3/1P6/8/8/8/8/8/4K3 w - - 0 1",
+            // castling with check from the rook.
+            "5k2/8/8/8/8/8/8/4K2R w K - 0 1",
+            // en passant that uncovers a bishop.
+            "8/8/8/1k6/2Pp4/8/8/5BK1 b - c3 0 1",
+            // en passant that removes the blocker on a rank.
+            "8/8/8/k2pP2R/8/8/8/7K w - d6 0 1",
+        ];
+        for fen in special
+            .into_iter()
+            .chain(bench::BENCH_POSITIONS.iter().copied())
+        {
+            let board = Board::from_fen(fen).unwrap();
+            for m in board.legal_moves() {
+                assert_eq!(
+                    board.gives_check(m),
+                    !matches!(board.gives(m), CheckState::None),
+                    "{fen} {}",
+                    m.display(board.rules())
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn pinned_pieces() {
+        use crate::chess::{piece::Colour, squareset::SquareSet};
+
+        // the bishop on b4 pins the knight on d2.
+        let board = Board::from_fen("7k/8/8/8/1b5N/8/3N4/4K2R w - - 0 1").unwrap();
+        assert_eq!(board.pinned(Colour::White), Square::D2.as_set());
+        assert_eq!(board.pinned(Colour::Black), SquareSet::EMPTY);
+    }
+
+    #[test]
+    fn updated_threats_match_generated() {
+        let mut rng = crate::rng::XorShiftState::with_seed(0x1234_5678);
+        for fen in crate::bench::BENCH_POSITIONS {
+            let mut board = Board::from_fen(fen).unwrap();
+            for _ in 0..60 {
+                let moves = board.legal_moves();
+                if moves.is_empty() {
+                    break;
+                }
+                // null moves change the threats too.
+                if !board.in_check() && rng.next_below(8) == 0 {
+                    board.make_nullmove();
+                } else {
+                    #[allow(clippy::cast_possible_truncation)]
+                    let m = moves[rng.next_below(moves.len() as u64) as usize];
+                    board.make_move_simple(m);
+                }
+                assert_eq!(
+                    board.state.threats,
+                    board.state.bbs.generate_threats(board.turn()),
+                    "{board}"
+                );
+            }
+        }
+    }
 }
@@ -1,7 +1,10 @@
+//! Move generation and attack tables.
+
 use arrayvec::ArrayVec;
 
 use std::{
     fmt::{Display, Formatter},
+    iter::FusedIterator,
     ops::{Deref, DerefMut},
 };
 
@@ -11,8 +14,8 @@ use crate::{
         board::{Board, Rules},
         chessmove::{Move, MoveFlags},
         magic::{
-            DIAG_ATTACKS, DIAG_REL_BITS, DIAG_TABLE, ORTH_ATTACKS, ORTH_REL_BITS, ORTH_TABLE,
-            diag_attacks_slow, orth_attacks_slow, set_occupancy,
+            self, DIAG_REL_BITS, DIAG_TABLE, ORTH_REL_BITS, ORTH_TABLE, diag_attacks_slow,
+            orth_attacks_slow, set_occupancy,
         },
         piece::{Black, Col, Colour, Piece, PieceType, White},
         squareset::SquareSet,
@@ -20,21 +23,27 @@ use crate::{
     },
 };
 
+/// The largest number of legal moves in any chess position.
 pub const MAX_POSITION_MOVES: usize = 218;
 
+/// A move, with a score for move ordering.
 #[repr(C)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct MoveListEntry {
+    /// The ordering score.
     pub score: i32,
+    /// The move.
     pub mov: Move,
 }
 
+/// A list of moves, big enough for any position.
 #[derive(Clone, Debug)]
 pub struct MoveList {
     inner: ArrayVec<MoveListEntry, MAX_POSITION_MOVES>,
 }
 
 impl MoveList {
+    /// An empty move list.
     pub fn new() -> Self {
         Self {
             inner: ArrayVec::new(),
@@ -45,15 +54,50 @@ impl MoveList {
         self.inner.push(MoveListEntry { mov: m, score: 0 });
     }
 
+    /// Iterate over the moves in the list.
     pub fn iter_moves(&self) -> impl Iterator<Item = &Move> {
         self.inner.iter().map(|e| &e.mov)
     }
 
+    /// Remove every move from the list.
     pub fn clear(&mut self) {
         self.inner.clear();
     }
 }
 
+impl Default for MoveList {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The legal moves of a position, generated into a list on the stack rather than the heap.
+/// Made by [`Board::legal_moves_iter`].
+#[derive(Clone, Debug)]
+pub struct LegalMoves {
+    list: MoveList,
+    next: usize,
+}
+
+impl Iterator for LegalMoves {
+    type Item = Move;
+
+    fn next(&mut self) -> Option<Move> {
+        let entry = self.list.get(self.next)?;
+        self.next += 1;
+        Some(entry.mov)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.list.len() - self.next;
+        (remaining, Some(remaining))
+    }
+}
+
+impl ExactSizeIterator for LegalMoves {}
+
+impl FusedIterator for LegalMoves {}
+
 impl Deref for MoveList {
     type Target = [MoveListEntry];
 
@@ -107,6 +151,7 @@ const fn in_between(sq1: Square, sq2: Square) -> SquareSet {
     SquareSet::from_inner(line & btwn)
 }
 
+/// The squares strictly between two squares on a line, or none if they don't share one.
 pub static RAY_BETWEEN: [[SquareSet; 64]; 64] = {
     let mut res = [[SquareSet::EMPTY; 64]; 64];
     let mut from = Square::A1;
@@ -127,6 +172,8 @@ pub static RAY_BETWEEN: [[SquareSet; 64]; 64] = {
     res
 };
 
+/// The squares between two squares on a line, along with the two squares themselves.
+/// If they don't share a line, just the two squares.
 pub static RAY_INTERSECTING: [[SquareSet; 64]; 64] = {
     let mut res = [[SquareSet::EMPTY; 64]; 64];
     let mut from = Square::A1;
@@ -148,6 +195,7 @@ pub static RAY_INTERSECTING: [[SquareSet; 64]; 64] = {
     res
 };
 
+/// The whole line through two squares, from edge to edge, or none if they don't share one.
 pub static RAY_FULL: [[SquareSet; 64]; 64] = {
     // cache these to accelerate consteval
     let mut rook_table = [SquareSet::EMPTY; 64];
@@ -200,6 +248,30 @@ pub static RAY_FULL: [[SquareSet; 64]; 64] = {
     res
 };
 
+/// For each colour and square, the squares ahead of a pawn of that colour on that square,
+/// on its own file and the two adjacent files. The pawn is passed if no enemy pawn is in this set.
+pub static PASSED_PAWN_MASKS: [[SquareSet; 64]; 2] = {
+    const FILE_A: u64 = 0x0101_0101_0101_0101;
+    let mut res = [[SquareSet::EMPTY; 64]; 2];
+    let mut sq = Square::A1;
+    loop {
+        let file = FILE_A << (sq.inner() % 8);
+        let span = file
+            | ((file << 1) & !SquareSet::FILE_A.inner())
+            | ((file >> 1) & !SquareSet::FILE_H.inner());
+        let rank = sq.inner() / 8;
+        let white_ahead = if rank == 7 { 0 } else { !0 << ((rank + 1) * 8) };
+        let black_ahead = (1 << (rank * 8)) - 1;
+        res[Colour::White as usize][sq.index()] = SquareSet::from_inner(span & white_ahead);
+        res[Colour::Black as usize][sq.index()] = SquareSet::from_inner(span & black_ahead);
+        let Some(next) = sq.add(1) else {
+            break;
+        };
+        sq = next;
+    }
+    res
+};
+
 const fn init_jumping_attacks<const IS_KNIGHT: bool>() -> [SquareSet; 64] {
     let mut attacks = [SquareSet::EMPTY; 64];
 
@@ -234,7 +306,7 @@ const fn jumping_attacks_slow<const IS_KNIGHT: bool>(sq: Square) -> SquareSet {
     SquareSet::from_inner(attacks_bb)
 }
 
-pub fn init_sliders_attacks() -> Result<(), std::io::Error> {
+pub(crate) fn init_sliders_attacks() -> Result<(), std::io::Error> {
     #![allow(clippy::large_stack_arrays)]
     let mut bishop_attacks = vec![[SquareSet::EMPTY; 512]; 64];
     for sq in Square::all() {
@@ -277,6 +349,31 @@ pub fn init_sliders_attacks() -> Result<(), std::io::Error> {
         }
     }
 
+    // the pext tables are indexed by the blockers' bits gathered from the mask, in the same
+    // order that `set_occupancy` spreads an index over it.
+    let mut bishop_attacks_pext = vec![[SquareSet::EMPTY; 512]; 64];
+    let mut rook_attacks_pext = vec![[SquareSet::EMPTY; 4096]; 64];
+    for sq in Square::all() {
+        let mask = magic::diag_mask(sq);
+        let entries = 1 << mask.count();
+        for (index, entry) in bishop_attacks_pext[sq as usize][..entries]
+            .iter_mut()
+            .enumerate()
+        {
+            let occupancy = set_occupancy(index, mask.count().into(), mask);
+            *entry = diag_attacks_slow(sq, occupancy);
+        }
+        let mask = magic::orth_mask(sq);
+        let entries = 1 << mask.count();
+        for (index, entry) in rook_attacks_pext[sq as usize][..entries]
+            .iter_mut()
+            .enumerate()
+        {
+            let occupancy = set_occupancy(index, mask.count().into(), mask);
+            *entry = orth_attacks_slow(sq, occupancy);
+        }
+    }
+
     // SAFETY: SquareSet is POD.
     let bishop_bytes = unsafe { bishop_attacks.align_to::<u8>().1 };
     // SAFETY: SquareSet is POD.
@@ -284,46 +381,67 @@ pub fn init_sliders_attacks() -> Result<(), std::io::Error> {
 
     std::fs::write("embeds/diagonal_attacks.bin", bishop_bytes)?;
     std::fs::write("embeds/orthogonal_attacks.bin", rook_bytes)?;
+    // SAFETY: SquareSet is POD.
+    let bishop_bytes = unsafe { bishop_attacks_pext.align_to::<u8>().1 };
+    // SAFETY: SquareSet is POD.
+    let rook_bytes = unsafe { rook_attacks_pext.align_to::<u8>().1 };
+    std::fs::write("embeds/diagonal_attacks_pext.bin", bishop_bytes)?;
+    std::fs::write("embeds/orthogonal_attacks_pext.bin", rook_bytes)?;
 
     Ok(())
 }
 
+/// The squares a bishop on `sq` attacks, with `blockers` in the way.
 #[allow(clippy::cast_possible_truncation)]
 pub fn diag_attacks(sq: Square, blockers: SquareSet) -> SquareSet {
-    let entry = &DIAG_TABLE[sq];
-    let relevant_blockers = blockers & entry.mask;
-    let data = relevant_blockers.inner().wrapping_mul(entry.magic);
-    let idx = (data >> (64 - DIAG_REL_BITS)) as usize;
-    // SAFETY: The largest value we can obtain from (data >> 55)
-    // is u64::MAX >> 55, which is 511 (0x1FF). DIAG_ATTACKS[sq]
-    // is 512 elements long, so this is always in bounds.
-    unsafe { *DIAG_ATTACKS[sq].get_unchecked(idx) }
+    #[cfg(pext)]
+    return magic::diag_attacks_pext(sq, blockers);
+    #[cfg(not(pext))]
+    {
+        let entry = &DIAG_TABLE[sq];
+        let relevant_blockers = blockers & entry.mask;
+        let data = relevant_blockers.inner().wrapping_mul(entry.magic);
+        let idx = (data >> (64 - DIAG_REL_BITS)) as usize;
+        // SAFETY: The largest value we can obtain from (data >> 55)
+        // is u64::MAX >> 55, which is 511 (0x1FF). DIAG_ATTACKS[sq]
+        // is 512 elements long, so this is always in bounds.
+        unsafe { *magic::DIAG_ATTACKS[sq].get_unchecked(idx) }
+    }
 }
+/// The squares a rook on `sq` attacks, with `blockers` in the way.
 #[allow(clippy::cast_possible_truncation)]
 pub fn orth_attacks(sq: Square, blockers: SquareSet) -> SquareSet {
-    let entry = &ORTH_TABLE[sq];
-    let relevant_blockers = blockers & entry.mask;
-    let data = relevant_blockers.inner().wrapping_mul(entry.magic);
-    let idx = (data >> (64 - ORTH_REL_BITS)) as usize;
-    // SAFETY: The largest value we can obtain from (data >> 52)
-    // is u64::MAX >> 52, which is 4095 (0xFFF). ORTH_ATTACKS[sq]
-    // is 4096 elements long, so this is always in bounds.
-    unsafe { *ORTH_ATTACKS[sq].get_unchecked(idx) }
+    #[cfg(pext)]
+    return magic::orth_attacks_pext(sq, blockers);
+    #[cfg(not(pext))]
+    {
+        let entry = &ORTH_TABLE[sq];
+        let relevant_blockers = blockers & entry.mask;
+        let data = relevant_blockers.inner().wrapping_mul(entry.magic);
+        let idx = (data >> (64 - ORTH_REL_BITS)) as usize;
+        // SAFETY: The largest value we can obtain from (data >> 52)
+        // is u64::MAX >> 52, which is 4095 (0xFFF). ORTH_ATTACKS[sq]
+        // is 4096 elements long, so this is always in bounds.
+        unsafe { *magic::ORTH_ATTACKS[sq].get_unchecked(idx) }
+    }
 }
+/// The squares a knight on `sq` attacks.
 pub fn knight_attacks(sq: Square) -> SquareSet {
     static KNIGHT_ATTACKS: [SquareSet; 64] = init_jumping_attacks::<true>();
     KNIGHT_ATTACKS[sq]
 }
+/// The squares a king on `sq` attacks.
 pub fn king_attacks(sq: Square) -> SquareSet {
     static KING_ATTACKS: [SquareSet; 64] = init_jumping_attacks::<false>();
     KING_ATTACKS[sq]
 }
-pub const fn knight_attacks_slow(sq: Square) -> SquareSet {
+pub(crate) const fn knight_attacks_slow(sq: Square) -> SquareSet {
     jumping_attacks_slow::<true>(sq)
 }
-pub const fn king_attacks_slow(sq: Square) -> SquareSet {
+pub(crate) const fn king_attacks_slow(sq: Square) -> SquareSet {
     jumping_attacks_slow::<false>(sq)
 }
+/// The squares attacked by pawns of colour `C` on the squares of `bb`.
 pub fn pawn_attacks<C: Col>(bb: SquareSet) -> SquareSet {
     if C::WHITE {
         bb.north_east_one() | bb.north_west_one()
@@ -331,6 +449,7 @@ pub fn pawn_attacks<C: Col>(bb: SquareSet) -> SquareSet {
         bb.south_east_one() | bb.south_west_one()
     }
 }
+/// The squares attacked by pawns of colour `colour` on the squares of `bb`.
 pub const fn pawn_attacks_by(bb: SquareSet, colour: Colour) -> SquareSet {
     if matches!(colour, Colour::White) {
         bb.north_east_one().union(bb.north_west_one())
@@ -339,6 +458,7 @@ pub const fn pawn_attacks_by(bb: SquareSet, colour: Colour) -> SquareSet {
     }
 }
 
+/// The squares `piece` on `sq` attacks, with `blockers` in the way.
 pub fn attacks_by_type(piece: Piece, sq: Square, blockers: SquareSet) -> SquareSet {
     match piece.piece_type() {
         PieceType::Pawn => pawn_attacks_by(sq.as_set(), piece.colour()),
@@ -350,7 +470,11 @@ pub fn attacks_by_type(piece: Piece, sq: Square, blockers: SquareSet) -> SquareS
     }
 }
 
-pub const fn attacks_by_type_slow(piece: Piece, sq: Square, blockers: SquareSet) -> SquareSet {
+pub(crate) const fn attacks_by_type_slow(
+    piece: Piece,
+    sq: Square,
+    blockers: SquareSet,
+) -> SquareSet {
     match piece.piece_type() {
         PieceType::Pawn => pawn_attacks_by(sq.as_set(), piece.colour()),
         PieceType::Knight => knight_attacks_slow(sq),
@@ -361,14 +485,18 @@ pub const fn attacks_by_type_slow(piece: Piece, sq: Square, blockers: SquareSet)
     }
 }
 
+/// Which promotions the staged capture generator produces.
 pub trait MoveGenMode {
+    /// Whether to skip underpromotions, generating only promotions to a queen.
     const SKIP_QUIETS: bool;
 }
 
+/// Generate only promotions to a queen.
 pub struct SkipQuiets;
 impl MoveGenMode for SkipQuiets {
     const SKIP_QUIETS: bool = true;
 }
+/// Generate promotions to every piece.
 pub struct AllMoves;
 impl MoveGenMode for AllMoves {
     const SKIP_QUIETS: bool = false;
@@ -378,14 +506,14 @@ impl Board {
     fn generate_pawn_caps<C: Col, Mode: MoveGenMode>(
         &self,
         move_list: &mut MoveList,
+        our_pawns: SquareSet,
         valid_target_squares: SquareSet,
     ) {
         #![allow(clippy::useless_let_if_seq)]
 
-        use PieceType::{Bishop, Knight, Pawn, Queen, Rook};
+        use PieceType::{Bishop, Knight, Queen, Rook};
 
         let bbs = &self.state.bbs;
-        let our_pawns = bbs.pieces[Pawn] & bbs.colours[C::COLOUR];
         let valid_targets = bbs.colours[!C::COLOUR] & valid_target_squares;
         let promo_rank = [SquareSet::RANK_7, SquareSet::RANK_2][C::COLOUR];
 
@@ -455,23 +583,28 @@ impl Board {
         } & our_pawns;
 
         for from_sq in attacks {
-            move_list.push(Move::new_with_flags(from_sq, ep_sq, MoveFlags::EnPassant));
+            // taking en passant removes two pawns from the same rank at once,
+            // which can expose the king in ways that pin masks don't capture.
+            let m = Move::new_with_flags(from_sq, ep_sq, MoveFlags::EnPassant);
+            if self.is_legal(m) {
+                move_list.push(m);
+            }
         }
     }
 
     fn generate_pawn_forward<C: Col>(
         &self,
         move_list: &mut MoveList,
+        our_pawns: SquareSet,
         valid_target_squares: SquareSet,
     ) {
         #![allow(clippy::useless_let_if_seq)]
 
-        use PieceType::{Bishop, Knight, Pawn, Queen, Rook};
+        use PieceType::{Bishop, Knight, Queen, Rook};
 
         let bbs = &self.state.bbs;
         let promo_rank = [SquareSet::RANK_7, SquareSet::RANK_2][C::COLOUR];
         let start_rank = [SquareSet::RANK_2, SquareSet::RANK_7][C::COLOUR];
-        let our_pawns = bbs.pieces[Pawn] & bbs.colours[C::COLOUR];
         let empty = bbs.empty();
 
         let shifted_valid_squares;
@@ -550,87 +683,210 @@ impl Board {
         }
     }
 
+    /// Generates all legal moves in the position.
     pub fn generate_moves(&self, move_list: &mut MoveList) {
         move_list.clear();
         if self.side == Colour::White {
-            self.generate_moves_for::<White>(move_list);
+            self.generate_moves_for::<White>(move_list, SquareSet::FULL);
         } else {
-            self.generate_moves_for::<Black>(move_list);
+            self.generate_moves_for::<Black>(move_list, SquareSet::FULL);
         }
         debug_assert!(move_list.iter_moves().all(|m| m.is_valid()));
+        debug_assert!(move_list.iter_moves().all(|&m| self.is_legal(m)));
     }
 
-    fn generate_moves_for<C: Col>(&self, move_list: &mut MoveList) {
-        use PieceType::{Bishop, King, Knight, Queen, Rook};
+    /// Iterate over the legal moves in the position, without allocating.
+    pub fn legal_moves_iter(&self) -> LegalMoves {
+        let mut list = MoveList::new();
+        self.generate_moves(&mut list);
+        LegalMoves { list, next: 0 }
+    }
+
+    /// The squares that the king on `king_sq` can step to without moving into check.
+    /// A slider that gives check also attacks the squares behind the king on its line,
+    /// which the threat map misses because the king itself blocks them.
+    fn king_destinations(&self, king_sq: Square) -> SquareSet {
+        use PieceType::{Bishop, Queen, Rook};
+        let bbs = &self.state.bbs;
+        let sliders = bbs.pieces[Bishop] | bbs.pieces[Rook] | bbs.pieces[Queen];
+        let mut targets = king_attacks(king_sq) & !self.state.threats.all;
+        for checker in self.state.threats.checkers & sliders {
+            targets &= !RAY_FULL[king_sq][checker] | checker.as_set();
+        }
+        targets
+    }
+
+    /// Generates the legal moves of the pieces on the squares in `pieces`.
+    fn generate_moves_for<C: Col>(&self, move_list: &mut MoveList, pieces: SquareSet) {
+        use PieceType::{Bishop, King, Knight, Pawn, Queen, Rook};
         #[cfg(debug_assertions)]
         self.check_validity();
 
+        if self.in_check() {
+            self.generate_evasions_for::<C>(move_list, pieces);
+            return;
+        }
+
         let bbs = &self.state.bbs;
         let our_pieces = bbs.colours[C::COLOUR];
+        let our_movers = our_pieces & pieces;
         let their_pieces = bbs.colours[!C::COLOUR];
         let freespace = !(our_pieces | their_pieces);
         let our_king = bbs.pieces[King] & our_pieces;
         debug_assert_eq!(our_king.count(), 1);
         let our_king_sq = our_king.first().unwrap();
 
-        if self.state.threats.checkers.count() > 1 {
-            // we're in double-check, so we can only move the king.
-            let moves = king_attacks(our_king_sq) & !self.state.threats.all;
-            for to in moves & (their_pieces | freespace) {
-                move_list.push(Move::new(our_king_sq, to));
+        // a pinned piece can only move along the line between its king and the pinner.
+        let pinned = self.state.pinned[C::COLOUR];
+        let targets_from = |sq: Square| {
+            if pinned.contains_square(sq) {
+                RAY_FULL[our_king_sq][sq]
+            } else {
+                SquareSet::FULL
             }
-            return;
-        }
-
-        let valid_target_squares = if self.in_check() {
-            RAY_INTERSECTING[our_king_sq][self.state.threats.checkers.first().unwrap()]
-        } else {
-            SquareSet::FULL
         };
 
-        self.generate_pawn_forward::<C>(move_list, valid_target_squares);
-        self.generate_pawn_caps::<C, AllMoves>(move_list, valid_target_squares);
-        self.generate_ep::<C>(move_list);
+        // pawns: the free ones all at once, and the pinned ones one by one.
+        let our_pawns = bbs.pieces[Pawn] & our_movers;
+        self.generate_pawn_forward::<C>(move_list, our_pawns & !pinned, SquareSet::FULL);
+        self.generate_pawn_caps::<C, AllMoves>(move_list, our_pawns & !pinned, SquareSet::FULL);
+        for sq in our_pawns & pinned {
+            self.generate_pawn_forward::<C>(move_list, sq.as_set(), targets_from(sq));
+            self.generate_pawn_caps::<C, AllMoves>(move_list, sq.as_set(), targets_from(sq));
+        }
+        if our_pawns != SquareSet::EMPTY {
+            self.generate_ep::<C>(move_list);
+        }
 
-        // knights
-        let our_knights = bbs.pieces[Knight] & our_pieces;
+        // knights, which can never move along a pin.
+        let our_knights = bbs.pieces[Knight] & our_movers & !pinned;
         for sq in our_knights {
-            let moves = knight_attacks(sq) & valid_target_squares;
-            for to in moves & (their_pieces | freespace) {
+            for to in knight_attacks(sq) & (their_pieces | freespace) {
                 move_list.push(Move::new(sq, to));
             }
         }
 
         // kings
-        let moves = king_attacks(our_king_sq) & !self.state.threats.all;
-        for to in moves & (their_pieces | freespace) {
-            move_list.push(Move::new(our_king_sq, to));
+        let king_moves = pieces.contains_square(our_king_sq);
+        if king_moves {
+            for to in self.king_destinations(our_king_sq) & (their_pieces | freespace) {
+                move_list.push(Move::new(our_king_sq, to));
+            }
         }
 
         // bishops and queens
-        let our_diagonal_sliders = (bbs.pieces[Queen] | bbs.pieces[Bishop]) & our_pieces;
+        let our_diagonal_sliders = (bbs.pieces[Queen] | bbs.pieces[Bishop]) & our_movers;
         let blockers = bbs.occupied();
         for sq in our_diagonal_sliders {
-            let moves = diag_attacks(sq, blockers) & valid_target_squares;
+            let moves = diag_attacks(sq, blockers) & targets_from(sq);
             for to in moves & (their_pieces | freespace) {
                 move_list.push(Move::new(sq, to));
             }
         }
 
         // rooks and queens
-        let our_orthogonal_sliders = (bbs.pieces[Queen] | bbs.pieces[Rook]) & our_pieces;
+        let our_orthogonal_sliders = (bbs.pieces[Queen] | bbs.pieces[Rook]) & our_movers;
         for sq in our_orthogonal_sliders {
-            let moves = orth_attacks(sq, blockers) & valid_target_squares;
+            let moves = orth_attacks(sq, blockers) & targets_from(sq);
             for to in moves & (their_pieces | freespace) {
                 move_list.push(Move::new(sq, to));
             }
         }
 
-        if !self.in_check() {
+        if king_moves {
             self.generate_castling_moves_for::<C>(move_list);
+            // castling generation checks the squares the king passes through,
+            // but not all of the conditions on where it lands.
+            move_list
+                .inner
+                .retain(|e| !e.mov.is_castle() || self.is_legal(e.mov));
         }
     }
 
+    /// Generates all legal moves in a position where the side to move is in check:
+    /// king moves, captures of the checking piece, and interpositions.
+    pub fn generate_evasions(&self, move_list: &mut MoveList) {
+        debug_assert!(self.in_check());
+        move_list.clear();
+        if self.side == Colour::White {
+            self.generate_evasions_for::<White>(move_list, SquareSet::FULL);
+        } else {
+            self.generate_evasions_for::<Black>(move_list, SquareSet::FULL);
+        }
+        debug_assert!(move_list.iter_moves().all(|m| m.is_valid()));
+        debug_assert!(move_list.iter_moves().all(|&m| self.is_legal(m)));
+    }
+
+    /// Generates the evasions that move the pieces on the squares in `pieces`.
+    fn generate_evasions_for<C: Col>(&self, move_list: &mut MoveList, pieces: SquareSet) {
+        use PieceType::{Bishop, King, Knight, Pawn, Queen, Rook};
+
+        let bbs = &self.state.bbs;
+        let our_pieces = bbs.colours[C::COLOUR];
+        let our_king = bbs.pieces[King] & our_pieces;
+        debug_assert_eq!(our_king.count(), 1);
+        let our_king_sq = our_king.first().unwrap();
+
+        if pieces.contains_square(our_king_sq) {
+            for to in self.king_destinations(our_king_sq) & !our_pieces {
+                move_list.push(Move::new(our_king_sq, to));
+            }
+        }
+
+        let checkers = self.state.threats.checkers;
+        if checkers.many() {
+            // we're in double-check, so we can only move the king.
+            return;
+        }
+        let Some(checker) = checkers.first() else {
+            return;
+        };
+
+        // the other pieces must capture the checker or block its line to the king.
+        // a pinned piece can't do either without exposing the king to a second attacker.
+        let valid_target_squares = RAY_INTERSECTING[our_king_sq][checker] & !our_pieces;
+        let movable = our_pieces & pieces & !self.state.pinned[C::COLOUR];
+
+        let our_pawns = bbs.pieces[Pawn] & movable;
+        self.generate_pawn_forward::<C>(move_list, our_pawns, valid_target_squares);
+        self.generate_pawn_caps::<C, AllMoves>(move_list, our_pawns, valid_target_squares);
+        if our_pawns != SquareSet::EMPTY {
+            self.generate_ep::<C>(move_list);
+        }
+
+        for sq in bbs.pieces[Knight] & movable {
+            for to in knight_attacks(sq) & valid_target_squares {
+                move_list.push(Move::new(sq, to));
+            }
+        }
+
+        let blockers = bbs.occupied();
+        for sq in (bbs.pieces[Queen] | bbs.pieces[Bishop]) & movable {
+            for to in diag_attacks(sq, blockers) & valid_target_squares {
+                move_list.push(Move::new(sq, to));
+            }
+        }
+        for sq in (bbs.pieces[Queen] | bbs.pieces[Rook]) & movable {
+            for to in orth_attacks(sq, blockers) & valid_target_squares {
+                move_list.push(Move::new(sq, to));
+            }
+        }
+    }
+
+    /// Generates the legal moves of pieces of type `piece_type` only.
+    pub fn generate_moves_for_piece_type(&self, piece_type: PieceType, move_list: &mut MoveList) {
+        move_list.clear();
+        let pieces = self.state.bbs.pieces[piece_type];
+        if self.side == Colour::White {
+            self.generate_moves_for::<White>(move_list, pieces);
+        } else {
+            self.generate_moves_for::<Black>(move_list, pieces);
+        }
+        debug_assert!(move_list.iter_moves().all(|m| m.is_valid()));
+        debug_assert!(move_list.iter_moves().all(|&m| self.is_legal(m)));
+    }
+
+    /// Generate the pseudo-legal captures and promotions, replacing the contents of `move_list`.
     pub fn generate_captures<Mode: MoveGenMode>(&self, move_list: &mut MoveList) {
         move_list.clear();
         if self.side == Colour::White {
@@ -672,7 +928,8 @@ impl Board {
         self.generate_forward_promos::<C, Mode>(move_list, valid_target_squares);
 
         // pawn captures and capture promos
-        self.generate_pawn_caps::<C, Mode>(move_list, valid_target_squares);
+        let our_pawns = bbs.pieces[PieceType::Pawn] & our_pieces;
+        self.generate_pawn_caps::<C, Mode>(move_list, our_pawns, valid_target_squares);
         self.generate_ep::<C>(move_list);
 
         // knights
@@ -824,6 +1081,7 @@ impl Board {
         }
     }
 
+    /// Add the pseudo-legal quiet moves, including castling, to `move_list`.
     pub fn generate_quiets(&self, move_list: &mut MoveList) {
         // we don't need to clear the move list here because we're only adding to it.
         if self.side == Colour::White {
@@ -944,7 +1202,7 @@ impl Board {
 }
 
 #[cfg(test)]
-pub fn synced_perft(pos: &mut Board, depth: usize) -> u64 {
+pub(crate) fn synced_perft(pos: &mut Board, depth: usize) -> u64 {
     #![allow(clippy::to_string_in_format_args)]
     #[cfg(debug_assertions)]
     pos.check_validity();
@@ -959,8 +1217,13 @@ pub fn synced_perft(pos: &mut Board, depth: usize) -> u64 {
     pos.generate_captures::<AllMoves>(&mut ml_staged);
     pos.generate_quiets(&mut ml_staged);
 
+    // staged generation is pseudo-legal, full generation is strictly legal.
     let mut full_moves_vec = ml.to_vec();
-    let mut staged_moves_vec = ml_staged.to_vec();
+    let mut staged_moves_vec = ml_staged
+        .iter()
+        .filter(|e| pos.is_legal(e.mov))
+        .copied()
+        .collect::<Vec<_>>();
     full_moves_vec.sort_unstable_by_key(|m| m.mov);
     staged_moves_vec.sort_unstable_by_key(|m| m.mov);
     let eq = full_moves_vec == staged_moves_vec;
@@ -986,9 +1249,6 @@ pub fn synced_perft(pos: &mut Board, depth: usize) -> u64 {
 
     let mut count = 0;
     for &m in ml.iter_moves() {
-        if !pos.is_legal(m) {
-            continue;
-        }
         pos.make_move_simple(m);
         count += synced_perft(pos, depth - 1);
         pos.unmake_move_base();
@@ -1024,6 +1284,65 @@ mod tests {
         }
     }
 
+    #[test]
+    fn legal_moves_iter_matches_move_list() {
+        for fen in bench::BENCH_POSITIONS {
+            let pos = Board::from_fen(fen).unwrap();
+            let mut ml = MoveList::new();
+            pos.generate_moves(&mut ml);
+            let mut iter = pos.legal_moves_iter();
+            assert_eq!(iter.len(), ml.len());
+            assert!(iter.by_ref().eq(ml.iter_moves().copied()));
+            assert_eq!(iter.next(), None);
+        }
+    }
+
+    #[test]
+    fn piece_type_movegen() {
+        let pos = Board::startpos();
+        let mut ml = MoveList::new();
+        pos.generate_moves_for_piece_type(PieceType::Knight, &mut ml);
+        let mut moves = ml.iter_moves().copied().collect::<Vec<_>>();
+        moves.sort_unstable();
+        let mut expected = vec![
+            Move::new(Square::B1, Square::A3),
+            Move::new(Square::B1, Square::C3),
+            Move::new(Square::G1, Square::F3),
+            Move::new(Square::G1, Square::H3),
+        ];
+        expected.sort_unstable();
+        assert_eq!(moves, expected);
+
+        // the king can't step along the line of a slider that checks it.
+        let pos = Board::from_fen("4k3/8/8/8/8/8/8/r3K3 w - - 0 1").unwrap();
+        pos.generate_moves_for_piece_type(PieceType::King, &mut ml);
+        let mut moves = ml.iter_moves().copied().collect::<Vec<_>>();
+        moves.sort_unstable();
+        let mut expected = vec![
+            Move::new(Square::E1, Square::D2),
+            Move::new(Square::E1, Square::E2),
+            Move::new(Square::E1, Square::F2),
+        ];
+        expected.sort_unstable();
+        assert_eq!(moves, expected);
+
+        // the union over all piece types must match full generation.
+        for fen in bench::BENCH_POSITIONS {
+            let pos = Board::from_fen(fen).unwrap();
+            let mut full = MoveList::new();
+            pos.generate_moves(&mut full);
+            let mut full = full.iter_moves().copied().collect::<Vec<_>>();
+            let mut by_type = Vec::new();
+            for piece_type in PieceType::all() {
+                pos.generate_moves_for_piece_type(piece_type, &mut ml);
+                by_type.extend(ml.iter_moves().copied());
+            }
+            full.sort_unstable();
+            by_type.sort_unstable();
+            assert_eq!(full, by_type, "mismatch in {fen}");
+        }
+    }
+
     #[test]
     fn no_king_into_check() {
         let pos = Board::from_fen("r4rk1/2pb1ppQ/2pp1q2/p1n5/2P1B3/PP2P3/3N1PPP/R4RK1 b - - 0 17")
@@ -1043,7 +1362,7 @@ mod tests {
         pos.generate_quiets(&mut ml);
 
         for m in ml.iter_moves() {
-            let Some(Piece::WK) = pos.state.mailbox[m.from()] else {
+            let Some(Piece::WK) = pos.mailbox[m.from()] else {
                 continue;
             };
             assert!(!pos.state.threats.all.contains_square(m.to()));
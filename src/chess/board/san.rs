@@ -36,7 +36,7 @@ impl Display for SanThunk<'_> {
             }
         }
         let to_sq = m.to();
-        let moved_piece = board.state.mailbox[m.from()].unwrap();
+        let moved_piece = board.mailbox[m.from()].unwrap();
         let is_capture = board.is_capture(m);
         let piece_prefix = match moved_piece.piece_type() {
             PieceType::Pawn if !is_capture => "",
@@ -91,6 +91,7 @@ impl Display for SanThunk<'_> {
 }
 
 impl Board {
+    /// The move `m` in SAN, if it's legal in this position.
     pub fn san(&self, m: Move) -> Option<SanThunk<'_>> {
         if !self.is_pseudo_legal(m) || !self.is_legal(m) {
             return None;
@@ -103,21 +104,32 @@ impl Board {
     ///
     /// Uses the current position as context to parse the move. Ambiguous moves are rejected.
     /// Overspecified moves (including long algebraic notation) are accepted. Common syntactical
-    /// variations are also accepted.
+    /// variations are also accepted: castling with zeroes, promotions without `=`, captures
+    /// without `x`, and trailing check, mate, en passant, and annotation marks (`+#!?`, `e.p.`).
+    /// Castling is resolved through the castling rights, so it works the same in Chess960.
     ///
     /// The returned move is guaranteed to be legal.
     #[expect(clippy::too_many_lines)]
     pub fn parse_san(&self, san: &str) -> Result<Move, SanError> {
+        const SUFFIX_MARKS: [char; 4] = ['+', '#', '!', '?'];
+
         let san = san.trim();
 
+        // Strip check/mate indicators and annotations for parsing
+        let trim = san.trim_end_matches(SUFFIX_MARKS);
+        let trim = trim
+            .strip_suffix("e.p.")
+            .map_or(trim, str::trim_end)
+            .trim_end_matches(SUFFIX_MARKS);
+
         // Handle castling
-        match san {
-            "O-O" | "O-O+" | "O-O#" | "0-0" | "0-0+" | "0-0#" => {
+        match trim {
+            "O-O" | "0-0" => {
                 return self
                     .find_castling_move(true)
                     .ok_or_else(|| SanError::IllegalMove(san.to_string()));
             }
-            "O-O-O" | "O-O-O+" | "O-O-O#" | "0-0-0" | "0-0-0+" | "0-0-0#" => {
+            "O-O-O" | "0-0-0" => {
                 return self
                     .find_castling_move(false)
                     .ok_or_else(|| SanError::IllegalMove(san.to_string()));
@@ -125,8 +137,6 @@ impl Board {
             _ => (),
         }
 
-        // Strip check/mate indicators for parsing
-        let trim = san.trim_end_matches(['+', '#']);
         let bytes = trim.as_bytes();
 
         if bytes.is_empty() {
@@ -245,13 +255,20 @@ impl Board {
             );
 
             if SquareSet::BACK_RANKS.contains_square(to_square)
-                && let Some(p) = self.state.mailbox[from_square]
+                && let Some(p) = self.mailbox[from_square]
                 && p.piece_type() == PieceType::Pawn
                 && promotion.is_none()
             {
                 return Err(SanError::MissingPromotion(san.to_string()));
             }
 
+            // a piece letter has to name the piece that's actually there, but a
+            // long-algebraic move without one (e.g. "g1f3") can move anything.
+            let moved = self.mailbox[from_square].map(Piece::piece_type);
+            if piece_type != PieceType::Pawn && moved != Some(piece_type) {
+                return Err(SanError::IllegalMove(san.to_string()));
+            }
+
             if !self.is_pseudo_legal(m) || !self.is_legal(m) {
                 return Err(SanError::IllegalMove(san.to_string()));
             }
@@ -292,7 +309,7 @@ impl Board {
             }
 
             // Check piece
-            if self.state.mailbox[m.from()] != Some(Piece::new(self.side, piece_type)) {
+            if self.mailbox[m.from()] != Some(Piece::new(self.side, piece_type)) {
                 continue;
             }
 
@@ -301,10 +318,6 @@ impl Board {
                 continue;
             }
 
-            if !self.is_legal(m) {
-                continue;
-            }
-
             if matched_move.is_some() {
                 return Err(SanError::AmbiguousMove(san.to_string()));
             }
@@ -587,6 +600,44 @@ mod tests {
         assert_eq!(m.to(), Square::F3);
     }
 
+    #[test]
+    fn annotations() {
+        let board = Board::startpos();
+        for san in ["e4!", "e4?", "e4!!", "e4?!", "e4!?"] {
+            assert_eq!(board.parse_san(san), Ok(Move::new(Square::E2, Square::E4)));
+        }
+        let board = Board::from_fen("r3k2r/pppppppp/8/8/8/8/PPPPPPPP/R3K2R w KQkq - 0 1").unwrap();
+        assert!(board.parse_san("O-O!").unwrap().is_castle());
+        assert!(board.parse_san("0-0-0+?").unwrap().is_castle());
+    }
+
+    #[test]
+    fn piece_letter_must_match() {
+        let board = Board::startpos();
+        let e = board.parse_san("Bg1f3").unwrap_err();
+        assert_eq!(e, SanError::IllegalMove("Bg1f3".into()));
+    }
+
+    #[test]
+    fn frc_castling() {
+        // the king is on b1, and the rooks are on a1 and f1.
+        let board = Board::from_fen("rk3r2/pppppppp/8/8/8/8/PPPPPPPP/RK3R2 w FAfa - 0 1").unwrap();
+        let m = board.parse_san("O-O").unwrap();
+        assert!(m.is_castle());
+        assert_eq!(m.from(), Square::B1);
+        assert_eq!(m.to(), Square::F1);
+        assert_eq!(board.san(m).unwrap().to_string(), "O-O");
+        // castling queenside only moves the rook, from a1 to d1, and the king to c1.
+        let m = board.parse_san("O-O-O").unwrap();
+        assert_eq!(m.to(), Square::A1);
+        // without the right, castling is illegal.
+        let board = Board::from_fen("rk3r2/pppppppp/8/8/8/8/PPPPPPPP/RK3R2 w Aa - 0 1").unwrap();
+        assert_eq!(
+            board.parse_san("O-O"),
+            Err(SanError::IllegalMove("O-O".into()))
+        );
+    }
+
     #[test]
     fn en_passant() {
         let board = Board::from_fen("rnbqkbnr/pppp1ppp/8/4pP2/8/8/PPPPP1PP/RNBQKBNR w KQkq e6 0 3")
@@ -597,5 +648,9 @@ mod tests {
         let m = board.parse_san("fe6").unwrap();
         assert!(m.is_ep());
         assert_eq!(m.to(), Square::E6);
+        let m = board.parse_san("fxe6 e.p.").unwrap();
+        assert!(m.is_ep());
+        let m = board.parse_san("fxe6e.p.+").unwrap();
+        assert!(m.is_ep());
     }
 }
@@ -1,9 +1,12 @@
 #![allow(unused_imports)]
 
+use anyhow::{bail, ensure};
+
 use crate::{
     chess::{
         board::Board,
         piece::{Colour, Piece},
+        squareset::SquareSet,
         types::{Rank, Square},
     },
     nnue::network::NNUEState,
@@ -11,79 +14,157 @@ use crate::{
 };
 
 impl Board {
+    /// Panics if the board is internally inconsistent.
     #[cfg(debug_assertions)]
-    #[allow(clippy::cognitive_complexity, clippy::too_many_lines)]
     pub fn check_validity(&self) {
-        #![allow(clippy::similar_names, clippy::cast_possible_truncation)]
-
-        // check turn
-        assert!(
-            !(self.side != Colour::White && self.side != Colour::Black),
-            "invalid side: {:?}",
-            self.side
-        );
+        if let Err(e) = self.validate() {
+            panic!("{e}");
+        }
+    }
 
+    /// Check that the board is internally consistent: the mailbox agrees with
+    /// the square-sets, the keys, threats, and pins match the position, and
+    /// each side has exactly one king.
+    #[allow(clippy::cognitive_complexity, dead_code)]
+    pub fn validate(&self) -> anyhow::Result<()> {
         // check square-set / piece array coherency
         for sq in Square::all() {
             let piece_ss = self.state.bbs.piece_at(sq);
-            let piece_mb = self.state.mailbox[sq];
-            assert_eq!(
-                piece_ss, piece_mb,
+            let piece_mb = self.mailbox[sq];
+            ensure!(
+                piece_ss == piece_mb,
                 "square-set / piece array coherency corrupt: expected square {sq} to be '{piece_ss:?}' but was '{piece_mb:?}'",
             );
         }
 
-        assert!(
-            self.side == Colour::White || self.side == Colour::Black,
-            "side is corrupt: expected WHITE or BLACK, got {:?}",
-            self.side
-        );
-        assert!(
-            self.state.generate_pos_keys(self.side) == self.state.keys,
-            "key is corrupt: expected {:?}, got {:?}",
-            self.state.generate_pos_keys(self.side),
-            self.state.keys
+        // check that no square is claimed by both colours, or by two piece types
+        let [white, black] = self.state.bbs.colours;
+        ensure!(
+            white & black == SquareSet::EMPTY,
+            "colour square-sets overlap on {:?}",
+            white & black
         );
-
-        if let Some(ep_square) = self.state.ep_square
-            && !(ep_square.rank() == Rank::Six && self.side == Colour::White)
-            && !(ep_square.rank() == Rank::Three && self.side == Colour::Black)
-        {
-            panic!(
-                "en passant square is corrupt: expected square to be None or to be on ranks 6 or 3, got {ep_square} ({:?})",
-                ep_square.rank()
+        let mut seen = SquareSet::EMPTY;
+        for bb in self.state.bbs.pieces {
+            ensure!(
+                seen & bb == SquareSet::EMPTY,
+                "piece square-sets overlap on {:?}",
+                seen & bb
             );
+            seen |= bb;
         }
-
-        // the fifty-move counter is allowed to be *exactly* 100, to allow a finished game to be
-        // created.
-        assert!(
-            self.state.fifty_move_counter <= 100,
-            "fifty move counter is corrupt: expected 0-100, got {}",
-            self.state.fifty_move_counter
+        ensure!(
+            seen == (white | black),
+            "piece square-sets and colour square-sets disagree: {seen:?} vs {:?}",
+            white | black
         );
 
         // check there are the correct number of kings for each side
-        assert!(
+        ensure!(
             self.state.bbs.piece_bb(Piece::WK).count() == 1,
             "white king count is corrupt: expected 1, got {}",
             self.state.bbs.piece_bb(Piece::WK).count()
         );
-        assert!(
+        ensure!(
             self.state.bbs.piece_bb(Piece::BK).count() == 1,
             "black king count is corrupt: expected 1, got {}",
             self.state.bbs.piece_bb(Piece::BK).count()
         );
 
-        assert!(
-            self.state.mailbox[self.state.bbs.king_sq(Colour::White)] == Some(Piece::WK),
+        ensure!(
+            self.mailbox[self.state.bbs.king_sq(Colour::White)] == Some(Piece::WK),
             "white king square is corrupt: expected white king, got {:?}",
-            self.state.mailbox[self.state.bbs.king_sq(Colour::White)]
+            self.mailbox[self.state.bbs.king_sq(Colour::White)]
         );
-        assert!(
-            self.state.mailbox[self.state.bbs.king_sq(Colour::Black)] == Some(Piece::BK),
+        ensure!(
+            self.mailbox[self.state.bbs.king_sq(Colour::Black)] == Some(Piece::BK),
             "black king square is corrupt: expected black king, got {:?}",
-            self.state.mailbox[self.state.bbs.king_sq(Colour::Black)]
+            self.mailbox[self.state.bbs.king_sq(Colour::Black)]
         );
+
+        if let Some(ep_square) = self.state.ep_square
+            && !(ep_square.rank() == Rank::Six && self.side == Colour::White)
+            && !(ep_square.rank() == Rank::Three && self.side == Colour::Black)
+        {
+            bail!(
+                "en passant square is corrupt: expected square to be None or to be on ranks 6 or 3, got {ep_square} ({:?})",
+                ep_square.rank()
+            );
+        }
+
+        // the fifty-move counter may run past 100, as the fifty-move draw must be claimed,
+        // but the seventy-five-move rule ends the game automatically at exactly 150.
+        ensure!(
+            self.state.fifty_move_counter <= 150,
+            "fifty move counter is corrupt: expected 0-150, got {}",
+            self.state.fifty_move_counter
+        );
+
+        let keys = self.state.generate_pos_keys(self.side);
+        ensure!(
+            keys == self.state.keys,
+            "key is corrupt: expected {keys:?}, got {:?}",
+            self.state.keys
+        );
+
+        let threats = self.state.bbs.generate_threats(self.side);
+        ensure!(
+            threats == self.state.threats,
+            "threats are corrupt: expected {threats:?}, got {:?}",
+            self.state.threats
+        );
+
+        let pinned = [
+            self.state.bbs.generate_pinned(Colour::White),
+            self.state.bbs.generate_pinned(Colour::Black),
+        ];
+        ensure!(
+            pinned == self.state.pinned,
+            "pinned pieces are corrupt: expected {pinned:?}, got {:?}",
+            self.state.pinned
+        );
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chess::{
+        board::Board,
+        piece::{Colour, Piece},
+        types::Square,
+    };
+
+    #[test]
+    fn corrupted_boards_fail_validation() {
+        let board = Board::startpos();
+        assert!(board.validate().is_ok());
+
+        // mailbox disagrees with the square-sets.
+        let mut corrupt = board.clone();
+        corrupt.mailbox[Square::E4] = Some(Piece::WQ);
+        assert!(corrupt.validate().is_err());
+
+        // a second white king, consistently added.
+        let mut corrupt = board.clone();
+        corrupt.state.bbs.set_piece_at(Square::E4, Piece::WK);
+        corrupt.mailbox[Square::E4] = Some(Piece::WK);
+        assert!(corrupt.validate().is_err());
+
+        // stale hash key.
+        let mut corrupt = board.clone();
+        corrupt.state.keys.zobrist ^= 1;
+        assert!(corrupt.validate().is_err());
+
+        // stale threats.
+        let mut corrupt = board.clone();
+        corrupt.state.threats = corrupt.state.bbs.generate_threats(Colour::Black);
+        assert!(corrupt.validate().is_err());
+
+        // fifty-move counter out of range.
+        let mut corrupt = board;
+        corrupt.state.fifty_move_counter = 151;
+        assert!(corrupt.validate().is_err());
     }
 }
@@ -1,3 +1,5 @@
+//! The compact [`Move`] representation.
+
 use std::{
     fmt::{Debug, Display, Formatter},
     num::NonZeroU16,
@@ -10,6 +12,7 @@ use crate::chess::{
     types::{File, Square},
 };
 
+/// A move, packed into sixteen bits. Castling is encoded as the king capturing its own rook.
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
 #[repr(transparent)]
 pub struct Move {
@@ -22,11 +25,15 @@ const PROMO_FLAG_BITS: u16 = 0b1100_0000_0000_0000;
 const EP_FLAG_BITS: u16 = 0b0100_0000_0000_0000;
 const CASTLE_FLAG_BITS: u16 = 0b1000_0000_0000_0000;
 
+/// The special kinds of move.
 #[repr(u16)]
 #[derive(PartialEq, Eq, Debug)]
 pub enum MoveFlags {
+    /// A pawn promotion.
     Promotion = PROMO_FLAG_BITS,
+    /// An en passant capture.
     EnPassant = EP_FLAG_BITS,
+    /// Castling.
     Castle = CASTLE_FLAG_BITS,
 }
 
@@ -36,6 +43,7 @@ impl Move {
     const PROMO_MASK: u16 = 0b11;
     const PROMO_SHIFT: usize = 12;
 
+    /// A promotion from `from` to `to`.
     pub fn new_with_promo(from: Square, to: Square, promotion: PieceType) -> Self {
         debug_assert!(u16::from(from) & Self::SQ_MASK == u16::from(from));
         debug_assert!(u16::from(to) & Self::SQ_MASK == u16::from(to));
@@ -59,6 +67,7 @@ impl Move {
         Self { data }
     }
 
+    /// A special move from `from` to `to`. Promotions should use [`Move::new_with_promo`].
     pub fn new_with_flags(from: Square, to: Square, flags: MoveFlags) -> Self {
         debug_assert_ne!(
             flags,
@@ -74,6 +83,7 @@ impl Move {
         Self { data }
     }
 
+    /// An ordinary move from `from` to `to`.
     pub fn new(from: Square, to: Square) -> Self {
         debug_assert!(u16::from(from) & Self::SQ_MASK == u16::from(from));
         debug_assert!(u16::from(to) & Self::SQ_MASK == u16::from(to));
@@ -86,18 +96,21 @@ impl Move {
         Self { data }
     }
 
+    /// The square the move starts on.
     pub const fn from(self) -> Square {
         let index = (self.data.get() & Self::SQ_MASK) as u8;
         // SAFETY: SQ_MASK guarantees that this is in bounds.
         unsafe { Square::new_unchecked(index) }
     }
 
+    /// The square the move ends on, which is the rook's square for castling.
     pub const fn to(self) -> Square {
         let index = ((self.data.get() >> Self::TO_SHIFT) & Self::SQ_MASK) as u8;
         // SAFETY: SQ_MASK guarantees that this is in bounds.
         unsafe { Square::new_unchecked(index) }
     }
 
+    /// The piece type promoted to, if this is a promotion.
     pub fn promotion_type(self) -> Option<PieceType> {
         if self.is_promo() {
             // SAFETY: out-of-range values are made impossible by the mask.
@@ -113,14 +126,17 @@ impl Move {
         }
     }
 
+    /// Whether this is a promotion.
     pub const fn is_promo(self) -> bool {
         (self.data.get() & PROMO_FLAG_BITS) == PROMO_FLAG_BITS
     }
 
+    /// Whether this is an en passant capture.
     pub const fn is_ep(self) -> bool {
         (self.data.get() & EP_FLAG_BITS) != 0 && self.data.get() & CASTLE_FLAG_BITS == 0
     }
 
+    /// Whether this is castling.
     pub const fn is_castle(self) -> bool {
         (self.data.get() & CASTLE_FLAG_BITS) != 0 && self.data.get() & EP_FLAG_BITS == 0
     }
@@ -143,6 +159,7 @@ impl Move {
         }
     }
 
+    /// Whether this move's bits are consistent, which a move read from outside might not be.
     pub fn is_valid(self) -> bool {
         let promotion = self.promotion_type();
         if promotion.is_some() && !self.is_promo() {
@@ -152,21 +169,25 @@ impl Move {
         promotion.is_none() || promotion.unwrap().legal_promo()
     }
 
+    /// The raw bits of this move.
     #[allow(dead_code)]
     pub const fn inner(self) -> u16 {
         self.data.get()
     }
 
+    /// The move with the given raw bits, if they're non-zero.
     #[allow(dead_code)]
     pub fn from_raw(data: u16) -> Option<Self> {
         // this is just a safe `transmute`.
         NonZeroU16::new(data).map(|nz| Self { data: nz })
     }
 
+    /// Display this move in UCI notation, writing castling in the form `rules` expects.
     pub const fn display(self, rules: Rules) -> MoveDisplay {
         MoveDisplay { m: self, rules }
     }
 
+    /// Whether this move goes from the second or seventh rank to the fourth or fifth.
     pub fn is_double_pawn_push_ranks(self) -> bool {
         let to = self.to().as_set();
         let from = self.from().as_set();
@@ -175,6 +196,7 @@ impl Move {
     }
 }
 
+/// A move in UCI notation, from [`Move::display`].
 pub struct MoveDisplay {
     m: Move,
     rules: Rules,
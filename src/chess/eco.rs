@@ -0,0 +1,542 @@
+//! Encyclopaedia of Chess Openings classification for common opening positions.
+
+use std::{collections::HashMap, sync::LazyLock};
+
+use crate::chess::board::{Board, Rules};
+
+/// A curated subset of mainline openings, as (ECO code, name, SAN moves from the start position).
+/// Positions are matched by hash key, so transpositions into a listed line are recognised.
+/// Where a position is listed more than once, the later entry wins.
+#[rustfmt::skip]
+static ECO_LINES: &[(&str, &str, &str)] = &[
+    // A: flank openings
+    ("A00", "Polish Opening", "b4"),
+    ("A00", "Grob Opening", "g4"),
+    ("A00", "Van't Kruijs Opening", "e3"),
+    ("A00", "Hungarian Opening", "g3"),
+    ("A00", "Amar Opening", "Nh3"),
+    ("A00", "Saragossa Opening", "c3"),
+    ("A00", "Mieses Opening", "d3"),
+    ("A00", "Anderssen's Opening", "a3"),
+    ("A00", "Ware Opening", "a4"),
+    ("A00", "Kádas Opening", "h4"),
+    ("A00", "Clemenz Opening", "h3"),
+    ("A00", "Barnes Opening", "f3"),
+    ("A00", "Van Geet Opening", "Nc3"),
+    ("A01", "Nimzo-Larsen Attack", "b3"),
+    ("A01", "Nimzo-Larsen Attack: Modern Variation", "b3 e5"),
+    ("A01", "Nimzo-Larsen Attack: Classical Variation", "b3 d5"),
+    ("A02", "Bird's Opening", "f4"),
+    ("A02", "Bird's Opening: From's Gambit", "f4 e5"),
+    ("A03", "Bird's Opening: Dutch Variation", "f4 d5"),
+    ("A04", "Réti Opening", "Nf3"),
+    ("A04", "Zukertort Opening: Sicilian Invitation", "Nf3 c5"),
+    ("A04", "Zukertort Opening: Dutch Variation", "Nf3 f5"),
+    ("A05", "Réti Opening", "Nf3 Nf6"),
+    ("A05", "King's Indian Attack", "Nf3 Nf6 g3"),
+    ("A06", "Réti Opening", "Nf3 d5"),
+    ("A07", "King's Indian Attack", "Nf3 d5 g3"),
+    ("A08", "King's Indian Attack", "Nf3 d5 g3 c5 Bg2"),
+    ("A09", "Réti Opening", "Nf3 d5 c4"),
+    ("A09", "Réti Opening: Advance Variation", "Nf3 d5 c4 d4"),
+    ("A09", "Réti Opening: Réti Accepted", "Nf3 d5 c4 dxc4"),
+    ("A10", "English Opening", "c4"),
+    ("A10", "English Opening: Anglo-Dutch Defence", "c4 f5"),
+    ("A11", "English Opening: Caro-Kann Defensive System", "c4 c6"),
+    ("A13", "English Opening: Agincourt Defence", "c4 e6"),
+    ("A15", "English Opening: Anglo-Indian Defence", "c4 Nf6"),
+    ("A16", "English Opening: Anglo-Indian Defence", "c4 Nf6 Nc3"),
+    ("A16", "English Opening: Anglo-Grünfeld Defence", "c4 Nf6 Nc3 d5"),
+    ("A17", "English Opening: Anglo-Indian Defence, Queen's Knight Variation", "c4 Nf6 Nc3 e6"),
+    ("A18", "English Opening: Mikenas-Carls Variation", "c4 Nf6 Nc3 e6 e4"),
+    ("A20", "English Opening: King's English Variation", "c4 e5"),
+    ("A21", "English Opening: King's English Variation", "c4 e5 Nc3"),
+    ("A22", "English Opening: King's English Variation", "c4 e5 Nc3 Nf6"),
+    ("A22", "English Opening: Bremen System", "c4 e5 Nc3 Nf6 g3"),
+    ("A25", "English Opening: Sicilian Reversed", "c4 e5 Nc3 Nc6"),
+    ("A25", "English Opening: Closed", "c4 e5 Nc3 Nc6 g3"),
+    ("A27", "English Opening: Three Knights System", "c4 e5 Nc3 Nc6 Nf3"),
+    ("A28", "English Opening: Four Knights System", "c4 e5 Nc3 Nc6 Nf3 Nf6"),
+    ("A29", "English Opening: Four Knights, Kingside Fianchetto", "c4 e5 Nc3 Nc6 Nf3 Nf6 g3"),
+    ("A30", "English Opening: Symmetrical Variation", "c4 c5"),
+    ("A34", "English Opening: Symmetrical Variation", "c4 c5 Nc3"),
+    ("A35", "English Opening: Symmetrical Variation", "c4 c5 Nc3 Nc6"),
+    ("A36", "English Opening: Symmetrical Variation", "c4 c5 Nc3 Nc6 g3"),
+    ("A40", "Queen's Pawn Game", "d4"),
+    ("A40", "Englund Gambit", "d4 e5"),
+    ("A40", "Modern Defence", "d4 g6"),
+    ("A40", "Horwitz Defence", "d4 e6"),
+    ("A41", "Queen's Pawn Game", "d4 d6"),
+    ("A42", "Modern Defence: Averbakh System", "d4 d6 c4 g6 Nc3 Bg7 e4"),
+    ("A43", "Old Benoni Defence", "d4 c5"),
+    ("A43", "Old Benoni Defence", "d4 c5 d5"),
+    ("A45", "Indian Defence", "d4 Nf6"),
+    ("A45", "Trompowsky Attack", "d4 Nf6 Bg5"),
+    ("A46", "Indian Defence", "d4 Nf6 Nf3"),
+    ("A46", "Torre Attack", "d4 Nf6 Nf3 e6 Bg5"),
+    ("A48", "Indian Defence: East Indian Defence", "d4 Nf6 Nf3 g6"),
+    ("A50", "Indian Defence", "d4 Nf6 c4"),
+    ("A51", "Budapest Gambit", "d4 Nf6 c4 e5"),
+    ("A51", "Budapest Defence: Fajarowicz Variation", "d4 Nf6 c4 e5 dxe5 Ne4"),
+    ("A52", "Budapest Gambit", "d4 Nf6 c4 e5 dxe5 Ng4"),
+    ("A53", "Old Indian Defence", "d4 Nf6 c4 d6"),
+    ("A54", "Old Indian Defence", "d4 Nf6 c4 d6 Nc3 e5"),
+    ("A56", "Benoni Defence", "d4 Nf6 c4 c5"),
+    ("A56", "Benoni Defence: Czech Benoni", "d4 Nf6 c4 c5 d5 e5"),
+    ("A57", "Benko Gambit", "d4 Nf6 c4 c5 d5 b5"),
+    ("A58", "Benko Gambit Accepted", "d4 Nf6 c4 c5 d5 b5 cxb5 a6 bxa6"),
+    ("A60", "Benoni Defence: Modern Variation", "d4 Nf6 c4 c5 d5 e6"),
+    ("A61", "Benoni Defence", "d4 Nf6 c4 c5 d5 e6 Nc3 exd5 cxd5 d6 Nf3 g6"),
+    ("A65", "Benoni Defence: King's Pawn Line", "d4 Nf6 c4 c5 d5 e6 Nc3 exd5 cxd5 d6 e4"),
+    ("A67", "Benoni Defence: Taimanov Variation", "d4 Nf6 c4 c5 d5 e6 Nc3 exd5 cxd5 d6 e4 g6 f4 Bg7 Bb5+"),
+    ("A80", "Dutch Defence", "d4 f5"),
+    ("A81", "Dutch Defence: Fianchetto Attack", "d4 f5 g3"),
+    ("A82", "Dutch Defence: Staunton Gambit", "d4 f5 e4"),
+    ("A83", "Dutch Defence: Staunton Gambit Accepted", "d4 f5 e4 fxe4 Nc3 Nf6 Bg5"),
+    ("A84", "Dutch Defence", "d4 f5 c4"),
+    ("A85", "Dutch Defence: Queen's Knight Variation", "d4 f5 c4 Nf6 Nc3"),
+    ("A86", "Dutch Defence", "d4 f5 c4 Nf6 g3"),
+    ("A87", "Dutch Defence: Leningrad Variation", "d4 f5 c4 Nf6 g3 g6 Bg2 Bg7 Nf3"),
+    ("A90", "Dutch Defence", "d4 f5 c4 Nf6 g3 e6 Bg2"),
+    ("A90", "Dutch Defence: Stonewall Variation", "d4 f5 c4 Nf6 g3 e6 Bg2 d5"),
+    ("A91", "Dutch Defence", "d4 f5 c4 Nf6 g3 e6 Bg2 Be7"),
+    // B: semi-open games other than the French
+    ("B00", "Nimzowitsch Defence", "e4 Nc6"),
+    ("B00", "Owen's Defence", "e4 b6"),
+    ("B00", "St. George Defence", "e4 a6"),
+    ("B00", "Borg Defence", "e4 g5"),
+    ("B00", "Carr Defence", "e4 h6"),
+    ("B00", "Nimzowitsch Defence: Scandinavian Variation", "e4 Nc6 d4 d5"),
+    ("B01", "Scandinavian Defence", "e4 d5"),
+    ("B01", "Scandinavian Defence: Modern Variation", "e4 d5 exd5 Nf6"),
+    ("B01", "Scandinavian Defence: Main Line", "e4 d5 exd5 Qxd5 Nc3 Qa5"),
+    ("B01", "Scandinavian Defence: Mieses-Kotroc Variation", "e4 d5 exd5 Qxd5"),
+    ("B01", "Scandinavian Defence: Gubinsky-Melts Defence", "e4 d5 exd5 Qxd5 Nc3 Qd6"),
+    ("B01", "Scandinavian Defence: Valencian Variation", "e4 d5 exd5 Qxd5 Nc3 Qd8"),
+    ("B02", "Alekhine's Defence", "e4 Nf6"),
+    ("B02", "Alekhine's Defence: Scandinavian Variation", "e4 Nf6 Nc3 d5"),
+    ("B02", "Alekhine's Defence: Two Pawns Attack", "e4 Nf6 e5 Nd5 c4"),
+    ("B03", "Alekhine's Defence", "e4 Nf6 e5 Nd5 d4"),
+    ("B03", "Alekhine's Defence: Four Pawns Attack", "e4 Nf6 e5 Nd5 d4 d6 c4 Nb6 f4"),
+    ("B03", "Alekhine's Defence: Exchange Variation", "e4 Nf6 e5 Nd5 d4 d6 c4 Nb6 exd6"),
+    ("B04", "Alekhine's Defence: Modern Variation", "e4 Nf6 e5 Nd5 d4 d6 Nf3"),
+    ("B05", "Alekhine's Defence: Modern Variation", "e4 Nf6 e5 Nd5 d4 d6 Nf3 Bg4"),
+    ("B06", "Modern Defence", "e4 g6"),
+    ("B06", "Modern Defence", "e4 g6 d4 Bg7"),
+    ("B06", "Modern Defence: Standard Line", "e4 g6 d4 Bg7 Nc3 d6"),
+    ("B07", "Pirc Defence", "e4 d6 d4 Nf6"),
+    ("B07", "Pirc Defence", "e4 d6"),
+    ("B07", "Pirc Defence", "e4 d6 d4 Nf6 Nc3 g6"),
+    ("B07", "Pirc Defence: 150 Attack", "e4 d6 d4 Nf6 Nc3 g6 Be3 c6 Qd2"),
+    ("B08", "Pirc Defence: Classical Variation", "e4 d6 d4 Nf6 Nc3 g6 Nf3"),
+    ("B09", "Pirc Defence: Austrian Attack", "e4 d6 d4 Nf6 Nc3 g6 f4"),
+    ("B10", "Caro-Kann Defence", "e4 c6"),
+    ("B10", "Caro-Kann Defence: Accelerated Panov Attack", "e4 c6 c4"),
+    ("B10", "Caro-Kann Defence: Two Knights Attack", "e4 c6 Nc3 d5 Nf3"),
+    ("B11", "Caro-Kann Defence: Two Knights Attack, Mindeno Variation", "e4 c6 Nc3 d5 Nf3 Bg4"),
+    ("B12", "Caro-Kann Defence: Advance Variation", "e4 c6 d4 d5 e5"),
+    ("B12", "Caro-Kann Defence", "e4 c6 d4 d5"),
+    ("B12", "Caro-Kann Defence: Advance Variation", "e4 c6 d4 d5 e5 Bf5"),
+    ("B13", "Caro-Kann Defence: Exchange Variation", "e4 c6 d4 d5 exd5 cxd5"),
+    ("B13", "Caro-Kann Defence: Panov Attack", "e4 c6 d4 d5 exd5 cxd5 c4"),
+    ("B14", "Caro-Kann Defence: Panov Attack", "e4 c6 d4 d5 exd5 cxd5 c4 Nf6 Nc3 e6"),
+    ("B15", "Caro-Kann Defence", "e4 c6 d4 d5 Nc3"),
+    ("B15", "Caro-Kann Defence", "e4 c6 d4 d5 Nc3 dxe4 Nxe4"),
+    ("B15", "Caro-Kann Defence: Tartakower Variation", "e4 c6 d4 d5 Nc3 dxe4 Nxe4 Nf6 Nxf6+ exf6"),
+    ("B16", "Caro-Kann Defence: Bronstein-Larsen Variation", "e4 c6 d4 d5 Nc3 dxe4 Nxe4 Nf6 Nxf6+ gxf6"),
+    ("B17", "Caro-Kann Defence: Karpov Variation", "e4 c6 d4 d5 Nc3 dxe4 Nxe4 Nd7"),
+    ("B18", "Caro-Kann Defence: Classical Variation", "e4 c6 d4 d5 Nc3 dxe4 Nxe4 Bf5"),
+    ("B19", "Caro-Kann Defence: Classical Variation", "e4 c6 d4 d5 Nc3 dxe4 Nxe4 Bf5 Ng3 Bg6 h4 h6 Nf3 Nd7 h5"),
+    ("B20", "Sicilian Defence", "e4 c5"),
+    ("B20", "Sicilian Defence: Bowdler Attack", "e4 c5 Bc4"),
+    ("B20", "Sicilian Defence: Wing Gambit", "e4 c5 b4"),
+    ("B21", "Sicilian Defence: Smith-Morra Gambit", "e4 c5 d4 cxd4 c3"),
+    ("B21", "Sicilian Defence: McDonnell Attack", "e4 c5 f4"),
+    ("B21", "Sicilian Defence: Smith-Morra Gambit Accepted", "e4 c5 d4 cxd4 c3 dxc3 Nxc3"),
+    ("B22", "Sicilian Defence: Alapin Variation", "e4 c5 c3"),
+    ("B22", "Sicilian Defence: Alapin Variation", "e4 c5 c3 Nf6"),
+    ("B22", "Sicilian Defence: Alapin Variation", "e4 c5 c3 d5"),
+    ("B23", "Sicilian Defence: Closed", "e4 c5 Nc3"),
+    ("B23", "Sicilian Defence: Grand Prix Attack", "e4 c5 Nc3 Nc6 f4"),
+    ("B24", "Sicilian Defence: Closed", "e4 c5 Nc3 Nc6 g3"),
+    ("B25", "Sicilian Defence: Closed", "e4 c5 Nc3 Nc6 g3 g6 Bg2 Bg7 d3 d6"),
+    ("B27", "Sicilian Defence", "e4 c5 Nf3"),
+    ("B27", "Sicilian Defence: Hyperaccelerated Dragon", "e4 c5 Nf3 g6"),
+    ("B27", "Sicilian Defence: Katalimov Variation", "e4 c5 Nf3 b6"),
+    ("B28", "Sicilian Defence: O'Kelly Variation", "e4 c5 Nf3 a6"),
+    ("B29", "Sicilian Defence: Nimzowitsch Variation", "e4 c5 Nf3 Nf6"),
+    ("B30", "Sicilian Defence: Old Sicilian", "e4 c5 Nf3 Nc6"),
+    ("B30", "Sicilian Defence: Rossolimo Variation", "e4 c5 Nf3 Nc6 Bb5"),
+    ("B31", "Sicilian Defence: Rossolimo Variation", "e4 c5 Nf3 Nc6 Bb5 g6"),
+    ("B32", "Sicilian Defence: Open", "e4 c5 Nf3 Nc6 d4 cxd4 Nxd4"),
+    ("B32", "Sicilian Defence: Open", "e4 c5 Nf3 Nc6 d4"),
+    ("B32", "Sicilian Defence: Open", "e4 c5 Nf3 Nc6 d4 cxd4"),
+    ("B32", "Sicilian Defence: Löwenthal Variation", "e4 c5 Nf3 Nc6 d4 cxd4 Nxd4 e5"),
+    ("B32", "Sicilian Defence: Kalashnikov Variation", "e4 c5 Nf3 Nc6 d4 cxd4 Nxd4 e5 Nb5 d6"),
+    ("B33", "Sicilian Defence: Sveshnikov Variation", "e4 c5 Nf3 Nc6 d4 cxd4 Nxd4 Nf6 Nc3 e5"),
+    ("B33", "Sicilian Defence: Open", "e4 c5 Nf3 Nc6 d4 cxd4 Nxd4 Nf6"),
+    ("B33", "Sicilian Defence: Sveshnikov Variation", "e4 c5 Nf3 Nc6 d4 cxd4 Nxd4 Nf6 Nc3 e5 Ndb5 d6"),
+    ("B34", "Sicilian Defence: Accelerated Dragon", "e4 c5 Nf3 Nc6 d4 cxd4 Nxd4 g6"),
+    ("B36", "Sicilian Defence: Accelerated Dragon, Maróczy Bind", "e4 c5 Nf3 Nc6 d4 cxd4 Nxd4 g6 c4"),
+    ("B40", "Sicilian Defence: French Variation", "e4 c5 Nf3 e6"),
+    ("B40", "Sicilian Defence: Pin Variation", "e4 c5 Nf3 e6 d4 cxd4 Nxd4 Nf6 Nc3 Bb4"),
+    ("B41", "Sicilian Defence: Kan Variation", "e4 c5 Nf3 e6 d4 cxd4 Nxd4 a6"),
+    ("B42", "Sicilian Defence: Kan Variation, Modern Variation", "e4 c5 Nf3 e6 d4 cxd4 Nxd4 a6 Bd3"),
+    ("B44", "Sicilian Defence: Taimanov Variation", "e4 c5 Nf3 e6 d4 cxd4 Nxd4 Nc6"),
+    ("B45", "Sicilian Defence: Four Knights Variation", "e4 c5 Nf3 e6 d4 cxd4 Nxd4 Nf6 Nc3 Nc6"),
+    ("B46", "Sicilian Defence: Taimanov Variation", "e4 c5 Nf3 e6 d4 cxd4 Nxd4 Nc6 Nc3 a6"),
+    ("B47", "Sicilian Defence: Taimanov Variation", "e4 c5 Nf3 e6 d4 cxd4 Nxd4 Nc6 Nc3 Qc7"),
+    ("B50", "Sicilian Defence", "e4 c5 Nf3 d6"),
+    ("B50", "Sicilian Defence: Delayed Alapin", "e4 c5 Nf3 d6 c3"),
+    ("B51", "Sicilian Defence: Moscow Variation", "e4 c5 Nf3 d6 Bb5+"),
+    ("B52", "Sicilian Defence: Moscow Variation", "e4 c5 Nf3 d6 Bb5+ Bd7"),
+    ("B53", "Sicilian Defence: Chekhover Variation", "e4 c5 Nf3 d6 d4 cxd4 Qxd4"),
+    ("B54", "Sicilian Defence: Open", "e4 c5 Nf3 d6 d4 cxd4 Nxd4"),
+    ("B56", "Sicilian Defence: Open", "e4 c5 Nf3 d6 d4 cxd4 Nxd4 Nf6 Nc3"),
+    ("B56", "Sicilian Defence: Classical Variation", "e4 c5 Nf3 d6 d4 cxd4 Nxd4 Nf6 Nc3 Nc6"),
+    ("B57", "Sicilian Defence: Sozin Attack", "e4 c5 Nf3 d6 d4 cxd4 Nxd4 Nf6 Nc3 Nc6 Bc4"),
+    ("B60", "Sicilian Defence: Richter-Rauzer Variation", "e4 c5 Nf3 d6 d4 cxd4 Nxd4 Nf6 Nc3 Nc6 Bg5"),
+    ("B70", "Sicilian Defence: Dragon Variation", "e4 c5 Nf3 d6 d4 cxd4 Nxd4 Nf6 Nc3 g6"),
+    ("B72", "Sicilian Defence: Dragon Variation", "e4 c5 Nf3 d6 d4 cxd4 Nxd4 Nf6 Nc3 g6 Be3 Bg7"),
+    ("B75", "Sicilian Defence: Dragon Variation, Yugoslav Attack", "e4 c5 Nf3 d6 d4 cxd4 Nxd4 Nf6 Nc3 g6 Be3 Bg7 f3"),
+    ("B76", "Sicilian Defence: Dragon Variation, Yugoslav Attack", "e4 c5 Nf3 d6 d4 cxd4 Nxd4 Nf6 Nc3 g6 Be3 Bg7 f3 O-O Qd2 Nc6"),
+    ("B80", "Sicilian Defence: Scheveningen Variation", "e4 c5 Nf3 d6 d4 cxd4 Nxd4 Nf6 Nc3 e6"),
+    ("B81", "Sicilian Defence: Scheveningen Variation, Keres Attack", "e4 c5 Nf3 d6 d4 cxd4 Nxd4 Nf6 Nc3 e6 g4"),
+    ("B90", "Sicilian Defence: Najdorf Variation", "e4 c5 Nf3 d6 d4 cxd4 Nxd4 Nf6 Nc3 a6"),
+    ("B90", "Sicilian Defence: Najdorf Variation, English Attack", "e4 c5 Nf3 d6 d4 cxd4 Nxd4 Nf6 Nc3 a6 Be3"),
+    ("B90", "Sicilian Defence: Najdorf Variation, Adams Attack", "e4 c5 Nf3 d6 d4 cxd4 Nxd4 Nf6 Nc3 a6 h3"),
+    ("B92", "Sicilian Defence: Najdorf Variation, Opočenský Variation", "e4 c5 Nf3 d6 d4 cxd4 Nxd4 Nf6 Nc3 a6 Be2"),
+    ("B93", "Sicilian Defence: Najdorf Variation, Amsterdam Variation", "e4 c5 Nf3 d6 d4 cxd4 Nxd4 Nf6 Nc3 a6 f4"),
+    ("B94", "Sicilian Defence: Najdorf Variation", "e4 c5 Nf3 d6 d4 cxd4 Nxd4 Nf6 Nc3 a6 Bg5"),
+    ("B96", "Sicilian Defence: Najdorf Variation", "e4 c5 Nf3 d6 d4 cxd4 Nxd4 Nf6 Nc3 a6 Bg5 e6 f4"),
+    ("B97", "Sicilian Defence: Najdorf Variation, Poisoned Pawn Variation", "e4 c5 Nf3 d6 d4 cxd4 Nxd4 Nf6 Nc3 a6 Bg5 e6 f4 Qb6"),
+    // C: open games and the French
+    ("C00", "French Defence", "e4 e6"),
+    ("C00", "French Defence: Knight Variation", "e4 e6 Nf3"),
+    ("C00", "French Defence: King's Indian Attack", "e4 e6 d3"),
+    ("C00", "French Defence: Normal Variation", "e4 e6 d4 d5"),
+    ("C01", "French Defence: Exchange Variation", "e4 e6 d4 d5 exd5"),
+    ("C02", "French Defence: Advance Variation", "e4 e6 d4 d5 e5"),
+    ("C02", "French Defence: Advance Variation", "e4 e6 d4 d5 e5 c5 c3"),
+    ("C02", "French Defence: Advance Variation", "e4 e6 d4 d5 e5 c5 c3 Nc6 Nf3 Qb6"),
+    ("C03", "French Defence: Tarrasch Variation", "e4 e6 d4 d5 Nd2"),
+    ("C03", "French Defence: Tarrasch Variation", "e4 e6 d4 d5 Nd2 Be7"),
+    ("C04", "French Defence: Tarrasch Variation, Guimard Defence", "e4 e6 d4 d5 Nd2 Nc6"),
+    ("C05", "French Defence: Tarrasch Variation, Closed Variation", "e4 e6 d4 d5 Nd2 Nf6"),
+    ("C07", "French Defence: Tarrasch Variation, Open System", "e4 e6 d4 d5 Nd2 c5"),
+    ("C08", "French Defence: Tarrasch Variation, Open System", "e4 e6 d4 d5 Nd2 c5 exd5 exd5"),
+    ("C10", "French Defence: Paulsen Variation", "e4 e6 d4 d5 Nc3"),
+    ("C10", "French Defence: Rubinstein Variation", "e4 e6 d4 d5 Nc3 dxe4"),
+    ("C10", "French Defence: Rubinstein Variation", "e4 e6 d4 d5 Nc3 dxe4 Nxe4"),
+    ("C11", "French Defence: Classical Variation", "e4 e6 d4 d5 Nc3 Nf6"),
+    ("C11", "French Defence: Steinitz Variation", "e4 e6 d4 d5 Nc3 Nf6 e5"),
+    ("C12", "French Defence: MacCutcheon Variation", "e4 e6 d4 d5 Nc3 Nf6 Bg5 Bb4"),
+    ("C13", "French Defence: Classical Variation", "e4 e6 d4 d5 Nc3 Nf6 Bg5 Be7"),
+    ("C13", "French Defence: Alekhine-Chatard Attack", "e4 e6 d4 d5 Nc3 Nf6 Bg5 Be7 e5 Nfd7 h4"),
+    ("C15", "French Defence: Winawer Variation", "e4 e6 d4 d5 Nc3 Bb4"),
+    ("C16", "French Defence: Winawer Variation, Advance Variation", "e4 e6 d4 d5 Nc3 Bb4 e5"),
+    ("C17", "French Defence: Winawer Variation", "e4 e6 d4 d5 Nc3 Bb4 e5 c5"),
+    ("C18", "French Defence: Winawer Variation", "e4 e6 d4 d5 Nc3 Bb4 e5 c5 a3 Bxc3+ bxc3"),
+    ("C20", "King's Pawn Game", "e4 e5"),
+    ("C20", "King's Pawn Game: Wayward Queen Attack", "e4 e5 Qh5"),
+    ("C20", "King's Pawn Game: Napoleon Attack", "e4 e5 Qf3"),
+    ("C20", "Alapin Opening", "e4 e5 Ne2"),
+    ("C21", "Centre Game", "e4 e5 d4 exd4"),
+    ("C21", "Danish Gambit", "e4 e5 d4 exd4 c3"),
+    ("C22", "Centre Game", "e4 e5 d4 exd4 Qxd4 Nc6"),
+    ("C23", "Bishop's Opening", "e4 e5 Bc4"),
+    ("C24", "Bishop's Opening: Berlin Defence", "e4 e5 Bc4 Nf6"),
+    ("C25", "Vienna Game", "e4 e5 Nc3"),
+    ("C25", "Vienna Game: Max Lange Defence", "e4 e5 Nc3 Nc6"),
+    ("C25", "Vienna Game: Vienna Gambit", "e4 e5 Nc3 Nc6 f4"),
+    ("C26", "Vienna Game: Falkbeer Variation", "e4 e5 Nc3 Nf6"),
+    ("C26", "Vienna Game: Stanley Variation", "e4 e5 Nc3 Nf6 Bc4"),
+    ("C27", "Vienna Game: Frankenstein-Dracula Variation", "e4 e5 Nc3 Nf6 Bc4 Nxe4"),
+    ("C29", "Vienna Game: Vienna Gambit", "e4 e5 Nc3 Nf6 f4"),
+    ("C30", "King's Gambit", "e4 e5 f4"),
+    ("C30", "King's Gambit Declined: Classical Variation", "e4 e5 f4 Bc5"),
+    ("C31", "King's Gambit Declined: Falkbeer Countergambit", "e4 e5 f4 d5"),
+    ("C33", "King's Gambit Accepted", "e4 e5 f4 exf4"),
+    ("C33", "King's Gambit Accepted: Bishop's Gambit", "e4 e5 f4 exf4 Bc4"),
+    ("C34", "King's Gambit Accepted: King's Knight's Gambit", "e4 e5 f4 exf4 Nf3"),
+    ("C34", "King's Gambit Accepted: Fischer Defence", "e4 e5 f4 exf4 Nf3 d6"),
+    ("C35", "King's Gambit Accepted: Cunningham Defence", "e4 e5 f4 exf4 Nf3 Be7"),
+    ("C36", "King's Gambit Accepted: Modern Defence", "e4 e5 f4 exf4 Nf3 d5"),
+    ("C40", "King's Knight Opening", "e4 e5 Nf3"),
+    ("C40", "Latvian Gambit", "e4 e5 Nf3 f5"),
+    ("C40", "Elephant Gambit", "e4 e5 Nf3 d5"),
+    ("C41", "Philidor Defence", "e4 e5 Nf3 d6"),
+    ("C41", "Philidor Defence", "e4 e5 Nf3 d6 d4"),
+    ("C41", "Philidor Defence: Exchange Variation", "e4 e5 Nf3 d6 d4 exd4"),
+    ("C42", "Petrov's Defence", "e4 e5 Nf3 Nf6"),
+    ("C42", "Petrov's Defence: Three Knights Game", "e4 e5 Nf3 Nf6 Nc3"),
+    ("C42", "Petrov's Defence", "e4 e5 Nf3 Nf6 Nxe5 d6 Nf3 Nxe4"),
+    ("C42", "Petrov's Defence: Classical Attack", "e4 e5 Nf3 Nf6 Nxe5 d6 Nf3 Nxe4 d4"),
+    ("C43", "Petrov's Defence: Modern Attack", "e4 e5 Nf3 Nf6 d4"),
+    ("C44", "King's Knight Opening: Normal Variation", "e4 e5 Nf3 Nc6"),
+    ("C44", "Scotch Game", "e4 e5 Nf3 Nc6 d4"),
+    ("C44", "Ponziani Opening", "e4 e5 Nf3 Nc6 c3"),
+    ("C44", "Scotch Gambit", "e4 e5 Nf3 Nc6 d4 exd4 Bc4"),
+    ("C45", "Scotch Game", "e4 e5 Nf3 Nc6 d4 exd4 Nxd4"),
+    ("C45", "Scotch Game: Classical Variation", "e4 e5 Nf3 Nc6 d4 exd4 Nxd4 Bc5"),
+    ("C45", "Scotch Game: Schmidt Variation", "e4 e5 Nf3 Nc6 d4 exd4 Nxd4 Nf6"),
+    ("C45", "Scotch Game: Mieses Variation", "e4 e5 Nf3 Nc6 d4 exd4 Nxd4 Nf6 Nxc6 bxc6 e5"),
+    ("C45", "Scotch Game: Steinitz Variation", "e4 e5 Nf3 Nc6 d4 exd4 Nxd4 Qh4"),
+    ("C46", "Three Knights Opening", "e4 e5 Nf3 Nc6 Nc3"),
+    ("C47", "Four Knights Game", "e4 e5 Nf3 Nc6 Nc3 Nf6"),
+    ("C47", "Four Knights Game: Scotch Variation", "e4 e5 Nf3 Nc6 Nc3 Nf6 d4"),
+    ("C47", "Four Knights Game: Glek System", "e4 e5 Nf3 Nc6 Nc3 Nf6 g3"),
+    ("C47", "Four Knights Game: Italian Variation", "e4 e5 Nf3 Nc6 Nc3 Nf6 Bc4"),
+    ("C48", "Four Knights Game: Spanish Variation", "e4 e5 Nf3 Nc6 Nc3 Nf6 Bb5"),
+    ("C48", "Four Knights Game: Spanish Variation, Rubinstein Variation", "e4 e5 Nf3 Nc6 Nc3 Nf6 Bb5 Nd4"),
+    ("C49", "Four Knights Game: Spanish Variation, Symmetrical Variation", "e4 e5 Nf3 Nc6 Nc3 Nf6 Bb5 Bb4"),
+    ("C50", "Italian Game", "e4 e5 Nf3 Nc6 Bc4"),
+    ("C50", "Giuoco Piano", "e4 e5 Nf3 Nc6 Bc4 Bc5"),
+    ("C50", "Italian Game: Hungarian Defence", "e4 e5 Nf3 Nc6 Bc4 Be7"),
+    ("C50", "Italian Game: Giuoco Pianissimo", "e4 e5 Nf3 Nc6 Bc4 Bc5 d3"),
+    ("C51", "Evans Gambit", "e4 e5 Nf3 Nc6 Bc4 Bc5 b4"),
+    ("C51", "Evans Gambit Accepted", "e4 e5 Nf3 Nc6 Bc4 Bc5 b4 Bxb4"),
+    ("C51", "Evans Gambit Declined", "e4 e5 Nf3 Nc6 Bc4 Bc5 b4 Bb6"),
+    ("C52", "Evans Gambit", "e4 e5 Nf3 Nc6 Bc4 Bc5 b4 Bxb4 c3 Ba5"),
+    ("C53", "Italian Game: Classical Variation", "e4 e5 Nf3 Nc6 Bc4 Bc5 c3"),
+    ("C54", "Italian Game: Classical Variation", "e4 e5 Nf3 Nc6 Bc4 Bc5 c3 Nf6 d4"),
+    ("C54", "Italian Game: Classical Variation, Giuoco Pianissimo", "e4 e5 Nf3 Nc6 Bc4 Bc5 c3 Nf6 d3"),
+    ("C55", "Two Knights Defence", "e4 e5 Nf3 Nc6 Bc4 Nf6"),
+    ("C55", "Italian Game: Two Knights Defence, Modern Bishop's Opening", "e4 e5 Nf3 Nc6 Bc4 Nf6 d3"),
+    ("C57", "Two Knights Defence: Knight Attack", "e4 e5 Nf3 Nc6 Bc4 Nf6 Ng5"),
+    ("C57", "Two Knights Defence: Traxler Counterattack", "e4 e5 Nf3 Nc6 Bc4 Nf6 Ng5 Bc5"),
+    ("C57", "Two Knights Defence: Fried Liver Attack", "e4 e5 Nf3 Nc6 Bc4 Nf6 Ng5 d5 exd5 Nxd5 Nxf7"),
+    ("C58", "Two Knights Defence: Polerio Defence", "e4 e5 Nf3 Nc6 Bc4 Nf6 Ng5 d5 exd5 Na5"),
+    ("C59", "Two Knights Defence: Polerio Defence", "e4 e5 Nf3 Nc6 Bc4 Nf6 Ng5 d5 exd5 Na5 Bb5+ c6 dxc6 bxc6 Be2 h6"),
+    ("C60", "Ruy Lopez", "e4 e5 Nf3 Nc6 Bb5"),
+    ("C60", "Ruy Lopez: Cozio Defence", "e4 e5 Nf3 Nc6 Bb5 Nge7"),
+    ("C60", "Ruy Lopez: Fianchetto Defence", "e4 e5 Nf3 Nc6 Bb5 g6"),
+    ("C61", "Ruy Lopez: Bird Variation", "e4 e5 Nf3 Nc6 Bb5 Nd4"),
+    ("C62", "Ruy Lopez: Steinitz Defence", "e4 e5 Nf3 Nc6 Bb5 d6"),
+    ("C63", "Ruy Lopez: Schliemann Defence", "e4 e5 Nf3 Nc6 Bb5 f5"),
+    ("C64", "Ruy Lopez: Classical Variation", "e4 e5 Nf3 Nc6 Bb5 Bc5"),
+    ("C65", "Ruy Lopez: Berlin Defence", "e4 e5 Nf3 Nc6 Bb5 Nf6"),
+    ("C65", "Ruy Lopez: Berlin Defence", "e4 e5 Nf3 Nc6 Bb5 Nf6 d3"),
+    ("C67", "Ruy Lopez: Berlin Defence", "e4 e5 Nf3 Nc6 Bb5 Nf6 O-O Nxe4"),
+    ("C67", "Ruy Lopez: Berlin Defence, Berlin Wall", "e4 e5 Nf3 Nc6 Bb5 Nf6 O-O Nxe4 d4 Nd6 Bxc6 dxc6 dxe5 Nf5 Qxd8+ Kxd8"),
+    ("C68", "Ruy Lopez: Morphy Defence", "e4 e5 Nf3 Nc6 Bb5 a6"),
+    ("C68", "Ruy Lopez: Exchange Variation", "e4 e5 Nf3 Nc6 Bb5 a6 Bxc6"),
+    ("C69", "Ruy Lopez: Exchange Variation", "e4 e5 Nf3 Nc6 Bb5 a6 Bxc6 dxc6 O-O"),
+    ("C70", "Ruy Lopez: Morphy Defence", "e4 e5 Nf3 Nc6 Bb5 a6 Ba4"),
+    ("C71", "Ruy Lopez: Modern Steinitz Defence", "e4 e5 Nf3 Nc6 Bb5 a6 Ba4 d6"),
+    ("C77", "Ruy Lopez: Morphy Defence", "e4 e5 Nf3 Nc6 Bb5 a6 Ba4 Nf6"),
+    ("C78", "Ruy Lopez: Morphy Defence", "e4 e5 Nf3 Nc6 Bb5 a6 Ba4 Nf6 O-O"),
+    ("C78", "Ruy Lopez: Møller Defence", "e4 e5 Nf3 Nc6 Bb5 a6 Ba4 Nf6 O-O Bc5"),
+    ("C78", "Ruy Lopez: Archangel Variation", "e4 e5 Nf3 Nc6 Bb5 a6 Ba4 Nf6 O-O b5 Bb3 Bb7"),
+    ("C80", "Ruy Lopez: Open", "e4 e5 Nf3 Nc6 Bb5 a6 Ba4 Nf6 O-O Nxe4"),
+    ("C84", "Ruy Lopez: Closed", "e4 e5 Nf3 Nc6 Bb5 a6 Ba4 Nf6 O-O Be7"),
+    ("C85", "Ruy Lopez: Exchange Variation Doubly Deferred", "e4 e5 Nf3 Nc6 Bb5 a6 Ba4 Nf6 O-O Be7 Bxc6"),
+    ("C86", "Ruy Lopez: Worrall Attack", "e4 e5 Nf3 Nc6 Bb5 a6 Ba4 Nf6 O-O Be7 Qe2"),
+    ("C87", "Ruy Lopez: Closed", "e4 e5 Nf3 Nc6 Bb5 a6 Ba4 Nf6 O-O Be7 Re1"),
+    ("C88", "Ruy Lopez: Closed", "e4 e5 Nf3 Nc6 Bb5 a6 Ba4 Nf6 O-O Be7 Re1 b5 Bb3"),
+    ("C88", "Ruy Lopez: Closed", "e4 e5 Nf3 Nc6 Bb5 a6 Ba4 Nf6 O-O Be7 Re1 b5 Bb3 O-O"),
+    ("C89", "Ruy Lopez: Marshall Attack", "e4 e5 Nf3 Nc6 Bb5 a6 Ba4 Nf6 O-O Be7 Re1 b5 Bb3 O-O c3 d5"),
+    ("C90", "Ruy Lopez: Closed", "e4 e5 Nf3 Nc6 Bb5 a6 Ba4 Nf6 O-O Be7 Re1 b5 Bb3 d6 c3 O-O"),
+    ("C92", "Ruy Lopez: Closed", "e4 e5 Nf3 Nc6 Bb5 a6 Ba4 Nf6 O-O Be7 Re1 b5 Bb3 d6 c3 O-O h3"),
+    ("C92", "Ruy Lopez: Zaitsev Variation", "e4 e5 Nf3 Nc6 Bb5 a6 Ba4 Nf6 O-O Be7 Re1 b5 Bb3 d6 c3 O-O h3 Bb7"),
+    ("C95", "Ruy Lopez: Breyer Variation", "e4 e5 Nf3 Nc6 Bb5 a6 Ba4 Nf6 O-O Be7 Re1 b5 Bb3 d6 c3 O-O h3 Nb8"),
+    ("C96", "Ruy Lopez: Closed", "e4 e5 Nf3 Nc6 Bb5 a6 Ba4 Nf6 O-O Be7 Re1 b5 Bb3 d6 c3 O-O h3 Na5 Bc2"),
+    ("C97", "Ruy Lopez: Chigorin Variation", "e4 e5 Nf3 Nc6 Bb5 a6 Ba4 Nf6 O-O Be7 Re1 b5 Bb3 d6 c3 O-O h3 Na5 Bc2 c5 d4 Qc7"),
+    // D: closed games
+    ("D00", "Queen's Pawn Game", "d4 d5"),
+    ("D00", "Queen's Pawn Game: Accelerated London System", "d4 d5 Bf4"),
+    ("D00", "Queen's Pawn Game: Levitsky Attack", "d4 d5 Bg5"),
+    ("D00", "Blackmar-Diemer Gambit", "d4 d5 e4"),
+    ("D00", "Queen's Pawn Game: Chigorin Variation", "d4 d5 Nc3"),
+    ("D00", "Queen's Pawn Game: Stonewall Attack", "d4 d5 e3 Nf6 Bd3 c5 c3 Nc6 f4"),
+    ("D01", "Richter-Veresov Attack", "d4 d5 Nc3 Nf6 Bg5"),
+    ("D02", "Queen's Pawn Game", "d4 d5 Nf3"),
+    ("D02", "London System", "d4 d5 Nf3 Nf6 Bf4"),
+    ("D02", "Queen's Pawn Game", "d4 d5 Nf3 Nf6"),
+    ("D03", "Queen's Pawn Game: Torre Attack", "d4 d5 Nf3 Nf6 Bg5"),
+    ("D04", "Queen's Pawn Game: Colle System", "d4 d5 Nf3 Nf6 e3"),
+    ("D05", "Colle System", "d4 d5 Nf3 Nf6 e3 e6 Bd3"),
+    ("D06", "Queen's Gambit", "d4 d5 c4"),
+    ("D06", "Queen's Gambit Declined: Marshall Defence", "d4 d5 c4 Nf6"),
+    ("D06", "Queen's Gambit Declined: Baltic Defence", "d4 d5 c4 Bf5"),
+    ("D06", "Queen's Gambit Declined: Austrian Defence", "d4 d5 c4 c5"),
+    ("D07", "Queen's Gambit Declined: Chigorin Defence", "d4 d5 c4 Nc6"),
+    ("D08", "Queen's Gambit Declined: Albin Countergambit", "d4 d5 c4 e5"),
+    ("D08", "Queen's Gambit Declined: Albin Countergambit", "d4 d5 c4 e5 dxe5 d4"),
+    ("D10", "Slav Defence", "d4 d5 c4 c6"),
+    ("D10", "Slav Defence: Exchange Variation", "d4 d5 c4 c6 cxd5 cxd5"),
+    ("D11", "Slav Defence", "d4 d5 c4 c6 Nf3"),
+    ("D11", "Slav Defence: Modern Line", "d4 d5 c4 c6 Nf3 Nf6"),
+    ("D12", "Slav Defence", "d4 d5 c4 c6 Nf3 Nf6 e3 Bf5"),
+    ("D15", "Slav Defence", "d4 d5 c4 c6 Nf3 Nf6 Nc3"),
+    ("D15", "Slav Defence: Chameleon Variation", "d4 d5 c4 c6 Nf3 Nf6 Nc3 a6"),
+    ("D15", "Slav Defence: Two Knights Attack", "d4 d5 c4 c6 Nf3 Nf6 Nc3 dxc4"),
+    ("D16", "Slav Defence: Alapin Variation", "d4 d5 c4 c6 Nf3 Nf6 Nc3 dxc4 a4"),
+    ("D17", "Slav Defence: Czech Variation", "d4 d5 c4 c6 Nf3 Nf6 Nc3 dxc4 a4 Bf5"),
+    ("D20", "Queen's Gambit Accepted", "d4 d5 c4 dxc4"),
+    ("D20", "Queen's Gambit Accepted: Central Variation", "d4 d5 c4 dxc4 e4"),
+    ("D20", "Queen's Gambit Accepted: Old Variation", "d4 d5 c4 dxc4 e3"),
+    ("D21", "Queen's Gambit Accepted", "d4 d5 c4 dxc4 Nf3"),
+    ("D23", "Queen's Gambit Accepted", "d4 d5 c4 dxc4 Nf3 Nf6"),
+    ("D24", "Queen's Gambit Accepted", "d4 d5 c4 dxc4 Nf3 Nf6 Nc3"),
+    ("D25", "Queen's Gambit Accepted", "d4 d5 c4 dxc4 Nf3 Nf6 e3"),
+    ("D26", "Queen's Gambit Accepted", "d4 d5 c4 dxc4 Nf3 Nf6 e3 e6"),
+    ("D27", "Queen's Gambit Accepted: Classical Variation", "d4 d5 c4 dxc4 Nf3 Nf6 e3 e6 Bxc4 c5 O-O a6"),
+    ("D30", "Queen's Gambit Declined", "d4 d5 c4 e6"),
+    ("D30", "Queen's Gambit Declined", "d4 d5 c4 e6 Nf3"),
+    ("D31", "Queen's Gambit Declined", "d4 d5 c4 e6 Nc3"),
+    ("D31", "Queen's Gambit Declined: Alatortsev Variation", "d4 d5 c4 e6 Nc3 Be7"),
+    ("D31", "Semi-Slav Defence", "d4 d5 c4 e6 Nc3 c6"),
+    ("D31", "Semi-Slav Defence: Marshall Gambit", "d4 d5 c4 e6 Nc3 c6 e4"),
+    ("D32", "Tarrasch Defence", "d4 d5 c4 e6 Nc3 c5"),
+    ("D33", "Tarrasch Defence", "d4 d5 c4 e6 Nc3 c5 cxd5 exd5 Nf3 Nc6 g3"),
+    ("D35", "Queen's Gambit Declined: Exchange Variation", "d4 d5 c4 e6 Nc3 Nf6 cxd5"),
+    ("D35", "Queen's Gambit Declined", "d4 d5 c4 e6 Nc3 Nf6"),
+    ("D36", "Queen's Gambit Declined: Exchange Variation", "d4 d5 c4 e6 Nc3 Nf6 cxd5 exd5 Bg5 c6 Qc2"),
+    ("D37", "Queen's Gambit Declined", "d4 d5 c4 e6 Nc3 Nf6 Nf3"),
+    ("D37", "Queen's Gambit Declined: Harrwitz Attack", "d4 d5 c4 e6 Nc3 Nf6 Nf3 Be7 Bf4"),
+    ("D38", "Queen's Gambit Declined: Ragozin Defence", "d4 d5 c4 e6 Nc3 Nf6 Nf3 Bb4"),
+    ("D40", "Queen's Gambit Declined: Semi-Tarrasch Defence", "d4 d5 c4 e6 Nc3 Nf6 Nf3 c5"),
+    ("D41", "Queen's Gambit Declined: Semi-Tarrasch Defence", "d4 d5 c4 e6 Nc3 Nf6 Nf3 c5 cxd5 Nxd5"),
+    ("D43", "Semi-Slav Defence", "d4 d5 c4 e6 Nc3 Nf6 Nf3 c6"),
+    ("D43", "Semi-Slav Defence: Moscow Variation", "d4 d5 c4 e6 Nc3 Nf6 Nf3 c6 Bg5 h6"),
+    ("D44", "Semi-Slav Defence: Botvinnik Variation", "d4 d5 c4 e6 Nc3 Nf6 Nf3 c6 Bg5 dxc4"),
+    ("D45", "Semi-Slav Defence", "d4 d5 c4 e6 Nc3 Nf6 Nf3 c6 e3"),
+    ("D45", "Semi-Slav Defence: Stoltz Variation", "d4 d5 c4 e6 Nc3 Nf6 Nf3 c6 e3 Nbd7 Qc2"),
+    ("D46", "Semi-Slav Defence: Main Line", "d4 d5 c4 e6 Nc3 Nf6 Nf3 c6 e3 Nbd7 Bd3"),
+    ("D47", "Semi-Slav Defence: Meran Variation", "d4 d5 c4 e6 Nc3 Nf6 Nf3 c6 e3 Nbd7 Bd3 dxc4 Bxc4 b5"),
+    ("D50", "Queen's Gambit Declined", "d4 d5 c4 e6 Nc3 Nf6 Bg5"),
+    ("D51", "Queen's Gambit Declined", "d4 d5 c4 e6 Nc3 Nf6 Bg5 Nbd7"),
+    ("D52", "Queen's Gambit Declined: Cambridge Springs Defence", "d4 d5 c4 e6 Nc3 Nf6 Bg5 Nbd7 e3 c6 Nf3 Qa5"),
+    ("D53", "Queen's Gambit Declined", "d4 d5 c4 e6 Nc3 Nf6 Bg5 Be7"),
+    ("D55", "Queen's Gambit Declined", "d4 d5 c4 e6 Nc3 Nf6 Bg5 Be7 e3 O-O Nf3"),
+    ("D56", "Queen's Gambit Declined: Lasker Defence", "d4 d5 c4 e6 Nc3 Nf6 Bg5 Be7 e3 O-O Nf3 h6 Bh4 Ne4"),
+    ("D58", "Queen's Gambit Declined: Tartakower Defence", "d4 d5 c4 e6 Nc3 Nf6 Bg5 Be7 e3 O-O Nf3 h6 Bh4 b6"),
+    ("D60", "Queen's Gambit Declined: Orthodox Defence", "d4 d5 c4 e6 Nc3 Nf6 Bg5 Be7 e3 O-O Nf3 Nbd7"),
+    ("D70", "Neo-Grünfeld Defence", "d4 Nf6 c4 g6 f3 d5"),
+    ("D71", "Neo-Grünfeld Defence", "d4 Nf6 c4 g6 g3 d5"),
+    ("D80", "Grünfeld Defence", "d4 Nf6 c4 g6 Nc3 d5"),
+    ("D80", "Grünfeld Defence: Stockholm Variation", "d4 Nf6 c4 g6 Nc3 d5 Bg5"),
+    ("D81", "Grünfeld Defence: Russian Variation", "d4 Nf6 c4 g6 Nc3 d5 Qb3"),
+    ("D82", "Grünfeld Defence: Brinckmann Attack", "d4 Nf6 c4 g6 Nc3 d5 Bf4"),
+    ("D85", "Grünfeld Defence: Exchange Variation", "d4 Nf6 c4 g6 Nc3 d5 cxd5 Nxd5"),
+    ("D85", "Grünfeld Defence: Exchange Variation", "d4 Nf6 c4 g6 Nc3 d5 cxd5 Nxd5 e4 Nxc3 bxc3 Bg7"),
+    ("D85", "Grünfeld Defence: Modern Exchange Variation", "d4 Nf6 c4 g6 Nc3 d5 cxd5 Nxd5 e4 Nxc3 bxc3 Bg7 Nf3"),
+    ("D86", "Grünfeld Defence: Exchange Variation, Classical Variation", "d4 Nf6 c4 g6 Nc3 d5 cxd5 Nxd5 e4 Nxc3 bxc3 Bg7 Bc4"),
+    ("D90", "Grünfeld Defence: Three Knights Variation", "d4 Nf6 c4 g6 Nc3 d5 Nf3"),
+    ("D94", "Grünfeld Defence: Three Knights Variation", "d4 Nf6 c4 g6 Nc3 d5 Nf3 Bg7 e3"),
+    ("D96", "Grünfeld Defence: Russian Variation", "d4 Nf6 c4 g6 Nc3 d5 Nf3 Bg7 Qb3"),
+    // E: indian defences
+    ("E00", "Indian Defence", "d4 Nf6 c4 e6"),
+    ("E01", "Catalan Opening", "d4 Nf6 c4 e6 g3"),
+    ("E04", "Catalan Opening: Open Defence", "d4 Nf6 c4 e6 g3 d5 Bg2 dxc4"),
+    ("E06", "Catalan Opening: Closed", "d4 Nf6 c4 e6 g3 d5 Bg2 Be7"),
+    ("E10", "Indian Defence", "d4 Nf6 c4 e6 Nf3"),
+    ("E10", "Blumenfeld Countergambit", "d4 Nf6 c4 e6 Nf3 c5 d5 b5"),
+    ("E11", "Bogo-Indian Defence", "d4 Nf6 c4 e6 Nf3 Bb4+"),
+    ("E11", "Bogo-Indian Defence: Grünfeld Variation", "d4 Nf6 c4 e6 Nf3 Bb4+ Nbd2"),
+    ("E11", "Bogo-Indian Defence: Exchange Variation", "d4 Nf6 c4 e6 Nf3 Bb4+ Bd2 Bxd2+"),
+    ("E12", "Queen's Indian Defence", "d4 Nf6 c4 e6 Nf3 b6"),
+    ("E12", "Queen's Indian Defence: Petrosian Variation", "d4 Nf6 c4 e6 Nf3 b6 a3"),
+    ("E12", "Queen's Indian Defence: Miles Variation", "d4 Nf6 c4 e6 Nf3 b6 Bf4"),
+    ("E14", "Queen's Indian Defence: Spassky System", "d4 Nf6 c4 e6 Nf3 b6 e3"),
+    ("E15", "Queen's Indian Defence: Fianchetto Variation", "d4 Nf6 c4 e6 Nf3 b6 g3"),
+    ("E15", "Queen's Indian Defence: Nimzowitsch Variation", "d4 Nf6 c4 e6 Nf3 b6 g3 Ba6"),
+    ("E16", "Queen's Indian Defence: Capablanca Variation", "d4 Nf6 c4 e6 Nf3 b6 g3 Bb7 Bg2 Bb4+"),
+    ("E17", "Queen's Indian Defence: Classical Variation", "d4 Nf6 c4 e6 Nf3 b6 g3 Bb7 Bg2 Be7"),
+    ("E20", "Nimzo-Indian Defence", "d4 Nf6 c4 e6 Nc3 Bb4"),
+    ("E20", "Nimzo-Indian Defence: Kmoch Variation", "d4 Nf6 c4 e6 Nc3 Bb4 f3"),
+    ("E20", "Nimzo-Indian Defence: Romanishin Variation", "d4 Nf6 c4 e6 Nc3 Bb4 g3"),
+    ("E21", "Nimzo-Indian Defence: Three Knights Variation", "d4 Nf6 c4 e6 Nc3 Bb4 Nf3"),
+    ("E22", "Nimzo-Indian Defence: Spielmann Variation", "d4 Nf6 c4 e6 Nc3 Bb4 Qb3"),
+    ("E24", "Nimzo-Indian Defence: Sämisch Variation", "d4 Nf6 c4 e6 Nc3 Bb4 a3 Bxc3+ bxc3"),
+    ("E30", "Nimzo-Indian Defence: Leningrad Variation", "d4 Nf6 c4 e6 Nc3 Bb4 Bg5"),
+    ("E32", "Nimzo-Indian Defence: Classical Variation", "d4 Nf6 c4 e6 Nc3 Bb4 Qc2"),
+    ("E32", "Nimzo-Indian Defence: Classical Variation", "d4 Nf6 c4 e6 Nc3 Bb4 Qc2 O-O"),
+    ("E34", "Nimzo-Indian Defence: Classical Variation, Noa Variation", "d4 Nf6 c4 e6 Nc3 Bb4 Qc2 d5"),
+    ("E38", "Nimzo-Indian Defence: Classical Variation", "d4 Nf6 c4 e6 Nc3 Bb4 Qc2 c5"),
+    ("E40", "Nimzo-Indian Defence: Rubinstein Variation", "d4 Nf6 c4 e6 Nc3 Bb4 e3"),
+    ("E41", "Nimzo-Indian Defence", "d4 Nf6 c4 e6 Nc3 Bb4 e3 c5"),
+    ("E41", "Nimzo-Indian Defence: Hübner Variation", "d4 Nf6 c4 e6 Nc3 Bb4 e3 c5 Bd3 Nc6 Nf3 Bxc3+ bxc3 d6"),
+    ("E43", "Nimzo-Indian Defence: St. Petersburg Variation", "d4 Nf6 c4 e6 Nc3 Bb4 e3 b6"),
+    ("E46", "Nimzo-Indian Defence", "d4 Nf6 c4 e6 Nc3 Bb4 e3 O-O"),
+    ("E46", "Nimzo-Indian Defence: Reshevsky Variation", "d4 Nf6 c4 e6 Nc3 Bb4 e3 O-O Nge2"),
+    ("E48", "Nimzo-Indian Defence", "d4 Nf6 c4 e6 Nc3 Bb4 e3 O-O Bd3 d5"),
+    ("E60", "King's Indian Defence", "d4 Nf6 c4 g6"),
+    ("E60", "King's Indian Defence: Fianchetto Variation", "d4 Nf6 c4 g6 g3"),
+    ("E61", "King's Indian Defence", "d4 Nf6 c4 g6 Nc3 Bg7"),
+    ("E61", "King's Indian Defence: Smyslov Variation", "d4 Nf6 c4 g6 Nc3 Bg7 Nf3 d6 Bg5"),
+    ("E62", "King's Indian Defence: Fianchetto Variation", "d4 Nf6 c4 g6 Nc3 Bg7 Nf3 d6 g3"),
+    ("E70", "King's Indian Defence", "d4 Nf6 c4 g6 Nc3 Bg7 e4 d6"),
+    ("E71", "King's Indian Defence: Makogonov Variation", "d4 Nf6 c4 g6 Nc3 Bg7 e4 d6 h3"),
+    ("E73", "King's Indian Defence: Averbakh Variation", "d4 Nf6 c4 g6 Nc3 Bg7 e4 d6 Be2 O-O Bg5"),
+    ("E76", "King's Indian Defence: Four Pawns Attack", "d4 Nf6 c4 g6 Nc3 Bg7 e4 d6 f4"),
+    ("E80", "King's Indian Defence: Sämisch Variation", "d4 Nf6 c4 g6 Nc3 Bg7 e4 d6 f3"),
+    ("E81", "King's Indian Defence: Sämisch Variation", "d4 Nf6 c4 g6 Nc3 Bg7 e4 d6 f3 O-O"),
+    ("E90", "King's Indian Defence", "d4 Nf6 c4 g6 Nc3 Bg7 e4 d6 Nf3"),
+    ("E91", "King's Indian Defence", "d4 Nf6 c4 g6 Nc3 Bg7 e4 d6 Nf3 O-O Be2"),
+    ("E92", "King's Indian Defence: Classical Variation", "d4 Nf6 c4 g6 Nc3 Bg7 e4 d6 Nf3 O-O Be2 e5"),
+    ("E92", "King's Indian Defence: Petrosian Variation", "d4 Nf6 c4 g6 Nc3 Bg7 e4 d6 Nf3 O-O Be2 e5 d5"),
+    ("E92", "King's Indian Defence: Gligoric-Taimanov System", "d4 Nf6 c4 g6 Nc3 Bg7 e4 d6 Nf3 O-O Be2 e5 Be3"),
+    ("E94", "King's Indian Defence: Orthodox Variation", "d4 Nf6 c4 g6 Nc3 Bg7 e4 d6 Nf3 O-O Be2 e5 O-O"),
+    ("E94", "King's Indian Defence: Orthodox Variation", "d4 Nf6 c4 g6 Nc3 Bg7 e4 d6 Nf3 O-O Be2 e5 O-O Nbd7"),
+    ("E97", "King's Indian Defence: Mar del Plata Variation", "d4 Nf6 c4 g6 Nc3 Bg7 e4 d6 Nf3 O-O Be2 e5 O-O Nc6"),
+    ("E98", "King's Indian Defence: Mar del Plata Variation", "d4 Nf6 c4 g6 Nc3 Bg7 e4 d6 Nf3 O-O Be2 e5 O-O Nc6 d5 Ne7 Ne1"),
+    ("E99", "King's Indian Defence: Mar del Plata Variation", "d4 Nf6 c4 g6 Nc3 Bg7 e4 d6 Nf3 O-O Be2 e5 O-O Nc6 d5 Ne7 Ne1 Nd7 f3 f5"),
+];
+
+/// Map from position hash key to (ECO code, name), built on first use.
+static ECO_TABLE: LazyLock<HashMap<u64, (&'static str, &'static str)>> = LazyLock::new(|| {
+    let mut table = HashMap::with_capacity(ECO_LINES.len());
+    for &(code, name, line) in ECO_LINES {
+        let mut board = Board::startpos();
+        for san in line.split_whitespace() {
+            let m = board
+                .parse_san(san)
+                .unwrap_or_else(|e| panic!("invalid move in ECO line {code} \"{line}\": {e}"));
+            board.make_move_simple(m);
+        }
+        table.insert(board.state.keys.zobrist, (code, name));
+    }
+    table
+});
+
+impl Board {
+    /// The ECO code and name of the current position, if it is a known opening.
+    pub fn eco(&self) -> Option<(&'static str, &'static str)> {
+        if self.rules() != Rules::Classical {
+            return None;
+        }
+        ECO_TABLE.get(&self.state.keys.zobrist).copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn all_lines_are_legal() {
+        assert!(!ECO_TABLE.is_empty());
+        assert_eq!(Board::startpos().eco(), None);
+    }
+
+    #[test]
+    fn ruy_lopez() {
+        let mut board = Board::startpos();
+        for san in ["e4", "e5", "Nf3", "Nc6", "Bb5"] {
+            board.make_move_simple(board.parse_san(san).unwrap());
+        }
+        assert_eq!(board.eco(), Some(("C60", "Ruy Lopez")));
+    }
+
+    #[test]
+    fn transposition() {
+        // 1.Nf3 d5 2.d4 reaches the same position as 1.d4 d5 2.Nf3.
+        let mut board = Board::startpos();
+        for san in ["Nf3", "d5", "d4"] {
+            board.make_move_simple(board.parse_san(san).unwrap());
+        }
+        assert_eq!(board.eco(), Some(("D02", "Queen's Pawn Game")));
+    }
+}
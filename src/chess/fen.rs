@@ -1,3 +1,5 @@
+//! Parsing positions in Forsyth-Edwards Notation.
+
 use std::{num::NonZeroUsize, str::SplitWhitespace};
 
 use arrayvec::ArrayVec;
@@ -15,11 +17,17 @@ use crate::{
 /// A parsed FEN representation.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Fen {
+    /// The pieces on the board.
     pub board: PieceLayout,
+    /// The side to move.
     pub turn: Colour,
+    /// Which rooks each side can castle with.
     pub castling: CastlingRights,
+    /// The en passant square.
     pub ep: Option<Square>,
+    /// The number of half moves since the last capture or pawn move.
     pub halfmove: u8,
+    /// The number of the current full move, starting at one.
     pub fullmove: NonZeroUsize,
 }
 
@@ -28,6 +36,8 @@ impl Fen {
 
     /// Parse a FEN string in strict mode.
     /// All 6 fields must be present, and no extra tokens are allowed.
+    /// Fields may be separated by any run of whitespace, and surrounding
+    /// whitespace (such as a CRLF line ending) is ignored.
     pub fn parse(fen: &str) -> Result<Self, FenParseError> {
         let mut tokens = fen.split_whitespace();
         let result = Self::parse_inner(&mut tokens, true)?;
@@ -231,18 +241,22 @@ impl Fen {
 
         for c in s.chars() {
             match c {
-                // Standard notation (assumes rooks on A/H files)
+                // Standard / X-FEN notation: the outermost rook on that side of the king
                 'K' => {
-                    rights.set_kingside(Colour::White, File::H);
+                    let file = Self::outermost_rook(board, Colour::White, white_king_sq, true);
+                    rights.set_kingside(Colour::White, file.unwrap_or(File::H));
                 }
                 'Q' => {
-                    rights.set_queenside(Colour::White, File::A);
+                    let file = Self::outermost_rook(board, Colour::White, white_king_sq, false);
+                    rights.set_queenside(Colour::White, file.unwrap_or(File::A));
                 }
                 'k' => {
-                    rights.set_kingside(Colour::Black, File::H);
+                    let file = Self::outermost_rook(board, Colour::Black, black_king_sq, true);
+                    rights.set_kingside(Colour::Black, file.unwrap_or(File::H));
                 }
                 'q' => {
-                    rights.set_queenside(Colour::Black, File::A);
+                    let file = Self::outermost_rook(board, Colour::Black, black_king_sq, false);
+                    rights.set_queenside(Colour::Black, file.unwrap_or(File::A));
                 }
                 // X-FEN / Shredder-FEN: uppercase file letter for white
                 'A'..='H' => {
@@ -309,6 +323,33 @@ impl Fen {
         Ok(rights)
     }
 
+    /// The file of the outermost rook of `colour` on the given side of its king,
+    /// if the king is on its back rank.
+    fn outermost_rook(
+        board: &PieceLayout,
+        colour: Colour,
+        king_sq: Square,
+        kingside: bool,
+    ) -> Option<File> {
+        let back_rank = [Rank::One, Rank::Eight][colour];
+        if king_sq.rank() != back_rank {
+            return None;
+        }
+        let rooks =
+            board.pieces[PieceType::Rook] & board.colours[colour] & SquareSet::RANKS[back_rank];
+        let outermost = if kingside {
+            rooks.last()
+        } else {
+            rooks.first()
+        }?;
+        let on_side = if kingside {
+            outermost.file() > king_sq.file()
+        } else {
+            outermost.file() < king_sq.file()
+        };
+        on_side.then(|| outermost.file())
+    }
+
     fn parse_ep(s: &str, turn: Colour) -> Result<Option<Square>, FenParseError> {
         if s == "-" {
             return Ok(None);
@@ -454,6 +495,15 @@ mod tests {
         assert!(matches!(result, Err(FenParseError::FullmoveNumberZero)));
     }
 
+    #[test]
+    fn tolerate_crlf_and_extra_spaces() {
+        let messy = "  rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR   w \tKQkq  -  0   1 \r\n";
+        assert_eq!(Fen::parse(messy), Fen::parse(STARTPOS));
+        assert_eq!(Fen::parse_relaxed(messy), Fen::parse(STARTPOS));
+        let crlf = format!("{STARTPOS}\r\n");
+        assert_eq!(Fen::parse(&crlf), Fen::parse(STARTPOS));
+    }
+
     #[test]
     fn reject_extra_tokens_strict() {
         let result = Fen::parse("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1 extra");
@@ -478,6 +528,19 @@ mod tests {
         assert!(fen.is_ok());
     }
 
+    #[test]
+    fn parse_xfen_outermost_rook() {
+        // K and Q name the outermost rooks, wherever they are.
+        let fen = Fen::parse("1r2k1r1/8/8/8/8/8/8/R3KR1R w KQkq - 0 1").unwrap();
+        assert_eq!(fen.castling.kingside(Colour::White), Some(File::H));
+        assert_eq!(fen.castling.queenside(Colour::White), Some(File::A));
+        assert_eq!(fen.castling.kingside(Colour::Black), Some(File::G));
+        assert_eq!(fen.castling.queenside(Colour::Black), Some(File::B));
+        // an inner rook has to be given by its file.
+        let fen = Fen::parse("4k3/8/8/8/8/8/8/R3KR1R w F - 0 1").unwrap();
+        assert_eq!(fen.castling.kingside(Colour::White), Some(File::F));
+    }
+
     #[test]
     fn relaxed_defaults_invalid_tokens() {
         // Invalid tokens for castling, ep, halfmove - should all default
@@ -0,0 +1,396 @@
+//! A game of chess from start to finish: the moves played, the clocks, and how it ended.
+
+use std::time::Duration;
+
+use anyhow::{Context, bail};
+
+use crate::{
+    chess::{
+        board::{Board, DrawType, GameOutcome, WinType},
+        chessmove::Move,
+        pgn::{self, Token},
+        piece::Colour,
+    },
+    tablebases::probe::WDL,
+};
+
+/// Tags that [`Game::to_pgn`] writes from the game itself, and so aren't kept as tags.
+const DERIVED_TAGS: [&str; 4] = ["Result", "FEN", "SetUp", "Variant"];
+
+/// A move that has been played in a [`Game`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlayedMove {
+    /// The move.
+    pub mv: Move,
+    /// The move in standard algebraic notation, as it was played.
+    pub san: String,
+    /// The time left on the mover's clock after the move, if it's known.
+    pub clock: Option<Duration>,
+}
+
+/// A game of chess: the position it started from, the moves played since, and its result.
+///
+/// The result is worked out from the position for checkmates and draws that need no claim,
+/// and can be set for games that end otherwise, by resignation, on time, by agreement, or by
+/// adjudication.
+#[derive(Debug, Clone)]
+pub struct Game {
+    start: Board,
+    board: Board,
+    moves: Vec<PlayedMove>,
+    tags: Vec<(String, String)>,
+    result: Option<GameOutcome>,
+}
+
+impl Game {
+    /// A game from the standard starting position.
+    pub fn new() -> Self {
+        Self::from_board(Board::startpos())
+    }
+
+    /// A game starting from `start`.
+    pub fn from_board(start: Board) -> Self {
+        Self {
+            board: start.clone(),
+            start,
+            moves: Vec::new(),
+            tags: Vec::new(),
+            result: None,
+        }
+    }
+
+    /// A game starting from the position given by `fen`.
+    pub fn from_fen(fen: &str) -> anyhow::Result<Self> {
+        Ok(Self::from_board(Board::from_fen(fen)?))
+    }
+
+    /// The position the game started from.
+    pub const fn start(&self) -> &Board {
+        &self.start
+    }
+
+    /// The current position.
+    pub const fn board(&self) -> &Board {
+        &self.board
+    }
+
+    /// The moves played so far.
+    pub fn moves(&self) -> &[PlayedMove] {
+        &self.moves
+    }
+
+    /// The PGN tags of the game, other than those written from the game itself.
+    pub fn tags(&self) -> &[(String, String)] {
+        &self.tags
+    }
+
+    /// The value of the tag `name`, if it's set.
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Set the tag `name` to `value`, replacing any earlier value. Tags are written in the
+    /// order they were first set.
+    pub fn set_tag(&mut self, name: &str, value: &str) {
+        if let Some((_, v)) = self.tags.iter_mut().find(|(n, _)| n == name) {
+            value.clone_into(v);
+        } else {
+            self.tags.push((name.to_string(), value.to_string()));
+        }
+    }
+
+    /// Play `mv`, which must be legal.
+    pub fn push(&mut self, mv: Move) -> anyhow::Result<()> {
+        if self.result.is_some() {
+            bail!("the game is over");
+        }
+        let Some(san) = self.board.san(mv) else {
+            bail!(
+                "illegal move {} in position {}",
+                mv.display(self.board.rules()),
+                self.board
+            );
+        };
+        self.moves.push(PlayedMove {
+            mv,
+            san: san.to_string(),
+            clock: None,
+        });
+        self.board.make_move_simple(mv);
+        Ok(())
+    }
+
+    /// Play a move given in UCI notation.
+    pub fn push_uci(&mut self, uci: &str) -> anyhow::Result<Move> {
+        let mv = self.board.parse_uci(uci)?;
+        self.push(mv)?;
+        Ok(mv)
+    }
+
+    /// Play a move given in standard algebraic notation.
+    pub fn push_san(&mut self, san: &str) -> anyhow::Result<Move> {
+        let mv = self.board.parse_san(san)?;
+        self.push(mv)?;
+        Ok(mv)
+    }
+
+    /// Take back the last move, and any result that was set after it.
+    pub fn pop(&mut self) -> Option<PlayedMove> {
+        let played = self.moves.pop()?;
+        self.board.unmake_move_base();
+        self.result = None;
+        Some(played)
+    }
+
+    /// Record the time left on the clock of the player who made the last move.
+    pub fn set_clock(&mut self, remaining: Duration) {
+        if let Some(last) = self.moves.last_mut() {
+            last.clock = Some(remaining);
+        }
+    }
+
+    /// End the game with a result that isn't decided by the position, such as a resignation,
+    /// a loss on time, an agreed draw, or an adjudication.
+    pub fn set_outcome(&mut self, outcome: GameOutcome) {
+        self.result = Some(outcome);
+    }
+
+    /// End the game with `colour` resigning.
+    pub fn resign(&mut self, colour: Colour) {
+        self.set_outcome(match colour {
+            Colour::White => GameOutcome::BlackWin(WinType::Resignation),
+            Colour::Black => GameOutcome::WhiteWin(WinType::Resignation),
+        });
+    }
+
+    /// The result of the game, if it's over: the result that was set, or else checkmate or
+    /// a draw that needs no claim in the current position.
+    pub fn outcome(&self) -> Option<GameOutcome> {
+        self.result.or_else(|| self.board.outcome())
+    }
+
+    /// The game in PGN. Each move's clock is written as a `[%clk]` comment, and a
+    /// `Termination` tag is added for games that didn't end on the board, unless one is set.
+    pub fn to_pgn(&self) -> String {
+        let outcome = self.outcome();
+        let mut tags = self
+            .tags
+            .iter()
+            .map(|(n, v)| (n.as_str(), v.as_str()))
+            .collect::<Vec<_>>();
+        if self.tag("Termination").is_none()
+            && let Some(termination) = outcome.and_then(termination)
+        {
+            tags.push(("Termination", termination));
+        }
+        let comments = self
+            .moves
+            .iter()
+            .map(|m| {
+                m.clock
+                    .map(|clock| format!("[%clk {}]", format_clock(clock)))
+            })
+            .collect::<Vec<_>>();
+        let wdl = outcome.map(|outcome| match outcome {
+            GameOutcome::WhiteWin(_) => WDL::Win,
+            GameOutcome::BlackWin(_) => WDL::Loss,
+            GameOutcome::Draw(_) => WDL::Draw,
+        });
+        let mut out = Vec::new();
+        pgn::write_game(
+            &mut out,
+            &tags,
+            &self.start,
+            self.moves
+                .iter()
+                .zip(&comments)
+                .map(|(m, comment)| (m.mv, comment.as_deref())),
+            wdl,
+        )
+        .expect("the moves of a game are legal, and writing to a vector can't fail");
+        String::from_utf8(out).expect("PGN is written as UTF-8")
+    }
+
+    /// Read the first game in `text`, a PGN file. Unlike the book builder's reader, which
+    /// skips what it can't use, any illegal or unreadable move is an error.
+    pub fn from_pgn(text: &str) -> anyhow::Result<Self> {
+        let raw = pgn::split_games(text)
+            .into_iter()
+            .next()
+            .context("no game found")?;
+        let start = raw.start_position()?;
+        let mut game = Self::from_board(start);
+        for (name, value) in &raw.tags {
+            if !DERIVED_TAGS.contains(&name.as_str()) {
+                game.set_tag(name, value);
+            }
+        }
+        for token in pgn::mainline(&raw.movetext) {
+            match token {
+                Token::Move(san) => {
+                    game.push_san(san)
+                        .with_context(|| format!("bad move {san:?} in position {}", game.board))?;
+                }
+                Token::Comment(comment) => {
+                    if let Some(clock) = parse_clock_comment(comment) {
+                        game.set_clock(clock);
+                    }
+                }
+                Token::Result(result) => {
+                    game.read_result(result);
+                    break;
+                }
+            }
+        }
+        Ok(game)
+    }
+
+    /// Set the result from a PGN result token, if the position doesn't already decide it.
+    /// Why the game ended is taken from the `Termination` tag.
+    fn read_result(&mut self, result: &str) {
+        let termination = self.tag("Termination").map(str::to_ascii_lowercase);
+        let win_type = match termination.as_deref() {
+            Some("time forfeit") => WinType::TimeForfeit,
+            Some("adjudication") => WinType::Adjudication,
+            _ => WinType::Resignation,
+        };
+        let draw_type = match termination.as_deref() {
+            Some("adjudication") => DrawType::Adjudication,
+            _ => DrawType::Agreement,
+        };
+        let outcome = match result {
+            "1-0" => GameOutcome::WhiteWin(win_type),
+            "0-1" => GameOutcome::BlackWin(win_type),
+            "1/2-1/2" => GameOutcome::Draw(draw_type),
+            _ => return,
+        };
+        let decided = self.board.outcome().is_some_and(|on_board| {
+            std::mem::discriminant(&on_board) == std::mem::discriminant(&outcome)
+        });
+        if !decided {
+            self.result = Some(outcome);
+        }
+    }
+}
+
+impl Default for Game {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The value of the `Termination` tag for a game that didn't end on the board.
+const fn termination(outcome: GameOutcome) -> Option<&'static str> {
+    match outcome {
+        GameOutcome::WhiteWin(WinType::TimeForfeit)
+        | GameOutcome::BlackWin(WinType::TimeForfeit) => Some("time forfeit"),
+        GameOutcome::WhiteWin(WinType::Adjudication)
+        | GameOutcome::BlackWin(WinType::Adjudication)
+        | GameOutcome::Draw(DrawType::Adjudication) => Some("adjudication"),
+        _ => None,
+    }
+}
+
+/// A clock reading as `h:mm:ss`, with milliseconds if there are any.
+fn format_clock(clock: Duration) -> String {
+    let secs = clock.as_secs();
+    let (h, m, s) = (secs / 3600, secs / 60 % 60, secs % 60);
+    match clock.subsec_millis() {
+        0 => format!("{h}:{m:02}:{s:02}"),
+        millis => format!("{h}:{m:02}:{s:02}.{millis:03}"),
+    }
+}
+
+/// The clock reading in a comment holding a `[%clk h:mm:ss]` command.
+fn parse_clock_comment(comment: &str) -> Option<Duration> {
+    let (_, rest) = comment.split_once("[%clk ")?;
+    let (clock, _) = rest.split_once(']')?;
+    let mut fields = clock.trim().rsplit(':');
+    let seconds = fields.next()?;
+    let (whole, fraction) = seconds.split_once('.').unwrap_or((seconds, ""));
+    let mut secs = whole.parse::<u64>().ok()?;
+    for (field, scale) in fields.zip([60, 3600]) {
+        secs += field.parse::<u64>().ok()? * scale;
+    }
+    let millis = if fraction.is_empty() {
+        0
+    } else {
+        let digits = fraction.get(..3).unwrap_or(fraction);
+        digits.parse::<u64>().ok()? * 10u64.pow(3 - u32::try_from(digits.len()).ok()?)
+    };
+    Some(Duration::from_secs(secs) + Duration::from_millis(millis))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plays_moves_and_detects_mate() {
+        let mut game = Game::new();
+        for san in ["f3", "e5", "g4"] {
+            game.push_san(san).unwrap();
+        }
+        assert_eq!(game.outcome(), None);
+        game.push_uci("d8h4").unwrap();
+        assert_eq!(game.outcome(), Some(GameOutcome::BlackWin(WinType::Mate)));
+        assert_eq!(game.moves().last().unwrap().san, "Qh4#");
+        assert!(game.push_uci("e1f2").is_err());
+
+        game.pop();
+        assert_eq!(game.outcome(), None);
+        assert_eq!(game.moves().len(), 3);
+    }
+
+    #[test]
+    fn resignation_ends_the_game() {
+        let mut game = Game::new();
+        game.push_san("e4").unwrap();
+        game.resign(Colour::Black);
+        assert_eq!(
+            game.outcome(),
+            Some(GameOutcome::WhiteWin(WinType::Resignation))
+        );
+        assert!(game.push_san("e5").is_err());
+    }
+
+    #[test]
+    fn pgn_round_trips() {
+        let mut game = Game::from_fen("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1").unwrap();
+        game.set_tag("Event", "test");
+        game.set_tag("White", "a \"quoted\" name");
+        for (san, millis) in [("e4", 60_000), ("Kd7", 59_500), ("e5", 3_723_004)] {
+            game.push_san(san).unwrap();
+            game.set_clock(Duration::from_millis(millis));
+        }
+        game.set_outcome(GameOutcome::BlackWin(WinType::TimeForfeit));
+
+        let text = game.to_pgn();
+        assert!(text.contains("[Termination \"time forfeit\"]"), "{text}");
+        assert!(
+            text.contains("1. e4 {[%clk 0:01:00]} Kd7 {[%clk 0:00:59.500]}"),
+            "{text}"
+        );
+        assert!(text.contains("{[%clk 1:02:03.004]} 0-1"), "{text}");
+
+        let read = Game::from_pgn(&text).unwrap();
+        assert_eq!(read.start().to_string(), game.start().to_string());
+        assert_eq!(read.moves(), game.moves());
+        assert_eq!(read.outcome(), game.outcome());
+        assert_eq!(read.tag("White"), Some("a \"quoted\" name"));
+        assert_eq!(read.to_pgn(), text);
+    }
+
+    #[test]
+    fn results_on_the_board_are_not_overridden() {
+        let game = Game::from_pgn("1. f3 e5 2. g4 Qh4# 0-1").unwrap();
+        assert_eq!(game.outcome(), Some(GameOutcome::BlackWin(WinType::Mate)));
+        let game = Game::from_pgn("[Event \"x\"]\n\n1. e4 (1. d4 d5) e5 $1 1/2-1/2").unwrap();
+        assert_eq!(game.moves().len(), 2);
+        assert_eq!(game.outcome(), Some(GameOutcome::Draw(DrawType::Agreement)));
+        assert!(Game::from_pgn("1. e4 e4 *").is_err());
+    }
+}
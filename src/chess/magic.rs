@@ -364,10 +364,15 @@ pub static ORTH_TABLE: [MagicEntry; 64] = {
 const DIAG_MASKS: [SquareSet; 64] = init_masks_with!(diag_attack_mask);
 const ORTH_MASKS: [SquareSet; 64] = init_masks_with!(orth_attack_mask);
 
+// The magic tables and the PEXT tables below are never both compiled in, so a build only
+// embeds the set it looks attacks up in.
+
 // SAFETY: All bitpatterns of u64 are valid, and SquareSet is repr(transparent) around u64.
+#[cfg(not(pext))]
 pub static DIAG_ATTACKS: [[SquareSet; 512]; 64] =
     unsafe { std::mem::transmute(*include_bytes!("../../embeds/diagonal_attacks.bin")) };
 // SAFETY: All bitpatterns of u64 are valid, and SquareSet is repr(transparent) around u64.
+#[cfg(not(pext))]
 #[allow(clippy::large_stack_arrays)]
 pub static ORTH_ATTACKS: [[SquareSet; 4096]; 64] =
     unsafe { std::mem::transmute(*include_bytes!("../../embeds/orthogonal_attacks.bin")) };
@@ -505,3 +510,126 @@ const ORTH_MAGICS: [u64; 64] = [
     0x0000_0088_2508_4204,
     0x2084_0021_1242_8402,
 ];
+
+/**************************************\
+|     PEXT-indexed attack tables       |
+\**************************************/
+
+// Slider attack tables indexed with the BMI2 `pext` instruction, which gathers the relevant
+// blockers straight into a dense index, with no multiplication by a magic number. Like the
+// magic tables, they're generated ahead of time by `init_sliders_attacks`. They're only
+// looked up with the `pext` feature on a target with BMI2 (see the build script), as checking
+// for it at run time costs more than `pext` saves. Tests always compile them in, so that the
+// tables are checked on every machine.
+
+// SAFETY: All bitpatterns of u64 are valid, and SquareSet is repr(transparent) around u64.
+#[cfg(any(pext, test))]
+pub static DIAG_ATTACKS_PEXT: [[SquareSet; 512]; 64] =
+    unsafe { std::mem::transmute(*include_bytes!("../../embeds/diagonal_attacks_pext.bin")) };
+// SAFETY: All bitpatterns of u64 are valid, and SquareSet is repr(transparent) around u64.
+#[cfg(any(pext, test))]
+#[allow(clippy::large_stack_arrays)]
+pub static ORTH_ATTACKS_PEXT: [[SquareSet; 4096]; 64] =
+    unsafe { std::mem::transmute(*include_bytes!("../../embeds/orthogonal_attacks_pext.bin")) };
+
+/// The masks of the squares whose occupancy matters to a bishop on each square.
+pub const fn diag_mask(sq: Square) -> SquareSet {
+    DIAG_MASKS[sq.index()]
+}
+
+/// The masks of the squares whose occupancy matters to a rook on each square.
+pub const fn orth_mask(sq: Square) -> SquareSet {
+    ORTH_MASKS[sq.index()]
+}
+
+/// The squares a bishop on `sq` attacks, with `blockers` in the way, looked up with `pext`.
+#[cfg(pext)]
+#[allow(clippy::cast_possible_truncation)]
+pub fn diag_attacks_pext(sq: Square, blockers: SquareSet) -> SquareSet {
+    // SAFETY: this is only compiled for CPUs with BMI2.
+    let index = unsafe { std::arch::x86_64::_pext_u64(blockers.inner(), DIAG_MASKS[sq].inner()) };
+    let index = index as usize;
+    // SAFETY: `pext` with a mask of n bits gives an index below 2^n, and no bishop
+    // mask has more than 9 bits, so this is always in bounds.
+    unsafe { *DIAG_ATTACKS_PEXT[sq].get_unchecked(index) }
+}
+
+/// The squares a rook on `sq` attacks, with `blockers` in the way, looked up with `pext`.
+#[cfg(pext)]
+#[allow(clippy::cast_possible_truncation)]
+pub fn orth_attacks_pext(sq: Square, blockers: SquareSet) -> SquareSet {
+    // SAFETY: this is only compiled for CPUs with BMI2.
+    let index = unsafe { std::arch::x86_64::_pext_u64(blockers.inner(), ORTH_MASKS[sq].inner()) };
+    let index = index as usize;
+    // SAFETY: `pext` with a mask of n bits gives an index below 2^n, and no rook
+    // mask has more than 12 bits, so this is always in bounds.
+    unsafe { *ORTH_ATTACKS_PEXT[sq].get_unchecked(index) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `pext` in software: the bits of `bits` under `mask`, packed together from the bottom.
+    fn pext(bits: SquareSet, mask: SquareSet) -> usize {
+        mask.iter()
+            .enumerate()
+            .filter(|&(_, sq)| bits.contains_square(sq))
+            .fold(0, |index, (i, _)| index | 1 << i)
+    }
+
+    #[test]
+    fn pext_tables_match_slow_attacks() {
+        for sq in Square::all() {
+            // visit every subset of each mask.
+            for (mask, table, slow) in [
+                (
+                    DIAG_MASKS[sq],
+                    &DIAG_ATTACKS_PEXT[sq][..],
+                    diag_attacks_slow as fn(Square, SquareSet) -> SquareSet,
+                ),
+                (
+                    ORTH_MASKS[sq],
+                    &ORTH_ATTACKS_PEXT[sq][..],
+                    orth_attacks_slow,
+                ),
+            ] {
+                let mut blockers = SquareSet::EMPTY;
+                loop {
+                    assert_eq!(table[pext(blockers, mask)], slow(sq, blockers));
+                    blockers = SquareSet::from_inner(
+                        blockers.inner().wrapping_sub(mask.inner()) & mask.inner(),
+                    );
+                    if blockers == SquareSet::EMPTY {
+                        break;
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn lookups_match_slow_attacks() {
+        use crate::chess::board::movegen::{diag_attacks, orth_attacks};
+
+        let mut rng = XorShiftState::with_seed(7);
+        for sq in Square::all() {
+            for _ in 0..256 {
+                let blockers = SquareSet::from_inner(rng.next() & rng.next());
+                #[cfg(pext)]
+                {
+                    assert_eq!(
+                        diag_attacks_pext(sq, blockers),
+                        diag_attacks_slow(sq, blockers)
+                    );
+                    assert_eq!(
+                        orth_attacks_pext(sq, blockers),
+                        orth_attacks_slow(sq, blockers)
+                    );
+                }
+                assert_eq!(diag_attacks(sq, blockers), diag_attacks_slow(sq, blockers));
+                assert_eq!(orth_attacks(sq, blockers), orth_attacks_slow(sq, blockers));
+            }
+        }
+    }
+}
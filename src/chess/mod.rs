@@ -1,9 +1,14 @@
+//! The rules of chess: positions, moves, and move generation.
+
 pub mod board;
 pub mod chessmove;
+pub(crate) mod eco;
 pub mod fen;
+pub mod game;
 mod magic;
+pub mod pgn;
 pub mod piece;
-pub mod piecelayout;
-pub mod quick;
+pub(crate) mod piecelayout;
+pub(crate) mod quick;
 pub mod squareset;
 pub mod types;
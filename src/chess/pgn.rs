@@ -0,0 +1,332 @@
+//! Reading and writing games in Portable Game Notation, so that they can be reviewed in a GUI
+//! or fed back in from one.
+
+use std::io::Write;
+
+use anyhow::Context;
+
+use crate::{
+    chess::{
+        board::{Board, Rules},
+        chessmove::Move,
+        fen::Fen,
+        piece::Colour,
+    },
+    tablebases::probe::WDL,
+};
+
+/// Lines of movetext are wrapped before they reach this many characters.
+const MAX_LINE_LENGTH: usize = 80;
+
+/// The PGN result token for a game, given its outcome from White's point of view.
+/// A game without a result is written as unfinished.
+pub const fn result_token(outcome: Option<WDL>) -> &'static str {
+    match outcome {
+        Some(WDL::Win) => "1-0",
+        Some(WDL::Loss) => "0-1",
+        Some(WDL::Draw) => "1/2-1/2",
+        None => "*",
+    }
+}
+
+/// Write one game to `out`.
+///
+/// `tags` come first, in the order given, and should begin with the tags of the seven-tag
+/// roster that precede the result (Event, Site, Date, Round, White, and Black). The Result
+/// tag follows, then a `FEN` tag if the game didn't start from the standard starting position,
+/// and a `Variant` tag for Chess960 games. Each move may carry a comment.
+pub fn write_game<'a>(
+    out: &mut impl Write,
+    tags: &[(&str, &str)],
+    start: &Board,
+    moves: impl IntoIterator<Item = (Move, Option<&'a str>)>,
+    outcome: Option<WDL>,
+) -> anyhow::Result<()> {
+    let result = result_token(outcome);
+    for (name, value) in tags {
+        writeln!(out, "[{name} \"{}\"]", escape(value))?;
+    }
+    writeln!(out, "[Result \"{result}\"]")?;
+    let fen = start.to_string();
+    if start.rules() == Rules::Chess960 {
+        writeln!(out, "[Variant \"Chess960\"]")?;
+    }
+    if start.rules() == Rules::Chess960 || fen != Board::STARTING_FEN {
+        writeln!(out, "[SetUp \"1\"]")?;
+        writeln!(out, "[FEN \"{fen}\"]")?;
+    }
+    writeln!(out)?;
+
+    let mut board = start.clone();
+    let mut line = String::new();
+    let mut push_token = |token: &str, out: &mut dyn Write| -> std::io::Result<()> {
+        if !line.is_empty() && line.len() + 1 + token.len() > MAX_LINE_LENGTH {
+            writeln!(out, "{line}")?;
+            line.clear();
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(token);
+        Ok(())
+    };
+    for (i, (mv, comment)) in moves.into_iter().enumerate() {
+        let number = board.ply() / 2 + 1;
+        if board.turn() == Colour::White {
+            push_token(&format!("{number}."), out)?;
+        } else if i == 0 {
+            push_token(&format!("{number}..."), out)?;
+        }
+        let san = board.san(mv).with_context(|| {
+            format!(
+                "Illegal move {} in position {board}.",
+                mv.display(board.rules())
+            )
+        })?;
+        push_token(&san.to_string(), out)?;
+        if let Some(comment) = comment {
+            // braces can't be escaped inside a comment, so they're dropped.
+            let comment = comment.replace(['{', '}'], "");
+            push_token(&format!("{{{comment}}}"), out)?;
+        }
+        board.make_move_simple(mv);
+    }
+    push_token(result, out)?;
+    writeln!(out, "{line}")?;
+    writeln!(out)?;
+    Ok(())
+}
+
+/// Escape a tag value, which is delimited by quotes.
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Undo [`escape`].
+fn unescape(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => out.extend(chars.next()),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// The tags and movetext of one game, not yet checked against the rules.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RawGame {
+    /// The tag pairs, in the order they were given.
+    pub tags: Vec<(String, String)>,
+    /// The movetext, with escaped lines and rest-of-line comments removed.
+    pub movetext: String,
+}
+
+impl RawGame {
+    /// The value of the tag `name`, if the game has it.
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// The position the game starts from: the `FEN` tag if there is one, else the standard
+    /// start position. Chess960 rules are used if the `Variant` tag names it, or if the
+    /// FEN's castling rights can't be classical.
+    pub fn start_position(&self) -> anyhow::Result<Board> {
+        let Some(fen) = self.tag("FEN") else {
+            return Ok(Board::startpos());
+        };
+        let parsed = Fen::parse_relaxed(fen).with_context(|| format!("bad FEN tag {fen:?}"))?;
+        let chess960 = self
+            .tag("Variant")
+            .is_some_and(|v| v.to_ascii_lowercase().contains("960"));
+        let mut board = Board::empty(if chess960 || parsed.castling.is_nonclassical() {
+            Rules::Chess960
+        } else {
+            Rules::Classical
+        });
+        board.set_from_fen(&parsed);
+        Ok(board)
+    }
+}
+
+/// Split the text of a PGN file into its games. A tag after some movetext starts a new game,
+/// and games without movetext are dropped.
+pub fn split_games(text: &str) -> Vec<RawGame> {
+    let mut games = Vec::new();
+    let mut game = RawGame::default();
+    for line in text.lines() {
+        let line = line.trim();
+        if let Some(tag) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            if !game.movetext.trim().is_empty() {
+                games.push(std::mem::take(&mut game));
+            }
+            let (name, value) = tag.split_once(' ').unwrap_or((tag, ""));
+            let value = value.trim();
+            let value = value
+                .strip_prefix('"')
+                .and_then(|v| v.strip_suffix('"'))
+                .unwrap_or(value);
+            game.tags.push((name.to_string(), unescape(value)));
+        } else if !line.starts_with('%') {
+            game.movetext.push_str(line.split(';').next().unwrap_or(""));
+            game.movetext.push(' ');
+        }
+    }
+    if !game.movetext.trim().is_empty() {
+        games.push(game);
+    }
+    games
+}
+
+/// One element of the main line of a game's movetext.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Token<'a> {
+    /// A move, in SAN, without its move number or annotations.
+    Move(&'a str),
+    /// A comment, without its braces.
+    Comment(&'a str),
+    /// The result token that ends the movetext.
+    Result(&'a str),
+}
+
+/// The main line of `movetext`. Move numbers, annotation glyphs, and variations, along with
+/// the comments inside them, are dropped.
+pub fn mainline(movetext: &str) -> Vec<Token<'_>> {
+    let mut tokens = Vec::new();
+    let mut variation = 0usize;
+    let mut rest = movetext;
+    loop {
+        rest = rest.trim_start();
+        let Some(c) = rest.chars().next() else {
+            break;
+        };
+        match c {
+            '{' => {
+                let end = rest.find('}').unwrap_or(rest.len());
+                if variation == 0 {
+                    tokens.push(Token::Comment(rest[1..end].trim()));
+                }
+                rest = rest.get(end + 1..).unwrap_or("");
+            }
+            '(' => {
+                variation += 1;
+                rest = &rest[1..];
+            }
+            ')' => {
+                variation = variation.saturating_sub(1);
+                rest = &rest[1..];
+            }
+            _ => {
+                let end = rest
+                    .find(|c: char| c.is_whitespace() || matches!(c, '{' | '(' | ')'))
+                    .unwrap_or(rest.len());
+                let (word, after) = rest.split_at(end);
+                rest = after;
+                if variation > 0 {
+                    continue;
+                }
+                if matches!(word, "1-0" | "0-1" | "1/2-1/2" | "*") {
+                    tokens.push(Token::Result(word));
+                    continue;
+                }
+                // drop move numbers, which may be attached to the move, and annotations.
+                let word = word
+                    .trim_start_matches(|c: char| c.is_ascii_digit() || c == '.')
+                    .trim_end_matches(['!', '?']);
+                if !word.is_empty() && !word.starts_with('$') {
+                    tokens.push(Token::Move(word));
+                }
+            }
+        }
+    }
+    tokens
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn play(start: &Board, moves: &[&str]) -> Vec<Move> {
+        let mut board = start.clone();
+        moves
+            .iter()
+            .map(|uci| {
+                let mv = board.parse_uci(uci).unwrap();
+                board.make_move_simple(mv);
+                mv
+            })
+            .collect()
+    }
+
+    #[test]
+    fn writes_tags_and_movetext() {
+        let board = Board::startpos();
+        let moves = play(&board, &["e2e4", "e7e5", "g1f3"]);
+        let comments = [Some("+0.30"), None, None];
+        let mut out = Vec::new();
+        write_game(
+            &mut out,
+            &[("Event", "test"), ("White", "a \"quoted\" name")],
+            &board,
+            moves.into_iter().zip(comments),
+            Some(WDL::Draw),
+        )
+        .unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "[Event \"test\"]\n\
+             [White \"a \\\"quoted\\\" name\"]\n\
+             [Result \"1/2-1/2\"]\n\
+             \n\
+             1. e4 {+0.30} e5 2. Nf3 1/2-1/2\n\
+             \n"
+        );
+    }
+
+    #[test]
+    fn set_up_positions_get_a_fen_tag() {
+        let fen = "4k3/8/8/8/8/8/4P3/4K3 b - - 0 40";
+        let board = Board::from_fen(fen).unwrap();
+        let moves = play(&board, &["e8d7", "e2e4"]);
+        let mut out = Vec::new();
+        write_game(
+            &mut out,
+            &[],
+            &board,
+            moves.into_iter().map(|m| (m, None)),
+            None,
+        )
+        .unwrap();
+        let text = String::from_utf8(out).unwrap();
+        assert!(text.contains(&format!("[FEN \"{fen}\"]")), "{text}");
+        assert!(text.contains("40... Kd7 41. e4 *"), "{text}");
+    }
+
+    #[test]
+    fn reads_games_back() {
+        let text = "[Event \"one\"]\n[White \"a \\\"quoted\\\" name\"]\n\n\
+                    1. e4 {best by test} e5 (1... c5 2. Nf3) 2.Nf3! $1 Nc6 ; a remark\n\
+                    1-0\n\n\
+                    [Event \"two\"]\n\n1. d4 *\n";
+        let games = split_games(text);
+        assert_eq!(games.len(), 2);
+        assert_eq!(games[0].tag("White"), Some("a \"quoted\" name"));
+        assert_eq!(games[1].tag("Event"), Some("two"));
+        assert_eq!(
+            mainline(&games[0].movetext),
+            [
+                Token::Move("e4"),
+                Token::Comment("best by test"),
+                Token::Move("e5"),
+                Token::Move("Nf3"),
+                Token::Move("Nc6"),
+                Token::Result("1-0"),
+            ]
+        );
+    }
+}
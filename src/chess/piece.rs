@@ -1,21 +1,33 @@
+//! Colours, piece types, and coloured pieces.
+
 use std::{
     fmt::{Debug, Display},
     mem::size_of,
     ops::{Index, IndexMut, Not},
 };
 
+/// A colour at the type level, so that code can be monomorphised for each side.
 pub trait Col {
+    /// The other colour.
     type Opposite: Col;
+    /// Whether this is White.
     const WHITE: bool;
+    /// The value-level colour.
     const COLOUR: Colour;
 
+    /// The square offset of a pawn capture towards the A-file, from White's point of view.
     const PAWN_LEFT_OFFSET: i8;
+    /// The square offset of a single pawn push.
     const PAWN_FWD_OFFSET: i8;
+    /// The square offset of a pawn capture towards the H-file, from White's point of view.
     const PAWN_RIGHT_OFFSET: i8;
+    /// The square offset of a double pawn push.
     const PAWN_DOUBLE_OFFSET: i8;
 }
 
+/// White, at the type level.
 pub struct White;
+/// Black, at the type level.
 pub struct Black;
 
 impl Col for White {
@@ -40,8 +52,10 @@ impl Col for Black {
     const PAWN_DOUBLE_OFFSET: i8 = -16;
 }
 
+/// A side in the game.
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
 #[repr(u8)]
+#[allow(missing_docs)] // the variants name themselves.
 pub enum Colour {
     White,
     Black,
@@ -58,9 +72,11 @@ impl Display for Colour {
     }
 }
 
+/// A kind of piece, without a colour.
 #[allow(clippy::module_name_repetitions)]
 #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
 #[repr(u8)]
+#[allow(missing_docs)] // the variants name themselves.
 pub enum PieceType {
     Pawn,
     Knight,
@@ -85,9 +101,11 @@ impl Display for PieceType {
     }
 }
 
+/// A piece of a given colour, as `WP` for a white pawn or `BK` for the black king.
 #[rustfmt::skip]
 #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug, Default, Hash)]
 #[repr(u8)]
+#[allow(missing_docs)] // the variants name themselves.
 pub enum Piece {
     #[default]
     WP, BP,
@@ -107,10 +125,12 @@ impl Display for Piece {
 }
 
 impl Colour {
+    /// Black if `v`, and White otherwise.
     pub const fn new(v: bool) -> Self {
         if v { Self::Black } else { Self::White }
     }
 
+    /// The other colour.
     pub const fn flip(self) -> Self {
         match self {
             Self::White => Self::Black,
@@ -118,14 +138,17 @@ impl Colour {
         }
     }
 
+    /// The index of this colour, for indexing arrays.
     pub const fn index(self) -> usize {
         self as usize
     }
 
+    /// The index of this colour, as a `u8`.
     pub const fn inner(self) -> u8 {
         self as u8
     }
 
+    /// Both colours, White first.
     pub fn all() -> impl DoubleEndedIterator<Item = Self> {
         [Self::White, Self::Black].into_iter()
     }
@@ -140,6 +163,7 @@ impl Not for Colour {
 }
 
 impl PieceType {
+    /// The piece type with the given index, counting from zero at the pawn.
     pub const fn new(v: u8) -> Option<Self> {
         if v < 6 {
             // SAFETY: inner is less than 6, so it corresponds to a valid enum variant.
@@ -149,22 +173,29 @@ impl PieceType {
         }
     }
 
-    /// SAFETY: you may only call this function with value of `inner` less than 6.
+    /// The piece type with the given index, without checking it.
+    ///
+    /// # Safety
+    ///
+    /// You may only call this function with a value of `v` less than 6.
     pub const unsafe fn from_index_unchecked(v: u8) -> Self {
         debug_assert!(v < 6);
         // Safety: caller's precondition.
         unsafe { std::mem::transmute(v) }
     }
 
+    /// The index of this piece type, as a `u8`.
     pub const fn inner(self) -> u8 {
         self as u8
     }
 
+    /// Whether a pawn can promote to this piece type.
     pub const fn legal_promo(self) -> bool {
         // self == Self::QUEEN || self == Self::KNIGHT || self == Self::BISHOP || self == Self::ROOK
         matches!(self, Self::Queen | Self::Knight | Self::Bishop | Self::Rook)
     }
 
+    /// The character for this piece type in UCI promotions, if a pawn can promote to it.
     pub const fn promo_char(self) -> Option<char> {
         match self {
             Self::Queen => Some('q'),
@@ -175,15 +206,18 @@ impl PieceType {
         }
     }
 
+    /// All the piece types, from the pawn to the king.
     pub fn all() -> impl DoubleEndedIterator<Item = Self> {
         // SAFETY: all values are within `0..6`.
         (0..6u8).map(|i| unsafe { std::mem::transmute(i) })
     }
 
+    /// The index of this piece type, for indexing arrays.
     pub const fn index(self) -> usize {
         self as usize
     }
 
+    /// The piece type with the given uppercase FEN symbol.
     pub fn from_symbol(c: u8) -> Option<Self> {
         const SYMBOLS: [u8; 7] = *b"PNBRQK.";
         SYMBOLS
@@ -194,6 +228,7 @@ impl PieceType {
 }
 
 impl Piece {
+    /// The piece of `colour` and `piece_type`.
     pub const fn new(colour: Colour, piece_type: PieceType) -> Self {
         let index = colour as u8 | (piece_type as u8) << 1;
         // SAFETY: Colour is {0, 1}, piece_type is {0, 1, 2, 3, 4, 5}.
@@ -202,6 +237,7 @@ impl Piece {
         unsafe { std::mem::transmute(index) }
     }
 
+    /// The piece with the given index.
     pub const fn from_index(v: u8) -> Option<Self> {
         if v < 12 {
             // SAFETY: inner is less than 12, so it corresponds to a valid enum variant.
@@ -211,6 +247,7 @@ impl Piece {
         }
     }
 
+    /// The colour of this piece.
     pub const fn colour(self) -> Colour {
         if (self as u8) & 1 == 0 {
             Colour::White
@@ -219,12 +256,14 @@ impl Piece {
         }
     }
 
+    /// The type of this piece.
     pub const fn piece_type(self) -> PieceType {
         let pt_index = self as u8 >> 1;
         // SAFETY: pt_index is always within the bounds of the type.
         unsafe { PieceType::from_index_unchecked(pt_index) }
     }
 
+    /// The FEN symbol for this piece, uppercase for White and lowercase for Black.
     pub const fn char(self) -> char {
         match self {
             Self::WP => 'P',
@@ -242,20 +281,24 @@ impl Piece {
         }
     }
 
+    /// The FEN symbol for this piece, as a byte.
     pub fn byte_char(self) -> u8 {
         b"PpNnBbRrQqKk"[self]
     }
 
+    /// All the pieces, alternating between White and Black.
     pub fn all() -> impl DoubleEndedIterator<Item = Self> {
         // SAFETY: all values are within `0..12`.
         (0..12u8).map(|i| unsafe { std::mem::transmute(i) })
     }
 
+    /// The index of this piece, as a `u8`.
     #[allow(dead_code)]
     pub const fn inner(self) -> u8 {
         self as u8
     }
 
+    /// The piece of the same type and the other colour.
     pub const fn flip_colour(self) -> Self {
         // SAFETY: flipping the colour bit of a valid piece always results in a valid piece.
         unsafe { std::mem::transmute(self as u8 ^ 1) }
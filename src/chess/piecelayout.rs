@@ -260,6 +260,80 @@ impl PieceLayout {
     }
 
     pub fn generate_threats(&self, side: Colour) -> Threats {
+        let mut attacks = [SquareSet::EMPTY; 6];
+        for piece_type in PieceType::all() {
+            attacks[piece_type] = self.attacks_by(piece_type, !side);
+        }
+        self.threats_from(side, attacks)
+    }
+
+    /// The same threats as [`Self::generate_threats`], reusing the attacks of each of the
+    /// opponent's piece types from `earlier_threats`, the threats against `side` in the
+    /// layout `earlier`, wherever they can't have changed since.
+    pub fn update_threats(
+        &self,
+        side: Colour,
+        earlier: &Self,
+        earlier_threats: &Threats,
+    ) -> Threats {
+        let them = self.colours[!side];
+        let earlier_them = earlier.colours[!side];
+        let changed = self.occupied() ^ earlier.occupied();
+        let mut attacks = earlier_threats.by_type;
+        for piece_type in PieceType::all() {
+            let moved = self.pieces[piece_type] & them != earlier.pieces[piece_type] & earlier_them;
+            // a slider's attacks stop at the first piece on each ray, so they only change
+            // when a square that it attacks is vacated, or a piece lands on one.
+            let blocked = matches!(
+                piece_type,
+                PieceType::Bishop | PieceType::Rook | PieceType::Queen
+            ) && attacks[piece_type] & changed != SquareSet::EMPTY;
+            if moved || blocked {
+                attacks[piece_type] = self.attacks_by(piece_type, !side);
+            }
+        }
+        self.threats_from(side, attacks)
+    }
+
+    /// The squares attacked by the pieces of type `piece_type` belonging to `colour`.
+    fn attacks_by(&self, piece_type: PieceType, colour: Colour) -> SquareSet {
+        let pieces = self.pieces[piece_type] & self.colours[colour];
+        let blockers = self.occupied();
+        let mut attacks = SquareSet::EMPTY;
+        match piece_type {
+            PieceType::Pawn => return pawn_attacks_by(pieces, colour),
+            PieceType::Knight => {
+                for sq in pieces {
+                    attacks |= knight_attacks(sq);
+                }
+            }
+            PieceType::Bishop => {
+                for sq in pieces {
+                    attacks |= diag_attacks(sq, blockers);
+                }
+            }
+            PieceType::Rook => {
+                for sq in pieces {
+                    attacks |= orth_attacks(sq, blockers);
+                }
+            }
+            PieceType::Queen => {
+                for sq in pieces {
+                    attacks |= diag_attacks(sq, blockers) | orth_attacks(sq, blockers);
+                }
+            }
+            PieceType::King => {
+                for sq in pieces {
+                    attacks |= king_attacks(sq);
+                }
+            }
+        }
+        attacks
+    }
+
+    /// Assemble the threats against `side`, given the attacks of each of its opponent's
+    /// piece types.
+    fn threats_from(&self, side: Colour, by_type: [SquareSet; 6]) -> Threats {
         let mut checkers = SquareSet::EMPTY;
 
         let us = self.colours[side];
@@ -272,29 +346,11 @@ impl PieceLayout {
         let their_king = (self.pieces[PieceType::King] & them).first().unwrap();
         let blockers = us | them;
 
-        // compute threats
-        let leq_pawn = match side {
-            Colour::White => their_pawns.south_east_one() | their_pawns.south_west_one(),
-            Colour::Black => their_pawns.north_east_one() | their_pawns.north_west_one(),
-        };
-
-        let mut leq_minor = leq_pawn;
-        for sq in their_knights {
-            leq_minor |= knight_attacks(sq);
-        }
-        for sq in their_bishops {
-            leq_minor |= diag_attacks(sq, blockers);
-        }
-        let mut leq_rook = leq_minor;
-        for sq in their_rooks {
-            leq_rook |= orth_attacks(sq, blockers);
-        }
-        let mut all_threats = leq_rook;
-        for sq in their_queens {
-            all_threats |= diag_attacks(sq, blockers);
-            all_threats |= orth_attacks(sq, blockers);
-        }
-        all_threats |= king_attacks(their_king);
+        // combine threats
+        let leq_pawn = by_type[PieceType::Pawn];
+        let leq_minor = leq_pawn | by_type[PieceType::Knight] | by_type[PieceType::Bishop];
+        let leq_rook = leq_minor | by_type[PieceType::Rook];
+        let all_threats = leq_rook | by_type[PieceType::Queen] | by_type[PieceType::King];
 
         // compute checkers
         let our_king_bb = us & self.pieces[PieceType::King];
@@ -325,6 +381,7 @@ impl PieceLayout {
             leq_rook,
             checkers,
             tellers,
+            by_type,
         }
     }
 }
@@ -337,6 +394,9 @@ pub struct Threats {
     pub leq_rook: SquareSet,
     pub checkers: SquareSet,
     pub tellers: [SquareSet; 6],
+    /// The squares attacked by each of the opponent's piece types, kept so that the next
+    /// threats against this side can be updated rather than generated from scratch.
+    pub by_type: [SquareSet; 6],
 }
 
 impl Display for PieceLayout {
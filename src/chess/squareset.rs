@@ -1,3 +1,5 @@
+//! Sets of squares, represented as 64-bit masks.
+
 use std::{
     fmt::Display,
     ops::{
@@ -19,64 +21,85 @@ pub struct SquareSet {
 }
 
 impl SquareSet {
+    /// The set with no squares.
     pub const EMPTY: Self = Self { inner: 0 };
+    /// The set with every square.
     pub const FULL: Self = Self { inner: !0 };
 
+    /// The squares of the first rank.
     pub const RANK_1: Self = Self {
         inner: 0x0000_0000_0000_00FF,
     };
+    /// The squares of the second rank.
     pub const RANK_2: Self = Self {
         inner: 0x0000_0000_0000_FF00,
     };
+    /// The squares of the third rank.
     pub const RANK_3: Self = Self {
         inner: 0x0000_0000_00FF_0000,
     };
+    /// The squares of the fourth rank.
     pub const RANK_4: Self = Self {
         inner: 0x0000_0000_FF00_0000,
     };
+    /// The squares of the fifth rank.
     pub const RANK_5: Self = Self {
         inner: 0x0000_00FF_0000_0000,
     };
+    /// The squares of the sixth rank.
     pub const RANK_6: Self = Self {
         inner: 0x0000_FF00_0000_0000,
     };
+    /// The squares of the seventh rank.
     pub const RANK_7: Self = Self {
         inner: 0x00FF_0000_0000_0000,
     };
+    /// The squares of the eighth rank.
     pub const RANK_8: Self = Self {
         inner: 0xFF00_0000_0000_0000,
     };
+    /// The squares of the A-file.
     pub const FILE_A: Self = Self {
         inner: 0x0101_0101_0101_0101,
     };
+    /// The squares of the B-file.
     pub const FILE_B: Self = Self {
         inner: 0x0202_0202_0202_0202,
     };
+    /// The squares of the C-file.
     pub const FILE_C: Self = Self {
         inner: 0x0404_0404_0404_0404,
     };
+    /// The squares of the D-file.
     pub const FILE_D: Self = Self {
         inner: 0x0808_0808_0808_0808,
     };
+    /// The squares of the E-file.
     pub const FILE_E: Self = Self {
         inner: 0x1010_1010_1010_1010,
     };
+    /// The squares of the F-file.
     pub const FILE_F: Self = Self {
         inner: 0x2020_2020_2020_2020,
     };
+    /// The squares of the G-file.
     pub const FILE_G: Self = Self {
         inner: 0x4040_4040_4040_4040,
     };
+    /// The squares of the H-file.
     pub const FILE_H: Self = Self {
         inner: 0x8080_8080_8080_8080,
     };
+    /// The light squares, such as h1.
     pub const LIGHT_SQUARES: Self = Self {
         inner: 0x55AA_55AA_55AA_55AA,
     };
+    /// The dark squares, such as a1.
     pub const DARK_SQUARES: Self = Self {
         inner: 0xAA55_AA55_AA55_AA55,
     };
 
+    /// Each rank's squares, indexed by rank.
     pub const RANKS: [Self; 8] = [
         Self::RANK_1,
         Self::RANK_2,
@@ -88,6 +111,7 @@ impl SquareSet {
         Self::RANK_8,
     ];
 
+    /// Each file's squares, indexed by file.
     pub const FILES: [Self; 8] = [
         Self::FILE_A,
         Self::FILE_B,
@@ -99,143 +123,181 @@ impl SquareSet {
         Self::FILE_H,
     ];
 
+    /// The first and eighth ranks.
     pub const BACK_RANKS: Self = Self::union(Self::RANK_1, Self::RANK_8);
 
+    /// The set whose bit `i` is set for each square of index `i` in it.
     pub const fn from_inner(inner: u64) -> Self {
         Self { inner }
     }
 
+    /// The raw bits of this set, with bit `i` set for each square of index `i` in it.
     pub const fn inner(self) -> u64 {
         self.inner
     }
 
+    /// The number of squares in this set.
     pub const fn count(self) -> u32 {
         self.inner.count_ones()
     }
 
+    /// The squares in both sets.
     pub const fn intersection(self, other: Self) -> Self {
         Self {
             inner: self.inner & other.inner,
         }
     }
 
+    /// Whether every square of `other` is in this set.
     pub const fn contains(self, other: Self) -> bool {
         (self.inner & other.inner) == other.inner
     }
 
+    /// Whether `square` is in this set.
     pub const fn contains_square(self, square: Square) -> bool {
         (self.inner & (1 << square.index())) != 0
     }
 
+    /// The squares in either set.
     pub const fn union(self, other: Self) -> Self {
         Self {
             inner: self.inner | other.inner,
         }
     }
 
+    /// This set, with `square` added.
     pub const fn add_square(self, square: Square) -> Self {
         Self {
             inner: self.inner | (1 << square.index()),
         }
     }
 
+    /// The squares of this set that aren't in `other`.
     pub const fn remove(self, other: Self) -> Self {
         Self {
             inner: self.inner & !other.inner,
         }
     }
 
+    /// This set, with `square` removed.
     pub const fn remove_square(self, square: Square) -> Self {
         Self {
             inner: self.inner & !(1 << square.index()),
         }
     }
 
+    /// The squares in exactly one of the two sets.
     pub const fn toggle(self, other: Self) -> Self {
         Self {
             inner: self.inner ^ other.inner,
         }
     }
 
+    /// This set, with `square` added if it was absent and removed if it was present.
     pub const fn toggle_square(self, square: Square) -> Self {
         Self {
             inner: self.inner ^ (1 << square.index()),
         }
     }
 
+    /// Iterate over the squares of this set, in increasing order.
     #[allow(clippy::missing_const_for_fn)]
     pub fn iter(self) -> SquareIter {
         SquareIter::new(self.inner)
     }
 
+    /// The lowest square in this set, if it isn't empty.
     #[allow(clippy::cast_possible_truncation)]
     pub const fn first(self) -> Option<Square> {
         Square::new(self.inner.trailing_zeros() as u8)
     }
 
+    /// The highest square in this set, if it isn't empty.
+    #[allow(clippy::cast_possible_truncation)]
+    pub const fn last(self) -> Option<Square> {
+        if self.inner == 0 {
+            return None;
+        }
+        Square::new(63 - self.inner.leading_zeros() as u8)
+    }
+
+    /// The set containing only `square`.
     pub const fn from_square(square: Square) -> Self {
         Self {
             inner: 1 << square.index(),
         }
     }
 
+    /// Every square of this set moved one square up and towards the H-file.
     pub const fn north_east_one(self) -> Self {
         Self {
             inner: self.inner << 9 & !Self::FILE_A.inner,
         }
     }
+    /// Every square of this set moved one square up and towards the A-file.
     pub const fn north_west_one(self) -> Self {
         Self {
             inner: self.inner << 7 & !Self::FILE_H.inner,
         }
     }
+    /// Every square of this set moved one square down and towards the H-file.
     pub const fn south_east_one(self) -> Self {
         Self {
             inner: self.inner >> 7 & !Self::FILE_A.inner,
         }
     }
+    /// Every square of this set moved one square down and towards the A-file.
     pub const fn south_west_one(self) -> Self {
         Self {
             inner: self.inner >> 9 & !Self::FILE_H.inner,
         }
     }
+    /// Every square of this set moved one square towards the H-file.
     pub const fn east_one(self) -> Self {
         Self {
             inner: self.inner << 1 & !Self::FILE_A.inner,
         }
     }
+    /// Every square of this set moved one square towards the A-file.
     pub const fn west_one(self) -> Self {
         Self {
             inner: self.inner >> 1 & !Self::FILE_H.inner,
         }
     }
+    /// Every square of this set moved one square up the board.
     pub const fn north_one(self) -> Self {
         Self {
             inner: self.inner << 8,
         }
     }
+    /// Every square of this set moved one square down the board.
     pub const fn south_one(self) -> Self {
         Self {
             inner: self.inner >> 8,
         }
     }
 
+    /// The set containing only the lowest square of this set.
     pub fn isolate_lsb(self) -> Self {
         self & (Self::from_inner(self.inner().wrapping_neg()))
     }
 
+    /// This set, without its lowest square.
     pub fn without_lsb(self) -> Self {
         self & (Self::from_inner(self.inner().wrapping_sub(1)))
     }
 
+    /// Whether this set has exactly one square.
     pub fn one(self) -> bool {
         self != Self::EMPTY && self.without_lsb() == Self::EMPTY
     }
 
+    /// Whether this set has more than one square.
     pub fn many(self) -> bool {
         self.without_lsb() != Self::EMPTY
     }
 
+    /// This set as seen from `colour`'s point of view, flipping the ranks for Black.
     pub fn relative_to(self, colour: Colour) -> Self {
         if colour == Colour::White {
             self
@@ -254,10 +316,12 @@ pub struct SquareIter {
 }
 
 impl SquareIter {
+    /// An iterator over the squares whose bits are set in `value`.
     pub const fn new(value: u64) -> Self {
         Self { value }
     }
 
+    /// The squares that haven't been yielded yet.
     pub const fn remaining(&self) -> SquareSet {
         SquareSet::from_inner(self.value)
     }
@@ -1,3 +1,5 @@
+//! Squares, files, ranks, castling rights, and the copyable state of a position.
+
 use std::{
     fmt::{self, Display},
     mem::size_of,
@@ -7,6 +9,7 @@ use std::{
 
 use crate::{
     chess::{
+        chessmove::Move,
         piece::{Colour, Piece, PieceType},
         piecelayout::{PieceLayout, Threats},
         squareset::SquareSet,
@@ -16,8 +19,10 @@ use crate::{
 
 use super::piece::Col;
 
+/// A file of the board, from the A-file to the H-file.
 #[derive(PartialEq, Eq, Clone, Copy, PartialOrd, Ord, Hash, Debug)]
 #[repr(u8)]
+#[allow(missing_docs)] // the variants name themselves.
 pub enum File {
     A,
     B,
@@ -32,10 +37,12 @@ pub enum File {
 const _FILE_ASSERT: () = assert!(size_of::<File>() == size_of::<Option<File>>());
 
 impl File {
+    /// The number of files between `self` and `other`.
     pub const fn abs_diff(self, other: Self) -> u8 {
         (self as u8).abs_diff(other as u8)
     }
 
+    /// The file with the given index, counting from zero at the A-file.
     pub const fn from_index(index: u8) -> Option<Self> {
         if index < 8 {
             // SAFETY: inner is less than 8, so it corresponds to a valid enum variant.
@@ -45,20 +52,24 @@ impl File {
         }
     }
 
+    /// The file `diff` files towards the H-file, if it's on the board.
     pub const fn add(self, diff: u8) -> Option<Self> {
         Self::from_index(self as u8 + diff)
     }
 
+    /// The file `diff` files towards the A-file, if it's on the board.
     pub const fn sub(self, diff: u8) -> Option<Self> {
         #![allow(clippy::cast_sign_loss, clippy::cast_possible_wrap)]
         Self::from_index((self as i8 - diff as i8) as u8)
     }
 
+    /// All the files, from A to H.
     pub fn all() -> impl DoubleEndedIterator<Item = Self> {
         // SAFETY: all values are within `0..64`.
         (0..8u8).map(|i| unsafe { std::mem::transmute(i) })
     }
 
+    /// The square at the intersection of this file and `rank`.
     pub const fn with(self, rank: Rank) -> Square {
         Square::from_rank_file(rank, self)
     }
@@ -80,8 +91,10 @@ impl<T> IndexMut<File> for [T; 8] {
     }
 }
 
+/// A rank of the board, from White's first rank to White's eighth.
 #[derive(PartialEq, Eq, Clone, Copy, PartialOrd, Ord, Hash, Debug)]
 #[repr(u8)]
+#[allow(missing_docs)] // the variants name themselves.
 pub enum Rank {
     One,
     Two,
@@ -96,10 +109,12 @@ pub enum Rank {
 const _RANK_ASSERT: () = assert!(size_of::<Rank>() == size_of::<Option<Rank>>());
 
 impl Rank {
+    /// The number of ranks between `self` and `other`.
     pub const fn abs_diff(self, other: Self) -> u8 {
         (self as u8).abs_diff(other as u8)
     }
 
+    /// The rank with the given index, counting from zero at the first rank.
     pub const fn from_index(index: u8) -> Option<Self> {
         if index < 8 {
             // SAFETY: inner is less than 8, so it corresponds to a valid enum variant.
@@ -109,20 +124,24 @@ impl Rank {
         }
     }
 
+    /// The rank `diff` ranks towards the eighth rank, if it's on the board.
     pub const fn add(self, diff: u8) -> Option<Self> {
         Self::from_index(self as u8 + diff)
     }
 
+    /// The rank `diff` ranks towards the first rank, if it's on the board.
     pub const fn sub(self, diff: u8) -> Option<Self> {
         #![allow(clippy::cast_sign_loss, clippy::cast_possible_wrap)]
         Self::from_index((self as i8 - diff as i8) as u8)
     }
 
+    /// All the ranks, from the first to the eighth.
     pub fn all() -> impl DoubleEndedIterator<Item = Self> {
         // SAFETY: all values are within `0..8`.
         (0..8u8).map(|i| unsafe { std::mem::transmute(i) })
     }
 
+    /// The square at the intersection of this rank and `file`.
     pub const fn with(self, file: File) -> Square {
         Square::from_rank_file(self, file)
     }
@@ -144,9 +163,11 @@ impl<T> IndexMut<Rank> for [T; 8] {
     }
 }
 
+/// A square of the board, indexed from zero at a1 to 63 at h8, rank by rank.
 #[rustfmt::skip]
 #[derive(PartialEq, Eq, Clone, Copy, PartialOrd, Ord, Hash, Debug, Default)]
 #[repr(u8)]
+#[allow(missing_docs)] // the variants name themselves.
 pub enum Square {
     #[default]
     A1, B1, C1, D1, E1, F1, G1, H1,
@@ -186,12 +207,14 @@ static SQUARE_NAMES: [&str; 64] = [
 
 #[allow(clippy::unusual_byte_groupings)]
 impl Square {
+    /// The square at the intersection of `rank` and `file`.
     pub const fn from_rank_file(rank: Rank, file: File) -> Self {
         let inner = rank as u8 * 8 + file as u8;
         // SAFETY: Rank and File are constrained such that inner is always < 64.
         unsafe { std::mem::transmute(inner) }
     }
 
+    /// The square with index `inner`, if it's on the board.
     pub const fn new(inner: u8) -> Option<Self> {
         if inner < 64 {
             // SAFETY: inner is less than 64, so it corresponds to a valid enum variant.
@@ -201,6 +224,7 @@ impl Square {
         }
     }
 
+    /// The square with index `inner`, or h8 if `inner` is past the end of the board.
     pub const fn new_clamped(inner: u8) -> Self {
         let inner = min!(inner, 63);
         let maybe_square = Self::new(inner);
@@ -211,25 +235,32 @@ impl Square {
         }
     }
 
-    /// SAFETY: you may only call this function with value of `inner` less than 64.
+    /// The square with index `inner`, without checking it.
+    ///
+    /// # Safety
+    ///
+    /// You may only call this function with a value of `inner` less than 64.
     pub const unsafe fn new_unchecked(inner: u8) -> Self {
         debug_assert!(inner < 64);
         // Safety: Caller's precondition.
         unsafe { std::mem::transmute(inner) }
     }
 
+    /// The square reflected across the horizontal midline of the board, as a1 to a8.
     pub const fn flip_rank(self) -> Self {
         // SAFETY: given the precondition that `self as u8` is less than 64,
         // this operation cannot construct a value >= 64.
         unsafe { std::mem::transmute(self as u8 ^ 0b111_000) }
     }
 
+    /// The square reflected across the vertical midline of the board, as a1 to h1.
     pub const fn flip_file(self) -> Self {
         // SAFETY: given the precondition that `self as u8` is less than 64,
         // this operation cannot construct a value >= 64.
         unsafe { std::mem::transmute(self as u8 ^ 0b000_111) }
     }
 
+    /// This square as seen from `side`'s point of view, flipping the ranks for Black.
     pub const fn relative_to(self, side: Colour) -> Self {
         if matches!(side, Colour::White) {
             self
@@ -252,33 +283,38 @@ impl Square {
         unsafe { std::mem::transmute(self as u8 / 8) }
     }
 
+    /// The Chebyshev distance between two squares, which is how many king moves apart they are.
     pub const fn distance(a: Self, b: Self) -> u8 {
         max!(a.file().abs_diff(b.file()), a.rank().abs_diff(b.rank()))
     }
 
+    /// The index of this square, as an `i8`.
     pub const fn signed_inner(self) -> i8 {
         #![allow(clippy::cast_possible_wrap)]
         self as i8
     }
 
+    /// The index of this square, for indexing arrays.
     pub const fn index(self) -> usize {
         self as usize
     }
 
+    /// The index of this square, as a `u8`.
     pub const fn inner(self) -> u8 {
         self as u8
     }
 
+    /// Offsets the square index upwards by `offset`, returning `None` if the result
+    /// would leave the board. Note that this works on the raw index, so it will wrap
+    /// from the H-file onto the A-file of the next rank.
     pub const fn add(self, offset: u8) -> Option<Self> {
-        #![allow(
-            clippy::cast_possible_truncation,
-            clippy::cast_possible_wrap,
-            clippy::cast_sign_loss
-        )]
-        let res = self as u8 + offset;
+        let Some(res) = (self as u8).checked_add(offset) else {
+            return None;
+        };
         Self::new(res)
     }
 
+    /// Offsets the square index upwards by `offset`, clamping to h8.
     pub const fn saturating_add(self, offset: u8) -> Self {
         #![allow(
             clippy::cast_possible_truncation,
@@ -295,12 +331,17 @@ impl Square {
         }
     }
 
+    /// Offsets the square index upwards by `offset`, wrapping around from h8 to a1.
     pub const fn wrapping_add(self, offset: u8) -> Self {
         // Safety: in-bounds due to modulus
         unsafe { Self::new_unchecked((self as u8 + offset) % 64) }
     }
 
-    /// SAFETY: You may not call this function with a square and offset such that
+    /// Offsets the square index by `offset`, without checking the result.
+    ///
+    /// # Safety
+    ///
+    /// You may not call this function with a square and offset such that
     /// `square as i8 + offset` is outwith `0..64`.
     pub const unsafe fn add_unchecked(self, offset: i8) -> Self {
         #![allow(
@@ -313,7 +354,11 @@ impl Square {
         unsafe { Self::new_unchecked(res as u8) }
     }
 
-    /// SAFETY: You may not call this function with a square and offset such that
+    /// Offsets the square index by `-offset`, without checking the result.
+    ///
+    /// # Safety
+    ///
+    /// You may not call this function with a square and offset such that
     /// `square as i8 - offset` is outwith `0..64`.
     pub const unsafe fn sub_unchecked(self, offset: i8) -> Self {
         #![allow(
@@ -326,28 +371,43 @@ impl Square {
         unsafe { Self::new_unchecked(res as u8) }
     }
 
+    /// Offsets the square index downwards by `offset`, returning `None` if the result
+    /// would leave the board. As with `add`, this works on the raw index.
     pub const fn sub(self, offset: u8) -> Option<Self> {
-        #![allow(
-            clippy::cast_possible_truncation,
-            clippy::cast_possible_wrap,
-            clippy::cast_sign_loss
-        )]
-        let res = self as u8 - offset;
+        let Some(res) = (self as u8).checked_sub(offset) else {
+            return None;
+        };
         Self::new(res)
     }
 
+    /// The set containing only this square.
     pub const fn as_set(self) -> SquareSet {
         SquareSet::from_inner(1 << self as u8)
     }
 
-    pub fn pawn_push(self, side: Colour) -> Option<Self> {
-        if side == Colour::White {
+    /// The square one rank further up the board from `side`'s perspective,
+    /// or `None` if this square is on `side`'s last rank.
+    pub const fn forward(self, side: Colour) -> Option<Self> {
+        if matches!(side, Colour::White) {
             self.add(8)
         } else {
             self.sub(8)
         }
     }
 
+    /// The square one rank further down the board from `side`'s perspective,
+    /// or `None` if this square is on `side`'s first rank.
+    pub const fn backward(self, side: Colour) -> Option<Self> {
+        self.forward(side.flip())
+    }
+
+    /// The square a pawn of `side` on this square moves to, if it's on the board.
+    pub fn pawn_push(self, side: Colour) -> Option<Self> {
+        self.forward(side)
+    }
+
+    /// The square a pawn of `side` on this square captures towards the H-file, from White's
+    /// point of view. Note that this works on the raw index, so it wraps from the H-file.
     pub fn pawn_right(self, side: Colour) -> Option<Self> {
         if side == Colour::White {
             self.add(9)
@@ -356,6 +416,8 @@ impl Square {
         }
     }
 
+    /// The square a pawn of `side` on this square captures towards the A-file, from White's
+    /// point of view. Note that this works on the raw index, so it wraps from the A-file.
     pub fn pawn_left(self, side: Colour) -> Option<Self> {
         if side == Colour::White {
             self.add(7)
@@ -364,20 +426,26 @@ impl Square {
         }
     }
 
+    /// Whether this square's index is less than or equal to `other`'s.
     #[rustfmt::skip]
     pub const fn le(self, other: Self) -> bool { self as u8 <= other as u8 }
+    /// Whether this square's index is greater than or equal to `other`'s.
     #[rustfmt::skip]
     pub const fn ge(self, other: Self) -> bool { self as u8 >= other as u8 }
+    /// Whether this square's index is less than `other`'s.
     #[rustfmt::skip]
     pub const fn lt(self, other: Self) -> bool { (self as u8) < other as u8  }
+    /// Whether this square's index is greater than `other`'s.
     #[rustfmt::skip]
     pub const fn gt(self, other: Self) -> bool { self as u8 > other as u8  }
 
+    /// All the squares, from a1 to h8.
     pub fn all() -> impl DoubleEndedIterator<Item = Self> {
         // SAFETY: all values are within `0..64`.
         (0..64u8).map(|i| unsafe { std::mem::transmute(i) })
     }
 
+    /// The name of this square in algebraic notation, such as "e4".
     pub fn name(self) -> &'static str {
         SQUARE_NAMES[self]
     }
@@ -409,7 +477,7 @@ impl From<Square> for u16 {
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
-pub struct ContHistIndex {
+pub(crate) struct ContHistIndex {
     pub piece: Piece,
     pub to: Square,
 }
@@ -446,8 +514,11 @@ pub struct Keys {
     pub major: u64,
 }
 
-/// Full state for a chess position.
-#[derive(Debug, Clone, PartialEq, Eq)]
+/// The state of a position that's saved before each move and restored when it's taken back.
+///
+/// The mailbox isn't part of it, as it's cheaper to undo a move's few changes to the mailbox
+/// than to copy all of it on every move.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
 pub struct State {
     /// Which rooks can castle.
     pub castle_perm: CastlingRights,
@@ -461,28 +532,23 @@ pub struct State {
     pub bbs: PieceLayout,
     /// Pieces that cannot be moved without the king being checked.
     pub pinned: [SquareSet; 2],
-    /// An array to accelerate `Board::piece_at()`.
-    pub mailbox: [Option<Piece>; 64],
     /// Zobrist hashes.
     pub keys: Keys,
 }
 
-impl Default for State {
-    fn default() -> Self {
-        Self {
-            mailbox: [None; 64],
-            castle_perm: CastlingRights::default(),
-            ep_square: None,
-            fifty_move_counter: 0,
-            threats: Threats::default(),
-            bbs: PieceLayout::default(),
-            pinned: <[SquareSet; 2]>::default(),
-            keys: Keys::default(),
-        }
-    }
+/// A position's state from before a move, with what's needed to take the move back.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Undo {
+    /// The state before the move.
+    pub state: State,
+    /// The move, or `None` for a null move.
+    pub mv: Option<Move>,
+    /// The piece captured by the move, unless it was taken en passant.
+    pub captured: Option<Piece>,
 }
 
 impl State {
+    /// Compute the Zobrist keys of this position from scratch, with `side` to move.
     pub fn generate_pos_keys(&self, side: Colour) -> Keys {
         let mut keys = Keys::default();
         self.bbs.visit_pieces(|sq, piece| {
@@ -513,18 +579,23 @@ impl State {
 
         keys.zobrist ^= CASTLE_KEYS[self.castle_perm.hashkey_index()];
 
-        debug_assert!(self.fifty_move_counter <= 100);
+        debug_assert!(self.fifty_move_counter <= 150);
 
         keys
     }
 }
 
+/// Whether the side to move is in check, or checkmated.
 pub enum CheckState {
+    /// The side to move isn't in check.
     None,
+    /// The side to move is in check, but has a legal move.
     Check,
+    /// The side to move is checkmated.
     Checkmate,
 }
 
+/// Which rooks each side can still castle with, stored by the rook's file.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub struct CastlingRights {
     // packed representation:
@@ -537,20 +608,22 @@ pub struct CastlingRights {
 
 #[allow(clippy::unusual_byte_groupings)]
 impl CastlingRights {
-    pub const WKCA: u16 = 0b0001;
-    pub const WQCA: u16 = 0b0010;
-    pub const BKCA: u16 = 0b0100;
-    pub const BQCA: u16 = 0b1000;
-    pub const WK_MASK: u16 = 0b111_000_000_000_0000;
-    pub const WQ_MASK: u16 = 0b000_111_000_000_0000;
-    pub const BK_MASK: u16 = 0b000_000_111_000_0000;
-    pub const BQ_MASK: u16 = 0b000_000_000_111_0000;
-    pub const WK_SHIFT: u8 = 4 + 3 + 3 + 3;
-    pub const WQ_SHIFT: u8 = 4 + 3 + 3;
-    pub const BK_SHIFT: u8 = 4 + 3;
-    pub const BQ_SHIFT: u8 = 4;
-    pub const KEY_MASK: u16 = 0b1111;
-
+    const WKCA: u16 = 0b0001;
+    const WQCA: u16 = 0b0010;
+    const BKCA: u16 = 0b0100;
+    const BQCA: u16 = 0b1000;
+    const WK_MASK: u16 = 0b111_000_000_000_0000;
+    const WQ_MASK: u16 = 0b000_111_000_000_0000;
+    const BK_MASK: u16 = 0b000_000_111_000_0000;
+    const BQ_MASK: u16 = 0b000_000_000_111_0000;
+    const WK_SHIFT: u8 = 4 + 3 + 3 + 3;
+    const WQ_SHIFT: u8 = 4 + 3 + 3;
+    const BK_SHIFT: u8 = 4 + 3;
+    const BQ_SHIFT: u8 = 4;
+    const KEY_MASK: u16 = 0b1111;
+
+    /// Castling rights with the given rook files for each side, where `None` means the right has
+    /// been lost.
     pub const fn new(
         wk: Option<File>,
         wq: Option<File>,
@@ -575,10 +648,12 @@ impl CastlingRights {
         Self { data }
     }
 
+    /// The index of these rights in the castling Zobrist keys, from the presence of each right.
     pub const fn hashkey_index(self) -> usize {
         (self.data & Self::KEY_MASK) as usize
     }
 
+    /// Remove both of `C`'s castling rights.
     pub fn clear<C: Col>(&mut self) {
         self.data &= if C::WHITE {
             !(Self::WK_MASK | Self::WQ_MASK | Self::WKCA | Self::WQCA)
@@ -587,6 +662,7 @@ impl CastlingRights {
         };
     }
 
+    /// Remove one of `C`'s castling rights, on the kingside if `IS_KINGSIDE`.
     pub fn clear_side<const IS_KINGSIDE: bool, C: Col>(&mut self) {
         #![allow(clippy::collapsible_else_if)]
         self.data &= !if C::WHITE {
@@ -604,6 +680,7 @@ impl CastlingRights {
         };
     }
 
+    /// Remove `C`'s castling right with the rook on `file`, if there is one.
     pub fn remove<C: Col>(&mut self, file: File) {
         if self.kingside(C::COLOUR) == Some(file) {
             self.clear_side::<true, C>();
@@ -612,6 +689,7 @@ impl CastlingRights {
         }
     }
 
+    /// The file of the rook that `side` can castle kingside with, if it has the right.
     pub fn kingside(self, side: Colour) -> Option<File> {
         #![allow(clippy::cast_possible_truncation)]
         let presence = [Self::WKCA, Self::BKCA][side];
@@ -624,6 +702,7 @@ impl CastlingRights {
         File::from_index(value as u8)
     }
 
+    /// The file of the rook that `side` can castle queenside with, if it has the right.
     pub fn queenside(self, side: Colour) -> Option<File> {
         #![allow(clippy::cast_possible_truncation)]
         let presence = [Self::WQCA, Self::BQCA][side];
@@ -636,6 +715,7 @@ impl CastlingRights {
         File::from_index(value as u8)
     }
 
+    /// Give `side` the right to castle kingside with the rook on `file`.
     pub fn set_kingside(&mut self, side: Colour, file: File) {
         let presence = [Self::WKCA, Self::BKCA][side];
         let shift = [Self::WK_SHIFT, Self::BK_SHIFT][side];
@@ -645,6 +725,7 @@ impl CastlingRights {
         self.data |= (value << shift) | presence;
     }
 
+    /// Give `side` the right to castle queenside with the rook on `file`.
     pub fn set_queenside(&mut self, side: Colour, file: File) {
         let presence = [Self::WQCA, Self::BQCA][side];
         let shift = [Self::WQ_SHIFT, Self::BQ_SHIFT][side];
@@ -696,6 +777,32 @@ mod tests {
         assert_eq!(Square::A8.relative_to(Colour::Black), Square::A1);
     }
 
+    #[test]
+    fn square_edge_arithmetic() {
+        use super::{Colour, Square};
+
+        assert_eq!(Square::A1.sub(1), None);
+        assert_eq!(Square::A1.sub(8), None);
+        assert_eq!(Square::H8.add(1), None);
+        assert_eq!(Square::H8.add(u8::MAX), None);
+        assert_eq!(Square::A1.add(63), Some(Square::H8));
+        assert_eq!(Square::H8.sub(63), Some(Square::A1));
+
+        assert_eq!(Square::E2.forward(Colour::White), Some(Square::E3));
+        assert_eq!(Square::E2.forward(Colour::Black), Some(Square::E1));
+        assert_eq!(Square::E2.backward(Colour::White), Some(Square::E1));
+        assert_eq!(Square::E2.backward(Colour::Black), Some(Square::E3));
+
+        for sq in Square::all() {
+            let last_rank = sq.rank() == Rank::Eight;
+            let first_rank = sq.rank() == Rank::One;
+            assert_eq!(sq.forward(Colour::White).is_none(), last_rank);
+            assert_eq!(sq.backward(Colour::White).is_none(), first_rank);
+            assert_eq!(sq.forward(Colour::Black).is_none(), first_rank);
+            assert_eq!(sq.backward(Colour::Black).is_none(), last_rank);
+        }
+    }
+
     #[test]
     fn test_kingside_getters_and_setters() {
         let mut rights = CastlingRights::default();
@@ -1,16 +1,54 @@
 use std::path::PathBuf;
 
-use clap::Parser;
+use clap::{Parser, ValueEnum};
 
 #[derive(Parser)]
 #[clap(author, version, about)]
 #[allow(clippy::struct_excessive_bools, clippy::option_option)]
 pub struct Cli {
+    /// Use a named search-parameter preset (default, aggressive, or positional).
+    #[clap(long, global = true, value_name = "NAME")]
+    pub preset: Option<String>,
+    /// The protocol to speak when no sub-command is given.
+    #[clap(long, value_enum, default_value_t = Protocol::Uci)]
+    pub protocol: Protocol,
+    /// Restore the hash table from this file at startup, if it exists, and save it back on exit.
+    #[clap(long, value_name = "PATH")]
+    pub hash_file: Option<PathBuf>,
     /// All sub-commands that viri supports.
     #[clap(subcommand)]
     pub subcommand: Option<Subcommands>,
 }
 
+/// The communication protocols that viri supports.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, ValueEnum)]
+pub enum Protocol {
+    /// The Universal Chess Interface.
+    Uci,
+    /// The xboard protocol (CECP).
+    Xboard,
+}
+
+/// The perft suites that viri can check itself against.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, ValueEnum)]
+pub enum PerftSuite {
+    /// Positions from standard chess.
+    Classical,
+    /// Chess960 and double Chess960 positions.
+    Frc,
+    /// Both of the above.
+    All,
+}
+
+/// The orders in which datagen can take positions from its opening book.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, ValueEnum)]
+pub enum BookOrder {
+    /// Each position in turn, shared between all threads, starting over when the book runs out.
+    Sequential,
+    /// Positions drawn at random, independently by each thread.
+    Sampled,
+}
+
 #[derive(Parser)]
 pub enum Subcommands {
     /// Output node benchmark for openbench
@@ -20,8 +58,17 @@ pub enum Subcommands {
         /// Optionally specify the number of threads to use.
         threads: Option<usize>,
     },
-    /// Run the perft suite.
-    Perft,
+    /// Run the perft suite, or a divided perft on a single position.
+    Perft {
+        /// Position to run a divided perft on, as a FEN string. Runs the perft suite if omitted.
+        #[clap(requires = "depth")]
+        fen: Option<String>,
+        /// Depth of the divided perft.
+        depth: Option<usize>,
+        /// Which perft suites to run.
+        #[clap(long, value_enum, default_value_t = PerftSuite::All)]
+        suite: PerftSuite,
+    },
     /// Quantise a network parameter file.
     Quantise {
         /// Path to input network parameter file.
@@ -41,10 +88,29 @@ pub enum Subcommands {
         /// Path to output verbatim network file.
         output: PathBuf,
     },
-    /// Generate graphical visualisations of the NNUE weights.
-    VisNNUE,
+    /// Render the NNUE weights as PNG heatmaps: each hidden neuron's piece-square weights,
+    /// a composite of all of them, and a PSQT-like projection of each king bucket.
+    VisNNUE {
+        /// Directory to write the images to.
+        #[clap(long, default_value = "nnue-visualisations")]
+        output: PathBuf,
+    },
     /// Dry-run the NNUE inference.
     NNUEDryRun,
+    /// Play through random games, checking the incrementally updated accumulators against a
+    /// refresh at every node, and report the first divergence.
+    VerifyNNUE {
+        /// Number of games to play through.
+        #[clap(long, value_name = "N", default_value_t = 100)]
+        games: usize,
+        /// Seed for the random games, to reproduce a run.
+        #[clap(long, value_name = "N", default_value_t = 0)]
+        seed: u64,
+        /// Use random first-layer weights in place of the embedded network, so that every
+        /// feature contributes to the accumulators.
+        #[clap(long)]
+        random_weights: bool,
+    },
     /// Emit configuration for SPSA
     Spsa {
         /// Emit configuration in JSON format instead of openbench format
@@ -62,6 +128,27 @@ pub enum Subcommands {
         #[clap(short, long)]
         bucket: Option<usize>,
     },
+    /// Compare the network's evaluation against a plain material count over an EPD file.
+    CompareEval {
+        /// Path to input EPD file.
+        input: PathBuf,
+        /// Number of worst-disagreement positions to print.
+        #[clap(short, long, default_value_t = 10)]
+        worst: usize,
+    },
+    /// Build a Polyglot opening book from a PGN file, or an EPD file of consecutive game positions.
+    MakeBook {
+        /// Path to input PGN or EPD file.
+        input: PathBuf,
+        /// Path to output book file.
+        output: PathBuf,
+        /// Only record moves made before this game ply.
+        #[clap(long, default_value_t = 20)]
+        max_ply: usize,
+        /// Leave out moves played in fewer games than this.
+        #[clap(long, default_value_t = 1)]
+        min_games: u32,
+    },
     /// Count the number of positions contained within one or more packed game records.
     #[cfg(feature = "datagen")]
     CountPositions {
@@ -130,22 +217,69 @@ pub enum Subcommands {
     #[cfg(feature = "datagen")]
     Datagen {
         /// Number of games to play
-        #[clap(long, value_name = "N")]
-        games: usize,
+        #[clap(long, value_name = "N", required_unless_present = "resume")]
+        games: Option<usize>,
         /// Number of threads to parallelise datagen across
-        #[clap(long, value_name = "N")]
-        threads: usize,
+        #[clap(long, value_name = "N", required_unless_present = "resume")]
+        threads: Option<usize>,
         /// Path to a tablebases folder
         #[clap(long, value_name = "PATH")]
         tbs: Option<PathBuf>,
-        /// Path to a book file to use for starting positions
+        /// Path to a book file to use for starting positions, in EPD or PGN format
         #[clap(long, value_name = "PATH")]
         book: Option<PathBuf>,
+        /// Whether to take book positions in order, or sample them at random.
+        #[clap(long, value_enum, default_value_t = BookOrder::Sequential, requires = "book")]
+        book_order: BookOrder,
+        /// Number of games to play from each book position.
+        #[clap(long, value_name = "N", default_value_t = 1, requires = "book")]
+        book_games: usize,
         /// Number of nodes to search per position.
-        #[clap(long)]
-        nodes: u64,
+        #[clap(long, required_unless_present = "resume")]
+        nodes: Option<u64>,
         // Whether to generate DFRC data.
         #[clap(long)]
         dfrc: bool,
+        // Whether to generate FRC data, with the same back rank for both sides.
+        #[clap(long, conflicts_with = "dfrc")]
+        frc: bool,
+        /// Number of opening moves to make before the game proper, give or take one.
+        #[clap(long, value_name = "N", default_value_t = 8)]
+        random_plies: usize,
+        /// Discard openings that the engine evaluates beyond this many centipawns either way.
+        #[clap(long, value_name = "CP", default_value_t = 450)]
+        opening_eval_bound: i32,
+        /// Choose opening moves among the engine's best few, with this softmax temperature in
+        /// centipawns, instead of uniformly at random.
+        #[clap(long, value_name = "CP", default_value_t = 0)]
+        temperature: u32,
+        /// Seed for random opening selection, to reproduce a run. Random if omitted.
+        #[clap(long, value_name = "N")]
+        seed: Option<u64>,
+        /// Also write the games out as PGN, next to the packed game records.
+        #[clap(long)]
+        pgn: bool,
+        /// Resume the interrupted run in this directory, with the options it was started with.
+        #[clap(
+            long,
+            value_name = "DIR",
+            conflicts_with_all = [
+                "games",
+                "threads",
+                "tbs",
+                "book",
+                "book_order",
+                "book_games",
+                "nodes",
+                "dfrc",
+                "frc",
+                "random_plies",
+                "opening_eval_bound",
+                "temperature",
+                "seed",
+                "pgn"
+            ]
+        )]
+        resume: Option<PathBuf>,
     },
 }
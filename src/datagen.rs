@@ -1,6 +1,8 @@
 #![allow(dead_code)]
 
+mod checkpoint;
 mod dataformat;
+mod openings;
 
 use std::{
     array::{from_mut, from_ref},
@@ -8,9 +10,9 @@ use std::{
     cmp::Reverse,
     collections::HashMap,
     fmt::{Display, Formatter},
-    fs::{self, File},
+    fs::{self, File, OpenOptions},
     hash::Hash,
-    io::{BufRead, BufReader, BufWriter, Seek, Write},
+    io::{BufRead, BufReader, BufWriter, Seek, SeekFrom, Write},
     ops::ControlFlow,
     path::{Path, PathBuf},
     sync::{
@@ -22,38 +24,47 @@ use std::{
 
 use anyhow::{Context, anyhow, bail};
 use bulletformat::ChessBoard;
+use checkpoint::Checkpoint;
 use dataformat::Filter;
-use rand::{Rng, rngs::ThreadRng, seq::IndexedRandom};
+use openings::{BookState, OpeningBook};
 
 use crate::{
     chess::{
         board::{Board, DrawType, GameOutcome, Rules, WinType},
-        chessmove::Move,
-        fen::Fen,
+        pgn,
         piece::{Colour, PieceType},
         types::Square,
     },
+    cli::BookOrder,
     datagen::dataformat::Game,
-    evaluation::{is_decisive, is_mate_score},
+    evaluation::{MATE_SCORE, is_decisive, is_mate_score},
     nnue::network::{NNUEParams, NNUEState},
-    search::{parameters::Config, search_position, static_exchange_eval},
+    rng::XorShiftState,
+    search::{Score, parameters::Config, search_position},
     searchinfo::Control,
     tablebases::{self, probe::WDL},
-    threadlocal::make_thread_data,
+    threadlocal::{ThreadData, make_thread_data},
     threadpool,
     timemgmt::{SearchLimit, TimeManager},
     transpositiontable::Cache,
     util::MEGABYTE,
 };
 
-/// Number of attempts to find a good random move before just picking any random move.
-const RANDOM_MOVE_ATTEMPTS: usize = 8;
-/// Number of random moves to make from the root position in classical startpos or DFRC.
-const RANDOM_MOVES_ROOT: usize = 8;
+/// The default number of opening moves to make from the root position in classical
+/// startpos, FRC, or DFRC.
+const DEFAULT_RANDOM_PLIES: usize = 8;
+/// The default largest evaluation, in centipawns, of an opening that's kept.
+const DEFAULT_OPENING_EVAL_BOUND: i32 = 450;
 /// Number of random moves to make from a book position.
 const RANDOM_MOVES_BOOK: usize = 0;
 /// The SEE threshold for random move selection.
 const RANDOM_SEE_THRESHOLD: i32 = -1000;
+/// How many of the engine's best moves an opening move is chosen from, under a temperature.
+const OPENING_CANDIDATES: usize = 4;
+/// The node limit for each search that finds a candidate opening move.
+const OPENING_NODES: u64 = 2_000;
+/// How many games each thread plays between saving its progress.
+const CHECKPOINT_INTERVAL: usize = 64;
 
 /// Global atomic counter for tracking progress.
 static FENS_GENERATED: AtomicU64 = AtomicU64::new(0);
@@ -70,12 +81,29 @@ struct DataGenOptions {
     num_threads: usize,
     // The (optional) path to the directory containing syzygy endgame tablebases.
     tablebases_path: Option<PathBuf>,
-    // The (optional) path to an EPD format book to use for generating starting positions.
+    // The (optional) path to an EPD or PGN book to use for generating starting positions.
     book: Option<PathBuf>,
+    // Whether to take book positions in order, or sample them at random.
+    book_order: BookOrder,
+    // The number of games to play from each book position.
+    book_games: usize,
     // The node limit for searches.
     nodes: u64,
     // Whether to generate DFRC data.
     generate_dfrc: bool,
+    // Whether to generate FRC data, with the same back rank for both sides.
+    generate_frc: bool,
+    // The number of opening moves to make before the game proper, give or take one.
+    random_plies: usize,
+    // The largest evaluation, in centipawns, of an opening that's kept.
+    opening_eval_bound: i32,
+    // The temperature, in centipawns, for choosing opening moves among the engine's best.
+    // Zero makes uniformly random moves instead.
+    temperature: u32,
+    // The seed for random opening selection.
+    seed: u64,
+    // Whether to also write the games out as PGN.
+    pgn: bool,
 }
 
 /// Builder for datagen options.
@@ -86,12 +114,28 @@ pub struct DataGenOptionsBuilder {
     pub threads: usize,
     // The (optional) path to the directory containing syzygy endgame tablebases.
     pub tbs: Option<PathBuf>,
-    // The (optional) path to an EPD format book to use for generating starting positions.
+    // The (optional) path to an EPD or PGN book to use for generating starting positions.
     pub book: Option<PathBuf>,
+    // Whether to take book positions in order, or sample them at random.
+    pub book_order: BookOrder,
+    // The number of games to play from each book position.
+    pub book_games: usize,
     // The node limit for searches.
     pub nodes: u64,
     // Whether to generate DFRC data.
     pub dfrc: bool,
+    // Whether to generate FRC data, with the same back rank for both sides.
+    pub frc: bool,
+    // The number of opening moves to make before the game proper, give or take one.
+    pub random_plies: usize,
+    // The largest evaluation, in centipawns, of an opening that's kept.
+    pub opening_eval_bound: i32,
+    // The temperature, in centipawns, for choosing opening moves among the engine's best.
+    pub temperature: u32,
+    // The (optional) seed for random opening selection. Chosen at random if absent.
+    pub seed: Option<u64>,
+    // Whether to also write the games out as PGN.
+    pub pgn: bool,
 }
 
 impl DataGenOptionsBuilder {
@@ -101,8 +145,16 @@ impl DataGenOptionsBuilder {
             num_threads: self.threads,
             tablebases_path: self.tbs,
             book: self.book,
+            book_order: self.book_order,
+            book_games: self.book_games,
             nodes: self.nodes,
             generate_dfrc: self.dfrc,
+            generate_frc: self.frc,
+            random_plies: self.random_plies,
+            opening_eval_bound: self.opening_eval_bound,
+            temperature: self.temperature,
+            seed: self.seed.unwrap_or_else(rand::random),
+            pgn: self.pgn,
         }
     }
 }
@@ -115,8 +167,16 @@ impl DataGenOptions {
             num_threads: 1,
             tablebases_path: None,
             book: None,
+            book_order: BookOrder::Sequential,
+            book_games: 1,
             nodes: 25_000,
             generate_dfrc: true,
+            generate_frc: false,
+            random_plies: DEFAULT_RANDOM_PLIES,
+            opening_eval_bound: DEFAULT_OPENING_EVAL_BOUND,
+            temperature: 0,
+            seed: 0,
+            pgn: false,
         }
     }
 
@@ -133,16 +193,15 @@ impl DataGenOptions {
             },
             if self.generate_dfrc {
                 "dfrc"
+            } else if self.generate_frc {
+                "frc"
             } else {
                 "classical"
             },
             self.nodes,
             self.book.as_ref().map_or_else(String::new, |book| format!(
                 "-{}",
-                book.file_name()
-                    .unwrap()
-                    .to_string_lossy()
-                    .trim_end_matches(".epd")
+                book.file_stem().unwrap().to_string_lossy()
             ))
         )
     }
@@ -150,114 +209,222 @@ impl DataGenOptions {
 
 trait StartposGenerator {
     fn generate(&mut self, board: &mut Board, conf: &Config) -> ControlFlow<(), ()>;
-}
-
-/// Make a random move on the board, attempting to pick only
-/// moves with a static exchange evaluation above the given threshold.
-/// If no such move is found, a random legal move is made.
-/// If there are no legal moves, None is returned.
-fn make_random_move(
-    rng: &mut ThreadRng,
-    board: &mut Board,
-    conf: &Config,
-    see_threshold: i32,
-) -> Option<Move> {
-    let legal_moves = board.legal_moves();
-    for _ in 0..RANDOM_MOVE_ATTEMPTS {
-        let m = *legal_moves.choose(rng)?;
-        if static_exchange_eval(board, conf, m, see_threshold) {
-            assert!(board.is_legal(m));
-            board.make_move_simple(m);
-            return Some(m);
-        }
-    }
-    let m = *legal_moves.choose(rng)?;
-    assert!(board.is_legal(m));
-    board.make_move_simple(m);
-    Some(m)
+    /// The random number generator behind the openings, which is saved in checkpoints.
+    fn rng(&mut self) -> &mut XorShiftState;
 }
 
 struct ClassicalStartposGenerator {
-    rng: ThreadRng,
+    rng: XorShiftState,
+    plies: usize,
 }
 
 struct DFRCStartposGenerator {
-    rng: ThreadRng,
+    rng: XorShiftState,
+    plies: usize,
+}
+
+struct FRCStartposGenerator {
+    rng: XorShiftState,
+    plies: usize,
 }
 
 struct BookStartposGenerator<'a> {
-    rng: ThreadRng,
-    source: &'a [&'a str],
+    rng: XorShiftState,
+    book: &'a OpeningBook,
+    state: BookState,
     cursor: &'a AtomicUsize,
 }
 
 impl StartposGenerator for ClassicalStartposGenerator {
+    fn rng(&mut self) -> &mut XorShiftState {
+        &mut self.rng
+    }
+
     fn generate(&mut self, board: &mut Board, conf: &Config) -> ControlFlow<(), ()> {
         board.set_startpos();
 
-        for _ in 0..RANDOM_MOVES_ROOT + usize::from(self.rng.random_bool(0.5)) {
-            let res = make_random_move(&mut self.rng, board, conf, RANDOM_SEE_THRESHOLD);
-            if res.is_none() {
-                return ControlFlow::Break(());
-            }
-            if board.outcome().is_some() {
-                return ControlFlow::Break(());
-            }
-        }
-
-        ControlFlow::Continue(())
+        let plies = opening_plies(self.plies, &mut self.rng);
+        make_random_moves(board, &mut self.rng, conf, plies)
     }
 }
 
 impl StartposGenerator for DFRCStartposGenerator {
+    fn rng(&mut self) -> &mut XorShiftState {
+        &mut self.rng
+    }
+
     fn generate(&mut self, board: &mut Board, conf: &Config) -> ControlFlow<(), ()> {
-        board.set_dfrc_idx(rand::Rng::random_range(&mut self.rng, 0..960 * 960));
+        #[allow(clippy::cast_possible_truncation)]
+        board.set_dfrc_idx(self.rng.next_below(960 * 960) as usize);
 
-        for _ in 0..RANDOM_MOVES_ROOT + usize::from(self.rng.random_bool(0.5)) {
-            let res = make_random_move(&mut self.rng, board, conf, RANDOM_SEE_THRESHOLD);
-            if res.is_none() {
-                return ControlFlow::Break(());
-            }
-            if board.outcome().is_some() {
-                return ControlFlow::Break(());
-            }
-        }
+        let plies = opening_plies(self.plies, &mut self.rng);
+        make_random_moves(board, &mut self.rng, conf, plies)
+    }
+}
+
+impl StartposGenerator for FRCStartposGenerator {
+    fn rng(&mut self) -> &mut XorShiftState {
+        &mut self.rng
+    }
+
+    fn generate(&mut self, board: &mut Board, conf: &Config) -> ControlFlow<(), ()> {
+        #[allow(clippy::cast_possible_truncation)]
+        board.set_frc_idx(self.rng.next_below(960) as usize);
 
-        ControlFlow::Continue(())
+        let plies = opening_plies(self.plies, &mut self.rng);
+        make_random_moves(board, &mut self.rng, conf, plies)
     }
 }
 
 impl StartposGenerator for BookStartposGenerator<'_> {
+    fn rng(&mut self) -> &mut XorShiftState {
+        &mut self.rng
+    }
+
     fn generate(&mut self, board: &mut Board, conf: &Config) -> ControlFlow<(), ()> {
-        let idx = self.cursor.fetch_add(1, Ordering::Relaxed);
-        if idx >= self.source.len() {
-            println!("Book exhausted!");
-            self.cursor.store(0, Ordering::Relaxed);
+        let Some(fen) = self.book.next(self.cursor, &mut self.state, &mut self.rng) else {
+            return ControlFlow::Break(());
+        };
+        board.set_from_fen(fen);
+
+        make_random_moves(board, &mut self.rng, conf, RANDOM_MOVES_BOOK)
+    }
+}
+
+/// The number of opening moves for one game: `plies`, or one more half the time, so that
+/// either side can be to move when the game proper starts.
+fn opening_plies(plies: usize, rng: &mut XorShiftState) -> usize {
+    if plies == 0 {
+        0
+    } else {
+        plies + usize::from(rng.next_bool())
+    }
+}
+
+/// Make `plies` random moves, avoiding ones that lose material where possible.
+/// Breaks if the game ends along the way.
+fn make_random_moves(
+    board: &mut Board,
+    rng: &mut XorShiftState,
+    conf: &Config,
+    plies: usize,
+) -> ControlFlow<(), ()> {
+    for _ in 0..plies {
+        let res = board.make_random_move_seeded(rng, conf, RANDOM_SEE_THRESHOLD);
+        if res.is_none() {
             return ControlFlow::Break(());
         }
-        let fen_str = self.source[idx];
-        let fen = Fen::parse(fen_str)
-            .with_context(|| format!("Failed to parse FEN {fen_str} at index {idx} in book."))
-            .unwrap();
-        board.set_from_fen(&fen);
+        if board.outcome().is_some() {
+            return ControlFlow::Break(());
+        }
+    }
 
-        #[allow(clippy::reversed_empty_ranges)]
-        for _ in 0..RANDOM_MOVES_BOOK {
-            let res = make_random_move(&mut self.rng, board, conf, RANDOM_SEE_THRESHOLD);
-            if res.is_none() {
-                return ControlFlow::Break(());
-            }
-            if board.outcome().is_some() {
-                return ControlFlow::Break(());
-            }
+    ControlFlow::Continue(())
+}
+
+/// Make `plies` opening moves, each chosen from the engine's best few moves in a short
+/// search, with probabilities given by a softmax over their scores at `temperature`.
+/// Breaks if the game ends along the way.
+fn make_temperature_moves(
+    board: &mut Board,
+    td: &mut Box<ThreadData>,
+    worker_thread: &threadpool::WorkerThread,
+    rng: &mut XorShiftState,
+    plies: usize,
+    temperature: u32,
+) -> ControlFlow<(), ()> {
+    let temp_limit = td.info.clock.limit().clone();
+    td.info.clock.set_limit(SearchLimit::SoftNodes {
+        soft_limit: OPENING_NODES,
+        hard_limit: OPENING_NODES * 8,
+    });
+    let mut flow = ControlFlow::Continue(());
+    for _ in 0..plies {
+        // find the best few moves by searching again with each one found left out.
+        let mut remaining = board.legal_moves().to_vec();
+        let mut candidates = Vec::with_capacity(OPENING_CANDIDATES);
+        while candidates.len() < OPENING_CANDIDATES && !remaining.is_empty() {
+            td.board = board.clone();
+            td.searchmoves.clone_from(&remaining);
+            let (score, best_move) = search_position(from_ref(worker_thread), from_mut(td));
+            let Some(best_move) = best_move.filter(|m| remaining.contains(m)) else {
+                break;
+            };
+            // scores come back from white's point of view.
+            let score = centipawns(if board.turn() == Colour::White {
+                score
+            } else {
+                -score
+            });
+            candidates.push((best_move, score));
+            remaining.retain(|&m| m != best_move);
+        }
+        td.searchmoves.clear();
+        if candidates.is_empty() {
+            flow = ControlFlow::Break(());
+            break;
+        }
+        let scores = candidates
+            .iter()
+            .map(|&(_, score)| score)
+            .collect::<Vec<_>>();
+        let (m, _) = candidates[temperature_choice(&scores, temperature, rng)];
+        board.make_move_simple(m);
+        if board.outcome().is_some() {
+            flow = ControlFlow::Break(());
+            break;
         }
+    }
+    td.info.clock.set_limit(temp_limit);
+    flow
+}
 
-        ControlFlow::Continue(())
+/// Convert a score from `search_position` to centipawns, placing mates beyond every
+/// other score, with shorter mates further out.
+fn centipawns(score: i32) -> i32 {
+    match Score::from_internal(score) {
+        Score::Centipawns(cp) => cp,
+        Score::Mate(moves) => moves.signum() * (MATE_SCORE - moves.abs()),
     }
 }
 
-#[allow(clippy::too_many_lines)]
+/// Pick an index into `scores`, in centipawns, at random, with each chosen with probability proportional
+/// to `exp(score / temperature)`, so that the best scores are picked most often.
+fn temperature_choice(scores: &[i32], temperature: u32, rng: &mut XorShiftState) -> usize {
+    #![allow(clippy::cast_precision_loss)]
+    let best = scores.iter().copied().max().unwrap_or(0);
+    let weights = scores
+        .iter()
+        .map(|&score| (f64::from(score - best) / f64::from(temperature.max(1))).exp())
+        .collect::<Vec<_>>();
+    let total = weights.iter().sum::<f64>();
+    let mut target = rng.next() as f64 / u64::MAX as f64 * total;
+    for (i, weight) in weights.iter().enumerate() {
+        if target < *weight {
+            return i;
+        }
+        target -= weight;
+    }
+    scores.len().saturating_sub(1)
+}
+
 pub fn gen_data_main(cli_config: DataGenOptionsBuilder) -> anyhow::Result<()> {
+    run_datagen(&cli_config.build(), None)
+}
+
+/// Carry on with the interrupted run in `data_dir`, with the options it was started with.
+pub fn resume_data_main(data_dir: &Path) -> anyhow::Result<()> {
+    let options = checkpoint::load_options(data_dir).with_context(|| {
+        format!(
+            "Failed to read the options of the run in {}",
+            data_dir.display()
+        )
+    })?;
+    run_datagen(&options, Some(data_dir))
+}
+
+#[allow(clippy::too_many_lines)]
+fn run_datagen(options: &DataGenOptions, resume_dir: Option<&Path>) -> anyhow::Result<()> {
     if !cfg!(feature = "datagen") {
         bail!("datagen feature not enabled (compile with --features datagen)");
     }
@@ -271,11 +438,10 @@ pub fn gen_data_main(cli_config: DataGenOptionsBuilder) -> anyhow::Result<()> {
     let nnue_params = NNUEParams::decompress_and_alloc()?;
     let control = Control::default();
 
-    let options: DataGenOptions = cli_config.build();
-
-    control
-        .chess960
-        .store(options.generate_dfrc, Ordering::Relaxed);
+    control.chess960.store(
+        options.generate_dfrc || options.generate_frc,
+        Ordering::Relaxed,
+    );
     FENS_GENERATED.store(0, Ordering::Relaxed);
 
     println!("Starting data generation with the following configuration:");
@@ -290,60 +456,106 @@ pub fn gen_data_main(cli_config: DataGenOptionsBuilder) -> anyhow::Result<()> {
     if let Some(tb_path) = &options.tablebases_path {
         let tb_path = tb_path.to_string_lossy();
         tablebases::probe::init(&tb_path, &control);
-        println!("Syzygy tablebases enabled.");
+        // games are adjudicated on entering the tablebases, so a run that can't probe them
+        // would quietly produce longer games with noisier labels.
+        let max_pieces = tablebases::probe::get_max_pieces_count(&control);
+        if max_pieces == 0 {
+            bail!(
+                "No Syzygy tablebases could be loaded from {tb_path}. Check the path, and that this build has the \"syzygy\" feature enabled."
+            );
+        }
+        println!("Syzygy tablebases enabled, adjudicating games with up to {max_pieces} pieces.");
     }
 
-    // create a new unique identifier for this generation run
-    // this is used to create a unique directory for the data
-    // and to name the data files.
-    // the ID is formed by taking the current date and time,
-    // plus a compressed representation of the options struct.
-    let run_id = format!(
-        "run_{}_{}",
-        chrono::Utc::now().format("%Y-%m-%d_%H-%M-%S"),
-        options.summary(&control)
-    );
-    println!("This run will be saved to the directory \"data/{run_id}\"");
-    println!("Each thread will save its data to a separate file in this directory.");
+    let data_dir = if let Some(data_dir) = resume_dir {
+        println!(
+            "Resuming the run in the directory \"{}\"",
+            data_dir.display()
+        );
+        data_dir.to_path_buf()
+    } else {
+        // create a new unique identifier for this generation run
+        // this is used to create a unique directory for the data
+        // and to name the data files.
+        // the ID is formed by taking the current date and time,
+        // plus a compressed representation of the options struct.
+        let run_id = format!(
+            "run_{}_{}",
+            chrono::Utc::now().format("%Y-%m-%d_%H-%M-%S"),
+            options.summary(&control)
+        );
+        println!("This run will be saved to the directory \"data/{run_id}\"");
+        println!("Each thread will save its data to a separate file in this directory.");
+
+        // create the directory for the data
+        let data_dir = PathBuf::from("data").join(run_id);
+        std::fs::create_dir_all(&data_dir).with_context(|| "Failed to create data directory")?;
+        checkpoint::save_options(options, &data_dir)?;
+        data_dir
+    };
 
-    // create the directory for the data
-    let data_dir = PathBuf::from("data").join(run_id);
-    std::fs::create_dir_all(&data_dir).with_context(|| "Failed to create data directory")?;
+    // when resuming, each thread picks up from its last checkpoint.
+    let checkpoints = (0..options.num_threads)
+        .map(|id| {
+            if resume_dir.is_some() {
+                Checkpoint::load(&Checkpoint::path(&data_dir, id))
+            } else {
+                Ok(None)
+            }
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
 
     let mut counters = Vec::new();
-    let book_positions = options
+    let book = options
         .book
         .as_deref()
-        .map(|book| fs::read_to_string(book).with_context(|| "Failed to read book file."))
+        .map(|path| OpeningBook::load(path, options.book_order, options.book_games))
         .transpose()?;
-    let book_positions = book_positions
-        .as_deref()
-        .map(|book| book.lines().collect::<Vec<_>>());
-    let cursor = AtomicUsize::new(0);
-    let book_positions = book_positions.as_deref();
+    if let Some(book) = &book {
+        println!("Loaded {} opening positions from the book.", book.len());
+    }
+    // book positions used by games after a checkpoint are skipped rather than reused.
+    let cursor = AtomicUsize::new(
+        checkpoints
+            .iter()
+            .flatten()
+            .map(|c| c.book_cursor)
+            .max()
+            .unwrap_or(0),
+    );
+    let book = book.as_ref();
     let cursor = &cursor;
     std::thread::scope(|s| {
         let thread_handles = (0..options.num_threads)
             .map(|id| {
-                let opt_ref = &options;
+                let opt_ref = options;
                 let path_ref = &data_dir;
                 let nnue_params_ref = &nnue_params;
                 let control_ref = &control;
+                let checkpoint = checkpoints[id];
                 s.spawn(move || {
-                    // this rng is different between each thread
-                    // (https://rust-random.github.io/book/guide-parallel.html)
-                    // so no worries :3
-                    let rng = rand::rng();
-                    let startpos_src = if let Some(source) = book_positions {
+                    // each thread gets its own stream, derived from the run seed,
+                    // so that a run with the same seed and thread count is reproducible.
+                    let rng = XorShiftState::with_seed(opt_ref.seed.wrapping_add(id as u64));
+                    // with a temperature, the opening moves are chosen by the engine instead.
+                    let plies = if opt_ref.temperature == 0 {
+                        opt_ref.random_plies
+                    } else {
+                        0
+                    };
+                    let startpos_src = if let Some(book) = book {
                         Box::new(BookStartposGenerator {
                             rng,
-                            source,
+                            book,
+                            state: BookState::default(),
                             cursor,
                         }) as Box<_>
                     } else if opt_ref.generate_dfrc {
-                        Box::new(DFRCStartposGenerator { rng }) as Box<_>
+                        Box::new(DFRCStartposGenerator { rng, plies }) as Box<_>
+                    } else if opt_ref.generate_frc {
+                        Box::new(FRCStartposGenerator { rng, plies }) as Box<_>
                     } else {
-                        Box::new(ClassicalStartposGenerator { rng }) as Box<_>
+                        Box::new(ClassicalStartposGenerator { rng, plies }) as Box<_>
                     };
                     generate_on_thread(
                         id,
@@ -352,6 +564,8 @@ pub fn gen_data_main(cli_config: DataGenOptionsBuilder) -> anyhow::Result<()> {
                         nnue_params_ref,
                         control_ref,
                         startpos_src,
+                        checkpoint,
+                        cursor,
                     )
                 })
             })
@@ -406,7 +620,58 @@ impl From<WDL> for GameOutcome {
     }
 }
 
-#[allow(clippy::too_many_lines)]
+/// Open an output file: a new one, or, when resuming, the existing one cut back to the
+/// length it had at the checkpoint.
+fn open_output(path: &Path, resume_len: Option<u64>) -> anyhow::Result<File> {
+    let Some(len) = resume_len else {
+        return File::create(path)
+            .with_context(|| format!("Failed to create output file at {}", path.display()));
+    };
+    let mut file = OpenOptions::new()
+        .write(true)
+        .open(path)
+        .with_context(|| format!("Failed to open output file at {}", path.display()))?;
+    let found = file.metadata()?.len();
+    if found < len {
+        bail!(
+            "{} is {found} bytes, shorter than the {len} bytes recorded in its checkpoint",
+            path.display()
+        );
+    }
+    file.set_len(len)?;
+    file.seek(SeekFrom::End(0))?;
+    Ok(file)
+}
+
+/// Flush a thread's output, and record that it has finished `games` games.
+fn save_checkpoint(
+    path: &Path,
+    games: usize,
+    rng: &XorShiftState,
+    output: &mut BufWriter<&mut File>,
+    pgn: Option<&mut BufWriter<File>>,
+    cursor: &AtomicUsize,
+) -> anyhow::Result<()> {
+    output.flush()?;
+    output.get_ref().sync_data()?;
+    let pgn_bytes = if let Some(pgn) = pgn {
+        pgn.flush()?;
+        pgn.get_ref().sync_data()?;
+        pgn.get_ref().metadata()?.len()
+    } else {
+        0
+    };
+    Checkpoint {
+        games,
+        rng: rng.state,
+        data_bytes: output.get_ref().metadata()?.len(),
+        pgn_bytes,
+        book_cursor: cursor.load(Ordering::Relaxed),
+    }
+    .save(path)
+}
+
+#[allow(clippy::too_many_lines, clippy::too_many_arguments)]
 fn generate_on_thread<'a>(
     id: usize,
     options: &DataGenOptions,
@@ -414,6 +679,8 @@ fn generate_on_thread<'a>(
     nnue_params: &'static NNUEParams,
     control: &'a Control,
     mut startpos_src: Box<dyn StartposGenerator + 'a>,
+    checkpoint: Option<Checkpoint>,
+    cursor: &AtomicUsize,
 ) -> anyhow::Result<HashMap<GameOutcome, u64>> {
     // Datagen uses the default configuration:
     let conf = Config::default();
@@ -476,17 +743,57 @@ fn generate_on_thread<'a>(
 
     let n_games_to_run = std::cmp::max(options.num_games / options.num_threads, 1);
 
-    let mut output_file = File::create(data_dir.join(format!("thread_{id}.bin")))
-        .with_context(|| "Failed to create output file.")?;
+    let first_game = checkpoint.map_or(0, |c| c.games);
+    if let Some(checkpoint) = checkpoint {
+        startpos_src.rng().state = checkpoint.rng;
+        println!("Thread {id} resuming from game {first_game}.");
+    }
+    let checkpoint_path = Checkpoint::path(data_dir, id);
+
+    let mut output_file = open_output(
+        &data_dir.join(format!("thread_{id}.bin")),
+        checkpoint.map(|c| c.data_bytes),
+    )?;
     let mut output_buffer = BufWriter::new(&mut output_file);
+    let mut pgn_buffer = if options.pgn {
+        let pgn_file = open_output(
+            &data_dir.join(format!("thread_{id}.pgn")),
+            checkpoint.map(|c| c.pgn_bytes),
+        )?;
+        Some(BufWriter::new(pgn_file))
+    } else {
+        None
+    };
+    let event = format!(
+        "viridithas datagen {}",
+        data_dir.file_name().unwrap_or_default().to_string_lossy()
+    );
+    let date = chrono::Utc::now().format("%Y.%m.%d").to_string();
 
     let mut counters = HashMap::<GameOutcome, u64>::new();
 
     let start = Instant::now();
-    'generation_main_loop: for game in 0..n_games_to_run {
+    let mut games_completed = n_games_to_run;
+    'generation_main_loop: for game_index in first_game..n_games_to_run {
         // report progress
-        if id == 0 && game % 32 == 0 && game > 0 {
-            print_progress(n_games_to_run, &counters, start, game)?;
+        if id == 0 && game_index % 32 == 0 && game_index > first_game {
+            print_progress(
+                n_games_to_run - first_game,
+                &counters,
+                start,
+                game_index - first_game,
+            )?;
+        }
+        // save progress, so that an interrupted run can pick up from here.
+        if game_index % CHECKPOINT_INTERVAL == 0 && game_index > first_game {
+            save_checkpoint(
+                &checkpoint_path,
+                game_index,
+                startpos_src.rng(),
+                &mut output_buffer,
+                pgn_buffer.as_mut(),
+                cursor,
+            )?;
         }
         // reset everything: board, thread data, tt, search info
         for (tt, td) in tts.iter().zip(thread_data.iter_mut()) {
@@ -500,6 +807,21 @@ fn generate_on_thread<'a>(
             ControlFlow::Break(()) => continue 'generation_main_loop,
             ControlFlow::Continue(()) => {}
         }
+        if options.temperature > 0 {
+            let plies = opening_plies(options.random_plies, startpos_src.rng());
+            let td = &mut thread_data[startpos.turn()];
+            let flow = make_temperature_moves(
+                &mut startpos,
+                td,
+                &worker_thread,
+                startpos_src.rng(),
+                plies,
+                options.temperature,
+            );
+            if flow.is_break() {
+                continue 'generation_main_loop;
+            }
+        }
 
         // set up both players with the starting position
         let [white, black] = &mut thread_data;
@@ -519,7 +841,7 @@ fn generate_on_thread<'a>(
         });
         let eval = search_position(from_ref(&worker_thread), from_mut(td)).0;
         td.info.clock.set_limit(temp_limit);
-        if eval.abs() > 1000 {
+        if centipawns(eval).abs() > options.opening_eval_bound {
             // if the position is too good or too bad, we don't want it
             continue 'generation_main_loop;
         }
@@ -614,19 +936,48 @@ fn generate_on_thread<'a>(
         // write to file
         game.serialise_into(&mut output_buffer)
             .with_context(|| "Failed to serialise game into output buffer.")?;
+        if let Some(pgn_buffer) = &mut pgn_buffer {
+            let round = (game_index + 1).to_string();
+            let evals = game
+                .buffer()
+                .iter()
+                .map(|&(_, eval)| eval.get().to_string())
+                .collect::<Vec<_>>();
+            pgn::write_game(
+                pgn_buffer,
+                &[
+                    ("Event", &event),
+                    ("Site", "NA"),
+                    ("Date", &date),
+                    ("Round", &round),
+                    ("White", "Viridithas"),
+                    ("Black", "Viridithas"),
+                ],
+                &game.initial_position(),
+                game.moves().zip(evals.iter().map(|e| Some(e.as_str()))),
+                Some(game.outcome()),
+            )?;
+        }
 
         // STEP 5: update the game outcome statistics
         *counters.entry(outcome).or_default() += 1;
 
         // STEP 6: check if we should stop because the STOP_GENERATION signal was set.
         if STOP_GENERATION.load(Ordering::SeqCst) {
+            games_completed = game_index + 1;
             break 'generation_main_loop;
         }
     }
 
-    output_buffer
-        .flush()
-        .with_context(|| "Failed to flush output buffer to file.")?;
+    save_checkpoint(
+        &checkpoint_path,
+        games_completed,
+        startpos_src.rng(),
+        &mut output_buffer,
+        pgn_buffer.as_mut(),
+        cursor,
+    )
+    .with_context(|| "Failed to flush output to file.")?;
 
     Ok(counters)
 }
@@ -685,6 +1036,7 @@ fn show_boot_info(options: &DataGenOptions) {
     println!("To start data generation, type \"start\" or \"go\".");
 }
 
+#[allow(clippy::too_many_lines)]
 fn config_loop(mut options: DataGenOptions) -> anyhow::Result<DataGenOptions> {
     println!();
     let mut user_input = String::new();
@@ -773,13 +1125,29 @@ fn config_loop(mut options: DataGenOptions) -> anyhow::Result<DataGenOptions> {
             "dfrc" => {
                 if let Ok(dfrc) = value.parse::<bool>() {
                     options.generate_dfrc = dfrc;
+                    options.generate_frc &= !dfrc;
                 } else {
                     eprintln!("Invalid value for dfrc, must be a boolean");
                 }
             }
+            "frc" => {
+                if let Ok(frc) = value.parse::<bool>() {
+                    options.generate_frc = frc;
+                    options.generate_dfrc &= !frc;
+                } else {
+                    eprintln!("Invalid value for frc, must be a boolean");
+                }
+            }
+            "pgn" => {
+                if let Ok(pgn) = value.parse::<bool>() {
+                    options.pgn = pgn;
+                } else {
+                    eprintln!("Invalid value for pgn, must be a boolean");
+                }
+            }
             other => {
                 eprintln!(
-                    "Invalid parameter (\"{other}\"), supported parameters are \"num_games\", \"num_threads\", \"tablebases_path\", \"use_nnue\", and \"nodes\"."
+                    "Invalid parameter (\"{other}\"), supported parameters are \"num_games\", \"num_threads\", \"tablebases_path\", \"use_nnue\", \"nodes\", and \"pgn\"."
                 );
             }
         }
@@ -800,8 +1168,23 @@ impl Display for DataGenOptions {
                 .as_ref()
                 .map_or_else(|| "None".into(), |path| path.to_string_lossy())
         )?;
+        if let Some(book) = &self.book {
+            writeln!(
+                f,
+                " |> book: {} ({:?}, {} games per position)",
+                book.display(),
+                self.book_order,
+                self.book_games
+            )?;
+        }
         writeln!(f, " |> limit: {} nodes", self.nodes)?;
+        writeln!(f, " |> random_plies: {}", self.random_plies)?;
+        writeln!(f, " |> opening_eval_bound: {}", self.opening_eval_bound)?;
+        writeln!(f, " |> temperature: {}", self.temperature)?;
         writeln!(f, " |> dfrc: {}", self.generate_dfrc)?;
+        writeln!(f, " |> frc: {}", self.generate_frc)?;
+        writeln!(f, " |> seed: {}", self.seed)?;
+        writeln!(f, " |> pgn: {}", self.pgn)?;
         if self.tablebases_path.is_none() {
             writeln!(
                 f,
@@ -917,24 +1300,7 @@ pub fn run_topgn(
         .file_name()
         .with_context(|| "Failed to get filename.")?
         .to_string_lossy();
-    let make_header = |outcome: WDL, fen: String| {
-        format!(
-            r#"[Event "datagen id {}"]
-[Site "NA"]
-[Date "NA"]
-[White "Viridithas"]
-[Black "Viridithas"]
-[Result "{}"]
-[FEN "{}"]"#,
-            file_name,
-            match outcome {
-                WDL::Win => "1-0",
-                WDL::Loss => "0-1",
-                WDL::Draw => "1/2-1/2",
-            },
-            fen
-        )
-    };
+    let event = format!("datagen id {file_name}");
 
     println!("Converting to PGN...");
     let mut move_buffer = Vec::new();
@@ -942,44 +1308,24 @@ pub fn run_topgn(
     while let Ok(game) =
         dataformat::Game::deserialise_from(&mut input_buffer, std::mem::take(&mut move_buffer))
     {
-        let outcome = game.outcome();
-        let mut board = game.initial_position();
-        let header = make_header(outcome, board.to_string());
-        writeln!(output_buffer, "{header}").unwrap();
-        let mut fullmoves = 0;
-        for &(mv, eval) in game.buffer() {
-            if fullmoves % 12 == 0 && board.turn() == Colour::White {
-                writeln!(output_buffer).unwrap();
-            }
-            let san = board.san(mv).with_context(|| {
-                format!(
-                    "Failed to create SAN for move {} in position {board:X}.",
-                    mv.display(board.rules())
-                )
-            })?;
-            if board.turn() == Colour::White {
-                write!(output_buffer, "{}. ", board.ply() / 2 + 1).unwrap();
-            } else {
-                fullmoves += 1;
-            }
-            if annotate {
-                write!(output_buffer, "{san} {{{eval}}} ", eval = eval.get()).unwrap();
-            } else {
-                write!(output_buffer, "{san} ").unwrap();
-            }
-            board.make_move_simple(mv);
-        }
-
-        write!(
-            output_buffer,
-            "{}\n\n",
-            match outcome {
-                WDL::Win => "1-0",
-                WDL::Loss => "0-1",
-                WDL::Draw => "1/2-1/2",
-            }
-        )
-        .unwrap();
+        let evals = game
+            .buffer()
+            .iter()
+            .map(|&(_, eval)| annotate.then(|| eval.get().to_string()))
+            .collect::<Vec<_>>();
+        pgn::write_game(
+            &mut output_buffer,
+            &[
+                ("Event", &event),
+                ("Site", "NA"),
+                ("Date", "NA"),
+                ("White", "Viridithas"),
+                ("Black", "Viridithas"),
+            ],
+            &game.initial_position(),
+            game.moves().zip(evals.iter().map(Option::as_deref)),
+            Some(game.outcome()),
+        )?;
 
         move_buffer = game.into_move_buffer();
         game_count += 1;
@@ -1564,4 +1910,18 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn temperature_favours_better_moves() {
+        let scores = [40, 20, -300];
+        let mut rng = crate::rng::XorShiftState::with_seed(3);
+        let mut picks = [0; 3];
+        for _ in 0..3000 {
+            picks[super::temperature_choice(&scores, 50, &mut rng)] += 1;
+        }
+        assert!(picks[0] > picks[1] && picks[1] > picks[2], "{picks:?}");
+        assert!(picks[1] > 0);
+        // a very low temperature always plays the best move.
+        assert!((0..100).all(|_| super::temperature_choice(&scores, 1, &mut rng) == 0));
+    }
 }
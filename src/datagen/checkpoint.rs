@@ -0,0 +1,213 @@
+//! Saving the progress of a datagen run, so that an interrupted run can be resumed.
+//!
+//! A run directory holds the run's options, and a checkpoint for each thread recording how
+//! many games it had finished, how long its output files were at that point, and the state of
+//! its random number generator. Resuming truncates each output file back to its checkpoint
+//! and carries on from there, so no game is lost or written twice.
+//!
+//! Both files are plain `key value` lines, and are replaced atomically by writing to a
+//! temporary file and renaming it over the old one.
+
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::Write,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use anyhow::{Context, anyhow};
+use clap::ValueEnum;
+
+use crate::cli::BookOrder;
+
+use super::DataGenOptions;
+
+/// The name of the file holding a run's options.
+const OPTIONS_FILE: &str = "options.txt";
+
+/// How far a datagen thread had got when it last saved its progress.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub struct Checkpoint {
+    /// The number of games the thread had finished, including skipped ones.
+    pub games: usize,
+    /// The state of the thread's random number generator.
+    pub rng: u128,
+    /// The length of the thread's packed game record.
+    pub data_bytes: u64,
+    /// The length of the thread's PGN file, if it writes one.
+    pub pgn_bytes: u64,
+    /// How far through the book the run had got.
+    pub book_cursor: usize,
+}
+
+impl Checkpoint {
+    /// The path of the checkpoint for thread `id`.
+    pub fn path(data_dir: &Path, id: usize) -> PathBuf {
+        data_dir.join(format!("thread_{id}.checkpoint"))
+    }
+
+    /// Replace the checkpoint at `path` with this one.
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        write_fields(
+            path,
+            &[
+                ("games", self.games.to_string()),
+                ("rng", format!("{:032x}", self.rng)),
+                ("data_bytes", self.data_bytes.to_string()),
+                ("pgn_bytes", self.pgn_bytes.to_string()),
+                ("book_cursor", self.book_cursor.to_string()),
+            ],
+        )
+    }
+
+    /// Read the checkpoint at `path`, or `None` if the thread never saved one.
+    pub fn load(path: &Path) -> anyhow::Result<Option<Self>> {
+        if !path.exists() {
+            return Ok(None);
+        }
+        let fields = read_fields(path)?;
+        Ok(Some(Self {
+            games: field(&fields, "games")?,
+            rng: u128::from_str_radix(raw_field(&fields, "rng")?, 16)
+                .with_context(|| "invalid rng state in checkpoint")?,
+            data_bytes: field(&fields, "data_bytes")?,
+            pgn_bytes: field(&fields, "pgn_bytes")?,
+            book_cursor: field(&fields, "book_cursor")?,
+        }))
+    }
+}
+
+/// Record the options of the run in `data_dir`.
+pub fn save_options(options: &DataGenOptions, data_dir: &Path) -> anyhow::Result<()> {
+    let path_field = |path: &Option<PathBuf>| {
+        path.as_ref()
+            .map_or_else(String::new, |p| p.to_string_lossy().into_owned())
+    };
+    write_fields(
+        &data_dir.join(OPTIONS_FILE),
+        &[
+            ("num_games", options.num_games.to_string()),
+            ("num_threads", options.num_threads.to_string()),
+            ("tablebases_path", path_field(&options.tablebases_path)),
+            ("book", path_field(&options.book)),
+            (
+                "book_order",
+                options
+                    .book_order
+                    .to_possible_value()
+                    .map_or_else(String::new, |v| v.get_name().to_string()),
+            ),
+            ("book_games", options.book_games.to_string()),
+            ("nodes", options.nodes.to_string()),
+            ("generate_dfrc", options.generate_dfrc.to_string()),
+            ("generate_frc", options.generate_frc.to_string()),
+            ("random_plies", options.random_plies.to_string()),
+            ("opening_eval_bound", options.opening_eval_bound.to_string()),
+            ("temperature", options.temperature.to_string()),
+            ("seed", options.seed.to_string()),
+            ("pgn", options.pgn.to_string()),
+        ],
+    )
+}
+
+/// Read back the options of the run in `data_dir`.
+pub fn load_options(data_dir: &Path) -> anyhow::Result<DataGenOptions> {
+    let fields = read_fields(&data_dir.join(OPTIONS_FILE))?;
+    let path_field = |name: &str| -> anyhow::Result<Option<PathBuf>> {
+        let value = raw_field(&fields, name)?;
+        Ok((!value.is_empty()).then(|| PathBuf::from(value)))
+    };
+    Ok(DataGenOptions {
+        num_games: field(&fields, "num_games")?,
+        num_threads: field(&fields, "num_threads")?,
+        tablebases_path: path_field("tablebases_path")?,
+        book: path_field("book")?,
+        book_order: BookOrder::from_str(raw_field(&fields, "book_order")?, true)
+            .map_err(|e| anyhow!("invalid value for field \"book_order\": {e}"))?,
+        book_games: field(&fields, "book_games")?,
+        nodes: field(&fields, "nodes")?,
+        generate_dfrc: field(&fields, "generate_dfrc")?,
+        generate_frc: field(&fields, "generate_frc")?,
+        random_plies: field(&fields, "random_plies")?,
+        opening_eval_bound: field(&fields, "opening_eval_bound")?,
+        temperature: field(&fields, "temperature")?,
+        seed: field(&fields, "seed")?,
+        pgn: field(&fields, "pgn")?,
+    })
+}
+
+fn write_fields(path: &Path, fields: &[(&str, String)]) -> anyhow::Result<()> {
+    let temp = path.with_extension("tmp");
+    let mut file = File::create(&temp)
+        .with_context(|| format!("Failed to create file at {}", temp.display()))?;
+    for (name, value) in fields {
+        writeln!(file, "{name} {value}")?;
+    }
+    file.sync_all()?;
+    fs::rename(&temp, path)
+        .with_context(|| format!("Failed to replace file at {}", path.display()))?;
+    Ok(())
+}
+
+fn read_fields(path: &Path) -> anyhow::Result<HashMap<String, String>> {
+    let text = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read file at {}", path.display()))?;
+    Ok(text
+        .lines()
+        .filter_map(|line| {
+            let (name, value) = line.split_once(' ').unwrap_or((line, ""));
+            (!name.is_empty()).then(|| (name.to_string(), value.to_string()))
+        })
+        .collect())
+}
+
+fn raw_field<'a>(fields: &'a HashMap<String, String>, name: &str) -> anyhow::Result<&'a str> {
+    fields
+        .get(name)
+        .map(String::as_str)
+        .ok_or_else(|| anyhow!("missing field \"{name}\""))
+}
+
+fn field<T: FromStr>(fields: &HashMap<String, String>, name: &str) -> anyhow::Result<T> {
+    raw_field(fields, name)?
+        .parse()
+        .map_err(|_| anyhow!("invalid value for field \"{name}\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let dir = std::env::temp_dir().join(format!("viri-checkpoint-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        let checkpoint = Checkpoint {
+            games: 1234,
+            rng: u128::MAX - 5,
+            data_bytes: 987_654,
+            pgn_bytes: 0,
+            book_cursor: 42,
+        };
+        let path = Checkpoint::path(&dir, 3);
+        assert_eq!(Checkpoint::load(&path).unwrap(), None);
+        checkpoint.save(&path).unwrap();
+        assert_eq!(Checkpoint::load(&path).unwrap(), Some(checkpoint));
+
+        let options = DataGenOptions {
+            book: Some(PathBuf::from("books/some book.epd")),
+            book_order: BookOrder::Sampled,
+            book_games: 4,
+            temperature: 150,
+            seed: u64::MAX,
+            ..DataGenOptions::new()
+        };
+        save_options(&options, &dir).unwrap();
+        let loaded = load_options(&dir).unwrap();
+        assert_eq!(format!("{loaded:?}"), format!("{options:?}"));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -270,7 +270,7 @@ impl Game {
             if !filter.should_filter(*mv, i32::from(eval), &board, outcome) {
                 cnt += 1;
             }
-            board.make_move_simple(*mv);
+            board = board.make_move_new(*mv);
         }
 
         cnt
@@ -296,7 +296,7 @@ impl Game {
             if !filter.should_filter(*mv, i32::from(eval), &board, outcome) {
                 callback(board.pack(eval, wdl, 0))?;
             }
-            board.make_move_simple(*mv);
+            board = board.make_move_new(*mv);
         }
 
         Ok(())
@@ -343,7 +343,7 @@ impl Game {
                     )?,
                 )?;
             }
-            board.make_move_simple(*mv);
+            board = board.make_move_new(*mv);
         }
 
         Ok(())
@@ -406,10 +406,7 @@ mod tests {
                 "black square-sets {msg}"
             );
             for sq in Square::all() {
-                assert_eq!(
-                    lhs.state.mailbox[sq], rhs.state.mailbox[sq],
-                    ".state.mailbox[{sq:?}] {msg}"
-                );
+                assert_eq!(lhs.mailbox[sq], rhs.mailbox[sq], ".mailbox[{sq:?}] {msg}");
             }
             assert_eq!(lhs.turn(), rhs.turn(), "side {msg}");
             assert_eq!(lhs.ep_sq(), rhs.ep_sq(), "ep_sq {msg}");
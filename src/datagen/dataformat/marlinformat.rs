@@ -34,7 +34,7 @@ impl PackedBoard {
 
         let mut pieces = util::U4Array32::default();
         for (i, sq) in occupancy.iter().enumerate() {
-            let piece = board.state.mailbox[sq].unwrap();
+            let piece = board.mailbox[sq].unwrap();
             let piece_type = piece.piece_type();
             let colour = piece.colour();
 
@@ -138,10 +138,12 @@ impl PackedBoard {
 }
 
 impl Board {
+    /// Pack this position along with its eval, WDL, and extra byte.
     pub fn pack(&self, eval: i16, wdl: u8, extra: u8) -> PackedBoard {
         PackedBoard::pack(self, eval, wdl, extra)
     }
 
+    /// Unpack a position, returning it with its eval, WDL, and extra byte.
     pub fn unpack(packed: &PackedBoard) -> (Self, i16, u8, u8) {
         packed.unpack()
     }
@@ -0,0 +1,182 @@
+//! Books of opening positions to start datagen games from.
+//!
+//! A book is either an EPD file with one position per line, or a PGN file, in which case each
+//! game's final position is an opening. Positions are handed out in the order of the file,
+//! shared between all threads, or sampled at random by each thread, and each position can be
+//! played a fixed number of times before moving on to the next.
+
+use std::{
+    path::Path,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use anyhow::{Context, bail};
+
+use crate::{book::builder::parse_pgn, chess::fen::Fen, cli::BookOrder, rng::XorShiftState};
+
+/// The opening positions of a book, and how they're handed out.
+pub struct OpeningBook {
+    positions: Vec<Fen>,
+    order: BookOrder,
+    games_per_position: usize,
+}
+
+/// Where one thread has got to in an [`OpeningBook`].
+#[derive(Default)]
+pub struct BookState {
+    /// The sampled position that this thread is playing, and how many more games it gets.
+    current: Option<(usize, usize)>,
+}
+
+impl OpeningBook {
+    /// Read the book at `path`, as PGN if it has a `.pgn` extension and as EPD otherwise.
+    pub fn load(path: &Path, order: BookOrder, games_per_position: usize) -> anyhow::Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read book file at {}", path.display()))?;
+        let is_pgn = path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("pgn"));
+        let positions = if is_pgn {
+            pgn_positions(&text)?
+        } else {
+            epd_positions(&text)?
+        };
+        if positions.is_empty() {
+            bail!("Book file at {} holds no positions.", path.display());
+        }
+        Ok(Self {
+            positions,
+            order,
+            games_per_position: games_per_position.max(1),
+        })
+    }
+
+    /// The number of positions in the book.
+    pub const fn len(&self) -> usize {
+        self.positions.len()
+    }
+
+    /// The position to start the next game from, or `None` if a sequential book has run out,
+    /// in which case it starts again from the beginning.
+    pub fn next(
+        &self,
+        cursor: &AtomicUsize,
+        state: &mut BookState,
+        rng: &mut XorShiftState,
+    ) -> Option<&Fen> {
+        match self.order {
+            BookOrder::Sequential => {
+                let game = cursor.fetch_add(1, Ordering::Relaxed);
+                let idx = game / self.games_per_position;
+                if idx >= self.positions.len() {
+                    println!("Book exhausted!");
+                    cursor.store(0, Ordering::Relaxed);
+                    return None;
+                }
+                Some(&self.positions[idx])
+            }
+            BookOrder::Sampled => {
+                let (idx, remaining) = match state.current {
+                    Some((idx, remaining)) if remaining > 0 => (idx, remaining),
+                    #[allow(clippy::cast_possible_truncation)]
+                    _ => (
+                        rng.next_below(self.positions.len() as u64) as usize,
+                        self.games_per_position,
+                    ),
+                };
+                state.current = Some((idx, remaining - 1));
+                Some(&self.positions[idx])
+            }
+        }
+    }
+}
+
+/// The positions of an EPD book. Blank lines and lines starting with `#` are skipped, and
+/// anything after the position's fields, such as EPD opcodes, is ignored.
+fn epd_positions(text: &str) -> anyhow::Result<Vec<Fen>> {
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty() && !line.trim_start().starts_with('#'))
+        .map(|(i, line)| {
+            Fen::parse_relaxed(line)
+                .with_context(|| format!("Failed to parse line {} of the book: {line}", i + 1))
+        })
+        .collect()
+}
+
+/// The final position of each game in a PGN book.
+fn pgn_positions(text: &str) -> anyhow::Result<Vec<Fen>> {
+    parse_pgn(text)
+        .into_iter()
+        .map(|game| {
+            let mut board = game.start;
+            for m in game.moves {
+                board.make_move_simple(m);
+            }
+            let fen = board.to_string();
+            Fen::parse(&fen).with_context(|| format!("Failed to read position {fen} in the book"))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EPD: &str = "\
+# two openings
+rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - c0 \"1. e4\";
+
+rnbqkbnr/pppppppp/8/8/3P4/8/PPP1PPPP/RNBQKBNR b KQkq -
+";
+
+    fn book(order: BookOrder, games_per_position: usize) -> OpeningBook {
+        OpeningBook {
+            positions: epd_positions(EPD).unwrap(),
+            order,
+            games_per_position,
+        }
+    }
+
+    #[test]
+    fn reads_epd_and_pgn() {
+        let from_epd = epd_positions(EPD).unwrap();
+        assert_eq!(from_epd.len(), 2);
+        let from_pgn =
+            pgn_positions("[Event \"?\"]\n\n1. e4 *\n\n[Event \"?\"]\n\n1. d4 {a comment} *\n")
+                .unwrap();
+        assert_eq!(from_pgn, from_epd);
+        assert!(epd_positions("not a position").is_err());
+    }
+
+    #[test]
+    fn sequential_repeats_then_wraps() {
+        let book = book(BookOrder::Sequential, 2);
+        let cursor = AtomicUsize::new(0);
+        let mut state = BookState::default();
+        let mut rng = XorShiftState::new();
+        let mut next = || {
+            book.next(&cursor, &mut state, &mut rng)
+                .map(|fen| book.positions.iter().position(|p| p == fen).unwrap())
+        };
+        let order = std::iter::repeat_with(&mut next)
+            .take(6)
+            .collect::<Vec<_>>();
+        assert_eq!(order, [Some(0), Some(0), Some(1), Some(1), None, Some(0)]);
+    }
+
+    #[test]
+    fn sampled_plays_each_draw_repeatedly() {
+        let book = book(BookOrder::Sampled, 3);
+        let cursor = AtomicUsize::new(0);
+        let mut state = BookState::default();
+        let mut rng = XorShiftState::with_seed(7);
+        let drawn = (0..30)
+            .map(|_| book.next(&cursor, &mut state, &mut rng).unwrap())
+            .collect::<Vec<_>>();
+        for chunk in drawn.chunks(3) {
+            assert!(chunk.iter().all(|&fen| fen == chunk[0]));
+        }
+        assert_eq!(cursor.load(Ordering::Relaxed), 0);
+    }
+}
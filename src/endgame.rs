@@ -0,0 +1,300 @@
+//! Recognisers for textbook endings.
+//!
+//! The network is trained on positions from real games, and so is often confident
+//! about endings that are known draws, or lost in endings where the winning plan
+//! is too long for the search to see. The recognisers here step in for a handful
+//! of those endings, either replacing the network's output with a known result
+//! or scaling it towards a draw.
+
+use crate::{
+    chess::{
+        board::Board,
+        piece::{Colour, PieceType},
+        squareset::SquareSet,
+        types::{Rank, Square},
+    },
+    lookups,
+};
+
+/// The denominator for scale factors, so a factor of `SCALE_NORMAL` leaves the evaluation unchanged.
+const SCALE_NORMAL: i32 = 128;
+/// The least score given to a king and pawn ending that the bitbase says is won.
+const KPK_WIN_SCORE: i32 = 400;
+/// The score for a king, bishop and knight against king, before the bonus for driving the king.
+const KBNK_BASE_SCORE: i32 = 1000;
+/// The bonus per step that the defending king is closer to a corner the bishop controls.
+const KBNK_CORNER_BONUS: i32 = 40;
+/// The bonus per step that the kings are closer together.
+const KBNK_KING_BONUS: i32 = 10;
+/// The least score given to a rook against pawn ending that the rook wins easily.
+const KRKP_WIN_SCORE: i32 = 400;
+/// The scale factor for a rook against an advanced and supported pawn.
+const KRKP_DRAWISH_SCALE: i32 = 16;
+/// The scale factor for opposite-coloured bishops with nothing else but pawns.
+const OCB_BASE_SCALE: i32 = 32;
+/// The increase in the opposite-coloured bishops scale factor for each passed pawn of the leader.
+const OCB_PASSER_SCALE: i32 = 16;
+
+/// What a recogniser makes of a position.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Verdict {
+    /// The given side should score at least this much.
+    AtLeast(Colour, i32),
+    /// The given side scores exactly this much.
+    Exact(Colour, i32),
+    /// The evaluation should be scaled by this factor over `SCALE_NORMAL`.
+    Scale(i32),
+}
+
+/// Whether `board` is a textbook draw, whatever the network thinks of it.
+pub fn is_recognised_draw(board: &Board) -> bool {
+    matches!(kpk(board), Some((_, false))) || is_wrong_rook_pawn(board)
+}
+
+/// Correct the network's evaluation `v`, from the side to move's point of view,
+/// if `board` is a recognised ending.
+pub fn adjust(board: &Board, v: i32) -> i32 {
+    let stm = board.turn();
+    let leader = if v >= 0 { stm } else { !stm };
+    match recognise(board, leader) {
+        Some(Verdict::AtLeast(side, score)) if side == stm => v.max(score),
+        Some(Verdict::AtLeast(_, score)) => v.min(-score),
+        Some(Verdict::Exact(side, score)) if side == stm => score,
+        Some(Verdict::Exact(_, score)) => -score,
+        Some(Verdict::Scale(factor)) => v * factor / SCALE_NORMAL,
+        None => v,
+    }
+}
+
+fn recognise(board: &Board, leader: Colour) -> Option<Verdict> {
+    if let Some((strong, true)) = kpk(board) {
+        return Some(Verdict::AtLeast(strong, KPK_WIN_SCORE));
+    }
+    kbnk(board)
+        .or_else(|| krkp(board))
+        .or_else(|| opposite_bishops(board, leader))
+}
+
+/// Whether `colour` has nothing but its king.
+fn bare_king(board: &Board, colour: Colour) -> bool {
+    board.state.bbs.colours[colour].one()
+}
+
+/// The pieces of type `piece_type` belonging to `colour`.
+fn pieces(board: &Board, colour: Colour, piece_type: PieceType) -> SquareSet {
+    board.state.bbs.pieces[piece_type] & board.state.bbs.colours[colour]
+}
+
+/// If the position is king and pawn against king, the side with the pawn,
+/// and whether it wins.
+fn kpk(board: &Board) -> Option<(Colour, bool)> {
+    let bbs = &board.state.bbs;
+    let pawns = bbs.pieces[PieceType::Pawn];
+    if bbs.occupied().count() != 3 || !pawns.one() {
+        return None;
+    }
+    let pawn = pawns.first()?;
+    let strong = if bbs.colours[Colour::White].contains_square(pawn) {
+        Colour::White
+    } else {
+        Colour::Black
+    };
+    let wins = lookups::probe_kpk(
+        strong,
+        bbs.king_sq(strong),
+        pawn,
+        bbs.king_sq(!strong),
+        board.turn(),
+    );
+    Some((strong, wins))
+}
+
+/// King, bishop and rook pawns against king, where the bishop doesn't control the
+/// queening square and the defending king has reached it.
+fn is_wrong_rook_pawn(board: &Board) -> bool {
+    Colour::all().any(|strong| {
+        let weak = !strong;
+        let pawns = pieces(board, strong, PieceType::Pawn);
+        let bishops = pieces(board, strong, PieceType::Bishop);
+        let file = if SquareSet::FILE_A.contains(pawns) {
+            SquareSet::FILE_A
+        } else if SquareSet::FILE_H.contains(pawns) {
+            SquareSet::FILE_H
+        } else {
+            return false;
+        };
+        if pawns == SquareSet::EMPTY
+            || bishops == SquareSet::EMPTY
+            || board.state.bbs.colours[strong] != pawns | bishops | board.king(strong)
+            || !bare_king(board, weak)
+        {
+            return false;
+        }
+        let Some(queening) = (file & SquareSet::RANK_8.relative_to(strong)).first() else {
+            return false;
+        };
+        let colour_of_queening = if SquareSet::LIGHT_SQUARES.contains_square(queening) {
+            SquareSet::LIGHT_SQUARES
+        } else {
+            SquareSet::DARK_SQUARES
+        };
+        bishops & colour_of_queening == SquareSet::EMPTY
+            && Square::distance(board.state.bbs.king_sq(weak), queening) <= 1
+    })
+}
+
+/// King, bishop and knight against king, scored to drive the defending king
+/// towards a corner of the bishop's colour.
+fn kbnk(board: &Board) -> Option<Verdict> {
+    let strong = Colour::all().find(|&c| {
+        board.state.bbs.colours[c].count() == 3
+            && pieces(board, c, PieceType::Bishop).one()
+            && pieces(board, c, PieceType::Knight).one()
+    })?;
+    let weak = !strong;
+    if !bare_king(board, weak) {
+        return None;
+    }
+    let bishop = pieces(board, strong, PieceType::Bishop);
+    let corners = if SquareSet::DARK_SQUARES.contains(bishop) {
+        [Square::A1, Square::H8]
+    } else {
+        [Square::A8, Square::H1]
+    };
+    let weak_king = board.state.bbs.king_sq(weak);
+    let strong_king = board.state.bbs.king_sq(strong);
+    let corner_distance = corners
+        .iter()
+        .map(|&c| Square::distance(weak_king, c))
+        .min()?;
+    let king_distance = Square::distance(weak_king, strong_king);
+    Some(Verdict::Exact(
+        strong,
+        KBNK_BASE_SCORE
+            + KBNK_CORNER_BONUS * (7 - i32::from(corner_distance))
+            + KBNK_KING_BONUS * (7 - i32::from(king_distance)),
+    ))
+}
+
+/// King and rook against king and pawn.
+fn krkp(board: &Board) -> Option<Verdict> {
+    let strong = Colour::all().find(|&c| {
+        board.state.bbs.colours[c].count() == 2 && pieces(board, c, PieceType::Rook).one()
+    })?;
+    let weak = !strong;
+    let pawn = pieces(board, weak, PieceType::Pawn);
+    if board.state.bbs.colours[weak].count() != 2 || !pawn.one() {
+        return None;
+    }
+    let pawn = pawn.first()?;
+    let rook = pieces(board, strong, PieceType::Rook).first()?;
+    let strong_king = board.state.bbs.king_sq(strong);
+    let weak_king = board.state.bbs.king_sq(weak);
+    let queening = Square::from_rank_file(Rank::Eight, pawn.file()).relative_to(weak);
+    // the strong king blocks the pawn's path.
+    let in_front = strong_king.file() == pawn.file()
+        && strong_king.relative_to(weak).rank() > pawn.relative_to(weak).rank();
+    // the pawn can't be defended in time, and the rook is safe.
+    let tempo = u8::from(board.turn() == weak);
+    let stranded =
+        Square::distance(weak_king, pawn) >= 3 + tempo && Square::distance(weak_king, rook) >= 3;
+    if in_front || stranded {
+        return Some(Verdict::AtLeast(strong, KRKP_WIN_SCORE));
+    }
+    // an advanced pawn, escorted by its king, with the strong king far away.
+    if pawn.relative_to(weak).rank() >= Rank::Six
+        && Square::distance(weak_king, pawn) <= 1
+        && Square::distance(strong_king, queening) >= 3
+    {
+        return Some(Verdict::Scale(KRKP_DRAWISH_SCALE));
+    }
+    None
+}
+
+/// Bishops of opposite colours, with nothing else but pawns on the board.
+fn opposite_bishops(board: &Board, leader: Colour) -> Option<Verdict> {
+    let bbs = &board.state.bbs;
+    let bishops = bbs.pieces[PieceType::Bishop];
+    let others =
+        bbs.pieces[PieceType::Knight] | bbs.pieces[PieceType::Rook] | bbs.pieces[PieceType::Queen];
+    if others != SquareSet::EMPTY
+        || !pieces(board, Colour::White, PieceType::Bishop).one()
+        || !pieces(board, Colour::Black, PieceType::Bishop).one()
+        || !(bishops & SquareSet::LIGHT_SQUARES).one()
+    {
+        return None;
+    }
+    #[allow(clippy::cast_possible_wrap)]
+    let passers = board.passed_pawns(leader).count() as i32;
+    Some(Verdict::Scale(
+        (OCB_BASE_SCALE + OCB_PASSER_SCALE * passers).min(SCALE_NORMAL),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn board(fen: &str) -> Board {
+        Board::from_fen(fen).unwrap()
+    }
+
+    #[test]
+    fn kpk_endings() {
+        // the defending king is in the square of the pawn.
+        assert!(is_recognised_draw(&board("8/8/8/2k5/P7/8/8/7K w - - 0 1")));
+        // ... and here it isn't, so the network's score is raised.
+        let won = board("7k/8/8/8/P7/8/8/7K w - - 0 1");
+        assert!(!is_recognised_draw(&won));
+        assert_eq!(adjust(&won, 50), KPK_WIN_SCORE);
+        assert_eq!(adjust(&won, 900), 900);
+    }
+
+    #[test]
+    fn wrong_rook_pawn() {
+        // a light-squared bishop can't drive the king from h8.
+        assert!(is_recognised_draw(&board("7k/8/8/7P/8/8/4B3/K7 w - - 0 1")));
+        // a dark-squared bishop can.
+        assert!(!is_recognised_draw(&board(
+            "7k/8/8/7P/8/8/3B4/K7 w - - 0 1"
+        )));
+        // and the king must have reached the corner.
+        assert!(!is_recognised_draw(&board(
+            "8/8/8/3k3P/8/8/4B3/K7 w - - 0 1"
+        )));
+        // the same for black.
+        assert!(is_recognised_draw(&board("k7/4b3/8/8/7p/8/8/7K w - - 0 1")));
+    }
+
+    #[test]
+    fn kbnk_drives_to_the_right_corner() {
+        // a light-squared bishop, so the king must go to a8 or h1.
+        let right = board("k7/8/1K6/8/8/8/8/3BN3 b - - 0 1");
+        let wrong = board("7k/8/6K1/8/8/8/8/3BN3 b - - 0 1");
+        let score = |b: &Board| -adjust(b, 0);
+        assert!(score(&right) > score(&wrong));
+        assert!(score(&wrong) >= KBNK_BASE_SCORE);
+    }
+
+    #[test]
+    fn krkp_endings() {
+        // the rook's king stands in front of the pawn.
+        let blocked = board("8/8/8/8/8/8/2p2k2/2K4R w - - 0 1");
+        assert_eq!(adjust(&blocked, 0), KRKP_WIN_SCORE);
+        // an escorted pawn on the seventh, with the rook's king far away.
+        let drawish = board("K7/8/8/8/8/8/2pk4/7R w - - 0 1");
+        assert_eq!(
+            adjust(&drawish, 256),
+            256 * KRKP_DRAWISH_SCALE / SCALE_NORMAL
+        );
+    }
+
+    #[test]
+    fn opposite_bishops_scale_down() {
+        let ocb = board("4k3/5p2/4b3/8/8/4B3/1P3P2/4K3 w - - 0 1");
+        assert!(adjust(&ocb, 200) < 200);
+        // bishops on the same colour are left alone.
+        let same = board("4k3/5p2/3b4/8/8/4B3/1P3P2/4K3 w - - 0 1");
+        assert_eq!(adjust(&same, 200), 200);
+    }
+}
@@ -167,6 +167,45 @@ pub enum GoParseError {
     },
     #[error("incomplete time control: got some of wtime/btime/winc/binc but not both clocks")]
     IncompleteTimeControl,
+    #[error("`searchmoves` must be followed by at least one move")]
+    EmptySearchMoves,
+    #[error("invalid move \"{text}\" in `searchmoves`: {source}")]
+    InvalidSearchMove {
+        text: String,
+        source: MoveParseError,
+    },
+}
+
+/// Errors that can occur when loading a Polyglot opening book.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum BookError {
+    #[error("failed to read book: {0}")]
+    Io(String),
+    #[error("book is {0} bytes long, which is not a whole number of 16-byte entries")]
+    BadLength(usize),
+}
+
+/// Errors that can occur when saving or restoring the hash table.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum HashFileError {
+    #[error("I/O error: {0}")]
+    Io(String),
+    #[error("not a saved hash table")]
+    BadMagic,
+    #[error("saved hash table has format version {0}, expected {1}")]
+    UnsupportedVersion(u32, u32),
+    #[error("saved hash table has invalid age {0}")]
+    BadAge(u8),
+    #[error("saved hash table should be {expected} bytes long, but is {got}")]
+    BadLength { expected: u64, got: u64 },
+    #[error("saved hash table is corrupt (checksum mismatch)")]
+    BadChecksum,
+}
+
+impl From<std::io::Error> for HashFileError {
+    fn from(e: std::io::Error) -> Self {
+        Self::Io(e.to_string())
+    }
 }
 
 /// Errors that can occur when parsing the `setoption` command.
@@ -198,6 +237,10 @@ pub enum SetOptionParseError {
     },
     #[error("invalid value for tuning parameter `{name}`: {message}")]
     InvalidTuningParam { name: String, message: String },
+    #[error("failed to load opening book \"{path}\": {source}")]
+    Book { path: String, source: BookError },
+    #[error("failed to load network \"{path}\": {message}")]
+    Network { path: String, message: String },
 }
 
 /// Errors that can occur when parsing a `go perft` command.
@@ -209,6 +252,19 @@ pub enum PerftParseError {
     InvalidDepth { text: String, source: ParseIntError },
 }
 
+/// Errors that can occur when parsing the `register` command.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum RegisterParseError {
+    #[error("`register` must be followed by `later` or `name <name> code <code>`")]
+    MissingArguments,
+    #[error("expected `later` or `name` after `register`, got \"{0}\"")]
+    UnexpectedToken(String),
+    #[error("`register name` requires a name")]
+    MissingName,
+    #[error("`register name <name>` must be followed by `code <code>`")]
+    MissingCode,
+}
+
 /// Top-level UCI errors.
 #[derive(Debug, Error)]
 pub enum UciError {
@@ -222,6 +278,8 @@ pub enum UciError {
     SetOption(#[from] SetOptionParseError),
     #[error("{0}")]
     Perft(#[from] PerftParseError),
+    #[error("{0}")]
+    Register(#[from] RegisterParseError),
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
     #[error("internal error: {0}")]
@@ -232,4 +290,22 @@ pub enum UciError {
     // TODO: Convert to non-anyhow, proper inner error.
     #[error("NNUE initialization failed: {0}")]
     NnueInit(String),
+    #[error("hash file \"{path}\": {source}")]
+    HashFile { path: String, source: HashFileError },
+}
+
+/// Errors in commands received over the xboard protocol.
+/// The messages are in the forms that xboard GUIs expect.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum XboardError {
+    #[error("Error (unknown command): {0}")]
+    UnknownCommand(String),
+    #[error("Error (bad argument): {0}")]
+    BadArgument(String),
+    #[error("Error (command not legal now): {0}")]
+    NotLegalNow(String),
+    #[error("Illegal move: {0}")]
+    IllegalMove(String),
+    #[error("tellusererror Illegal position")]
+    IllegalPosition,
 }
@@ -0,0 +1,80 @@
+//! A cache for raw network evaluations, keyed on the board's Zobrist key.
+//!
+//! The same position is often reached along different paths, especially in
+//! quiescence search, and the network forward pass is the most expensive part
+//! of evaluating it. Only the raw network output is stored: correction history
+//! changes as the search runs, so it is applied on top of a cached value rather
+//! than baked into it.
+
+#[derive(Debug, Clone, Copy, Default)]
+struct EvalCacheEntry {
+    key: u64,
+    eval: i32,
+}
+
+/// A fixed-size, always-replace table of raw network evaluations.
+pub struct EvalCache {
+    table: Vec<EvalCacheEntry>,
+}
+
+impl EvalCache {
+    /// The default number of entries.
+    pub const DEFAULT_ENTRIES: usize = 1 << 16;
+
+    /// Create a table with `entries` slots (at least one).
+    pub fn new(entries: usize) -> Self {
+        Self {
+            table: vec![EvalCacheEntry::default(); entries.max(1)],
+        }
+    }
+
+    fn index(&self, key: u64) -> usize {
+        #![allow(clippy::cast_possible_truncation)]
+        // fixed-point multiplication trick, as in the transposition table.
+        ((u128::from(key) * self.table.len() as u128) >> 64) as usize
+    }
+
+    /// Look up the cached evaluation of the position with hash `key`.
+    /// The stored key is verified, so an index collision is reported as a miss.
+    pub fn get(&self, key: u64) -> Option<i32> {
+        let entry = &self.table[self.index(key)];
+        (entry.key == key).then_some(entry.eval)
+    }
+
+    /// Store the evaluation of the position with hash `key`, replacing whatever was there.
+    pub fn insert(&mut self, key: u64, eval: i32) {
+        let idx = self.index(key);
+        self.table[idx] = EvalCacheEntry { key, eval };
+    }
+
+    /// Empty the table.
+    pub fn clear(&mut self) {
+        self.table.fill(EvalCacheEntry::default());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stored_evals_are_found() {
+        let mut cache = EvalCache::new(EvalCache::DEFAULT_ENTRIES);
+        let key = 0x0123_4567_89ab_cdef;
+        assert_eq!(cache.get(key), None);
+        cache.insert(key, -37);
+        assert_eq!(cache.get(key), Some(-37));
+        cache.clear();
+        assert_eq!(cache.get(key), None);
+    }
+
+    #[test]
+    fn colliding_keys_miss() {
+        // with a single slot, every key shares an index.
+        let mut cache = EvalCache::new(1);
+        cache.insert(1, 100);
+        cache.insert(2, 200);
+        assert_eq!(cache.get(1), None);
+        assert_eq!(cache.get(2), Some(200));
+    }
+}
@@ -2,9 +2,12 @@
 
 use std::{
     collections::HashMap,
+    fmt::{Display, Formatter},
     fs::File,
     io::{BufRead, BufReader, BufWriter, Write},
+    num::NonZeroUsize,
     path::Path,
+    sync::LazyLock,
 };
 
 use anyhow::Context;
@@ -14,13 +17,16 @@ use crate::{
         board::Board,
         chessmove::Move,
         fen::Fen,
-        piece::{Colour, PieceType},
+        piece::{Colour, Piece, PieceType},
         squareset::SquareSet,
+        types::{CastlingRights, Rank, Square},
     },
+    endgame,
     nnue::network::{self, NNUEParams, NNUEState},
-    search::{draw_score, parameters::Config},
+    search::{adj_shuffle, draw_score, parameters::Config, static_exchange_eval},
     searchinfo::SearchInfo,
     threadlocal::ThreadData,
+    uci::fmt::NORMALISE_TO_PAWN_VALUE,
     util::MAX_DEPTH,
 };
 
@@ -73,7 +79,7 @@ pub const SEE_QUEEN_VALUE: i32 = 1253;
 pub const MATERIAL_SCALE_BASE: i32 = 856;
 
 impl Board {
-    pub fn material(&self, info: &SearchInfo) -> i32 {
+    pub(crate) fn material(&self, info: &SearchInfo) -> i32 {
         #![allow(clippy::cast_possible_wrap)]
         let b = &self.state.bbs;
         (info.conf.see_knight_value * b.pieces[PieceType::Knight].count() as i32
@@ -83,7 +89,7 @@ impl Board {
             / 32
     }
 
-    pub fn zugzwang_unlikely(&self) -> bool {
+    pub(crate) fn zugzwang_unlikely(&self) -> bool {
         let stm = self.turn();
         let us = self.state.bbs.colours[stm];
         let kings = self.state.bbs.pieces[PieceType::King];
@@ -91,9 +97,9 @@ impl Board {
         (us & (kings | pawns)) != us
     }
 
-    pub fn estimated_see(&self, conf: &Config, m: Move) -> i32 {
+    pub(crate) fn estimated_see(&self, conf: &Config, m: Move) -> i32 {
         // initially take the value of the thing on the target square
-        let mut value = self.state.mailbox[m.to()].map_or(0, |p| see_value(p.piece_type(), conf));
+        let mut value = self.mailbox[m.to()].map_or(0, |p| see_value(p.piece_type(), conf));
 
         if let Some(promo) = m.promotion_type() {
             // if it's a promo, swap a pawn for the promoted piece type
@@ -105,6 +111,14 @@ impl Board {
 
         value
     }
+
+    /// Whether the exchange begun by `m` wins at least `threshold` centipawns of material,
+    /// with the default piece values. The search uses [`static_exchange_eval`] directly,
+    /// so that the values can be tuned.
+    pub fn see(&self, m: Move, threshold: i32) -> bool {
+        static DEFAULT_CONFIG: LazyLock<Config> = LazyLock::new(Config::default);
+        static_exchange_eval(self, &DEFAULT_CONFIG, m, threshold)
+    }
 }
 
 pub fn evaluate_nnue(t: &ThreadData) -> i32 {
@@ -118,10 +132,32 @@ pub fn evaluate_nnue(t: &ThreadData) -> i32 {
     v.clamp(-MINIMUM_TB_WIN_SCORE + 1024, MINIMUM_TB_WIN_SCORE - 1024)
 }
 
+/// The material imbalance, in centipawns, beyond which the small network evaluates
+/// positions in place of the main network, when one is loaded.
+///
+/// The choice depends only on the position, never on the search window, because evaluations
+/// are cached by position: a window-dependent choice would let whichever path first reached
+/// a position decide its evaluation for every other path.
+const SMALL_NET_THRESHOLD: i32 = 962;
+
+/// The material balance from the side to move's point of view, using the search's piece values.
+fn material_imbalance(board: &Board, conf: &Config) -> i32 {
+    #![allow(clippy::cast_possible_wrap)]
+    let us = board.turn();
+    PieceType::all()
+        .map(|pt| {
+            let ours = board.state.bbs.piece_bb(Piece::new(us, pt)).count() as i32;
+            let theirs = board.state.bbs.piece_bb(Piece::new(!us, pt)).count() as i32;
+            see_value(pt, conf) * (ours - theirs)
+        })
+        .sum()
+}
+
 pub fn evaluate(t: &mut ThreadData, nodes: u64) -> i32 {
-    // detect draw by insufficient material
+    // detect draw by insufficient material, or a textbook drawn ending.
     if t.board.state.bbs.pieces[PieceType::Pawn] == SquareSet::EMPTY
         && t.board.state.bbs.is_material_draw()
+        || endgame::is_recognised_draw(&t.board)
     {
         return if t.board.turn() == Colour::White {
             draw_score(t, nodes, t.board.turn())
@@ -129,11 +165,155 @@ pub fn evaluate(t: &mut ThreadData, nodes: u64) -> i32 {
             -draw_score(t, nodes, t.board.turn())
         };
     }
-    // apply all in-waiting updates to generate a valid
-    // neural network accumulator state.
+    // the same position is often reached by transposition, so reuse
+    // the network output if we've already computed it.
+    let key = t.board.state.keys.zobrist;
+    let v = if let Some(v) = t.eval_cache.get(key) {
+        v
+    } else {
+        let v = match t.small_net {
+            // a clearly decided position doesn't need the main network's precision.
+            Some(net) if material_imbalance(&t.board, &t.info.conf).abs() > SMALL_NET_THRESHOLD => {
+                net.evaluate(&t.board)
+                    .clamp(-MINIMUM_TB_WIN_SCORE + 1024, MINIMUM_TB_WIN_SCORE - 1024)
+            }
+            _ => {
+                // apply all in-waiting updates to generate a valid
+                // neural network accumulator state.
+                t.nnue.force(&t.board, t.nnue_params);
+                // run the neural network evaluation
+                evaluate_nnue(t)
+            }
+        };
+        t.eval_cache.insert(key, v);
+        v
+    };
+    // correct the network in endings it's known to misjudge.
+    endgame::adjust(&t.board, v)
+}
+
+/// How the static evaluation of a position is put together.
+/// Every score is from white's point of view.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EvalBreakdown {
+    /// The position that was evaluated.
+    pub board: Board,
+    /// A plain material count, as in [`material_balance`].
+    pub material: i32,
+    /// The output bucket that the network used.
+    pub bucket: usize,
+    /// The network's output.
+    pub network: i32,
+    /// The evaluation after the endgame recognisers, which is zero for a recognised draw.
+    pub endgame: i32,
+    /// The evaluation after scaling for material and the fifty-move counter.
+    pub scaled: i32,
+    /// The adjustment from correction history.
+    pub correction: i32,
+    /// The evaluation that the search would use.
+    pub total: i32,
+    /// For each square holding a piece other than a king, how much the network's
+    /// output would drop if that piece were taken off the board.
+    pub piece_values: [Option<i32>; 64],
+}
+
+/// Break down the static evaluation of the position on `t.board`.
+pub fn explain(t: &mut ThreadData) -> EvalBreakdown {
+    let white_pov = |v: i32, turn: Colour| if turn == Colour::White { v } else { -v };
+    let turn = t.board.turn();
     t.nnue.force(&t.board, t.nnue_params);
-    // run the neural network evaluation
-    evaluate_nnue(t)
+    let network = evaluate_nnue(t);
+    let drawn = t.board.state.bbs.pieces[PieceType::Pawn] == SquareSet::EMPTY
+        && t.board.state.bbs.is_material_draw()
+        || endgame::is_recognised_draw(&t.board);
+    let endgame = if drawn {
+        0
+    } else {
+        endgame::adjust(&t.board, network)
+    };
+    let scaled = adj_shuffle(t, endgame, t.board.fifty_move_counter());
+    let correction = t.correction();
+
+    let full = t.nnue.evaluate(t.nnue_params, &t.board);
+    let mut piece_values = [None; 64];
+    t.board.state.bbs.visit_pieces(|sq, piece| {
+        if piece.piece_type() == PieceType::King {
+            return;
+        }
+        let mut fen = Fen {
+            board: t.board.state.bbs,
+            turn,
+            castling: CastlingRights::default(),
+            ep: None,
+            halfmove: 0,
+            fullmove: NonZeroUsize::MIN,
+        };
+        fen.board.clear_piece_at(sq, piece);
+        let mut without = t.board.clone();
+        without.set_from_fen(&fen);
+        let nnue = NNUEState::new(&without, t.nnue_params);
+        let reduced = nnue.evaluate(t.nnue_params, &without);
+        piece_values[sq] = Some(white_pov(full - reduced, turn));
+    });
+
+    EvalBreakdown {
+        board: t.board.clone(),
+        material: white_pov(material_balance(&t.board), turn),
+        bucket: network::output_bucket(&t.board),
+        network: white_pov(network, turn),
+        endgame: white_pov(endgame, turn),
+        scaled: white_pov(scaled, turn),
+        correction: white_pov(correction, turn),
+        total: white_pov(scaled + correction, turn),
+        piece_values,
+    }
+}
+
+impl Display for EvalBreakdown {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let pawns = |v: i32| f64::from(v) / f64::from(NORMALISE_TO_PAWN_VALUE);
+        let border = "+-------".repeat(8) + "+";
+        writeln!(f, "Network-derived piece values:")?;
+        for rank in Rank::all().rev() {
+            writeln!(f, "{border}")?;
+            let row = Square::all()
+                .filter(|sq| sq.rank() == rank)
+                .collect::<Vec<_>>();
+            for &sq in &row {
+                match self.board.mailbox[sq] {
+                    Some(piece) => write!(f, "|   {piece}   ")?,
+                    None => write!(f, "|       ")?,
+                }
+            }
+            writeln!(f, "|")?;
+            for &sq in &row {
+                match self.piece_values[sq] {
+                    // keep to five characters, so that big values still fit.
+                    Some(v) if pawns(v).abs() >= 9.995 => write!(f, "| {:+5.1} ", pawns(v))?,
+                    Some(v) => write!(f, "| {:+5.2} ", pawns(v))?,
+                    None => write!(f, "|       ")?,
+                }
+            }
+            writeln!(f, "|")?;
+        }
+        writeln!(f, "{border}")?;
+        writeln!(f)?;
+        writeln!(f, "Material              {:+6.2}", pawns(self.material))?;
+        writeln!(
+            f,
+            "Network (bucket {})    {:+6.2}",
+            self.bucket,
+            pawns(self.network)
+        )?;
+        writeln!(f, "Endgame recognisers   {:+6.2}", pawns(self.endgame))?;
+        writeln!(f, "Scaled                {:+6.2}", pawns(self.scaled))?;
+        writeln!(f, "Correction history    {:+6.2}", pawns(self.correction))?;
+        write!(
+            f,
+            "Final evaluation      {:+6.2} (white side)",
+            pawns(self.total)
+        )
+    }
 }
 
 pub const fn see_value(piece_type: PieceType, conf: &Config) -> i32 {
@@ -274,3 +454,190 @@ pub fn eval_stats(
 
     Ok(())
 }
+
+/// A plain material count from the side to move's point of view, using the
+/// default SEE piece values. Serves as a hand-written baseline to compare the
+/// network against.
+pub fn material_balance(board: &Board) -> i32 {
+    material_imbalance(board, &Config::default())
+}
+
+/// Summary statistics from comparing the network's evaluation against the
+/// material baseline over a set of positions.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EvalComparison {
+    /// The number of positions compared.
+    pub count: usize,
+    /// The Pearson correlation between the two evaluations.
+    pub correlation: f64,
+    /// The mean absolute difference between the two evaluations, in centipawns.
+    pub mean_abs_diff: f64,
+    /// The positions on which the two evaluations disagree most, as
+    /// `(fen, nnue, material)`, largest disagreement first.
+    pub worst: Vec<(String, i32, i32)>,
+}
+
+/// Compare the network's evaluation against [`material_balance`] on each
+/// position in `fens`, keeping the `n_worst` largest disagreements.
+/// Positions in check are skipped, as in [`eval_stats`].
+pub fn compare_evals<'a>(
+    fens: impl IntoIterator<Item = &'a str>,
+    n_worst: usize,
+) -> anyhow::Result<EvalComparison> {
+    let mut board = Board::startpos();
+    let nnue_params = NNUEParams::decompress_and_alloc()?;
+    let mut nnue = NNUEState::new(&board, nnue_params);
+
+    let mut pairs = Vec::new();
+    for (i, line) in fens.into_iter().enumerate() {
+        let parsed = Fen::parse_relaxed(line)
+            .with_context(|| format!("Failed to parse FEN from line {}: {}", i + 1, line))?;
+        board.set_from_fen(&parsed);
+
+        if board.in_check() {
+            continue;
+        }
+
+        nnue.reïnit_from(&board, nnue_params);
+        let eval = nnue.evaluate(nnue_params, &board);
+        pairs.push((line, eval, material_balance(&board)));
+    }
+
+    let count = pairs.len();
+    #[expect(clippy::cast_precision_loss)]
+    let n = count as f64;
+    let (mut sx, mut sy, mut sxx, mut syy, mut sxy, mut abs_diff) = (0.0, 0.0, 0.0, 0.0, 0.0, 0.0);
+    for &(_, x, y) in &pairs {
+        let (x, y) = (f64::from(x), f64::from(y));
+        sx += x;
+        sy += y;
+        sxx += x * x;
+        syy += y * y;
+        sxy += x * y;
+        abs_diff += (x - y).abs();
+    }
+    let covariance = n.mul_add(sxy, -sx * sy);
+    let spread = (n.mul_add(sxx, -sx * sx) * n.mul_add(syy, -sy * sy)).sqrt();
+    // a constant series has no defined correlation; report zero rather than NaN.
+    let correlation = if spread > 0.0 {
+        covariance / spread
+    } else {
+        0.0
+    };
+    let mean_abs_diff = if pairs.is_empty() { 0.0 } else { abs_diff / n };
+
+    pairs.sort_by_key(|&(_, x, y)| std::cmp::Reverse((x - y).abs()));
+    let worst = pairs
+        .into_iter()
+        .take(n_worst)
+        .map(|(fen, x, y)| (fen.to_string(), x, y))
+        .collect();
+
+    Ok(EvalComparison {
+        count,
+        correlation,
+        mean_abs_diff,
+        worst,
+    })
+}
+
+/// Compare the network's evaluation against a plain material count over the
+/// positions in an EPD file, printing the correlation, mean absolute
+/// difference, and the positions where the two disagree most.
+pub fn compare_eval(input: &Path, n_worst: usize) -> anyhow::Result<()> {
+    let f = File::open(input).with_context(|| format!("Failed to open {}", input.display()))?;
+    let lines = BufReader::new(f)
+        .lines()
+        .collect::<Result<Vec<_>, _>>()
+        .with_context(|| "Failed to read lines from input file.")?;
+
+    let cmp = compare_evals(lines.iter().map(String::as_str), n_worst)?;
+
+    println!(" EVALUATION COMPARISON (NNUE vs MATERIAL):");
+    println!("          COUNT: {:>7}", cmp.count);
+    println!("    CORRELATION: {:>10.4}", cmp.correlation);
+    println!(" MEAN ABS DIFF: {:>10.2}", cmp.mean_abs_diff);
+    if !cmp.worst.is_empty() {
+        println!(" WORST DISAGREEMENTS:");
+        for (fen, nnue, material) in &cmp.worst {
+            println!("  nnue {nnue:>6} material {material:>6} | {fen}");
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compare_evals_produces_finite_statistics() {
+        let fens = [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "4k3/8/8/8/8/8/8/3QK3 w - - 0 1",
+            "4k3/8/8/8/8/8/8/3QK3 b - - 0 1",
+            "3qk3/8/8/8/8/8/PPP5/4K3 w - - 0 1",
+            "4k3/pppppppp/8/8/8/8/8/R3K3 w - - 0 1",
+        ];
+        let cmp = compare_evals(fens, 3).unwrap();
+        assert_eq!(cmp.count, fens.len());
+        assert!(cmp.correlation.is_finite());
+        assert!((-1.0..=1.0).contains(&cmp.correlation));
+        assert!(cmp.mean_abs_diff.is_finite());
+        assert_eq!(cmp.worst.len(), 3);
+        let gaps = cmp
+            .worst
+            .iter()
+            .map(|(_, x, y)| (x - y).abs())
+            .collect::<Vec<_>>();
+        assert!(gaps.windows(2).all(|w| w[0] >= w[1]));
+    }
+
+    #[test]
+    fn material_balance_is_side_relative() {
+        let white = Board::from_fen("4k3/8/8/8/8/8/8/3QK3 w - - 0 1").unwrap();
+        let black = Board::from_fen("4k3/8/8/8/8/8/8/3QK3 b - - 0 1").unwrap();
+        assert_eq!(material_balance(&white), SEE_QUEEN_VALUE);
+        assert_eq!(material_balance(&black), -SEE_QUEEN_VALUE);
+    }
+
+    #[test]
+    fn explain_covers_every_piece() {
+        use std::sync::atomic::{AtomicBool, AtomicU64};
+
+        use crate::{searchinfo::Control, threadpool, transpositiontable::Cache};
+
+        let board = Board::from_fen("4k3/8/8/8/8/8/8/3QK3 b - - 0 1").unwrap();
+        let stopped = AtomicBool::new(false);
+        let nodes = AtomicU64::new(0);
+        let tbhits = AtomicU64::new(0);
+        let control = Control::default();
+        let pool = threadpool::make_worker_threads(1);
+        let mut cache = Cache::new();
+        cache.resize(1 << 20, &pool);
+        let nnue_params = NNUEParams::decompress_and_alloc().unwrap();
+        let mut t = Box::new(ThreadData::new(
+            0,
+            board,
+            cache.view(),
+            nnue_params,
+            &stopped,
+            &nodes,
+            &tbhits,
+            &control,
+        ));
+        let breakdown = explain(&mut t);
+        assert_eq!(breakdown.material, SEE_QUEEN_VALUE);
+        assert_eq!(breakdown.total, breakdown.scaled + breakdown.correction);
+        let valued = breakdown
+            .piece_values
+            .iter()
+            .enumerate()
+            .filter_map(|(sq, v)| v.map(|_| sq))
+            .collect::<Vec<_>>();
+        assert_eq!(valued, [Square::D1.index()]);
+        assert!(breakdown.to_string().contains("Final evaluation"));
+    }
+}
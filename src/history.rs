@@ -55,9 +55,9 @@ impl Histories {
     pub fn update_inbound_edge(&mut self, board: &Board, mov: Move, delta: i32) {
         let from = mov.from();
         let to = mov.history_to_square();
-        let moved = board.state.mailbox[to].expect("Cannot fail, move has been made.");
+        let moved = board.mailbox[to].expect("Cannot fail, move has been made.");
         debug_assert_eq!(moved.colour(), !board.turn());
-        let threats = board.history().last().unwrap().threats.all;
+        let threats = board.history().last().unwrap().state.threats.all;
         self.update_main_history_single(from, to, moved, threats, delta);
     }
 
@@ -76,16 +76,17 @@ impl Histories {
     }
 
     /// Update the continuation history counters for a single move.
+    /// `ss` is the search stack up to and including the current ply.
     pub fn update_cont_hist_single(
         &mut self,
         ctx: UpdateCtx,
-        ss: &[StackFrame; MAX_DEPTH + 1],
+        ss: &[StackFrame],
         to: Square,
         piece: Piece,
         depth: i32,
-        height: usize,
         good: bool,
     ) {
+        let height = ss.len() - 1;
         let conf = &ctx.info.conf;
         let plies_back = [
             (1, conf.cont1_stat_score_mul, &conf.cont1_history),
@@ -93,7 +94,7 @@ impl Histories {
             // (3, conf.cont3_stat_score_mul, &conf.cont3_history),
             (4, conf.cont4_stat_score_mul, &conf.cont4_history),
             // (5, conf.cont5_stat_score_mul, &conf.cont5_history),
-            // (6, conf.cont6_stat_score_mul, &conf.cont6_history),
+            (6, conf.cont6_stat_score_mul, &conf.cont6_history),
         ];
 
         let mut sum = 0;
@@ -132,14 +133,14 @@ impl Histories {
         let conf = &ctx.info.conf;
         let from = m.from();
         let to = m.history_to_square();
-        let moved = ctx.board.state.mailbox[from].unwrap();
+        let moved = ctx.board.mailbox[from].unwrap();
         let threats = ctx.board.state.threats.all;
 
         let main_delta = history_delta(&conf.main_history, depth, good);
         let pawn_delta = history_delta(&conf.pawn_history, depth, good);
 
         self.update_main_history_single(from, to, moved, threats, main_delta);
-        self.update_cont_hist_single(ctx, ss, to, moved, depth, height, good);
+        self.update_cont_hist_single(ctx, &ss[..=height], to, moved, depth, good);
         self.update_pawn_history_single(ctx, to, moved, pawn_delta);
     }
 
@@ -170,7 +171,7 @@ impl Histories {
         let deltas =
             [false, true].map(|good| history_delta(&ctx.info.conf.tactical_history, depth, good));
         for &m in moves {
-            let piece_moved = ctx.board.state.mailbox[m.from()].unwrap();
+            let piece_moved = ctx.board.mailbox[m.from()].unwrap();
             let capture = caphist_piece_type(ctx.board, m);
             let to = m.to();
             let to_threat = threats.contains_square(to);
@@ -188,6 +189,29 @@ impl ThreadData<'_> {
         self.killer_move_table[idx] = Some(m);
     }
 
+    /// The piece and destination square of the move that led to the current position,
+    /// unless that was a null move or we're at the root.
+    fn previous_move_target(&self) -> Option<(Piece, Square)> {
+        let height = self.board.height();
+        let prev = self.ss[height.checked_sub(1)?].searching?;
+        let to = prev.history_to_square();
+        let moved = self.board.mailbox[to]?;
+        Some((moved, to))
+    }
+
+    /// The move that last refuted the move that led to the current position.
+    pub fn counter_move(&self) -> Option<Move> {
+        let (moved, to) = self.previous_move_target()?;
+        self.histories.counter_moves[moved][to]
+    }
+
+    /// Record a quiet move as the refutation of the move that led to the current position.
+    pub fn insert_counter_move(&mut self, m: Move) {
+        if let Some((moved, to)) = self.previous_move_target() {
+            self.histories.counter_moves[moved][to] = Some(m);
+        }
+    }
+
     /// Update the correction history for a pawn pattern.
     pub fn update_correction_history(&mut self, depth: i32, tt_complexity: i32, diff: i32) {
         #![allow(clippy::cast_possible_truncation, clippy::cast_precision_loss)]
@@ -215,6 +239,8 @@ impl ThreadData<'_> {
         let nonpawn_black = nonpawn_black.get_mut(us, keys.non_pawn[Black]);
         let minor = self.minor_corrhist.get_mut(us, keys.minor);
         let major = self.major_corrhist.get_mut(us, keys.major);
+        let threat =
+            threat_corrhist_key(&self.board).map(|key| self.threat_corrhist.get_mut(us, key));
 
         let update = move |entry: &mut i16| {
             update_correction(entry, bonus);
@@ -225,6 +251,9 @@ impl ThreadData<'_> {
         update(nonpawn_black);
         update(minor);
         update(major);
+        if let Some(threat) = threat {
+            update(threat);
+        }
 
         if height > 2 {
             let index = cont_corrhist_index(&self.ss, height, 2);
@@ -252,6 +281,8 @@ impl ThreadData<'_> {
         let black = black.get(us, keys.non_pawn[Black]);
         let minor = self.minor_corrhist.get(us, keys.minor);
         let major = self.major_corrhist.get(us, keys.major);
+        let threat =
+            threat_corrhist_key(&self.board).map_or(0, |key| self.threat_corrhist.get(us, key));
 
         let cont12 = if height > 2 {
             self.cont_corrhist
@@ -270,6 +301,7 @@ impl ThreadData<'_> {
         let adjustment = pawn * i64::from(self.info.conf.pawn_corrhist_weight)
             + major * i64::from(self.info.conf.major_corrhist_weight)
             + minor * i64::from(self.info.conf.minor_corrhist_weight)
+            + threat * i64::from(self.info.conf.threat_corrhist_weight)
             + (white + black) * i64::from(self.info.conf.nonpawn_corrhist_weight)
             + cont12 * i64::from(self.info.conf.continuation_12_corrhist_weight)
             + cont14 * i64::from(self.info.conf.continuation_14_corrhist_weight);
@@ -286,6 +318,20 @@ fn cont_corrhist_index(ss: &[StackFrame; MAX_DEPTH + 1], height: usize, back: us
     PIECE_KEYS[ch1.piece][ch1.to] ^ PIECE_KEYS[ch2.piece][ch2.to]
 }
 
+/// Compute the threat-correction-history key from the pieces
+/// of the side to move that the opponent is attacking. Positions
+/// with nothing attacked have no key, rather than all sharing one entry.
+fn threat_corrhist_key(board: &Board) -> Option<u64> {
+    let attacked = board.state.threats.all & board.state.bbs.colours[board.turn()];
+    if attacked == SquareSet::EMPTY {
+        return None;
+    }
+    Some(attacked.into_iter().fold(0, |key, sq| {
+        let piece = board.mailbox[sq].expect("attacked square is occupied");
+        key ^ PIECE_KEYS[piece][sq]
+    }))
+}
+
 pub fn caphist_piece_type(pos: &Board, mv: Move) -> PieceType {
     if mv.is_ep() || mv.is_promo() {
         // it's fine to make all promos of type PAWN,
@@ -295,7 +341,7 @@ pub fn caphist_piece_type(pos: &Board, mv: Move) -> PieceType {
         PieceType::Pawn
     } else {
         debug_assert!(!mv.is_castle(), "shouldn't be using caphist for castling.");
-        pos.state.mailbox[mv.to()]
+        pos.mailbox[mv.to()]
             .expect("you weren't capturing anything!")
             .piece_type()
     }
@@ -1,6 +1,9 @@
 use std::ops::{Deref, DerefMut};
 
-use crate::{chess::piece::Colour, search::parameters::HistoryConfig};
+use crate::{
+    chess::{chessmove::Move, piece::Colour},
+    search::parameters::HistoryConfig,
+};
 
 #[inline]
 pub fn history_bonus(conf: &HistoryConfig, depth: i32) -> i32 {
@@ -50,8 +53,10 @@ fn gravity_update<const MAX: i32>(val: &mut i16, delta: i32) {
 fn gravity_update_with_modulator<const MAX: i32>(val: &mut i16, modulator: i32, delta: i32) {
     #![allow(clippy::cast_possible_truncation)]
     const { assert!(MAX < i16::MAX as i32 * 3 / 4) }
-    let new = i32::from(*val) + delta - modulator * delta.abs() / MAX;
-    *val = i32::clamp(new, -MAX, MAX) as i16;
+    // widen, so that large modulators or deltas can't overflow the intermediate product.
+    let (val64, modulator, delta) = (i64::from(*val), i64::from(modulator), i64::from(delta));
+    let new = val64 + delta - modulator * delta.abs() / i64::from(MAX);
+    *val = i64::clamp(new, -i64::from(MAX), i64::from(MAX)) as i16;
 }
 
 #[repr(transparent)]
@@ -147,6 +152,46 @@ impl<T> DerefMut for ThreatsHistoryTable<T> {
     }
 }
 
+/// The quiet move that last refuted each move, indexed by the piece moved and its destination.
+#[repr(transparent)]
+pub struct CounterMoveTable {
+    table: [[Option<Move>; 64]; 12],
+}
+
+impl CounterMoveTable {
+    pub fn boxed() -> Box<Self> {
+        #![allow(clippy::cast_ptr_alignment)]
+        // SAFETY: we're allocating a zeroed block of memory, and then casting it to a Box<Self>
+        // this is fine! because Move is a NonZeroU16, so a zeroed Option<Move> is None.
+        unsafe {
+            let layout = std::alloc::Layout::new::<Self>();
+            let ptr = std::alloc::alloc_zeroed(layout);
+            if ptr.is_null() {
+                std::alloc::handle_alloc_error(layout);
+            }
+            Box::from_raw(ptr.cast())
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.table.as_flattened_mut().fill(None);
+    }
+}
+
+impl Deref for CounterMoveTable {
+    type Target = [[Option<Move>; 64]; 12];
+
+    fn deref(&self) -> &Self::Target {
+        &self.table
+    }
+}
+
+impl DerefMut for CounterMoveTable {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.table
+    }
+}
+
 #[repr(transparent)]
 pub struct CaptureHistoryTable {
     table: [[PieceToTable; 6]; 2],
@@ -322,3 +367,31 @@ impl DerefMut for CorrectionHistoryTable {
         &mut self.table
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn history_updates_stay_bounded() {
+        for delta in [i32::MAX, i32::MIN, i32::MAX / 2, -(i32::MAX / 2), 1, -1] {
+            let mut val = 0;
+            for _ in 0..1000 {
+                update_history(&mut val, delta);
+                assert!((-MAX_HISTORY..=MAX_HISTORY).contains(&i32::from(val)));
+            }
+            let mut val = 0;
+            for _ in 0..1000 {
+                update_cont_history(&mut val, i32::MAX, delta);
+                assert!((-MAX_HISTORY..=MAX_HISTORY).contains(&i32::from(val)));
+            }
+            let mut val = 0;
+            for _ in 0..1000 {
+                update_correction(&mut val, delta);
+                assert!(
+                    (-CORRECTION_HISTORY_MAX..=CORRECTION_HISTORY_MAX).contains(&i32::from(val))
+                );
+            }
+        }
+    }
+}
@@ -120,44 +120,97 @@ impl Image {
         self.height
     }
 
-    // Write the image to a TGA file with the given name.
-    // Format specification: http://www.gamers.org/dEngine/quake3/TGA.txt
-    pub fn save_as_tga(&self, filename: impl AsRef<Path>) {
+    // Write the image to a PNG file with the given name.
+    // The pixel data is stored uncompressed, which keeps the encoder small at the
+    // cost of file size. Format specification: https://www.w3.org/TR/png/
+    pub fn save_as_png(&self, filename: impl AsRef<Path>) -> std::io::Result<()> {
         #![allow(clippy::cast_possible_truncation)]
-        let file = File::create(&filename).unwrap();
-        // use a buffered writer
-        let mut writer = BufWriter::new(file);
-
-        // Write the TGA header.
-        #[rustfmt::skip]
-        let header: [u8; 18] = [
-            0, // no image ID
-            0, // no colour map
-            2, // uncompressed 24-bit image
-            0, 0, 0, 0, 0, // empty colour map specification
-            0, 0, // X origin
-            0, 0, // Y origin
-            self.width() as u8, (self.width() >> 8) as u8, // width
-            self.height() as u8, (self.height() >> 8) as u8, // height
-            24, // bits per pixel
-            0, // image descriptor
-        ];
-
-        writer.write_all(&header).unwrap();
+        // the largest amount of data in a stored deflate block.
+        const MAX_STORED: usize = 65535;
 
+        let mut raw = Vec::with_capacity(self.height() * (1 + 3 * self.width()));
         for row in self.rows() {
+            raw.push(0); // no filter
             for &loc in row {
-                let pixel: [u8; 3] = [
-                    (loc & 0xFF) as u8,
-                    ((loc >> 8) & 0xFF) as u8,
-                    ((loc >> 16) & 0xFF) as u8,
-                ];
-                writer.write_all(&pixel).unwrap();
+                raw.extend_from_slice(&[(loc >> 16) as u8, (loc >> 8) as u8, loc as u8]);
             }
         }
 
-        writer.flush().unwrap();
+        let mut zlib = vec![0x78, 0x01];
+        let mut blocks = raw.chunks(MAX_STORED).peekable();
+        if blocks.peek().is_none() {
+            zlib.extend_from_slice(&[1, 0, 0, 0xFF, 0xFF]);
+        }
+        while let Some(block) = blocks.next() {
+            let len = block.len() as u16;
+            zlib.push(u8::from(blocks.peek().is_none()));
+            zlib.extend_from_slice(&len.to_le_bytes());
+            zlib.extend_from_slice(&(!len).to_le_bytes());
+            zlib.extend_from_slice(block);
+        }
+        zlib.extend_from_slice(&adler32(&raw).to_be_bytes());
+
+        let mut header = Vec::with_capacity(13);
+        header.extend_from_slice(&(self.width() as u32).to_be_bytes());
+        header.extend_from_slice(&(self.height() as u32).to_be_bytes());
+        header.extend_from_slice(&[
+            8, // bits per channel
+            2, // truecolour
+            0, // deflate
+            0, // adaptive filtering
+            0, // not interlaced
+        ]);
+
+        let file = File::create(&filename)?;
+        let mut writer = BufWriter::new(file);
+        writer.write_all(b"\x89PNG\r\n\x1a\n")?;
+        write_png_chunk(&mut writer, *b"IHDR", &header)?;
+        write_png_chunk(&mut writer, *b"IDAT", &zlib)?;
+        write_png_chunk(&mut writer, *b"IEND", &[])?;
+        writer.flush()?;
 
         println!("Wrote {}", filename.as_ref().display());
+        Ok(())
+    }
+}
+
+fn write_png_chunk(writer: &mut impl Write, kind: [u8; 4], data: &[u8]) -> std::io::Result<()> {
+    #![allow(clippy::cast_possible_truncation)]
+    writer.write_all(&(data.len() as u32).to_be_bytes())?;
+    writer.write_all(&kind)?;
+    writer.write_all(data)?;
+    let crc = crc32(kind.iter().chain(data));
+    writer.write_all(&crc.to_be_bytes())
+}
+
+fn crc32<'a>(bytes: impl IntoIterator<Item = &'a u8>) -> u32 {
+    let mut crc = !0u32;
+    for &byte in bytes {
+        crc ^= u32::from(byte);
+        for _ in 0..8 {
+            crc = (crc >> 1) ^ (0xEDB8_8320 & (crc & 1).wrapping_neg());
+        }
+    }
+    !crc
+}
+
+fn adler32(bytes: &[u8]) -> u32 {
+    const MOD: u32 = 65521;
+    let (mut a, mut b) = (1u32, 0u32);
+    for &byte in bytes {
+        a = (a + u32::from(byte)) % MOD;
+        b = (b + a) % MOD;
+    }
+    (b << 16) | a
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checksums() {
+        assert_eq!(crc32(b"IEND"), 0xAE42_6082);
+        assert_eq!(adler32(b"Wikipedia"), 0x11E6_0398);
     }
 }
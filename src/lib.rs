@@ -0,0 +1,238 @@
+#![warn(clippy::all, clippy::pedantic, clippy::nursery, clippy::cargo)]
+#![allow(
+    clippy::missing_const_for_fn,
+    clippy::must_use_candidate,
+    clippy::return_self_not_must_use,
+    clippy::missing_errors_doc,
+    clippy::missing_panics_doc
+)]
+#![deny(missing_docs, clippy::undocumented_unsafe_blocks)]
+
+//! Viridithas, a UCI chess engine written in Rust.
+//!
+//! The engine is also usable as a library. [`chess`] holds the board representation and
+//! move generation, [`nnue`] the evaluation network, [`search`] the search itself, which
+//! [`search::Search`] runs from code, and [`transpositiontable`] the hash table that search
+//! threads share.
+//!
+//! ```
+//! use viridithas::chess::board::Board;
+//!
+//! let mut board = Board::startpos();
+//! let e4 = board.parse_san("e4").unwrap();
+//! board.make_move_simple(e4);
+//! assert_eq!(board.legal_moves().len(), 20);
+//! ```
+
+#[macro_use]
+mod macros;
+
+#[cfg(feature = "datagen")]
+mod datagen;
+
+#[cfg(feature = "stats")]
+pub mod stats;
+
+mod bench;
+mod book;
+pub mod chess;
+mod cli;
+mod cuckoo;
+mod endgame;
+mod errors;
+mod evalcache;
+mod evaluation;
+mod history;
+mod historytable;
+mod image;
+mod lookups;
+mod movepicker;
+pub mod nnue;
+mod perft;
+#[cfg(feature = "python")]
+mod python;
+mod rng;
+pub mod search;
+mod searchinfo;
+mod stack;
+mod strength;
+mod tablebases;
+mod term;
+mod threadlocal;
+mod threadpool;
+mod timemgmt;
+pub mod transpositiontable;
+mod uci;
+mod util;
+#[cfg(feature = "wasm")]
+mod wasm;
+mod xboard;
+
+#[cfg(feature = "datagen")]
+use cli::Subcommands::{Analyse, CountPositions, Datagen, Relabel, Rescale, Splat};
+use cli::Subcommands::{
+    Bench, CompareEval, EvalStats, MakeBook, Merge, NNUEDryRun, Perft, Quantise, Spsa, Verbatim,
+    VerifyNNUE, VisNNUE,
+};
+
+use anyhow::Context;
+
+/// The name of the engine.
+pub static NAME: &str = "Viridithas";
+/// The version of the engine.
+pub static VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Run the engine with the command-line arguments of this process: a subcommand if one is
+/// given, and otherwise the UCI or xboard protocol on standard input and output.
+#[allow(clippy::too_many_lines)]
+pub fn run() -> anyhow::Result<()> {
+    if std::env::args_os().len() == 1 {
+        // fast path to UCI:
+        return Ok(uci::main_loop(
+            &search::parameters::Config::default(),
+            None,
+        )?);
+    }
+
+    let cli = <cli::Cli as clap::Parser>::parse();
+
+    let config = match cli.preset.as_deref() {
+        Some(name) => search::parameters::Config::preset(name).with_context(|| {
+            format!(
+                "unknown preset \"{name}\", expected one of: {}",
+                search::parameters::Config::PRESETS.join(", ")
+            )
+        })?,
+        None => search::parameters::Config::default(),
+    };
+
+    match cli.subcommand {
+        Some(Bench { depth, threads }) => {
+            let nnue_params = nnue::network::NNUEParams::decompress_and_alloc()?;
+            Ok(uci::bench(
+                "openbench",
+                &config,
+                nnue_params,
+                depth,
+                threads,
+            )?)
+        }
+        Some(Perft {
+            fen: Some(fen),
+            depth: Some(depth),
+            ..
+        }) => {
+            let board = chess::board::Board::from_fen(&fen)
+                .with_context(|| format!("invalid FEN \"{fen}\""))?;
+            perft::divide(&board, depth);
+            Ok(())
+        }
+        Some(Perft { suite, .. }) => perft::gamut(suite),
+        Some(Quantise { input, output }) => nnue::network::quantise(&input, &output),
+        Some(Merge { input, output }) => nnue::network::merge(&input, &output),
+        Some(Verbatim { output }) => nnue::network::dump_verbatim(&output),
+        Some(VisNNUE { output }) => nnue::network::visualise_nnue(&output),
+        Some(NNUEDryRun) => nnue::network::dry_run(),
+        Some(VerifyNNUE {
+            games,
+            seed,
+            random_weights,
+        }) => nnue::network::verify::verify(games, seed, random_weights),
+        Some(Spsa { json }) => {
+            if json {
+                println!(
+                    "{}",
+                    search::parameters::Config::default().emit_json_for_spsa()
+                );
+            } else {
+                println!(
+                    "{}",
+                    search::parameters::Config::default().emit_csv_for_spsa()
+                );
+            }
+            Ok(())
+        }
+        Some(EvalStats {
+            input,
+            output,
+            bucket,
+        }) => evaluation::eval_stats(&input, output.as_deref(), bucket),
+        Some(CompareEval { input, worst }) => evaluation::compare_eval(&input, worst),
+        Some(MakeBook {
+            input,
+            output,
+            max_ply,
+            min_games,
+        }) => book::builder::make_book(&input, &output, max_ply, min_games),
+        #[cfg(feature = "datagen")]
+        Some(Analyse { input }) => datagen::dataset_stats(&input),
+        #[cfg(feature = "datagen")]
+        Some(CountPositions { input }) => datagen::dataset_count(&input),
+        #[cfg(feature = "datagen")]
+        Some(Rescale {
+            scale,
+            input,
+            output,
+        }) => datagen::run_rescale(&input, &output, scale),
+        #[cfg(feature = "datagen")]
+        Some(Relabel { input, output }) => datagen::run_relabel(&input, &output),
+        #[cfg(feature = "datagen")]
+        Some(Splat {
+            input,
+            marlinformat,
+            pgn,
+            output,
+            limit,
+            cfg_path,
+            annotate,
+        }) => {
+            if pgn {
+                datagen::run_topgn(&input, &output, limit, annotate)
+            } else {
+                datagen::run_splat(&input, &output, cfg_path.as_deref(), marlinformat, limit)
+            }
+        }
+        #[cfg(feature = "datagen")]
+        Some(Datagen {
+            resume: Some(data_dir),
+            ..
+        }) => datagen::resume_data_main(&data_dir),
+        #[cfg(feature = "datagen")]
+        Some(Datagen {
+            games,
+            threads,
+            tbs,
+            book,
+            book_order,
+            book_games,
+            nodes,
+            dfrc,
+            frc,
+            random_plies,
+            opening_eval_bound,
+            temperature,
+            seed,
+            pgn,
+            resume: None,
+        }) => datagen::gen_data_main(datagen::DataGenOptionsBuilder {
+            games: games.context("--games is required")?,
+            threads: threads.context("--threads is required")?,
+            tbs,
+            book,
+            book_order,
+            book_games,
+            nodes: nodes.context("--nodes is required")?,
+            dfrc,
+            frc,
+            random_plies,
+            opening_eval_bound,
+            temperature,
+            seed,
+            pgn,
+        }),
+        None => match cli.protocol {
+            cli::Protocol::Uci => Ok(uci::main_loop(&config, cli.hash_file.as_deref())?),
+            cli::Protocol::Xboard => Ok(xboard::main_loop(&config, cli.hash_file.as_deref())?),
+        },
+    }
+}
@@ -1,6 +1,16 @@
 #![allow(clippy::cast_possible_truncation)]
 
-use crate::rng::XorShiftState;
+use std::sync::LazyLock;
+
+use crate::{
+    chess::{
+        board::movegen::{king_attacks, pawn_attacks_by},
+        piece::Colour,
+        squareset::SquareSet,
+        types::{File, Rank, Square},
+    },
+    rng::XorShiftState,
+};
 
 /// Implements a C-style for loop, for use in const fn.
 #[macro_export]
@@ -75,6 +85,171 @@ pub static CASTLE_KEYS: [u64; 16] = init_hash_keys().2;
 pub const SIDE_KEY: u64 = init_hash_keys().3;
 pub static HM_CLOCK_KEYS: [u64; 256] = init_hash_keys().4;
 
+/// The number of positions in the KPK bitbase: the pawn on ranks two to seven
+/// of files A to D, both kings anywhere, and either side to move.
+const KPK_POSITIONS: usize = 24 * 64 * 64 * 2;
+
+/// One bit per KPK position, set if the side with the pawn wins.
+/// Generated by retrograde analysis on first use.
+static KPK_BITBASE: LazyLock<Box<[u64]>> = LazyLock::new(generate_kpk);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum KpkResult {
+    Invalid,
+    Unknown,
+    Draw,
+    Win,
+}
+
+/// Index a KPK position, with the pawn on files A to D.
+/// Squares are from the perspective of the side with the pawn, as if it were white.
+fn kpk_index(strong_to_move: bool, strong_king: Square, weak_king: Square, pawn: Square) -> usize {
+    let pawn = (pawn.rank() as usize - 1) * 4 + pawn.file() as usize;
+    usize::from(strong_to_move) | weak_king.index() << 1 | strong_king.index() << 7 | pawn << 13
+}
+
+/// The inverse of `kpk_index`.
+fn kpk_decode(index: usize) -> (bool, Square, Square, Square) {
+    let square = |i: usize| Square::new_clamped(i as u8);
+    let pawn = index >> 13;
+    (
+        index & 1 != 0,
+        square(index >> 7 & 63),
+        square(index >> 1 & 63),
+        square((pawn / 4 + 1) * 8 + pawn % 4),
+    )
+}
+
+/// Classify a position without looking at its successors, where possible.
+fn kpk_initial(index: usize) -> KpkResult {
+    let (strong_to_move, strong_king, weak_king, pawn) = kpk_decode(index);
+    let pawn_attacks = pawn_attacks_by(pawn.as_set(), Colour::White);
+    if Square::distance(strong_king, weak_king) <= 1
+        || strong_king == pawn
+        || weak_king == pawn
+        || strong_to_move && pawn_attacks.contains_square(weak_king)
+    {
+        return KpkResult::Invalid;
+    }
+    if strong_to_move && pawn.rank() == Rank::Seven {
+        // the pawn can promote, and the queen can't be taken.
+        let queening = pawn.add(8).expect("pawn on the seventh rank");
+        if queening != strong_king
+            && (Square::distance(weak_king, queening) > 1
+                || Square::distance(strong_king, queening) == 1)
+        {
+            return KpkResult::Win;
+        }
+    }
+    if !strong_to_move {
+        let guarded = king_attacks(strong_king) | pawn_attacks;
+        let in_check = pawn_attacks.contains_square(weak_king);
+        let escapes = king_attacks(weak_king) & !guarded;
+        // stalemate, or the pawn can be taken for free.
+        if !in_check && escapes == SquareSet::EMPTY
+            || (king_attacks(weak_king) & !king_attacks(strong_king)).contains_square(pawn)
+        {
+            return KpkResult::Draw;
+        }
+    }
+    KpkResult::Unknown
+}
+
+/// Classify a position from the results of its successors.
+fn kpk_classify(results: &[KpkResult], index: usize) -> KpkResult {
+    let (strong_to_move, strong_king, weak_king, pawn) = kpk_decode(index);
+    let (good, bad) = if strong_to_move {
+        (KpkResult::Win, KpkResult::Draw)
+    } else {
+        (KpkResult::Draw, KpkResult::Win)
+    };
+    let mut unknown = false;
+    let mut successors = Vec::with_capacity(10);
+    if strong_to_move {
+        for to in king_attacks(strong_king) {
+            successors.push(kpk_index(false, to, weak_king, pawn));
+        }
+        if pawn.rank() < Rank::Seven
+            && let Some(push) = pawn
+                .add(8)
+                .filter(|&sq| sq != strong_king && sq != weak_king)
+        {
+            successors.push(kpk_index(false, strong_king, weak_king, push));
+            if pawn.rank() == Rank::Two
+                && let Some(double) = push
+                    .add(8)
+                    .filter(|&sq| sq != strong_king && sq != weak_king)
+            {
+                successors.push(kpk_index(false, strong_king, weak_king, double));
+            }
+        }
+    } else {
+        for to in king_attacks(weak_king) {
+            successors.push(kpk_index(true, strong_king, to, pawn));
+        }
+    }
+    for successor in successors {
+        match results[successor] {
+            r if r == good => return good,
+            KpkResult::Unknown => unknown = true,
+            _ => (),
+        }
+    }
+    // a position with no legal successors is checkmate or stalemate, and the
+    // stalemates were found in the initial pass.
+    if unknown { KpkResult::Unknown } else { bad }
+}
+
+fn generate_kpk() -> Box<[u64]> {
+    let mut results = (0..KPK_POSITIONS).map(kpk_initial).collect::<Vec<_>>();
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for index in 0..KPK_POSITIONS {
+            if results[index] == KpkResult::Unknown {
+                let result = kpk_classify(&results, index);
+                if result != KpkResult::Unknown {
+                    results[index] = result;
+                    changed = true;
+                }
+            }
+        }
+    }
+    let mut bits = vec![0; KPK_POSITIONS / 64].into_boxed_slice();
+    for (index, &result) in results.iter().enumerate() {
+        if result == KpkResult::Win {
+            bits[index / 64] |= 1 << (index % 64);
+        }
+    }
+    bits
+}
+
+/// Whether the side with the pawn wins a king and pawn against king ending.
+/// `pawn_side` is the colour of the side with the pawn.
+pub fn probe_kpk(
+    pawn_side: Colour,
+    strong_king: Square,
+    pawn: Square,
+    weak_king: Square,
+    side_to_move: Colour,
+) -> bool {
+    let normalise = |sq: Square| {
+        let sq = sq.relative_to(pawn_side);
+        if pawn.file() >= File::E {
+            sq.flip_file()
+        } else {
+            sq
+        }
+    };
+    let index = kpk_index(
+        side_to_move == pawn_side,
+        normalise(strong_king),
+        normalise(weak_king),
+        normalise(pawn),
+    );
+    KPK_BITBASE[index / 64] & 1 << (index % 64) != 0
+}
+
 #[cfg(test)]
 mod tests {
     #[test]
@@ -98,4 +273,34 @@ mod tests {
         let len_after = hashkeys.len();
         assert_eq!(len_before, len_after);
     }
+
+    #[test]
+    fn kpk_bitbase() {
+        use crate::{
+            chess::{piece::Colour, types::Square},
+            lookups::probe_kpk,
+        };
+        use Colour::{Black, White};
+        // king on the sixth in front of its pawn wins whoever is to move.
+        assert!(probe_kpk(White, Square::E6, Square::E5, Square::E8, White));
+        assert!(probe_kpk(White, Square::E6, Square::E5, Square::E8, Black));
+        // one rank further back, the side with the opposition wins.
+        for (king, pawn, defender) in [
+            (Square::E5, Square::E4, Square::E7),
+            (Square::D5, Square::D4, Square::D7),
+        ] {
+            assert!(!probe_kpk(White, king, pawn, defender, White));
+            assert!(probe_kpk(White, king, pawn, defender, Black));
+        }
+        // the same, with colours reversed.
+        assert!(!probe_kpk(Black, Square::E4, Square::E5, Square::E2, Black));
+        assert!(probe_kpk(Black, Square::E4, Square::E5, Square::E2, White));
+        // a rook pawn can't drive the king out of the corner.
+        assert!(!probe_kpk(White, Square::H1, Square::A4, Square::A8, White));
+        assert!(!probe_kpk(Black, Square::A8, Square::H5, Square::H1, Black));
+        // the pawn outruns the defending king, unless it can reach the square of the pawn.
+        assert!(probe_kpk(White, Square::H3, Square::A7, Square::H1, White));
+        assert!(probe_kpk(White, Square::H1, Square::B5, Square::F8, White));
+        assert!(!probe_kpk(White, Square::H1, Square::B5, Square::F8, Black));
+    }
 }
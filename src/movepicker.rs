@@ -12,7 +12,7 @@ use crate::{
     },
     history,
     historytable::{HASH_HISTORY_SIZE, MAX_HISTORY},
-    search::static_exchange_eval,
+    search::{parameters::Config, static_exchange_eval},
     stack::StackFrame,
     threadlocal::{Histories, ThreadData},
     util::MAX_DEPTH,
@@ -27,8 +27,12 @@ pub enum Stage {
     GenerateCaptures,
     YieldGoodCaptures,
     YieldKiller,
+    YieldCounterMove,
     GenerateQuiets,
     YieldRemaining,
+    EvasionTTMove,
+    GenerateEvasions,
+    YieldEvasions,
     Done,
 }
 
@@ -39,6 +43,7 @@ pub struct MovePicker {
     pub stage: Stage,
     tt_move: Option<Move>,
     killer: Option<Move>,
+    counter_move: Option<Move>,
     pub skip_quiets: bool,
     see_threshold: i32,
 }
@@ -62,24 +67,42 @@ fn fast_select(entries: &[Cell<MoveListEntry>]) -> Option<&Cell<MoveListEntry>>
 }
 
 impl MovePicker {
-    pub fn new(tt_move: Option<Move>, killer: Option<Move>, see_threshold: i32) -> Self {
+    pub fn new(
+        tt_move: Option<Move>,
+        killer: Option<Move>,
+        counter_move: Option<Move>,
+        see_threshold: i32,
+    ) -> Self {
         Self {
             moves: MoveList::new(),
             index: 0,
             stage: Stage::TTMove,
             tt_move,
             killer,
+            counter_move,
             skip_quiets: false,
             see_threshold,
         }
     }
 
+    /// A picker for a position where the side to move is in check, which tries
+    /// only the legal replies to the check.
+    pub fn evasions(tt_move: Option<Move>, see_threshold: i32) -> Self {
+        Self {
+            stage: Stage::EvasionTTMove,
+            ..Self::new(tt_move, None, None, see_threshold)
+        }
+    }
+
     /// Select the next move to try. Returns None if there are no more moves to try.
     #[allow(clippy::cognitive_complexity)]
     pub fn next(&mut self, t: &ThreadData) -> Option<Move> {
         if self.stage == Stage::Done {
             return None;
         }
+        if self.stage >= Stage::EvasionTTMove {
+            return self.next_evasion(t);
+        }
         if self.stage == Stage::TTMove {
             self.stage = Stage::GenerateCaptures;
             if let Some(tt_move) = self.tt_move {
@@ -118,7 +141,7 @@ impl MovePicker {
             };
         }
         if self.stage == Stage::YieldKiller {
-            self.stage = Stage::GenerateQuiets;
+            self.stage = Stage::YieldCounterMove;
             if !self.skip_quiets
                 && self.killer != self.tt_move
                 && let Some(killer) = self.killer
@@ -128,6 +151,18 @@ impl MovePicker {
                 return Some(killer);
             }
         }
+        if self.stage == Stage::YieldCounterMove {
+            self.stage = Stage::GenerateQuiets;
+            if !self.skip_quiets
+                && self.counter_move != self.tt_move
+                && self.counter_move != self.killer
+                && let Some(counter_move) = self.counter_move
+                && t.board.is_pseudo_legal(counter_move)
+            {
+                debug_assert!(!t.board.is_tactical(counter_move));
+                return Some(counter_move);
+            }
+        }
         if self.stage == Stage::GenerateQuiets {
             self.stage = Stage::YieldRemaining;
             if !self.skip_quiets {
@@ -146,6 +181,37 @@ impl MovePicker {
         None
     }
 
+    fn next_evasion(&mut self, t: &ThreadData) -> Option<Move> {
+        if self.stage == Stage::EvasionTTMove {
+            self.stage = Stage::GenerateEvasions;
+            if let Some(tt_move) = self.tt_move {
+                return Some(tt_move);
+            }
+        }
+        if self.stage == Stage::GenerateEvasions {
+            self.stage = Stage::YieldEvasions;
+            t.board.generate_evasions(&mut self.moves);
+            // captures and promotions first, so that they're ordered as in the main stages.
+            let mut tactical = 0;
+            for i in 0..self.moves.len() {
+                if t.board.is_tactical(self.moves[i].mov) {
+                    self.moves.swap(tactical, i);
+                    tactical += 1;
+                }
+            }
+            let (captures, quiets) = self.moves.split_at_mut(tactical);
+            Self::score_captures(&t.board, &t.histories, captures);
+            Self::score_quiets(&t.board, &t.histories, &t.ss, quiets);
+        }
+        if self.stage == Stage::YieldEvasions {
+            if let Some(m) = self.yield_once(t) {
+                return Some(m.mov);
+            }
+            self.stage = Stage::Done;
+        }
+        None
+    }
+
     /// Perform iterations of partial insertion sort.
     /// Extracts the best move from the unsorted portion of the movelist,
     /// or returns None if there are no more moves to try.
@@ -165,15 +231,13 @@ impl MovePicker {
                 best.score,
                 MIN_WINNING_SEE_SCORE
             );
-            // test if this is a potentially-winning capture that's yet to be SEE-ed:
-            if best.score >= MIN_WINNING_SEE_SCORE
-                && !static_exchange_eval(&t.board, &t.info.conf, best.mov, self.see_threshold)
-            {
-                // if it fails SEE, then we want to try the next best move, and de-mark this one.
-                best_entry_ref.set(MoveListEntry {
-                    score: best.score - WINNING_CAPTURE_BONUS,
-                    mov: best.mov,
-                });
+            // classify potentially-winning captures only when they reach the front,
+            // so that a cutoff saves the exchange evaluation of the rest.
+            let classified =
+                Self::classify_capture(&t.board, &t.info.conf, best, self.see_threshold);
+            if classified.score != best.score {
+                // it's losing, so we want to try the next best move, and de-mark this one.
+                best_entry_ref.set(classified);
                 continue;
             }
 
@@ -189,7 +253,10 @@ impl MovePicker {
                 // and we're skipping quiet moves, so we're done.
                 return None;
             }
-            if !(Some(best.mov) == self.tt_move || Some(best.mov) == self.killer) {
+            if !(Some(best.mov) == self.tt_move
+                || Some(best.mov) == self.killer
+                || Some(best.mov) == self.counter_move)
+            {
                 return Some(best);
             }
         }
@@ -198,6 +265,30 @@ impl MovePicker {
         None
     }
 
+    /// Classify a capture scored by [`Self::score_captures`] as winning or losing, by whether
+    /// it passes static exchange evaluation at `see_threshold`. A winning capture is returned
+    /// as it was, still scored at least [`MIN_WINNING_SEE_SCORE`] so that it's yielded in the
+    /// good-captures stage; a losing one has [`WINNING_CAPTURE_BONUS`] taken off its score,
+    /// which holds it back until after the quiet moves. Entries that aren't scored as
+    /// potentially winning are returned unchanged.
+    pub fn classify_capture(
+        board: &Board,
+        conf: &Config,
+        entry: MoveListEntry,
+        see_threshold: i32,
+    ) -> MoveListEntry {
+        if entry.score >= MIN_WINNING_SEE_SCORE
+            && !static_exchange_eval(board, conf, entry.mov, see_threshold)
+        {
+            MoveListEntry {
+                score: entry.score - WINNING_CAPTURE_BONUS,
+                mov: entry.mov,
+            }
+        } else {
+            entry
+        }
+    }
+
     pub fn score_quiets(
         board: &Board,
         histories: &Histories,
@@ -206,8 +297,8 @@ impl MovePicker {
     ) {
         let height = board.height();
 
-        let cont_blocks =
-            [1, 2].map(|i| (height > i).then(|| &histories.continuation[ss[height - i].ch_idx]));
+        let cont_blocks = [1, 2, 4, 6]
+            .map(|i| (height > i).then(|| &histories.continuation[ss[height - i].ch_idx]));
 
         let threats = board.state.threats.all;
         #[expect(clippy::cast_possible_truncation)]
@@ -227,7 +318,7 @@ impl MovePicker {
 
         for m in ms {
             let from = m.mov.from();
-            let piece = board.state.mailbox[from].unwrap();
+            let piece = board.mailbox[from].unwrap();
             let to = m.mov.history_to_square();
             let from_threat = usize::from(threats.contains_square(from));
             let to_threat = usize::from(threats.contains_square(to));
@@ -302,7 +393,7 @@ impl MovePicker {
             let from = m.mov.from();
             let to = m.mov.to();
             let threat_to = threats.contains_square(to);
-            let piece = board.state.mailbox[from].unwrap();
+            let piece = board.mailbox[from].unwrap();
             let capture = history::caphist_piece_type(board, m.mov);
 
             // optimistically initialised with the winning-SEE score.
@@ -316,3 +407,68 @@ impl MovePicker {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::threadlocal::ThreadFixture;
+
+    #[test]
+    fn captures_are_classified_by_exchange() {
+        let board = Board::from_fen("4k3/2p5/3p4/7n/6P1/8/8/3QK3 w - - 0 1").unwrap();
+        let conf = Config::default();
+        let classify = |uci: &str| {
+            let entry = MoveListEntry {
+                mov: board.parse_uci(uci).unwrap(),
+                score: WINNING_CAPTURE_BONUS,
+            };
+            MovePicker::classify_capture(&board, &conf, entry, 0).score
+        };
+        // PxN, undefended, wins a piece.
+        assert_eq!(classify("g4h5"), WINNING_CAPTURE_BONUS);
+        // QxP, defended by a pawn, loses the queen.
+        assert!(classify("d1d6") < MIN_WINNING_SEE_SCORE);
+    }
+
+    #[test]
+    fn losing_captures_wait_for_the_quiet_stage() {
+        let board = Board::from_fen("4k3/2p5/3p4/7n/6P1/8/8/3QK3 w - - 0 1").unwrap();
+        let fixture = ThreadFixture::new();
+        let t = fixture.thread_data(board.clone());
+
+        let mut move_picker = MovePicker::new(None, None, None, 0);
+        let mut picked = Vec::new();
+        while let Some(m) = move_picker.next(&t) {
+            picked.push((m, move_picker.stage));
+        }
+        let stage_of = |uci: &str| {
+            let mv = board.parse_uci(uci).unwrap();
+            picked.iter().find(|&&(m, _)| m == mv).unwrap().1
+        };
+        // PxN, undefended, wins a piece, so it's tried first.
+        assert_eq!(picked[0].0, board.parse_uci("g4h5").unwrap());
+        assert_eq!(stage_of("g4h5"), Stage::YieldGoodCaptures);
+        // QxP, defended by a pawn, loses the queen, so it's held back until the quiets.
+        assert_eq!(stage_of("d1d6"), Stage::YieldRemaining);
+    }
+
+    #[test]
+    fn evasions_yield_every_legal_move_once() {
+        // the rook on e8 checks the king, which can step aside or have the check blocked.
+        let board = Board::from_fen("4r1k1/8/8/8/1B6/8/3P1P2/R3K2N w Q - 0 1").unwrap();
+        let fixture = ThreadFixture::new();
+        let t = fixture.thread_data(board.clone());
+
+        let mut move_picker = MovePicker::evasions(None, 0);
+        let mut picked = Vec::new();
+        while let Some(m) = move_picker.next(&t) {
+            picked.push(m);
+        }
+        let mut legal = board.legal_moves().to_vec();
+        picked.sort_unstable();
+        legal.sort_unstable();
+        assert_eq!(picked, legal);
+        // Kd1, Kf1, and the interposition Be7: castling out of check is illegal.
+        assert_eq!(picked.len(), 3);
+    }
+}
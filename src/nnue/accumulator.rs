@@ -40,6 +40,7 @@ mod simd {
             },
             simd::{self, I16_CHUNK},
         },
+        util::prefetch,
     };
 
     /// Apply add/subtract PSQT updates in place.
@@ -104,9 +105,6 @@ mod simd {
         king: Square,
         colour: Colour,
     ) {
-        #[cfg(target_arch = "x86_64")]
-        use std::arch::x86_64::{_MM_HINT_T0, _mm_prefetch};
-
         const REGISTERS: usize = 16;
         const UNROLL: usize = I16_CHUNK * REGISTERS;
 
@@ -123,18 +121,10 @@ mod simd {
             add_threat_indexes(updates, king, colour, &mut add_blocks, &mut sub_blocks);
             add_pawn_pawn_indexes(updates, king, colour, &mut add_blocks, &mut sub_blocks);
             for &offset in &add_blocks {
-                #[cfg(target_arch = "x86_64")]
-                _mm_prefetch(
-                    (*weights).as_ptr().add(offset as usize).cast::<i8>(),
-                    _MM_HINT_T0,
-                );
+                prefetch((*weights).as_ptr().add(offset as usize));
             }
             for &offset in &sub_blocks {
-                #[cfg(target_arch = "x86_64")]
-                _mm_prefetch(
-                    (*weights).as_ptr().add(offset as usize).cast::<i8>(),
-                    _MM_HINT_T0,
-                );
+                prefetch((*weights).as_ptr().add(offset as usize));
             }
             let mut registers = [simd::zero_i16(); REGISTERS];
             for i in 0..L1_SIZE / UNROLL {
@@ -281,9 +271,6 @@ mod simd {
         board: &Board,
         colour: Colour,
     ) {
-        #[cfg(target_arch = "x86_64")]
-        use std::arch::x86_64::{_MM_HINT_T0, _mm_prefetch};
-
         const REGISTERS: usize = 16;
         const UNROLL: usize = I16_CHUNK * REGISTERS;
 
@@ -300,11 +287,11 @@ mod simd {
 
             // add threat features
             for from in bb {
-                let attacker = board.state.mailbox[from].unwrap();
+                let attacker = board.mailbox[from].unwrap();
                 let threats =
                     occ & attacks_by_type(attacker, from, occ) & !bbs.pieces[PieceType::King];
                 for to in threats {
-                    let victim = board.state.mailbox[to].unwrap();
+                    let victim = board.mailbox[to].unwrap();
                     let (good, feature) = threat_index(colour, king, attacker, victim, from, to);
                     let len = indexes.len();
                     debug_assert!(len < indexes.capacity(), "OOB write");
@@ -345,11 +332,7 @@ mod simd {
             }
 
             for &offset in &indexes {
-                #[cfg(target_arch = "x86_64")]
-                _mm_prefetch(
-                    (*weights).as_ptr().add(offset as usize).cast::<i8>(),
-                    _MM_HINT_T0,
-                );
+                prefetch((*weights).as_ptr().add(offset as usize));
             }
 
             let mut registers = [simd::zero_i16(); REGISTERS];
@@ -6,9 +6,9 @@ mod vbmi;
 #[cfg(target_feature = "avx512vbmi")]
 pub use vbmi::*;
 
-#[cfg(not(any(target_feature = "neon", target_feature = "avx512vbmi")))]
+#[cfg(all(target_feature = "avx2", not(target_feature = "avx512vbmi")))]
 mod avx2;
-#[cfg(not(any(target_feature = "neon", target_feature = "avx512vbmi")))]
+#[cfg(all(target_feature = "avx2", not(target_feature = "avx512vbmi")))]
 pub use avx2::*;
 
 #[cfg(target_feature = "neon")]
@@ -16,6 +16,11 @@ mod neon;
 #[cfg(target_feature = "neon")]
 pub use neon::*;
 
+#[cfg(not(any(target_feature = "avx2", target_feature = "neon")))]
+mod generic;
+#[cfg(not(any(target_feature = "avx2", target_feature = "neon")))]
+pub use generic::*;
+
 use crate::{cfor, chess::piece::Piece};
 
 /// Bits that can be queried in order to extract masks from `BitRays`.
@@ -0,0 +1,109 @@
+//! A portable implementation of the ray-vector operations, for targets without a
+//! supported SIMD instruction set.
+
+use crate::{
+    chess::{piece::Piece, types::Square},
+    nnue::geometry::Bit,
+};
+
+use super::{BitRays, INCOMING_SLIDERS_MASK, INCOMING_THREATS_MASK, PERMUTATION, PIECE_TO_BIT};
+
+#[derive(Clone, Copy)]
+#[repr(transparent)]
+pub struct Vector {
+    pub raw: [u8; 64],
+}
+
+impl Vector {
+    #[expect(unused)]
+    pub fn flip(self) -> Self {
+        let mut raw = self.raw;
+        raw.rotate_left(32);
+        Self { raw }
+    }
+
+    /// The mask of the bytes for which `f` holds.
+    fn mask_by(self, f: impl Fn(u8) -> bool) -> BitRays {
+        let mut mask = 0;
+        for (i, &byte) in self.raw.iter().enumerate() {
+            mask |= u64::from(f(byte)) << i;
+        }
+        BitRays(mask)
+    }
+}
+
+pub struct Permutation {
+    pub indexes: Vector,
+}
+
+pub fn permutation_for(focus: Square) -> Permutation {
+    Permutation {
+        indexes: Vector {
+            raw: PERMUTATION[focus.index()],
+        },
+    }
+}
+
+fn permute_mailbox_inner(permutation: &Permutation, mailbox: &[u8; 64]) -> (Vector, Vector) {
+    let mut permuted = [0; 64];
+    let mut bits = [0; 64];
+    for (i, &index) in permutation.indexes.raw.iter().enumerate() {
+        // the 0×80 sentinel marks a slot that's off the board, which stays empty.
+        if index & 0x80 == 0 {
+            let piece = mailbox[usize::from(index & 63)];
+            permuted[i] = piece;
+            bits[i] = PIECE_TO_BIT[usize::from(piece & 15)].0;
+        }
+    }
+    (Vector { raw: permuted }, Vector { raw: bits })
+}
+
+fn mailbox_bytes(mailbox: &[Option<Piece>; 64]) -> [u8; 64] {
+    // Safety: `Option<Piece>` is a single byte, so the mailbox is 64 bytes.
+    unsafe { std::mem::transmute::<[Option<Piece>; 64], [u8; 64]>(*mailbox) }
+}
+
+pub fn permute_mailbox(
+    permutation: &Permutation,
+    mailbox: &[Option<Piece>; 64],
+) -> (Vector, Vector) {
+    permute_mailbox_inner(permutation, &mailbox_bytes(mailbox))
+}
+
+pub fn permute_mailbox_ignoring(
+    permutation: &Permutation,
+    mailbox: &[Option<Piece>; 64],
+    ignore: Square,
+) -> (Vector, Vector) {
+    // Safety: `Option<Piece>` is a single byte.
+    const NO_PIECE: u8 = unsafe { std::mem::transmute::<Option<Piece>, u8>(None) };
+    let mut mb = mailbox_bytes(mailbox);
+    mb[ignore.index()] = NO_PIECE;
+    permute_mailbox_inner(permutation, &mb)
+}
+
+pub fn closest_occupied(bits: Vector) -> BitRays {
+    let occupied = bits.mask_by(|b| b != 0).0;
+    let o = occupied | 0x8181_8181_8181_8181;
+    BitRays((o ^ o.wrapping_sub(0x0303_0303_0303_0303)) & occupied)
+}
+
+fn test_mask(bits: Vector, mask: &[Bit; 64]) -> BitRays {
+    let mut out = 0;
+    for (i, (&b, m)) in bits.raw.iter().zip(mask).enumerate() {
+        out |= u64::from(b & m.0 != 0) << i;
+    }
+    BitRays(out)
+}
+
+pub fn incoming_attackers(bits: Vector, closest: BitRays) -> BitRays {
+    test_mask(bits, &INCOMING_THREATS_MASK) & closest
+}
+
+pub fn incoming_sliders(bits: Vector, closest: BitRays) -> BitRays {
+    test_mask(bits, &INCOMING_SLIDERS_MASK) & closest & BitRays::NON_KNIGHT
+}
+
+pub fn test_bit(bits: Vector, bit: Bit) -> BitRays {
+    bits.mask_by(|b| b & bit.0 != 0)
+}
@@ -5,7 +5,7 @@ use std::arch::aarch64::*;
 use crate::{
     chess::{piece::Piece, types::Square},
     nnue::geometry::Bit,
-    util::Align64,
+    util::Align,
 };
 
 use super::{BitRays, INCOMING_SLIDERS_MASK, INCOMING_THREATS_MASK, PERMUTATION, PIECE_TO_BIT};
@@ -133,7 +133,7 @@ pub fn permute_mailbox_ignoring(
 ) -> (Vector, Vector) {
     const NO_PIECE: u8 = unsafe { std::mem::transmute::<Option<Piece>, u8>(None) };
     unsafe {
-        let iota = Align64([
+        let iota = Align([
             0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23,
             24, 25, 26, 27, 28, 29, 30, 31, 32, 33, 34, 35, 36, 37, 38, 39, 40, 41, 42, 43, 44, 45,
             46, 47, 48, 49, 50, 51, 52, 53, 54, 55, 56, 57, 58, 59, 60, 61, 62, 63,
@@ -1,5 +1,8 @@
+//! The efficiently updatable neural network that evaluates positions.
+
 mod accumulator;
 pub mod network;
+pub mod small;
 #[macro_use]
 mod simd;
-pub mod geometry;
+pub(crate) mod geometry;
@@ -1,17 +1,21 @@
+//! The network's weights, and the incrementally updated state used to evaluate positions.
+
 use std::{
     fmt::{Debug, Display},
-    fs::{File, OpenOptions},
+    fs::File,
     hash::Hasher,
     io::{BufReader, BufWriter, Write},
     mem::size_of,
     ops::Deref,
-    path::Path,
+    path::{Path, PathBuf},
     sync::{LazyLock, Mutex, OnceLock},
-    time::Duration,
 };
+#[cfg(not(target_family = "wasm"))]
+use std::{fs::OpenOptions, time::Duration};
 
 use anyhow::Context;
 use arrayvec::ArrayVec;
+#[cfg(not(target_family = "wasm"))]
 use memmap2::Mmap;
 
 use crate::{
@@ -23,16 +27,17 @@ use crate::{
         types::Square,
     },
     image::{self, Image},
-    nnue,
-    util::{Align, MAX_DEPTH},
+    util::{Align, Instant, MAX_DEPTH},
 };
 
 use super::accumulator::{self, Accumulator};
 
-pub mod feature;
-pub mod layers;
-pub mod pawn_updates;
-pub mod threat_updates;
+pub(crate) mod feature;
+pub(crate) mod header;
+pub(crate) mod layers;
+pub(crate) mod pawn_updates;
+pub(crate) mod threat_updates;
+pub(crate) mod verify;
 
 /// The embedded neural network parameters.
 pub static EMBEDDED_NNUE: &[u8] = include_bytes_aligned!("../../viridithas.nnue.zst");
@@ -111,6 +116,7 @@ pub fn output_bucket(pos: &Board) -> usize {
     (pos.state.bbs.occupied().count() as usize - 2) / DIVISOR
 }
 
+/// A checksum of the embedded network and its repermutation, for telling networks apart.
 pub fn nnue_checksum() -> u64 {
     let mut hasher = fxhash::FxHasher::default();
     hasher.write(EMBEDDED_NNUE);
@@ -121,22 +127,25 @@ pub fn nnue_checksum() -> u64 {
 }
 
 /// Struct representing the floating-point parameter file emitted by bullet.
+///
+/// Networks trained without output buckets are read as `UnquantisedNetwork<1>`, and have
+/// their single bucket copied into all of them.
 #[rustfmt::skip]
 #[repr(C)]
-struct UnquantisedNetwork {
+struct UnquantisedNetwork<const OB: usize = OUTPUT_BUCKETS> {
     l0_aux:        [f32; AUX_FEATURES * L1_SIZE],
     // extra bucket for the feature-factoriser.
     l0_weights:    [f32; 12 * 64 * L1_SIZE * (BUCKETS + UNQUANTISED_HAS_FACTORISER as usize)],
     l0_biases:     [f32; L1_SIZE],
-    l1_weights:  [[[f32; L2_SIZE]; OUTPUT_BUCKETS]; L1_SIZE],
-    l1_biases:    [[f32; L2_SIZE]; OUTPUT_BUCKETS],
-    l2x_weights: [[[f32; L3_SIZE * 2]; OUTPUT_BUCKETS]; L2_SIZE],
+    l1_weights:  [[[f32; L2_SIZE]; OB]; L1_SIZE],
+    l1_biases:    [[f32; L2_SIZE]; OB],
+    l2x_weights: [[[f32; L3_SIZE * 2]; OB]; L2_SIZE],
     l2f_weights:  [[f32; L3_SIZE * 2]; L2_SIZE],
-    l2x_biases:   [[f32; L3_SIZE * 2]; OUTPUT_BUCKETS],
+    l2x_biases:   [[f32; L3_SIZE * 2]; OB],
     l2f_biases:    [f32; L3_SIZE * 2],
-    l3x_weights: [[[f32;   HEADS]; OUTPUT_BUCKETS]; L3_SIZE],
+    l3x_weights: [[[f32;   HEADS]; OB]; L3_SIZE],
     l3f_weights:  [[f32;   HEADS]; L3_SIZE],
-    l3x_biases:   [[f32;   HEADS]; OUTPUT_BUCKETS],
+    l3x_biases:   [[f32;   HEADS]; OB],
     l3f_biases:    [f32;   HEADS],
 }
 
@@ -176,15 +185,15 @@ struct QuantisedNetwork {
 #[rustfmt::skip]
 #[repr(C)]
 pub struct NNUEParams {
-    pub l0_aux:       Align<[ i8; AUX_FEATURES * L1_SIZE]>,
-    pub l0_weights:   Align<[i16; PSQT_FEATURES * L1_SIZE * BUCKETS]>,
-    pub l0_biases:    Align<[i16; L1_SIZE]>,
-    pub l1_weights:  [Align<[ i8; L1_SIZE * L2_SIZE]>; OUTPUT_BUCKETS],
-    pub l1_bias:     [Align<[f32; L2_SIZE]>; OUTPUT_BUCKETS],
-    pub l2_weights:  [Align<[f32; L2_SIZE * L3_SIZE * 2]>; OUTPUT_BUCKETS],
-    pub l2_bias:     [Align<[f32; L3_SIZE * 2]>; OUTPUT_BUCKETS],
-    pub l3_weights: [[Align<[f32; L3_SIZE]>; HEADS]; OUTPUT_BUCKETS],
-    pub l3_bias:             [[f32; HEADS]; OUTPUT_BUCKETS],
+    pub(crate) l0_aux:       Align<[ i8; AUX_FEATURES * L1_SIZE]>,
+    pub(crate) l0_weights:   Align<[i16; PSQT_FEATURES * L1_SIZE * BUCKETS]>,
+    pub(crate) l0_biases:    Align<[i16; L1_SIZE]>,
+    pub(crate) l1_weights:  [Align<[ i8; L1_SIZE * L2_SIZE]>; OUTPUT_BUCKETS],
+    pub(crate) l1_bias:     [Align<[f32; L2_SIZE]>; OUTPUT_BUCKETS],
+    pub(crate) l2_weights:  [Align<[f32; L2_SIZE * L3_SIZE * 2]>; OUTPUT_BUCKETS],
+    pub(crate) l2_bias:     [Align<[f32; L3_SIZE * 2]>; OUTPUT_BUCKETS],
+    pub(crate) l3_weights: [[Align<[f32; L3_SIZE]>; HEADS]; OUTPUT_BUCKETS],
+    pub(crate) l3_bias:             [[f32; HEADS]; OUTPUT_BUCKETS],
 }
 
 // const REPERMUTE_INDICES: [usize; L1_SIZE / 2] = {
@@ -356,7 +365,9 @@ impl UnquantisedNetwork {
 
         net
     }
+}
 
+impl<const OB: usize> UnquantisedNetwork<OB> {
     fn zeroed() -> Box<Self> {
         // SAFETY: UnquantisedNetwork can be zeroed.
         unsafe {
@@ -383,6 +394,61 @@ impl UnquantisedNetwork {
     }
 }
 
+impl UnquantisedNetwork<1> {
+    /// Copy the single output bucket of a network trained without them into every bucket.
+    fn broadcast_output_buckets(&self) -> Box<UnquantisedNetwork> {
+        let mut net = UnquantisedNetwork::zeroed();
+        net.l0_aux.copy_from_slice(&self.l0_aux);
+        net.l0_weights.copy_from_slice(&self.l0_weights);
+        net.l0_biases = self.l0_biases;
+        for (tgt, [src]) in net.l1_weights.iter_mut().zip(&self.l1_weights) {
+            *tgt = [*src; OUTPUT_BUCKETS];
+        }
+        net.l1_biases = [self.l1_biases[0]; OUTPUT_BUCKETS];
+        for (tgt, [src]) in net.l2x_weights.iter_mut().zip(&self.l2x_weights) {
+            *tgt = [*src; OUTPUT_BUCKETS];
+        }
+        net.l2f_weights = self.l2f_weights;
+        net.l2x_biases = [self.l2x_biases[0]; OUTPUT_BUCKETS];
+        net.l2f_biases = self.l2f_biases;
+        for (tgt, [src]) in net.l3x_weights.iter_mut().zip(&self.l3x_weights) {
+            *tgt = [*src; OUTPUT_BUCKETS];
+        }
+        net.l3f_weights = self.l3f_weights;
+        net.l3x_biases = [self.l3x_biases[0]; OUTPUT_BUCKETS];
+        net.l3f_biases = self.l3f_biases;
+        net
+    }
+}
+
+impl UnquantisedNetwork {
+    /// Read a bullet parameter file, with or without output buckets, telling the two
+    /// apart by the size of the file.
+    fn read_file(path: &Path) -> anyhow::Result<Box<Self>> {
+        let file = File::open(path)
+            .with_context(|| format!("Failed to open file at {}", path.display()))?;
+        let len = file
+            .metadata()
+            .with_context(|| format!("Failed to read metadata of {}", path.display()))?
+            .len();
+        let mut reader = BufReader::new(file);
+        // bullet may pad the file, so anything at least as big as a layout is accepted.
+        if len >= size_of::<Self>() as u64 {
+            Self::read(&mut reader)
+        } else if len >= size_of::<UnquantisedNetwork<1>>() as u64 {
+            println!("[#] Network has no output buckets, sharing its layers between all of them");
+            Ok(UnquantisedNetwork::<1>::read(&mut reader)?.broadcast_output_buckets())
+        } else {
+            anyhow::bail!(
+                "{} is {len} bytes, too small for a network with {OUTPUT_BUCKETS} output buckets ({} bytes) or without them ({} bytes)",
+                path.display(),
+                size_of::<Self>(),
+                size_of::<UnquantisedNetwork<1>>(),
+            )
+        }
+    }
+}
+
 impl MergedNetwork {
     fn zeroed() -> Box<Self> {
         // SAFETY: NNUEParams can be zeroed.
@@ -531,7 +597,7 @@ impl QuantisedNetwork {
         clippy::needless_range_loop,
         clippy::too_many_lines
     )]
-    fn permute(&self, use_simd: bool) -> Box<NNUEParams> {
+    fn permute(&self, kernel: layers::Kernel) -> Box<NNUEParams> {
         let mut net = NNUEParams::zeroed();
         // permute the feature transformer weights
         let src_buckets = self.l0_weights.chunks_exact(PSQT_FEATURES * L1_SIZE);
@@ -547,7 +613,9 @@ impl QuantisedNetwork {
         repermute_threat_weights(&mut net.l0_aux, &self.l0_aux);
 
         // transpose FT weights and biases so that packus transposes it back to the intended order
-        if use_simd {
+        let order = kernel.packus_order();
+        let num_regs = order.len();
+        if num_regs > 1 {
             type PermChunk<I> = [I; 8];
             // reinterpret as data of size __m128i
             let mut weights: Vec<&mut PermChunk<i16>> = net
@@ -562,35 +630,6 @@ impl QuantisedNetwork {
                 .collect();
             let num_chunks = size_of::<PermChunk<i16>>() / size_of::<i16>();
 
-            #[cfg(target_feature = "avx512f")]
-            let num_regs = 8;
-            #[cfg(all(target_feature = "avx2", not(target_feature = "avx512f")))]
-            let num_regs = 4;
-            #[cfg(all(
-                target_arch = "x86_64",
-                not(target_feature = "avx2"),
-                not(target_feature = "avx512f")
-            ))]
-            let num_regs = 2;
-            #[cfg(target_feature = "neon")]
-            let num_regs = 2;
-            #[cfg(not(any(target_arch = "x86_64", target_feature = "neon")))]
-            let num_regs = 1;
-            #[cfg(target_feature = "avx512f")]
-            let order = [0, 2, 4, 6, 1, 3, 5, 7];
-            #[cfg(all(target_feature = "avx2", not(target_feature = "avx512f")))]
-            let order = [0, 2, 1, 3];
-            #[cfg(all(
-                target_arch = "x86_64",
-                not(target_feature = "avx2"),
-                not(target_feature = "avx512f")
-            ))]
-            let order = [0, 1];
-            #[cfg(target_feature = "neon")]
-            let order = [0, 1];
-            #[cfg(not(any(target_arch = "x86_64", target_feature = "neon")))]
-            let order = [0];
-
             let mut regs = vec![[0i16; 8]; num_regs];
 
             // transpose weights
@@ -636,20 +675,13 @@ impl QuantisedNetwork {
         repermute_l1_weights(&mut sorted, &self.l1_weights);
         for bucket in 0..OUTPUT_BUCKETS {
             // quant the L1 weights
-            if use_simd {
-                for i in 0..L1_SIZE / L1_CHUNK_PER_32 {
-                    for j in 0..L2_SIZE {
-                        for k in 0..L1_CHUNK_PER_32 {
-                            net.l1_weights[bucket]
-                                [i * L1_CHUNK_PER_32 * L2_SIZE + j * L1_CHUNK_PER_32 + k] =
-                                sorted[i * L1_CHUNK_PER_32 + k][bucket][j];
-                        }
-                    }
-                }
-            } else {
-                for i in 0..L1_SIZE {
-                    for j in 0..L2_SIZE {
-                        net.l1_weights[bucket][j * L1_SIZE + i] = sorted[i][bucket][j];
+            // every backend, including the portable one, reads the L1 weights in chunks.
+            for i in 0..L1_SIZE / L1_CHUNK_PER_32 {
+                for j in 0..L2_SIZE {
+                    for k in 0..L1_CHUNK_PER_32 {
+                        net.l1_weights[bucket]
+                            [i * L1_CHUNK_PER_32 * L2_SIZE + j * L1_CHUNK_PER_32 + k] =
+                            sorted[i * L1_CHUNK_PER_32 + k][bucket][j];
                     }
                 }
             }
@@ -698,6 +730,7 @@ impl QuantisedNetwork {
     }
 
     fn write(&self, writer: &mut impl std::io::Write) -> anyhow::Result<()> {
+        writer.write_all(&header::NetworkHeader::current().to_bytes())?;
         let ptr = std::ptr::from_ref::<Self>(self).cast::<u8>();
         let len = size_of::<Self>();
         // SAFETY: We're writing a slice of bytes, and we know that the slice is valid.
@@ -767,18 +800,10 @@ fn repermute_threat_weights(
 }
 
 impl NNUEParams {
-    #[allow(clippy::too_many_lines)]
+    /// Decompress the embedded network into its in-memory layout. The weights are shared with
+    /// other processes through a memory map where possible, and leaked, as they live as long as
+    /// the engine.
     pub fn decompress_and_alloc() -> anyhow::Result<&'static Self> {
-        #[cfg(not(feature = "zstd"))]
-        type ZstdDecoder<R, D> = ruzstd::decoding::StreamingDecoder<R, D>;
-        #[cfg(feature = "zstd")]
-        type ZstdDecoder<'a, R> = zstd::stream::Decoder<'a, R>;
-
-        // this function is not particularly happy about running in parallel.
-        static LOCK: Mutex<()> = Mutex::new(());
-        // additionally, we'd quite like to cache the results of this function.
-        static CACHED: OnceLock<Mmap> = OnceLock::new();
-
         // If we’re under MIRI, this function is way too slow.
         if cfg!(miri) {
             static MIRI_NULL_NETWORK: LazyLock<Box<NNUEParams>> = LazyLock::new(|| {
@@ -813,6 +838,34 @@ impl NNUEParams {
             }
         }
 
+        #[cfg(target_family = "wasm")]
+        return Self::decompress_onto_heap();
+        #[cfg(not(target_family = "wasm"))]
+        Self::decompress_and_share()
+    }
+
+    /// There's no filesystem to share the weights through on the web,
+    /// so they're decompressed onto the heap, once.
+    #[cfg(target_family = "wasm")]
+    fn decompress_onto_heap() -> anyhow::Result<&'static Self> {
+        static HEAP: OnceLock<&'static NNUEParams> = OnceLock::new();
+        if let Some(params) = HEAP.get() {
+            return Ok(params);
+        }
+        let params = Box::leak(Self::decompress_embedded()?);
+        Ok(HEAP.get_or_init(|| params))
+    }
+
+    /// Decompress the network into a file in the temporary directory, named for this build,
+    /// which other processes running the same build map rather than decompressing it again.
+    #[cfg(not(target_family = "wasm"))]
+    #[allow(clippy::too_many_lines)]
+    fn decompress_and_share() -> anyhow::Result<&'static Self> {
+        // this function is not particularly happy about running in parallel.
+        static LOCK: Mutex<()> = Mutex::new(());
+        // additionally, we'd quite like to cache the results of this function.
+        static CACHED: OnceLock<Mmap> = OnceLock::new();
+
         let _guard = LOCK.lock().unwrap();
         // check if we've already loaded the weights
         if let Some(cached) = CACHED.get() {
@@ -828,8 +881,8 @@ impl NNUEParams {
             "viridithas-shared-network-weights-{}-{}-{}-{:X}.bin",
             std::env::consts::ARCH,
             std::env::consts::OS,
-            // target cpu
-            nnue::simd::ARCH,
+            // the SIMD kernel, which decides the layout of the weights
+            layers::Kernel::get().name(),
             // avoid clashing with other versions
             nnue_checksum(),
         );
@@ -861,31 +914,7 @@ impl NNUEParams {
             return Ok(params);
         }
 
-        let mut net = QuantisedNetwork::zeroed();
-        // SAFETY: QN is POD and we only write to it.
-        let mut mem = unsafe {
-            std::slice::from_raw_parts_mut(
-                std::ptr::from_mut(net.as_mut()).cast::<u8>(),
-                size_of::<QuantisedNetwork>(),
-            )
-        };
-        let expected_bytes = mem.len() as u64;
-        let decoding_start = std::time::Instant::now();
-        let mut decoder = ZstdDecoder::new(EMBEDDED_NNUE)
-            .with_context(|| "Failed to construct zstd decoder for NNUE weights.")?;
-        let bytes_written = std::io::copy(&mut decoder, &mut mem)
-            .with_context(|| "Failed to decompress NNUE weights.")?;
-        let decoding_time = decoding_start.elapsed();
-        println!(
-            "info string decompressed NNUE weights in {}us",
-            decoding_time.as_micros()
-        );
-        anyhow::ensure!(
-            bytes_written == expected_bytes,
-            "encountered issue while decompressing NNUE weights, expected {expected_bytes} bytes, but got {bytes_written}"
-        );
-        let use_simd = cfg!(any(target_arch = "x86_64", target_feature = "neon"));
-        let net = net.permute(use_simd);
+        let net = Self::decompress_embedded()?;
 
         // create a temporary file to store the weights
         // uses a path unique to our process to avoid
@@ -996,6 +1025,57 @@ impl NNUEParams {
         Ok(params)
     }
 
+    /// Load a network from a file, either raw or zstd-compressed, and permute it for inference.
+    /// The weights live as long as the engine, so each file is only loaded once, and loading
+    /// it again returns the same weights.
+    pub fn from_file(path: &Path) -> anyhow::Result<&'static Self> {
+        const ZSTD_MAGIC: [u8; 4] = [0x28, 0xB5, 0x2F, 0xFD];
+        static LOADED: Mutex<Vec<(PathBuf, &'static NNUEParams)>> = Mutex::new(Vec::new());
+        let path = std::fs::canonicalize(path)
+            .with_context(|| format!("Failed to find network file at {}", path.display()))?;
+        let mut loaded = LOADED.lock().unwrap();
+        if let Some(&(_, net)) = loaded.iter().find(|(p, _)| *p == path) {
+            return Ok(net);
+        }
+        let bytes = std::fs::read(&path)
+            .with_context(|| format!("Failed to read network file at {}", path.display()))?;
+        let net = if bytes.starts_with(&ZSTD_MAGIC) {
+            Self::decompress(&bytes)?
+        } else {
+            header::read_quantised(&mut &bytes[..])?.permute(layers::Kernel::get())
+        };
+        let net = Box::leak(net);
+        loaded.push((path, net));
+        drop(loaded);
+        Ok(net)
+    }
+
+    /// Decompress the embedded network, and permute it for inference.
+    fn decompress_embedded() -> anyhow::Result<Box<Self>> {
+        Self::decompress(EMBEDDED_NNUE)
+    }
+
+    /// Decompress a zstd-compressed network, and permute it for inference.
+    fn decompress(compressed: &[u8]) -> anyhow::Result<Box<Self>> {
+        #[cfg(not(feature = "zstd"))]
+        type ZstdDecoder<R, D> = ruzstd::decoding::StreamingDecoder<R, D>;
+        #[cfg(feature = "zstd")]
+        type ZstdDecoder<'a, R> = zstd::stream::Decoder<'a, R>;
+
+        let decoding_start = Instant::now();
+        let mut decoder = ZstdDecoder::new(compressed)
+            .with_context(|| "Failed to construct zstd decoder for NNUE weights.")?;
+        let net = header::read_quantised(&mut decoder)
+            .with_context(|| "Failed to decompress NNUE weights.")?;
+        let decoding_time = decoding_start.elapsed();
+        println!(
+            "info string decompressed NNUE weights in {}us",
+            decoding_time.as_micros()
+        );
+        Ok(net.permute(layers::Kernel::get()))
+    }
+
+    #[cfg(not(target_family = "wasm"))]
     fn map_weight_file(weights_path: &Path) -> anyhow::Result<Mmap> {
         let without_full_ext = weights_path.with_extension("tmp");
         let without_full_ext = without_full_ext.as_os_str().to_string_lossy();
@@ -1076,7 +1156,10 @@ impl NNUEParams {
         }
     }
 
-    pub fn select_feature_weights(&self, bucket: usize) -> &Align<[i16; PSQT_FEATURES * L1_SIZE]> {
+    pub(crate) fn select_feature_weights(
+        &self,
+        bucket: usize,
+    ) -> &Align<[i16; PSQT_FEATURES * L1_SIZE]> {
         // handle mirroring
         let bucket = bucket % BUCKETS;
         let start = bucket * PSQT_FEATURES * L1_SIZE;
@@ -1097,32 +1180,26 @@ impl NNUEParams {
     }
 }
 
-pub fn quantise(input: &std::path::Path, output: &std::path::Path) -> anyhow::Result<()> {
-    let input_file =
-        File::open(input).with_context(|| format!("Failed to open file at {}", input.display()))?;
-    let mut reader = BufReader::new(input_file);
+pub(crate) fn quantise(input: &std::path::Path, output: &std::path::Path) -> anyhow::Result<()> {
     let mut writer = File::create(output)
         .with_context(|| format!("Failed to create file at {}", output.display()))?;
-    let unquantised_net = UnquantisedNetwork::read(&mut reader)?;
+    let unquantised_net = UnquantisedNetwork::read_file(input)?;
     let net = unquantised_net.merge().quantise();
     net.write(&mut writer)?;
     Ok(())
 }
 
-pub fn merge(input: &std::path::Path, output: &std::path::Path) -> anyhow::Result<()> {
-    let input_file =
-        File::open(input).with_context(|| format!("Failed to open file at {}", input.display()))?;
-    let mut reader = BufReader::new(input_file);
+pub(crate) fn merge(input: &std::path::Path, output: &std::path::Path) -> anyhow::Result<()> {
     let output_file = File::create(output)
         .with_context(|| format!("Failed to create file at {}", output.display()))?;
     let mut writer = BufWriter::new(output_file);
-    let unquantised_net = UnquantisedNetwork::read(&mut reader)?;
+    let unquantised_net = UnquantisedNetwork::read_file(input)?;
     let net = unquantised_net.merge();
     net.write_bullet(&mut writer)?;
     Ok(())
 }
 
-pub fn dump_verbatim(output: &std::path::Path) -> anyhow::Result<()> {
+pub(crate) fn dump_verbatim(output: &std::path::Path) -> anyhow::Result<()> {
     let output_file = File::create(output)
         .with_context(|| format!("Failed to create file at {}", output.display()))?;
     let mut writer = BufWriter::new(output_file);
@@ -1154,7 +1231,7 @@ impl<T> Deref for BoxedOrStatic<T> {
     }
 }
 
-pub fn dry_run() -> anyhow::Result<()> {
+pub(crate) fn dry_run() -> anyhow::Result<()> {
     use BoxedOrStatic::{Boxed, Static};
     if !EMBEDDED_NNUE_VERBATIM {
         println!("[#] Embedded NNUE is compressed, dry-run must operate on zeroed network.");
@@ -1182,22 +1259,22 @@ const ACC_STACK_SIZE: usize = MAX_DEPTH + 1;
 /// Struct representing some unmaterialised feature update made as part of a move.
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub struct PsqtFeatureUpdate {
-    pub sq: Square,
-    pub piece: Piece,
+    pub(crate) sq: Square,
+    pub(crate) piece: Piece,
 }
 
 /// Struct representing some unmaterialised threat update made as part of a move.
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 #[repr(C)]
 pub struct ThreatFeatureUpdate {
-    pub attacker: Piece,
-    pub from: Square,
-    pub victim: Piece,
-    pub to: Square,
+    pub(crate) attacker: Piece,
+    pub(crate) from: Square,
+    pub(crate) victim: Piece,
+    pub(crate) to: Square,
 }
 
 impl ThreatFeatureUpdate {
-    pub fn index(self, colour: Colour, king: Square) -> (bool, u32) {
+    pub(crate) fn index(self, colour: Colour, king: Square) -> (bool, u32) {
         feature::threat_index(colour, king, self.attacker, self.victim, self.from, self.to)
     }
 }
@@ -1209,49 +1286,49 @@ impl Display for PsqtFeatureUpdate {
 }
 
 #[derive(PartialEq, Eq, Clone, Debug, Default)]
-pub struct PsqtUpdateBuffer {
+pub(crate) struct PsqtUpdateBuffer {
     add: ArrayVec<PsqtFeatureUpdate, 2>,
     sub: ArrayVec<PsqtFeatureUpdate, 2>,
 }
 
 impl PsqtUpdateBuffer {
-    pub fn move_piece(&mut self, from: Square, to: Square, piece: Piece) {
+    pub(crate) fn move_piece(&mut self, from: Square, to: Square, piece: Piece) {
         self.add.push(PsqtFeatureUpdate { sq: to, piece });
         self.sub.push(PsqtFeatureUpdate { sq: from, piece });
     }
 
-    pub fn clear_piece(&mut self, sq: Square, piece: Piece) {
+    pub(crate) fn clear_piece(&mut self, sq: Square, piece: Piece) {
         self.sub.push(PsqtFeatureUpdate { sq, piece });
     }
 
-    pub fn add_piece(&mut self, sq: Square, piece: Piece) {
+    pub(crate) fn add_piece(&mut self, sq: Square, piece: Piece) {
         self.add.push(PsqtFeatureUpdate { sq, piece });
     }
 
-    pub fn adds(&self) -> &[PsqtFeatureUpdate] {
+    pub(crate) fn adds(&self) -> &[PsqtFeatureUpdate] {
         &self.add[..]
     }
 
-    pub fn subs(&self) -> &[PsqtFeatureUpdate] {
+    pub(crate) fn subs(&self) -> &[PsqtFeatureUpdate] {
         &self.sub[..]
     }
 
-    pub fn clear(&mut self) {
+    pub(crate) fn clear(&mut self) {
         self.add.clear();
         self.sub.clear();
     }
 }
 
 #[derive(PartialEq, Eq, Clone, Debug, Default)]
-pub struct AuxUpdateBuffer {
-    pub add: ArrayVec<ThreatFeatureUpdate, 128>,
-    pub sub: ArrayVec<ThreatFeatureUpdate, 128>,
-    pub afore: [SquareSet; 2],
-    pub after: [SquareSet; 2],
+pub(crate) struct AuxUpdateBuffer {
+    pub(crate) add: ArrayVec<ThreatFeatureUpdate, 128>,
+    pub(crate) sub: ArrayVec<ThreatFeatureUpdate, 128>,
+    pub(crate) afore: [SquareSet; 2],
+    pub(crate) after: [SquareSet; 2],
 }
 
 impl AuxUpdateBuffer {
-    pub fn clear(&mut self) {
+    pub(crate) fn clear(&mut self) {
         self.add.clear();
         self.sub.clear();
         #[cfg(debug_assertions)]
@@ -1265,20 +1342,21 @@ impl AuxUpdateBuffer {
 /// Combined PSQT + threat update buffer, filled during move-making.
 #[derive(PartialEq, Eq, Clone, Debug, Default)]
 pub struct UpdateBuffer {
-    pub psqt: PsqtUpdateBuffer,
-    pub aux: AuxUpdateBuffer,
+    pub(crate) psqt: PsqtUpdateBuffer,
+    pub(crate) aux: AuxUpdateBuffer,
 }
 
 impl UpdateBuffer {
-    pub fn clear(&mut self) {
+    pub(crate) fn clear(&mut self) {
         self.psqt.clear();
         self.aux.clear();
     }
 }
 
-/// Stores last-seen accumulators for each bucket, so that we can hopefully avoid
-/// having to completely recompute the accumulator for a position, instead
-/// partially reconstructing it from the last-seen accumulator.
+/// Stores last-seen accumulators for each bucket.
+///
+/// This lets us avoid having to completely recompute the accumulator for a position,
+/// instead partially reconstructing it from the last-seen accumulator.
 pub struct BucketAccumulatorCache {
     // both of these are BUCKETS * 2, rather than just BUCKETS,
     // because we use a horizontally-mirrored architecture.
@@ -1288,7 +1366,7 @@ pub struct BucketAccumulatorCache {
 
 impl BucketAccumulatorCache {
     #[allow(clippy::too_many_lines)]
-    pub fn load_accumulator_for_position(
+    pub(crate) fn load_accumulator_for_position(
         &mut self,
         nnue_params: &NNUEParams,
         board_state: PieceLayout,
@@ -1323,10 +1401,10 @@ impl BucketAccumulatorCache {
 }
 
 #[derive(Clone, Copy, Debug)]
-pub struct MovedPiece {
-    pub from: Square,
-    pub to: Square,
-    pub piece: Piece,
+pub(crate) struct MovedPiece {
+    pub(crate) from: Square,
+    pub(crate) to: Square,
+    pub(crate) piece: Piece,
 }
 
 trait AccUpdateType {
@@ -1347,25 +1425,25 @@ impl AccUpdateType for ThreatUpdate {
 #[allow(clippy::upper_case_acronyms)]
 pub struct NNUEState {
     /// Board-state accumulators for the first layer.
-    pub psqt_accumulators: [Accumulator; ACC_STACK_SIZE],
+    pub(crate) psqt_accumulators: [Accumulator; ACC_STACK_SIZE],
     /// “dirty” flags for the PSQT accumulators.
-    pub psqt_correct: [[bool; 2]; ACC_STACK_SIZE],
+    pub(crate) psqt_correct: [[bool; 2]; ACC_STACK_SIZE],
 
     /// Threat-state accumulators for the first layer.
-    pub threat_accumulators: [Accumulator; ACC_STACK_SIZE],
+    pub(crate) threat_accumulators: [Accumulator; ACC_STACK_SIZE],
     /// “dirty” flags for the threat accumulators.
-    pub threat_correct: [[bool; 2]; ACC_STACK_SIZE],
+    pub(crate) threat_correct: [[bool; 2]; ACC_STACK_SIZE],
 
     /// Diffs for the updates.
-    pub updates: [UpdateBuffer; ACC_STACK_SIZE],
+    pub(crate) updates: [UpdateBuffer; ACC_STACK_SIZE],
 
     /// Moves made for update computation.
-    pub moves: [MovedPiece; ACC_STACK_SIZE],
+    pub(crate) moves: [MovedPiece; ACC_STACK_SIZE],
     /// Index of the current accumulator.
-    pub current_acc: usize,
+    pub(crate) current_acc: usize,
 
     /// Cache of last-seen accumulators for each bucket.
-    pub bucket_cache: BucketAccumulatorCache,
+    pub(crate) bucket_cache: BucketAccumulatorCache,
 }
 
 impl NNUEState {
@@ -1567,7 +1645,7 @@ impl NNUEState {
         }
     }
 
-    pub fn hint_common_access(&mut self, pos: &Board, nnue_params: &NNUEParams) {
+    pub(crate) fn hint_common_access(&mut self, pos: &Board, nnue_params: &NNUEParams) {
         self.hint_common_access_for_perspective::<White>(pos, nnue_params);
         self.hint_common_access_for_perspective::<Black>(pos, nnue_params);
     }
@@ -1666,7 +1744,7 @@ impl NNUEState {
         }
     }
 
-    pub fn materialise_new_psqt_acc_from(
+    pub(crate) fn materialise_new_psqt_acc_from(
         src_acc: &Accumulator,
         tgt_acc: &mut Accumulator,
         updates: &PsqtUpdateBuffer,
@@ -1707,7 +1785,7 @@ impl NNUEState {
         }
     }
 
-    pub fn materialise_new_aux_acc_from(
+    pub(crate) fn materialise_new_aux_acc_from(
         src_acc: &Accumulator,
         tgt_acc: &mut Accumulator,
         updates: &AuxUpdateBuffer,
@@ -1841,16 +1919,19 @@ pub fn inference_benchmark(state: &NNUEState, nnue_params: &NNUEParams) {
     println!("{ns_per_eval} ns per evaluation");
 }
 
-pub fn visualise_nnue() -> anyhow::Result<()> {
+pub(crate) fn visualise_nnue(path: &Path) -> anyhow::Result<()> {
     let nnue_params = NNUEParams::decompress_and_alloc()?;
     // create folder for the images
-    let path = std::path::PathBuf::from("nnue-visualisations");
-    std::fs::create_dir_all(&path)
+    std::fs::create_dir_all(path)
         .with_context(|| "Failed to create NNUE visualisations folder.")?;
     for neuron in 0..crate::nnue::network::L1_SIZE {
-        nnue_params.visualise_neuron(neuron, &path);
+        nnue_params.visualise_neuron(neuron, path)?;
+    }
+    nnue_params.composite_neurons(path)?;
+    for bucket in 0..BUCKETS {
+        let image = nnue_params.bucket_projection_image(bucket);
+        image.save_as_png(path.join(format!("bucket_{bucket}.png")))?;
     }
-    nnue_params.composite_neurons(&path);
     let (min, max) = nnue_params.min_max_feature_weight();
     println!("Min / Max FT values: {min} / {max}");
     Ok(())
@@ -1858,75 +1939,100 @@ pub fn visualise_nnue() -> anyhow::Result<()> {
 
 const IMAGE_SPACING: usize = 0;
 
-impl NNUEParams {
-    pub fn visualise_neuron(&self, neuron: usize, path: &std::path::Path) {
-        let image = self.neuron_image(neuron);
-        let path = path.join(format!("neuron_{neuron}.tga"));
-        image.save_as_tga(path);
+/// Render a heatmap of a value for each piece on each square, with a board for each piece,
+/// White's pieces on the top row and Black's beneath. Positive values are warm, and
+/// negative ones cool.
+fn piece_square_image(value: impl Fn(PsqtFeatureUpdate) -> i32) -> Image {
+    #![allow(
+        clippy::cast_sign_loss,
+        clippy::cast_possible_truncation,
+        clippy::cast_precision_loss
+    )]
+    // remap pieces to keep opposite colours together
+    static PIECE_REMAPPING: [usize; 12] = [0, 2, 4, 6, 8, 10, 1, 3, 5, 7, 9, 11];
+    let mut slice = Vec::with_capacity(768);
+    for colour in Colour::all() {
+        for piece_type in PieceType::all() {
+            for square in Square::all() {
+                slice.push(value(PsqtFeatureUpdate {
+                    sq: square,
+                    piece: Piece::new(colour, piece_type),
+                }));
+            }
+        }
     }
 
-    fn neuron_image(&self, neuron: usize) -> Image {
-        #![allow(clippy::cast_sign_loss, clippy::cast_possible_truncation)]
-        // remap pieces to keep opposite colours together
-        static PIECE_REMAPPING: [usize; 12] = [0, 2, 4, 6, 8, 10, 1, 3, 5, 7, 9, 11];
-        assert!(neuron < L1_SIZE);
-        let starting_idx = neuron;
-        let mut slice = Vec::with_capacity(768);
-        for colour in Colour::all() {
-            for piece_type in PieceType::all() {
-                for square in Square::all() {
-                    let white_king = Square::H1;
-                    let f = PsqtFeatureUpdate {
-                        sq: square,
-                        piece: Piece::new(colour, piece_type),
-                    };
-                    let feature_index = feature::psqt_index(Colour::White, white_king, f);
-                    let index = feature_index.index() * L1_SIZE + starting_idx;
-                    slice.push(self.l0_weights[index]);
-                }
-            }
+    let max_abs = slice.iter().copied().map(i32::unsigned_abs).max().unwrap();
+    let weight_to_colour = |weight: i32| -> u32 {
+        if max_abs == 0 {
+            return image::inferno_colour_map(0);
+        }
+        let magnitude = weight.unsigned_abs() as f32 / max_abs as f32;
+        let idx = (magnitude * 255.0).round() as u8;
+        if weight >= 0 {
+            image::inferno_colour_map(idx)
+        } else {
+            image::cool_inferno_colour_map(idx)
         }
+    };
 
-        let max_abs = slice.iter().copied().map(i16::unsigned_abs).max().unwrap();
-        let weight_to_colour = |weight: i16| -> u32 {
-            if max_abs == 0 {
-                return image::inferno_colour_map(0);
-            }
-            let magnitude = f32::from(weight.unsigned_abs()) / f32::from(max_abs);
-            let idx = (magnitude * 255.0).round() as u8;
-            if weight >= 0 {
-                image::inferno_colour_map(idx)
+    let mut image = Image::zeroed(8 * 6 + IMAGE_SPACING * 5, 8 * 2 + IMAGE_SPACING);
+
+    for (piece, chunk) in slice.chunks(64).enumerate() {
+        let piece = PIECE_REMAPPING[piece];
+        let piece_colour = piece % 2;
+        let piece_type = piece / 2;
+        for (square, &weight) in chunk.iter().enumerate() {
+            let row = square / 8;
+            let col = square % 8;
+            let colour = if (row == 0 || row == 7) && piece_type == 0 {
+                0 // pawns on first and last rank are always 0
             } else {
-                image::cool_inferno_colour_map(idx)
-            }
-        };
-
-        let mut image = Image::zeroed(8 * 6 + IMAGE_SPACING * 5, 8 * 2 + IMAGE_SPACING);
-
-        for (piece, chunk) in slice.chunks(64).enumerate() {
-            let piece = PIECE_REMAPPING[piece];
-            let piece_colour = piece % 2;
-            let piece_type = piece / 2;
-            for (square, &weight) in chunk.iter().enumerate() {
-                let row = square / 8;
-                let col = square % 8;
-                let colour = if (row == 0 || row == 7) && piece_type == 0 {
-                    0 // pawns on first and last rank are always 0
-                } else {
-                    weight_to_colour(weight)
-                };
-                image.set(
-                    col + piece_type * (8 + IMAGE_SPACING),
-                    row + piece_colour * (8 + IMAGE_SPACING),
-                    colour,
-                );
-            }
+                weight_to_colour(weight)
+            };
+            image.set(
+                col + piece_type * (8 + IMAGE_SPACING),
+                row + piece_colour * (8 + IMAGE_SPACING),
+                colour,
+            );
         }
+    }
+
+    image
+}
 
-        image
+impl NNUEParams {
+    pub(crate) fn visualise_neuron(&self, neuron: usize, path: &Path) -> std::io::Result<()> {
+        let image = self.neuron_image(neuron);
+        image.save_as_png(path.join(format!("neuron_{neuron}.png")))
     }
 
-    pub fn composite_neurons(&self, path: &Path) {
+    fn neuron_image(&self, neuron: usize) -> Image {
+        assert!(neuron < L1_SIZE);
+        let white_king = Square::H1;
+        piece_square_image(|f| {
+            let feature_index = feature::psqt_index(Colour::White, white_king, f);
+            i32::from(self.l0_weights[feature_index.index() * L1_SIZE + neuron])
+        })
+    }
+
+    /// A PSQT-like view of a king bucket: each feature's weights summed over the hidden
+    /// layer, which shows how strongly a piece on a square pushes the accumulator.
+    fn bucket_projection_image(&self, bucket: usize) -> Image {
+        let weights = self.select_feature_weights(bucket);
+        // a king on the queenside, so that the features aren't mirrored.
+        let white_king = Square::A1;
+        piece_square_image(|f| {
+            let start = feature::psqt_index(Colour::White, white_king, f).index() * L1_SIZE;
+            weights[start..start + L1_SIZE]
+                .iter()
+                .copied()
+                .map(i32::from)
+                .sum()
+        })
+    }
+
+    pub(crate) fn composite_neurons(&self, path: &Path) -> std::io::Result<()> {
         const TILE_W: usize = 8 * 6 + IMAGE_SPACING * 5;
         const TILE_H: usize = 8 * 2 + IMAGE_SPACING;
 
@@ -1968,12 +2074,10 @@ impl NNUEParams {
             }
         }
 
-        let path = path.join("composite.tga");
-
-        composite.save_as_tga(path);
+        composite.save_as_png(path.join("composite.png"))
     }
 
-    pub fn min_max_feature_weight(&self) -> (i16, i16) {
+    pub(crate) fn min_max_feature_weight(&self) -> (i16, i16) {
         let mut min = i16::MAX;
         let mut max = i16::MIN;
         for &f in &self.l0_weights.0 {
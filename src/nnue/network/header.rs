@@ -0,0 +1,237 @@
+//! The header at the start of a quantised network file.
+//!
+//! The header records the architecture the weights were quantised for, so that a file made
+//! for a different architecture is rejected with an explanation, rather than misread as a
+//! blob of the right size. Files without a header are still read, as long as they're exactly
+//! the size of a network.
+
+use std::io::Read;
+
+use anyhow::{Context, bail};
+
+use super::{
+    AUX_FEATURES, BUCKETS, HALF_BUCKET_MAP, HEADS, L1_SIZE, L2_SIZE, L3_SIZE, OUTPUT_BUCKETS,
+    PSQT_FEATURES, QuantisedNetwork,
+};
+
+/// The bytes a network file with a header starts with.
+const MAGIC: [u8; 4] = *b"VIRI";
+/// The version of the header format.
+const VERSION: u16 = 1;
+/// The size of the header, in bytes.
+pub const HEADER_SIZE: usize = 4 + 2 + 2 + 8 * 4 + 32;
+
+/// The architecture of a network, as recorded in its header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NetworkHeader {
+    /// The version of the header format.
+    pub version: u16,
+    /// The size of one half of the hidden layer.
+    pub l1_size: u32,
+    /// The size of the second layer.
+    pub l2_size: u32,
+    /// The size of the third layer.
+    pub l3_size: u32,
+    /// The number of output heads.
+    pub heads: u32,
+    /// The number of piece-square features.
+    pub psqt_features: u32,
+    /// The number of threat and pawn-tuple features.
+    pub aux_features: u32,
+    /// The number of king buckets.
+    pub input_buckets: u32,
+    /// The number of output buckets.
+    pub output_buckets: u32,
+    /// The king bucket of each square on one half of the board.
+    pub bucket_map: [u8; 32],
+}
+
+impl NetworkHeader {
+    /// The header of a network for this build's architecture.
+    pub fn current() -> Self {
+        #![allow(clippy::cast_possible_truncation)]
+        Self {
+            version: VERSION,
+            l1_size: L1_SIZE as u32,
+            l2_size: L2_SIZE as u32,
+            l3_size: L3_SIZE as u32,
+            heads: HEADS as u32,
+            psqt_features: PSQT_FEATURES as u32,
+            aux_features: AUX_FEATURES as u32,
+            input_buckets: BUCKETS as u32,
+            output_buckets: OUTPUT_BUCKETS as u32,
+            bucket_map: HALF_BUCKET_MAP.map(|b| b as u8),
+        }
+    }
+
+    /// The header as it's written to a file.
+    pub fn to_bytes(self) -> [u8; HEADER_SIZE] {
+        let mut out = [0; HEADER_SIZE];
+        out[..4].copy_from_slice(&MAGIC);
+        out[4..6].copy_from_slice(&self.version.to_le_bytes());
+        let sizes = [
+            self.l1_size,
+            self.l2_size,
+            self.l3_size,
+            self.heads,
+            self.psqt_features,
+            self.aux_features,
+            self.input_buckets,
+            self.output_buckets,
+        ];
+        for (chunk, size) in out[8..40].chunks_exact_mut(4).zip(sizes) {
+            chunk.copy_from_slice(&size.to_le_bytes());
+        }
+        out[40..].copy_from_slice(&self.bucket_map);
+        out
+    }
+
+    /// Parse a header, or return `None` if the bytes don't start with one.
+    pub fn from_bytes(bytes: &[u8; HEADER_SIZE]) -> Option<Self> {
+        if bytes[..4] != MAGIC {
+            return None;
+        }
+        let word =
+            |i: usize| u32::from_le_bytes([bytes[i], bytes[i + 1], bytes[i + 2], bytes[i + 3]]);
+        let mut bucket_map = [0; 32];
+        bucket_map.copy_from_slice(&bytes[40..]);
+        Some(Self {
+            version: u16::from_le_bytes([bytes[4], bytes[5]]),
+            l1_size: word(8),
+            l2_size: word(12),
+            l3_size: word(16),
+            heads: word(20),
+            psqt_features: word(24),
+            aux_features: word(28),
+            input_buckets: word(32),
+            output_buckets: word(36),
+            bucket_map,
+        })
+    }
+
+    /// Check that a network with this header can be loaded by this build.
+    pub fn check(&self) -> anyhow::Result<()> {
+        let expected = Self::current();
+        if self.version != expected.version {
+            bail!(
+                "network file has header version {}, but this build reads version {}",
+                self.version,
+                expected.version
+            );
+        }
+        let fields = [
+            ("hidden layer size", self.l1_size, expected.l1_size),
+            ("second layer size", self.l2_size, expected.l2_size),
+            ("third layer size", self.l3_size, expected.l3_size),
+            ("number of output heads", self.heads, expected.heads),
+            (
+                "number of piece-square features",
+                self.psqt_features,
+                expected.psqt_features,
+            ),
+            (
+                "number of threat features",
+                self.aux_features,
+                expected.aux_features,
+            ),
+            (
+                "number of king buckets",
+                self.input_buckets,
+                expected.input_buckets,
+            ),
+            (
+                "number of output buckets",
+                self.output_buckets,
+                expected.output_buckets,
+            ),
+        ];
+        for (name, found, wanted) in fields {
+            if found != wanted {
+                bail!("network has {name} {found}, but this build expects {wanted}");
+            }
+        }
+        if self.bucket_map != expected.bucket_map {
+            bail!("network was trained with a different king-bucket layout to this build");
+        }
+        Ok(())
+    }
+}
+
+/// Read a quantised network, checking its header if it has one. A file without a header
+/// must hold exactly one network's worth of weights.
+pub(super) fn read_quantised(reader: &mut impl Read) -> anyhow::Result<Box<QuantisedNetwork>> {
+    let mut net = QuantisedNetwork::zeroed();
+    // SAFETY: QN is POD and we only write to it.
+    let mem = unsafe {
+        std::slice::from_raw_parts_mut(
+            std::ptr::from_mut(net.as_mut()).cast::<u8>(),
+            size_of::<QuantisedNetwork>(),
+        )
+    };
+    let too_small = || {
+        format!(
+            "network is smaller than this build's architecture, which needs {} bytes of weights",
+            size_of::<QuantisedNetwork>()
+        )
+    };
+
+    let mut start = [0; HEADER_SIZE];
+    reader.read_exact(&mut start).with_context(too_small)?;
+    if let Some(header) = NetworkHeader::from_bytes(&start) {
+        header.check()?;
+        reader.read_exact(mem).with_context(too_small)?;
+    } else {
+        // an old file, with no header: the first bytes are weights.
+        mem[..HEADER_SIZE].copy_from_slice(&start);
+        reader
+            .read_exact(&mut mem[HEADER_SIZE..])
+            .with_context(too_small)?;
+    }
+
+    let mut rest = [0; 1];
+    if reader.read(&mut rest)? != 0 {
+        bail!(
+            "network is larger than this build's architecture, which needs {} bytes of weights",
+            size_of::<QuantisedNetwork>()
+        );
+    }
+    Ok(net)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let header = NetworkHeader::current();
+        assert_eq!(NetworkHeader::from_bytes(&header.to_bytes()), Some(header));
+        assert!(header.check().is_ok());
+        assert_eq!(NetworkHeader::from_bytes(&[0; HEADER_SIZE]), None);
+    }
+
+    #[test]
+    fn rejects_mismatched_architecture() {
+        let header = NetworkHeader {
+            l1_size: 2 * NetworkHeader::current().l1_size,
+            ..NetworkHeader::current()
+        };
+        let message = header.check().unwrap_err().to_string();
+        assert!(message.contains("hidden layer size"), "{message}");
+
+        let mut bytes = header.to_bytes().to_vec();
+        bytes.resize(HEADER_SIZE + size_of::<QuantisedNetwork>(), 0);
+        assert!(read_quantised(&mut &bytes[..]).is_err());
+    }
+
+    #[test]
+    fn reads_with_and_without_header() {
+        let mut bytes = NetworkHeader::current().to_bytes().to_vec();
+        bytes.resize(HEADER_SIZE + size_of::<QuantisedNetwork>(), 0);
+        assert!(read_quantised(&mut &bytes[..]).is_ok());
+        assert!(read_quantised(&mut &bytes[HEADER_SIZE..]).is_ok());
+        assert!(read_quantised(&mut &bytes[HEADER_SIZE + 1..]).is_err());
+        bytes.push(0);
+        assert!(read_quantised(&mut &bytes[..]).is_err());
+    }
+}
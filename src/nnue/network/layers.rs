@@ -27,13 +27,17 @@ pub static FT_OUTPUT_FILE: std::sync::LazyLock<
     std::sync::Mutex::new(w)
 });
 
-mod simd {
+/// The forward pass, written once against the SIMD backends in `nnue::simd`, and compiled
+/// into the module `$name` for the backend `$backend`, with the target features it needs.
+macro_rules! forward_pass {
+    ($name:ident, $backend:ident $(, $features:literal)?) => {
+mod $name {
     use crate::nnue::{
         network::{
             Align, L1_CHUNK_PER_32, L1_SIZE, L2_SIZE, L3_SIZE, QA,
             layers::{AVX512CHUNK, FT_SHIFT, L1_MUL, SWISH_K},
         },
-        simd::{self, F32_CHUNK, I16_CHUNK, S, U8_CHUNK, VecI32},
+        simd::$backend::{self as simd, F32_CHUNK, I16_CHUNK, S, U8_CHUNK, VecI32},
     };
     use std::mem::MaybeUninit;
 
@@ -95,7 +99,8 @@ mod simd {
         clippy::needless_range_loop,
         clippy::similar_names
     )]
-    pub fn activate_ft_and_propagate_l1(
+    $(#[target_feature(enable = $features)])?
+    pub unsafe fn activate_ft_and_propagate_l1(
         stm_psqt: &Align<[i16; L1_SIZE]>,
         ntm_psqt: &Align<[i16; L1_SIZE]>,
         stm_thrt: &Align<[i16; L1_SIZE]>,
@@ -112,7 +117,7 @@ mod simd {
 
         // on NEON, the instruction used for mulhi doubles the results.
         // this is effectively a shift by another bit, so we shift by one fewer.
-        const SHIFT: S = 16 - FT_SHIFT as S - cfg!(target_feature = "neon") as S;
+        const SHIFT: S = 16 - FT_SHIFT as S - simd::MUL_HIGH_DOUBLES as S;
 
         // SAFETY: Breaking it down by unsafe operations:
         // 1. get_unchecked[_mut] / .as[_mut]_ptr().add(): We only ever index at most
@@ -256,6 +261,7 @@ mod simd {
     }
 
     #[allow(clippy::similar_names, clippy::identity_op)]
+    #[inline(always)]
     fn propagate_l1(
         ft_outputs: &Align<[MaybeUninit<u8>; L1_SIZE]>,
         nnz_slice: &[u16],
@@ -378,7 +384,8 @@ mod simd {
     }
 
     #[allow(clippy::needless_range_loop, clippy::cast_ptr_alignment)]
-    pub fn propagate_l2(
+    $(#[target_feature(enable = $features)])?
+    pub unsafe fn propagate_l2(
         inputs: &Align<[f32; L2_SIZE]>,
         weights: &Align<[f32; L2_SIZE * L3_SIZE * 2]>,
         biases: &Align<[f32; L3_SIZE * 2]>,
@@ -433,7 +440,8 @@ mod simd {
     }
 
     #[allow(clippy::modulo_one)]
-    pub fn propagate_l3(
+    $(#[target_feature(enable = $features)])?
+    pub unsafe fn propagate_l3(
         inputs: &Align<[f32; L3_SIZE]>,
         weights: &Align<[f32; L3_SIZE]>,
         bias: f32,
@@ -462,10 +470,166 @@ mod simd {
         }
     }
 }
+    };
+}
 
-pub use simd::*;
+#[cfg(target_arch = "x86_64")]
+forward_pass!(avx512vnni, avx512vnni, "avx512f,avx512bw,avx512vnni");
+#[cfg(target_arch = "x86_64")]
+forward_pass!(avx512, avx512, "avx512f,avx512bw");
+#[cfg(target_arch = "x86_64")]
+forward_pass!(avx2, avx2, "avx2,fma");
+#[cfg(target_feature = "neon")]
+forward_pass!(neon, neon);
+forward_pass!(generic, generic);
+
+/// The instruction sets that the forward pass is compiled for. The best one that the CPU
+/// supports is picked when the network is first loaded, so a binary built for baseline
+/// x86-64 still runs the AVX2 or AVX-512 kernels where it can.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Kernel {
+    /// 512-bit registers, with AVX-512 F, BW, and VNNI.
+    #[cfg(target_arch = "x86_64")]
+    Avx512Vnni,
+    /// 512-bit registers, with AVX-512 F and BW.
+    #[cfg(target_arch = "x86_64")]
+    Avx512,
+    /// 256-bit registers, with AVX2 and FMA.
+    #[cfg(target_arch = "x86_64")]
+    Avx2,
+    /// 128-bit NEON registers.
+    #[cfg(target_feature = "neon")]
+    Neon,
+    /// Portable code, vectorised as well as the compiler can manage.
+    Generic,
+}
 
-use super::{QA, QB};
+impl Kernel {
+    /// The kernel that evaluation uses on this CPU.
+    pub fn get() -> Self {
+        static KERNEL: LazyLock<Kernel> = LazyLock::new(|| Kernel::available()[0]);
+        *KERNEL
+    }
+
+    /// The kernels that this CPU can run, best first.
+    #[cfg_attr(
+        not(target_arch = "x86_64"),
+        expect(
+            clippy::vec_init_then_push,
+            reason = "only x86-64 detects kernels at run time, so elsewhere every push is unconditional"
+        )
+    )]
+    pub fn available() -> Vec<Self> {
+        let mut kernels = Vec::new();
+        #[cfg(target_arch = "x86_64")]
+        {
+            if is_x86_feature_detected!("avx512f") && is_x86_feature_detected!("avx512bw") {
+                if is_x86_feature_detected!("avx512vnni") {
+                    kernels.push(Self::Avx512Vnni);
+                }
+                kernels.push(Self::Avx512);
+            }
+            if is_x86_feature_detected!("avx2") && is_x86_feature_detected!("fma") {
+                kernels.push(Self::Avx2);
+            }
+        }
+        #[cfg(target_feature = "neon")]
+        kernels.push(Self::Neon);
+        kernels.push(Self::Generic);
+        kernels
+    }
+
+    /// The name of the kernel's instruction set.
+    #[cfg_attr(target_family = "wasm", allow(dead_code))]
+    pub const fn name(self) -> &'static str {
+        match self {
+            #[cfg(target_arch = "x86_64")]
+            Self::Avx512Vnni => simd::avx512vnni::INNER_ARCH,
+            #[cfg(target_arch = "x86_64")]
+            Self::Avx512 => simd::avx512::INNER_ARCH,
+            #[cfg(target_arch = "x86_64")]
+            Self::Avx2 => simd::avx2::INNER_ARCH,
+            #[cfg(target_feature = "neon")]
+            Self::Neon => simd::neon::INNER_ARCH,
+            Self::Generic => simd::generic::INNER_ARCH,
+        }
+    }
+
+    /// Packing i16s into u8s interleaves the 128-bit lanes of its inputs, so the feature
+    /// transformer is stored with its blocks of eight outputs in this order, for packing to
+    /// put them back in place.
+    pub const fn packus_order(self) -> &'static [usize] {
+        match self {
+            #[cfg(target_arch = "x86_64")]
+            Self::Avx512Vnni | Self::Avx512 => &[0, 2, 4, 6, 1, 3, 5, 7],
+            #[cfg(target_arch = "x86_64")]
+            Self::Avx2 => &[0, 2, 1, 3],
+            #[cfg(target_feature = "neon")]
+            Self::Neon => &[0, 1],
+            Self::Generic => &[0],
+        }
+    }
+}
+
+/// Call the function `$f` of the kernel that this CPU runs.
+macro_rules! dispatch {
+    ($f:ident($($arg:expr),*)) => {
+        // SAFETY: `Kernel::get` only picks kernels whose instruction sets the CPU supports.
+        unsafe {
+            match Kernel::get() {
+                #[cfg(target_arch = "x86_64")]
+                Kernel::Avx512Vnni => avx512vnni::$f($($arg),*),
+                #[cfg(target_arch = "x86_64")]
+                Kernel::Avx512 => avx512::$f($($arg),*),
+                #[cfg(target_arch = "x86_64")]
+                Kernel::Avx2 => avx2::$f($($arg),*),
+                #[cfg(target_feature = "neon")]
+                Kernel::Neon => neon::$f($($arg),*),
+                Kernel::Generic => generic::$f($($arg),*),
+            }
+        }
+    };
+}
+
+/// Activate the feature transformer outputs, and propagate them through the first layer.
+pub fn activate_ft_and_propagate_l1(
+    stm_psqt: &Align<[i16; L1_SIZE]>,
+    ntm_psqt: &Align<[i16; L1_SIZE]>,
+    stm_thrt: &Align<[i16; L1_SIZE]>,
+    ntm_thrt: &Align<[i16; L1_SIZE]>,
+    weights: &Align<[i8; L1_SIZE * L2_SIZE]>,
+    biases: &Align<[f32; L2_SIZE]>,
+    output: &mut Align<[f32; L2_SIZE]>,
+) {
+    dispatch!(activate_ft_and_propagate_l1(
+        stm_psqt, ntm_psqt, stm_thrt, ntm_thrt, weights, biases, output
+    ));
+}
+
+/// Propagate the first layer's outputs through the second layer.
+pub fn propagate_l2(
+    inputs: &Align<[f32; L2_SIZE]>,
+    weights: &Align<[f32; L2_SIZE * L3_SIZE * 2]>,
+    biases: &Align<[f32; L3_SIZE * 2]>,
+    output: &mut Align<[f32; L3_SIZE]>,
+) {
+    dispatch!(propagate_l2(inputs, weights, biases, output));
+}
+
+/// Propagate the second layer's outputs through the output layer.
+pub fn propagate_l3(
+    inputs: &Align<[f32; L3_SIZE]>,
+    weights: &Align<[f32; L3_SIZE]>,
+    bias: f32,
+    output: &mut f32,
+) {
+    dispatch!(propagate_l3(inputs, weights, bias, output));
+}
+
+use std::sync::LazyLock;
+
+use super::{Align, L1_SIZE, L2_SIZE, L3_SIZE, QA, QB};
+use crate::nnue::simd;
 
 // logging for permutation
 #[cfg(feature = "nnz-counts")]
@@ -473,3 +637,100 @@ pub static NNZ_COUNTS: [[std::sync::atomic::AtomicU64; super::L1_SIZE / 2]; supe
     // Safety: AtomicU64 is repr-compatible with u64.
     unsafe { std::mem::transmute([[0u64; super::L1_SIZE / 2]; super::L1_SIZE / 2]) }
 };
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::XorShiftState;
+
+    /// Lay out feature transformer outputs as `kernel` expects them.
+    fn interleave(values: &Align<[i16; L1_SIZE]>, kernel: Kernel) -> Box<Align<[i16; L1_SIZE]>> {
+        let order = kernel.packus_order();
+        let mut out = Box::new(Align([0; L1_SIZE]));
+        let src = values.0.as_chunks::<8>().0;
+        let dst = out.0.as_chunks_mut::<8>().0;
+        for (i, block) in dst.iter_mut().enumerate() {
+            let group = i - i % order.len();
+            *block = src[group + order[i % order.len()]];
+        }
+        out
+    }
+
+    #[test]
+    #[allow(
+        clippy::cast_possible_truncation,
+        clippy::cast_possible_wrap,
+        clippy::cast_precision_loss,
+        clippy::float_cmp,
+        clippy::large_stack_arrays
+    )]
+    fn kernels_agree() {
+        let mut rng = XorShiftState::with_seed(5);
+        let mut i16s = |lo: i16, span: u64| {
+            let mut out = Box::new(Align([0; L1_SIZE]));
+            for v in &mut out.0 {
+                *v = lo + (rng.next() % span) as i16;
+            }
+            out
+        };
+        let [stm_psqt, ntm_psqt] = [i16s(-100, 400), i16s(-100, 400)];
+        let [stm_thrt, ntm_thrt] = [i16s(-50, 100), i16s(-50, 100)];
+        let mut rng = XorShiftState::with_seed(6);
+        let mut weights = Box::new(Align([0; L1_SIZE * L2_SIZE]));
+        for w in &mut weights.0 {
+            *w = (rng.next() % 128) as i8 - 64;
+        }
+        let mut float = || (rng.next() % 2000) as f32 / 1000.0 - 1.0;
+        let l1_biases = Align(std::array::from_fn(|_| float()));
+        let l2_weights = Box::new(Align(std::array::from_fn(|_| float())));
+        let l2_biases = Align(std::array::from_fn(|_| float()));
+        let l3_weights = Align(std::array::from_fn(|_| float()));
+
+        let run = |kernel: Kernel| {
+            let [sp, np, st, nt] =
+                [&stm_psqt, &ntm_psqt, &stm_thrt, &ntm_thrt].map(|v| interleave(v, kernel));
+            let mut l1_out = Align([0.0; L2_SIZE]);
+            let mut l2_out = Align([0.0; L3_SIZE]);
+            let mut l3_out = 0.0;
+            // SAFETY: `Kernel::available` only lists kernels that the CPU supports.
+            unsafe {
+                macro_rules! forward {
+                    ($kernel:ident) => {{
+                        $kernel::activate_ft_and_propagate_l1(
+                            &sp,
+                            &np,
+                            &st,
+                            &nt,
+                            &weights,
+                            &l1_biases,
+                            &mut l1_out,
+                        );
+                        $kernel::propagate_l2(&l1_out, &l2_weights, &l2_biases, &mut l2_out);
+                        $kernel::propagate_l3(&l2_out, &l3_weights, 0.5, &mut l3_out);
+                    }};
+                }
+                match kernel {
+                    #[cfg(target_arch = "x86_64")]
+                    Kernel::Avx512Vnni => forward!(avx512vnni),
+                    #[cfg(target_arch = "x86_64")]
+                    Kernel::Avx512 => forward!(avx512),
+                    #[cfg(target_arch = "x86_64")]
+                    Kernel::Avx2 => forward!(avx2),
+                    #[cfg(target_feature = "neon")]
+                    Kernel::Neon => forward!(neon),
+                    Kernel::Generic => forward!(generic),
+                }
+            }
+            (l1_out.0, l2_out.0, l3_out)
+        };
+
+        let expected = run(Kernel::Generic);
+        assert!(expected.0.iter().any(|&x| x != 0.0));
+        for kernel in Kernel::available() {
+            let (l1, l2, l3) = run(kernel);
+            assert_eq!(l1, expected.0, "{}", kernel.name());
+            assert_eq!(l2, expected.1, "{}", kernel.name());
+            assert!((l3 - expected.2).abs() < 1e-3, "{}", kernel.name());
+        }
+    }
+}
@@ -369,7 +369,7 @@ pub fn on_change<Op: AddSub>(
 ) {
     // make an index-list & rays for `sq`, the focus-square.
     let perm = geometry::permutation_for(sq);
-    let (rays, bits) = geometry::permute_mailbox(&perm, &board.state.mailbox);
+    let (rays, bits) = geometry::permute_mailbox(&perm, &board.mailbox);
     let non_king = !geometry::test_bit(bits, geometry::Bit::KING);
 
     // focus-square relative threats
@@ -425,7 +425,7 @@ pub fn on_mutate(
 ) {
     // make an index-list & rays for `sq`, the focus-square.
     let perm = geometry::permutation_for(sq);
-    let (rays, bits) = geometry::permute_mailbox(&perm, &board.state.mailbox);
+    let (rays, bits) = geometry::permute_mailbox(&perm, &board.mailbox);
     let non_king = !geometry::test_bit(bits, geometry::Bit::KING);
 
     // focus-square relative threats
@@ -464,9 +464,8 @@ pub fn on_move(
 ) {
     let src_perm = geometry::permutation_for(src);
     let dst_perm = geometry::permutation_for(dst);
-    let (src_rays, src_bits) =
-        geometry::permute_mailbox_ignoring(&src_perm, &board.state.mailbox, dst);
-    let (dst_rays, dst_bits) = geometry::permute_mailbox(&dst_perm, &board.state.mailbox);
+    let (src_rays, src_bits) = geometry::permute_mailbox_ignoring(&src_perm, &board.mailbox, dst);
+    let (dst_rays, dst_bits) = geometry::permute_mailbox(&dst_perm, &board.mailbox);
     let src_non_king = !geometry::test_bit(src_bits, geometry::Bit::KING);
     let dst_non_king = !geometry::test_bit(dst_bits, geometry::Bit::KING);
 
@@ -547,10 +546,10 @@ mod tests {
         let non_kings = occ & !bbs.pieces[PieceType::King];
 
         for from in non_kings {
-            let attacker = board.state.mailbox[from].unwrap();
+            let attacker = board.mailbox[from].unwrap();
             let targets = occ & attacks_by_type(attacker, from, occ) & !bbs.pieces[PieceType::King];
             for to in targets {
-                let victim = board.state.mailbox[to].unwrap();
+                let victim = board.mailbox[to].unwrap();
                 threats.push(ThreatFeatureUpdate {
                     attacker,
                     from,
@@ -612,7 +611,7 @@ mod tests {
         let threats_before = collect_threats_simple(&board_before);
 
         let mut buf = AuxUpdateBuffer::default();
-        let piece = board_before.state.mailbox[m.from()].unwrap();
+        let piece = board_before.mailbox[m.from()].unwrap();
 
         // Remove threats before the move
         on_change::<Sub>(&mut buf, &board_before, piece, m.from());
@@ -0,0 +1,180 @@
+//! Checking the incrementally updated accumulators against ones refreshed from scratch.
+//!
+//! Incremental-update bugs usually show up as a slightly wrong evaluation many moves after
+//! the move that caused them, so we play through games and compare the accumulators at every
+//! node, reporting the first position where they disagree.
+
+use anyhow::bail;
+
+use crate::{
+    chess::{board::Board, chessmove::Move, piece::Colour},
+    rng::XorShiftState,
+    util::MAX_DEPTH,
+};
+
+use super::{NNUEParams, NNUEState};
+
+/// The deepest line of unforced moves explored from each position of a game, so that
+/// multi-move lazy updates and unmaking are checked as well as single moves.
+const PROBE_DEPTH: usize = 4;
+/// The longest game played, which must leave room on the accumulator stack for the probes.
+const MAX_GAME_LENGTH: usize = MAX_DEPTH - PROBE_DEPTH - 1;
+
+/// Play `games` random games, checking the incremental accumulators against a refresh at
+/// every node. Random weights make every feature count, which the embedded network
+/// may not.
+pub fn verify(games: usize, seed: u64, random_weights: bool) -> anyhow::Result<()> {
+    let mut rng = XorShiftState::with_seed(seed);
+    let nnue_params: &NNUEParams = if random_weights {
+        Box::leak(randomised_params(&mut rng))
+    } else {
+        NNUEParams::decompress_and_alloc()?
+    };
+
+    let mut incremental = NNUEState::new(&Board::startpos(), nnue_params);
+    let mut fresh = NNUEState::new(&Board::startpos(), nnue_params);
+    let mut positions = 0u64;
+
+    for game in 0..games {
+        // alternate between standard chess and double Chess960, for the castling variety.
+        #[allow(clippy::cast_possible_truncation)]
+        let mut board = if game % 2 == 0 {
+            Board::startpos()
+        } else {
+            Board::from_dfrc_idx(rng.next_below(960 * 960) as usize)
+        };
+        let root = board.clone();
+        incremental.reïnit_from(&board, nnue_params);
+        let mut line = Vec::new();
+
+        for _ in 0..MAX_GAME_LENGTH {
+            let Some(m) = random_move(&board, &mut rng) else {
+                break;
+            };
+            board.make_move(m, &mut incremental);
+            line.push(m);
+
+            // walk a few moves further without forcing the updates, as the search does.
+            #[allow(clippy::cast_possible_truncation)]
+            let depth = rng.next_below(PROBE_DEPTH as u64 + 1) as usize;
+            let mut probe = 0;
+            while probe < depth {
+                let Some(m) = random_move(&board, &mut rng) else {
+                    break;
+                };
+                board.make_move(m, &mut incremental);
+                line.push(m);
+                probe += 1;
+            }
+
+            for unwinding in (0..=probe).rev() {
+                incremental.force(&board, nnue_params);
+                fresh.reïnit_from(&board, nnue_params);
+                positions += 1;
+                if let Some(divergence) = first_divergence(&incremental, &fresh) {
+                    println!("[#] divergence in game {game}, at {board}");
+                    println!("[#] starting from {root}");
+                    println!("[#] after {}", format_line(&root, &line));
+                    println!("[#] {divergence}");
+                    bail!("incremental NNUE update diverged from a refresh");
+                }
+                if unwinding > 0 {
+                    board.unmake_move(&mut incremental);
+                    line.pop();
+                }
+            }
+        }
+    }
+
+    println!("[#] checked {positions} positions over {games} games, no divergence");
+    Ok(())
+}
+
+/// A network whose first-layer weights are random, and everything else zero.
+fn randomised_params(rng: &mut XorShiftState) -> Box<NNUEParams> {
+    #![allow(clippy::cast_possible_truncation)]
+    let mut params = NNUEParams::zeroed();
+    for w in params.l0_weights.iter_mut() {
+        *w = rng.next() as i16 >> 6;
+    }
+    for w in params.l0_biases.iter_mut() {
+        *w = rng.next() as i16 >> 6;
+    }
+    for w in params.l0_aux.iter_mut() {
+        *w = rng.next() as i8;
+    }
+    params
+}
+
+fn random_move(board: &Board, rng: &mut XorShiftState) -> Option<Move> {
+    #![allow(clippy::cast_possible_truncation)]
+    let moves = board.legal_moves();
+    if moves.is_empty() {
+        return None;
+    }
+    Some(moves[rng.next_below(moves.len() as u64) as usize])
+}
+
+fn format_line(root: &Board, line: &[Move]) -> String {
+    let rules = root.rules();
+    line.iter()
+        .map(|m| m.display(rules).to_string())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Describe the first neuron at which the current accumulators of `incremental` and `fresh`
+/// differ, if any do.
+fn first_divergence(incremental: &NNUEState, fresh: &NNUEState) -> Option<String> {
+    let pairs = [
+        (
+            "piece-square",
+            &incremental.psqt_accumulators[incremental.current_acc],
+            &fresh.psqt_accumulators[fresh.current_acc],
+        ),
+        (
+            "threat",
+            &incremental.threat_accumulators[incremental.current_acc],
+            &fresh.threat_accumulators[fresh.current_acc],
+        ),
+    ];
+    for (kind, inc, refreshed) in pairs {
+        for colour in Colour::all() {
+            let found = inc.halves[colour]
+                .iter()
+                .zip(refreshed.halves[colour].iter())
+                .enumerate()
+                .find(|(_, (a, b))| a != b);
+            if let Some((neuron, (a, b))) = found {
+                return Some(format!(
+                    "{kind} accumulator for {colour} differs at neuron {neuron}: incremental {a}, refreshed {b}"
+                ));
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn incremental_updates_match_refresh() {
+        verify(2, 0, true).unwrap();
+    }
+
+    #[test]
+    fn reports_divergence() {
+        let mut rng = XorShiftState::new();
+        let params = randomised_params(&mut rng);
+        let board = Board::startpos();
+        let incremental = NNUEState::new(&board, &params);
+        let mut fresh = NNUEState::new(&board, &params);
+        assert!(first_divergence(&incremental, &fresh).is_none());
+        fresh.psqt_accumulators[0].halves[Colour::Black][7] += 1;
+        let report = first_divergence(&incremental, &fresh).unwrap();
+        assert!(report.starts_with("piece-square accumulator for"));
+        assert!(report.contains("neuron 7"));
+    }
+}
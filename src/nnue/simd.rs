@@ -32,6 +32,21 @@ pub const fn mm_shuffle(z: i32, y: i32, x: i32, w: i32) -> i32 {
     ((z) << 6) | ((y) << 4) | ((x) << 2) | (w)
 }
 
+/// Reinterpret the bytes of an i32 vector as i8s, and back, for a backend's register types.
+#[allow(unused_macros)]
+macro_rules! register_transmutes {
+    () => {
+        #[inline(always)]
+        pub fn trans_i32_i8(vec: VecI32) -> VecI8 {
+            unsafe { VecI8::from_raw(std::mem::transmute(vec.inner())) }
+        }
+        #[inline(always)]
+        pub fn trans_i8_i32(vec: VecI8) -> VecI32 {
+            unsafe { VecI32::from_raw(std::mem::transmute(vec.inner())) }
+        }
+    };
+}
+
 /// Given a regular type and a SIMD register type, and the new type name, create a new type that wraps the register type.
 #[allow(unused_macros)]
 macro_rules! wrap_simd_register {
@@ -52,18 +67,20 @@ macro_rules! wrap_simd_register {
     };
 }
 
-#[cfg(target_feature = "avx512f")]
-mod avx512 {
+#[cfg(target_arch = "x86_64")]
+pub mod avx512 {
     #![allow(non_camel_case_types)]
     use std::arch::x86_64::*;
 
     pub const INNER_ARCH: &str = "avx512";
+    pub const MUL_HIGH_DOUBLES: bool = false;
 
     wrap_simd_register!(__m512i, i8, VecI8);
     wrap_simd_register!(__m512i, i16, VecI16);
     wrap_simd_register!(__m512i, i32, VecI32);
     wrap_simd_register!(__m512i, i64, VecI64);
     wrap_simd_register!(__m512, f32, VecF32);
+    register_transmutes!();
 
     wrap_simd_register!(__m128i, i16, Vec128U16);
 
@@ -233,20 +250,9 @@ mod avx512 {
     #[inline(always)]
     pub unsafe fn madd_u8_to_i32(sum: VecI32, vec0: VecI8, vec1: VecI8) -> VecI32 {
         unsafe {
-            #[cfg(target_feature = "avx512vnni")]
-            {
-                return VecI32::from_raw(_mm512_dpbusd_epi32(
-                    sum.inner(),
-                    vec0.inner(),
-                    vec1.inner(),
-                ));
-            }
-            #[cfg(not(target_feature = "avx512vnni"))]
-            {
-                let product16 = _mm512_maddubs_epi16(vec0.inner(), vec1.inner());
-                let product32 = _mm512_madd_epi16(product16, _mm512_set1_epi16(1));
-                return VecI32::from_raw(_mm512_add_epi32(sum.inner(), product32));
-            }
+            let product16 = _mm512_maddubs_epi16(vec0.inner(), vec1.inner());
+            let product32 = _mm512_madd_epi16(product16, _mm512_set1_epi16(1));
+            return VecI32::from_raw(_mm512_add_epi32(sum.inner(), product32));
         }
     }
     #[inline(always)]
@@ -258,24 +264,13 @@ mod avx512 {
         vec3: VecI8,
     ) -> VecI32 {
         unsafe {
-            #[cfg(target_feature = "avx512vnni")]
-            {
-                return VecI32::from_raw(_mm512_dpbusd_epi32(
-                    _mm512_dpbusd_epi32(sum.inner(), vec0.inner(), vec1.inner()),
-                    vec2.inner(),
-                    vec3.inner(),
-                ));
-            }
-            #[cfg(not(target_feature = "avx512vnni"))]
-            {
-                let product16a = _mm512_maddubs_epi16(vec0.inner(), vec1.inner());
-                let product16b = _mm512_maddubs_epi16(vec2.inner(), vec3.inner());
-                let product32 = _mm512_madd_epi16(
-                    _mm512_add_epi16(product16a, product16b),
-                    _mm512_set1_epi16(1),
-                );
-                return VecI32::from_raw(_mm512_add_epi32(sum.inner(), product32));
-            }
+            let product16a = _mm512_maddubs_epi16(vec0.inner(), vec1.inner());
+            let product16b = _mm512_maddubs_epi16(vec2.inner(), vec3.inner());
+            let product32 = _mm512_madd_epi16(
+                _mm512_add_epi16(product16a, product16b),
+                _mm512_set1_epi16(1),
+            );
+            return VecI32::from_raw(_mm512_add_epi32(sum.inner(), product32));
         }
     }
     #[inline(always)]
@@ -408,18 +403,54 @@ mod avx512 {
     pub const F32_CHUNK: usize = std::mem::size_of::<VecF32>() / std::mem::size_of::<f32>();
 }
 
-#[cfg(not(any(target_feature = "neon", target_feature = "avx512f")))]
-mod avx2 {
+/// AVX-512 with VNNI, which multiplies u8s by i8s and accumulates into i32s in one
+/// `vpdpbusd`, where plain AVX-512 needs three instructions.
+#[cfg(target_arch = "x86_64")]
+pub mod avx512vnni {
+    use std::arch::x86_64::*;
+
+    pub use super::avx512::*;
+
+    pub const INNER_ARCH: &str = "avx512vnni";
+
+    #[inline(always)]
+    pub unsafe fn madd_u8_to_i32(sum: VecI32, vec0: VecI8, vec1: VecI8) -> VecI32 {
+        unsafe {
+            return VecI32::from_raw(_mm512_dpbusd_epi32(sum.inner(), vec0.inner(), vec1.inner()));
+        }
+    }
+    #[inline(always)]
+    pub unsafe fn madd_2xu8_to_i32(
+        sum: VecI32,
+        vec0: VecI8,
+        vec1: VecI8,
+        vec2: VecI8,
+        vec3: VecI8,
+    ) -> VecI32 {
+        unsafe {
+            return VecI32::from_raw(_mm512_dpbusd_epi32(
+                _mm512_dpbusd_epi32(sum.inner(), vec0.inner(), vec1.inner()),
+                vec2.inner(),
+                vec3.inner(),
+            ));
+        }
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+pub mod avx2 {
     #![allow(non_camel_case_types)]
     use std::arch::x86_64::*;
 
     pub const INNER_ARCH: &str = "avx2";
+    pub const MUL_HIGH_DOUBLES: bool = false;
 
     wrap_simd_register!(__m256i, i8, VecI8);
     wrap_simd_register!(__m256i, i16, VecI16);
     wrap_simd_register!(__m256i, i32, VecI32);
     wrap_simd_register!(__m256i, i64, VecI64);
     wrap_simd_register!(__m256, f32, VecF32);
+    register_transmutes!();
 
     wrap_simd_register!(__m128i, i16, Vec128U16);
 
@@ -768,10 +799,12 @@ mod avx2 {
 }
 
 #[cfg(target_feature = "neon")]
-mod neon {
+pub mod neon {
     use std::arch::aarch64::*;
 
     pub const INNER_ARCH: &str = "neon";
+    // on NEON, the instruction used for mulhi doubles the results.
+    pub const MUL_HIGH_DOUBLES: bool = true;
 
     // trying to universally use the 128-bit vectors.
     wrap_simd_register!(int8x16_t, i8, VecI8);
@@ -779,6 +812,7 @@ mod neon {
     wrap_simd_register!(int32x4_t, i32, VecI32);
     wrap_simd_register!(int64x2_t, i64, VecI64);
     wrap_simd_register!(float32x4_t, i8, VecF32);
+    register_transmutes!();
 
     wrap_simd_register!(uint16x8_t, u16, Vec128U16);
 
@@ -1160,23 +1194,315 @@ mod neon {
     pub const F32_CHUNK: usize = std::mem::size_of::<VecF32>() / std::mem::size_of::<f32>();
 }
 
+/// A portable fallback for targets without a supported SIMD instruction set, such as
+/// `wasm32`, and for x86-64 CPUs without AVX2. The "registers" are plain arrays as wide as an AVX2 register, and each
+/// operation matches the AVX2 one lane-for-lane, except that packing doesn't interleave
+/// lanes, so the network weights need no SIMD permutation.
+pub mod generic {
+    pub const INNER_ARCH: &str = "generic";
+    pub const MUL_HIGH_DOUBLES: bool = false;
+
+    wrap_simd_register!([i8; 32], i8, VecI8);
+    wrap_simd_register!([i16; 16], i16, VecI16);
+    wrap_simd_register!([i32; 8], i32, VecI32);
+    wrap_simd_register!([i64; 4], i64, VecI64);
+    wrap_simd_register!([f32; 8], f32, VecF32);
+    register_transmutes!();
+
+    wrap_simd_register!([u16; 8], u16, Vec128U16);
+
+    #[inline(always)]
+    fn map<T: Copy, U, const N: usize>(a: [T; N], f: impl Fn(T) -> U) -> [U; N] {
+        a.map(f)
+    }
+    #[inline(always)]
+    fn zip<T: Copy, U, const N: usize>(a: [T; N], b: [T; N], f: impl Fn(T, T) -> U) -> [U; N] {
+        std::array::from_fn(|i| f(a[i], b[i]))
+    }
+    /// `_mm256_maddubs_epi16`: multiply unsigned bytes by signed bytes, and add adjacent
+    /// pairs with saturation.
+    #[inline(always)]
+    fn maddubs(a: VecI8, b: VecI8) -> [i16; 16] {
+        let (a, b) = (a.inner(), b.inner());
+        std::array::from_fn(|i| {
+            let lo = i32::from(a[2 * i] as u8) * i32::from(b[2 * i]);
+            let hi = i32::from(a[2 * i + 1] as u8) * i32::from(b[2 * i + 1]);
+            (lo + hi).clamp(i32::from(i16::MIN), i32::from(i16::MAX)) as i16
+        })
+    }
+    /// `_mm256_madd_epi16` against ones: add adjacent pairs of words into dwords.
+    #[inline(always)]
+    fn hadd_pairs(a: [i16; 16]) -> [i32; 8] {
+        std::array::from_fn(|i| i32::from(a[2 * i]) + i32::from(a[2 * i + 1]))
+    }
+    /// Horizontal sum, in the same order as the AVX2 reduction.
+    #[inline(always)]
+    fn reduce(v: [f32; 8]) -> f32 {
+        let sum_128: [f32; 4] = std::array::from_fn(|i| v[i + 4] + v[i]);
+        let sum_64 = [sum_128[2] + sum_128[0], sum_128[3] + sum_128[1]];
+        sum_64[1] + sum_64[0]
+    }
+
+    #[inline(always)]
+    pub unsafe fn zero_i16() -> VecI16 {
+        return VecI16::from_raw([0; 16]);
+    }
+    #[inline(always)]
+    pub unsafe fn zero_i32() -> VecI32 {
+        return VecI32::from_raw([0; 8]);
+    }
+    #[inline(always)]
+    pub unsafe fn splat_i16(n: i16) -> VecI16 {
+        return VecI16::from_raw([n; 16]);
+    }
+    #[inline(always)]
+    pub unsafe fn splat_i32(n: i32) -> VecI32 {
+        return VecI32::from_raw([n; 8]);
+    }
+    #[inline(always)]
+    pub unsafe fn load_i8(src: *const i8) -> VecI8 {
+        unsafe {
+            return VecI8::from_raw(src.cast::<[i8; 32]>().read_unaligned());
+        }
+    }
+    #[inline(always)]
+    pub unsafe fn store_i8(dst: *mut i8, vec: VecI8) {
+        unsafe {
+            dst.cast::<[i8; 32]>().write_unaligned(vec.inner());
+        }
+    }
+    #[inline(always)]
+    pub unsafe fn load_u8(src: *const u8) -> VecI8 {
+        unsafe {
+            return VecI8::from_raw(src.cast::<[i8; 32]>().read_unaligned());
+        }
+    }
+    #[inline(always)]
+    pub unsafe fn store_u8(dst: *mut u8, vec: VecI8) {
+        unsafe {
+            dst.cast::<[i8; 32]>().write_unaligned(vec.inner());
+        }
+    }
+    #[inline(always)]
+    pub unsafe fn load_i16(src: *const i16) -> VecI16 {
+        unsafe {
+            return VecI16::from_raw(src.cast::<[i16; 16]>().read_unaligned());
+        }
+    }
+    #[inline(always)]
+    pub unsafe fn store_i16(dst: *mut i16, vec: VecI16) {
+        unsafe {
+            dst.cast::<[i16; 16]>().write_unaligned(vec.inner());
+        }
+    }
+    #[inline(always)]
+    pub unsafe fn load_i32(src: *const i32) -> VecI32 {
+        unsafe {
+            return VecI32::from_raw(src.cast::<[i32; 8]>().read_unaligned());
+        }
+    }
+    #[inline(always)]
+    pub unsafe fn store_i32(dst: *mut i32, vec: VecI32) {
+        unsafe {
+            dst.cast::<[i32; 8]>().write_unaligned(vec.inner());
+        }
+    }
+    #[inline(always)]
+    pub unsafe fn store_u32(dst: *mut u32, vec: VecI32) {
+        unsafe {
+            dst.cast::<[i32; 8]>().write_unaligned(vec.inner());
+        }
+    }
+    #[inline(always)]
+    pub unsafe fn max_i16(vec0: VecI16, vec1: VecI16) -> VecI16 {
+        return VecI16::from_raw(zip(vec0.inner(), vec1.inner(), i16::max));
+    }
+    #[inline(always)]
+    pub unsafe fn min_i16(vec0: VecI16, vec1: VecI16) -> VecI16 {
+        return VecI16::from_raw(zip(vec0.inner(), vec1.inner(), i16::min));
+    }
+    #[inline(always)]
+    pub unsafe fn add_i16(vec0: VecI16, vec1: VecI16) -> VecI16 {
+        return VecI16::from_raw(zip(vec0.inner(), vec1.inner(), i16::wrapping_add));
+    }
+    #[inline(always)]
+    pub unsafe fn sub_i16(vec0: VecI16, vec1: VecI16) -> VecI16 {
+        return VecI16::from_raw(zip(vec0.inner(), vec1.inner(), i16::wrapping_sub));
+    }
+    #[inline(always)]
+    pub unsafe fn add_i32(vec0: VecI32, vec1: VecI32) -> VecI32 {
+        return VecI32::from_raw(zip(vec0.inner(), vec1.inner(), i32::wrapping_add));
+    }
+    #[inline(always)]
+    pub unsafe fn mul_high_i16(vec0: VecI16, vec1: VecI16) -> VecI16 {
+        return VecI16::from_raw(zip(vec0.inner(), vec1.inner(), |a, b| {
+            ((i32::from(a) * i32::from(b)) >> 16) as i16
+        }));
+    }
+    // stupid hack for the different intrinsics
+    pub type S = i32;
+    #[inline(always)]
+    pub unsafe fn shl_i16<const SHIFT: i32>(vec: VecI16) -> VecI16 {
+        return VecI16::from_raw(map(vec.inner(), |a| ((a as u16) << SHIFT) as i16));
+    }
+    #[inline(always)]
+    pub unsafe fn shift_mul_high_i16<const SHIFT: i32>(vec0: VecI16, vec1: VecI16) -> VecI16 {
+        unsafe {
+            return mul_high_i16(shl_i16::<SHIFT>(vec0), vec1);
+        }
+    }
+    #[inline(always)]
+    pub unsafe fn nonzero_mask_i32(vec: VecI32) -> u16 {
+        let mut mask = 0;
+        for (i, lane) in vec.inner().into_iter().enumerate() {
+            mask |= u16::from(lane > 0) << i;
+        }
+        return mask;
+    }
+    #[inline(always)]
+    pub unsafe fn pack_i16_to_u8(vec0: VecI16, vec1: VecI16) -> VecI8 {
+        let (a, b) = (vec0.inner(), vec1.inner());
+        return VecI8::from_raw(std::array::from_fn(|i| {
+            let x = if i < 16 { a[i] } else { b[i - 16] };
+            x.clamp(0, 255) as u8 as i8
+        }));
+    }
+    #[inline(always)]
+    pub unsafe fn madd_u8_to_i32(sum: VecI32, vec0: VecI8, vec1: VecI8) -> VecI32 {
+        let product32 = hadd_pairs(maddubs(vec0, vec1));
+        return VecI32::from_raw(zip(sum.inner(), product32, i32::wrapping_add));
+    }
+    #[inline(always)]
+    pub unsafe fn madd_2xu8_to_i32(
+        sum: VecI32,
+        vec0: VecI8,
+        vec1: VecI8,
+        vec2: VecI8,
+        vec3: VecI8,
+    ) -> VecI32 {
+        let product16 = zip(maddubs(vec0, vec1), maddubs(vec2, vec3), i16::wrapping_add);
+        let product32 = hadd_pairs(product16);
+        return VecI32::from_raw(zip(sum.inner(), product32, i32::wrapping_add));
+    }
+    #[inline(always)]
+    pub unsafe fn i32_to_f32(vec: VecI32) -> VecF32 {
+        return VecF32::from_raw(map(vec.inner(), |a| a as f32));
+    }
+    #[inline(always)]
+    pub unsafe fn zero_f32() -> VecF32 {
+        return VecF32::from_raw([0.0; 8]);
+    }
+    #[inline(always)]
+    pub unsafe fn splat_f32(n: f32) -> VecF32 {
+        return VecF32::from_raw([n; 8]);
+    }
+    #[inline(always)]
+    pub unsafe fn load_f32(src: *const f32) -> VecF32 {
+        unsafe {
+            return VecF32::from_raw(src.cast::<[f32; 8]>().read_unaligned());
+        }
+    }
+    #[inline(always)]
+    pub unsafe fn store_f32(dst: *mut f32, vec: VecF32) {
+        unsafe {
+            dst.cast::<[f32; 8]>().write_unaligned(vec.inner());
+        }
+    }
+    #[inline(always)]
+    pub unsafe fn add_f32(vec0: VecF32, vec1: VecF32) -> VecF32 {
+        return VecF32::from_raw(zip(vec0.inner(), vec1.inner(), |a, b| a + b));
+    }
+    #[inline(always)]
+    pub unsafe fn mul_f32(vec0: VecF32, vec1: VecF32) -> VecF32 {
+        return VecF32::from_raw(zip(vec0.inner(), vec1.inner(), |a, b| a * b));
+    }
+    #[inline(always)]
+    pub unsafe fn div_f32(vec0: VecF32, vec1: VecF32) -> VecF32 {
+        return VecF32::from_raw(zip(vec0.inner(), vec1.inner(), |a, b| a / b));
+    }
+    #[inline(always)]
+    pub unsafe fn max_f32(vec0: VecF32, vec1: VecF32) -> VecF32 {
+        // like maxps, the second operand is returned if the comparison fails.
+        return VecF32::from_raw(zip(
+            vec0.inner(),
+            vec1.inner(),
+            |a, b| if a > b { a } else { b },
+        ));
+    }
+    #[inline(always)]
+    pub unsafe fn min_f32(vec0: VecF32, vec1: VecF32) -> VecF32 {
+        return VecF32::from_raw(zip(
+            vec0.inner(),
+            vec1.inner(),
+            |a, b| if a < b { a } else { b },
+        ));
+    }
+    #[inline(always)]
+    pub unsafe fn madd_f32(vec0: VecF32, vec1: VecF32, vec2: VecF32) -> VecF32 {
+        let (a, b, c) = (vec0.inner(), vec1.inner(), vec2.inner());
+        return VecF32::from_raw(std::array::from_fn(|i| a[i].mul_add(b[i], c[i])));
+    }
+    #[inline(always)]
+    pub unsafe fn sum_f32(vec: VecF32) -> f32 {
+        return reduce(vec.inner());
+    }
+    #[inline(always)]
+    pub unsafe fn reduce_add_f32s(vec: &[VecF32; 2]) -> f32 {
+        return reduce(zip(vec[0].inner(), vec[1].inner(), |a, b| a + b));
+    }
+
+    #[inline(always)]
+    pub unsafe fn load_extend_i8(src: *const i8) -> VecI16 {
+        unsafe {
+            let half_load = src.cast::<[i8; 16]>().read_unaligned();
+            return VecI16::from_raw(map(half_load, i16::from));
+        }
+    }
+
+    #[inline(always)]
+    pub unsafe fn v128_zero() -> Vec128U16 {
+        return Vec128U16::from_raw([0; 8]);
+    }
+    #[inline(always)]
+    pub unsafe fn v128_splat(n: u16) -> Vec128U16 {
+        return Vec128U16::from_raw([n; 8]);
+    }
+    #[inline(always)]
+    pub unsafe fn v128_load(src: *const u16) -> Vec128U16 {
+        unsafe {
+            return Vec128U16::from_raw(src.cast::<[u16; 8]>().read_unaligned());
+        }
+    }
+    #[inline(always)]
+    pub unsafe fn v128_store(dst: *mut u16, vec: Vec128U16) {
+        unsafe {
+            dst.cast::<[u16; 8]>().write_unaligned(vec.inner());
+        }
+    }
+    #[inline(always)]
+    pub unsafe fn v128_add(vec0: Vec128U16, vec1: Vec128U16) -> Vec128U16 {
+        return Vec128U16::from_raw(zip(vec0.inner(), vec1.inner(), u16::wrapping_add));
+    }
+
+    pub const U8_CHUNK: usize = std::mem::size_of::<VecI8>() / std::mem::size_of::<u8>();
+    pub const I8_CHUNK_I32: usize = std::mem::size_of::<i32>() / std::mem::size_of::<u8>();
+    pub const I8_CHUNK: usize = std::mem::size_of::<VecI8>() / std::mem::size_of::<i8>();
+    pub const I16_CHUNK: usize = std::mem::size_of::<VecI16>() / std::mem::size_of::<i16>();
+    pub const I32_CHUNK: usize = std::mem::size_of::<VecI32>() / std::mem::size_of::<i32>();
+    pub const F32_CHUNK: usize = std::mem::size_of::<VecF32>() / std::mem::size_of::<f32>();
+}
+
+// the forward pass picks between the backends at runtime (see `layers::Kernel`), but the
+// accumulator updates use whichever one the target is compiled for.
+
 #[cfg(target_feature = "avx512f")]
 pub use avx512::*;
 
-#[cfg(not(any(target_feature = "neon", target_feature = "avx512f")))]
+#[cfg(all(target_feature = "avx2", not(target_feature = "avx512f")))]
 pub use avx2::*;
 
+#[cfg(not(any(target_feature = "avx2", target_feature = "neon")))]
+pub use generic::*;
+
 #[cfg(target_feature = "neon")]
 pub use neon::*;
-
-#[inline(always)]
-pub fn trans_i32_i8(vec: VecI32) -> VecI8 {
-    unsafe { VecI8::from_raw(std::mem::transmute(vec.inner())) }
-}
-
-#[inline(always)]
-pub fn trans_i8_i32(vec: VecI8) -> VecI32 {
-    unsafe { VecI32::from_raw(std::mem::transmute(vec.inner())) }
-}
-
-pub const ARCH: &str = INNER_ARCH;
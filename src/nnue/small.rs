@@ -0,0 +1,158 @@
+//! A tiny companion network, used in place of the main network when a position is clearly
+//! decided, where a rough evaluation is enough and the main network's cost isn't worth paying.
+//!
+//! The layout is bullet's simple `(768 -> HIDDEN)x2 -> 1` perspective network with a
+//! squared-clipped-ReLU activation. It's small enough to evaluate from scratch, so it keeps
+//! no accumulators.
+
+use std::{
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use anyhow::Context;
+
+use crate::chess::{
+    board::Board,
+    piece::{Colour, Piece},
+    types::Square,
+};
+
+/// The width of the hidden layer.
+pub const HIDDEN: usize = 128;
+/// The number of input features: a piece on a square, for each piece and colour.
+const INPUTS: usize = 768;
+const QA: i32 = 255;
+const QB: i32 = 64;
+const SCALE: i64 = 400;
+
+/// The quantised weights of the small network, as bullet writes them.
+#[repr(C)]
+pub struct SmallNet {
+    feature_weights: [[i16; HIDDEN]; INPUTS],
+    feature_bias: [i16; HIDDEN],
+    output_weights: [i16; 2 * HIDDEN],
+    output_bias: i16,
+}
+
+impl SmallNet {
+    /// Load the network from a file. Files with trailing padding are accepted, as bullet pads
+    /// its output. The weights live as long as the engine, so each file is only loaded once,
+    /// and loading it again returns the same weights.
+    pub fn load(path: &Path) -> anyhow::Result<&'static Self> {
+        static LOADED: Mutex<Vec<(PathBuf, &'static SmallNet)>> = Mutex::new(Vec::new());
+        let path = std::fs::canonicalize(path)
+            .with_context(|| format!("Failed to find network file at {}", path.display()))?;
+        let mut loaded = LOADED.lock().unwrap();
+        if let Some(&(_, net)) = loaded.iter().find(|(p, _)| *p == path) {
+            return Ok(net);
+        }
+        let bytes = std::fs::read(&path)
+            .with_context(|| format!("Failed to read network file at {}", path.display()))?;
+        anyhow::ensure!(
+            bytes.len() >= size_of::<Self>(),
+            "network file is {} bytes, but a small network is {} bytes",
+            bytes.len(),
+            size_of::<Self>()
+        );
+        // SAFETY: All bitpatterns of Self are valid.
+        let mut net: Box<Self> = unsafe { Box::new_zeroed().assume_init() };
+        // SAFETY: Self is POD, and the file holds at least as many bytes as it.
+        unsafe {
+            std::ptr::copy_nonoverlapping(
+                bytes.as_ptr(),
+                std::ptr::from_mut(net.as_mut()).cast::<u8>(),
+                size_of::<Self>(),
+            );
+        }
+        let net = Box::leak(net);
+        loaded.push((path, net));
+        drop(loaded);
+        Ok(net)
+    }
+
+    /// The input feature for `piece` on `sq`, from `pov`'s point of view.
+    const fn feature(pov: Colour, piece: Piece, sq: Square) -> usize {
+        let colour = (piece.colour() as usize ^ pov as usize) * 384;
+        colour + piece.piece_type().index() * 64 + sq.relative_to(pov).index()
+    }
+
+    /// The accumulator for `pov`, computed from scratch.
+    fn accumulate(&self, board: &Board, pov: Colour) -> [i16; HIDDEN] {
+        let mut acc = self.feature_bias;
+        for sq in board.occupied() {
+            let Some(piece) = board.mailbox[sq] else {
+                continue;
+            };
+            let weights = &self.feature_weights[Self::feature(pov, piece, sq)];
+            for (a, &w) in acc.iter_mut().zip(weights) {
+                *a = a.wrapping_add(w);
+            }
+        }
+        acc
+    }
+
+    /// The evaluation of `board`, in centipawns, from the side to move's point of view.
+    pub fn evaluate(&self, board: &Board) -> i32 {
+        #![allow(clippy::cast_possible_truncation)]
+        let us = self.accumulate(board, board.turn());
+        let them = self.accumulate(board, !board.turn());
+        let (our_weights, their_weights) = self.output_weights.split_at(HIDDEN);
+
+        let screlu = |acc: &[i16; HIDDEN], weights: &[i16]| -> i64 {
+            acc.iter()
+                .zip(weights)
+                .map(|(&a, &w)| {
+                    let a = i32::from(a).clamp(0, QA);
+                    i64::from(a * i32::from(w)) * i64::from(a)
+                })
+                .sum()
+        };
+
+        let output = screlu(&us, our_weights) + screlu(&them, their_weights);
+        let output = output / i64::from(QA) + i64::from(self.output_bias);
+        (output * SCALE / i64::from(QA * QB)) as i32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::XorShiftState;
+
+    #[test]
+    fn colour_symmetric() {
+        #![allow(clippy::cast_possible_truncation)]
+        // SAFETY: All bitpatterns of SmallNet are valid.
+        let mut net: Box<SmallNet> = unsafe { Box::new_zeroed().assume_init() };
+        let mut rng = XorShiftState::new();
+        for w in net.feature_weights.as_flattened_mut() {
+            *w = rng.next_below(128) as i16 - 32;
+        }
+        for w in &mut net.output_weights {
+            *w = rng.next_below(128) as i16 - 64;
+        }
+        net.output_bias = 100;
+
+        let white =
+            Board::from_fen("rnbqkbnr/pppp1ppp/8/4p3/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").unwrap();
+        let black =
+            Board::from_fen("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1").unwrap();
+        assert_ne!(net.evaluate(&white), 0);
+        assert_eq!(net.evaluate(&white), net.evaluate(&black));
+    }
+
+    #[test]
+    fn loads_each_file_once() {
+        let dir = std::env::temp_dir().join(format!("viridithas-small-net-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("small.bin");
+        std::fs::write(&path, vec![0; size_of::<SmallNet>()]).unwrap();
+
+        let first = SmallNet::load(&path).unwrap();
+        let again = SmallNet::load(&dir.join(".").join("small.bin")).unwrap();
+        assert!(std::ptr::eq(first, again));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -3,19 +3,26 @@
 use std::{
     fs::File,
     io::{BufRead, BufReader},
+    time::Instant,
 };
 
 use anyhow::{Context, bail};
 
-use crate::chess::{
-    board::{Board, Rules, movegen::MoveList},
-    fen::Fen,
+use crate::{
+    chess::{
+        board::{Board, Rules, movegen::MoveList},
+        fen::Fen,
+    },
+    cli::PerftSuite,
 };
 
 #[cfg(test)]
 use crate::threadlocal::ThreadData;
 
-pub fn perft(pos: &mut Board, depth: usize) -> u64 {
+const CLASSICAL_SUITE: &str = "assets/epds/perftsuite.epd";
+const FRC_SUITE: &str = "assets/epds/frcperftsuite.epd";
+
+pub fn perft(pos: &Board, depth: usize) -> u64 {
     #[cfg(debug_assertions)]
     pos.check_validity();
 
@@ -29,21 +36,44 @@ pub fn perft(pos: &mut Board, depth: usize) -> u64 {
     let mut count = 0;
 
     if depth == 1 {
-        return ml.iter_moves().filter(|&m| pos.is_legal(*m)).count() as u64;
+        return ml.len() as u64;
     }
 
     for &m in ml.iter_moves() {
-        if !pos.is_legal(m) {
-            continue;
-        }
-        pos.make_move_simple(m);
-        count += perft(pos, depth - 1);
-        pos.unmake_move_base();
+        count += perft(&pos.make_move_new(m), depth - 1);
     }
 
     count
 }
 
+/// Run perft to `depth`, printing the node count below each legal move ("divide"),
+/// then the total along with timing. Comparing this against another move generator
+/// narrows a discrepancy down to the move that causes it.
+pub fn divide(pos: &Board, depth: usize) -> u64 {
+    #![allow(clippy::cast_precision_loss)]
+    let start_time = Instant::now();
+    let nodes = if depth == 0 {
+        1
+    } else {
+        let mut ml = MoveList::new();
+        pos.generate_moves(&mut ml);
+        let mut nodes = 0;
+        for &m in ml.iter_moves() {
+            let arm_nodes = perft(&pos.make_move_new(m), depth - 1);
+            nodes += arm_nodes;
+            println!("{}: {arm_nodes}", m.display(pos.rules()));
+        }
+        nodes
+    };
+    let elapsed = start_time.elapsed();
+    println!(
+        "info depth {depth} nodes {nodes} time {elapsed} nps {nps:.0}",
+        elapsed = elapsed.as_millis(),
+        nps = nodes as f64 / elapsed.as_secs_f64()
+    );
+    nodes
+}
+
 #[cfg(test)]
 pub fn nnue_perft(t: &mut ThreadData, depth: usize) -> u64 {
     #[cfg(debug_assertions)]
@@ -60,13 +90,10 @@ pub fn nnue_perft(t: &mut ThreadData, depth: usize) -> u64 {
     let mut count = 0;
 
     if depth == 1 {
-        return ml.iter_moves().filter(|&m| t.board.is_legal(*m)).count() as u64;
+        return ml.len() as u64;
     }
 
     for &m in ml.iter_moves() {
-        if !t.board.is_legal(m) {
-            continue;
-        }
         t.board.make_move_nnue(m, &mut t.nnue);
         count += nnue_perft(t, depth - 1);
         t.board.unmake_move_nnue(&mut t.nnue);
@@ -85,7 +112,7 @@ pub fn movepicker_perft(t: &mut ThreadData, depth: usize) -> u64 {
         return 1;
     }
 
-    let mut ml = crate::movepicker::MovePicker::new(None, None, 0);
+    let mut ml = crate::movepicker::MovePicker::new(None, None, None, 0);
 
     let mut count = 0;
     while let Some(m) = ml.next(t) {
@@ -100,68 +127,63 @@ pub fn movepicker_perft(t: &mut ThreadData, depth: usize) -> u64 {
     count
 }
 
-pub fn gamut() -> anyhow::Result<()> {
+/// Check one line of a perft EPD suite, of the form `<fen> ;D1 <nodes> ;D2 <nodes> ...`,
+/// stopping at the first depth whose expected node count exceeds `nodes_limit`.
+fn check_suite_line(pos: &mut Board, line: &str, nodes_limit: u64) -> anyhow::Result<()> {
+    let mut parts = line.split(';');
+    let fen_str = parts
+        .next()
+        .with_context(|| "Failed to find fen in line.")?
+        .trim();
+    let fen = Fen::parse_relaxed(fen_str)?;
+    pos.set_from_fen(&fen);
+    for depth_part in parts {
+        let depth_part = depth_part.trim();
+        let (d, nodes) = depth_part
+            .split_once(' ')
+            .with_context(|| format!("Malformed depth entry \"{depth_part}\""))?;
+        let d = d
+            .strip_prefix('D')
+            .and_then(|d| d.parse::<usize>().ok())
+            .with_context(|| format!("Malformed depth \"{d}\""))?;
+        let nodes = nodes.parse::<u64>()?;
+        if nodes > nodes_limit {
+            println!("Skipping...");
+            break;
+        }
+        let perft_nodes = perft(pos, d);
+        if perft_nodes == nodes {
+            println!("PASS: fen {fen_str}, depth {d}");
+        } else {
+            bail!("FAIL: fen {fen_str}, depth {d}: expected {nodes}, got {perft_nodes}");
+        }
+    }
+    Ok(())
+}
+
+/// Run every position in the perft EPD suite at `path` under `rules`.
+fn run_suite(path: &str, rules: Rules, nodes_limit: u64) -> anyhow::Result<()> {
+    println!("running perft on {path}");
+    let f = File::open(path).with_context(|| format!("Failed to open {path}"))?;
+    let mut pos = Board::empty(rules);
+    for line in BufReader::new(f).lines() {
+        check_suite_line(&mut pos, &line?, nodes_limit)?;
+    }
+    Ok(())
+}
+
+/// Run the perft suites selected by `suite`. The Chess960 suite includes
+/// double Chess960 positions, with castling rights in Shredder-FEN notation.
+pub fn gamut(suite: PerftSuite) -> anyhow::Result<()> {
     #[cfg(debug_assertions)]
     const NODES_LIMIT: u64 = 60_000;
     #[cfg(not(debug_assertions))]
     const NODES_LIMIT: u64 = 60_000_000;
-    // open perftsuite.epd
-    println!("running perft on perftsuite.epd");
-    let f = File::open("assets/epds/perftsuite.epd")
-        .with_context(|| "Failed to open assets/epds/perftsuite.epd")?;
-    let mut pos = Board::empty(Rules::Classical);
-    for line in BufReader::new(f).lines() {
-        let line = line?;
-        let mut parts = line.split(';');
-        let fen_str = parts
-            .next()
-            .with_context(|| "Failed to find fen in line.")?
-            .trim();
-        let fen = Fen::parse_relaxed(fen_str)?;
-        pos.set_from_fen(&fen);
-        for depth_part in parts {
-            let depth_part = depth_part.trim();
-            let (d, nodes) = depth_part.split_once(' ').unwrap();
-            let d = d.chars().nth(1).unwrap().to_digit(10).unwrap();
-            let nodes = nodes.parse::<u64>().unwrap();
-            if nodes > NODES_LIMIT {
-                println!("Skipping...");
-                break;
-            }
-            let perft_nodes = perft(&mut pos, d as usize);
-            if perft_nodes == nodes {
-                println!("PASS: fen {fen_str}, depth {d}");
-            } else {
-                bail!("FAIL: fen {fen_str}, depth {d}: expected {nodes}, got {perft_nodes}");
-            }
-        }
+    if matches!(suite, PerftSuite::Classical | PerftSuite::All) {
+        run_suite(CLASSICAL_SUITE, Rules::Classical, NODES_LIMIT)?;
     }
-    // open frcperftsuite.epd
-    println!("running perft on frcperftsuite.epd");
-    let f = File::open("assets/epds/frcperftsuite.epd").unwrap();
-    let mut pos = Board::empty(Rules::Chess960);
-    for line in BufReader::new(f).lines() {
-        let line = line.unwrap();
-        let mut parts = line.split(';');
-        let fen_str = parts.next().unwrap().trim();
-        let fen = Fen::parse_relaxed(fen_str).unwrap();
-        pos.set_from_fen(&fen);
-        for depth_part in parts {
-            let depth_part = depth_part.trim();
-            let (d, nodes) = depth_part.split_once(' ').unwrap();
-            let d = d.chars().nth(1).unwrap().to_digit(10).unwrap();
-            let nodes = nodes.parse::<u64>().unwrap();
-            if nodes > NODES_LIMIT {
-                println!("Skipping...");
-                break;
-            }
-            let perft_nodes = perft(&mut pos, d as usize);
-            if perft_nodes == nodes {
-                println!("PASS: fen {fen_str}, depth {d}");
-            } else {
-                bail!("FAIL: fen {fen_str}, depth {d}: expected {nodes}, got {perft_nodes}");
-            }
-        }
+    if matches!(suite, PerftSuite::Frc | PerftSuite::All) {
+        run_suite(FRC_SUITE, Rules::Chess960, NODES_LIMIT)?;
     }
     Ok(())
 }
@@ -186,8 +208,8 @@ mod tests {
         const TEST_FEN: &str =
             "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
 
-        let mut pos = Board::from_fen(TEST_FEN).unwrap();
-        assert_eq!(perft(&mut pos, 1), 48, "got {}", {
+        let pos = Board::from_fen(TEST_FEN).unwrap();
+        assert_eq!(perft(&pos, 1), 48, "got {}", {
             pos.legal_moves()
                 .into_iter()
                 .map(|m| m.display(pos.rules()).to_string())
@@ -195,27 +217,27 @@ mod tests {
                 .join(", ")
         });
         #[cfg(not(miri))]
-        assert_eq!(perft(&mut pos, 2), 2_039);
-        // assert_eq!(perft(&mut pos, 3), 97_862);
-        // assert_eq!(perft(&mut pos, 4), 4_085_603);
+        assert_eq!(perft(&pos, 2), 2_039);
+        // assert_eq!(perft(&pos, 3), 97_862);
+        // assert_eq!(perft(&pos, 4), 4_085_603);
     }
 
     #[test]
     fn perft_start_position() {
         use super::*;
 
-        let mut pos = Board::startpos();
-        assert_eq!(perft(&mut pos, 1), 20, "got {}", {
+        let pos = Board::startpos();
+        assert_eq!(perft(&pos, 1), 20, "got {}", {
             pos.legal_moves()
                 .into_iter()
                 .map(|m| m.display(pos.rules()).to_string())
                 .collect::<Vec<_>>()
                 .join(", ")
         });
-        assert_eq!(perft(&mut pos, 2), 400);
+        assert_eq!(perft(&pos, 2), 400);
         #[cfg(not(miri))]
-        assert_eq!(perft(&mut pos, 3), 8_902);
-        // assert_eq!(perft(&mut pos, 4), 197_281);
+        assert_eq!(perft(&pos, 3), 8_902);
+        // assert_eq!(perft(&pos, 4), 197_281);
     }
 
     #[test]
@@ -251,7 +273,7 @@ mod tests {
         });
         assert_eq!(nnue_perft(&mut t, 2), 400);
         assert_eq!(nnue_perft(&mut t, 3), 8_902);
-        // assert_eq!(nnue_perft(&mut pos, &mut t, 4), 197_281);
+        // assert_eq!(nnue_perft(&pos, &mut t, 4), 197_281);
     }
 
     #[test]
@@ -408,8 +430,8 @@ mod tests {
     fn perft_krk() {
         use super::*;
 
-        let mut pos = Board::from_fen("8/8/8/8/8/8/1k6/R2K4 b - - 1 1").unwrap();
-        assert_eq!(perft(&mut pos, 1), 3, "got {}", {
+        let pos = Board::from_fen("8/8/8/8/8/8/1k6/R2K4 b - - 1 1").unwrap();
+        assert_eq!(perft(&pos, 1), 3, "got {}", {
             pos.legal_moves()
                 .into_iter()
                 .map(|m| m.display(pos.rules()).to_string())
@@ -418,6 +440,19 @@ mod tests {
         });
     }
 
+    #[test]
+    fn frc_suite_shallow() {
+        use super::*;
+
+        // a sample of the suite, to shallow depth, so that the 960 castling
+        // code is checked on every test run without taking too long.
+        let suite = std::fs::read_to_string(FRC_SUITE).unwrap();
+        let mut pos = Board::empty(Rules::Chess960);
+        for line in suite.lines().step_by(16) {
+            check_suite_line(&mut pos, line, 1_000).unwrap();
+        }
+    }
+
     #[test]
     fn simple_move_undoability() {
         use super::*;
@@ -457,4 +492,23 @@ mod tests {
         println!("{bb_returned}", bb_returned = pos.state.bbs);
         assert_eq!(pos.state.keys.zobrist, hashkey_before);
     }
+
+    #[test]
+    fn copy_make_matches_make() {
+        use super::*;
+
+        let mut pos =
+            Board::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1")
+                .unwrap();
+        for m in pos.legal_moves() {
+            let next = pos.make_move_new(m);
+            pos.make_move_simple(m);
+            assert_eq!(next.state, pos.state);
+            assert_eq!(next.mailbox, pos.mailbox);
+            assert_eq!(next.turn(), pos.turn());
+            assert_eq!(next.ply(), pos.ply());
+            assert!(next.history().is_empty());
+            pos.unmake_move_base();
+        }
+    }
 }
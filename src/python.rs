@@ -0,0 +1,280 @@
+//! Python bindings, so that the engine can be driven from a notebook rather than over UCI.
+//!
+//! Build the extension module with `maturin develop --release`, which picks up the
+//! features listed in `pyproject.toml`.
+
+use pyo3::{
+    exceptions::{PyIndexError, PyValueError},
+    prelude::*,
+};
+
+use crate::{
+    chess::{
+        board::{Board, CastlingNotation, GameOutcome},
+        piece::Colour,
+    },
+    nnue::network::{NNUEParams, NNUEState},
+    search::{Score, Search, SearchLimit},
+    uci::fmt::NORMALISE_TO_PAWN_VALUE,
+};
+
+/// A chess position, along with the moves that led to it.
+#[pyclass(name = "Board", module = "viridithas")]
+struct PyBoard {
+    board: Board,
+}
+
+#[pymethods]
+impl PyBoard {
+    /// Set up the position given by `fen`, or the starting position if it's omitted.
+    #[new]
+    #[pyo3(signature = (fen = None))]
+    fn new(fen: Option<&str>) -> PyResult<Self> {
+        let board = match fen {
+            Some(fen) => Board::from_fen(fen).map_err(value_error)?,
+            None => Board::startpos(),
+        };
+        Ok(Self { board })
+    }
+
+    /// The FEN of the position, in Shredder-FEN for Chess960 positions.
+    fn fen(&self) -> String {
+        self.board.to_string()
+    }
+
+    /// The FEN of the position, with castling rights in X-FEN.
+    fn xfen(&self) -> String {
+        self.board.fen(CastlingNotation::XFen)
+    }
+
+    /// The side to move, as "w" or "b".
+    #[getter]
+    fn turn(&self) -> &'static str {
+        match self.board.turn() {
+            Colour::White => "w",
+            Colour::Black => "b",
+        }
+    }
+
+    /// The legal moves in the position, in UCI notation.
+    fn legal_moves(&self) -> Vec<String> {
+        let rules = self.board.rules();
+        self.board
+            .legal_moves()
+            .into_iter()
+            .map(|m| m.display(rules).to_string())
+            .collect()
+    }
+
+    /// Make a move given in UCI notation.
+    fn push_uci(&mut self, uci: &str) -> PyResult<()> {
+        let m = self.board.parse_uci(uci).map_err(value_error)?;
+        self.board.make_move_simple(m);
+        Ok(())
+    }
+
+    /// Make a move given in SAN, returning it in UCI notation.
+    fn push_san(&mut self, san: &str) -> PyResult<String> {
+        let m = self.board.parse_san(san).map_err(value_error)?;
+        let uci = m.display(self.board.rules()).to_string();
+        self.board.make_move_simple(m);
+        Ok(uci)
+    }
+
+    /// Take back the last move.
+    fn pop(&mut self) -> PyResult<()> {
+        if self.board.history().is_empty() {
+            return Err(PyIndexError::new_err("no moves to take back"));
+        }
+        self.board.unmake_move_base();
+        Ok(())
+    }
+
+    /// The SAN of a legal move given in UCI notation.
+    fn san(&self, uci: &str) -> PyResult<String> {
+        let m = self.board.parse_uci(uci).map_err(value_error)?;
+        self.board
+            .san(m)
+            .map(|san| san.to_string())
+            .ok_or_else(|| PyValueError::new_err(format!("illegal move {uci}")))
+    }
+
+    /// Whether the side to move is in check.
+    fn in_check(&self) -> bool {
+        self.board.in_check()
+    }
+
+    /// The result of the game as "1-0", "0-1", or "1/2-1/2", if it's over, or `None`.
+    fn outcome(&self) -> Option<&'static str> {
+        self.board.outcome().map(|outcome| match outcome {
+            GameOutcome::WhiteWin(_) => "1-0",
+            GameOutcome::BlackWin(_) => "0-1",
+            GameOutcome::Draw(_) => "1/2-1/2",
+        })
+    }
+
+    /// The raw network evaluation of the position in centipawns, from the side to move's
+    /// point of view.
+    fn evaluate(&self) -> PyResult<i32> {
+        let nnue_params = NNUEParams::decompress_and_alloc().map_err(value_error)?;
+        let nnue = NNUEState::new(&self.board, nnue_params);
+        Ok(nnue.evaluate(nnue_params, &self.board) * 100 / NORMALISE_TO_PAWN_VALUE)
+    }
+
+    fn __str__(&self) -> String {
+        self.board.to_string()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Board('{}')", self.board)
+    }
+}
+
+/// The outcome of a search. Scores are from the side to move's point of view.
+#[pyclass(name = "SearchResult", module = "viridithas", get_all)]
+struct PySearchResult {
+    /// The best move, in UCI notation.
+    best_move: String,
+    /// The score in centipawns, or `None` if the search found a forced mate.
+    score: Option<i32>,
+    /// The number of moves to a forced mate, negative if the side to move is being mated,
+    /// or `None` if no mate was found.
+    mate: Option<i32>,
+    /// The principal variation, in UCI notation.
+    pv: Vec<String>,
+    /// The depth of the last completed iteration.
+    depth: usize,
+    /// The nodes searched, across all threads.
+    nodes: u64,
+}
+
+#[pymethods]
+impl PySearchResult {
+    fn __repr__(&self) -> String {
+        let score = match (self.score, self.mate) {
+            (_, Some(mate)) => format!("mate={mate}"),
+            (Some(cp), None) => format!("score={cp}"),
+            (None, None) => String::new(),
+        };
+        format!(
+            "SearchResult(best_move='{}', {score}, depth={}, nodes={})",
+            self.best_move, self.depth, self.nodes
+        )
+    }
+}
+
+/// A search engine, which keeps its threads, transposition table, and network between
+/// searches.
+#[pyclass(name = "Engine", module = "viridithas")]
+struct PyEngine {
+    search: Search,
+}
+
+#[pymethods]
+impl PyEngine {
+    /// Start `threads` search threads, sharing a transposition table of `hash` megabytes.
+    #[new]
+    #[pyo3(signature = (*, threads = 1, hash = 16))]
+    fn new(threads: usize, hash: usize) -> PyResult<Self> {
+        let search = Search::new(threads, hash).map_err(value_error)?;
+        Ok(Self { search })
+    }
+
+    /// Search `board` until exactly one of `depth`, `nodes`, or `movetime` (in milliseconds)
+    /// is reached.
+    ///
+    /// The interpreter lock is released while the search runs.
+    #[pyo3(signature = (board, *, depth = None, nodes = None, movetime = None))]
+    fn search(
+        &mut self,
+        py: Python<'_>,
+        board: &PyBoard,
+        depth: Option<usize>,
+        nodes: Option<u64>,
+        movetime: Option<u64>,
+    ) -> PyResult<PySearchResult> {
+        let limit = search_limit(depth, nodes, movetime)?;
+        let board = board.board.clone();
+        if board.legal_moves().is_empty() {
+            return Err(PyValueError::new_err("the position has no legal moves"));
+        }
+        let result = py
+            .detach(|| self.search.run(&board, limit, &()))
+            .map_err(value_error)?;
+        let best_move = result
+            .best_move
+            .ok_or_else(|| PyValueError::new_err("no move was found"))?;
+        let rules = board.rules();
+        let (score, mate) = match result.score {
+            Score::Centipawns(cp) => (Some(cp), None),
+            Score::Mate(moves) => (None, Some(moves)),
+        };
+        Ok(PySearchResult {
+            best_move: best_move.display(rules).to_string(),
+            score,
+            mate,
+            pv: result
+                .pv
+                .iter()
+                .map(|m| m.display(rules).to_string())
+                .collect(),
+            depth: result.depth,
+            nodes: result.nodes,
+        })
+    }
+
+    /// Forget everything learned from earlier searches, as before a new game.
+    fn clear(&mut self) {
+        self.search.clear();
+    }
+}
+
+/// Search the position given by `fen` with a new [`Engine`], until exactly one of `depth`,
+/// `nodes`, or `movetime` (in milliseconds) is reached.
+///
+/// The interpreter lock is released while the search runs.
+#[pyfunction]
+#[pyo3(signature = (fen, *, depth = None, nodes = None, movetime = None, threads = 1, hash = 16))]
+fn search(
+    py: Python<'_>,
+    fen: &str,
+    depth: Option<usize>,
+    nodes: Option<u64>,
+    movetime: Option<u64>,
+    threads: usize,
+    hash: usize,
+) -> PyResult<PySearchResult> {
+    let board = PyBoard::new(Some(fen))?;
+    let mut engine = PyEngine::new(threads, hash)?;
+    engine.search(py, &board, depth, nodes, movetime)
+}
+
+fn search_limit(
+    depth: Option<usize>,
+    nodes: Option<u64>,
+    movetime: Option<u64>,
+) -> PyResult<SearchLimit> {
+    match (depth, nodes, movetime) {
+        (Some(depth), None, None) => Ok(SearchLimit::Depth(depth)),
+        (None, Some(nodes), None) => Ok(SearchLimit::Nodes(nodes)),
+        (None, None, Some(movetime)) => Ok(SearchLimit::Time(movetime)),
+        _ => Err(PyValueError::new_err(
+            "give exactly one of depth, nodes, or movetime",
+        )),
+    }
+}
+
+fn value_error(e: impl std::fmt::Display) -> PyErr {
+    PyValueError::new_err(e.to_string())
+}
+
+/// The `viridithas` Python module.
+#[pymodule]
+fn viridithas(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyBoard>()?;
+    m.add_class::<PyEngine>()?;
+    m.add_class::<PySearchResult>()?;
+    m.add_function(wrap_pyfunction!(search, m)?)?;
+    m.add("__version__", env!("CARGO_PKG_VERSION"))?;
+    Ok(())
+}
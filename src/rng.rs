@@ -8,6 +8,26 @@ impl XorShiftState {
         Self { state: SEED }
     }
 
+    /// Creates a generator from a 64-bit seed, so that a run can be reproduced.
+    /// Distinct seeds give unrelated sequences.
+    pub const fn with_seed(seed: u64) -> Self {
+        // spread the seed over the full state with splitmix64,
+        // so that nearby seeds don't produce correlated streams.
+        const fn splitmix64(mut z: u64) -> u64 {
+            z = z.wrapping_add(0x9E37_79B9_7F4A_7C15);
+            z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+            z ^ (z >> 31)
+        }
+        let lo = splitmix64(seed);
+        let hi = splitmix64(lo);
+        let state = ((hi as u128) << 64) | lo as u128;
+        // xorshift is stuck at zero forever, so avoid that state.
+        Self {
+            state: if state == 0 { SEED } else { state },
+        }
+    }
+
     /// Generates the next random number in the sequence, consuming self
     /// This is done to allow for const evaluation.
     pub const fn next_self(mut self) -> (u64, Self) {
@@ -34,6 +54,27 @@ impl XorShiftState {
         r ^ (x >> 64) as u64 // add in the high bits.
     }
 
+    /// Generates a random number in the range `0..bound`, which must be non-empty.
+    pub fn next_below(&mut self, bound: u64) -> u64 {
+        #![allow(clippy::cast_possible_truncation)]
+        debug_assert!(bound > 0, "next_below called with an empty range");
+        // Lemire's multiply-shift: the high half of the product is the result,
+        // and rejecting the few low halves below 2^64 mod bound removes the bias.
+        let mut m = u128::from(self.next()) * u128::from(bound);
+        if (m as u64) < bound {
+            let threshold = bound.wrapping_neg() % bound;
+            while (m as u64) < threshold {
+                m = u128::from(self.next()) * u128::from(bound);
+            }
+        }
+        (m >> 64) as u64
+    }
+
+    /// Generates a random boolean.
+    pub fn next_bool(&mut self) -> bool {
+        self.next() & 1 != 0
+    }
+
     /// Generates a random number with only a few bits set.
     /// This will advance the generator by three steps.
     pub fn random_few_bits(&mut self) -> u64 {
@@ -1,9 +1,15 @@
+//! Alpha-beta search, with iterative deepening and Lazy SMP.
+
 #![allow(clippy::too_many_arguments)]
 
-pub mod parameters;
+mod driver;
+pub(crate) mod mcts;
+pub(crate) mod parameters;
 pub mod pv;
 
-use std::{sync::atomic::Ordering, thread};
+pub use self::driver::{IterationInfo, Score, Search, SearchCallbacks, SearchLimit, SearchResult};
+
+use std::{ops::ControlFlow, sync::atomic::Ordering, thread};
 
 use arrayvec::ArrayVec;
 
@@ -27,13 +33,14 @@ use crate::{
     historytable::history_bonus,
     lookups::HM_CLOCK_KEYS,
     movepicker::{MovePicker, Stage},
+    rng::XorShiftState,
     search::pv::PVariation,
     searchinfo::SearchInfo,
+    strength::StrengthLimit,
     tablebases::{self, probe::WDL},
     threadlocal::ThreadData,
     threadpool::{self, ScopeExt},
-    timemgmt::SearchLimit,
-    transpositiontable::{Bound, CacheResult},
+    transpositiontable::{Bound, CacheResult, CacheView},
     uci,
     util::{INFINITY, MAX_DEPTH, VALUE_NONE},
 };
@@ -63,6 +70,14 @@ const RFP_IMPROVING_MARGIN: i32 = 76;
 const NMP_IMPROVING_MARGIN: i32 = 132;
 const NMP_DEPTH_MUL: i32 = -8;
 const NMP_REDUCTION_EVAL_DIVISOR: i32 = 174;
+const NMP_BASE_REDUCTION: i32 = 4;
+const NMP_REDUCTION_DEPTH_DIVISOR: i32 = 3;
+const IIR_DEPTH: i32 = 8;
+/// The depth from which to reduce PV nodes without a cached move, or zero to never reduce them.
+const IIR_PV_DEPTH: i32 = 0;
+/// The depth from which to run internal iterative deepening, or zero to always use IIR.
+const IID_DEPTH: i32 = 0;
+const IID_REDUCTION: i32 = 4;
 const SEE_QUIET_MARGIN: i32 = -62;
 const SEE_TACTICAL_MARGIN: i32 = -28;
 const FUTILITY_COEFF_0: i32 = 86;
@@ -101,16 +116,19 @@ const MAIN_HISTORY: HistoryConfig = HistoryConfig::new(357, 226, 2241, 111, 561,
 const CONT1_HISTORY: HistoryConfig = HistoryConfig::new(287, 150, 3729, 270, 267, 1178);
 const CONT2_HISTORY: HistoryConfig = HistoryConfig::new(177, 178, 1596, 280, 130, 943);
 const CONT4_HISTORY: HistoryConfig = HistoryConfig::new(177, 185, 1630, 201, -32, 945);
+const CONT6_HISTORY: HistoryConfig = HistoryConfig::new(120, 120, 1100, 140, -32, 640);
 const PAWN_HISTORY: HistoryConfig = HistoryConfig::new(169, 162, 2208, 251, 188, 1281);
 const TACTICAL_HISTORY: HistoryConfig = HistoryConfig::new(104, 328, 1248, 29, 394, 1122);
 const MAIN_STAT_SCORE_MUL: i32 = 26;
 const CONT1_STAT_SCORE_MUL: i32 = 37;
 const CONT2_STAT_SCORE_MUL: i32 = 33;
 const CONT4_STAT_SCORE_MUL: i32 = 13;
+const CONT6_STAT_SCORE_MUL: i32 = 8;
 const TACT_STAT_SCORE_MUL: i32 = 43;
 const PAWN_CORRHIST_WEIGHT: i32 = 1890;
 const MAJOR_CORRHIST_WEIGHT: i32 = 1461;
 const MINOR_CORRHIST_WEIGHT: i32 = 1292;
+const THREAT_CORRHIST_WEIGHT: i32 = 1024;
 const NONPAWN_CORRHIST_WEIGHT: i32 = 1887;
 const CONTINUATION_12_CORRHIST_WEIGHT: i32 = 1942;
 const CONTINUATION_14_CORRHIST_WEIGHT: i32 = 1942;
@@ -121,12 +139,13 @@ const HINDSIGHT_RED_DEPTH: i32 = 2494;
 const HINDSIGHT_RED_EVAL: i32 = 128;
 const OPTIMISM_OFFSET: i32 = 196;
 const OPTIMISM_MATERIAL_BASE: i32 = 1869;
+const FIFTY_MOVE_DAMPING: i32 = 200;
 const EVAL_POLICY_UPDATE_MAX: i32 = 94;
 const PROBCUT_SEE_SCALE: i32 = 266;
 const PROBCUT_ADA_OFFSET: i32 = 50;
 const PROBCUT_ADA_DIV: i32 = 300;
 
-pub trait NodeType {
+pub(crate) trait NodeType {
     /// Whether this node is on the principal variation.
     const PV: bool;
     /// Whether this node is the root of the search tree.
@@ -165,11 +184,11 @@ impl NodeType for CheckForced {
     type Next = OffPV;
 }
 
-pub trait SmpThreadType {
+pub(crate) trait SmpThreadType {
     const MAIN_THREAD: bool;
 }
-pub struct MainThread;
-pub struct HelperThread;
+pub(crate) struct MainThread;
+pub(crate) struct HelperThread;
 impl SmpThreadType for MainThread {
     const MAIN_THREAD: bool = true;
 }
@@ -178,34 +197,58 @@ impl SmpThreadType for HelperThread {
 }
 
 /// Performs the root search. Returns the score of the position, from white's perspective, and the best move.
-#[allow(clippy::too_many_lines)]
 pub fn search_position(
     pool: &[threadpool::WorkerThread],
     thread_headers: &mut [Box<ThreadData>],
 ) -> (i32, Option<Move>) {
+    let (score, result) = search_root(pool, thread_headers);
+    (score, result.best_move)
+}
+
+/// Performs the root search. Returns the score of the position, from white's perspective,
+/// and the full result of the search.
+#[allow(clippy::too_many_lines)]
+fn search_root(
+    pool: &[threadpool::WorkerThread],
+    thread_headers: &mut [Box<ThreadData>],
+) -> (i32, SearchResult) {
     for t in &mut *thread_headers {
         t.board.zero_height();
         t.info.set_up_for_search();
         t.set_up_for_search();
     }
 
-    let legal_moves = thread_headers[0].board.legal_moves();
+    let mut legal_moves = thread_headers[0].board.legal_moves();
     if legal_moves.is_empty() {
-        eprintln!("info string warning search called on a position with no legal moves");
-        if thread_headers[0].board.in_check() {
-            println!("info depth 0 score mate 0");
-        } else {
-            println!("info depth 0 score cp 0");
+        let in_check = thread_headers[0].board.in_check();
+        if thread_headers[0].info.print_to_stdout {
+            eprintln!("info string warning search called on a position with no legal moves");
+            if in_check {
+                println!("info depth 0 score mate 0");
+            } else {
+                println!("info depth 0 score cp 0");
+            }
+            println!("bestmove (none)");
         }
-        println!("bestmove (none)");
-        return (0, None);
+        let score = if in_check {
+            Score::Mate(0)
+        } else {
+            Score::Centipawns(0)
+        };
+        return (
+            0,
+            search_result(&thread_headers[0], None, None, score, &[], 0),
+        );
     }
+    // with `go searchmoves`, only the listed moves are candidates.
+    legal_moves.retain(|&mut m| !thread_headers[0].root_move_excluded(m));
     if legal_moves.len() == 1 {
         thread_headers[0].info.clock.notify_one_legal_move();
     }
 
     // Probe the tablebases if we're in a TB position and in a game.
     if thread_headers[0].info.clock.is_dynamic()
+        && thread_headers[0].searchmoves.is_empty()
         && let Some((best_move, score)) = tablebases::probe::get_tablebase_move(
             &thread_headers[0].board,
             thread_headers[0].info.control,
@@ -229,7 +272,34 @@ pub fn search_position(
                 best_move.display(thread_headers[0].board.rules())
             );
         }
-        return (score, Some(best_move));
+        let result = search_result(
+            &thread_headers[0],
+            Some(best_move),
+            None,
+            Score::from_internal(score),
+            &[best_move],
+            1,
+        );
+        return (score, result);
+    }
+
+    // Otherwise, restrict the root to the moves that keep the tablebase result,
+    // so that the search can't throw away a win, or let one slip past the fifty-move rule.
+    if let Some(tb_moves) = tablebases::probe::get_root_moves_preserving_wdl(
+        &thread_headers[0].board,
+        thread_headers[0].info.control,
+    ) {
+        let tb_moves = tb_moves
+            .into_iter()
+            .filter(|&m| legal_moves.contains(&m))
+            .collect::<Vec<_>>();
+        // if none of the `searchmoves` keep the result, search them all anyway.
+        if !tb_moves.is_empty() {
+            legal_moves.retain(|m| tb_moves.contains(m));
+            for t in &mut *thread_headers {
+                t.tb_root_moves.clone_from(&tb_moves);
+            }
+        }
     }
 
     let global_stopped = thread_headers[0].info.stopped;
@@ -238,19 +308,32 @@ pub fn search_position(
         "global_stopped must be false"
     );
 
+    // tree search is single-threaded, so the helpers sit it out.
+    let use_mcts = thread_headers[0]
+        .info
+        .control
+        .use_mcts
+        .load(Ordering::Relaxed);
+
     // start search threads:
     let (t1, rest) = thread_headers.split_first_mut().unwrap();
     let (w1, rest_workers) = pool.split_first().unwrap();
+    let helpers = if use_mcts { 0 } else { rest.len() };
     thread::scope(|s| {
         let mut handles = Vec::with_capacity(pool.len());
         handles.push(s.spawn_into(
             || {
-                iterative_deepening::<MainThread>(t1);
+                if use_mcts {
+                    mcts::search(t1, &legal_moves);
+                } else {
+                    iterative_deepening::<MainThread>(t1);
+                }
+                t1.info.wait_for_ponder_end();
                 global_stopped.store(true, Ordering::SeqCst);
             },
             w1,
         ));
-        for (t, w) in rest.iter_mut().zip(rest_workers) {
+        for (t, w) in rest.iter_mut().zip(rest_workers).take(helpers) {
             handles.push(s.spawn_into(
                 || {
                     assert!(matches!(t.info.clock.limit(), SearchLimit::Infinite));
@@ -264,8 +347,24 @@ pub fn search_position(
         }
     });
 
-    let best_thread = select_best(thread_headers);
-    let pv = best_thread.pv();
+    // in multi-PV mode only the main thread searches the secondary lines, so report its results.
+    let best_thread = if thread_headers[0]
+        .info
+        .control
+        .multi_pv
+        .load(Ordering::Relaxed)
+        > 1
+        || thread_headers[0].info.strength.is_some()
+        || use_mcts
+    {
+        &thread_headers[0]
+    } else {
+        select_best(thread_headers)
+    };
+    let pv = thread_headers[0].info.strength.map_or_else(
+        || best_thread.pv(),
+        |strength| weakened_line(best_thread, strength),
+    );
     let best_move = pv
         .moves
         .first()
@@ -281,8 +380,13 @@ pub fn search_position(
         true,
     );
 
+    let ponder_move = pv
+        .moves
+        .get(1)
+        .copied()
+        .or_else(|| ponder_move_from_cache(&thread_headers[0], best_move));
     if thread_headers[0].info.print_to_stdout {
-        let maybe_ponder = pv.moves.get(1).map_or_else(String::new, |ponder_move| {
+        let maybe_ponder = ponder_move.map_or_else(String::new, |ponder_move| {
             format!(
                 " ponder {}",
                 ponder_move.display(thread_headers[0].board.rules())
@@ -294,7 +398,8 @@ pub fn search_position(
         );
         #[cfg(feature = "stats")]
         {
-            thread_headers[0].info.print_stats();
+            crate::searchinfo::SearchStats::sum(thread_headers.iter().map(|t| &t.info.stats))
+                .print();
             #[allow(clippy::cast_precision_loss)]
             let branching_factor = (thread_headers[0].info.nodes.get_global() as f64)
                 .powf(1.0 / thread_headers[0].completed as f64);
@@ -312,16 +417,46 @@ pub fn search_position(
         .stopped
         .store(false, Ordering::Relaxed);
 
+    let result = search_result(
+        &thread_headers[0],
+        Some(best_move),
+        ponder_move,
+        Score::from_internal(best_thread.score_scratch),
+        &pv.moves,
+        best_thread.completed,
+    );
     (
         if thread_headers[0].board.turn() == Colour::White {
             best_thread.score_scratch
         } else {
             -best_thread.score_scratch
         },
-        Some(best_move),
+        result,
     )
 }
 
+/// Gather up the result of a search, given its outcome and the thread that found it.
+fn search_result(
+    t: &ThreadData,
+    best_move: Option<Move>,
+    ponder_move: Option<Move>,
+    score: Score,
+    pv: &[Move],
+    depth: usize,
+) -> SearchResult {
+    SearchResult {
+        best_move,
+        ponder_move,
+        score,
+        pv: pv.to_vec(),
+        depth,
+        seldepth: t.info.seldepth.try_into().unwrap_or_default(),
+        nodes: t.info.nodes.get_global(),
+        tbhits: t.info.tbhits.get_global(),
+        elapsed: t.info.clock.elapsed(),
+    }
+}
+
 /// Performs the iterative deepening search.
 /// Returns the score of the position, from the side to move's perspective, and the best move.
 /// For Lazy SMP, the main thread calls this function with `T0 = true`, and the helper threads with `T0 = false`.
@@ -337,6 +472,9 @@ fn iterative_deepening<ThTy: SmpThreadType>(t: &mut ThreadData) {
         t.iteration = iteration;
         t.root_depth = i32::try_from(iteration).unwrap();
         t.optimism = [0; 2];
+        // seldepth covers the whole iteration, including aspiration re-searches and
+        // the searches for secondary lines, so it's only reset between iterations.
+        t.info.seldepth = 0;
 
         let min_depth = (t.root_depth / 2).max(1);
 
@@ -369,9 +507,12 @@ fn iterative_deepening<ThTy: SmpThreadType>(t: &mut ThreadData) {
             if t.score_scratch <= alpha {
                 if ThTy::MAIN_THREAD {
                     readout_info(t, &t.info, Bound::Upper, t.info.nodes.get_global(), false);
-                    t.info
-                        .clock
-                        .report_aspiration_fail(t.root_depth, Bound::Upper, &t.info.conf);
+                    t.info.clock.report_aspiration_fail(
+                        t.root_depth,
+                        Bound::Upper,
+                        t.info.nodes.get_global(),
+                        &t.info.conf,
+                    );
                 }
                 beta = i32::midpoint(alpha, beta);
                 alpha = (t.score_scratch - delta).max(-INFINITY);
@@ -383,9 +524,12 @@ fn iterative_deepening<ThTy: SmpThreadType>(t: &mut ThreadData) {
                 t.update_best_line();
                 if ThTy::MAIN_THREAD {
                     readout_info(t, &t.info, Bound::Lower, t.info.nodes.get_global(), false);
-                    t.info
-                        .clock
-                        .report_aspiration_fail(t.root_depth, Bound::Lower, &t.info.conf);
+                    t.info.clock.report_aspiration_fail(
+                        t.root_depth,
+                        Bound::Lower,
+                        t.info.nodes.get_global(),
+                        &t.info.conf,
+                    );
                 }
                 beta = (t.score_scratch + delta).min(INFINITY);
                 reduction += 1;
@@ -404,6 +548,20 @@ fn iterative_deepening<ThTy: SmpThreadType>(t: &mut ThreadData) {
         }
 
         // if we've made it here, it means we got an exact score.
+        if ThTy::MAIN_THREAD {
+            let lines = t
+                .info
+                .control
+                .multi_pv
+                .load(Ordering::Relaxed)
+                .max(t.info.strength.map_or(1, |_| StrengthLimit::CANDIDATES));
+            // a stop while the secondary lines are searched still leaves this
+            // iteration's principal line complete, so it's reported once the search ends.
+            if lines > 1 && search_extra_lines(t, lines - 1).is_break() {
+                break 'deepening;
+            }
+        }
+
         let best_move = t.pv_scratch[0]
             .moves
             .first()
@@ -461,12 +619,11 @@ fn iterative_deepening<ThTy: SmpThreadType>(t: &mut ThreadData) {
 
         if ThTy::MAIN_THREAD {
             // consider stopping early if we've neatly completed a depth,
-            // or if we were told to find a mate and we found one,
+            // or if we were told to find a mate and we've proven one short enough,
             // or if we're on the clock and we've solved a mate.
             if t.info.clock.is_past_opt_time(t.info.nodes.get_global())
-                || (iteration > 10
-                    && (t.info.clock.solved_breaker(t.score_scratch)
-                        || t.info.clock.mate_found_breaker(t.score_scratch)))
+                || t.info.clock.solved_breaker(t.score_scratch)
+                || (iteration > 10 && t.info.clock.mate_found_breaker(t.score_scratch))
             {
                 t.info.stopped.store(true, Ordering::SeqCst);
                 break 'deepening;
@@ -475,8 +632,108 @@ fn iterative_deepening<ThTy: SmpThreadType>(t: &mut ThreadData) {
     }
 }
 
+/// Pick the line to play under a strength limit, from the principal line and
+/// the secondary lines searched alongside it.
+fn weakened_line<'a>(t: &'a ThreadData, strength: StrengthLimit) -> &'a PVariation {
+    let lines = std::iter::once((t.score(), t.pv()))
+        .chain(t.multi_pv_lines.iter().map(|(score, pv)| (*score, pv)))
+        .filter(|(_, pv)| !pv.moves.is_empty())
+        .collect::<Vec<_>>();
+    let scores = lines.iter().map(|&(score, _)| score).collect::<Vec<_>>();
+    #[allow(clippy::cast_possible_truncation)]
+    let seed = t.info.nodes.get_global() ^ t.info.clock.elapsed().as_nanos() as u64;
+    let choice = strength.choose(&scores, &mut XorShiftState::with_seed(seed));
+    lines.get(choice).map_or_else(|| t.pv(), |&(_, pv)| pv)
+}
+
+/// Search the lines after the principal one for multi-PV analysis, by repeating the
+/// root search with the moves of the lines already found excluded, and store them in
+/// `multi_pv_lines`.
+///
+/// If the search is stopped before the lines are complete, this breaks, keeping the lines
+/// that were finished. Either way, the principal line is left as it was.
+fn search_extra_lines(t: &mut ThreadData, count: usize) -> ControlFlow<()> {
+    let (best_score, best_line) = (t.score(), t.pv().clone());
+    t.root_excluded.clear();
+    t.root_excluded.extend(best_line.moves.first());
+    let root_moves = t
+        .board
+        .legal_moves()
+        .into_iter()
+        .filter(|&m| !t.root_move_excluded(m))
+        .count();
+    let count = count.min(root_moves);
+    let mut lines = Vec::with_capacity(count);
+    let mut flow = ControlFlow::Continue(());
+    for _ in 0..count {
+        let score = alpha_beta::<Root>(t, t.root_depth, -INFINITY, INFINITY, false);
+        if t.info.check_up() {
+            flow = ControlFlow::Break(());
+            break;
+        }
+        let Some(&m) = t.pv_scratch[0].moves.first() else {
+            break;
+        };
+        t.root_excluded.push(m);
+        lines.push((score, t.pv_scratch[0].clone()));
+    }
+    t.root_excluded.clear();
+    t.multi_pv_lines = lines;
+    // the rest of the iteration, and the result of the search, work from the principal line.
+    t.score_scratch = best_score;
+    t.pv_scratch[0] = best_line;
+    flow
+}
+
 fn dyn_max_depth(t: &ThreadData<'_>) -> usize {
-    t.info.clock.limit().depth().unwrap_or(MAX_DEPTH - 1)
+    let depth = t.info.clock.limit().depth().unwrap_or(MAX_DEPTH - 1);
+    t.info
+        .strength
+        .map_or(depth, |strength| depth.min(strength.depth_limit()))
+}
+
+/// Find a reply to ponder on when the principal variation is only one move long,
+/// by looking up the position after `best_move` in the transposition table.
+fn ponder_move_from_cache(t: &ThreadData, best_move: Move) -> Option<Move> {
+    let mut board = t.board.clone();
+    board.make_move_simple(best_move);
+    let key = board.state.keys.zobrist ^ HM_CLOCK_KEYS[board.fifty_move_counter() as usize];
+    let (m, _) = t.cache.probe_move(key)?;
+    m.filter(|&m| board.is_pseudo_legal(m) && board.is_legal(m))
+}
+
+/// Extend `pv` with moves from the transposition table until it is `depth` moves long,
+/// so that lines cut short by cache cutoffs are still reported in full.
+///
+/// Every move is checked for legality before it is played, and the walk stops as soon as
+/// it reaches a drawn position, as the cache can hold cycles. If `pv` itself can't be
+/// replayed from `board`, it is returned unchanged.
+fn extend_pv_from_cache(
+    board: &Board,
+    cache: CacheView,
+    pv: &PVariation,
+    depth: usize,
+) -> PVariation {
+    let mut line = pv.clone();
+    let mut board = board.clone();
+    for &m in &pv.moves {
+        if !(board.is_pseudo_legal(m) && board.is_legal(m)) {
+            return line;
+        }
+        board.make_move_simple(m);
+    }
+    while line.moves.len() < depth.min(MAX_DEPTH) && !board.is_draw() {
+        let key = board.state.keys.zobrist ^ HM_CLOCK_KEYS[board.fifty_move_counter() as usize];
+        let Some((Some(m), _)) = cache.probe_move(key) else {
+            break;
+        };
+        if !(board.is_pseudo_legal(m) && board.is_legal(m)) {
+            break;
+        }
+        board.make_move_simple(m);
+        line.moves.push(m);
+    }
+    line
 }
 
 /// Give a legal default move in the case where we don't have enough time to search.
@@ -486,16 +743,16 @@ fn default_move(t: &ThreadData) -> Move {
         .probe_move(t.board.state.keys.zobrist)
         .and_then(|e| e.0);
 
-    let mut mp = MovePicker::new(tt_move, t.killer_move_table[t.board.height()], 0);
+    let mut mp = MovePicker::new(tt_move, t.killer_move_table[t.board.height()], None, 0);
 
     std::iter::from_fn(|| mp.next(t))
-        .find(|&m| t.board.is_legal(m))
+        .find(|&m| t.board.is_legal(m) && !t.root_move_excluded(m))
         .expect("Board::default_move called on a position with no legal moves")
 }
 
 /// Perform a tactical resolution search, searching only captures and promotions.
 #[allow(clippy::too_many_lines, clippy::cognitive_complexity)]
-pub fn quiescence<NT: NodeType>(t: &mut ThreadData, mut alpha: i32, beta: i32) -> i32 {
+pub(crate) fn quiescence<NT: NodeType>(t: &mut ThreadData, mut alpha: i32, beta: i32) -> i32 {
     #[cfg(debug_assertions)]
     t.board.check_validity();
 
@@ -626,12 +883,14 @@ pub fn quiescence<NT: NodeType>(t: &mut ThreadData, mut alpha: i32, beta: i32) -
     let mut best_score = stand_pat;
 
     let mut moves_made = 0;
-    let mut move_picker = MovePicker::new(
-        cache_hit.and_then(|e| e.mov),
-        None,
-        t.info.conf.qs_see_bound,
-    );
-    move_picker.skip_quiets = !in_check;
+    let tt_move = cache_hit.and_then(|e| e.mov);
+    let mut move_picker = if in_check {
+        MovePicker::evasions(tt_move, t.info.conf.qs_see_bound)
+    } else {
+        let mut move_picker = MovePicker::new(tt_move, None, None, t.info.conf.qs_see_bound);
+        move_picker.skip_quiets = true;
+        move_picker
+    };
 
     let futility = stand_pat + t.info.conf.qs_futility;
 
@@ -659,7 +918,7 @@ pub fn quiescence<NT: NodeType>(t: &mut ThreadData, mut alpha: i32, beta: i32) -
         }
         t.ss[height].searching = Some(m);
         t.ss[height].searching_tactical = is_tactical;
-        let moved = t.board.state.mailbox[m.from()].unwrap();
+        let moved = t.board.mailbox[m.from()].unwrap();
         t.ss[height].ch_idx = ContHistIndex {
             piece: moved,
             to: m.history_to_square(),
@@ -731,7 +990,7 @@ pub fn quiescence<NT: NodeType>(t: &mut ThreadData, mut alpha: i32, beta: i32) -
 
 /// Perform alpha-beta minimax search.
 #[allow(clippy::too_many_lines, clippy::cognitive_complexity)]
-pub fn alpha_beta<NT: NodeType>(
+pub(crate) fn alpha_beta<NT: NodeType>(
     t: &mut ThreadData,
     mut depth: i32,
     mut alpha: i32,
@@ -765,11 +1024,7 @@ pub fn alpha_beta<NT: NodeType>(
         "Non-PV nodes must be zero-window."
     );
 
-    t.info.seldepth = if NT::ROOT {
-        0
-    } else {
-        t.info.seldepth.max(i32::try_from(height).unwrap())
-    };
+    t.info.seldepth = t.info.seldepth.max(i32::try_from(height).unwrap());
 
     let in_check = t.board.in_check();
 
@@ -811,6 +1066,8 @@ pub fn alpha_beta<NT: NodeType>(
     let cached = if excluded.is_none()
         && let Some(hit) = t.cache.probe(key, height, clock)
     {
+        #[cfg(feature = "stats")]
+        t.info.log_tt_probe(true);
         let illegal = hit
             .mov
             .is_some_and(|m| !t.board.is_pseudo_legal(m) || !t.board.is_legal(m));
@@ -844,6 +1101,10 @@ pub fn alpha_beta<NT: NodeType>(
 
         if illegal { None } else { Some(hit) }
     } else {
+        #[cfg(feature = "stats")]
+        if excluded.is_none() {
+            t.info.log_tt_probe(false);
+        }
         // do not probe the cache if we're in a singular-verification search.
         None
     };
@@ -855,7 +1116,7 @@ pub fn alpha_beta<NT: NodeType>(
     // Probe the tablebases.
     let (mut syzygy_max, mut syzygy_min) = (MATE_SCORE, -MATE_SCORE);
     let cardinality = u32::from(tablebases::probe::get_max_pieces_count(t.info.control));
-    let n_men = t.board.state.bbs.occupied().count();
+    let n_men = t.board.occupied().count();
     if !NT::ROOT
         && excluded.is_none()
         && n_men <= cardinality
@@ -1027,8 +1288,8 @@ pub fn alpha_beta<NT: NodeType>(
     // clear out the next killer move.
     t.killer_move_table[height + 1] = None;
 
-    let tt_move = cached.and_then(|e| e.mov);
-    let tt_capture = tt_move.filter(|m| t.board.is_tactical(*m));
+    let mut tt_move = cached.and_then(|e| e.mov);
+    let mut tt_capture = tt_move.filter(|m| t.board.is_tactical(*m));
 
     // whole-node techniques:
     if !NT::ROOT && !NT::PV && !in_check && excluded.is_none() {
@@ -1093,8 +1354,8 @@ pub fn alpha_beta<NT: NodeType>(
                 if t.board.estimated_see(&t.info.conf, m) > t.info.conf.see_pawn_value * 2)
         {
             t.cache.prefetch(t.board.key_after_null_move());
-            let r = 4
-                + depth / 3
+            let r = t.info.conf.nmp_base_reduction
+                + depth / t.info.conf.nmp_reduction_depth_divisor
                 + std::cmp::min(
                     (static_eval - beta) / t.info.conf.nmp_reduction_eval_divisor,
                     4,
@@ -1113,6 +1374,8 @@ pub fn alpha_beta<NT: NodeType>(
             if t.info.stopped() {
                 return 0;
             }
+            #[cfg(feature = "stats")]
+            t.info.log_null_move(null_score >= beta);
             if null_score >= beta {
                 // only perform verification when depth is high or mates are flying.
                 if depth < 12 && !is_decisive(beta) {
@@ -1139,12 +1402,32 @@ pub fn alpha_beta<NT: NodeType>(
         }
     }
 
-    // reduction in uncached expected-Cut nodes:
-    if cut_node
-        && excluded.is_none()
-        && (tt_move.is_none() || !matches!(cached, Some(ce) if ce.depth + 4 > depth))
-    {
-        depth -= i32::from(depth >= 8);
+    // internal iterative deepening and reduction, for PV and expected-Cut nodes
+    // that the cache knows little about. without a good move to try first, such
+    // a node is expensive to search, so either find one with a shallower search,
+    // or reduce the depth, as the node can't have been important before. PV nodes
+    // are only reduced if IIR_PV_DEPTH is set.
+    if !NT::ROOT && excluded.is_none() && (NT::PV || cut_node) {
+        let iid_depth = t.info.conf.iid_depth;
+        if tt_move.is_none() && iid_depth > 0 && depth >= iid_depth {
+            alpha_beta::<NT>(t, depth - t.info.conf.iid_reduction, alpha, beta, cut_node);
+            if t.info.stopped() {
+                return 0;
+            }
+            tt_move = t
+                .cache
+                .probe(key, height, clock)
+                .and_then(|hit| hit.mov)
+                .filter(|&m| t.board.is_pseudo_legal(m) && t.board.is_legal(m));
+            tt_capture = tt_move.filter(|m| t.board.is_tactical(*m));
+        } else if cut_node
+            && (tt_move.is_none() || !matches!(cached, Some(ce) if ce.depth + 4 > depth))
+        {
+            depth -= i32::from(depth >= t.info.conf.iir_depth);
+        } else if NT::PV && tt_move.is_none() {
+            let iir_pv_depth = t.info.conf.iir_pv_depth;
+            depth -= i32::from(iir_pv_depth > 0 && depth >= iir_pv_depth);
+        }
     }
 
     // the margins for static-exchange-evaluation pruning for tactical and quiet moves.
@@ -1173,7 +1456,7 @@ pub fn alpha_beta<NT: NodeType>(
         // base reduced probcut depth
         let depth_base = depth - 3 - (static_eval - beta) / t.info.conf.probcut_eval_div;
         let see_pivot = (pc_beta - static_eval) * t.info.conf.probcut_see_scale / 256;
-        let mut move_picker = MovePicker::new(tt_capture, None, see_pivot);
+        let mut move_picker = MovePicker::new(tt_capture, None, None, see_pivot);
         move_picker.skip_quiets = true;
         while let Some(m) = move_picker.next(t) {
             t.cache.prefetch(t.board.key_after(m));
@@ -1182,7 +1465,7 @@ pub fn alpha_beta<NT: NodeType>(
             }
             t.ss[height].searching = Some(m);
             t.ss[height].searching_tactical = true;
-            let moved = t.board.state.mailbox[m.from()].unwrap();
+            let moved = t.board.mailbox[m.from()].unwrap();
             t.ss[height].ch_idx = ContHistIndex {
                 piece: moved,
                 to: m.history_to_square(),
@@ -1262,14 +1545,18 @@ pub fn alpha_beta<NT: NodeType>(
     let lmp_threshold = t.info.lm_table.lmp_movecount(depth, improving);
 
     let killer = t.killer_move_table[height].filter(|m| !t.board.is_tactical(*m));
-    let mut move_picker = MovePicker::new(tt_move, killer, t.info.conf.main_see_bound);
+    let counter_move = t
+        .counter_move()
+        .filter(|&m| !t.board.is_tactical(m) && Some(m) != killer);
+    let mut move_picker =
+        MovePicker::new(tt_move, killer, counter_move, t.info.conf.main_see_bound);
 
     let mut quiets_tried = ArrayVec::<_, MAX_POSITION_MOVES>::new();
     // there are never more than 32 captures in a position.
     let mut tacticals_tried = ArrayVec::<_, 32>::new();
 
     while let Some(m) = move_picker.next(t) {
-        if excluded == Some(m) {
+        if excluded == Some(m) || NT::ROOT && t.root_move_excluded(m) {
             continue;
         }
 
@@ -1285,7 +1572,7 @@ pub fn alpha_beta<NT: NodeType>(
 
         let from = m.from();
         let hist_to = m.history_to_square();
-        let moved = t.board.state.mailbox[from].unwrap();
+        let moved = t.board.mailbox[from].unwrap();
         let threats = t.board.state.threats.all;
         let from_threat = usize::from(threats.contains_square(from));
         let to_threat = usize::from(threats.contains_square(hist_to));
@@ -1306,7 +1593,8 @@ pub fn alpha_beta<NT: NodeType>(
             // history pruning
             // if this move's history score is too low, we start skipping moves.
             if is_quiet
-                && (Some(m) != killer)
+                && Some(m) != killer
+                && Some(m) != counter_move
                 && lmr_depth < 7
                 && stat_score < t.info.conf.history_pruning_margin * (depth - 1)
             {
@@ -1358,6 +1646,13 @@ pub fn alpha_beta<NT: NodeType>(
         t.info.nodes.increment();
         moves_made += 1;
 
+        if NT::ROOT && t.thread_id == 0 && t.info.report_currmove() {
+            println!(
+                "info depth {depth} currmove {} currmovenumber {moves_made}",
+                m.display(t.board.rules())
+            );
+        }
+
         #[expect(clippy::cast_sign_loss)]
         let root_depth = t.root_depth as usize;
 
@@ -1456,7 +1751,8 @@ pub fn alpha_beta<NT: NodeType>(
                 // extend/reduce using the stat_score of the move
                 r -= stat_score * 1024 / t.info.conf.history_lmr_divisor;
                 // reduce refutation moves less
-                r -= i32::from(Some(m) == killer) * t.info.conf.lmr_refutation_mul;
+                r -= i32::from(Some(m) == killer || Some(m) == counter_move)
+                    * t.info.conf.lmr_refutation_mul;
                 // reduce more if not improving
                 r += i32::from(!improving) * t.info.conf.lmr_non_improving_mul;
                 // reduce more if the move from the transposition table is tactical
@@ -1503,11 +1799,10 @@ pub fn alpha_beta<NT: NodeType>(
                 if is_quiet && (score <= alpha || score >= beta) {
                     t.histories.update_cont_hist_single(
                         history::ctx!(t),
-                        &t.ss,
+                        &t.ss[..=height],
                         hist_to,
                         moved,
                         new_depth,
-                        height,
                         score > alpha,
                     );
                 }
@@ -1590,6 +1885,7 @@ pub fn alpha_beta<NT: NodeType>(
         let best_move = best_move.expect("if alpha was raised, we should have a best move.");
         if !t.board.is_tactical(best_move) {
             t.insert_killer(best_move);
+            t.insert_counter_move(best_move);
 
             // this heuristic is on the whole unmotivated, beyond mere empiricism.
             // perhaps it's really important to know which quiet moves are good in "bad" positions?
@@ -1644,16 +1940,19 @@ pub fn alpha_beta<NT: NodeType>(
         {
             t.update_correction_history(depth, tt_complexity, best_score - fresh_eval);
         }
-        t.cache.store(
-            key,
-            height,
-            best_move,
-            best_score,
-            raw_eval,
-            flag,
-            depth,
-            t.ss[height].ttpv,
-        );
+        // a root search that skips moves doesn't tell us about the whole position.
+        if !NT::ROOT || !t.root_restricted() {
+            t.cache.store(
+                key,
+                height,
+                best_move,
+                best_score,
+                raw_eval,
+                flag,
+                depth,
+                t.ss[height].ttpv,
+            );
+        }
     }
 
     t.ss[height].best_move = best_move;
@@ -1706,6 +2005,10 @@ fn get_cont_history(t: &ThreadData<'_>, height: usize, hist_to: Square, moved: P
         stat_score += i32::from(t.histories.continuation[t.ss[height - 4].ch_idx][moved][hist_to])
             * t.info.conf.cont4_stat_score_mul;
     }
+    if height >= 6 {
+        stat_score += i32::from(t.histories.continuation[t.ss[height - 6].ch_idx][moved][hist_to])
+            * t.info.conf.cont6_stat_score_mul;
+    }
     stat_score
 }
 
@@ -1734,11 +2037,11 @@ pub fn is_forced(margin: i32, t: &mut ThreadData, m: Move, value: i32, depth: i3
 /// Cheaply estimate whether there's an obvious winning capture to be made
 /// somewhere in the position.
 pub fn can_win_material(pos: &Board) -> bool {
-    let us = pos.state.bbs.colours[pos.turn()];
-    let queens = pos.state.bbs.pieces[PieceType::Queen] & us;
-    let rooks = pos.state.bbs.pieces[PieceType::Rook] & us;
-    let bishops = pos.state.bbs.pieces[PieceType::Bishop] & us;
-    let knights = pos.state.bbs.pieces[PieceType::Knight] & us;
+    let us = pos.turn();
+    let queens = pos.queens(us);
+    let rooks = pos.rooks(us);
+    let bishops = pos.bishops(us);
+    let knights = pos.knights(us);
 
     (pos.state.threats.leq_rook & queens) != SquareSet::EMPTY
         || (pos.state.threats.leq_minor & (queens | rooks)) != SquareSet::EMPTY
@@ -1746,6 +2049,7 @@ pub fn can_win_material(pos: &Board) -> bool {
 }
 
 /// See if a move looks like it would initiate a winning exchange.
+///
 /// This function simulates flowing all moves on to the target square of
 /// the given move, from least to most valuable moved piece, and returns
 /// true if the exchange comes out with a material advantage of at
@@ -1757,7 +2061,7 @@ pub fn static_exchange_eval(board: &Board, conf: &Config, m: Move, threshold: i3
 
     let mut next_victim = m
         .promotion_type()
-        .unwrap_or_else(|| board.state.mailbox[from].unwrap().piece_type());
+        .unwrap_or_else(|| board.mailbox[from].unwrap().piece_type());
 
     let mut balance = board.estimated_see(conf, m) - threshold;
 
@@ -1780,14 +2084,19 @@ pub fn static_exchange_eval(board: &Board, conf: &Config, m: Move, threshold: i3
     // occupied starts with the position after the move `m` is made.
     let mut occupied = (bbs.occupied() ^ from.as_set()) | to.as_set();
     if m.is_ep() {
-        occupied ^= board.ep_sq().unwrap().as_set();
+        // the captured pawn isn't on the target square, so remove it separately.
+        let captured = board
+            .ep_sq()
+            .and_then(|ep_sq| ep_sq.backward(board.turn()))
+            .expect("en-passant move without a valid en-passant square");
+        occupied ^= captured.as_set();
     }
 
     // after the move, it's the opponent's turn.
     let mut colour = !board.turn();
 
-    let white_pinned = board.state.pinned[Colour::White];
-    let black_pinned = board.state.pinned[Colour::Black];
+    let white_pinned = board.pinned(Colour::White);
+    let black_pinned = board.pinned(Colour::Black);
 
     let kings = bbs.pieces[PieceType::King];
     let white_king = kings & bbs.colours[Colour::White];
@@ -1800,7 +2109,7 @@ pub fn static_exchange_eval(board: &Board, conf: &Config, m: Move, threshold: i3
         | (white_pinned & white_king_ray)
         | (black_pinned & black_king_ray);
 
-    let mut attackers = bbs.all_attackers_to_sq(to, occupied) & allowed;
+    let mut attackers = board.attackers_to(to, occupied) & allowed;
 
     loop {
         let my_attackers = attackers & bbs.colours[colour];
@@ -1855,7 +2164,7 @@ pub fn static_exchange_eval(board: &Board, conf: &Config, m: Move, threshold: i3
     board.turn() != colour
 }
 
-pub fn adj_shuffle(t: &ThreadData, raw_eval: i32, clock: u8) -> i32 {
+pub(crate) fn adj_shuffle(t: &ThreadData, raw_eval: i32, clock: u8) -> i32 {
     if cfg!(feature = "datagen") {
         // during datagen, we want to use raw evals only.
         // source: chef.
@@ -1873,11 +2182,13 @@ pub fn adj_shuffle(t: &ThreadData, raw_eval: i32, clock: u8) -> i32 {
 
     // scale down the value when the fifty-move counter is high.
     // this goes some way toward making viri realise when he's not
-    // making progress in a position.
-    raw_eval * (200 - i32::from(clock)) / 200
+    // making progress in a position. the damping is the clock value
+    // at which the evaluation would reach zero.
+    let damping = t.info.conf.fifty_move_damping;
+    raw_eval * (damping - i32::from(clock).min(damping)) / damping
 }
 
-pub fn select_best<'a>(thread_headers: &'a [Box<ThreadData<'a>>]) -> &'a ThreadData<'a> {
+pub(crate) fn select_best<'a>(thread_headers: &'a [Box<ThreadData<'a>>]) -> &'a ThreadData<'a> {
     #![expect(clippy::cast_possible_wrap)]
 
     let Some((mut best, rest @ [_next, ..])) = thread_headers.split_first() else {
@@ -1986,6 +2297,7 @@ fn readout_info(
     force_print: bool,
 ) {
     #![allow(
+        clippy::too_many_lines,
         clippy::cast_precision_loss,
         clippy::cast_sign_loss,
         clippy::cast_possible_truncation
@@ -1993,6 +2305,26 @@ fn readout_info(
     const GREY_START: &str = "\x1b[90m";
     const RESET: &str = "\x1b[0m";
     const PREFIX_LEN: usize = 54;
+    if let Some(callbacks) = info.callbacks {
+        let pv = t.pv();
+        let pv = if pv.moves.is_empty() {
+            Vec::new()
+        } else {
+            extend_pv_from_cache(&t.board, t.cache, pv, t.iteration)
+                .moves
+                .to_vec()
+        };
+        callbacks.on_iteration(&IterationInfo {
+            depth: t.iteration,
+            seldepth: info.seldepth as usize,
+            score: Score::from_internal(t.score()),
+            bound,
+            pv,
+            nodes,
+            tbhits: info.tbhits.get_global(),
+            elapsed: info.clock.elapsed(),
+        });
+    }
     if !info.print_to_stdout {
         return;
     }
@@ -2001,13 +2333,27 @@ fn readout_info(
     if info.skip_print() && !force_print {
         return;
     }
+    // completed iterations are always reported, but aspiration window failures
+    // can come thick and fast, so rate-limit them to avoid flooding the GUI.
+    if bound != Bound::Exact && !force_print && info.throttle_print() {
+        return;
+    }
+    info.mark_printed();
     let ThreadData {
         board,
         iteration,
         cache: tt,
         ..
     } = t;
-    let pv = t.pv();
+    // the line is only worth extending if it was actually searched.
+    let extend = |pv: &PVariation| {
+        if pv.moves.is_empty() {
+            pv.clone()
+        } else {
+            extend_pv_from_cache(board, *tt, pv, *iteration)
+        }
+    };
+    let pv = extend(t.pv());
     let normal_uci_output = !info.control.pretty_print.load(Ordering::SeqCst);
     let nps = (nodes as f64 / info.clock.elapsed().as_secs_f64()) as u64;
     if board.turn() == Colour::Black {
@@ -2019,19 +2365,39 @@ fn readout_info(
         _ => "",
     };
     if normal_uci_output {
-        println!(
-            "info depth {iteration} seldepth {} nodes {nodes} time {} nps {nps} hashfull {hashfull} tbhits {tbhits} score {sstr}{bound_string} wdl {wdl} {pv}",
-            info.seldepth as usize,
-            info.clock.elapsed().as_millis(),
-            sstr = uci::fmt::format_score(t.score()),
-            hashfull = tt.hashfull(),
-            tbhits = t.info.tbhits.get_global(),
-            wdl = uci::fmt::format_wdl(t.score(), board.ply()),
-            pv = pv.display(board.rules()),
-        );
+        let requested_lines = info.control.multi_pv.load(Ordering::Relaxed);
+        let multi_pv = requested_lines > 1;
+        // secondary lines are only complete alongside an exact principal line.
+        let extra_lines = if bound == Bound::Exact {
+            &t.multi_pv_lines[..]
+        } else {
+            &[]
+        };
+        let hashfull = tt.hashfull();
+        let lines = std::iter::once((t.score(), pv))
+            .chain(extra_lines.iter().map(|(score, pv)| (*score, extend(pv))))
+            // a strength limit may search more lines than were asked for.
+            .take(requested_lines);
+        for (i, (score, pv)) in lines.enumerate() {
+            let multi_pv_string = if multi_pv {
+                format!(" multipv {}", i + 1)
+            } else {
+                String::new()
+            };
+            let bound_string = if i == 0 { bound_string } else { "" };
+            println!(
+                "info depth {iteration} seldepth {}{multi_pv_string} nodes {nodes} time {} nps {nps} hashfull {hashfull} tbhits {tbhits} score {sstr}{bound_string} wdl {wdl} {pv}",
+                info.seldepth as usize,
+                info.clock.elapsed().as_millis(),
+                sstr = uci::fmt::format_score(score),
+                tbhits = t.info.tbhits.get_global(),
+                wdl = uci::fmt::format_wdl(score, board.ply()),
+                pv = pv.display(board.rules()),
+            );
+        }
     } else {
         let value = uci::fmt::pretty_format_score(t.score(), board.turn());
-        let mut pv_string = board.pv_san(pv).unwrap();
+        let mut pv_string = board.pv_san(&pv).unwrap();
         let pv_string_len = pv_string.len();
         // truncate the pv string if it's too long
         let max_length =
@@ -2075,7 +2441,7 @@ fn readout_info(
     }
 }
 
-pub fn draw_score(t: &ThreadData, nodes: u64, stm: Colour) -> i32 {
+pub(crate) fn draw_score(t: &ThreadData, nodes: u64, stm: Colour) -> i32 {
     // score fuzzing helps with threefolds.
     let random_component = (nodes & 0b11) as i32 - 2;
     // higher contempt means we will play on in drawn positions more often,
@@ -2092,7 +2458,7 @@ pub fn draw_score(t: &ThreadData, nodes: u64, stm: Colour) -> i32 {
 }
 
 #[derive(Clone, Debug)]
-pub struct LMTable {
+pub(crate) struct LMTable {
     /// The reduction table. rtable\[depth]\[played] is the base LMR reduction for a move
     lm_reduction_table: [[i32; 64]; 64],
     /// The movecount table. ptable\[played]\[improving] is the movecount at which LMP is triggered.
@@ -2100,7 +2466,7 @@ pub struct LMTable {
 }
 
 impl LMTable {
-    pub fn new(config: &Config) -> Self {
+    pub(crate) fn new(config: &Config) -> Self {
         #![allow(
             clippy::cast_possible_truncation,
             clippy::cast_precision_loss,
@@ -2128,14 +2494,124 @@ impl LMTable {
         out
     }
 
-    pub fn lm_reduction(&self, depth: i32, moves_made: usize) -> i32 {
+    pub(crate) fn lm_reduction(&self, depth: i32, moves_made: usize) -> i32 {
         let depth: usize = depth.clamp(0, 63).try_into().unwrap_or_default();
         let played = moves_made.min(63);
         self.lm_reduction_table[depth][played]
     }
 
-    pub fn lmp_movecount(&self, depth: i32, improving: bool) -> usize {
+    pub(crate) fn lmp_movecount(&self, depth: i32, improving: bool) -> usize {
         let depth: usize = depth.clamp(0, 11).try_into().unwrap_or_default();
         self.lmp_movecount_table[usize::from(improving)][depth]
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn see_en_passant_removes_captured_pawn() {
+        // after exd6, the white rook on d1 defends d6 through the square
+        // vacated by the captured pawn, so black can't profitably recapture.
+        let mut board = Board::from_fen("3r3k/3p4/8/4P3/8/8/8/3R3K b - - 0 1").unwrap();
+        board.make_move_simple(board.parse_uci("d7d5").unwrap());
+        let conf = Config::default();
+        let m = board.parse_uci("e5d6").unwrap();
+        assert!(m.is_ep());
+        assert!(static_exchange_eval(&board, &conf, m, conf.see_pawn_value));
+    }
+
+    #[test]
+    fn see_scores_simple_exchanges() {
+        let see = |fen: &str, uci: &str, threshold: i32| {
+            let board = Board::from_fen(fen).unwrap();
+            let m = board.parse_uci(uci).unwrap();
+            board.see(m, threshold)
+        };
+        let conf = Config::default();
+        // an undefended knight is won outright.
+        let hanging = "4k3/8/8/3n4/8/8/8/3RK3 w - - 0 1";
+        assert!(see(hanging, "d1d5", conf.see_knight_value));
+        assert!(!see(hanging, "d1d5", conf.see_knight_value + 1));
+        // a pawn-defended knight costs the rook that takes it.
+        let defended = "4k3/8/4p3/3n4/8/8/8/3RK3 w - - 0 1";
+        assert!(see(
+            defended,
+            "d1d5",
+            conf.see_knight_value - conf.see_rook_value
+        ));
+        assert!(!see(defended, "d1d5", 0));
+        // a quiet move onto an attacked square loses the piece.
+        let quiet = "4k3/8/4p3/8/8/8/8/4KR2 w - - 0 1";
+        assert!(see(quiet, "f1f4", 0));
+        assert!(!see(quiet, "f1f5", 0));
+        assert!(see(quiet, "f1f5", -conf.see_rook_value));
+    }
+
+    #[test]
+    fn see_counts_promotions() {
+        let conf = Config::default();
+        // promoting on a safe square gains a queen for a pawn.
+        let board = Board::from_fen("4k3/1P6/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        let m = board.parse_uci("b7b8q").unwrap();
+        assert!(board.see(m, conf.see_queen_value - conf.see_pawn_value));
+        assert!(!board.see(m, conf.see_queen_value - conf.see_pawn_value + 1));
+        // if the new queen is taken at once, only the pawn is lost.
+        let board = Board::from_fen("1r2k3/P7/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        let m = board.parse_uci("a7a8q").unwrap();
+        assert!(!board.see(m, 0));
+        let capture = board.parse_uci("a7b8q").unwrap();
+        assert!(board.see(capture, conf.see_rook_value));
+    }
+
+    #[test]
+    fn pv_is_extended_from_cache() {
+        let pool = threadpool::make_worker_threads(1);
+        let mut cache = crate::transpositiontable::Cache::new();
+        cache.resize(crate::util::MEGABYTE, &pool);
+        let tt = cache.view();
+        let board = Board::startpos();
+        let store = |line: &[&str], reply: &str| {
+            let mut board = board.clone();
+            for m in line {
+                board.make_move_simple(board.parse_uci(m).unwrap());
+            }
+            let key = board.state.keys.zobrist ^ HM_CLOCK_KEYS[board.fifty_move_counter() as usize];
+            let m = board.parse_uci(reply).unwrap();
+            tt.store(key, 0, Some(m), 0, 0, Bound::Exact, 5, true);
+        };
+        let pv = |line: &[&str]| {
+            let mut pv = PVariation::new();
+            let mut board = board.clone();
+            for m in line {
+                let m = board.parse_uci(m).unwrap();
+                pv.moves.push(m);
+                board.make_move_simple(m);
+            }
+            pv
+        };
+        let uci = |pv: &PVariation| pv.to_string().trim().to_string();
+
+        // a knight shuffle, which the walk must not follow forever.
+        store(&["g1f3"], "g8f6");
+        store(&["g1f3", "g8f6"], "f3g1");
+        store(&["g1f3", "g8f6", "f3g1"], "f6g8");
+        store(&["g1f3", "g8f6", "f3g1", "f6g8"], "g1f3");
+        let extended = extend_pv_from_cache(&board, tt, &pv(&["g1f3"]), 20);
+        assert_eq!(uci(&extended), "pv g1f3 g8f6 f3g1 f6g8");
+        // the walk stops at the requested depth.
+        let extended = extend_pv_from_cache(&board, tt, &pv(&["g1f3"]), 2);
+        assert_eq!(uci(&extended), "pv g1f3 g8f6");
+
+        // a move that is illegal in the position it is looked up in is never shown.
+        let mut after_e4 = board.clone();
+        after_e4.make_move_simple(board.parse_uci("e2e4").unwrap());
+        let key =
+            after_e4.state.keys.zobrist ^ HM_CLOCK_KEYS[after_e4.fifty_move_counter() as usize];
+        let bogus = board.parse_uci("d2d4").unwrap();
+        tt.store(key, 0, Some(bogus), 0, 0, Bound::Exact, 5, true);
+        let extended = extend_pv_from_cache(&board, tt, &pv(&["e2e4"]), 20);
+        assert_eq!(uci(&extended), "pv e2e4");
+    }
+}
@@ -0,0 +1,247 @@
+//! Running searches from code, rather than through the UCI loop.
+
+use std::{
+    fmt,
+    sync::atomic::{AtomicBool, AtomicU64},
+    time::Duration,
+};
+
+use vec1::Vec1;
+
+use crate::{
+    chess::{board::Board, chessmove::Move},
+    evaluation::{MATE_SCORE, TB_WIN_SCORE, is_decisive, is_mate_score},
+    nnue::network::NNUEParams,
+    searchinfo::Control,
+    threadlocal::make_thread_data,
+    threadpool::{self, WorkerThread},
+    transpositiontable::{Bound, Cache},
+    uci::fmt::NORMALISE_TO_PAWN_VALUE,
+    util::MEGABYTE,
+};
+
+pub use crate::timemgmt::SearchLimit;
+
+/// A score, from the point of view of the side to move.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Score {
+    /// An evaluation, in centipawns.
+    Centipawns(i32),
+    /// A forced mate in this many moves, or, if it's negative, a forced mate against the side
+    /// to move.
+    Mate(i32),
+}
+
+impl Score {
+    /// Convert a score in the search's internal units.
+    pub(crate) fn from_internal(score: i32) -> Self {
+        // tablebase wins are reported as very large, but not mate, scores.
+        const FAKE_TB_WIN_SCORE: i32 = 30_000;
+        if is_mate_score(score) {
+            let plies_to_mate = MATE_SCORE - score.abs();
+            let moves_to_mate = (plies_to_mate + 1) / 2;
+            Self::Mate(moves_to_mate * score.signum())
+        } else if is_decisive(score) {
+            let adjustment = TB_WIN_SCORE - FAKE_TB_WIN_SCORE;
+            Self::Centipawns(score - score.signum() * adjustment)
+        } else {
+            Self::Centipawns(
+                (score * 100 / NORMALISE_TO_PAWN_VALUE)
+                    .clamp(-FAKE_TB_WIN_SCORE + 1, FAKE_TB_WIN_SCORE - 1),
+            )
+        }
+    }
+}
+
+/// A report on the progress of a search, made as each iteration completes, when an
+/// iteration's aspiration window fails, and once more when the search ends.
+#[derive(Clone, Debug)]
+pub struct IterationInfo {
+    /// The depth of the iteration.
+    pub depth: usize,
+    /// The greatest depth reached by any line of the iteration.
+    pub seldepth: usize,
+    /// The score of the principal variation.
+    pub score: Score,
+    /// How the score relates to the true score: exact, or a bound from a failed aspiration
+    /// window.
+    pub bound: Bound,
+    /// The principal variation.
+    pub pv: Vec<Move>,
+    /// The nodes searched so far, across all threads.
+    pub nodes: u64,
+    /// The tablebase hits so far, across all threads.
+    pub tbhits: u64,
+    /// The time since the search started.
+    pub elapsed: Duration,
+}
+
+/// Receives reports from a running search. Reports come from whichever thread is searching,
+/// so implementations must be `Sync`.
+///
+/// Closures taking an [`IterationInfo`] are callbacks, and `()` ignores every report.
+pub trait SearchCallbacks: Sync {
+    /// Called with each report on the progress of the search.
+    fn on_iteration(&self, info: &IterationInfo);
+}
+
+impl<F: Fn(&IterationInfo) + Sync> SearchCallbacks for F {
+    fn on_iteration(&self, info: &IterationInfo) {
+        self(info);
+    }
+}
+
+impl SearchCallbacks for () {
+    fn on_iteration(&self, _: &IterationInfo) {}
+}
+
+impl fmt::Debug for dyn SearchCallbacks + '_ {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SearchCallbacks")
+    }
+}
+
+/// The outcome of a search.
+#[derive(Clone, Debug)]
+pub struct SearchResult {
+    /// The move to play, or `None` if the position has no legal moves.
+    pub best_move: Option<Move>,
+    /// The reply the search expects, if it has one.
+    pub ponder_move: Option<Move>,
+    /// The score of the position.
+    pub score: Score,
+    /// The principal variation, starting with the best move.
+    pub pv: Vec<Move>,
+    /// The depth of the last completed iteration.
+    pub depth: usize,
+    /// The greatest depth reached by any line of the last iteration.
+    pub seldepth: usize,
+    /// The nodes searched, across all threads.
+    pub nodes: u64,
+    /// The tablebase hits, across all threads.
+    pub tbhits: u64,
+    /// How long the search took.
+    pub elapsed: Duration,
+}
+
+/// A search engine: worker threads, a transposition table, and the network, ready to search
+/// positions one after another.
+///
+/// ```no_run
+/// use viridithas::{chess::board::Board, search::{Search, SearchLimit}};
+///
+/// let mut search = Search::new(1, 16).unwrap();
+/// let result = search
+///     .run(&Board::startpos(), SearchLimit::Depth(10), &|info: &_| println!("{info:?}"))
+///     .unwrap();
+/// println!("{:?} {:?}", result.best_move, result.score);
+/// ```
+pub struct Search {
+    pool: Vec1<WorkerThread>,
+    cache: Cache,
+    nnue_params: &'static NNUEParams,
+    control: Control,
+}
+
+impl Search {
+    /// Start `threads` search threads, sharing a transposition table of `hash_mb` megabytes.
+    pub fn new(threads: usize, hash_mb: usize) -> anyhow::Result<Self> {
+        anyhow::ensure!(threads > 0, "a search needs at least one thread");
+        anyhow::ensure!(hash_mb > 0, "a search needs a transposition table");
+        let nnue_params = NNUEParams::decompress_and_alloc()?;
+        let pool = threadpool::make_worker_threads(threads);
+        let mut cache = Cache::new();
+        cache.resize(hash_mb * MEGABYTE, &pool);
+        Ok(Self {
+            pool,
+            cache,
+            nnue_params,
+            control: Control::default(),
+        })
+    }
+
+    /// Forget everything learned from earlier searches, as before a new game.
+    pub fn clear(&mut self) {
+        self.cache.clear(&self.pool);
+    }
+
+    /// Search `position`, within `limits`, reporting progress to `callbacks`.
+    pub fn run(
+        &mut self,
+        position: &Board,
+        limits: SearchLimit,
+        callbacks: &dyn SearchCallbacks,
+    ) -> anyhow::Result<SearchResult> {
+        let stopped = AtomicBool::new(false);
+        let nodes = AtomicU64::new(0);
+        let tbhits = AtomicU64::new(0);
+        let mut thread_data = make_thread_data(
+            position,
+            self.cache.view(),
+            self.nnue_params,
+            &stopped,
+            &nodes,
+            &tbhits,
+            &self.control,
+            &self.pool,
+        )?;
+        thread_data[0].info.print_to_stdout = false;
+        thread_data[0].info.callbacks = Some(callbacks);
+        thread_data[0].info.clock.start();
+        thread_data[0].info.clock.set_limit(limits);
+        self.cache.increase_age();
+        let (_, result) = super::search_root(&self.pool, &mut thread_data);
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+    use crate::evaluation::mate_in;
+
+    #[test]
+    fn mate_scores_count_moves() {
+        assert_eq!(Score::from_internal(mate_in(1)), Score::Mate(1));
+        assert_eq!(Score::from_internal(mate_in(3)), Score::Mate(2));
+        assert_eq!(Score::from_internal(-mate_in(4)), Score::Mate(-2));
+        assert_eq!(Score::from_internal(0), Score::Centipawns(0));
+    }
+
+    #[test]
+    fn reports_each_iteration() {
+        let board = Board::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+        let mut search = Search::new(1, 1).unwrap();
+        let depths = Mutex::new(Vec::new());
+        let result = search
+            .run(&board, SearchLimit::Depth(5), &|info: &IterationInfo| {
+                if info.bound == Bound::Exact {
+                    depths.lock().unwrap().push(info.depth);
+                }
+            })
+            .unwrap();
+        let depths = depths.into_inner().unwrap();
+        assert_eq!(&depths[..5], &[1, 2, 3, 4, 5]);
+        assert_eq!(result.depth, 5);
+        assert_eq!(result.score, Score::Mate(1));
+        assert_eq!(result.best_move, result.pv.first().copied());
+        assert_eq!(
+            result
+                .best_move
+                .map(|m| m.display(board.rules()).to_string()),
+            Some("a1a8".into())
+        );
+        assert!(result.nodes > 0);
+    }
+
+    #[test]
+    fn no_legal_moves() {
+        let board = Board::from_fen("R5k1/5ppp/8/8/8/8/8/6K1 b - - 0 1").unwrap();
+        let mut search = Search::new(1, 1).unwrap();
+        let result = search.run(&board, SearchLimit::Depth(5), &()).unwrap();
+        assert_eq!(result.best_move, None);
+        assert_eq!(result.score, Score::Mate(0));
+    }
+}
@@ -0,0 +1,298 @@
+//! An experimental Monte Carlo tree search, as an alternative to alpha-beta.
+//!
+//! The tree is grown one leaf per iteration with PUCT selection, as in `AlphaZero`.
+//! There is no policy network, so the move priors come from a cheap static ordering
+//! (good captures and promotions first), and the NNUE evaluation stands in for a value
+//! head, squashed into an expected score in `[0, 1]`.
+//!
+//! The search runs on the main thread only, and reports the most-visited line as its
+//! principal variation, with the average depth of the tree as its depth.
+
+use std::sync::atomic::Ordering;
+
+use crate::{
+    chess::{board::Board, chessmove::Move},
+    evaluation::{MINIMUM_TB_WIN_SCORE, evaluate},
+    search::{dyn_max_depth, readout_info},
+    threadlocal::ThreadData,
+    transpositiontable::Bound,
+    util::MAX_DEPTH,
+};
+
+/// The exploration constant in the PUCT formula.
+const CPUCT: f32 = 1.8;
+/// How much worse than its parent an unvisited child is assumed to be.
+const FPU_REDUCTION: f32 = 0.25;
+/// The evaluation, in centipawns, at which the expected score is about 73%.
+const EVAL_SCALE: f32 = 400.0;
+/// The most nodes the tree may hold, which bounds its memory use to a few hundred megabytes.
+const MAX_TREE_NODES: usize = 1 << 23;
+/// How often, in iterations, to check the clock and report progress.
+const CHECK_INTERVAL: u64 = 1024;
+
+/// A node of the search tree, reached by playing `mv` from its parent.
+#[derive(Debug, Clone, Copy)]
+struct Node {
+    mv: Option<Move>,
+    parent: u32,
+    first_child: u32,
+    children: u8,
+    expanded: bool,
+    /// The result of the game if the node is a terminal position, for the side to move.
+    terminal: Option<f32>,
+    prior: f32,
+    visits: u32,
+    /// The sum of the results backed up through this node, for the side that played `mv`.
+    total: f64,
+}
+
+impl Node {
+    const fn new(mv: Option<Move>, parent: u32, prior: f32) -> Self {
+        Self {
+            mv,
+            parent,
+            first_child: 0,
+            children: 0,
+            expanded: false,
+            terminal: None,
+            prior,
+            visits: 0,
+            total: 0.0,
+        }
+    }
+
+    #[allow(clippy::cast_possible_truncation)]
+    fn q(&self) -> f32 {
+        (self.total / f64::from(self.visits)) as f32
+    }
+
+    fn child_range(&self) -> std::ops::Range<usize> {
+        let first = self.first_child as usize;
+        first..first + usize::from(self.children)
+    }
+}
+
+/// A search tree, stored as an arena with the root at index zero.
+struct Tree {
+    nodes: Vec<Node>,
+}
+
+impl Tree {
+    fn new() -> Self {
+        Self {
+            nodes: vec![Node::new(None, 0, 1.0)],
+        }
+    }
+
+    /// Pick the child of `parent` with the highest PUCT score.
+    fn select(&self, parent: usize) -> usize {
+        #![allow(clippy::cast_precision_loss)]
+        let node = &self.nodes[parent];
+        let explore = CPUCT * (node.visits.max(1) as f32).sqrt();
+        // the parent's value is stored for the other side, so flip it for ours.
+        let fpu = if node.visits == 0 {
+            0.5
+        } else {
+            1.0 - node.q()
+        } - FPU_REDUCTION;
+        node.child_range()
+            .max_by(|&a, &b| {
+                let score = |i: usize| {
+                    let child = &self.nodes[i];
+                    let q = if child.visits == 0 { fpu } else { child.q() };
+                    q + explore * child.prior / (1 + child.visits) as f32
+                };
+                score(a).total_cmp(&score(b))
+            })
+            .expect("selected from a node without children")
+    }
+
+    /// Add the children of `index`, with priors for the moves in `moves`.
+    fn expand(&mut self, index: usize, board: &Board, moves: &[Move]) {
+        #![allow(clippy::cast_possible_truncation)]
+        let scores = moves
+            .iter()
+            .map(|&m| move_prior_score(board, m))
+            .collect::<Vec<_>>();
+        let max = scores.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+        let sum = scores.iter().map(|&s| (s - max).exp()).sum::<f32>();
+        let first = self.nodes.len();
+        for (&m, &s) in moves.iter().zip(&scores) {
+            self.nodes
+                .push(Node::new(Some(m), index as u32, (s - max).exp() / sum));
+        }
+        let node = &mut self.nodes[index];
+        node.first_child = first as u32;
+        node.children = moves.len() as u8;
+        node.expanded = true;
+    }
+
+    /// The most-visited child of `index`, if it has been visited at all.
+    fn best_child(&self, index: usize) -> Option<usize> {
+        self.nodes[index]
+            .child_range()
+            .filter(|&i| self.nodes[i].visits > 0)
+            .max_by(|&a, &b| {
+                let (a, b) = (&self.nodes[a], &self.nodes[b]);
+                a.visits.cmp(&b.visits).then(a.q().total_cmp(&b.q()))
+            })
+    }
+}
+
+/// A log-scale preference for a move, before normalisation into a prior.
+fn move_prior_score(board: &Board, m: Move) -> f32 {
+    let mut score = 0.0;
+    if m.is_promo() {
+        score += 2.0;
+    }
+    if board.is_capture(m) {
+        score += if board.see(m, 0) { 2.0 } else { -0.5 };
+    }
+    score
+}
+
+/// Convert an evaluation in centipawns into an expected score in `[0, 1]`.
+fn expected_score(eval: i32) -> f32 {
+    #![allow(clippy::cast_precision_loss)]
+    1.0 / (1.0 + (-(eval as f32) / EVAL_SCALE).exp())
+}
+
+/// Convert an expected score back into an evaluation in centipawns.
+fn eval_from_expected_score(q: f32) -> i32 {
+    #![allow(clippy::cast_possible_truncation)]
+    let q = q.clamp(1e-6, 1.0 - 1e-6);
+    let eval = (EVAL_SCALE * (q / (1.0 - q)).ln()) as i32;
+    eval.clamp(-MINIMUM_TB_WIN_SCORE + 1, MINIMUM_TB_WIN_SCORE - 1)
+}
+
+/// Search the position on `t.board` with MCTS, considering only `root_moves` at the root.
+///
+/// Results are written into `t` as an iterative deepening search would leave them,
+/// so that the caller can report them in the same way.
+pub fn search(t: &mut ThreadData, root_moves: &[Move]) {
+    if root_moves.is_empty() {
+        return;
+    }
+    let max_depth = dyn_max_depth(t);
+    t.info.seldepth = 0;
+    let mut tree = Tree::new();
+    let root_board = t.board.clone();
+    tree.expand(0, &root_board, root_moves);
+    let mut depth_sum = 0u64;
+    let mut iterations = 0u64;
+    let mut reported_depth = 0;
+
+    loop {
+        // selection: walk down the tree to a leaf, playing the moves as we go.
+        let mut index = 0;
+        let mut depth = 0;
+        while tree.nodes[index].expanded && tree.nodes[index].terminal.is_none() {
+            index = tree.select(index);
+            let m = tree.nodes[index].mv.expect("non-root node without a move");
+            t.board.make_move(m, &mut t.nnue);
+            depth += 1;
+        }
+
+        // every iteration counts as a node, even if it ends in a known result.
+        t.info.nodes.increment();
+
+        // expansion and evaluation, for the side to move at the leaf.
+        let value = if let Some(value) = tree.nodes[index].terminal {
+            value
+        } else {
+            let moves = t.board.legal_moves();
+            let terminal = if moves.is_empty() {
+                Some(if t.board.in_check() { 0.0 } else { 0.5 })
+            } else if t.board.is_draw() {
+                Some(0.5)
+            } else {
+                None
+            };
+            if let Some(value) = terminal {
+                tree.nodes[index].terminal = Some(value);
+                value
+            } else {
+                let value = expected_score(evaluate(t, t.info.nodes.get_global()));
+                if depth < MAX_DEPTH - 1 && tree.nodes.len() + moves.len() <= MAX_TREE_NODES {
+                    tree.expand(index, &t.board, &moves);
+                }
+                value
+            }
+        };
+
+        // backpropagation: each node holds the value for the side that moved into it.
+        let mut value = 1.0 - value;
+        loop {
+            let node = &mut tree.nodes[index];
+            node.visits += 1;
+            node.total += f64::from(value);
+            if index == 0 {
+                break;
+            }
+            index = node.parent as usize;
+            t.board.unmake_move(&mut t.nnue);
+            value = 1.0 - value;
+        }
+
+        depth_sum += depth as u64;
+        iterations += 1;
+        t.info.seldepth = t
+            .info
+            .seldepth
+            .max(i32::try_from(depth).unwrap_or(i32::MAX));
+
+        let tree_full = tree.nodes.len() + usize::from(u8::MAX) > MAX_TREE_NODES;
+        if iterations.is_multiple_of(CHECK_INTERVAL) || tree_full {
+            let average_depth = usize::try_from(depth_sum / iterations)
+                .unwrap_or(MAX_DEPTH)
+                .clamp(1, MAX_DEPTH - 1);
+            if average_depth > reported_depth {
+                reported_depth = average_depth;
+                publish(t, &tree, average_depth);
+                readout_info(t, &t.info, Bound::Exact, t.info.nodes.get_global(), false);
+            }
+            if t.info.check_up()
+                || tree_full
+                || reported_depth >= max_depth
+                || t.info.clock.is_past_opt_time(t.info.nodes.get_global())
+            {
+                t.info.stopped.store(true, Ordering::SeqCst);
+                break;
+            }
+        }
+    }
+
+    t.info.nodes.flush();
+    publish(t, &tree, reported_depth.max(1));
+}
+
+/// Record the most-visited line and its value as the result of iteration `depth`.
+fn publish(t: &mut ThreadData, tree: &Tree, depth: usize) {
+    let mut index = 0;
+    t.pv_scratch[0].moves.clear();
+    while let Some(child) = tree.best_child(index) {
+        let m = tree.nodes[child].mv.expect("non-root node without a move");
+        t.pv_scratch[0].moves.push(m);
+        index = child;
+    }
+    let value = tree
+        .best_child(0)
+        .map_or(0.5, |child| tree.nodes[child].q());
+    t.iteration = depth;
+    t.score_scratch = eval_from_expected_score(value);
+    t.update_best_line();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expected_score_round_trips() {
+        for eval in [-1500, -200, 0, 35, 900] {
+            let back = eval_from_expected_score(expected_score(eval));
+            assert!((back - eval).abs() <= 1, "{eval} became {back}");
+        }
+        assert!((expected_score(0) - 0.5).abs() < f32::EPSILON);
+    }
+}
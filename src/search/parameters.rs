@@ -7,32 +7,34 @@ use crate::{
     },
     search::{
         ASPIRATION_EVAL_DIVISOR, CONT1_HISTORY, CONT1_STAT_SCORE_MUL, CONT2_HISTORY,
-        CONT2_STAT_SCORE_MUL, CONT4_HISTORY, CONT4_STAT_SCORE_MUL, CONTINUATION_12_CORRHIST_WEIGHT,
-        CONTINUATION_14_CORRHIST_WEIGHT, DELTA_BASE_MUL, DELTA_INITIAL, DELTA_REDUCTION_MUL,
-        DO_DEEPER_BASE_MARGIN, DO_DEEPER_DEPTH_MARGIN, DO_SHALLOWER_MARGIN,
-        DOUBLE_EXTENSION_MARGIN, EVAL_POLICY_IMPROVEMENT_SCALE, EVAL_POLICY_OFFSET,
-        EVAL_POLICY_UPDATE_MAX, FUTILITY_COEFF_0, FUTILITY_COEFF_1, HINDSIGHT_EXT_DEPTH,
+        CONT2_STAT_SCORE_MUL, CONT4_HISTORY, CONT4_STAT_SCORE_MUL, CONT6_HISTORY,
+        CONT6_STAT_SCORE_MUL, CONTINUATION_12_CORRHIST_WEIGHT, CONTINUATION_14_CORRHIST_WEIGHT,
+        DELTA_BASE_MUL, DELTA_INITIAL, DELTA_REDUCTION_MUL, DO_DEEPER_BASE_MARGIN,
+        DO_DEEPER_DEPTH_MARGIN, DO_SHALLOWER_MARGIN, DOUBLE_EXTENSION_MARGIN,
+        EVAL_POLICY_IMPROVEMENT_SCALE, EVAL_POLICY_OFFSET, EVAL_POLICY_UPDATE_MAX,
+        FIFTY_MOVE_DAMPING, FUTILITY_COEFF_0, FUTILITY_COEFF_1, HINDSIGHT_EXT_DEPTH,
         HINDSIGHT_RED_DEPTH, HINDSIGHT_RED_EVAL, HISTORY_LMR_DIVISOR, HISTORY_PRUNING_MARGIN,
-        LMR_ALPHA_RAISE_MUL, LMR_BASE, LMR_BASE_OFFSET, LMR_CHECK_MUL, LMR_CORR_MUL,
-        LMR_CUT_NODE_MUL, LMR_DIVISION, LMR_NON_IMPROVING_MUL, LMR_NON_PV_MUL, LMR_REFUTATION_MUL,
-        LMR_TT_CAPTURE_MUL, LMR_TTPV_FAIL_LOW_MUL, LMR_TTPV_MUL, MAIN_HISTORY, MAIN_SEE_BOUND,
-        MAIN_STAT_SCORE_MUL, MAJOR_CORRHIST_WEIGHT, MINOR_CORRHIST_WEIGHT, NMP_DEPTH_MUL,
-        NMP_IMPROVING_MARGIN, NMP_REDUCTION_EVAL_DIVISOR, NONPAWN_CORRHIST_WEIGHT,
-        OPTIMISM_MATERIAL_BASE, OPTIMISM_OFFSET, PAWN_CORRHIST_WEIGHT, PAWN_HISTORY,
-        PROBCUT_ADA_DIV, PROBCUT_ADA_OFFSET, PROBCUT_EVAL_DIV, PROBCUT_IMPROVING_MARGIN,
-        PROBCUT_MARGIN, PROBCUT_SEE_SCALE, QS_FUTILITY, QS_SEE_BOUND, RAZORING_COEFF_0,
-        RAZORING_COEFF_1, RFP_IMPROVING_MARGIN, RFP_MARGIN, SEE_QUIET_MARGIN, SEE_STAT_SCORE_MUL,
-        SEE_TACTICAL_MARGIN, TACT_STAT_SCORE_MUL, TACTICAL_HISTORY, TRIPLE_EXTENSION_MARGIN,
-        TTPV_LMR_DEPTH_MUL,
+        IID_DEPTH, IID_REDUCTION, IIR_DEPTH, IIR_PV_DEPTH, LMR_ALPHA_RAISE_MUL, LMR_BASE,
+        LMR_BASE_OFFSET, LMR_CHECK_MUL, LMR_CORR_MUL, LMR_CUT_NODE_MUL, LMR_DIVISION,
+        LMR_NON_IMPROVING_MUL, LMR_NON_PV_MUL, LMR_REFUTATION_MUL, LMR_TT_CAPTURE_MUL,
+        LMR_TTPV_FAIL_LOW_MUL, LMR_TTPV_MUL, MAIN_HISTORY, MAIN_SEE_BOUND, MAIN_STAT_SCORE_MUL,
+        MAJOR_CORRHIST_WEIGHT, MINOR_CORRHIST_WEIGHT, NMP_BASE_REDUCTION, NMP_DEPTH_MUL,
+        NMP_IMPROVING_MARGIN, NMP_REDUCTION_DEPTH_DIVISOR, NMP_REDUCTION_EVAL_DIVISOR,
+        NONPAWN_CORRHIST_WEIGHT, OPTIMISM_MATERIAL_BASE, OPTIMISM_OFFSET, PAWN_CORRHIST_WEIGHT,
+        PAWN_HISTORY, PROBCUT_ADA_DIV, PROBCUT_ADA_OFFSET, PROBCUT_EVAL_DIV,
+        PROBCUT_IMPROVING_MARGIN, PROBCUT_MARGIN, PROBCUT_SEE_SCALE, QS_FUTILITY, QS_SEE_BOUND,
+        RAZORING_COEFF_0, RAZORING_COEFF_1, RFP_IMPROVING_MARGIN, RFP_MARGIN, SEE_QUIET_MARGIN,
+        SEE_STAT_SCORE_MUL, SEE_TACTICAL_MARGIN, TACT_STAT_SCORE_MUL, TACTICAL_HISTORY,
+        THREAT_CORRHIST_WEIGHT, TRIPLE_EXTENSION_MARGIN, TTPV_LMR_DEPTH_MUL,
     },
     timemgmt::{
         DEFAULT_MOVES_TO_GO, FAIL_LOW_TM_BONUS, HARD_WINDOW_FRAC, INCREMENT_FRAC,
-        NODE_TM_SUBTREE_MULTIPLIER, OPTIMAL_WINDOW_FRAC, STRONG_FORCED_TM_FRAC,
-        WEAK_FORCED_TM_FRAC,
+        INSTABILITY_TM_BONUS, NODE_TM_SUBTREE_MULTIPLIER, OPTIMAL_WINDOW_FRAC, SCORE_SWING_TM_FRAC,
+        STRONG_FORCED_TM_FRAC, WEAK_FORCED_TM_FRAC,
     },
 };
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct HistoryConfig {
     pub bonus_mul: i32,
     pub bonus_offset: i32,
@@ -62,7 +64,7 @@ impl HistoryConfig {
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct Config {
     pub aspiration_eval_divisor: i32,
     pub delta_initial: i32,
@@ -73,6 +75,12 @@ pub struct Config {
     pub nmp_improving_margin: i32,
     pub nmp_depth_mul: i32,
     pub nmp_reduction_eval_divisor: i32,
+    pub nmp_base_reduction: i32,
+    pub nmp_reduction_depth_divisor: i32,
+    pub iir_depth: i32,
+    pub iir_pv_depth: i32,
+    pub iid_depth: i32,
+    pub iid_reduction: i32,
     pub see_quiet_margin: i32,
     pub see_tactical_margin: i32,
     pub futility_coeff_0: i32,
@@ -96,6 +104,8 @@ pub struct Config {
     pub increment_frac: u32,
     pub node_tm_subtree_multiplier: u32,
     pub fail_low_tm_bonus: u32,
+    pub instability_tm_bonus: u32,
+    pub score_swing_tm_frac: u32,
     pub history_lmr_divisor: i32,
     pub qs_see_bound: i32,
     pub main_see_bound: i32,
@@ -120,16 +130,19 @@ pub struct Config {
     pub cont1_history: HistoryConfig,
     pub cont2_history: HistoryConfig,
     pub cont4_history: HistoryConfig,
+    pub cont6_history: HistoryConfig,
     pub pawn_history: HistoryConfig,
     pub tactical_history: HistoryConfig,
     pub main_stat_score_mul: i32,
     pub cont1_stat_score_mul: i32,
     pub cont2_stat_score_mul: i32,
     pub cont4_stat_score_mul: i32,
+    pub cont6_stat_score_mul: i32,
     pub tactical_stat_score_mul: i32,
     pub pawn_corrhist_weight: i32,
     pub major_corrhist_weight: i32,
     pub minor_corrhist_weight: i32,
+    pub threat_corrhist_weight: i32,
     pub nonpawn_corrhist_weight: i32,
     pub continuation_12_corrhist_weight: i32,
     pub continuation_14_corrhist_weight: i32,
@@ -146,6 +159,7 @@ pub struct Config {
     pub hindsight_red_eval: i32,
     pub optimism_offset: i32,
     pub optimism_mat_base: i32,
+    pub fifty_move_damping: i32,
     pub eval_policy_update_max: i32,
     pub probcut_see_scale: i32,
     pub ttpv_lmr_depth_mul: i32,
@@ -163,6 +177,12 @@ impl Config {
             nmp_improving_margin: NMP_IMPROVING_MARGIN,
             nmp_depth_mul: NMP_DEPTH_MUL,
             nmp_reduction_eval_divisor: NMP_REDUCTION_EVAL_DIVISOR,
+            nmp_base_reduction: NMP_BASE_REDUCTION,
+            nmp_reduction_depth_divisor: NMP_REDUCTION_DEPTH_DIVISOR,
+            iir_depth: IIR_DEPTH,
+            iir_pv_depth: IIR_PV_DEPTH,
+            iid_depth: IID_DEPTH,
+            iid_reduction: IID_REDUCTION,
             see_quiet_margin: SEE_QUIET_MARGIN,
             see_tactical_margin: SEE_TACTICAL_MARGIN,
             futility_coeff_0: FUTILITY_COEFF_0,
@@ -186,6 +206,8 @@ impl Config {
             increment_frac: INCREMENT_FRAC,
             node_tm_subtree_multiplier: NODE_TM_SUBTREE_MULTIPLIER,
             fail_low_tm_bonus: FAIL_LOW_TM_BONUS,
+            instability_tm_bonus: INSTABILITY_TM_BONUS,
+            score_swing_tm_frac: SCORE_SWING_TM_FRAC,
             history_lmr_divisor: HISTORY_LMR_DIVISOR,
             qs_see_bound: QS_SEE_BOUND,
             main_see_bound: MAIN_SEE_BOUND,
@@ -210,16 +232,19 @@ impl Config {
             cont1_history: CONT1_HISTORY,
             cont2_history: CONT2_HISTORY,
             cont4_history: CONT4_HISTORY,
+            cont6_history: CONT6_HISTORY,
             pawn_history: PAWN_HISTORY,
             tactical_history: TACTICAL_HISTORY,
             main_stat_score_mul: MAIN_STAT_SCORE_MUL,
             cont1_stat_score_mul: CONT1_STAT_SCORE_MUL,
             cont2_stat_score_mul: CONT2_STAT_SCORE_MUL,
             cont4_stat_score_mul: CONT4_STAT_SCORE_MUL,
+            cont6_stat_score_mul: CONT6_STAT_SCORE_MUL,
             tactical_stat_score_mul: TACT_STAT_SCORE_MUL,
             pawn_corrhist_weight: PAWN_CORRHIST_WEIGHT,
             major_corrhist_weight: MAJOR_CORRHIST_WEIGHT,
             minor_corrhist_weight: MINOR_CORRHIST_WEIGHT,
+            threat_corrhist_weight: THREAT_CORRHIST_WEIGHT,
             nonpawn_corrhist_weight: NONPAWN_CORRHIST_WEIGHT,
             continuation_12_corrhist_weight: CONTINUATION_12_CORRHIST_WEIGHT,
             continuation_14_corrhist_weight: CONTINUATION_14_CORRHIST_WEIGHT,
@@ -236,11 +261,40 @@ impl Config {
             hindsight_red_eval: HINDSIGHT_RED_EVAL,
             optimism_offset: OPTIMISM_OFFSET,
             optimism_mat_base: OPTIMISM_MATERIAL_BASE,
+            fifty_move_damping: FIFTY_MOVE_DAMPING,
             eval_policy_update_max: EVAL_POLICY_UPDATE_MAX,
             probcut_see_scale: PROBCUT_SEE_SCALE,
             ttpv_lmr_depth_mul: TTPV_LMR_DEPTH_MUL,
         }
     }
+
+    /// The names of the presets accepted by [`Config::preset`].
+    pub const PRESETS: [&'static str; 3] = ["default", "aggressive", "positional"];
+
+    /// Look up a named preset, returning `None` if no preset has the given name.
+    ///
+    /// Presets are small deltas on top of the default configuration that
+    /// alter the engine's playing style, rather than its strength.
+    pub fn preset(name: &str) -> Option<Self> {
+        let base = Self::default();
+        match name {
+            "default" => Some(base),
+            // play for a win: stronger optimism, and keener to keep material on the board.
+            "aggressive" => Some(Self {
+                optimism_offset: base.optimism_offset / 2,
+                optimism_mat_base: base.optimism_mat_base * 3 / 2,
+                ..base
+            }),
+            // play it safe: weaker optimism, and less reluctance to trade down.
+            "positional" => Some(Self {
+                optimism_offset: base.optimism_offset * 2,
+                optimism_mat_base: base.optimism_mat_base * 2 / 3,
+                material_scale_base: base.material_scale_base * 5 / 4,
+                ..base
+            }),
+            _ => None,
+        }
+    }
 }
 
 impl Display for Config {
@@ -293,6 +347,12 @@ impl Config {
             NMP_IMPROVING_MARGIN = [self.nmp_improving_margin],
             NMP_DEPTH_MUL = [self.nmp_depth_mul],
             NMP_REDUCTION_EVAL_DIVISOR = [self.nmp_reduction_eval_divisor],
+            NMP_BASE_REDUCTION = [self.nmp_base_reduction],
+            NMP_REDUCTION_DEPTH_DIVISOR = [self.nmp_reduction_depth_divisor],
+            IIR_DEPTH = [self.iir_depth],
+            IIR_PV_DEPTH = [self.iir_pv_depth],
+            IID_DEPTH = [self.iid_depth],
+            IID_REDUCTION = [self.iid_reduction],
             SEE_QUIET_MARGIN = [self.see_quiet_margin],
             SEE_TACTICAL_MARGIN = [self.see_tactical_margin],
             FUTILITY_COEFF_0 = [self.futility_coeff_0],
@@ -316,6 +376,8 @@ impl Config {
             INCREMENT_FRAC = [self.increment_frac],
             NODE_TM_SUBTREE_MULTIPLIER = [self.node_tm_subtree_multiplier],
             FAIL_LOW_TM_BONUS = [self.fail_low_tm_bonus],
+            INSTABILITY_TM_BONUS = [self.instability_tm_bonus],
+            SCORE_SWING_TM_FRAC = [self.score_swing_tm_frac],
             HISTORY_LMR_DIVISOR = [self.history_lmr_divisor],
             QS_SEE_BOUND = [self.qs_see_bound],
             MAIN_SEE_BOUND = [self.main_see_bound],
@@ -360,6 +422,12 @@ impl Config {
             CONT4_HISTORY_MALUS_MUL = [self.cont4_history.malus_mul],
             CONT4_HISTORY_MALUS_OFFSET = [self.cont4_history.malus_offset],
             CONT4_HISTORY_MALUS_MAX = [self.cont4_history.malus_max],
+            CONT6_HISTORY_BONUS_MUL = [self.cont6_history.bonus_mul],
+            CONT6_HISTORY_BONUS_OFFSET = [self.cont6_history.bonus_offset],
+            CONT6_HISTORY_BONUS_MAX = [self.cont6_history.bonus_max],
+            CONT6_HISTORY_MALUS_MUL = [self.cont6_history.malus_mul],
+            CONT6_HISTORY_MALUS_OFFSET = [self.cont6_history.malus_offset],
+            CONT6_HISTORY_MALUS_MAX = [self.cont6_history.malus_max],
             PAWN_HISTORY_BONUS_MUL = [self.pawn_history.bonus_mul],
             PAWN_HISTORY_BONUS_OFFSET = [self.pawn_history.bonus_offset],
             PAWN_HISTORY_BONUS_MAX = [self.pawn_history.bonus_max],
@@ -376,10 +444,12 @@ impl Config {
             CONT1_STAT_SCORE_MUL = [self.cont1_stat_score_mul],
             CONT2_STAT_SCORE_MUL = [self.cont2_stat_score_mul],
             CONT4_STAT_SCORE_MUL = [self.cont4_stat_score_mul],
+            CONT6_STAT_SCORE_MUL = [self.cont6_stat_score_mul],
             TACT_STAT_SCORE_MUL = [self.tactical_stat_score_mul],
             PAWN_CORRHIST_WEIGHT = [self.pawn_corrhist_weight],
             MAJOR_CORRHIST_WEIGHT = [self.major_corrhist_weight],
             MINOR_CORRHIST_WEIGHT = [self.minor_corrhist_weight],
+            THREAT_CORRHIST_WEIGHT = [self.threat_corrhist_weight],
             NONPAWN_CORRHIST_WEIGHT = [self.nonpawn_corrhist_weight],
             CONTINUATION_12_CORRHIST_WEIGHT = [self.continuation_12_corrhist_weight],
             CONTINUATION_14_CORRHIST_WEIGHT = [self.continuation_14_corrhist_weight],
@@ -396,6 +466,7 @@ impl Config {
             HINDSIGHT_RED_EVAL = [self.hindsight_red_eval],
             OPTIMISM_OFFSET = [self.optimism_offset],
             OPTIMISM_MATERIAL_BASE = [self.optimism_mat_base],
+            FIFTY_MOVE_DAMPING = [self.fifty_move_damping],
             EVAL_POLICY_UPDATE_MAX = [self.eval_policy_update_max],
             PROBCUT_SEE_SCALE = [self.probcut_see_scale],
             TTPV_LMR_DEPTH_MUL = [self.ttpv_lmr_depth_mul]
@@ -423,6 +494,12 @@ impl Config {
             NMP_IMPROVING_MARGIN = [self.nmp_improving_margin, 16, 256, 10],
             NMP_DEPTH_MUL = [self.nmp_depth_mul, -128, 128, 8],
             NMP_REDUCTION_EVAL_DIVISOR = [self.nmp_reduction_eval_divisor, 32, 512, 20],
+            NMP_BASE_REDUCTION = [self.nmp_base_reduction, 1, 8, 1],
+            NMP_REDUCTION_DEPTH_DIVISOR = [self.nmp_reduction_depth_divisor, 1, 8, 1],
+            IIR_DEPTH = [self.iir_depth, 2, 16, 1],
+            IIR_PV_DEPTH = [self.iir_pv_depth, 0, 16, 1],
+            IID_DEPTH = [self.iid_depth, 0, 16, 1],
+            IID_REDUCTION = [self.iid_reduction, 1, 8, 1],
             SEE_QUIET_MARGIN = [self.see_quiet_margin, -256, -4, 5],
             SEE_TACTICAL_MARGIN = [self.see_tactical_margin, -256, -1, 3],
             FUTILITY_COEFF_0 = [self.futility_coeff_0, 8, 256, 10],
@@ -446,6 +523,8 @@ impl Config {
             INCREMENT_FRAC = [self.increment_frac, 1, 100, 10],
             NODE_TM_SUBTREE_MULTIPLIER = [self.node_tm_subtree_multiplier, 1, 1000, 15],
             FAIL_LOW_TM_BONUS = [self.fail_low_tm_bonus, 1, 1000, 30],
+            INSTABILITY_TM_BONUS = [self.instability_tm_bonus, 1, 1000, 15],
+            SCORE_SWING_TM_FRAC = [self.score_swing_tm_frac, 1, 900, 25],
             HISTORY_LMR_DIVISOR = [self.history_lmr_divisor, 1, 65536, 512],
             QS_SEE_BOUND = [self.qs_see_bound, -1024, 1024, 50],
             MAIN_SEE_BOUND = [self.main_see_bound, -1024, 1024, 50],
@@ -490,6 +569,12 @@ impl Config {
             CONT4_HISTORY_MALUS_MUL = [self.cont4_history.malus_mul, 1, 1536, 32],
             CONT4_HISTORY_MALUS_OFFSET = [self.cont4_history.malus_offset, -1024, 1024, 64],
             CONT4_HISTORY_MALUS_MAX = [self.cont4_history.malus_max, 1, 4096, 256],
+            CONT6_HISTORY_BONUS_MUL = [self.cont6_history.bonus_mul, 1, 1536, 32],
+            CONT6_HISTORY_BONUS_OFFSET = [self.cont6_history.bonus_offset, -1024, 1024, 64],
+            CONT6_HISTORY_BONUS_MAX = [self.cont6_history.bonus_max, 1, 4096, 256],
+            CONT6_HISTORY_MALUS_MUL = [self.cont6_history.malus_mul, 1, 1536, 32],
+            CONT6_HISTORY_MALUS_OFFSET = [self.cont6_history.malus_offset, -1024, 1024, 64],
+            CONT6_HISTORY_MALUS_MAX = [self.cont6_history.malus_max, 1, 4096, 256],
             PAWN_HISTORY_BONUS_MUL = [self.pawn_history.bonus_mul, 1, 1536, 32],
             PAWN_HISTORY_BONUS_OFFSET = [self.pawn_history.bonus_offset, -1024, 1024, 64],
             PAWN_HISTORY_BONUS_MAX = [self.pawn_history.bonus_max, 1, 4096, 256],
@@ -506,10 +591,12 @@ impl Config {
             CONT1_STAT_SCORE_MUL = [self.cont1_stat_score_mul, 1, 128, 8],
             CONT2_STAT_SCORE_MUL = [self.cont2_stat_score_mul, 1, 128, 8],
             CONT4_STAT_SCORE_MUL = [self.cont4_stat_score_mul, 1, 128, 8],
+            CONT6_STAT_SCORE_MUL = [self.cont6_stat_score_mul, 1, 128, 8],
             TACT_STAT_SCORE_MUL = [self.tactical_stat_score_mul, 1, 128, 8],
             PAWN_CORRHIST_WEIGHT = [self.pawn_corrhist_weight, 1, 4096, 144],
             MAJOR_CORRHIST_WEIGHT = [self.major_corrhist_weight, 1, 4096, 144],
             MINOR_CORRHIST_WEIGHT = [self.minor_corrhist_weight, 1, 4096, 144],
+            THREAT_CORRHIST_WEIGHT = [self.threat_corrhist_weight, 1, 4096, 144],
             NONPAWN_CORRHIST_WEIGHT = [self.nonpawn_corrhist_weight, 1, 4096, 144],
             CONTINUATION_12_CORRHIST_WEIGHT = [self.continuation_12_corrhist_weight, 1, 4096, 144],
             CONTINUATION_14_CORRHIST_WEIGHT = [self.continuation_14_corrhist_weight, 1, 4096, 144],
@@ -526,6 +613,7 @@ impl Config {
             HINDSIGHT_RED_EVAL = [self.hindsight_red_eval, -4096, 4096, 8],
             OPTIMISM_OFFSET = [self.optimism_offset, -4096, 4096, 16],
             OPTIMISM_MATERIAL_BASE = [self.optimism_mat_base, 1, 8192, 256],
+            FIFTY_MOVE_DAMPING = [self.fifty_move_damping, 100, 1000, 20],
             EVAL_POLICY_UPDATE_MAX = [self.eval_policy_update_max, 1, 4096, 8],
             PROBCUT_SEE_SCALE = [self.probcut_see_scale, 1, 1024, 16],
             TTPV_LMR_DEPTH_MUL = [self.ttpv_lmr_depth_mul, 1, 2048, 48]
@@ -567,6 +655,18 @@ impl Config {
 
 #[cfg(test)]
 mod tests {
+    #[test]
+    fn presets() {
+        use super::Config;
+
+        assert_eq!(Config::preset("default"), Some(Config::default()));
+        assert_eq!(Config::preset("no-such-preset"), None);
+        for name in Config::PRESETS {
+            let preset = Config::preset(name).unwrap();
+            assert_eq!(name == "default", preset == Config::default());
+        }
+    }
+
     #[test]
     fn macro_hackery_same_length() {
         let mut sp = super::Config::default();
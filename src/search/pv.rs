@@ -1,3 +1,5 @@
+//! Principal variations.
+
 use std::fmt::Display;
 
 use arrayvec::ArrayVec;
@@ -6,18 +8,31 @@ use crate::chess::board::Rules;
 use crate::chess::chessmove::Move;
 use crate::util::MAX_DEPTH;
 
+/// A principal variation: the line of best play found by a search.
 #[derive(Clone, Debug)]
 pub struct PVariation {
     pub(crate) moves: ArrayVec<Move, MAX_DEPTH>,
 }
 
+impl Default for PVariation {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl PVariation {
+    /// An empty principal variation.
     pub fn new() -> Self {
         Self {
             moves: ArrayVec::new(),
         }
     }
 
+    /// The moves of this line, starting from the root.
+    pub fn moves(&self) -> &[Move] {
+        &self.moves
+    }
+
     pub(crate) fn load_from(&mut self, m: Move, rest: &Self) {
         self.moves.clear();
         self.moves.push(m);
@@ -26,11 +41,13 @@ impl PVariation {
             .expect("attempted to construct a PV longer than MAX_PLY.");
     }
 
+    /// Display the moves of this line in UCI notation, writing castling in the form `rules` expects.
     pub const fn display(&self, rules: Rules) -> PVariationDisplay<'_> {
         PVariationDisplay { pv: self, rules }
     }
 }
 
+/// The moves of a principal variation in UCI notation, from [`PVariation::display`].
 pub struct PVariationDisplay<'a> {
     pv: &'a PVariation,
     rules: Rules,
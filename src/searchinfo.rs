@@ -1,12 +1,18 @@
-use std::sync::{
-    Mutex,
-    atomic::{AtomicBool, AtomicI32, AtomicU8, AtomicU64, AtomicUsize, Ordering},
-    mpsc,
+use std::{
+    cell::Cell,
+    sync::{
+        Mutex,
+        atomic::{AtomicBool, AtomicI32, AtomicU8, AtomicU32, AtomicU64, AtomicUsize, Ordering},
+        mpsc,
+    },
+    time::Duration,
 };
 
 use crate::{
-    search::{LMTable, parameters::Config},
-    timemgmt::TimeManager,
+    book::DEFAULT_BOOK_DEPTH,
+    search::{LMTable, SearchCallbacks, parameters::Config},
+    strength::{DEFAULT_ELO, StrengthLimit},
+    timemgmt::{DEFAULT_SLOW_MOVER, SearchLimit, TimeManager},
     util::{BatchedAtomicCounter, MAX_DEPTH},
 };
 
@@ -33,31 +39,39 @@ pub struct SearchInfo<'a> {
     pub stdin_rx: Option<&'a Mutex<mpsc::Receiver<String>>>,
     /// Whether to print the search info to stdout.
     pub print_to_stdout: bool,
+    /// Receives the search info, for searches run through [`Search`](crate::search::Search).
+    pub callbacks: Option<&'a dyn SearchCallbacks>,
+    /// When the last info line was printed, measured from the start of the search.
+    pub last_info_print: Cell<Option<Duration>>,
+    /// When the last `currmove` line was printed, measured from the start of the search.
+    pub last_currmove_print: Cell<Option<Duration>>,
+    /// The number of info lines printed during the current search.
+    pub info_lines_printed: Cell<usize>,
     /// Search parameters.
     pub conf: Config,
     /// LMR + LMP lookup table.
-    pub lm_table: LMTable,
+    pub(crate) lm_table: LMTable,
     /// The time manager.
     pub clock: TimeManager,
+    /// The strength limit for the current search, if one is set.
+    pub strength: Option<StrengthLimit>,
+    /// The command that interrupted the current search, if one did.
+    pub interrupt: Option<String>,
 
-    /* Conditionally-compiled stat trackers: */
-    /// The number of fail-highs found (beta cutoffs).
-    #[cfg(feature = "stats")]
-    pub failhigh: u64,
-    /// The number of fail-highs that occurred on a given ply.
-    #[cfg(feature = "stats")]
-    pub failhigh_index: [u64; MAX_POSITION_MOVES],
-    /// Tracks fail-highs of different types.
-    #[cfg(feature = "stats")]
-    pub failhigh_types: [u64; 8],
-    /// The number of fail-highs found in quiescence search.
+    /// Counters of search events, for tuning and debugging.
     #[cfg(feature = "stats")]
-    pub qfailhigh: u64,
-    /// The number of fail-highs that occurred on a given ply in quiescence search.
-    #[cfg(feature = "stats")]
-    pub qfailhigh_index: [u64; MAX_POSITION_MOVES],
+    pub stats: SearchStats,
 }
 
+/// The minimum time between intermediate (fail-high / fail-low) info lines.
+pub const DEFAULT_INFO_INTERVAL_MS: u64 = 50;
+/// How long a search runs before the root move being searched is reported.
+const CURRMOVE_DELAY: Duration = Duration::from_secs(3);
+/// The minimum time between `currmove` lines.
+const CURRMOVE_INTERVAL: Duration = Duration::from_millis(500);
+/// The largest number of lines that can be requested with `MultiPV`.
+pub const MAX_MULTI_PV: usize = 256;
+
 #[derive(Debug)]
 pub struct Control {
     pub quit: AtomicBool,
@@ -69,6 +83,21 @@ pub struct Control {
     pub syzygy_probe_limit: AtomicU8,
     pub syzygy_probe_depth: AtomicI32,
     pub contempt: AtomicI32,
+    pub debug: AtomicBool,
+    pub info_interval_ms: AtomicU64,
+    pub multi_pv: AtomicUsize,
+    pub limit_strength: AtomicBool,
+    pub uci_elo: AtomicU32,
+    pub own_book: AtomicBool,
+    pub book_depth: AtomicUsize,
+    pub age_hash_on_new_game: AtomicBool,
+    pub use_mcts: AtomicBool,
+    /// Nodes per millisecond of simulated clock time, or zero to use the real clock.
+    pub nodes_time: AtomicU64,
+    /// The least time, in milliseconds, to aim to spend on a move.
+    pub min_think_time: AtomicU64,
+    /// A percentage scaling of the time spent on each move.
+    pub slow_mover: AtomicU64,
 }
 
 impl Default for Control {
@@ -83,6 +112,18 @@ impl Default for Control {
             syzygy_probe_limit: AtomicU8::new(7),
             syzygy_probe_depth: AtomicI32::new(1),
             contempt: AtomicI32::new(0),
+            debug: AtomicBool::new(false),
+            info_interval_ms: AtomicU64::new(DEFAULT_INFO_INTERVAL_MS),
+            multi_pv: AtomicUsize::new(1),
+            limit_strength: AtomicBool::new(false),
+            uci_elo: AtomicU32::new(DEFAULT_ELO),
+            own_book: AtomicBool::new(false),
+            book_depth: AtomicUsize::new(DEFAULT_BOOK_DEPTH),
+            age_hash_on_new_game: AtomicBool::new(false),
+            use_mcts: AtomicBool::new(false),
+            nodes_time: AtomicU64::new(0),
+            min_think_time: AtomicU64::new(0),
+            slow_mover: AtomicU64::new(DEFAULT_SLOW_MOVER),
         }
     }
 }
@@ -104,19 +145,17 @@ impl<'a> SearchInfo<'a> {
             seldepth: 0,
             stdin_rx: None,
             print_to_stdout: true,
+            callbacks: None,
+            last_info_print: Cell::new(None),
+            last_currmove_print: Cell::new(None),
+            info_lines_printed: Cell::new(0),
             conf: Config::default(),
             lm_table: LMTable::new(&Config::default()),
             clock: TimeManager::default(),
+            strength: None,
+            interrupt: None,
             #[cfg(feature = "stats")]
-            failhigh: 0,
-            #[cfg(feature = "stats")]
-            failhigh_index: [0; MAX_POSITION_MOVES],
-            #[cfg(feature = "stats")]
-            failhigh_types: [0; 8],
-            #[cfg(feature = "stats")]
-            qfailhigh: 0,
-            #[cfg(feature = "stats")]
-            qfailhigh_index: [0; MAX_POSITION_MOVES],
+            stats: SearchStats::new(),
         };
         assert!(!out.stopped.load(Ordering::SeqCst));
         out
@@ -129,14 +168,21 @@ impl<'a> SearchInfo<'a> {
         for rmnc in self.root_move_nodes.iter_mut().flatten() {
             *rmnc = 0;
         }
+        self.clock
+            .set_nodes_time(self.control.nodes_time.load(Ordering::Relaxed));
+        self.clock.set_time_usage(
+            self.control.min_think_time.load(Ordering::Relaxed),
+            self.control.slow_mover.load(Ordering::Relaxed),
+        );
         self.clock.reset_for_id(&self.conf);
+        self.strength = StrengthLimit::from_control(self.control);
+        self.interrupt = None;
+        self.last_info_print.set(None);
+        self.last_currmove_print.set(None);
+        self.info_lines_printed.set(0);
         #[cfg(feature = "stats")]
         {
-            self.failhigh = 0;
-            self.failhigh_index = [0; MAX_POSITION_MOVES];
-            self.failhigh_types = [0; 8];
-            self.qfailhigh = 0;
-            self.qfailhigh_index = [0; MAX_POSITION_MOVES];
+            self.stats = SearchStats::new();
         }
     }
 
@@ -149,31 +195,99 @@ impl<'a> SearchInfo<'a> {
         if already_stopped {
             return true;
         }
-        let res = self.clock.check_up(self.stopped, self.nodes.get_global());
-        if let Some(Ok(cmd)) = self.stdin_rx.map(|m| m.lock().unwrap().try_recv()) {
-            let cmd = cmd.trim();
-            if cmd == "ponderhit" {
-                println!("info string limit was {:?}", self.clock.limit());
-                let unpondering_limit = self.clock.limit().clone().from_pondering();
-                println!("info string unpondering limit is {unpondering_limit:?}");
-                self.clock.set_limit(unpondering_limit);
-                self.clock.start();
-                return self.clock.check_up(self.stopped, self.nodes.get_global());
-            }
+        let nodes = self.nodes.get_global();
+        let past_strength_limit = self.strength.is_some_and(|s| nodes >= s.node_limit());
+        if past_strength_limit {
             self.stopped.store(true, Ordering::SeqCst);
-            if cmd == "quit" {
-                self.control.quit.store(true, Ordering::SeqCst);
-            }
-            true
+        }
+        let res = self.clock.check_up(self.stopped, nodes) || past_strength_limit;
+        if let Some(Ok(cmd)) = self.stdin_rx.map(|m| m.lock().unwrap().try_recv()) {
+            self.handle_command(cmd.trim())
         } else {
             res
         }
     }
 
+    /// React to a command received mid-search. Returns true if the search should stop.
+    fn handle_command(&mut self, cmd: &str) -> bool {
+        if cmd == "ponderhit" {
+            println!("info string limit was {:?}", self.clock.limit());
+            let unpondering_limit = self.clock.limit().clone().from_pondering();
+            println!("info string unpondering limit is {unpondering_limit:?}");
+            self.clock.set_limit(unpondering_limit);
+            // our clock only starts running once the ponder move is played.
+            self.clock.start();
+            return self.clock.check_up(self.stopped, self.nodes.get_global());
+        }
+        self.stopped.store(true, Ordering::SeqCst);
+        if cmd == "quit" {
+            self.control.quit.store(true, Ordering::SeqCst);
+        }
+        self.interrupt = Some(cmd.to_string());
+        true
+    }
+
+    /// If a ponder search has finished of its own accord, block until the GUI
+    /// ends it with `ponderhit` or `stop`, as a `bestmove` must not be sent
+    /// while pondering.
+    pub fn wait_for_ponder_end(&mut self) {
+        if !matches!(self.clock.limit(), SearchLimit::Pondering { .. })
+            || self.stopped.load(Ordering::SeqCst)
+        {
+            return;
+        }
+        let Some(stdin_rx) = self.stdin_rx else {
+            return;
+        };
+        let cmd = stdin_rx.lock().unwrap().recv();
+        match cmd {
+            Ok(cmd) => {
+                self.handle_command(cmd.trim());
+            }
+            // stdin has closed, so nobody is left to wait for.
+            Err(_) => self.stopped.store(true, Ordering::SeqCst),
+        }
+    }
+
     pub fn skip_print(&self) -> bool {
         self.clock.is_dynamic() && self.clock.time_since_start().as_millis() < 50
     }
 
+    /// Should an intermediate info line be suppressed, because another one was printed too recently?
+    pub fn throttle_print(&self) -> bool {
+        let interval = Duration::from_millis(self.control.info_interval_ms.load(Ordering::Relaxed));
+        self.last_info_print
+            .get()
+            .is_some_and(|last| self.clock.elapsed().saturating_sub(last) < interval)
+    }
+
+    /// Should the root move about to be searched be reported with `info currmove`?
+    /// This is only done in UCI mode, once the search has been running for a while,
+    /// and no more often than [`CURRMOVE_INTERVAL`]. If it returns true, the print is recorded.
+    pub fn report_currmove(&self) -> bool {
+        if !self.print_to_stdout || self.control.pretty_print.load(Ordering::Relaxed) {
+            return false;
+        }
+        let elapsed = self.clock.elapsed();
+        if elapsed < CURRMOVE_DELAY
+            || self
+                .last_currmove_print
+                .get()
+                .is_some_and(|last| elapsed.saturating_sub(last) < CURRMOVE_INTERVAL)
+        {
+            return false;
+        }
+        self.last_currmove_print.set(Some(elapsed));
+        true
+    }
+
+    /// Record that an info line has just been printed.
+    pub fn mark_printed(&self) {
+        self.last_info_print.set(Some(self.clock.elapsed()));
+        self.info_lines_printed
+            .set(self.info_lines_printed.get() + 1);
+    }
+
     pub fn stopped(&self) -> bool {
         self.stopped.load(Ordering::SeqCst)
     }
@@ -181,16 +295,107 @@ impl<'a> SearchInfo<'a> {
     #[cfg(feature = "stats")]
     pub fn log_fail_high<const QSEARCH: bool>(&mut self, move_index: usize) {
         if QSEARCH {
-            self.qfailhigh += 1;
-            self.qfailhigh_index[move_index] += 1;
+            self.stats.qfailhigh += 1;
+            self.stats.qfailhigh_index[move_index] += 1;
         } else {
-            self.failhigh += 1;
-            self.failhigh_index[move_index] += 1;
+            self.stats.failhigh += 1;
+            self.stats.failhigh_index[move_index] += 1;
         }
     }
 
     #[cfg(feature = "stats")]
-    pub fn print_stats(&self) {
+    pub fn log_tt_probe(&mut self, hit: bool) {
+        self.stats.tt_probes += 1;
+        self.stats.tt_hits += u64::from(hit);
+    }
+
+    #[cfg(feature = "stats")]
+    pub fn log_null_move(&mut self, cutoff: bool) {
+        self.stats.nmp_attempts += 1;
+        self.stats.nmp_cutoffs += u64::from(cutoff);
+    }
+}
+
+/// Counters of search events, kept by each thread in builds with the `stats` feature.
+#[cfg(feature = "stats")]
+#[derive(Clone, Debug)]
+pub struct SearchStats {
+    /// The number of fail-highs found (beta cutoffs).
+    pub failhigh: u64,
+    /// The number of fail-highs that occurred on a given ply.
+    pub failhigh_index: [u64; MAX_POSITION_MOVES],
+    /// Tracks fail-highs of different types.
+    pub failhigh_types: [u64; 8],
+    /// The number of fail-highs found in quiescence search.
+    pub qfailhigh: u64,
+    /// The number of fail-highs that occurred on a given ply in quiescence search.
+    pub qfailhigh_index: [u64; MAX_POSITION_MOVES],
+    /// The number of cache probes made in the main search.
+    pub tt_probes: u64,
+    /// The number of those probes that found an entry.
+    pub tt_hits: u64,
+    /// The number of null-move searches made.
+    pub nmp_attempts: u64,
+    /// The number of null-move searches that failed high.
+    pub nmp_cutoffs: u64,
+}
+
+#[cfg(feature = "stats")]
+impl SearchStats {
+    pub const fn new() -> Self {
+        Self {
+            failhigh: 0,
+            failhigh_index: [0; MAX_POSITION_MOVES],
+            failhigh_types: [0; 8],
+            qfailhigh: 0,
+            qfailhigh_index: [0; MAX_POSITION_MOVES],
+            tt_probes: 0,
+            tt_hits: 0,
+            nmp_attempts: 0,
+            nmp_cutoffs: 0,
+        }
+    }
+
+    /// The statistics of several threads, added together.
+    pub fn sum<'b>(stats: impl IntoIterator<Item = &'b Self>) -> Self {
+        let add = |total: &mut [u64], part: &[u64]| {
+            for (t, p) in total.iter_mut().zip(part) {
+                *t += p;
+            }
+        };
+        let mut total = Self::new();
+        for s in stats {
+            total.failhigh += s.failhigh;
+            add(&mut total.failhigh_index, &s.failhigh_index);
+            add(&mut total.failhigh_types, &s.failhigh_types);
+            total.qfailhigh += s.qfailhigh;
+            add(&mut total.qfailhigh_index, &s.qfailhigh_index);
+            total.tt_probes += s.tt_probes;
+            total.tt_hits += s.tt_hits;
+            total.nmp_attempts += s.nmp_attempts;
+            total.nmp_cutoffs += s.nmp_cutoffs;
+        }
+        total
+    }
+
+    /// Print the statistics.
+    pub fn print(&self) {
+        #[allow(clippy::cast_precision_loss)]
+        let percentage = |part: u64, whole: u64| part as f64 * 100.0 / whole.max(1) as f64;
+        println!(
+            "beta cutoffs {} in search, {} in quiescence",
+            self.failhigh, self.qfailhigh
+        );
+        println!(
+            "cache hits {:5.2}% of {} probes",
+            percentage(self.tt_hits, self.tt_probes),
+            self.tt_probes
+        );
+        println!(
+            "null-move cutoffs {:5.2}% of {} attempts",
+            percentage(self.nmp_cutoffs, self.nmp_attempts),
+            self.nmp_attempts
+        );
         #[allow(clippy::cast_precision_loss)]
         let fail_high_percentages = self
             .failhigh_index
@@ -234,19 +439,15 @@ mod tests {
     use super::Control;
     use std::{
         array,
-        sync::atomic::{AtomicBool, AtomicU64},
+        sync::atomic::{AtomicBool, AtomicU64, Ordering},
     };
 
     use crate::{
         chess::board::Board,
         evaluation::{mate_in, mated_in},
-        nnue::network::NNUEParams,
         search::search_position,
-        threadlocal::ThreadData,
-        threadpool,
+        threadlocal::ThreadFixture,
         timemgmt::{SearchLimit, TimeManager},
-        transpositiontable::Cache,
-        util::MEGABYTE,
     };
 
     #[cfg(test)] // while running tests, we don't want multiple concurrent searches
@@ -259,26 +460,10 @@ mod tests {
 
         let position =
             Board::from_fen("r1b2bkr/ppp3pp/2n5/3qp3/2B5/8/PPPP1PPP/RNB1K2R w KQ - 0 9").unwrap();
-        let stopped = AtomicBool::new(false);
-        let nodes = AtomicU64::new(0);
-        let tbhits = AtomicU64::new(0);
-        let control = Control::default();
-        let pool = threadpool::make_worker_threads(1);
-        let mut cache = Cache::new();
-        cache.resize(MEGABYTE, &pool);
-        let nnue_params = NNUEParams::decompress_and_alloc().unwrap();
-        let mut t = Box::new(ThreadData::new(
-            0,
-            position,
-            cache.view(),
-            nnue_params,
-            &stopped,
-            &nodes,
-            &tbhits,
-            &control,
-        ));
+        let fixture = ThreadFixture::new();
+        let mut t = fixture.thread_data(position);
         t.info.clock = TimeManager::default_with_limit(SearchLimit::mate_in(2));
-        let (value, mov) = search_position(&pool, array::from_mut(&mut t));
+        let (value, mov) = search_position(&fixture.pool, array::from_mut(&mut t));
 
         assert!(matches!(
             t.board
@@ -289,6 +474,27 @@ mod tests {
             Some("Bxd5+")
         ));
         assert_eq!(value, mate_in(3)); // 3 ply because we're mating.
+        // the search stops as soon as the mate is proven, rather than running on.
+        assert!(t.completed <= 4, "search ran to depth {}", t.completed);
+
+        drop(guard);
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)] // too slow.
+    fn mcts_finds_mate_in_1() {
+        let guard = TEST_LOCK.lock().unwrap();
+
+        let position = Board::from_fen("6k1/5ppp/8/8/8/8/5PPP/R5K1 w - - 0 1").unwrap();
+        let fixture = ThreadFixture::new();
+        fixture.control.use_mcts.store(true, Ordering::Relaxed);
+        let mut t = fixture.thread_data(position);
+        t.info.clock = TimeManager::default_with_limit(SearchLimit::Nodes(20_000));
+        let (value, mov) = search_position(&fixture.pool, array::from_mut(&mut t));
+
+        assert_eq!(mov, t.board.parse_uci("a1a8").ok());
+        assert!(value > 1000, "mate scored as {value}");
+        assert!(fixture.nodes.load(Ordering::Relaxed) < 30_000);
 
         drop(guard);
     }
@@ -300,26 +506,10 @@ mod tests {
 
         let position =
             Board::from_fen("r1bq1bkr/ppp3pp/2n5/3Qp3/2B5/8/PPPP1PPP/RNB1K2R b KQ - 0 8").unwrap();
-        let stopped = AtomicBool::new(false);
-        let nodes = AtomicU64::new(0);
-        let tbhits = AtomicU64::new(0);
-        let control = Control::default();
-        let pool = threadpool::make_worker_threads(1);
-        let mut cache = Cache::new();
-        cache.resize(MEGABYTE, &pool);
-        let nnue_params = NNUEParams::decompress_and_alloc().unwrap();
-        let mut t = Box::new(ThreadData::new(
-            0,
-            position,
-            cache.view(),
-            nnue_params,
-            &stopped,
-            &nodes,
-            &tbhits,
-            &control,
-        ));
+        let fixture = ThreadFixture::new();
+        let mut t = fixture.thread_data(position);
         t.info.clock = TimeManager::default_with_limit(SearchLimit::mate_in(2));
-        let (value, mov) = search_position(&pool, array::from_mut(&mut t));
+        let (value, mov) = search_position(&fixture.pool, array::from_mut(&mut t));
 
         assert!(matches!(
             t.board
@@ -341,26 +531,10 @@ mod tests {
 
         let position =
             Board::from_fen("rnb1k2r/pppp1ppp/8/2b5/3qP3/P1N5/1PP3PP/R1BQ1BKR w kq - 0 9").unwrap();
-        let stopped = AtomicBool::new(false);
-        let nodes = AtomicU64::new(0);
-        let tbhits = AtomicU64::new(0);
-        let control = Control::default();
-        let pool = threadpool::make_worker_threads(1);
-        let mut cache = Cache::new();
-        cache.resize(MEGABYTE, &pool);
-        let nnue_params = NNUEParams::decompress_and_alloc().unwrap();
-        let mut t = Box::new(ThreadData::new(
-            0,
-            position,
-            cache.view(),
-            nnue_params,
-            &stopped,
-            &nodes,
-            &tbhits,
-            &control,
-        ));
+        let fixture = ThreadFixture::new();
+        let mut t = fixture.thread_data(position);
         t.info.clock = TimeManager::default_with_limit(SearchLimit::mate_in(2));
-        let (value, mov) = search_position(&pool, array::from_mut(&mut t));
+        let (value, mov) = search_position(&fixture.pool, array::from_mut(&mut t));
 
         assert!(matches!(
             t.board
@@ -382,26 +556,10 @@ mod tests {
 
         let position =
             Board::from_fen("rnb1k2r/pppp1ppp/8/2b5/3QP3/P1N5/1PP3PP/R1B2BKR b kq - 0 9").unwrap();
-        let stopped = AtomicBool::new(false);
-        let nodes = AtomicU64::new(0);
-        let tbhits = AtomicU64::new(0);
-        let control = Control::default();
-        let pool = threadpool::make_worker_threads(1);
-        let mut cache = Cache::new();
-        cache.resize(MEGABYTE, &pool);
-        let nnue_params = NNUEParams::decompress_and_alloc().unwrap();
-        let mut t = Box::new(ThreadData::new(
-            0,
-            position,
-            cache.view(),
-            nnue_params,
-            &stopped,
-            &nodes,
-            &tbhits,
-            &control,
-        ));
+        let fixture = ThreadFixture::new();
+        let mut t = fixture.thread_data(position);
         t.info.clock = TimeManager::default_with_limit(SearchLimit::mate_in(2));
-        let (value, mov) = search_position(&pool, array::from_mut(&mut t));
+        let (value, mov) = search_position(&fixture.pool, array::from_mut(&mut t));
 
         assert!(matches!(
             t.board
@@ -415,4 +573,145 @@ mod tests {
 
         drop(guard);
     }
+
+    #[test]
+    #[cfg_attr(miri, ignore)] // too slow.
+    fn info_lines_are_throttled() {
+        let guard = TEST_LOCK.lock().unwrap();
+
+        let position = Board::startpos();
+        let fixture = ThreadFixture::new();
+        // long enough that no aspiration failures can be reported.
+        fixture
+            .control
+            .info_interval_ms
+            .store(1_000_000, std::sync::atomic::Ordering::SeqCst);
+        let mut t = fixture.thread_data(position);
+        t.info.clock = TimeManager::default_with_limit(SearchLimit::Nodes(50_000));
+        search_position(&fixture.pool, array::from_mut(&mut t));
+
+        // one line per completed iteration, plus the final report.
+        assert!(t.completed > 1);
+        assert!(t.info.info_lines_printed.get() <= t.completed + 1);
+
+        drop(guard);
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)] // too slow.
+    fn multi_pv_reports_distinct_lines() {
+        let guard = TEST_LOCK.lock().unwrap();
+
+        for (fen, requested, expected) in [
+            (
+                "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+                3,
+                2,
+            ),
+            // fewer legal moves than requested lines.
+            ("7k/8/8/8/8/8/6q1/7K w - - 0 1", 5, 0),
+            ("6rk/8/8/8/8/8/P7/7K w - - 0 1", 5, 2),
+        ] {
+            let position = Board::from_fen(fen).unwrap();
+            let legal = position.legal_moves().len();
+            let fixture = ThreadFixture::new();
+            fixture
+                .control
+                .multi_pv
+                .store(requested, std::sync::atomic::Ordering::SeqCst);
+            let mut t = fixture.thread_data(position);
+            t.info.clock = TimeManager::default_with_limit(SearchLimit::Depth(6));
+            let (_, best) = search_position(&fixture.pool, array::from_mut(&mut t));
+
+            assert_eq!(t.multi_pv_lines.len(), expected, "{fen} ({legal} legal)");
+            let mut firsts = vec![best.unwrap()];
+            for (_, pv) in &t.multi_pv_lines {
+                let m = pv.moves[0];
+                assert!(!firsts.contains(&m), "{fen}: repeated root move");
+                firsts.push(m);
+            }
+            assert!(t.root_excluded.is_empty());
+        }
+
+        drop(guard);
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)] // too slow.
+    fn searchmoves_restrict_the_root() {
+        let guard = TEST_LOCK.lock().unwrap();
+
+        let position = Board::startpos();
+        let allowed = vec![
+            position.parse_uci("a2a3").unwrap(),
+            position.parse_uci("h2h3").unwrap(),
+        ];
+        let fixture = ThreadFixture::new();
+        fixture
+            .control
+            .multi_pv
+            .store(4, std::sync::atomic::Ordering::SeqCst);
+        let mut t = fixture.thread_data(position);
+        t.searchmoves.clone_from(&allowed);
+        t.info.clock = TimeManager::default_with_limit(SearchLimit::Depth(5));
+        let (_, best) = search_position(&fixture.pool, array::from_mut(&mut t));
+
+        assert!(allowed.contains(&best.unwrap()));
+        assert_eq!(t.multi_pv_lines.len(), 1);
+        assert!(allowed.contains(&t.multi_pv_lines[0].1.moves[0]));
+
+        drop(guard);
+    }
+
+    #[test]
+    fn ponder_waits_for_the_gui() {
+        use super::SearchInfo;
+        use std::sync::{Mutex, atomic::Ordering, mpsc};
+
+        let stopped = AtomicBool::new(false);
+        let nodes = AtomicU64::new(0);
+        let tbhits = AtomicU64::new(0);
+        let control = Control::default();
+        let (tx, rx) = mpsc::channel();
+        let rx = Mutex::new(rx);
+        let mut info = SearchInfo::new(&stopped, &nodes, &tbhits, &control);
+        info.set_stdin(&rx);
+
+        // not pondering: nothing is consumed.
+        info.clock.set_limit(SearchLimit::Depth(5));
+        tx.send("stop".to_string()).unwrap();
+        info.wait_for_ponder_end();
+        assert!(!stopped.load(Ordering::SeqCst));
+        assert_eq!(rx.lock().unwrap().try_recv().as_deref(), Ok("stop"));
+
+        // ponderhit turns the search back into a normal one.
+        info.clock.set_limit(SearchLimit::Depth(5).to_pondering());
+        tx.send("ponderhit".to_string()).unwrap();
+        info.wait_for_ponder_end();
+        assert_eq!(info.clock.limit(), &SearchLimit::Depth(5));
+        assert!(!stopped.load(Ordering::SeqCst));
+
+        // stop ends the ponder outright.
+        info.clock.set_limit(SearchLimit::Depth(5).to_pondering());
+        tx.send("stop".to_string()).unwrap();
+        info.wait_for_ponder_end();
+        assert!(stopped.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)] // too slow.
+    fn seldepth_reaches_the_search_depth() {
+        let guard = TEST_LOCK.lock().unwrap();
+
+        let fixture = ThreadFixture::new();
+        let mut t = fixture.thread_data(Board::startpos());
+        t.info.clock = TimeManager::default_with_limit(SearchLimit::Depth(6));
+        search_position(&fixture.pool, array::from_mut(&mut t));
+
+        assert_eq!(t.completed, 6);
+        // extensions and quiescence can only take the deepest line further.
+        assert!(t.info.seldepth >= 6, "seldepth was {}", t.info.seldepth);
+
+        drop(guard);
+    }
 }
@@ -14,6 +14,6 @@ pub struct StackFrame {
     pub searching_tactical: bool,
     pub dextensions: i32,
     pub ttpv: bool,
-    pub ch_idx: ContHistIndex,
+    pub(crate) ch_idx: ContHistIndex,
     pub reduction: i32,
 }
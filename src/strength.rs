@@ -0,0 +1,137 @@
+//! Strength limiting, for playing at an approximate Elo target.
+//!
+//! The engine is weakened in two ways: the search is capped in nodes and depth,
+//! and the final move is picked from the best few root lines after perturbing
+//! their scores with random noise, so that weaker settings sometimes play a
+//! plausible but inferior move. The noise is applied at the root rather than
+//! inside the evaluation, which keeps the transposition table consistent.
+//!
+//! The calibration is approximate: the Elo range is mapped onto the limits
+//! geometrically, which roughly matches how playing strength scales with search effort.
+
+use crate::{rng::XorShiftState, searchinfo::Control};
+
+/// The weakest strength that can be requested with `UCI_Elo`.
+pub const MIN_ELO: u32 = 1320;
+/// The strongest strength that can be requested with `UCI_Elo`.
+pub const MAX_ELO: u32 = 3000;
+/// The default value of the `UCI_Elo` option.
+pub const DEFAULT_ELO: u32 = 1500;
+
+/// Node budget at the weakest setting.
+const MIN_NODES: f64 = 1_000.0;
+/// Node budget at the strongest setting.
+const MAX_NODES: f64 = 2_000_000.0;
+/// Depth limit at the weakest setting.
+const MIN_DEPTH: f64 = 1.0;
+/// Depth limit at the strongest setting.
+const MAX_DEPTH: f64 = 20.0;
+/// Largest root-score perturbation, in centipawns, at the weakest setting.
+const MAX_NOISE: f64 = 250.0;
+
+/// The limits on search and move selection for a given Elo target.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StrengthLimit {
+    elo: u32,
+}
+
+impl StrengthLimit {
+    /// The number of root lines searched, from which the played move is chosen.
+    pub const CANDIDATES: usize = 4;
+
+    /// Create a limit for `elo`, clamped into the supported range.
+    pub fn new(elo: u32) -> Self {
+        Self {
+            elo: elo.clamp(MIN_ELO, MAX_ELO),
+        }
+    }
+
+    /// The limit requested over UCI, if `UCI_LimitStrength` is enabled.
+    pub fn from_control(control: &Control) -> Option<Self> {
+        use std::sync::atomic::Ordering;
+        control
+            .limit_strength
+            .load(Ordering::Relaxed)
+            .then(|| Self::new(control.uci_elo.load(Ordering::Relaxed)))
+    }
+
+    /// How strong this setting is, from 0.0 at [`MIN_ELO`] to 1.0 at [`MAX_ELO`].
+    fn skill(self) -> f64 {
+        f64::from(self.elo - MIN_ELO) / f64::from(MAX_ELO - MIN_ELO)
+    }
+
+    /// The most nodes a search may use.
+    pub fn node_limit(self) -> u64 {
+        #![allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        (MIN_NODES * (MAX_NODES / MIN_NODES).powf(self.skill())) as u64
+    }
+
+    /// The deepest iteration a search may complete.
+    pub fn depth_limit(self) -> usize {
+        #![allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        MAX_DEPTH
+            .mul_add(self.skill(), MIN_DEPTH * (1.0 - self.skill()))
+            .round() as usize
+    }
+
+    /// The largest amount, in centipawns, by which a root score may be perturbed.
+    pub fn noise(self) -> i32 {
+        #![allow(clippy::cast_possible_truncation)]
+        (MAX_NOISE * (1.0 - self.skill()).powi(2)).round() as i32
+    }
+
+    /// Pick the index of the line to play from `scores`, the scores of the root lines
+    /// in best-first order. Each score is perturbed by up to [`Self::noise`], and the
+    /// line with the best perturbed score wins.
+    pub fn choose(self, scores: &[i32], rng: &mut XorShiftState) -> usize {
+        let noise = self.noise();
+        let spread = u64::from(noise.unsigned_abs()) * 2 + 1;
+        scores
+            .iter()
+            .enumerate()
+            .map(|(i, &score)| {
+                #[allow(clippy::cast_possible_truncation, clippy::cast_possible_wrap)]
+                let perturbation = rng.next_below(spread) as i32 - noise;
+                (score.saturating_add(perturbation), i)
+            })
+            // ties go to the earlier, better-searched line.
+            .max_by_key(|&(score, i)| (score, std::cmp::Reverse(i)))
+            .map_or(0, |(_, i)| i)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn limits_scale_with_elo() {
+        let weak = StrengthLimit::new(MIN_ELO);
+        let strong = StrengthLimit::new(MAX_ELO);
+        assert_eq!(StrengthLimit::new(0), weak);
+        assert_eq!(StrengthLimit::new(u32::MAX), strong);
+        assert!(weak.node_limit() < StrengthLimit::new(DEFAULT_ELO).node_limit());
+        assert!(StrengthLimit::new(DEFAULT_ELO).node_limit() < strong.node_limit());
+        assert_eq!(weak.depth_limit(), 1);
+        assert_eq!(strong.depth_limit(), 20);
+        assert_eq!(strong.noise(), 0);
+        assert!(weak.noise() > StrengthLimit::new(2000).noise());
+    }
+
+    #[test]
+    fn choice_follows_noise() {
+        let scores = [50, 30, -400, -900];
+        let mut rng = XorShiftState::with_seed(7);
+        // with no noise, the best line is always played.
+        let strong = StrengthLimit::new(MAX_ELO);
+        assert!((0..100).all(|_| strong.choose(&scores, &mut rng) == 0));
+        // with lots of noise, close alternatives get played, but blunders don't.
+        let weak = StrengthLimit::new(MIN_ELO);
+        let picks = (0..1000)
+            .map(|_| weak.choose(&scores, &mut rng))
+            .collect::<Vec<_>>();
+        assert!(picks.contains(&0));
+        assert!(picks.contains(&1));
+        assert!(!picks.contains(&3));
+    }
+}
@@ -17,10 +17,11 @@ use crate::{
     tablebases::bindings::{
         PYRRHIC_FLAG_BPROMO as TB_PROMOTES_BISHOP, PYRRHIC_FLAG_NPROMO as TB_PROMOTES_KNIGHT,
         PYRRHIC_FLAG_QPROMO as TB_PROMOTES_QUEEN, PYRRHIC_FLAG_RPROMO as TB_PROMOTES_ROOK,
-        TB_BLESSED_LOSS, TB_CURSED_WIN, TB_DRAW, TB_LARGEST, TB_LOSS, TB_RESULT_DTZ_MASK,
-        TB_RESULT_DTZ_SHIFT, TB_RESULT_FAILED, TB_RESULT_FROM_MASK, TB_RESULT_FROM_SHIFT,
-        TB_RESULT_PROMOTES_MASK, TB_RESULT_PROMOTES_SHIFT, TB_RESULT_TO_MASK, TB_RESULT_TO_SHIFT,
-        TB_RESULT_WDL_MASK, TB_RESULT_WDL_SHIFT, TB_WIN, tb_init, tb_probe_root, tb_probe_wdl,
+        TB_BLESSED_LOSS, TB_CURSED_WIN, TB_DRAW, TB_LARGEST, TB_LOSS, TB_MAX_MOVES,
+        TB_RESULT_DTZ_MASK, TB_RESULT_DTZ_SHIFT, TB_RESULT_FAILED, TB_RESULT_FROM_MASK,
+        TB_RESULT_FROM_SHIFT, TB_RESULT_PROMOTES_MASK, TB_RESULT_PROMOTES_SHIFT, TB_RESULT_TO_MASK,
+        TB_RESULT_TO_SHIFT, TB_RESULT_WDL_MASK, TB_RESULT_WDL_SHIFT, TB_WIN, tb_init,
+        tb_probe_root, tb_probe_wdl,
     },
 };
 use std::ffi::CString;
@@ -148,33 +149,92 @@ pub fn get_root_wdl_dtz(board: &Board) -> Option<WdlDtzResult> {
         let mut moves = MoveList::new();
         board.generate_moves(&mut moves);
 
-        let from =
-            Square::new(((result & TB_RESULT_FROM_MASK) >> TB_RESULT_FROM_SHIFT) as u8).unwrap();
-        let to = Square::new(((result & TB_RESULT_TO_MASK) >> TB_RESULT_TO_SHIFT) as u8).unwrap();
-        let promotion = (result & TB_RESULT_PROMOTES_MASK) >> TB_RESULT_PROMOTES_SHIFT;
+        find_result_move(&moves, result).map(|best_move| WdlDtzResult {
+            wdl,
+            dtz,
+            best_move,
+        })
+    }
+    #[cfg(not(feature = "syzygy"))]
+    None
+}
 
-        let promo_piece_type = match promotion {
-            TB_PROMOTES_QUEEN => Some(PieceType::Queen),
-            TB_PROMOTES_ROOK => Some(PieceType::Rook),
-            TB_PROMOTES_BISHOP => Some(PieceType::Bishop),
-            TB_PROMOTES_KNIGHT => Some(PieceType::Knight),
-            _ => None,
-        };
+/// Finds the move in `moves` that is encoded in a root probe `result`.
+fn find_result_move(moves: &MoveList, result: u32) -> Option<Move> {
+    #![allow(clippy::cast_possible_truncation)]
+    let from = Square::new(((result & TB_RESULT_FROM_MASK) >> TB_RESULT_FROM_SHIFT) as u8)?;
+    let to = Square::new(((result & TB_RESULT_TO_MASK) >> TB_RESULT_TO_SHIFT) as u8)?;
+    let promotion = (result & TB_RESULT_PROMOTES_MASK) >> TB_RESULT_PROMOTES_SHIFT;
+
+    let promo_piece_type = match promotion {
+        TB_PROMOTES_QUEEN => Some(PieceType::Queen),
+        TB_PROMOTES_ROOK => Some(PieceType::Rook),
+        TB_PROMOTES_BISHOP => Some(PieceType::Bishop),
+        TB_PROMOTES_KNIGHT => Some(PieceType::Knight),
+        _ => None,
+    };
+
+    moves.iter_moves().copied().find(|m| {
+        m.from() == from
+            && m.to() == to
+            && (promotion == 0 || m.promotion_type() == promo_piece_type)
+    })
+}
+
+/// Gets the root moves that keep the best tablebase result available in the position
+/// specified in `board`, taking the fifty-move rule into account: a win that can't be
+/// converted before the counter runs out counts for less than one that can.
+/// Restricting the root search to these moves lets it convert won endings correctly.
+/// Returns [None] if data couldn't be obtained, the position has too many pieces,
+/// or the feature is disabled.
+pub fn get_root_moves_preserving_wdl(board: &Board, control: &Control) -> Option<Vec<Move>> {
+    if board.state.bbs.occupied().count() > u32::from(get_max_pieces_count(control)) {
+        return None;
+    }
 
-        for &m in moves.iter_moves() {
-            if m.from() == from
-                && m.to() == to
-                && (promotion == 0 || m.promotion_type() == promo_piece_type)
-            {
-                return Some(WdlDtzResult {
-                    wdl,
-                    dtz,
-                    best_move: m,
-                });
-            }
+    // SAFETY: Not much.
+    #[cfg(feature = "syzygy")]
+    unsafe {
+        let b = &board.state.bbs;
+        let ep = board.ep_sq().map_or(0, |sq| sq as u32);
+        let mut results = [TB_RESULT_FAILED; TB_MAX_MOVES as usize];
+        let result = tb_probe_root(
+            b.colours[Colour::White].inner(),
+            b.colours[Colour::Black].inner(),
+            b.pieces[PieceType::King].inner(),
+            b.pieces[PieceType::Queen].inner(),
+            b.pieces[PieceType::Rook].inner(),
+            b.pieces[PieceType::Bishop].inner(),
+            b.pieces[PieceType::Knight].inner(),
+            b.pieces[PieceType::Pawn].inner(),
+            u32::from(board.fifty_move_counter()),
+            ep,
+            board.turn() == Colour::White,
+            results.as_mut_ptr(),
+        );
+
+        if result == TB_RESULT_FAILED {
+            return None;
         }
 
-        None
+        let mut moves = MoveList::new();
+        board.generate_moves(&mut moves);
+
+        // the per-move results are from the point of view of the side to move at the root,
+        // and are ordered loss < blessed loss < draw < cursed win < win.
+        let results = results
+            .iter()
+            .take_while(|&&r| r != TB_RESULT_FAILED)
+            .map(|&r| ((r & TB_RESULT_WDL_MASK) >> TB_RESULT_WDL_SHIFT, r))
+            .collect::<Vec<_>>();
+        let best = results.iter().map(|&(wdl, _)| wdl).max()?;
+        let preserving = results
+            .iter()
+            .filter(|&&(wdl, _)| wdl == best)
+            .filter_map(|&(_, r)| find_result_move(&moves, r))
+            .collect::<Vec<_>>();
+
+        (!preserving.is_empty()).then_some(preserving)
     }
     #[cfg(not(feature = "syzygy"))]
     None
@@ -269,6 +329,27 @@ mod tests {
         }
     }
 
+    #[test]
+    fn root_moves_keep_the_win() {
+        LazyLock::force(&INIT_SYZYGY);
+        let control = &TEST_CONTROL;
+
+        if get_max_pieces_count(control) < 3 {
+            return;
+        }
+
+        // Qf1+, Qf2+ and Qg1+ all hang the queen, so not every legal move keeps the win.
+        let board = Board::from_fen("8/8/8/8/8/8/6k1/K3Q3 w - - 0 1").unwrap();
+        let moves = get_root_moves_preserving_wdl(&board, control).unwrap();
+        assert!(!moves.is_empty());
+        assert!(moves.len() < board.legal_moves().len());
+        for m in moves {
+            let mut after = board.clone();
+            after.make_move_simple(m);
+            assert_eq!(get_wdl(&after), Some(WDL::Loss));
+        }
+    }
+
     #[test]
     fn solve_7man() {
         LazyLock::force(&INIT_SYZYGY);
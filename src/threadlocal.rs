@@ -9,11 +9,12 @@ use vec1::Vec1;
 
 use crate::{
     chess::{board::Board, chessmove::Move, piece::Colour},
+    evalcache::EvalCache,
     historytable::{
-        CaptureHistoryTable, CorrectionHistoryTable, DoubleHistoryTable, FromToTable,
-        HashHistoryTable, PieceToTable, ThreatsHistoryTable,
+        CaptureHistoryTable, CorrectionHistoryTable, CounterMoveTable, DoubleHistoryTable,
+        FromToTable, HashHistoryTable, PieceToTable, ThreatsHistoryTable,
     },
-    nnue::{self, network::NNUEParams},
+    nnue::{self, network::NNUEParams, small::SmallNet},
     search::pv::PVariation,
     searchinfo::{Control, SearchInfo},
     stack::StackFrame,
@@ -28,6 +29,7 @@ pub struct Histories {
     pub tactical: Box<CaptureHistoryTable>,
     pub continuation: Box<DoubleHistoryTable>,
     pub pawn: Box<HashHistoryTable>,
+    pub counter_moves: Box<CounterMoveTable>,
 }
 
 impl Histories {
@@ -38,6 +40,7 @@ impl Histories {
             tactical: CaptureHistoryTable::boxed(),
             continuation: DoubleHistoryTable::boxed(),
             pawn: HashHistoryTable::boxed(),
+            counter_moves: CounterMoveTable::boxed(),
         }
     }
 
@@ -47,6 +50,7 @@ impl Histories {
         self.tactical.clear();
         self.continuation.clear();
         self.pawn.clear();
+        self.counter_moves.clear();
     }
 }
 
@@ -58,6 +62,8 @@ pub struct ThreadData<'a> {
     pub banned_nmp: u8,
     pub nnue: Box<nnue::network::NNUEState>,
     pub nnue_params: &'static NNUEParams,
+    /// The small network used for clearly decided positions, if one is loaded.
+    pub small_net: Option<&'static SmallNet>,
 
     pub histories: Histories,
     pub killer_move_table: [Option<Move>; MAX_DEPTH + 1],
@@ -66,6 +72,8 @@ pub struct ThreadData<'a> {
     pub major_corrhist: Box<CorrectionHistoryTable>,
     pub minor_corrhist: Box<CorrectionHistoryTable>,
     pub cont_corrhist: Box<CorrectionHistoryTable>,
+    pub threat_corrhist: Box<CorrectionHistoryTable>,
+    pub eval_cache: EvalCache,
 
     pub thread_id: usize,
 
@@ -84,6 +92,14 @@ pub struct ThreadData<'a> {
     pub score_scratch: i32,
     /// scratch space for PVs as they move up/down the stack.
     pub pv_scratch: Vec<PVariation>,
+    /// root moves skipped by the root search, used to find secondary lines in multi-PV mode.
+    pub root_excluded: Vec<Move>,
+    /// if non-empty, the only root moves to consider, as given by `go searchmoves`.
+    pub searchmoves: Vec<Move>,
+    /// if non-empty, the root moves that keep the tablebase result, in a tablebase position.
+    pub tb_root_moves: Vec<Move>,
+    /// the second-best and subsequent lines from the last completed iteration, in multi-PV mode.
+    pub multi_pv_lines: Vec<(i32, PVariation)>,
 
     pub stm_at_root: Colour,
     pub optimism: [i32; 2],
@@ -114,6 +130,7 @@ impl<'a> ThreadData<'a> {
             banned_nmp: 0,
             nnue: nnue::network::NNUEState::new(&board, nnue_params),
             nnue_params,
+            small_net: None,
             histories: Histories::new(),
             killer_move_table: [None; MAX_DEPTH + 1],
             pawn_corrhist: CorrectionHistoryTable::boxed(),
@@ -124,6 +141,8 @@ impl<'a> ThreadData<'a> {
             major_corrhist: CorrectionHistoryTable::boxed(),
             minor_corrhist: CorrectionHistoryTable::boxed(),
             cont_corrhist: CorrectionHistoryTable::boxed(),
+            threat_corrhist: CorrectionHistoryTable::boxed(),
+            eval_cache: EvalCache::new(EvalCache::DEFAULT_ENTRIES),
             thread_id,
             pvs: vec![
                 PVariation {
@@ -142,6 +161,10 @@ impl<'a> ThreadData<'a> {
                 };
                 MAX_DEPTH + 1 // reaches forward by one when bootstrapping
             ],
+            root_excluded: Vec::new(),
+            searchmoves: Vec::new(),
+            tb_root_moves: Vec::new(),
+            multi_pv_lines: Vec::new(),
             stm_at_root: board.turn(),
             optimism: [0; 2],
             cache,
@@ -188,6 +211,8 @@ impl<'a> ThreadData<'a> {
         self.major_corrhist.clear();
         self.minor_corrhist.clear();
         self.cont_corrhist.clear();
+        self.threat_corrhist.clear();
+        self.eval_cache.clear();
         self.killer_move_table.fill(None);
         self.root_depth = 0;
         self.completed = 0;
@@ -199,10 +224,29 @@ impl<'a> ThreadData<'a> {
         self.root_depth = 0;
         self.completed = 0;
         self.pvs.fill_with(PVariation::new);
+        self.root_excluded.clear();
+        self.tb_root_moves.clear();
+        self.multi_pv_lines.clear();
         self.nnue.reïnit_from(&self.board, self.nnue_params);
         self.stm_at_root = self.board.turn();
     }
 
+    /// Should the root search skip `m`, either because it isn't among the
+    /// `searchmoves`, because it throws away a tablebase result, or because
+    /// it heads a line already found in multi-PV mode?
+    pub fn root_move_excluded(&self, m: Move) -> bool {
+        self.root_excluded.contains(&m)
+            || !self.searchmoves.is_empty() && !self.searchmoves.contains(&m)
+            || !self.tb_root_moves.is_empty() && !self.tb_root_moves.contains(&m)
+    }
+
+    /// Is the root search restricted to a subset of the legal moves?
+    pub fn root_restricted(&self) -> bool {
+        !self.root_excluded.is_empty()
+            || !self.searchmoves.is_empty()
+            || !self.tb_root_moves.is_empty()
+    }
+
     pub fn update_best_line(&mut self) {
         self.completed = self.iteration;
         self.pvs[self.iteration] = self.pv_scratch[0].clone();
@@ -272,3 +316,48 @@ pub fn make_thread_data<'a>(
         Ok(thread_data.try_into()?)
     })
 }
+
+/// Everything that a [`ThreadData`] borrows, owned in one place for tests.
+#[cfg(test)]
+pub struct ThreadFixture {
+    pub stopped: AtomicBool,
+    pub nodes: AtomicU64,
+    pub tbhits: AtomicU64,
+    pub control: Control,
+    pub pool: Vec1<threadpool::WorkerThread>,
+    pub cache: crate::transpositiontable::Cache,
+    pub nnue_params: &'static NNUEParams,
+}
+
+#[cfg(test)]
+impl ThreadFixture {
+    /// Default controls, a single worker thread, and a one-megabyte hash table.
+    pub fn new() -> Self {
+        let pool = threadpool::make_worker_threads(1);
+        let mut cache = crate::transpositiontable::Cache::new();
+        cache.resize(crate::util::MEGABYTE, &pool);
+        Self {
+            stopped: AtomicBool::new(false),
+            nodes: AtomicU64::new(0),
+            tbhits: AtomicU64::new(0),
+            control: Control::default(),
+            pool,
+            cache,
+            nnue_params: NNUEParams::decompress_and_alloc().unwrap(),
+        }
+    }
+
+    /// The data for the main thread, set up at `board`.
+    pub fn thread_data(&self, board: Board) -> Box<ThreadData<'_>> {
+        Box::new(ThreadData::new(
+            0,
+            board,
+            self.cache.view(),
+            self.nnue_params,
+            &self.stopped,
+            &self.nodes,
+            &self.tbhits,
+            &self.control,
+        ))
+    }
+}
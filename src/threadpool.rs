@@ -9,11 +9,13 @@ use vec1::Vec1;
 // and a receiver for receiving messages from the worker thread.
 pub struct WorkSender {
     // INVARIANT: Each send must be matched by a receive.
+    #[cfg_attr(target_family = "wasm", allow(dead_code))]
     sender: SyncSender<Box<dyn FnOnce() + Send>>,
     completion_signal: Arc<(Mutex<bool>, Condvar)>,
 }
 
 /// Handle for the receiver side of a worker thread.
+#[cfg_attr(target_family = "wasm", allow(dead_code))]
 struct WorkReceiver {
     receiver: Receiver<Box<dyn FnOnce() + Send>>,
     completion_signal: Arc<(Mutex<bool>, Condvar)>,
@@ -92,12 +94,21 @@ impl<'scope, 'env> ScopeExt<'scope, 'env> for Scope<'scope, 'env> {
             *completed = false;
         }
 
+        #[cfg(not(target_family = "wasm"))]
         thread
             .comms
             .sender
             .send(f)
             .expect("Failed to send function to worker thread");
 
+        // without threads, the work is done here and now.
+        #[cfg(target_family = "wasm")]
+        {
+            f();
+            let (lock, _) = &*thread.comms.completion_signal;
+            *lock.lock().unwrap() = true;
+        }
+
         ReceiverHandle {
             completion_signal: &thread.comms.completion_signal,
             // Important: We start with `received` as false.
@@ -106,6 +117,7 @@ impl<'scope, 'env> ScopeExt<'scope, 'env> for Scope<'scope, 'env> {
     }
 }
 
+#[cfg(not(target_family = "wasm"))]
 fn make_worker_thread() -> WorkerThread {
     let (sender, receiver) = make_work_channel();
 
@@ -126,6 +138,15 @@ fn make_worker_thread() -> WorkerThread {
     }
 }
 
+/// `wasm32-unknown-unknown` can't spawn threads, so a "worker" there runs its work
+/// on the thread that hands it over.
+#[cfg(target_family = "wasm")]
+fn make_worker_thread() -> WorkerThread {
+    let (sender, _) = make_work_channel();
+
+    WorkerThread { comms: sender }
+}
+
 /// Create some number of worker threads. Panics if `num_threads` is zero.
 pub fn make_worker_threads(num_threads: usize) -> Vec1<WorkerThread> {
     (0..num_threads)
@@ -136,6 +157,7 @@ pub fn make_worker_threads(num_threads: usize) -> Vec1<WorkerThread> {
 }
 
 pub struct WorkerThread {
+    #[cfg(not(target_family = "wasm"))]
     handle: std::thread::JoinHandle<()>,
     comms: WorkSender,
 }
@@ -143,6 +165,7 @@ pub struct WorkerThread {
 impl WorkerThread {
     pub fn join(self) {
         drop(self.comms); // Drop the sender to signal the worker thread to finish
+        #[cfg(not(target_family = "wasm"))]
         self.handle.join().expect("Worker thread panicked");
     }
 }
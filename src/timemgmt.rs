@@ -1,19 +1,31 @@
 use std::{
     sync::atomic::{AtomicBool, Ordering},
-    time::{Duration, Instant},
+    time::Duration,
 };
 
 use crate::{
     chess::chessmove::Move,
-    evaluation::{is_mate_score, mate_in},
+    evaluation::{is_decisive, is_mate_score, mate_in},
     search::parameters::Config,
     transpositiontable::Bound,
+    util::Instant,
 };
 
 /// A buffer to account for communication between the engine and the client.
 const MOVE_OVERHEAD: u64 = 30;
 /// The fraction per mille that we are ever allowed to use of the bank.
 const MAX_BANK_USABLE: u64 = 600;
+/// The fraction per mille of the bank held in reserve under a move-count time control.
+const MOVES_TO_GO_RESERVE: u64 = 50;
+/// The largest change in score between iterations, in centipawns, that affects the time budget.
+const MAX_SCORE_SWING: i32 = 100;
+/// How much of the count of best-move changes carries over to the next iteration.
+const BEST_MOVE_CHANGE_DECAY: f64 = 0.5;
+/// The fraction of the optimal time after which failing low at the root sends us into a panic.
+const PANIC_THRESHOLD: f64 = 0.6;
+
+/// The default for the `SlowMover` option, as a percentage of the normal time usage.
+pub const DEFAULT_SLOW_MOVER: u64 = 100;
 
 pub const STRONG_FORCED_TM_FRAC: u32 = 386;
 pub const WEAK_FORCED_TM_FRAC: u32 = 627;
@@ -23,6 +35,8 @@ pub const OPTIMAL_WINDOW_FRAC: u32 = 73;
 pub const INCREMENT_FRAC: u32 = 94;
 pub const NODE_TM_SUBTREE_MULTIPLIER: u32 = 140;
 pub const FAIL_LOW_TM_BONUS: u32 = 340;
+pub const INSTABILITY_TM_BONUS: u32 = 150;
+pub const SCORE_SWING_TM_FRAC: u32 = 250;
 
 #[derive(PartialEq, Eq, Clone, Copy, Debug)]
 pub enum Forcedness {
@@ -43,42 +57,61 @@ impl Forcedness {
     }
 }
 
+/// What bounds a search.
 #[derive(PartialEq, Eq, Clone, Debug, Default)]
 pub enum SearchLimit {
+    /// Search until told to stop.
     #[default]
     Infinite,
+    /// Search to a fixed depth.
     Depth(usize),
+    /// Search for a fixed number of milliseconds.
     Time(u64),
+    /// Search a fixed number of nodes.
     Nodes(u64),
+    /// Search for a mate within `ply` half-moves.
     Mate {
+        /// The length of the mate to look for, in half-moves.
         ply: usize,
     },
+    /// Manage the time on a game clock, in milliseconds.
     Dynamic {
+        /// The time left on the clock of the side to move.
         our_clock: u64,
+        /// The time left on the opponent's clock.
         their_clock: u64,
+        /// The increment of the side to move.
         our_inc: u64,
+        /// The opponent's increment.
         their_inc: u64,
+        /// The number of moves until the next time control, if there is one.
         moves_to_go: Option<u64>,
     },
+    /// Search `soft_limit` nodes, unless a depth is still in progress, but never more than
+    /// `hard_limit`.
     #[cfg(feature = "datagen")]
     SoftNodes {
+        /// The node count after which no new depth is started.
         soft_limit: u64,
+        /// The node count at which the search stops.
         hard_limit: u64,
     },
+    /// Search until the opponent makes the expected move, then switch to `saved_limit`.
     Pondering {
+        /// The limit to search under once the ponder move is played.
         saved_limit: Box<Self>,
     },
 }
 
 impl SearchLimit {
-    #[expect(clippy::wrong_self_convention)]
+    /// Ponder, keeping this limit for when the ponder move is played.
     pub fn to_pondering(self) -> Self {
         Self::Pondering {
             saved_limit: Box::new(self),
         }
     }
 
-    #[expect(clippy::wrong_self_convention)]
+    /// The limit to search under once pondering ends.
     pub fn from_pondering(self) -> Self {
         match self {
             Self::Pondering { saved_limit } => *saved_limit,
@@ -86,6 +119,7 @@ impl SearchLimit {
         }
     }
 
+    /// The fixed depth to search to, if there is one.
     pub const fn depth(&self) -> Option<usize> {
         match self {
             Self::Depth(d) => Some(*d),
@@ -93,7 +127,7 @@ impl SearchLimit {
         }
     }
 
-    pub fn compute_time_windows(
+    fn compute_time_windows(
         our_clock: u64,
         moves_to_go: Option<u64>,
         our_inc: u64,
@@ -105,13 +139,18 @@ impl SearchLimit {
         // The maximum time we can spend searching before forcibly stopping:
         let hard_time_window = (our_clock * u64::from(conf.hard_window_frac) / 100).min(max_time);
 
-        // If we have a moves to go, we can use that to compute a time window.
+        // Under a move-count control the clock is replenished after `moves_to_go` moves,
+        // so the bank can be split evenly between them, holding back only a small reserve.
         if let Some(moves_to_go) = moves_to_go {
-            // Use more time if we have fewer moves to go, but not more than default_moves_to_go.
-            let divisor = moves_to_go.clamp(2, u64::from(conf.default_moves_to_go));
-            let computed_time_window = our_clock / divisor;
+            let usable =
+                (our_clock * (1000 - MOVES_TO_GO_RESERVE) / 1000).saturating_sub(MOVE_OVERHEAD);
+            // even with one move left, never spend more than the bank limit allows.
+            let per_move = (usable / moves_to_go.max(1)
+                + our_inc * u64::from(conf.increment_frac) / 100)
+                .min(max_time);
+            let hard_time_window = hard_time_window.max(per_move).min(max_time);
             let optimal_time_window =
-                computed_time_window.min(max_time) * u64::from(conf.optimal_window_frac) / 100;
+                (per_move * u64::from(conf.optimal_window_frac) / 100).min(hard_time_window);
             return (optimal_time_window, hard_time_window, max_time);
         }
 
@@ -127,6 +166,7 @@ impl SearchLimit {
         (optimal_time_window, hard_time_window, max_time)
     }
 
+    /// Search for a mate in `moves` moves.
     #[cfg(test)]
     pub const fn mate_in(moves: usize) -> Self {
         Self::Mate { ply: moves * 2 }
@@ -153,14 +193,24 @@ pub struct TimeManager {
     stability: usize,
     /// Number of times that we have failed low.
     failed_low: i32,
+    /// Whether the current iteration failed low late enough to extend our time to the limit.
+    panicking: bool,
     /// Number of ID iterations that a mate score has remained.
     mate_counter: usize,
     /// The nature of the forced move (if any)
     forcedness: Forcedness,
-    /// The last set of multiplicative factors.
-    last_factors: [f64; 2],
+    /// A decaying count of how often the best move has changed between iterations.
+    best_move_changes: f64,
+    /// The last set of multiplicative factors: stability, failing low, and volatility.
+    last_factors: [f64; 3],
     /// Fraction of nodes that were underneath the best move.
     best_move_nodes_fraction: Option<f64>,
+    /// Nodes per millisecond of simulated time, or zero to use the wall clock.
+    nodes_time: u64,
+    /// The least time, in milliseconds, that we aim to spend on a move.
+    min_think_time: u64,
+    /// A percentage scaling of the time that we aim to spend on a move.
+    slow_mover: u64,
 }
 
 impl Default for TimeManager {
@@ -175,10 +225,15 @@ impl Default for TimeManager {
             prev_move: None,
             stability: 0,
             failed_low: 0,
+            panicking: false,
             mate_counter: 0,
             forcedness: Forcedness::None,
-            last_factors: [1.0, 1.0],
+            best_move_changes: 0.0,
+            last_factors: [1.0; 3],
             best_move_nodes_fraction: None,
+            nodes_time: 0,
+            min_think_time: 0,
+            slow_mover: DEFAULT_SLOW_MOVER,
         }
     }
 }
@@ -196,6 +251,45 @@ impl TimeManager {
         self.start_time.elapsed()
     }
 
+    /// Measure time limits in nodes, at `nodes_time` nodes per millisecond, rather than
+    /// on the wall clock. This makes timed searches reproducible across machines.
+    pub const fn set_nodes_time(&mut self, nodes_time: u64) {
+        self.nodes_time = nodes_time;
+    }
+
+    /// Set how the user wants the clock spent: aim for at least `min_think_time` milliseconds
+    /// per move, and scale the time spent on each move by `slow_mover` percent.
+    pub const fn set_time_usage(&mut self, min_think_time: u64, slow_mover: u64) {
+        self.min_think_time = min_think_time;
+        self.slow_mover = slow_mover;
+    }
+
+    /// The optimal, hard, and maximum time windows for a move, adjusted for the user's settings.
+    fn time_windows(
+        &self,
+        our_clock: u64,
+        moves_to_go: Option<u64>,
+        our_inc: u64,
+        conf: &Config,
+    ) -> (u64, u64, u64) {
+        let (opt_time, hard_time, max_time) =
+            SearchLimit::compute_time_windows(our_clock, moves_to_go, our_inc, conf);
+        // never let the user's settings take us past the point of losing on time.
+        let adjust = |window: u64| {
+            (window * self.slow_mover / 100)
+                .max(self.min_think_time)
+                .min(max_time)
+        };
+        (adjust(opt_time), adjust(hard_time), max_time)
+    }
+
+    /// The time that counts against the limits, having searched `nodes` nodes.
+    fn time_used(&self, nodes: u64) -> Duration {
+        nodes
+            .checked_div(self.nodes_time)
+            .map_or_else(|| self.start_time.elapsed(), Duration::from_millis)
+    }
+
     pub const fn limit(&self) -> &SearchLimit {
         &self.limit
     }
@@ -213,9 +307,11 @@ impl TimeManager {
         self.prev_move = None;
         self.stability = 0;
         self.failed_low = 0;
+        self.panicking = false;
         self.mate_counter = 0;
         self.forcedness = Forcedness::None;
-        self.last_factors = [1.0, 1.0];
+        self.best_move_changes = 0.0;
+        self.last_factors = [1.0; 3];
         self.best_move_nodes_fraction = None;
 
         if let SearchLimit::Dynamic {
@@ -226,7 +322,7 @@ impl TimeManager {
         } = self.limit.clone().from_pondering()
         {
             let (opt_time, mut hard_time, max_time) =
-                SearchLimit::compute_time_windows(our_clock, moves_to_go, our_inc, conf);
+                self.time_windows(our_clock, moves_to_go, our_inc, conf);
             // deal with "ponderhit" arriving while we're stuck on a depth:
             if matches!(self.limit, SearchLimit::Pondering { .. }) {
                 hard_time = opt_time;
@@ -250,7 +346,7 @@ impl TimeManager {
                 past_limit
             }
             SearchLimit::Time(millis) => {
-                let elapsed = self.start_time.elapsed();
+                let elapsed = self.time_used(nodes_so_far);
                 // this cast is safe to do, because u64::MAX milliseconds is 585K centuries.
                 #[allow(clippy::cast_possible_truncation)]
                 let elapsed_millis = elapsed.as_millis() as u64;
@@ -261,7 +357,7 @@ impl TimeManager {
                 past_limit
             }
             SearchLimit::Dynamic { .. } => {
-                let past_limit = self.time_since_start() >= self.hard_time;
+                let past_limit = self.time_used(nodes_so_far) >= self.hard_time;
                 if past_limit {
                     stopped.store(true, Ordering::SeqCst);
                 }
@@ -281,10 +377,9 @@ impl TimeManager {
     }
 
     /// If we have used enough time that stopping after finishing a depth would be good here.
-    #[allow(unused_variables)]
     pub fn is_past_opt_time(&self, nodes: u64) -> bool {
         match self.limit {
-            SearchLimit::Dynamic { .. } => self.time_since_start() >= self.opt_time,
+            SearchLimit::Dynamic { .. } => self.time_used(nodes) >= self.opt_time,
             #[cfg(feature = "datagen")]
             SearchLimit::SoftNodes { soft_limit, .. } => nodes >= soft_limit,
             _ => false,
@@ -299,6 +394,8 @@ impl TimeManager {
         matches!(self.limit, SearchLimit::Dynamic { .. })
     }
 
+    /// Whether a mate search has been answered: the score from a completed
+    /// iteration proves a mate (for either side) within the requested distance.
     pub const fn solved_breaker(&self, value: i32) -> bool {
         if let SearchLimit::Mate { ply } = self.limit {
             value.abs() >= mate_in(ply)
@@ -369,6 +466,26 @@ impl TimeManager {
         (1.62 - nodes_fraction) * f64::from(conf.node_tm_subtree_multiplier) / 100.0
     }
 
+    /// Scale the time budget by how unsettled the search is: the more often the best
+    /// move has changed, and the further the score has just dropped, the more time we
+    /// take. A rising score lets us move sooner.
+    fn volatility_multiplier(&self, eval: i32, conf: &Config) -> f64 {
+        let instability_multiplier = self
+            .best_move_changes
+            .mul_add(f64::from(conf.instability_tm_bonus) / 1000.0, 1.0);
+        // there is nothing to compare against on the first iteration, and
+        // decisive scores jump around without saying much about the position.
+        let swing = if self.prev_move.is_none() || is_decisive(eval) || is_decisive(self.prev_score)
+        {
+            0
+        } else {
+            (self.prev_score - eval).clamp(-MAX_SCORE_SWING, MAX_SCORE_SWING)
+        };
+        let swing_multiplier = (f64::from(swing) / f64::from(MAX_SCORE_SWING))
+            .mul_add(f64::from(conf.score_swing_tm_frac) / 1000.0, 1.0);
+        instability_multiplier * swing_multiplier
+    }
+
     pub fn report_completed_depth(
         &mut self,
         _depth: i32,
@@ -385,7 +502,7 @@ impl TimeManager {
         } = self.limit
         {
             let (opt_time, hard_time, max_time) =
-                SearchLimit::compute_time_windows(our_clock, moves_to_go, our_inc, conf);
+                self.time_windows(our_clock, moves_to_go, our_inc, conf);
             let max_time = Duration::from_millis(max_time);
             let hard_time = Duration::from_millis(hard_time);
             let opt_time = Duration::from_millis(opt_time);
@@ -395,9 +512,16 @@ impl TimeManager {
             } else {
                 self.stability = 0;
             }
+            // a completed iteration has resolved any fail-low that we panicked over.
+            self.panicking = false;
+            let changed = self.prev_move.is_some_and(|m| m != best_move);
+            self.best_move_changes = self
+                .best_move_changes
+                .mul_add(BEST_MOVE_CHANGE_DECAY, f64::from(u8::from(changed)));
             self.best_move_nodes_fraction = best_move_nodes_fraction;
 
             let stability_multiplier = Self::best_move_stability_multiplier(self.stability);
+            let volatility_multiplier = self.volatility_multiplier(eval, conf);
             // retain time added by windows that failed low
             let failed_low_multiplier =
                 f64::from(self.failed_low).mul_add(f64::from(conf.fail_low_tm_bonus) / 1000.0, 1.0);
@@ -409,7 +533,8 @@ impl TimeManager {
             let multiplier = stability_multiplier
                 * failed_low_multiplier
                 * forced_move_multiplier
-                * subtree_size_multiplier;
+                * subtree_size_multiplier
+                * volatility_multiplier;
 
             let hard_time = Duration::from_secs_f64(hard_time.as_secs_f64() * multiplier);
             let opt_time = Duration::from_secs_f64(opt_time.as_secs_f64() * multiplier);
@@ -417,14 +542,18 @@ impl TimeManager {
             self.hard_time = hard_time.min(max_time);
             self.opt_time = opt_time.min(max_time);
 
-            self.last_factors = [stability_multiplier, failed_low_multiplier];
+            self.last_factors = [
+                stability_multiplier,
+                failed_low_multiplier,
+                volatility_multiplier,
+            ];
         }
 
         self.prev_move = Some(best_move);
         self.prev_score = eval;
     }
 
-    pub fn report_aspiration_fail(&mut self, depth: i32, bound: Bound, conf: &Config) {
+    pub fn report_aspiration_fail(&mut self, depth: i32, bound: Bound, nodes: u64, conf: &Config) {
         const FAIL_LOW_UPDATE_THRESHOLD: i32 = 0;
         let SearchLimit::Dynamic {
             our_clock,
@@ -435,16 +564,18 @@ impl TimeManager {
         else {
             return;
         };
+        let near_opt_time = self.time_used(nodes) >= self.opt_time.mul_f64(PANIC_THRESHOLD);
         if depth >= FAIL_LOW_UPDATE_THRESHOLD && bound == Bound::Upper && self.failed_low < 2 {
             self.failed_low += 1;
 
             let (opt_time, hard_time, max_time) =
-                SearchLimit::compute_time_windows(our_clock, moves_to_go, our_inc, conf);
+                self.time_windows(our_clock, moves_to_go, our_inc, conf);
             let max_time = Duration::from_millis(max_time);
             let hard_time = Duration::from_millis(hard_time);
             let opt_time = Duration::from_millis(opt_time);
 
             let stability_multiplier = self.last_factors[0];
+            let volatility_multiplier = self.last_factors[2];
             // calculate the failed low multiplier
             let failed_low_multiplier =
                 f64::from(self.failed_low).mul_add(f64::from(conf.fail_low_tm_bonus) / 1000.0, 1.0);
@@ -456,7 +587,8 @@ impl TimeManager {
             let multiplier = stability_multiplier
                 * failed_low_multiplier
                 * forced_move_multiplier
-                * subtree_size_multiplier;
+                * subtree_size_multiplier
+                * volatility_multiplier;
 
             let hard_time = Duration::from_secs_f64(hard_time.as_secs_f64() * multiplier);
             let opt_time = Duration::from_secs_f64(opt_time.as_secs_f64() * multiplier);
@@ -466,5 +598,156 @@ impl TimeManager {
 
             self.last_factors[1] = failed_low_multiplier;
         }
+        // failing low just as we were about to stop means that the move we would have
+        // played is probably being refuted, so keep searching until the iteration resolves,
+        // up to the hard limit.
+        if bound == Bound::Upper && (self.panicking || near_opt_time) {
+            self.panicking = true;
+            self.opt_time = self.opt_time.max(self.hard_time);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chess::types::Square;
+
+    #[test]
+    fn moves_to_go_divides_the_clock() {
+        let conf = Config::default();
+        let clock = 60_000;
+        let (last_opt, last_hard, last_max) =
+            SearchLimit::compute_time_windows(clock, Some(1), 0, &conf);
+        let (many_opt, many_hard, many_max) =
+            SearchLimit::compute_time_windows(clock, Some(40), 0, &conf);
+        let (sd_opt, _, _) = SearchLimit::compute_time_windows(clock, None, 0, &conf);
+
+        // with one move to go, the whole bank limit may be spent, but no more.
+        assert_eq!(last_hard, last_max);
+        assert!(last_max <= clock * MAX_BANK_USABLE / 1000);
+        assert!(last_opt <= last_hard && last_hard <= last_max);
+
+        // with forty moves to go, the budget is a small slice of the clock.
+        assert!(many_opt < clock / 40);
+        assert!(many_opt <= many_hard && many_hard <= many_max);
+        assert!(last_opt > 20 * many_opt);
+
+        // forty moves to go is stingier than the sudden-death estimate.
+        assert!(many_opt < sd_opt);
+    }
+
+    #[test]
+    fn moves_to_go_adds_increment() {
+        let conf = Config::default();
+        let (no_inc, _, _) = SearchLimit::compute_time_windows(60_000, Some(20), 0, &conf);
+        let (inc, _, _) = SearchLimit::compute_time_windows(60_000, Some(20), 1_000, &conf);
+        assert!(inc > no_inc);
+    }
+
+    #[test]
+    fn volatile_searches_get_more_time() {
+        let conf = Config::default();
+        let a = Move::new(Square::E2, Square::E4);
+        let b = Move::new(Square::D2, Square::D4);
+        let opt_time_after = |line: &dyn Fn(i32) -> (Move, i32)| {
+            let mut clock = TimeManager::default_with_limit(SearchLimit::Dynamic {
+                our_clock: 60_000,
+                their_clock: 60_000,
+                our_inc: 0,
+                their_inc: 0,
+                moves_to_go: None,
+            });
+            clock.reset_for_id(&conf);
+            for depth in 1..=10 {
+                let (mv, eval) = line(depth);
+                clock.report_completed_depth(depth, eval, mv, None, &conf);
+            }
+            clock.opt_time
+        };
+        // every line ends on the same move for long enough to be equally stable.
+        let settled = opt_time_after(&|_| (a, 30));
+        let falling = opt_time_after(&|depth| (a, 30 - 15 * depth));
+        let rising = opt_time_after(&|depth| (a, 30 + 15 * depth));
+        let unsettled =
+            opt_time_after(&|depth| (if depth < 5 && depth % 2 == 0 { b } else { a }, 30));
+        assert!(falling > settled);
+        assert!(rising < settled);
+        assert!(unsettled > settled);
+    }
+
+    #[test]
+    fn late_fail_low_extends_time() {
+        let conf = Config::default();
+        let a = Move::new(Square::E2, Square::E4);
+        let start = || {
+            let mut clock = TimeManager::default_with_limit(SearchLimit::Dynamic {
+                our_clock: 60_000,
+                their_clock: 60_000,
+                our_inc: 0,
+                their_inc: 0,
+                moves_to_go: None,
+            });
+            // measure time in nodes, so that the test can say exactly when the fail-low comes.
+            clock.set_nodes_time(1);
+            clock.reset_for_id(&conf);
+            for depth in 1..=10 {
+                clock.report_completed_depth(depth, 30, a, None, &conf);
+            }
+            clock
+        };
+
+        let mut early = start();
+        early.report_aspiration_fail(11, Bound::Upper, 0, &conf);
+        assert!(!early.panicking);
+        assert!(early.hard_time < early.max_time);
+
+        let mut late = start();
+        let opt_millis = u64::try_from(late.opt_time.as_millis()).unwrap();
+        late.report_aspiration_fail(11, Bound::Upper, opt_millis, &conf);
+        assert!(late.panicking);
+        assert_eq!(late.opt_time, late.hard_time);
+        assert!(late.hard_time < late.max_time);
+        assert!(!late.is_past_opt_time(opt_millis));
+
+        // the panic is over once the iteration completes.
+        late.report_completed_depth(11, -50, a, None, &conf);
+        assert!(!late.panicking);
+    }
+
+    #[test]
+    fn time_usage_settings_scale_the_windows() {
+        let conf = Config::default();
+        let windows = |min_think_time, slow_mover| {
+            let mut clock = TimeManager::default();
+            clock.set_time_usage(min_think_time, slow_mover);
+            clock.time_windows(60_000, None, 0, &conf)
+        };
+        let (opt, hard, max) = windows(0, DEFAULT_SLOW_MOVER);
+        let (slow_opt, slow_hard, _) = windows(0, 2 * DEFAULT_SLOW_MOVER);
+        assert_eq!(slow_opt, 2 * opt);
+        assert_eq!(slow_hard, (2 * hard).min(max));
+        let (fast_opt, _, _) = windows(0, DEFAULT_SLOW_MOVER / 2);
+        assert_eq!(fast_opt, opt / 2);
+        let (floor_opt, floor_hard, _) = windows(5000, DEFAULT_SLOW_MOVER / 10);
+        assert_eq!(floor_opt, 5000);
+        assert!(floor_hard >= 5000);
+        // the floor never outweighs the clock.
+        let mut clock = TimeManager::default();
+        clock.set_time_usage(5000, DEFAULT_SLOW_MOVER);
+        let (opt, hard, max) = clock.time_windows(1000, None, 0, &conf);
+        assert!(opt <= max && hard <= max && max < 1000);
+    }
+
+    #[test]
+    fn nodes_time_measures_limits_in_nodes() {
+        let stopped = AtomicBool::new(false);
+        let mut clock = TimeManager::default_with_limit(SearchLimit::Time(100));
+        clock.set_nodes_time(1000);
+        clock.start();
+        assert!(!clock.check_up(&stopped, 99_999));
+        assert!(!stopped.load(Ordering::SeqCst));
+        assert!(clock.check_up(&stopped, 100_000));
+        assert!(stopped.load(Ordering::SeqCst));
     }
 }
@@ -1,35 +1,51 @@
+//! The transposition table, shared between search threads.
+
+mod memory;
+
 use std::{
+    fs::File,
+    io::{BufReader, BufWriter, Read, Write},
     mem::{MaybeUninit, size_of},
-    ptr::slice_from_raw_parts_mut,
+    path::Path,
     sync::atomic::{AtomicU8, AtomicU64, Ordering},
 };
 
 use crate::{
     chess::chessmove::Move,
+    errors::HashFileError,
     evaluation::{MATE_SCORE, MINIMUM_MATE_SCORE, MINIMUM_TB_WIN_SCORE},
     threadpool::{self, ScopeExt},
-    util::{MEGABYTE, SendPtr, VALUE_NONE},
+    util::{self, Instant, MEGABYTE, SendPtr, VALUE_NONE},
 };
 
+use self::memory::TableMemory;
+
+/// How a stored score relates to the true score of a position.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(u8)]
 pub enum Bound {
+    /// There is no entry.
     Empty = 0,
+    /// The true score is at most the stored score.
     Upper = 1,
+    /// The true score is at least the stored score.
     Lower = 2,
+    /// The stored score is exact.
     Exact = 3,
 }
 
 impl Bound {
+    /// Whether the stored score is a lower bound on the true score, including exact scores.
     pub fn is_lower(self) -> bool {
         self as u8 & 0b10 != 0
     }
 
-    #[expect(unused)]
+    /// Whether the stored score is an upper bound on the true score, including exact scores.
     pub fn is_upper(self) -> bool {
         self as u8 & 0b01 != 0
     }
 
+    /// The bound from the other side's point of view.
     pub fn invert(self) -> Self {
         match self {
             Self::Upper => Self::Lower,
@@ -75,7 +91,7 @@ unsafe fn threaded_memset_zero(
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub struct PackedMeta {
+pub(crate) struct PackedMeta {
     data: u8,
 }
 
@@ -107,16 +123,18 @@ impl PackedMeta {
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(C)]
-pub struct CacheEntry {
-    pub tag: u16,         // 2 bytes
-    pub depth: u8,        // 1 byte
-    pub info: PackedMeta, // 1 byte (5 + 1 + 2 bits), wrapper around a u8
-    pub m: Option<Move>,  // 2 bytes
-    pub score: i16,       // 2 bytes
-    pub evaluation: i16,  // 2 bytes
+pub(crate) struct CacheEntry {
+    pub(crate) tag: u16,         // 2 bytes
+    pub(crate) depth: u8,        // 1 byte
+    pub(crate) info: PackedMeta, // 1 byte (5 + 1 + 2 bits), wrapper around a u8
+    pub(crate) m: Option<Move>,  // 2 bytes
+    pub(crate) score: i16,       // 2 bytes
+    pub(crate) evaluation: i16,  // 2 bytes
 }
 
 const CLUSTER_SIZE: usize = 3;
+/// The number of clusters sampled to estimate the occupancy of the table.
+const OCCUPANCY_SAMPLE_CLUSTERS: usize = 1000;
 
 /// Object representing the backing memory used to store cache sets.
 #[derive(Debug, Default)]
@@ -173,11 +191,42 @@ const _CLUSTER_SIZE: () = assert!(
     "Cache set size is suboptimal."
 );
 
+/// The size of the table used if the requested size can't be allocated.
+const FALLBACK_CACHE_BYTES: usize = 16 * MEGABYTE;
+const FALLBACK_CACHE_SETS: usize = FALLBACK_CACHE_BYTES / size_of::<RawCacheSet>();
+
+/// Map `key` onto an index in `0..len` with the fixed-point multiplication trick.
+///
+/// The product is taken in 128 bits, so this is exact for any table length
+/// representable in a `usize`, and the high bits of the key pick the index.
+/// Keys only share bits between the index and the 16-bit tag once the table
+/// passes 2⁴⁸ sets, which is far beyond any table we allow.
+const fn bucket_index(key: u64, len: usize) -> usize {
+    #![expect(clippy::cast_possible_truncation, reason = "the result is below len")]
+    ((key as u128 * len as u128) >> 64) as usize
+}
+
+/// Magic bytes at the start of a saved hash table.
+const HASH_FILE_MAGIC: [u8; 8] = *b"VIRIHASH";
+/// The version of the saved hash table format.
+/// This must be bumped whenever the layout of a cache set changes.
+const HASH_FILE_VERSION: u32 = 1;
+/// The size of the header of a saved hash table: magic, version, age and padding, and set count.
+const HASH_FILE_HEADER_SIZE: usize = 24;
+
+/// Fold one word of the table into the checksum of a saved hash table.
+const fn fold_checksum(checksum: u64, word: u64) -> u64 {
+    (checksum ^ word)
+        .wrapping_mul(0x0000_0100_0000_01B3)
+        .rotate_left(29)
+}
+
 /// The cache for Viridithas’s search. SMP threads communicate by reading and writing this.
 #[derive(Debug)]
 pub struct Cache {
-    table: Vec<RawCacheSet>,
+    table: TableMemory,
     age: AtomicU8,
+    large_pages: bool,
 }
 
 /// A borrowed view into the cache.
@@ -190,45 +239,96 @@ pub struct CacheView<'a> {
 /// The result of probing the cache for an entry.
 #[derive(Debug, Clone, Copy)]
 pub struct CacheResult {
+    /// The best move found, if any.
     pub mov: Option<Move>,
+    /// The depth the position was searched to.
     pub depth: i32,
+    /// How the stored score relates to the true score.
     pub bound: Bound,
+    /// The stored score, adjusted for the distance to any mate.
     pub value: i32,
+    /// The static evaluation of the position.
     pub eval: i32,
+    /// Whether the position was on a principal variation when it was stored.
     pub was_pv: bool,
+    /// The generation of the search that stored the entry.
+    pub age: u8,
+}
+
+impl Default for Cache {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Cache {
+    /// An unallocated cache. It has to be resized before use.
     pub const fn new() -> Self {
         Self {
-            table: Vec::new(),
+            table: TableMemory::empty(),
             age: AtomicU8::new(0),
+            large_pages: false,
         }
     }
 
+    /// Whether the table should be allocated on large pages where the system allows it.
+    /// This takes effect on the next [`Cache::resize`].
+    pub const fn set_large_pages(&mut self, large_pages: bool) {
+        self.large_pages = large_pages;
+    }
+
+    /// Whether the table should be allocated on large pages where the system allows it.
+    pub const fn large_pages(&self) -> bool {
+        self.large_pages
+    }
+
+    /// Resize the cache to `bytes`, rounded down to a whole number of sets, clearing it.
+    ///
+    /// If a table of that size can't be allocated, the cache falls back to
+    /// [`FALLBACK_CACHE_BYTES`], so that a request for more memory than the machine
+    /// has doesn't take the engine down.
     pub fn resize(&mut self, bytes: usize, threads: &[threadpool::WorkerThread]) {
-        let start = std::time::Instant::now();
-        let new_len = bytes / size_of::<RawCacheSet>();
-        // dealloc the old table:
-        self.table = Vec::new();
-        // construct a new vec:
-        // SAFETY: zeroed memory is a legal bitpattern for AtomicUXX.
-        unsafe {
-            let layout = std::alloc::Layout::array::<RawCacheSet>(new_len).unwrap();
-            let ptr = std::alloc::alloc(layout);
-            if ptr.is_null() {
-                std::alloc::handle_alloc_error(layout);
-            }
-            threaded_memset_zero(ptr.cast(), new_len * size_of::<RawCacheSet>(), threads);
-            self.table = Box::from_raw(slice_from_raw_parts_mut(ptr.cast(), new_len)).into();
+        let start = Instant::now();
+        // dealloc the old table first, so that its memory can be reused:
+        self.table = TableMemory::empty();
+        self.table = self.alloc_zeroed_table(bytes, threads).unwrap_or_else(|| {
+            println!(
+                "info string failed to allocate {}mb hash table, falling back to {}mb",
+                bytes / MEGABYTE,
+                FALLBACK_CACHE_BYTES / MEGABYTE
+            );
+            self.alloc_zeroed_table(FALLBACK_CACHE_BYTES, threads)
+                .unwrap_or_else(|| {
+                    std::alloc::handle_alloc_error(std::alloc::Layout::new::<
+                        [RawCacheSet; FALLBACK_CACHE_SETS],
+                    >())
+                })
+        });
+        if self.large_pages {
+            println!(
+                "info string hash table allocated on {}",
+                self.table.kind().name()
+            );
         }
         println!(
             "info string hash initialisation of {}mb complete in {}ms",
-            bytes / MEGABYTE,
+            self.size() / MEGABYTE,
             start.elapsed().as_millis()
         );
     }
 
+    /// Allocate a zeroed table of `bytes`, rounded down to a whole number of
+    /// sets, but at least one set. Returns `None` if the allocation fails.
+    fn alloc_zeroed_table(
+        &self,
+        bytes: usize,
+        threads: &[threadpool::WorkerThread],
+    ) -> Option<TableMemory> {
+        let len = (bytes / size_of::<RawCacheSet>()).max(1);
+        TableMemory::allocate(len, self.large_pages, threads)
+    }
+
+    /// Clear every entry, splitting the work between `threads`.
     pub fn clear(&self, threads: &[threadpool::WorkerThread]) {
         fn divide_into_chunks<T>(slice: &[T], chunks: usize) -> impl Iterator<Item = &[T]> {
             let chunk_size = slice.len() / chunks + 1; // +1 to avoid 0
@@ -253,6 +353,7 @@ impl Cache {
         });
     }
 
+    /// A view into the cache at the current age.
     pub fn view(&self) -> CacheView<'_> {
         CacheView {
             table: &self.table,
@@ -260,18 +361,133 @@ impl Cache {
         }
     }
 
+    /// Move on to the next search's generation, so that older entries get replaced first.
     pub fn increase_age(&self) {
+        self.advance_age(1);
+    }
+
+    /// Mark every entry in the table as belonging to an earlier game, without clearing it.
+    ///
+    /// The age jumps by half a cycle, so the old entries lose as much replacement priority
+    /// as they can while the new game's searches still have room to count up before the
+    /// generation counter wraps back round to them.
+    pub fn age_for_new_game(&self) {
+        #![allow(clippy::cast_possible_truncation)]
+        self.advance_age(MAX_AGE as u8 / 2);
+    }
+
+    fn advance_age(&self, generations: u8) {
         #![allow(clippy::cast_possible_truncation)]
-        let new_age = (self.age.load(Ordering::Relaxed) + 1) & AGE_MASK as u8; // keep age in range [0, MAX_AGE]
+        let new_age = (self.age.load(Ordering::Relaxed) + generations) & AGE_MASK as u8; // keep age in range [0, MAX_AGE]
         self.age.store(new_age, Ordering::Relaxed);
     }
 
+    /// The size of the table in bytes.
     pub fn size(&self) -> usize {
         self.table.len() * size_of::<RawCacheSet>()
     }
+
+    /// Write the contents of the cache to `path`, so that it can be restored with [`Cache::load`].
+    ///
+    /// The file holds a versioned header, the raw cache sets in little-endian order,
+    /// and a trailing checksum over the sets.
+    pub fn save(&self, path: &Path) -> Result<(), HashFileError> {
+        let mut out = BufWriter::new(File::create(path)?);
+        out.write_all(&HASH_FILE_MAGIC)?;
+        out.write_all(&HASH_FILE_VERSION.to_le_bytes())?;
+        out.write_all(&[self.age.load(Ordering::Relaxed), 0, 0, 0])?;
+        out.write_all(&(self.table.len() as u64).to_le_bytes())?;
+        let mut checksum = 0;
+        for set in self.table.iter() {
+            for word in &set.memory {
+                let word = word.load(Ordering::Relaxed);
+                checksum = fold_checksum(checksum, word);
+                out.write_all(&word.to_le_bytes())?;
+            }
+        }
+        out.write_all(&checksum.to_le_bytes())?;
+        out.flush()?;
+        Ok(())
+    }
+
+    /// Replace the contents of the cache with a table saved by [`Cache::save`],
+    /// resizing the cache to match.
+    ///
+    /// The header and length of the file are checked before the cache is touched,
+    /// but if the checksum fails, the cache is left empty.
+    pub fn load(
+        &mut self,
+        path: &Path,
+        threads: &[threadpool::WorkerThread],
+    ) -> Result<(), HashFileError> {
+        const SET_SIZE: u64 = size_of::<RawCacheSet>() as u64;
+        let file = File::open(path)?;
+        let file_len = file.metadata()?.len();
+        let mut input = BufReader::new(file);
+
+        let mut header = [0; HASH_FILE_HEADER_SIZE];
+        if file_len < HASH_FILE_HEADER_SIZE as u64 {
+            return Err(HashFileError::BadMagic);
+        }
+        input.read_exact(&mut header)?;
+        let (magic, rest) = header.split_at(8);
+        let (version, rest) = rest.split_at(4);
+        let (age, sets) = rest.split_at(4);
+        if magic != HASH_FILE_MAGIC {
+            return Err(HashFileError::BadMagic);
+        }
+        let version = u32::from_le_bytes(version.try_into().unwrap());
+        if version != HASH_FILE_VERSION {
+            return Err(HashFileError::UnsupportedVersion(
+                version,
+                HASH_FILE_VERSION,
+            ));
+        }
+        let age = age[0];
+        if i32::from(age) > AGE_MASK {
+            return Err(HashFileError::BadAge(age));
+        }
+        let sets = u64::from_le_bytes(sets.try_into().unwrap());
+        let expected = sets
+            .checked_mul(SET_SIZE)
+            .and_then(|body| body.checked_add(HASH_FILE_HEADER_SIZE as u64 + 8))
+            .unwrap_or(u64::MAX);
+        // an empty table can't be indexed, so it can't have been saved.
+        if sets == 0 || file_len != expected {
+            return Err(HashFileError::BadLength {
+                expected,
+                got: file_len,
+            });
+        }
+
+        #[allow(clippy::cast_possible_truncation)]
+        self.resize((sets * SET_SIZE) as usize, threads);
+        let mut checksum = 0;
+        let mut word = [0; 8];
+        for set in self.table.iter() {
+            for slot in &set.memory {
+                input.read_exact(&mut word)?;
+                let word = u64::from_le_bytes(word);
+                checksum = fold_checksum(checksum, word);
+                slot.store(word, Ordering::Relaxed);
+            }
+        }
+        input.read_exact(&mut word)?;
+        if u64::from_le_bytes(word) != checksum {
+            self.clear(threads);
+            return Err(HashFileError::BadChecksum);
+        }
+        self.age.store(age, Ordering::Relaxed);
+        Ok(())
+    }
 }
 
 impl CacheView<'_> {
+    /// The generation of the current search.
+    pub const fn age(&self) -> u8 {
+        self.age
+    }
+
     /// Given a Zobrist key for a position, derive an index into the cache,
     /// and a tag for the corresponding entry.
     /// The index is computed using Daniel Lemire’s fast alternative to the
@@ -286,14 +502,15 @@ impl CacheView<'_> {
     fn derive_index_tag(&self, key: u64) -> (usize, u16) {
         #![expect(clippy::cast_possible_truncation, reason = "deliberately truncating")]
 
-        // fixed-point multiplication trick!
-        let index = ((u128::from(key) * self.table.len() as u128) >> 64) as usize;
+        let index = bucket_index(key, self.table.len());
         // take low bits:
         let tag = key as u16;
 
         (index, tag)
     }
 
+    /// Store the result of searching the position with Zobrist key `key`, `height` moves
+    /// from the root. Mate scores are stored relative to the position.
     #[allow(clippy::too_many_arguments)]
     pub fn store(
         &self,
@@ -381,6 +598,8 @@ impl CacheView<'_> {
         }
     }
 
+    /// Look up the position with Zobrist key `key`, `ply` moves from the root and with
+    /// `clock` for the fifty-move counter, which are used to adjust mate and tablebase scores.
     pub fn probe(&self, key: u64, ply: usize, clock: u8) -> Option<CacheResult> {
         let (index, tag) = self.derive_index_tag(key);
 
@@ -402,55 +621,46 @@ impl CacheView<'_> {
                 value: reconstruct_gt_truth_score(entry.score.into(), ply, clock),
                 eval: entry.evaluation.into(),
                 was_pv: entry.info.pv(),
+                age: entry.info.age(),
             });
         }
 
         None
     }
 
+    /// Hint to the processor that the entry for `key` will be needed soon.
     pub fn prefetch(&self, key: u64) {
-        // SAFETY: The pointer we construct is in-bounds, and _mm_prefetch
-        // doesn't really do anything particularly dangerous anyway.
-        #[cfg(target_arch = "x86_64")]
-        unsafe {
-            use std::arch::x86_64::{_MM_HINT_T0, _mm_prefetch};
-
-            // get a reference to the entry in the table:
-            let (index, _) = self.derive_index_tag(key);
-            let entry = &self.table[index];
-
-            // prefetch the entry:
-            _mm_prefetch(
-                std::ptr::from_ref::<RawCacheSet>(entry).cast::<i8>(),
-                _MM_HINT_T0,
-            );
-        }
-        #[cfg(target_arch = "aarch64")]
-        {
-            // Silence warnings on ARM, which lacks a prefetch equivalent.
-            let _ = self;
-            let _ = key;
-        }
+        let (index, _) = self.derive_index_tag(key);
+        util::prefetch(&raw const self.table[index]);
     }
 
+    /// The stored move and score for `key`, with mate scores counted from that position.
     pub fn probe_move(&self, key: u64) -> Option<(Option<Move>, i32)> {
         self.probe(key, 0, 0)
             .map(|CacheResult { mov, value, .. }| (mov, value))
     }
 
-    // TODO: rename and fix impl.
-    pub fn hashfull(&self) -> usize {
-        let mut hit = 0;
-        for i in 0..2000 {
-            let cluster = self.table[i].load();
-            for i in 0..CLUSTER_SIZE {
-                let entry = cluster.entries[i];
-                if entry.tag != 0 && entry.info.age() == self.age {
-                    hit += 1;
+    /// Estimate how much of the table is occupied by entries from each generation,
+    /// in entries per thousand, indexed by age. Only the first [`OCCUPANCY_SAMPLE_CLUSTERS`]
+    /// clusters are inspected, as the keys spread entries evenly across the table.
+    pub fn occupancy_by_age(&self) -> [usize; MAX_AGE as usize] {
+        let sampled = &self.table[..self.table.len().min(OCCUPANCY_SAMPLE_CLUSTERS)];
+        let mut counts = [0; MAX_AGE as usize];
+        for cluster in sampled {
+            for entry in cluster.load().entries {
+                if entry.tag != 0 {
+                    counts[usize::from(entry.info.age())] += 1;
                 }
             }
         }
-        hit / (2 * CLUSTER_SIZE)
+        let total = (sampled.len() * CLUSTER_SIZE).max(1);
+        counts.map(|count| count * 1000 / total)
+    }
+
+    /// Estimate the permille of the table filled by entries from the current search,
+    /// as reported in `hashfull`.
+    pub fn hashfull(&self) -> usize {
+        self.occupancy_by_age()[usize::from(self.age)]
     }
 }
 
@@ -575,4 +785,139 @@ mod tests {
             assert_eq!(*v, 0, "unset at index {i}");
         }
     }
+
+    #[test]
+    fn occupancy_is_sampled_by_age() {
+        let pool = threadpool::make_worker_threads(1);
+        let mut cache = Cache::new();
+        cache.resize(MEGABYTE, &pool);
+        assert_eq!(cache.view().hashfull(), 0);
+        // spread keys evenly over the table, so that the sample sees a fair share.
+        let fill = |cache: &Cache| {
+            let view = cache.view();
+            for i in 0..10_000u64 {
+                // the low bits become the tag, which must be non-zero.
+                let key = i.wrapping_mul(0x9E37_79B9_7F4A_7C15) | 1;
+                view.store(key, 0, None, 0, 0, Bound::Exact, 1, false);
+            }
+        };
+        fill(&cache);
+        let first = cache.view().hashfull();
+        assert!(first > 0);
+        cache.increase_age();
+        assert_eq!(cache.view().hashfull(), 0);
+        assert_eq!(cache.view().occupancy_by_age()[0], first);
+        // a tiny table is sampled in full, rather than read out of bounds.
+        cache.resize(size_of::<RawCacheSet>() * 10, &pool);
+        fill(&cache);
+        assert!(cache.view().hashfull() <= 1000);
+    }
+
+    #[test]
+    fn new_game_ages_out_old_entries() {
+        let pool = threadpool::make_worker_threads(1);
+        let mut cache = Cache::new();
+        // a single set, so that every key competes for the same three slots.
+        cache.resize(size_of::<RawCacheSet>(), &pool);
+        for tag in 1..=3 {
+            cache
+                .view()
+                .store(tag, 0, None, 0, 0, Bound::Exact, 10, false);
+        }
+        cache.age_for_new_game();
+        assert_eq!(cache.view().hashfull(), 0);
+        assert!(cache.view().probe(1, 0, 0).is_some(), "aging keeps entries");
+
+        // shallow entries from the new game displace deep ones from the old game,
+        // rather than each other.
+        cache.view().store(4, 0, None, 0, 0, Bound::Exact, 1, false);
+        cache.view().store(5, 0, None, 0, 0, Bound::Exact, 1, false);
+        assert!(cache.view().probe(4, 0, 0).is_some());
+        assert!(cache.view().probe(5, 0, 0).is_some());
+    }
+
+    #[test]
+    fn hash_file_round_trip() {
+        let pool = threadpool::make_worker_threads(1);
+        let mut cache = Cache::new();
+        cache.resize(MEGABYTE, &pool);
+        cache.increase_age();
+        let keys = (1..1000u64).map(|i| i.wrapping_mul(0x9E37_79B9_7F4A_7C15));
+        for (i, key) in keys.clone().enumerate() {
+            let depth = i32::try_from(i % 50).unwrap() + 1;
+            cache
+                .view()
+                .store(key, 0, None, depth, -depth, Bound::Lower, depth, false);
+        }
+        let path = std::env::temp_dir().join(format!("viri-hash-test-{}", std::process::id()));
+        cache.save(&path).unwrap();
+
+        let mut restored = Cache::new();
+        restored.resize(2 * MEGABYTE, &pool);
+        restored.load(&path, &pool).unwrap();
+        assert_eq!(restored.size(), cache.size());
+        assert_eq!(restored.view().hashfull(), cache.view().hashfull());
+        for key in keys {
+            let expected = cache.view().probe(key, 0, 0).map(|r| (r.value, r.depth));
+            let got = restored.view().probe(key, 0, 0).map(|r| (r.value, r.depth));
+            assert_eq!(got, expected);
+        }
+
+        // corruption anywhere in the file is caught.
+        let bytes = std::fs::read(&path).unwrap();
+        let mut corrupt = bytes.clone();
+        corrupt[HASH_FILE_HEADER_SIZE + 100] ^= 1;
+        std::fs::write(&path, &corrupt).unwrap();
+        assert_eq!(restored.load(&path, &pool), Err(HashFileError::BadChecksum));
+        assert_eq!(restored.view().hashfull(), 0);
+        std::fs::write(&path, &bytes[..bytes.len() - 1]).unwrap();
+        assert!(matches!(
+            restored.load(&path, &pool),
+            Err(HashFileError::BadLength { .. })
+        ));
+        std::fs::write(&path, b"not a hash table, but long enough").unwrap();
+        assert_eq!(restored.load(&path, &pool), Err(HashFileError::BadMagic));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn bucket_index_handles_huge_tables() {
+        // a 512 GiB table, and a length that uses all 64 bits.
+        for len in [(512 << 30) / size_of::<RawCacheSet>(), usize::MAX] {
+            assert_eq!(bucket_index(0, len), 0);
+            assert_eq!(bucket_index(u64::MAX, len), len - 1);
+            assert_eq!(bucket_index(1 << 63, len), len / 2);
+            assert!(bucket_index(u64::MAX / 3, len) < bucket_index(u64::MAX / 3 * 2, len));
+        }
+        assert_eq!(bucket_index(u64::MAX, 1), 0);
+    }
+
+    #[test]
+    fn impossible_sizes_fall_back() {
+        let pool = threadpool::make_worker_threads(1);
+        let mut cache = Cache::new();
+        cache.resize(usize::MAX, &pool);
+        assert_eq!(cache.size(), FALLBACK_CACHE_BYTES);
+        cache.resize(1, &pool);
+        assert_eq!(cache.size(), size_of::<RawCacheSet>());
+    }
+
+    #[test]
+    fn large_pages_fall_back_gracefully() {
+        let pool = threadpool::make_worker_threads(2);
+        let mut cache = Cache::new();
+        cache.set_large_pages(true);
+        // sizes that aren't a whole number of huge pages are not rounded up.
+        for bytes in [3 * MEGABYTE, MEGABYTE + size_of::<RawCacheSet>()] {
+            cache.resize(bytes, &pool);
+            assert_eq!(cache.size(), bytes);
+            let view = cache.view();
+            assert_eq!(view.hashfull(), 0);
+            view.store(0xDEAD_BEEF, 0, None, 42, 0, Bound::Exact, 7, false);
+            let hit = view.probe(0xDEAD_BEEF, 0, 0).map(|r| (r.value, r.depth));
+            assert_eq!(hit, Some((42, 7)));
+        }
+        cache.clear(&pool);
+        assert!(cache.view().probe(0xDEAD_BEEF, 0, 0).is_none());
+    }
 }
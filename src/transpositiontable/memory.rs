@@ -0,0 +1,294 @@
+//! Backing memory for the transposition table.
+//!
+//! A large table is read at random, so almost every probe misses in the TLB.
+//! Backing the table with large pages (2 MiB on x86-64, rather than 4 KiB) cuts the
+//! number of translations needed to cover it by a factor of 512, which is worth
+//! several percent of speed on big hash sizes. Large pages aren't always available,
+//! so every route falls back to ordinary memory.
+
+use std::ops::Deref;
+
+use crate::threadpool;
+
+use super::{RawCacheSet, threaded_memset_zero};
+
+/// What kind of memory backs a table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum PageKind {
+    /// Ordinary pages from the global allocator.
+    Normal,
+    /// Pages that the kernel has been asked to back with transparent huge pages.
+    #[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+    Transparent,
+    /// Explicitly reserved large pages.
+    #[cfg_attr(not(any(target_os = "linux", windows)), allow(dead_code))]
+    Large,
+}
+
+impl PageKind {
+    pub(super) const fn name(self) -> &'static str {
+        match self {
+            Self::Normal => "normal pages",
+            Self::Transparent => "transparent huge pages",
+            Self::Large => "large pages",
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Backing {
+    Heap(Vec<RawCacheSet>),
+    #[cfg(target_os = "linux")]
+    Mapped(memmap2::MmapMut),
+    #[cfg(windows)]
+    LargePages(windows::LargePageAllocation),
+}
+
+/// A zero-initialised table of cache sets.
+#[derive(Debug)]
+pub(super) struct TableMemory {
+    backing: Backing,
+    #[cfg_attr(not(any(target_os = "linux", windows)), allow(dead_code))]
+    len: usize,
+    kind: PageKind,
+}
+
+impl TableMemory {
+    /// An empty table, which owns no memory.
+    pub(super) const fn empty() -> Self {
+        Self {
+            backing: Backing::Heap(Vec::new()),
+            len: 0,
+            kind: PageKind::Normal,
+        }
+    }
+
+    /// Allocate a table of `len` zeroed sets, trying large pages first if `large_pages` is set.
+    /// Returns `None` if no memory could be had at all.
+    ///
+    /// The memory is zeroed across `threads`, which also faults it in on their NUMA nodes.
+    pub(super) fn allocate(
+        len: usize,
+        large_pages: bool,
+        threads: &[threadpool::WorkerThread],
+    ) -> Option<Self> {
+        if large_pages && let Some(table) = Self::allocate_large(len, threads) {
+            return Some(table);
+        }
+        let layout = std::alloc::Layout::array::<RawCacheSet>(len).ok()?;
+        if layout.size() == 0 {
+            return Some(Self::empty());
+        }
+        // SAFETY: the layout has non-zero size, and zeroed memory is a legal bitpattern for AtomicUXX.
+        unsafe {
+            let ptr = std::alloc::alloc(layout);
+            if ptr.is_null() {
+                return None;
+            }
+            threaded_memset_zero(ptr.cast(), layout.size(), threads);
+            let table: Box<[RawCacheSet]> =
+                Box::from_raw(std::ptr::slice_from_raw_parts_mut(ptr.cast(), len));
+            Some(Self {
+                backing: Backing::Heap(table.into()),
+                len,
+                kind: PageKind::Normal,
+            })
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    fn allocate_large(len: usize, threads: &[threadpool::WorkerThread]) -> Option<Self> {
+        use memmap2::{Advice, MmapOptions};
+        /// `log2` of the size of a huge page, for `MAP_HUGE_2MB`.
+        const HUGE_PAGE_BITS: u8 = 21;
+        let bytes = len
+            .checked_mul(size_of::<RawCacheSet>())?
+            .checked_next_multiple_of(1 << HUGE_PAGE_BITS)?;
+        // explicit huge pages only exist if the administrator has reserved some,
+        // so this usually fails, and we ask for transparent huge pages instead.
+        let (mut map, kind) = if let Ok(map) = MmapOptions::new()
+            .len(bytes)
+            .huge(Some(HUGE_PAGE_BITS))
+            .map_anon()
+        {
+            (map, PageKind::Large)
+        } else {
+            let map = MmapOptions::new().len(bytes).map_anon().ok()?;
+            // the mapping is still usable if transparent huge pages are disabled.
+            let kind = if map.advise(Advice::HugePage).is_ok() {
+                PageKind::Transparent
+            } else {
+                PageKind::Normal
+            };
+            (map, kind)
+        };
+        // SAFETY: the mapping is `bytes` long. anonymous mappings are zeroed already,
+        // but writing to them faults the pages in now rather than during search.
+        unsafe {
+            threaded_memset_zero(map.as_mut_ptr().cast(), bytes, threads);
+        }
+        Some(Self {
+            backing: Backing::Mapped(map),
+            len,
+            kind,
+        })
+    }
+
+    #[cfg(windows)]
+    fn allocate_large(len: usize, _threads: &[threadpool::WorkerThread]) -> Option<Self> {
+        // large pages on windows are locked in memory, and so are resident from the start.
+        let bytes = len.checked_mul(size_of::<RawCacheSet>())?;
+        windows::LargePageAllocation::new(bytes).map(|allocation| Self {
+            backing: Backing::LargePages(allocation),
+            len,
+            kind: PageKind::Large,
+        })
+    }
+
+    #[cfg(not(any(target_os = "linux", windows)))]
+    fn allocate_large(_len: usize, _threads: &[threadpool::WorkerThread]) -> Option<Self> {
+        None
+    }
+
+    /// What kind of memory backs the table.
+    pub(super) const fn kind(&self) -> PageKind {
+        self.kind
+    }
+}
+
+impl Deref for TableMemory {
+    type Target = [RawCacheSet];
+
+    fn deref(&self) -> &[RawCacheSet] {
+        match &self.backing {
+            Backing::Heap(table) => table,
+            #[cfg(target_os = "linux")]
+            Backing::Mapped(map) => {
+                // SAFETY: the mapping is page-aligned, at least `len` sets long, and
+                // zeroed, which is a legal bitpattern for AtomicUXX.
+                unsafe { std::slice::from_raw_parts(map.as_ptr().cast(), self.len) }
+            }
+            #[cfg(windows)]
+            // SAFETY: the allocation was made for `len` sets.
+            Backing::LargePages(allocation) => unsafe { allocation.as_slice(self.len) },
+        }
+    }
+}
+
+#[cfg(windows)]
+mod windows {
+    use std::ffi::c_void;
+
+    use windows_sys::Win32::{
+        Foundation::{CloseHandle, ERROR_SUCCESS, GetLastError, HANDLE, LUID},
+        Security::{
+            AdjustTokenPrivileges, LUID_AND_ATTRIBUTES, LookupPrivilegeValueW, SE_LOCK_MEMORY_NAME,
+            SE_PRIVILEGE_ENABLED, TOKEN_ADJUST_PRIVILEGES, TOKEN_PRIVILEGES, TOKEN_QUERY,
+        },
+        System::{
+            Memory::{
+                GetLargePageMinimum, MEM_COMMIT, MEM_LARGE_PAGES, MEM_RELEASE, MEM_RESERVE,
+                PAGE_READWRITE, VirtualAlloc, VirtualFree,
+            },
+            Threading::{GetCurrentProcess, OpenProcessToken},
+        },
+    };
+
+    use super::RawCacheSet;
+
+    /// Memory allocated on large pages with `VirtualAlloc`.
+    #[derive(Debug)]
+    pub(super) struct LargePageAllocation {
+        ptr: *mut c_void,
+    }
+
+    // SAFETY: the allocation is uniquely owned, and only read through atomics.
+    unsafe impl Send for LargePageAllocation {}
+    // SAFETY: as above.
+    unsafe impl Sync for LargePageAllocation {}
+
+    impl LargePageAllocation {
+        /// Allocate at least `bytes` of zeroed memory on large pages.
+        /// This needs the "Lock pages in memory" privilege, which users must be granted explicitly.
+        pub(super) fn new(bytes: usize) -> Option<Self> {
+            // SAFETY: plain system calls, with every pointer argument valid or null as permitted.
+            unsafe {
+                let page_size = GetLargePageMinimum();
+                if page_size == 0 || !enable_lock_memory_privilege() {
+                    return None;
+                }
+                let size = bytes.checked_next_multiple_of(page_size)?;
+                let ptr = VirtualAlloc(
+                    std::ptr::null(),
+                    size,
+                    MEM_RESERVE | MEM_COMMIT | MEM_LARGE_PAGES,
+                    PAGE_READWRITE,
+                );
+                (!ptr.is_null()).then_some(Self { ptr })
+            }
+        }
+
+        /// View the allocation as `len` cache sets.
+        ///
+        /// # Safety
+        ///
+        /// The allocation must be at least `len` sets long.
+        pub(super) unsafe fn as_slice(&self, len: usize) -> &[RawCacheSet] {
+            // SAFETY: the allocation is page-aligned and long enough, and committed
+            // memory is zeroed, which is a legal bitpattern for AtomicUXX.
+            unsafe { std::slice::from_raw_parts(self.ptr.cast(), len) }
+        }
+    }
+
+    impl Drop for LargePageAllocation {
+        fn drop(&mut self) {
+            // SAFETY: the pointer came from VirtualAlloc, and is released exactly once.
+            unsafe {
+                VirtualFree(self.ptr, 0, MEM_RELEASE);
+            }
+        }
+    }
+
+    /// Try to enable `SeLockMemoryPrivilege` for this process.
+    unsafe fn enable_lock_memory_privilege() -> bool {
+        // SAFETY: plain system calls, with every pointer argument valid or null as permitted.
+        unsafe {
+            let mut token: HANDLE = std::ptr::null_mut();
+            if OpenProcessToken(
+                GetCurrentProcess(),
+                TOKEN_ADJUST_PRIVILEGES | TOKEN_QUERY,
+                &raw mut token,
+            ) == 0
+            {
+                return false;
+            }
+            let mut luid = LUID {
+                LowPart: 0,
+                HighPart: 0,
+            };
+            let mut enabled = false;
+            if LookupPrivilegeValueW(std::ptr::null(), SE_LOCK_MEMORY_NAME, &raw mut luid) != 0 {
+                let privileges = TOKEN_PRIVILEGES {
+                    PrivilegeCount: 1,
+                    Privileges: [LUID_AND_ATTRIBUTES {
+                        Luid: luid,
+                        Attributes: SE_PRIVILEGE_ENABLED,
+                    }],
+                };
+                // AdjustTokenPrivileges succeeds even if the privilege wasn't granted,
+                // so the last error has to be checked too.
+                enabled = AdjustTokenPrivileges(
+                    token,
+                    0,
+                    &raw const privileges,
+                    0,
+                    std::ptr::null_mut(),
+                    std::ptr::null_mut(),
+                ) != 0
+                    && GetLastError() == ERROR_SUCCESS;
+            }
+            CloseHandle(token);
+            enabled
+        }
+    }
+}
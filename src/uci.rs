@@ -10,6 +10,7 @@ pub mod fmt;
 
 use std::{
     io::Write as _,
+    path::{Path, PathBuf},
     sync::{
         Arc, Mutex, Once,
         atomic::{self, AtomicBool, AtomicU64, Ordering},
@@ -18,46 +19,60 @@ use std::{
     time::Instant,
 };
 
+use vec1::Vec1;
+
 use crate::{
     NAME, VERSION,
     bench::BENCH_POSITIONS,
+    book::{DEFAULT_BOOK_DEPTH, MAX_BOOK_DEPTH, PolyglotBook},
     chess::{
-        board::{
-            Board,
-            movegen::{self, MoveList},
-        },
+        board::{Board, CastlingNotation, movegen},
+        chessmove::Move,
         fen::Fen,
-        piece::Colour,
         quick::Quick,
     },
     cuckoo,
-    errors::{GoParseError, PerftParseError, PositionParseError, SetOptionParseError, UciError},
-    evaluation::evaluate,
-    nnue::{self, network::NNUEParams},
+    errors::{
+        GoParseError, PerftParseError, PositionParseError, RegisterParseError, SetOptionParseError,
+        UciError,
+    },
+    evaluation::explain,
+    lookups::HM_CLOCK_KEYS,
+    nnue::{self, network::NNUEParams, small::SmallNet},
     perft,
-    search::{LMTable, adj_shuffle, parameters::Config, search_position},
-    searchinfo::{Control, SearchInfo},
+    rng::XorShiftState,
+    search::{LMTable, parameters::Config, search_position},
+    searchinfo::{Control, DEFAULT_INFO_INTERVAL_MS, MAX_MULTI_PV, SearchInfo},
+    strength::{DEFAULT_ELO, MAX_ELO, MIN_ELO},
     tablebases, term,
     threadlocal::{ThreadData, make_thread_data},
     threadpool,
-    timemgmt::SearchLimit,
-    transpositiontable::Cache,
-    util::{MAX_DEPTH, MEGABYTE},
+    timemgmt::{DEFAULT_SLOW_MOVER, SearchLimit},
+    transpositiontable::{Bound, Cache, CacheView},
+    util::{MAX_DEPTH, MEGABYTE, VALUE_NONE},
 };
 
 #[cfg(feature = "nnz-counts")]
 use crate::nnue::network::layers::{NNZ_COUNT, NNZ_DENOM};
 
-const UCI_DEFAULT_HASH_MEGABYTES: usize = 16;
-const UCI_MAX_HASH_MEGABYTES: usize = 1_048_576;
-const UCI_MAX_THREADS: usize = 512;
+pub const UCI_DEFAULT_HASH_MEGABYTES: usize = 16;
+/// The largest hash size that can be requested: 32 TiB on 64-bit platforms,
+/// or as much as fits in the address space on 32-bit ones.
+pub const UCI_MAX_HASH_MEGABYTES: usize = if cfg!(target_pointer_width = "64") {
+    33_554_432
+} else {
+    1024
+};
+pub const UCI_MAX_THREADS: usize = 512;
 const BENCH_DEPTH: usize = 14;
 const BENCH_THREADS: usize = 1;
+/// The default value of the `HashFile` option.
+const DEFAULT_HASH_FILE: &str = "viridithas.hash";
 
 static SET_TERM: Once = Once::new();
-static STDIN_READER_THREAD_KEEP_RUNNING: AtomicBool = AtomicBool::new(true);
+pub static STDIN_READER_THREAD_KEEP_RUNNING: AtomicBool = AtomicBool::new(true);
 #[allow(clippy::too_many_lines, clippy::cognitive_complexity)]
-pub fn main_loop() -> Result<(), UciError> {
+pub fn main_loop(search_config: &Config, persistent_hash: Option<&Path>) -> Result<(), UciError> {
     let version_extension = if cfg!(feature = "final-release") {
         ""
     } else {
@@ -69,10 +84,16 @@ pub fn main_loop() -> Result<(), UciError> {
 
     let mut cache = Cache::new();
     cache.resize(UCI_DEFAULT_HASH_MEGABYTES * MEGABYTE, &worker_threads); // default hash size
+    if let Some(path) = persistent_hash {
+        load_persistent_hash(&mut cache, path, &worker_threads);
+    }
+    let mut hash_file =
+        persistent_hash.map_or_else(|| PathBuf::from(DEFAULT_HASH_FILE), Path::to_path_buf);
 
     let control = Arc::new(Control::default());
-    let nnue_params =
+    let mut nnue_params =
         NNUEParams::decompress_and_alloc().map_err(|e| UciError::NnueInit(e.to_string()))?;
+    let mut small_net: Option<&'static SmallNet> = None;
 
     let (stdin, stdin_reader_handle) = stdin_reader(Arc::clone(&control))?;
     let stdin = Mutex::new(stdin);
@@ -91,6 +112,18 @@ pub fn main_loop() -> Result<(), UciError> {
     )
     .map_err(|e| UciError::NnueInit(e.to_string()))?;
     thread_data[0].info.set_stdin(&stdin);
+    for t in &mut thread_data {
+        t.info.conf = search_config.clone();
+        t.info.lm_table = LMTable::new(&t.info.conf);
+    }
+
+    let mut book: Option<Arc<PolyglotBook>> = None;
+    #[allow(clippy::cast_possible_truncation)]
+    let mut book_rng = XorShiftState::with_seed(
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |d| d.as_nanos() as u64),
+    );
 
     loop {
         std::io::stdout().flush()?;
@@ -104,107 +137,51 @@ pub fn main_loop() -> Result<(), UciError> {
         let input = line.trim();
 
         let res: Result<(), UciError> = match input {
-            "uci" => {
-                #[cfg(feature = "tuning")]
-                print_uci_response(&thread_data[0].info, true);
-                #[cfg(not(feature = "tuning"))]
-                print_uci_response(&thread_data[0].info, false);
-                control.pretty_print.store(false, Ordering::SeqCst);
-                Ok(())
-            }
-            "ucifull" => {
-                print_uci_response(&thread_data[0].info, true);
-                control.pretty_print.store(false, Ordering::SeqCst);
-                Ok(())
-            }
-            arg @ ("ucidump" | "ucidumpfull") => {
-                // dump the values of the current UCI options
-                println!("Hash: {}", cache.size() / MEGABYTE);
-                println!("Threads: {}", thread_data.len());
-                println!(
-                    "PrettyPrint: {}",
-                    control.pretty_print.load(Ordering::SeqCst)
-                );
-                println!("Ponder: {}", control.ponder.load(Ordering::SeqCst));
-                println!("UCI_Chess960: {}", control.chess960.load(Ordering::SeqCst));
-                println!(
-                    "SyzygyProbeLimit: {}",
-                    control.syzygy_probe_limit.load(Ordering::SeqCst)
-                );
-                println!(
-                    "SyzygyProbeDepth: {}",
-                    control.syzygy_probe_depth.load(Ordering::SeqCst)
-                );
-                println!("Contempt: {}", control.contempt.load(Ordering::SeqCst));
-                if arg == "ucidumpfull" {
-                    for (id, default) in Config::default().ids_with_values() {
-                        println!("{id}: {default}");
-                    }
-                }
-                Ok(())
-            }
-            "isready" => {
-                println!("readyok");
-                Ok(())
-            }
-            "quit" => {
-                control.quit.store(true, Ordering::SeqCst);
-                break;
-            }
-            "ucinewgame" => do_newgame(&cache, &mut thread_data, &worker_threads),
-            "eval" => {
-                let t = thread_data.first_mut();
-                let eval = if t.board.in_check() {
-                    0
-                } else {
-                    let eval = evaluate(t, 0);
-                    adj_shuffle(t, eval, t.board.fifty_move_counter())
-                };
-                println!("{eval}");
-                Ok(())
-            }
-            "raweval" => {
-                let t = thread_data.first_mut();
-                let eval = if t.board.in_check() {
-                    0
-                } else {
-                    t.nnue.evaluate(t.nnue_params, &t.board)
-                };
-                println!("{eval}");
-                Ok(())
-            }
-            "show" => {
-                let t = thread_data.first_mut();
-                println!("{:X}", t.board);
-                Ok(())
-            }
-            "d" | "debug" => {
-                let t = thread_data.first_mut();
-                println!("{:?}", t.board);
-                Ok(())
-            }
-            "nnuebench" => {
-                nnue::network::inference_benchmark(
-                    &thread_data[0].nnue,
-                    thread_data[0].nnue_params,
-                );
-                Ok(())
-            }
-            "gobench" => go_benchmark(nnue_params),
-            "initcuckoo" => Ok(cuckoo::init()?),
-            "initattacks" => Ok(movegen::init_sliders_attacks()?),
             input if is_cmd(input, "setoption") => {
                 let pre_config = SetOptions {
                     search_config: thread_data[0].info.conf.clone(),
                     hash_mb: cache.size() / MEGABYTE,
                     threads: thread_data.len(),
+                    book: book.clone(),
+                    hash_file: hash_file.clone(),
+                    hash_action: None,
+                    large_pages: cache.large_pages(),
+                    nnue_params,
+                    small_net,
                 };
                 let hash_before = pre_config.hash_mb;
+                let large_pages_before = pre_config.large_pages;
                 let threads_before = thread_data.len();
                 let chess960_before = control.chess960.load(Ordering::Relaxed);
                 match parse_setoption(input, pre_config, &control) {
                     Ok(conf) => {
-                        let hash_changed = hash_before != conf.hash_mb;
+                        book.clone_from(&conf.book);
+                        let nets_changed = !std::ptr::eq(nnue_params, conf.nnue_params)
+                            || small_net.map(std::ptr::from_ref)
+                                != conf.small_net.map(std::ptr::from_ref);
+                        nnue_params = conf.nnue_params;
+                        small_net = conf.small_net;
+                        hash_file.clone_from(&conf.hash_file);
+                        let mut hash_file_result = if conf.hash_action == Some(HashAction::Save) {
+                            cache
+                                .save(&hash_file)
+                                .map(|()| {
+                                    println!(
+                                        "info string saved hash table to {}",
+                                        hash_file.display()
+                                    );
+                                })
+                                .map_err(|source| UciError::HashFile {
+                                    path: hash_file.display().to_string(),
+                                    source,
+                                })
+                        } else {
+                            Ok(())
+                        };
+                        let load_hash = conf.hash_action == Some(HashAction::Load);
+                        // switching page kinds needs a fresh allocation, as a resize does.
+                        let hash_changed =
+                            hash_before != conf.hash_mb || large_pages_before != conf.large_pages;
                         let threads_changed = threads_before != conf.threads;
                         if threads_changed {
                             println!(
@@ -216,11 +193,26 @@ pub fn main_loop() -> Result<(), UciError> {
                                 .for_each(threadpool::WorkerThread::join);
                             worker_threads = threadpool::make_worker_threads(conf.threads);
                         }
-                        if hash_changed || threads_changed {
+                        if hash_changed || threads_changed || load_hash {
                             let pos = thread_data[0].board.clone();
                             // Drop all thread data before resizing, as they borrow the old TT.
                             std::mem::drop(thread_data);
-                            if hash_changed {
+                            cache.set_large_pages(conf.large_pages);
+                            if load_hash {
+                                hash_file_result = cache
+                                    .load(&hash_file, &worker_threads)
+                                    .map(|()| {
+                                        println!(
+                                            "info string loaded {}mb hash table from {}",
+                                            cache.size() / MEGABYTE,
+                                            hash_file.display()
+                                        );
+                                    })
+                                    .map_err(|source| UciError::HashFile {
+                                        path: hash_file.display().to_string(),
+                                        source,
+                                    });
+                            } else if hash_changed {
                                 cache.resize(conf.hash_mb * MEGABYTE, &worker_threads);
                             }
                             thread_data = make_thread_data(
@@ -240,6 +232,13 @@ pub fn main_loop() -> Result<(), UciError> {
                             t.info.conf = conf.search_config.clone();
                             t.info.lm_table = LMTable::new(&t.info.conf);
                             t.info.set_stdin(&stdin);
+                            t.small_net = small_net;
+                            if nets_changed {
+                                t.nnue_params = nnue_params;
+                                t.nnue.reïnit_from(&t.board, nnue_params);
+                                // cached evaluations came from the old networks.
+                                t.eval_cache.clear();
+                            }
                         }
 
                         let chess960 = control.chess960.load(Ordering::Relaxed);
@@ -257,118 +256,35 @@ pub fn main_loop() -> Result<(), UciError> {
                             }
                         }
 
-                        Ok(())
+                        hash_file_result
                     }
                     Err(e) => Err(e.into()),
                 }
             }
-            input if is_cmd(input, "position") => thread_data
-                .iter_mut()
-                .try_for_each(|t| {
-                    parse_position(input, &mut t.board)?;
-                    t.nnue.reïnit_from(&t.board, t.nnue_params);
-                    Ok::<_, PositionParseError>(())
-                })
-                .map_err(Into::into),
-            input if is_cmd(input, "go perft") || is_cmd(input, "perft") => {
-                parse_perft(thread_data.first_mut(), input)
-            }
-            input if is_cmd(input, "go") => {
-                // start the clock *immediately*
-                thread_data[0].info.clock.start();
-
-                // if we're in pretty-printing mode, set the terminal properly:
-                if control.pretty_print.load(Ordering::SeqCst) {
-                    SET_TERM.call_once(|| {
-                        term::set_mode_uci();
-                    });
-                }
+            input => handle_command(
+                input,
+                &mut thread_data,
+                Session {
+                    cache: &cache,
+                    control: &control,
+                    worker_threads: &worker_threads,
+                    nnue_params,
+                    book: book.as_deref(),
+                    book_rng: &mut book_rng,
+                    hash_file: &hash_file,
+                },
+            ),
+        };
 
-                match parse_go(input, thread_data[0].board.turn(), &control) {
-                    Ok(search_limit) => {
-                        thread_data[0].info.clock.set_limit(search_limit);
-                        cache.increase_age();
-                        search_position(&worker_threads, &mut thread_data);
-                        Ok(())
-                    }
-                    Err(e) => Err(e.into()),
-                }
-            }
-            "ponderhit" => {
-                println!("info error ponderhit given while not searching.");
-                Ok(())
-            }
-            benchcmd @ ("bench" | "benchfull") => {
-                bench(benchcmd, &thread_data[0].info.conf, nnue_params, None, None)
-            }
-            command => {
-                // before failing outright, try to parse as a fen:
-                if command.contains('/')
-                    && let Ok(fen) = Fen::parse_relaxed(command)
-                {
-                    for t in &mut thread_data {
-                        t.board.set_from_fen(&fen);
-                        for tok in command.split_whitespace() {
-                            if let Ok(mv) =
-                                t.board.parse_uci(tok).or_else(|_| t.board.parse_san(tok))
-                            {
-                                t.board.make_move_simple(mv);
-                                t.board.zero_height();
-                            }
-                        }
-                        t.board.zero_height();
-                        t.nnue.reïnit_from(&t.board, t.nnue_params);
-                    }
-                    Ok(())
-                // then try to quick-ly parse
-                } else if let Some(first_40) = command.get(..command.len().min(40))
-                    && let Ok(quick) = Quick::parse(first_40)
-                {
-                    for t in &mut thread_data {
-                        t.board.set_from_quick(&quick);
-                        for tok in command.split_whitespace() {
-                            if let Ok(mv) =
-                                t.board.parse_uci(tok).or_else(|_| t.board.parse_san(tok))
-                            {
-                                t.board.make_move_simple(mv);
-                                t.board.zero_height();
-                            }
-                        }
-                        t.board.zero_height();
-                        t.nnue.reïnit_from(&t.board, t.nnue_params);
-                    }
-                    Ok(())
-                // lastly, attempt to find some legal moves
-                // this is a tad iffy, and comes the closest to
-                // silently accepting keysmashes
-                } else if command.split_whitespace().any(|tok| {
-                    thread_data[0]
-                        .board
-                        .parse_uci(tok)
-                        .or_else(|_| thread_data[0].board.parse_san(tok))
-                        .is_ok()
-                }) {
-                    for t in &mut thread_data {
-                        for tok in command.split_whitespace() {
-                            if let Ok(mv) =
-                                t.board.parse_uci(tok).or_else(|_| t.board.parse_san(tok))
-                            {
-                                t.board.make_move_simple(mv);
-                                t.board.zero_height();
-                            }
-                        }
-                        t.board.zero_height();
-                        t.nnue.reïnit_from(&t.board, t.nnue_params);
-                    }
-                    Ok(())
-                } else {
-                    Err(UciError::UnknownCommand(input.to_string()))
+        match res {
+            // unknown commands are silently ignored unless the GUI has asked for debug output.
+            Err(UciError::UnknownCommand(cmd)) => {
+                if control.debug.load(Ordering::SeqCst) {
+                    eprintln!("info string unknown command: {cmd}");
                 }
             }
-        };
-
-        if let Err(e) = res {
-            eprintln!("info string {e}");
+            Err(e) => eprintln!("info string {e}"),
+            Ok(()) => (),
         }
 
         if control.quit.load(Ordering::SeqCst) {
@@ -376,6 +292,9 @@ pub fn main_loop() -> Result<(), UciError> {
             break;
         }
     }
+    if let Some(path) = persistent_hash {
+        save_persistent_hash(&cache, path);
+    }
     STDIN_READER_THREAD_KEEP_RUNNING.store(false, atomic::Ordering::SeqCst);
     if stdin_reader_handle.is_finished() {
         stdin_reader_handle
@@ -389,11 +308,315 @@ pub fn main_loop() -> Result<(), UciError> {
     Ok(())
 }
 
+/// The engine state that UCI commands act on, besides the thread data.
+struct Session<'a> {
+    cache: &'a Cache,
+    control: &'a Control,
+    worker_threads: &'a [threadpool::WorkerThread],
+    nnue_params: &'static NNUEParams,
+    book: Option<&'a PolyglotBook>,
+    book_rng: &'a mut XorShiftState,
+    hash_file: &'a Path,
+}
+
+/// Run a single line of UCI input, other than `setoption`, which can reallocate
+/// the state that the thread data borrows, and so is handled by [`main_loop`].
+#[allow(clippy::too_many_lines)]
+fn handle_command(
+    input: &str,
+    thread_data: &mut Vec1<Box<ThreadData>>,
+    session: Session,
+) -> Result<(), UciError> {
+    let Session {
+        cache,
+        control,
+        worker_threads,
+        nnue_params,
+        book,
+        book_rng,
+        hash_file,
+    } = session;
+    match input {
+        "uci" => {
+            #[cfg(feature = "tuning")]
+            print_uci_response(&thread_data[0].info, true);
+            #[cfg(not(feature = "tuning"))]
+            print_uci_response(&thread_data[0].info, false);
+            control.pretty_print.store(false, Ordering::SeqCst);
+            Ok(())
+        }
+        "ucifull" => {
+            print_uci_response(&thread_data[0].info, true);
+            control.pretty_print.store(false, Ordering::SeqCst);
+            Ok(())
+        }
+        arg @ ("ucidump" | "ucidumpfull") => {
+            // dump the values of the current UCI options
+            println!("Hash: {}", cache.size() / MEGABYTE);
+            println!("Threads: {}", thread_data.len());
+            println!(
+                "PrettyPrint: {}",
+                control.pretty_print.load(Ordering::SeqCst)
+            );
+            println!("Ponder: {}", control.ponder.load(Ordering::SeqCst));
+            println!("UCI_Chess960: {}", control.chess960.load(Ordering::SeqCst));
+            println!(
+                "SyzygyProbeLimit: {}",
+                control.syzygy_probe_limit.load(Ordering::SeqCst)
+            );
+            println!(
+                "SyzygyProbeDepth: {}",
+                control.syzygy_probe_depth.load(Ordering::SeqCst)
+            );
+            println!("Contempt: {}", control.contempt.load(Ordering::SeqCst));
+            println!(
+                "InfoInterval: {}",
+                control.info_interval_ms.load(Ordering::SeqCst)
+            );
+            println!("MultiPV: {}", control.multi_pv.load(Ordering::SeqCst));
+            println!(
+                "UCI_LimitStrength: {}",
+                control.limit_strength.load(Ordering::SeqCst)
+            );
+            println!("UCI_Elo: {}", control.uci_elo.load(Ordering::SeqCst));
+            println!("OwnBook: {}", control.own_book.load(Ordering::SeqCst));
+            println!(
+                "AgeHashOnNewGame: {}",
+                control.age_hash_on_new_game.load(Ordering::SeqCst)
+            );
+            println!("BookDepth: {}", control.book_depth.load(Ordering::SeqCst));
+            println!("UseMCTS: {}", control.use_mcts.load(Ordering::SeqCst));
+            println!("nodestime: {}", control.nodes_time.load(Ordering::SeqCst));
+            println!(
+                "MinThinkTime: {}",
+                control.min_think_time.load(Ordering::SeqCst)
+            );
+            println!("SlowMover: {}", control.slow_mover.load(Ordering::SeqCst));
+            println!("HashFile: {}", hash_file.display());
+            println!("LargePages: {}", cache.large_pages());
+            if arg == "ucidumpfull" {
+                for (id, default) in Config::default().ids_with_values() {
+                    println!("{id}: {default}");
+                }
+            }
+            Ok(())
+        }
+        "isready" => {
+            println!("readyok");
+            Ok(())
+        }
+        "quit" => {
+            control.quit.store(true, Ordering::SeqCst);
+            Ok(())
+        }
+        "ucinewgame" => do_newgame(cache, thread_data, worker_threads),
+        "eval" => {
+            let t = thread_data.first_mut();
+            println!("{}", explain(t));
+            Ok(())
+        }
+        "raweval" => {
+            let t = thread_data.first_mut();
+            let eval = if t.board.in_check() {
+                0
+            } else {
+                t.nnue.evaluate(t.nnue_params, &t.board)
+            };
+            println!("{eval}");
+            Ok(())
+        }
+        "show" => {
+            let t = thread_data.first_mut();
+            println!("{:X}", t.board);
+            Ok(())
+        }
+        "fen" => {
+            println!("{}", thread_data[0].board);
+            Ok(())
+        }
+        "fen xfen" => {
+            println!("{}", thread_data[0].board.fen(CastlingNotation::XFen));
+            Ok(())
+        }
+        "fen shredder" => {
+            println!("{}", thread_data[0].board.fen(CastlingNotation::Shredder));
+            Ok(())
+        }
+        "probe" => {
+            println!(
+                "{}",
+                describe_cache_entry(&thread_data[0].board, cache.view())
+            );
+            Ok(())
+        }
+        "debug on" => {
+            control.debug.store(true, Ordering::SeqCst);
+            Ok(())
+        }
+        "debug off" => {
+            control.debug.store(false, Ordering::SeqCst);
+            Ok(())
+        }
+        // we don't require registration, so `register` is acknowledged and ignored.
+        input if is_cmd(input, "register") => parse_register(input).map_err(Into::into),
+        "d" | "debug" => {
+            let t = thread_data.first_mut();
+            println!("{:?}", t.board);
+            Ok(())
+        }
+        "nnuebench" => {
+            nnue::network::inference_benchmark(&thread_data[0].nnue, thread_data[0].nnue_params);
+            Ok(())
+        }
+        "gobench" => go_benchmark(nnue_params),
+        "initcuckoo" => Ok(cuckoo::init()?),
+        "initattacks" => Ok(movegen::init_sliders_attacks()?),
+        input if is_cmd(input, "position") => thread_data
+            .iter_mut()
+            .try_for_each(|t| {
+                parse_position(input, &mut t.board)?;
+                t.nnue.reïnit_from(&t.board, t.nnue_params);
+                Ok::<_, PositionParseError>(())
+            })
+            .map_err(Into::into),
+        input if is_cmd(input, "go perft") || is_cmd(input, "perft") => {
+            parse_perft(thread_data.first(), input)
+        }
+        input if is_cmd(input, "go") => {
+            // start the clock *immediately*
+            thread_data[0].info.clock.start();
+
+            // if we're in pretty-printing mode, set the terminal properly:
+            if control.pretty_print.load(Ordering::SeqCst) {
+                SET_TERM.call_once(|| {
+                    term::set_mode_uci();
+                });
+            }
+
+            match parse_go(input, &thread_data[0].board, control) {
+                Ok((search_limit, searchmoves)) => {
+                    if let Some(book) = book
+                        && let Some(m) = book_move(
+                            book,
+                            &thread_data[0].board,
+                            &search_limit,
+                            &searchmoves,
+                            control,
+                            book_rng,
+                        )
+                    {
+                        println!("info string playing book move");
+                        println!("bestmove {}", m.display(thread_data[0].board.rules()));
+                        return Ok(());
+                    }
+                    thread_data[0].info.clock.set_limit(search_limit);
+                    for t in thread_data.iter_mut() {
+                        t.searchmoves.clone_from(&searchmoves);
+                    }
+                    cache.increase_age();
+                    search_position(worker_threads, thread_data);
+                    Ok(())
+                }
+                Err(e) => Err(e.into()),
+            }
+        }
+        "ponderhit" => {
+            println!("info error ponderhit given while not searching.");
+            Ok(())
+        }
+        benchcmd @ ("bench" | "benchfull") => {
+            bench(benchcmd, &thread_data[0].info.conf, nnue_params, None, None)
+        }
+        command => {
+            // before failing outright, try to parse as a fen:
+            if command.contains('/')
+                && let Ok(fen) = Fen::parse_relaxed(command)
+            {
+                for t in thread_data.iter_mut() {
+                    t.board.set_from_fen(&fen);
+                    for tok in command.split_whitespace() {
+                        if let Ok(mv) = t.board.parse_uci(tok).or_else(|_| t.board.parse_san(tok)) {
+                            t.board.make_move_simple(mv);
+                            t.board.zero_height();
+                        }
+                    }
+                    t.board.zero_height();
+                    t.nnue.reïnit_from(&t.board, t.nnue_params);
+                }
+                Ok(())
+            // then try to quick-ly parse
+            } else if let Some(first_40) = command.get(..command.len().min(40))
+                && let Ok(quick) = Quick::parse(first_40)
+            {
+                for t in thread_data.iter_mut() {
+                    t.board.set_from_quick(&quick);
+                    for tok in command.split_whitespace() {
+                        if let Ok(mv) = t.board.parse_uci(tok).or_else(|_| t.board.parse_san(tok)) {
+                            t.board.make_move_simple(mv);
+                            t.board.zero_height();
+                        }
+                    }
+                    t.board.zero_height();
+                    t.nnue.reïnit_from(&t.board, t.nnue_params);
+                }
+                Ok(())
+            // lastly, attempt to find some legal moves
+            // this is a tad iffy, and comes the closest to
+            // silently accepting keysmashes
+            } else if command.split_whitespace().any(|tok| {
+                thread_data[0]
+                    .board
+                    .parse_uci(tok)
+                    .or_else(|_| thread_data[0].board.parse_san(tok))
+                    .is_ok()
+            }) {
+                for t in thread_data.iter_mut() {
+                    for tok in command.split_whitespace() {
+                        if let Ok(mv) = t.board.parse_uci(tok).or_else(|_| t.board.parse_san(tok)) {
+                            t.board.make_move_simple(mv);
+                            t.board.zero_height();
+                        }
+                    }
+                    t.board.zero_height();
+                    t.nnue.reïnit_from(&t.board, t.nnue_params);
+                }
+                Ok(())
+            } else {
+                Err(UciError::UnknownCommand(input.to_string()))
+            }
+        }
+    }
+}
+
 /// Check if `input` is the command `cmd` itself, or starts with `cmd` followed by a space.
 fn is_cmd(input: &str, cmd: &str) -> bool {
     input == cmd || (input.starts_with(cmd) && input.as_bytes().get(cmd.len()) == Some(&b' '))
 }
 
+// register later
+// register name Stefan MK code 4359874324
+fn parse_register(text: &str) -> Result<(), RegisterParseError> {
+    let mut parts = text.split_ascii_whitespace().skip(1).peekable();
+    match parts.next() {
+        None => Err(RegisterParseError::MissingArguments),
+        Some("later") => Ok(()),
+        Some("name") => {
+            let mut got_name = false;
+            while parts.next_if(|&tok| tok != "code").is_some() {
+                got_name = true;
+            }
+            if !got_name {
+                return Err(RegisterParseError::MissingName);
+            }
+            match (parts.next(), parts.next()) {
+                (Some("code"), Some(_)) => Ok(()),
+                _ => Err(RegisterParseError::MissingCode),
+            }
+        }
+        Some(other) => Err(RegisterParseError::UnexpectedToken(other.to_string())),
+    }
+}
+
 // position fen
 // position startpos
 // ... moves e2e4 e7e5 b7b8q
@@ -467,8 +690,30 @@ fn parse_position(text: &str, pos: &mut Board) -> Result<(), PositionParseError>
     Ok(())
 }
 
-fn parse_go(text: &str, stm: Colour, control: &Control) -> Result<SearchLimit, GoParseError> {
+/// Parse a `go` command into a search limit and the `searchmoves` to restrict the root to.
+fn parse_go(
+    text: &str,
+    board: &Board,
+    control: &Control,
+) -> Result<(SearchLimit, Vec<Move>), GoParseError> {
     #![allow(clippy::too_many_lines)]
+    const SUBCOMMANDS: [&str; 13] = [
+        "depth",
+        "movestogo",
+        "movetime",
+        "wtime",
+        "btime",
+        "winc",
+        "binc",
+        "infinite",
+        "mate",
+        "nodes",
+        "ponder",
+        "searchmoves",
+        "perft",
+    ];
+
+    let stm = board.turn();
 
     let mut depth: Option<usize> = None;
     let mut moves_to_go: Option<u64> = None;
@@ -478,8 +723,9 @@ fn parse_go(text: &str, stm: Colour, control: &Control) -> Result<SearchLimit, G
     let mut nodes: Option<u64> = None;
     let mut limit = SearchLimit::Infinite;
     let mut ponder = false;
+    let mut searchmoves = Vec::new();
 
-    let mut parts = text.split_ascii_whitespace();
+    let mut parts = text.split_ascii_whitespace().peekable();
     let command = parts.next().ok_or(GoParseError::EmptyCommand)?;
     debug_assert_eq!(command, "go", "parse_go called with non-go command");
 
@@ -501,6 +747,20 @@ fn parse_go(text: &str, stm: Colour, control: &Control) -> Result<SearchLimit, G
             }
             "nodes" => nodes = Some(go_part_parse("nodes", parts.next())?),
             "ponder" => ponder = true,
+            "searchmoves" => {
+                while let Some(text) = parts.next_if(|p| !SUBCOMMANDS.contains(p)) {
+                    let m = board.parse_uci(text).map_err(|source| {
+                        GoParseError::InvalidSearchMove {
+                            text: text.to_string(),
+                            source,
+                        }
+                    })?;
+                    searchmoves.push(m);
+                }
+                if searchmoves.is_empty() {
+                    return Err(GoParseError::EmptySearchMoves);
+                }
+            }
             other => return Err(GoParseError::UnknownSubcommand(other.to_string())),
         }
     }
@@ -540,7 +800,7 @@ fn parse_go(text: &str, stm: Colour, control: &Control) -> Result<SearchLimit, G
         limit = limit.to_pondering();
     }
 
-    Ok(limit)
+    Ok((limit, searchmoves))
 }
 
 fn go_part_parse<T>(param: &'static str, next_part: Option<&str>) -> Result<T, GoParseError>
@@ -553,7 +813,7 @@ where
         .map_err(|e| GoParseError::InvalidValue { param, source: e })
 }
 
-fn parse_perft(t: &mut ThreadData<'_>, input: &str) -> Result<(), UciError> {
+fn parse_perft(t: &ThreadData<'_>, input: &str) -> Result<(), UciError> {
     let tail = input
         .strip_prefix("go perft")
         .or_else(|| input.strip_prefix("perft"))
@@ -575,7 +835,7 @@ fn parse_perft(t: &mut ThreadData<'_>, input: &str) -> Result<(), UciError> {
                     text: depth_str.to_string(),
                     source: e,
                 })?;
-            divide_perft(depth, &mut t.board);
+            perft::divide(&t.board, depth);
             Ok(())
         }
         Some(depth_str) => {
@@ -585,17 +845,30 @@ fn parse_perft(t: &mut ThreadData<'_>, input: &str) -> Result<(), UciError> {
                     text: depth_str.to_string(),
                     source: e,
                 })?;
-            block_perft(depth, &mut t.board);
+            block_perft(depth, &t.board);
             Ok(())
         }
         None => Err(PerftParseError::MissingDepth.into()),
     }
 }
 
+/// An operation on the hash table, requested with a button option.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum HashAction {
+    Save,
+    Load,
+}
+
 struct SetOptions {
     pub search_config: Config,
     pub hash_mb: usize,
     pub threads: usize,
+    pub book: Option<Arc<PolyglotBook>>,
+    pub hash_file: PathBuf,
+    pub hash_action: Option<HashAction>,
+    pub large_pages: bool,
+    pub nnue_params: &'static NNUEParams,
+    pub small_net: Option<&'static SmallNet>,
 }
 
 #[allow(clippy::too_many_lines)]
@@ -616,9 +889,16 @@ fn parse_setoption(
         ));
     }
     let opt_name = parts.next().ok_or(SetOptionParseError::MissingOptionName)?;
-    let value_part = parts
-        .next()
-        .ok_or_else(|| SetOptionParseError::ExpectedValueKeyword(String::new()))?;
+    // buttons are sent without a value.
+    let Some(value_part) = parts.next() else {
+        let mut out = pre_config;
+        out.hash_action = match opt_name {
+            "SaveHash" => Some(HashAction::Save),
+            "LoadHash" => Some(HashAction::Load),
+            _ => return Err(SetOptionParseError::ExpectedValueKeyword(String::new())),
+        };
+        return Ok(out);
+    };
     if value_part != "value" {
         return Err(SetOptionParseError::ExpectedValueKeyword(
             value_part.to_string(),
@@ -765,6 +1045,226 @@ fn parse_setoption(
             }
             control.contempt.store(value, Ordering::SeqCst);
         }
+        "InfoInterval" => {
+            let value: u64 =
+                opt_value
+                    .parse()
+                    .map_err(|e| SetOptionParseError::InvalidIntValue {
+                        name: "InfoInterval".to_string(),
+                        source: e,
+                    })?;
+            if value > 10000 {
+                return Err(SetOptionParseError::ValueOutOfRange {
+                    name: "InfoInterval".to_string(),
+                    lo: 0,
+                    hi: 10000,
+                    got: i64::try_from(value).unwrap_or(i64::MAX),
+                });
+            }
+            control.info_interval_ms.store(value, Ordering::SeqCst);
+        }
+        "MultiPV" => {
+            let value: usize =
+                opt_value
+                    .parse()
+                    .map_err(|e| SetOptionParseError::InvalidIntValue {
+                        name: "MultiPV".to_string(),
+                        source: e,
+                    })?;
+            if !(1..=MAX_MULTI_PV).contains(&value) {
+                return Err(SetOptionParseError::ValueOutOfRange {
+                    name: "MultiPV".to_string(),
+                    lo: 1,
+                    hi: i64::try_from(MAX_MULTI_PV).unwrap_or(i64::MAX),
+                    got: i64::try_from(value).unwrap_or(i64::MAX),
+                });
+            }
+            control.multi_pv.store(value, Ordering::SeqCst);
+        }
+        "OwnBook" => {
+            let val: bool =
+                opt_value
+                    .parse()
+                    .map_err(|e| SetOptionParseError::InvalidBoolValue {
+                        name: "OwnBook".to_string(),
+                        source: e,
+                    })?;
+            control.own_book.store(val, Ordering::SeqCst);
+        }
+        "UseMCTS" => {
+            let val: bool =
+                opt_value
+                    .parse()
+                    .map_err(|e| SetOptionParseError::InvalidBoolValue {
+                        name: "UseMCTS".to_string(),
+                        source: e,
+                    })?;
+            control.use_mcts.store(val, Ordering::SeqCst);
+        }
+        "nodestime" => {
+            let value: u64 =
+                opt_value
+                    .parse()
+                    .map_err(|e| SetOptionParseError::InvalidIntValue {
+                        name: "nodestime".to_string(),
+                        source: e,
+                    })?;
+            if value > 10000 {
+                return Err(SetOptionParseError::ValueOutOfRange {
+                    name: "nodestime".to_string(),
+                    lo: 0,
+                    hi: 10000,
+                    got: i64::try_from(value).unwrap_or(i64::MAX),
+                });
+            }
+            control.nodes_time.store(value, Ordering::SeqCst);
+        }
+        "MinThinkTime" => {
+            let value: u64 =
+                opt_value
+                    .parse()
+                    .map_err(|e| SetOptionParseError::InvalidIntValue {
+                        name: "MinThinkTime".to_string(),
+                        source: e,
+                    })?;
+            if value > 5000 {
+                return Err(SetOptionParseError::ValueOutOfRange {
+                    name: "MinThinkTime".to_string(),
+                    lo: 0,
+                    hi: 5000,
+                    got: i64::try_from(value).unwrap_or(i64::MAX),
+                });
+            }
+            control.min_think_time.store(value, Ordering::SeqCst);
+        }
+        "SlowMover" => {
+            let value: u64 =
+                opt_value
+                    .parse()
+                    .map_err(|e| SetOptionParseError::InvalidIntValue {
+                        name: "SlowMover".to_string(),
+                        source: e,
+                    })?;
+            if !(10..=1000).contains(&value) {
+                return Err(SetOptionParseError::ValueOutOfRange {
+                    name: "SlowMover".to_string(),
+                    lo: 10,
+                    hi: 1000,
+                    got: i64::try_from(value).unwrap_or(i64::MAX),
+                });
+            }
+            control.slow_mover.store(value, Ordering::SeqCst);
+        }
+        "AgeHashOnNewGame" => {
+            let val: bool =
+                opt_value
+                    .parse()
+                    .map_err(|e| SetOptionParseError::InvalidBoolValue {
+                        name: "AgeHashOnNewGame".to_string(),
+                        source: e,
+                    })?;
+            control.age_hash_on_new_game.store(val, Ordering::SeqCst);
+        }
+        "BookFile" => {
+            if opt_value == "<empty>" {
+                out.book = None;
+            } else {
+                let book =
+                    PolyglotBook::load(opt_value).map_err(|source| SetOptionParseError::Book {
+                        path: opt_value.to_string(),
+                        source,
+                    })?;
+                println!(
+                    "info string loaded {} book entries from {opt_value}",
+                    book.len()
+                );
+                out.book = Some(Arc::new(book));
+            }
+        }
+        "EvalFile" => {
+            let loaded = if opt_value == "<empty>" {
+                NNUEParams::decompress_and_alloc()
+            } else {
+                NNUEParams::from_file(Path::new(opt_value))
+            };
+            out.nnue_params = loaded.map_err(|e| SetOptionParseError::Network {
+                path: opt_value.to_string(),
+                message: format!("{e:#}"),
+            })?;
+            if opt_value != "<empty>" {
+                println!("info string loaded network from {opt_value}");
+            }
+        }
+        "EvalFileSmall" => {
+            if opt_value == "<empty>" {
+                out.small_net = None;
+            } else {
+                let net = SmallNet::load(Path::new(opt_value)).map_err(|e| {
+                    SetOptionParseError::Network {
+                        path: opt_value.to_string(),
+                        message: format!("{e:#}"),
+                    }
+                })?;
+                println!("info string loaded small network from {opt_value}");
+                out.small_net = Some(net);
+            }
+        }
+        "HashFile" => out.hash_file = PathBuf::from(opt_value),
+        "LargePages" => {
+            out.large_pages =
+                opt_value
+                    .parse()
+                    .map_err(|e| SetOptionParseError::InvalidBoolValue {
+                        name: "LargePages".to_string(),
+                        source: e,
+                    })?;
+        }
+        "BookDepth" => {
+            let value: usize =
+                opt_value
+                    .parse()
+                    .map_err(|e| SetOptionParseError::InvalidIntValue {
+                        name: "BookDepth".to_string(),
+                        source: e,
+                    })?;
+            if !(1..=MAX_BOOK_DEPTH).contains(&value) {
+                return Err(SetOptionParseError::ValueOutOfRange {
+                    name: "BookDepth".to_string(),
+                    lo: 1,
+                    hi: i64::try_from(MAX_BOOK_DEPTH).unwrap_or(i64::MAX),
+                    got: i64::try_from(value).unwrap_or(i64::MAX),
+                });
+            }
+            control.book_depth.store(value, Ordering::SeqCst);
+        }
+        "UCI_LimitStrength" => {
+            let val: bool =
+                opt_value
+                    .parse()
+                    .map_err(|e| SetOptionParseError::InvalidBoolValue {
+                        name: "UCI_LimitStrength".to_string(),
+                        source: e,
+                    })?;
+            control.limit_strength.store(val, Ordering::SeqCst);
+        }
+        "UCI_Elo" => {
+            let value: u32 =
+                opt_value
+                    .parse()
+                    .map_err(|e| SetOptionParseError::InvalidIntValue {
+                        name: "UCI_Elo".to_string(),
+                        source: e,
+                    })?;
+            if !(MIN_ELO..=MAX_ELO).contains(&value) {
+                return Err(SetOptionParseError::ValueOutOfRange {
+                    name: "UCI_Elo".to_string(),
+                    lo: i64::from(MIN_ELO),
+                    hi: i64::from(MAX_ELO),
+                    got: i64::from(value),
+                });
+            }
+            control.uci_elo.store(value, Ordering::SeqCst);
+        }
         "UCI_Chess960" => {
             let val: bool =
                 opt_value
@@ -782,12 +1282,35 @@ fn parse_setoption(
     Ok(out)
 }
 
-type StdinReader = (
+/// Pick a move from the opening book, if the book is enabled and the search allows it.
+/// Analysis and pondering always search, as they must run until told to stop.
+fn book_move(
+    book: &PolyglotBook,
+    board: &Board,
+    limit: &SearchLimit,
+    searchmoves: &[Move],
+    control: &Control,
+    rng: &mut XorShiftState,
+) -> Option<Move> {
+    if !control.own_book.load(Ordering::Relaxed)
+        || board.ply() >= control.book_depth.load(Ordering::Relaxed)
+        || matches!(
+            limit,
+            SearchLimit::Infinite | SearchLimit::Pondering { .. } | SearchLimit::Mate { .. }
+        )
+    {
+        return None;
+    }
+    book.probe(board, rng)
+        .filter(|m| searchmoves.is_empty() || searchmoves.contains(m))
+}
+
+pub type StdinReader = (
     mpsc::Receiver<String>,
     std::thread::JoinHandle<Result<(), UciError>>,
 );
 
-fn stdin_reader(control: Arc<Control>) -> Result<StdinReader, std::io::Error> {
+pub fn stdin_reader(control: Arc<Control>) -> Result<StdinReader, std::io::Error> {
     let (sender, receiver) = mpsc::channel();
     let handle = std::thread::Builder::new()
         .name("stdin-reader".into())
@@ -842,8 +1365,30 @@ fn print_uci_response(info: &SearchInfo, full: bool) {
     println!("option name SyzygyProbeLimit type spin default 7 min 0 max 7");
     println!("option name SyzygyProbeDepth type spin default 1 min 1 max 100");
     println!("option name Contempt type spin default 0 min -10000 max 10000");
+    println!(
+        "option name InfoInterval type spin default {DEFAULT_INFO_INTERVAL_MS} min 0 max 10000"
+    );
+    println!("option name MultiPV type spin default 1 min 1 max {MAX_MULTI_PV}");
     println!("option name Ponder type check default false");
     println!("option name UCI_Chess960 type check default false");
+    println!("option name OwnBook type check default false");
+    println!("option name BookFile type string default <empty>");
+    println!(
+        "option name BookDepth type spin default {DEFAULT_BOOK_DEPTH} min 1 max {MAX_BOOK_DEPTH}"
+    );
+    println!("option name EvalFile type string default <empty>");
+    println!("option name EvalFileSmall type string default <empty>");
+    println!("option name HashFile type string default {DEFAULT_HASH_FILE}");
+    println!("option name SaveHash type button");
+    println!("option name LoadHash type button");
+    println!("option name LargePages type check default false");
+    println!("option name AgeHashOnNewGame type check default false");
+    println!("option name UseMCTS type check default false");
+    println!("option name nodestime type spin default 0 min 0 max 10000");
+    println!("option name MinThinkTime type spin default 0 min 0 max 5000");
+    println!("option name SlowMover type spin default {DEFAULT_SLOW_MOVER} min 10 max 1000");
+    println!("option name UCI_LimitStrength type check default false");
+    println!("option name UCI_Elo type spin default {DEFAULT_ELO} min {MIN_ELO} max {MAX_ELO}");
     if full {
         for (id, default, min, max, _) in info.conf.base_config() {
             println!("option name {id} type spin default {default} min {min} max {max}");
@@ -899,9 +1444,9 @@ pub fn bench(
             t.nnue.reïnit_from(&t.board, nnue_params);
         }
         thread_data[0].info.clock.start();
-        let res = parse_go(&bench_string, thread_data[0].board.turn(), &control);
+        let res = parse_go(&bench_string, &thread_data[0].board, &control);
         match res {
-            Ok(limit) => thread_data[0].info.clock.set_limit(limit),
+            Ok((limit, _)) => thread_data[0].info.clock.set_limit(limit),
             Err(e) => {
                 thread_data[0].info.print_to_stdout = true;
                 return Err(e.into());
@@ -989,9 +1534,9 @@ pub fn go_benchmark(nnue_params: &'static NNUEParams) -> Result<(), UciError> {
     let start = std::time::Instant::now();
     for _ in 0..COUNT {
         thread_data[0].info.clock.start();
-        let limit = parse_go(
+        let (limit, _) = parse_go(
             std::hint::black_box("go wtime 0 btime 0 winc 0 binc 0"),
-            thread_data[0].board.turn(),
+            &thread_data[0].board,
             &control,
         )?;
         thread_data[0].info.clock.set_limit(limit);
@@ -1004,7 +1549,7 @@ pub fn go_benchmark(nnue_params: &'static NNUEParams) -> Result<(), UciError> {
     Ok(())
 }
 
-fn block_perft(depth: usize, pos: &mut Board) {
+fn block_perft(depth: usize, pos: &Board) {
     #![allow(clippy::cast_possible_truncation, clippy::cast_precision_loss)]
     let start_time = Instant::now();
     let nodes = perft::perft(pos, depth);
@@ -1016,39 +1561,225 @@ fn block_perft(depth: usize, pos: &mut Board) {
     );
 }
 
-fn divide_perft(depth: usize, pos: &mut Board) {
-    #![allow(clippy::cast_possible_truncation, clippy::cast_precision_loss)]
-    let start_time = Instant::now();
-    let mut nodes = 0;
-    let mut ml = MoveList::new();
-    pos.generate_moves(&mut ml);
-    for &m in ml.iter_moves() {
-        if !pos.is_legal(m) {
-            continue;
-        }
-        pos.make_move_simple(m);
-        let arm_nodes = perft::perft(pos, depth - 1);
-        nodes += arm_nodes;
-        println!("{}: {arm_nodes}", m.display(pos.rules()));
-        pos.unmake_move_base();
+/// Restore the hash table saved at `path` by a previous session, if there is one.
+/// Failures are reported, but otherwise leave an empty table to start from.
+pub fn load_persistent_hash(cache: &mut Cache, path: &Path, pool: &[threadpool::WorkerThread]) {
+    if !path.exists() {
+        return;
     }
-    let elapsed = start_time.elapsed();
-    println!(
-        "info depth {depth} nodes {nodes} time {elapsed} nps {nps:.0}",
-        elapsed = elapsed.as_millis(),
-        nps = nodes as f64 / elapsed.as_secs_f64()
+    match cache.load(path, pool) {
+        Ok(()) => println!(
+            "info string loaded {}mb hash table from {}",
+            cache.size() / MEGABYTE,
+            path.display()
+        ),
+        Err(e) => println!("info string not using hash file {}: {e}", path.display()),
+    }
+}
+
+/// Save the hash table to `path` at the end of a session, reporting any failure.
+pub fn save_persistent_hash(cache: &Cache, path: &Path) {
+    if let Err(e) = cache.save(path) {
+        eprintln!(
+            "info string failed to save hash file {}: {e}",
+            path.display()
+        );
+    }
+}
+
+/// Describe the transposition table entry for the position on `board`, for debugging.
+fn describe_cache_entry(board: &Board, cache: CacheView) -> String {
+    let clock = board.fifty_move_counter();
+    let key = board.state.keys.zobrist ^ HM_CLOCK_KEYS[clock as usize];
+    let Some(hit) = cache.probe(key, 0, clock) else {
+        return "info string no hash entry for this position".to_string();
+    };
+    let mov = hit.mov.map_or_else(
+        || "none".to_string(),
+        |m| m.display(board.rules()).to_string(),
     );
+    let score = if hit.value == VALUE_NONE {
+        "none".to_string()
+    } else {
+        fmt::format_score(hit.value).to_string()
+    };
+    let bound = match hit.bound {
+        Bound::Empty => "none",
+        Bound::Upper => "upper",
+        Bound::Lower => "lower",
+        Bound::Exact => "exact",
+    };
+    format!(
+        "info string hash move {mov} score {score} bound {bound} depth {} eval {} pv {} generation {} current {}",
+        hit.depth,
+        hit.eval,
+        hit.was_pv,
+        hit.age,
+        cache.age(),
+    )
 }
 
-fn do_newgame(
+pub fn do_newgame(
     cache: &Cache,
     thread_data: &mut [Box<ThreadData>],
     pool: &[threadpool::WorkerThread],
 ) -> Result<(), UciError> {
-    cache.clear(pool);
+    // entries from the last game are often still useful, for instance when the
+    // same opening is played again, so the table may be aged rather than cleared.
+    let age_hash = thread_data
+        .first()
+        .is_some_and(|t| t.info.control.age_hash_on_new_game.load(Ordering::Relaxed));
+    if age_hash {
+        cache.age_for_new_game();
+    } else {
+        cache.clear(pool);
+    }
     for t in thread_data {
         parse_position("position startpos\n", &mut t.board)?;
         t.clear_tables();
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::threadlocal::ThreadFixture;
+
+    #[test]
+    fn register_is_acknowledged() {
+        assert_eq!(parse_register("register later"), Ok(()));
+        assert_eq!(
+            parse_register("register name Stefan MK code 4359874324"),
+            Ok(())
+        );
+        assert_eq!(
+            parse_register("register"),
+            Err(RegisterParseError::MissingArguments)
+        );
+        assert_eq!(
+            parse_register("register name code 1234"),
+            Err(RegisterParseError::MissingName)
+        );
+        assert_eq!(
+            parse_register("register name Stefan"),
+            Err(RegisterParseError::MissingCode)
+        );
+        assert!(!is_cmd("registered", "register"));
+    }
+
+    #[test]
+    fn register_does_not_disrupt_later_commands() -> anyhow::Result<()> {
+        let fixture = ThreadFixture::new();
+        let mut thread_data = Vec1::new(fixture.thread_data(Board::startpos()));
+        let mut book_rng = XorShiftState::with_seed(1);
+        let hash_file = PathBuf::from(DEFAULT_HASH_FILE);
+        let mut run = |line: &str| {
+            let session = Session {
+                cache: &fixture.cache,
+                control: &fixture.control,
+                worker_threads: &fixture.pool,
+                nnue_params: fixture.nnue_params,
+                book: None,
+                book_rng: &mut book_rng,
+                hash_file: &hash_file,
+            };
+            handle_command(line, &mut thread_data, session)
+        };
+        run("register later")?;
+        assert!(matches!(
+            run("register name Stefan"),
+            Err(UciError::Register(RegisterParseError::MissingCode))
+        ));
+        run("position startpos moves e2e4 e7e5")?;
+        assert_eq!(thread_data[0].board.ply(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn go_searchmoves() {
+        let board = Board::startpos();
+        let control = Control::default();
+        let parsed = parse_go("go searchmoves e2e4 d2d4 depth 3", &board, &control).map(
+            |(limit, searchmoves)| {
+                let searchmoves = searchmoves
+                    .iter()
+                    .map(|m| m.display(board.rules()).to_string())
+                    .collect::<Vec<_>>();
+                (limit, searchmoves)
+            },
+        );
+        assert_eq!(
+            parsed,
+            Ok((
+                SearchLimit::Depth(3),
+                vec!["e2e4".to_string(), "d2d4".to_string()]
+            ))
+        );
+        assert_eq!(
+            parse_go("go searchmoves depth 3", &board, &control),
+            Err(GoParseError::EmptySearchMoves)
+        );
+        assert!(matches!(
+            parse_go("go depth 3 searchmoves e2e5", &board, &control),
+            Err(GoParseError::InvalidSearchMove { .. })
+        ));
+    }
+
+    #[test]
+    fn probe_describes_the_cache_entry() {
+        let pool = threadpool::make_worker_threads(1);
+        let mut cache = Cache::new();
+        cache.resize(MEGABYTE, &pool);
+        cache.increase_age();
+        let board = Board::startpos();
+        assert_eq!(
+            describe_cache_entry(&board, cache.view()),
+            "info string no hash entry for this position"
+        );
+        let key = board.state.keys.zobrist ^ HM_CLOCK_KEYS[0];
+        let m = board.parse_uci("e2e4").ok();
+        cache.view().store(key, 0, m, 0, 25, Bound::Lower, 12, true);
+        cache.increase_age();
+        assert_eq!(
+            describe_cache_entry(&board, cache.view()),
+            "info string hash move e2e4 score cp 0 bound lower depth 12 eval 25 pv true generation 1 current 2"
+        );
+    }
+
+    #[test]
+    fn hash_buttons() -> anyhow::Result<()> {
+        let control = Control::default();
+        let nnue_params = NNUEParams::decompress_and_alloc()?;
+        let options = || SetOptions {
+            search_config: Config::default(),
+            hash_mb: UCI_DEFAULT_HASH_MEGABYTES,
+            threads: 1,
+            book: None,
+            hash_file: PathBuf::from(DEFAULT_HASH_FILE),
+            hash_action: None,
+            large_pages: false,
+            nnue_params,
+            small_net: None,
+        };
+        let res = parse_setoption(
+            "setoption name HashFile value analysis.hash",
+            options(),
+            &control,
+        );
+        assert!(
+            res.is_ok_and(|o| o.hash_file == Path::new("analysis.hash") && o.hash_action.is_none())
+        );
+        let res = parse_setoption("setoption name SaveHash", options(), &control);
+        assert!(res.is_ok_and(|o| o.hash_action == Some(HashAction::Save)));
+        let res = parse_setoption("setoption name LoadHash", options(), &control);
+        assert!(res.is_ok_and(|o| o.hash_action == Some(HashAction::Load)));
+        // only buttons may be sent without a value.
+        let res = parse_setoption("setoption name Hash", options(), &control);
+        assert!(matches!(
+            res,
+            Err(SetOptionParseError::ExpectedValueKeyword(_))
+        ));
+        Ok(())
+    }
+}
@@ -5,6 +5,13 @@ use std::{
 
 use crate::evaluation::MATE_SCORE;
 
+/// A monotonic clock. `std::time::Instant` panics on `wasm32-unknown-unknown`,
+/// so the browser's clock is used there instead.
+#[cfg(not(target_family = "wasm"))]
+pub use std::time::Instant;
+#[cfg(target_family = "wasm")]
+pub use web_time::Instant;
+
 pub const MAX_DEPTH: usize = 128;
 pub const INFINITY: i32 = MATE_SCORE + 1;
 pub const VALUE_NONE: i32 = INFINITY + 1;
@@ -70,6 +77,29 @@ impl<'a> BatchedAtomicCounter<'a> {
     }
 }
 
+/// Hint to the processor that the memory at `ptr` will be read soon, pulling it into the
+/// L1 cache. This is only a hint: it never faults, whatever `ptr` points to.
+#[inline]
+pub fn prefetch<T>(ptr: *const T) {
+    // SAFETY: prefetches don't access memory architecturally, so can't fault.
+    #[cfg(target_arch = "x86_64")]
+    unsafe {
+        use std::arch::x86_64::{_MM_HINT_T0, _mm_prefetch};
+        _mm_prefetch(ptr.cast::<i8>(), _MM_HINT_T0);
+    }
+    // SAFETY: as above; `prfm` only touches the cache.
+    #[cfg(target_arch = "aarch64")]
+    unsafe {
+        std::arch::asm!(
+            "prfm pldl1keep, [{ptr}]",
+            ptr = in(reg) ptr,
+            options(nostack, readonly, preserves_flags),
+        );
+    }
+    #[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+    let _ = ptr;
+}
+
 /// A transparent wrapper that aligns its contents to 64 bytes.
 #[derive(Debug, Clone, PartialEq, Eq, Default)]
 #[repr(C, align(64))]
@@ -0,0 +1,237 @@
+//! JavaScript bindings, so that the engine can run inside a browser analysis board.
+//!
+//! Build with `make wasm`, which also generates the JavaScript glue with `wasm-bindgen`.
+//! The browser has no threads to lend us, so searches run on the calling thread, and should be driven
+//! from a web worker to keep the page responsive.
+
+use wasm_bindgen::prelude::*;
+
+use crate::{
+    chess::{
+        board::{Board, CastlingNotation, GameOutcome},
+        piece::Colour,
+    },
+    nnue::network::{NNUEParams, NNUEState},
+    search::{Score, Search, SearchLimit},
+    uci::fmt::NORMALISE_TO_PAWN_VALUE,
+};
+
+/// A chess position, along with the moves that led to it.
+#[wasm_bindgen(js_name = Board)]
+pub struct JsBoard {
+    board: Board,
+}
+
+#[wasm_bindgen(js_class = Board)]
+impl JsBoard {
+    /// Set up the position given by `fen`, or the starting position if it's omitted.
+    #[wasm_bindgen(constructor)]
+    pub fn new(fen: Option<String>) -> Result<Self, JsError> {
+        let board = match fen {
+            Some(fen) => Board::from_fen(&fen).map_err(js_error)?,
+            None => Board::startpos(),
+        };
+        Ok(Self { board })
+    }
+
+    /// The FEN of the position, in Shredder-FEN for Chess960 positions.
+    pub fn fen(&self) -> String {
+        self.board.to_string()
+    }
+
+    /// The FEN of the position, with castling rights in X-FEN.
+    pub fn xfen(&self) -> String {
+        self.board.fen(CastlingNotation::XFen)
+    }
+
+    /// The side to move, as "w" or "b".
+    #[wasm_bindgen(getter)]
+    pub fn turn(&self) -> String {
+        match self.board.turn() {
+            Colour::White => "w",
+            Colour::Black => "b",
+        }
+        .into()
+    }
+
+    /// The legal moves in the position, in UCI notation.
+    #[wasm_bindgen(js_name = legalMoves)]
+    pub fn legal_moves(&self) -> Vec<String> {
+        let rules = self.board.rules();
+        self.board
+            .legal_moves()
+            .into_iter()
+            .map(|m| m.display(rules).to_string())
+            .collect()
+    }
+
+    /// Make a move given in UCI notation.
+    #[wasm_bindgen(js_name = pushUci)]
+    pub fn push_uci(&mut self, uci: &str) -> Result<(), JsError> {
+        let m = self.board.parse_uci(uci).map_err(js_error)?;
+        self.board.make_move_simple(m);
+        Ok(())
+    }
+
+    /// Make a move given in SAN, returning it in UCI notation.
+    #[wasm_bindgen(js_name = pushSan)]
+    pub fn push_san(&mut self, san: &str) -> Result<String, JsError> {
+        let m = self.board.parse_san(san).map_err(js_error)?;
+        let uci = m.display(self.board.rules()).to_string();
+        self.board.make_move_simple(m);
+        Ok(uci)
+    }
+
+    /// Take back the last move.
+    pub fn pop(&mut self) -> Result<(), JsError> {
+        if self.board.history().is_empty() {
+            return Err(JsError::new("no moves to take back"));
+        }
+        self.board.unmake_move_base();
+        Ok(())
+    }
+
+    /// The SAN of a legal move given in UCI notation.
+    pub fn san(&self, uci: &str) -> Result<String, JsError> {
+        let m = self.board.parse_uci(uci).map_err(js_error)?;
+        self.board
+            .san(m)
+            .map(|san| san.to_string())
+            .ok_or_else(|| JsError::new(&format!("illegal move {uci}")))
+    }
+
+    /// Whether the side to move is in check.
+    #[wasm_bindgen(js_name = inCheck)]
+    pub fn in_check(&self) -> bool {
+        self.board.in_check()
+    }
+
+    /// The result of the game as "1-0", "0-1", or "1/2-1/2", if it's over.
+    pub fn outcome(&self) -> Option<String> {
+        self.board.outcome().map(|outcome| {
+            match outcome {
+                GameOutcome::WhiteWin(_) => "1-0",
+                GameOutcome::BlackWin(_) => "0-1",
+                GameOutcome::Draw(_) => "1/2-1/2",
+            }
+            .into()
+        })
+    }
+
+    /// The raw network evaluation of the position in centipawns, from the side to move's
+    /// point of view.
+    pub fn evaluate(&self) -> Result<i32, JsError> {
+        let nnue_params = NNUEParams::decompress_and_alloc().map_err(js_error)?;
+        let nnue = NNUEState::new(&self.board, nnue_params);
+        Ok(nnue.evaluate(nnue_params, &self.board) * 100 / NORMALISE_TO_PAWN_VALUE)
+    }
+
+    /// The FEN of the position.
+    #[wasm_bindgen(js_name = toString)]
+    pub fn to_js_string(&self) -> String {
+        self.board.to_string()
+    }
+}
+
+/// The outcome of a search. Scores are from the side to move's point of view.
+#[wasm_bindgen(getter_with_clone)]
+pub struct SearchResult {
+    /// The best move, in UCI notation.
+    #[wasm_bindgen(js_name = bestMove)]
+    pub best_move: String,
+    /// The score in centipawns, or `undefined` if the search found a forced mate.
+    pub score: Option<i32>,
+    /// The number of moves to a forced mate, negative if the side to move is being mated,
+    /// or `undefined` if no mate was found.
+    pub mate: Option<i32>,
+    /// The principal variation, in UCI notation.
+    pub pv: Vec<String>,
+    /// The depth of the last completed iteration.
+    pub depth: usize,
+}
+
+/// A search engine, which keeps its transposition table and network between searches.
+#[wasm_bindgen(js_name = Engine)]
+pub struct JsEngine {
+    search: Search,
+}
+
+#[wasm_bindgen(js_class = Engine)]
+impl JsEngine {
+    /// Create an engine with a transposition table of `hash` megabytes, 16 if it's omitted.
+    #[wasm_bindgen(constructor)]
+    pub fn new(hash: Option<u32>) -> Result<Self, JsError> {
+        let hash = hash.unwrap_or(16) as usize;
+        let search = Search::new(1, hash).map_err(js_error)?;
+        Ok(Self { search })
+    }
+
+    /// Search `board` until exactly one of `depth`, `nodes`, or `movetime` (in milliseconds)
+    /// is reached.
+    pub fn search(
+        &mut self,
+        board: &JsBoard,
+        depth: Option<u32>,
+        nodes: Option<u32>,
+        movetime: Option<u32>,
+    ) -> Result<SearchResult, JsError> {
+        let limit = match (depth, nodes, movetime) {
+            (Some(depth), None, None) => SearchLimit::Depth(depth as usize),
+            (None, Some(nodes), None) => SearchLimit::Nodes(nodes.into()),
+            (None, None, Some(movetime)) => SearchLimit::Time(movetime.into()),
+            _ => {
+                return Err(JsError::new(
+                    "give exactly one of depth, nodes, or movetime",
+                ));
+            }
+        };
+        let board = &board.board;
+        if board.legal_moves().is_empty() {
+            return Err(JsError::new("the position has no legal moves"));
+        }
+        let result = self.search.run(board, limit, &()).map_err(js_error)?;
+        let best_move = result
+            .best_move
+            .ok_or_else(|| JsError::new("no move was found"))?;
+        let rules = board.rules();
+        let (score, mate) = match result.score {
+            Score::Centipawns(cp) => (Some(cp), None),
+            Score::Mate(moves) => (None, Some(moves)),
+        };
+        Ok(SearchResult {
+            best_move: best_move.display(rules).to_string(),
+            score,
+            mate,
+            pv: result
+                .pv
+                .iter()
+                .map(|m| m.display(rules).to_string())
+                .collect(),
+            depth: result.depth,
+        })
+    }
+
+    /// Forget everything learned from earlier searches, as before a new game.
+    pub fn clear(&mut self) {
+        self.search.clear();
+    }
+}
+
+/// Search the position given by `fen` with a new [`JsEngine`], until exactly one of `depth`,
+/// `nodes`, or `movetime` (in milliseconds) is reached, using a transposition table of `hash`
+/// megabytes.
+#[wasm_bindgen]
+pub fn search(
+    fen: &str,
+    depth: Option<u32>,
+    nodes: Option<u32>,
+    movetime: Option<u32>,
+    hash: Option<u32>,
+) -> Result<SearchResult, JsError> {
+    let board = JsBoard::new(Some(fen.into()))?;
+    JsEngine::new(hash)?.search(&board, depth, nodes, movetime)
+}
+
+fn js_error(e: impl std::fmt::Display) -> JsError {
+    JsError::new(&e.to_string())
+}
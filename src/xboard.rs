@@ -0,0 +1,658 @@
+//! A front-end for the xboard protocol (CECP), as an alternative to UCI.
+//!
+//! Unlike a UCI engine, an xboard engine keeps track of the game itself: it is sent
+//! the moves one at a time, decides for itself when it is on move, and is told about
+//! the clocks separately from the moves. The search is shared with the UCI front-end,
+//! so this module only has to translate between the two models.
+
+#![deny(
+    clippy::panic,
+    clippy::unwrap_used,
+    clippy::expect_used,
+    clippy::todo,
+    clippy::unimplemented
+)]
+
+use std::{
+    io::Write as _,
+    path::Path,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, AtomicU64, Ordering},
+    },
+};
+
+use crate::{
+    NAME, VERSION,
+    chess::{
+        board::{Board, DrawType, GameOutcome},
+        chessmove::Move,
+        fen::Fen,
+        piece::Colour,
+    },
+    errors::{UciError, XboardError},
+    evaluation::{MATE_SCORE, is_mate_score},
+    nnue::network::NNUEParams,
+    search::{LMTable, parameters::Config, search_position},
+    searchinfo::Control,
+    threadlocal::{ThreadData, make_thread_data},
+    threadpool,
+    timemgmt::SearchLimit,
+    transpositiontable::Cache,
+    uci::{
+        self, STDIN_READER_THREAD_KEEP_RUNNING, UCI_DEFAULT_HASH_MEGABYTES, UCI_MAX_HASH_MEGABYTES,
+        UCI_MAX_THREADS, fmt::NORMALISE_TO_PAWN_VALUE,
+    },
+    util::MEGABYTE,
+};
+
+/// Commands that end the engine's turn without it moving, if they arrive mid-search.
+const ABORTING_COMMANDS: [&str; 8] = [
+    "new", "force", "quit", "result", "undo", "remove", "setboard", "edit",
+];
+
+/// The time control set by `level`, `st`, and `sd`, and the clocks reported by `time` and `otim`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct TimeControl {
+    /// The number of moves in each time control period, or zero if the whole game is one period.
+    moves_per_period: u64,
+    /// The time for each period, in milliseconds.
+    base: u64,
+    /// The increment per move, in milliseconds.
+    increment: u64,
+    /// A fixed time per move, in milliseconds, from `st`.
+    move_time: Option<u64>,
+    /// A fixed search depth, from `sd`.
+    depth: Option<usize>,
+    /// Our remaining time, in milliseconds.
+    our_clock: Option<u64>,
+    /// Our opponent's remaining time, in milliseconds.
+    their_clock: Option<u64>,
+}
+
+impl Default for TimeControl {
+    fn default() -> Self {
+        // xboard always sends a time control, but be safe if it doesn't.
+        Self {
+            moves_per_period: 0,
+            base: 5 * 60_000,
+            increment: 0,
+            move_time: None,
+            depth: None,
+            our_clock: None,
+            their_clock: None,
+        }
+    }
+}
+
+impl TimeControl {
+    /// Apply a `level` command, like `level 40 5 0` or `level 0 2:30 1.5`.
+    fn set_level(&mut self, args: &str) -> Result<(), XboardError> {
+        let bad = || XboardError::BadArgument(format!("level {args}"));
+        let parts = args.split_whitespace().collect::<Vec<_>>();
+        let [mps, base, inc] = parts.as_slice() else {
+            return Err(bad());
+        };
+        self.moves_per_period = mps.parse().map_err(|_| bad())?;
+        // the base time is either minutes, or minutes and seconds.
+        self.base = match base.split_once(':') {
+            Some((minutes, seconds)) => {
+                let minutes = minutes.parse::<u64>().map_err(|_| bad())?;
+                let seconds = seconds.parse::<u64>().map_err(|_| bad())?;
+                minutes * 60_000 + seconds * 1000
+            }
+            None => base.parse::<u64>().map_err(|_| bad())? * 60_000,
+        };
+        self.increment = seconds_to_millis(inc).ok_or_else(bad)?;
+        self.move_time = None;
+        Ok(())
+    }
+
+    /// The limit for a search, when we have already made `moves_played` moves this game.
+    fn search_limit(&self, moves_played: usize) -> SearchLimit {
+        if let Some(move_time) = self.move_time {
+            return SearchLimit::Time(move_time);
+        }
+        if let Some(depth) = self.depth {
+            return SearchLimit::Depth(depth);
+        }
+        let moves_to_go = (self.moves_per_period > 0)
+            .then(|| self.moves_per_period - moves_played as u64 % self.moves_per_period);
+        SearchLimit::Dynamic {
+            our_clock: self.our_clock.unwrap_or(self.base),
+            their_clock: self.their_clock.unwrap_or(self.base),
+            our_inc: self.increment,
+            their_inc: self.increment,
+            moves_to_go,
+        }
+    }
+}
+
+/// Parse a non-negative, possibly fractional, number of seconds.
+fn seconds_to_millis(text: &str) -> Option<u64> {
+    #![allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    let seconds = text.parse::<f64>().ok()?;
+    (seconds.is_finite() && seconds >= 0.0).then(|| (seconds * 1000.0).round() as u64)
+}
+
+/// Parse a clock reading given in centiseconds, as sent by `time` and `otim`.
+fn centis_to_millis(cmd: &str, text: &str) -> Result<u64, XboardError> {
+    text.parse::<u64>()
+        .map(|centis| centis * 10)
+        .map_err(|_| XboardError::BadArgument(format!("{cmd} {text}")))
+}
+
+/// What the main loop has to do once a command has been applied to the game.
+#[derive(Debug, PartialEq, Eq)]
+enum Action {
+    /// Nothing more.
+    None,
+    /// Print a reply to the GUI.
+    Reply(String),
+    /// The position has changed, and it may now be our move.
+    Position,
+    /// A new game has begun.
+    NewGame,
+    /// Resize the hash table to this many megabytes.
+    Memory(usize),
+    /// Search with this many threads.
+    Cores(usize),
+    /// Exit the engine.
+    Quit,
+}
+
+/// The state of the game, as the GUI has described it to us.
+struct Game {
+    /// The position the game began from, set by `new` or `setboard`.
+    start: Board,
+    /// The moves played since the game began.
+    moves: Vec<Move>,
+    /// The current position.
+    board: Board,
+    /// The side we are playing, or `None` in force mode.
+    engine: Option<Colour>,
+    /// Whether to show our thinking.
+    post: bool,
+    time_control: TimeControl,
+}
+
+impl Game {
+    fn new() -> Self {
+        Self {
+            start: Board::startpos(),
+            moves: Vec::new(),
+            board: Board::startpos(),
+            engine: Some(Colour::Black),
+            post: false,
+            time_control: TimeControl::default(),
+        }
+    }
+
+    /// Is it our move?
+    fn engine_to_move(&self) -> bool {
+        self.engine == Some(self.board.turn())
+    }
+
+    /// Play `m`, which must be legal, on the game board.
+    fn play(&mut self, m: Move) {
+        self.moves.push(m);
+        self.board.make_move_simple(m);
+        self.board.zero_height();
+    }
+
+    /// Take back the last `count` moves.
+    fn take_back(&mut self, count: usize, cmd: &str) -> Result<(), XboardError> {
+        if self.moves.len() < count {
+            return Err(XboardError::NotLegalNow(cmd.to_string()));
+        }
+        self.moves.truncate(self.moves.len() - count);
+        self.board = self.start.clone();
+        for &m in &self.moves {
+            self.board.make_move_simple(m);
+        }
+        self.board.zero_height();
+        Ok(())
+    }
+
+    /// Play a move sent by the GUI, in coordinate notation or SAN.
+    fn play_user_move(&mut self, text: &str) -> Result<(), XboardError> {
+        let m = self
+            .board
+            .parse_uci(text)
+            .ok()
+            .or_else(|| self.board.parse_san(text).ok())
+            .ok_or_else(|| XboardError::IllegalMove(text.to_string()))?;
+        self.play(m);
+        Ok(())
+    }
+
+    /// The result of the game, in the form xboard expects, if it has ended.
+    fn result(&self) -> Option<&'static str> {
+        Some(match self.board.outcome()? {
+            GameOutcome::WhiteWin(_) => "1-0 {White mates}",
+            GameOutcome::BlackWin(_) => "0-1 {Black mates}",
+            GameOutcome::Draw(DrawType::Stalemate) => "1/2-1/2 {Stalemate}",
+            GameOutcome::Draw(DrawType::Repetition) => "1/2-1/2 {Draw by repetition}",
+            GameOutcome::Draw(DrawType::FiftyMoves) => "1/2-1/2 {Draw by fifty move rule}",
+            GameOutcome::Draw(DrawType::InsufficientMaterial) => "1/2-1/2 {Insufficient material}",
+            GameOutcome::Draw(_) => "1/2-1/2 {Draw}",
+        })
+    }
+
+    /// Apply a command from the GUI to the game.
+    #[allow(clippy::too_many_lines)]
+    fn handle(&mut self, input: &str) -> Result<Action, XboardError> {
+        let (cmd, args) = input
+            .split_once(' ')
+            .map_or((input, ""), |(cmd, args)| (cmd, args.trim()));
+        let bad_argument = || XboardError::BadArgument(input.to_string());
+        match cmd {
+            "protover" => Ok(Action::Reply(format!(
+                "feature done=0\n\
+                 feature myname=\"{NAME} {VERSION}\" ping=1 setboard=1 playother=1 usermove=1 \
+                 san=0 time=1 draw=0 sigint=0 sigterm=0 reuse=1 analyze=0 colors=0 \
+                 memory=1 smp=1 variants=\"normal\"\n\
+                 feature done=1"
+            ))),
+            "new" => {
+                *self = Self {
+                    post: self.post,
+                    time_control: TimeControl {
+                        depth: None,
+                        our_clock: None,
+                        their_clock: None,
+                        ..self.time_control.clone()
+                    },
+                    ..Self::new()
+                };
+                Ok(Action::NewGame)
+            }
+            "variant" if args == "normal" => Ok(Action::None),
+            "variant" => Err(bad_argument()),
+            "force" | "result" => {
+                self.engine = None;
+                Ok(Action::None)
+            }
+            "go" => {
+                self.engine = Some(self.board.turn());
+                Ok(Action::Position)
+            }
+            "playother" => {
+                self.engine = Some(!self.board.turn());
+                Ok(Action::None)
+            }
+            "usermove" => {
+                self.play_user_move(args)?;
+                Ok(Action::Position)
+            }
+            "setboard" => {
+                let fen = Fen::parse_relaxed(args).map_err(|_| XboardError::IllegalPosition)?;
+                let mut board = Board::startpos();
+                board.set_from_fen(&fen);
+                if board.validate().is_err() {
+                    return Err(XboardError::IllegalPosition);
+                }
+                self.start = board.clone();
+                self.board = board;
+                self.moves.clear();
+                Ok(Action::Position)
+            }
+            "undo" => {
+                self.take_back(1, cmd)?;
+                Ok(Action::Position)
+            }
+            "remove" => {
+                self.take_back(2, cmd)?;
+                Ok(Action::Position)
+            }
+            "level" => {
+                self.time_control.set_level(args)?;
+                Ok(Action::None)
+            }
+            "st" => {
+                self.time_control.move_time =
+                    Some(seconds_to_millis(args).ok_or_else(bad_argument)?);
+                Ok(Action::None)
+            }
+            "sd" => {
+                self.time_control.depth = Some(args.parse().map_err(|_| bad_argument())?);
+                Ok(Action::None)
+            }
+            "time" => {
+                self.time_control.our_clock = Some(centis_to_millis(cmd, args)?);
+                Ok(Action::None)
+            }
+            "otim" => {
+                self.time_control.their_clock = Some(centis_to_millis(cmd, args)?);
+                Ok(Action::None)
+            }
+            "ping" => Ok(Action::Reply(format!("pong {args}"))),
+            "post" => {
+                self.post = true;
+                Ok(Action::None)
+            }
+            "nopost" => {
+                self.post = false;
+                Ok(Action::None)
+            }
+            "memory" => args
+                .parse()
+                .ok()
+                .filter(|mb| (1..=UCI_MAX_HASH_MEGABYTES).contains(mb))
+                .map(Action::Memory)
+                .ok_or_else(bad_argument),
+            "cores" => args
+                .parse()
+                .ok()
+                .filter(|n| (1..=UCI_MAX_THREADS).contains(n))
+                .map(Action::Cores)
+                .ok_or_else(bad_argument),
+            "quit" => Ok(Action::Quit),
+            // commands that need no response from us. we don't ponder, so `hard` and
+            // `easy` are no-ops, and a `?` when we aren't thinking has nothing to interrupt.
+            "xboard" | "accepted" | "rejected" | "random" | "computer" | "name" | "rating"
+            | "ics" | "hard" | "easy" | "?" | "." | "white" | "black" => Ok(Action::None),
+            // without the usermove feature, moves are sent bare.
+            _ if args.is_empty()
+                && (self.board.parse_uci(cmd).is_ok() || self.board.parse_san(cmd).is_ok()) =>
+            {
+                self.play_user_move(cmd)?;
+                Ok(Action::Position)
+            }
+            _ => Err(XboardError::UnknownCommand(input.to_string())),
+        }
+    }
+}
+
+/// Convert a score to the scale xboard expects: centipawns, or 100000 + N for a mate in N.
+fn xboard_score(score: i32) -> i32 {
+    if is_mate_score(score) {
+        let moves_to_mate = (MATE_SCORE - score.abs() + 1) / 2;
+        score.signum() * (100_000 + moves_to_mate)
+    } else {
+        score * 100 / NORMALISE_TO_PAWN_VALUE
+    }
+}
+
+/// Set every thread's board to the current position of the game.
+fn sync_boards(thread_data: &mut [Box<ThreadData>], game: &Game) {
+    for t in thread_data {
+        t.board = game.board.clone();
+        t.nnue.reïnit_from(&t.board, t.nnue_params);
+    }
+}
+
+#[allow(clippy::too_many_lines)]
+pub fn main_loop(search_config: &Config, persistent_hash: Option<&Path>) -> Result<(), UciError> {
+    let mut worker_threads = threadpool::make_worker_threads(1);
+
+    let mut cache = Cache::new();
+    cache.resize(UCI_DEFAULT_HASH_MEGABYTES * MEGABYTE, &worker_threads);
+    if let Some(path) = persistent_hash {
+        uci::load_persistent_hash(&mut cache, path, &worker_threads);
+    }
+
+    let control = Arc::new(Control::default());
+    let nnue_params: &'static NNUEParams =
+        NNUEParams::decompress_and_alloc().map_err(|e| UciError::NnueInit(e.to_string()))?;
+
+    let (stdin, stdin_reader_handle) = uci::stdin_reader(Arc::clone(&control))?;
+    let stdin = Mutex::new(stdin);
+    let stopped = AtomicBool::new(false);
+    let nodes = AtomicU64::new(0);
+    let tbhits = AtomicU64::new(0);
+    let mut thread_data = make_thread_data(
+        &Board::startpos(),
+        cache.view(),
+        nnue_params,
+        &stopped,
+        &nodes,
+        &tbhits,
+        &control,
+        &worker_threads,
+    )
+    .map_err(|e| UciError::NnueInit(e.to_string()))?;
+    for t in &mut thread_data {
+        t.info.conf = search_config.clone();
+        t.info.lm_table = LMTable::new(&t.info.conf);
+        t.info.print_to_stdout = false;
+    }
+    thread_data[0].info.set_stdin(&stdin);
+
+    let mut game = Game::new();
+    // a command that interrupted a search, still to be handled.
+    let mut pending: Option<String> = None;
+
+    loop {
+        std::io::stdout().flush()?;
+        let line = if let Some(line) = pending.take() {
+            line
+        } else {
+            let Ok(line) = stdin
+                .lock()
+                .map_err(|_| UciError::Internal("failed to take lock on stdin"))?
+                .recv()
+            else {
+                break;
+            };
+            line
+        };
+        let input = line.trim();
+
+        let action = match game.handle(input) {
+            Ok(action) => action,
+            Err(e) => {
+                println!("{e}");
+                continue;
+            }
+        };
+
+        let mut resize = None;
+        match action {
+            Action::None => (),
+            Action::Reply(reply) => println!("{reply}"),
+            Action::Quit => break,
+            Action::NewGame => uci::do_newgame(&cache, &mut thread_data, &worker_threads)?,
+            Action::Memory(hash_mb) => resize = Some((hash_mb, thread_data.len())),
+            Action::Cores(threads) => resize = Some((cache.size() / MEGABYTE, threads)),
+            Action::Position => {
+                sync_boards(&mut thread_data, &game);
+                if let Some(result) = game.result() {
+                    println!("{result}");
+                } else if game.engine_to_move() {
+                    thread_data[0].info.clock.start();
+                    let limit = game.time_control.search_limit(game.moves.len() / 2);
+                    thread_data[0].info.clock.set_limit(limit);
+                    for t in &mut thread_data {
+                        t.searchmoves.clear();
+                    }
+                    cache.increase_age();
+                    let (score, best_move) = search_position(&worker_threads, &mut thread_data);
+                    pending = thread_data[0].info.interrupt.take();
+                    let aborted = pending.as_deref().is_some_and(|cmd| {
+                        ABORTING_COMMANDS.contains(&cmd.split_whitespace().next().unwrap_or(cmd))
+                    });
+                    if let Some(best_move) = best_move
+                        && !aborted
+                    {
+                        let t = &thread_data[0];
+                        let rules = t.board.rules();
+                        if game.post {
+                            let stm_score = if t.board.turn() == Colour::White {
+                                score
+                            } else {
+                                -score
+                            };
+                            let pv = if t.pv().moves.first() == Some(&best_move) {
+                                t.pv().moves.as_slice()
+                            } else {
+                                std::slice::from_ref(&best_move)
+                            };
+                            let pv = pv
+                                .iter()
+                                .map(|m| m.display(rules).to_string())
+                                .collect::<Vec<_>>()
+                                .join(" ");
+                            println!(
+                                "{} {} {} {} {pv}",
+                                t.completed,
+                                xboard_score(stm_score),
+                                t.info.clock.elapsed().as_millis() / 10,
+                                t.info.nodes.get_global(),
+                            );
+                        }
+                        println!("move {}", best_move.display(rules));
+                        game.play(best_move);
+                        sync_boards(&mut thread_data, &game);
+                        if let Some(result) = game.result() {
+                            println!("{result}");
+                        }
+                    }
+                }
+            }
+        }
+
+        if let Some((hash_mb, threads)) = resize {
+            let hash_changed = hash_mb != cache.size() / MEGABYTE;
+            let threads_changed = threads != thread_data.len();
+            if threads_changed {
+                worker_threads
+                    .into_iter()
+                    .for_each(threadpool::WorkerThread::join);
+                worker_threads = threadpool::make_worker_threads(threads);
+            }
+            if hash_changed || threads_changed {
+                // Drop all thread data before resizing, as they borrow the old TT.
+                std::mem::drop(thread_data);
+                if hash_changed {
+                    cache.resize(hash_mb * MEGABYTE, &worker_threads);
+                }
+                thread_data = make_thread_data(
+                    &game.board,
+                    cache.view(),
+                    nnue_params,
+                    &stopped,
+                    &nodes,
+                    &tbhits,
+                    &control,
+                    &worker_threads,
+                )
+                .map_err(|e| UciError::NnueInit(e.to_string()))?;
+                for t in &mut thread_data {
+                    t.info.conf = search_config.clone();
+                    t.info.lm_table = LMTable::new(&t.info.conf);
+                    t.info.print_to_stdout = false;
+                }
+                thread_data[0].info.set_stdin(&stdin);
+            }
+        }
+
+        if control.quit.load(Ordering::SeqCst) {
+            break;
+        }
+    }
+    if let Some(path) = persistent_hash {
+        uci::save_persistent_hash(&cache, path);
+    }
+    STDIN_READER_THREAD_KEEP_RUNNING.store(false, Ordering::SeqCst);
+    if stdin_reader_handle.is_finished() {
+        stdin_reader_handle
+            .join()
+            .map_err(|_| UciError::Thread("stdin reader thread panicked".to_string()))??;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(game: &mut Game, commands: &[&str]) -> Vec<Result<Action, XboardError>> {
+        commands.iter().map(|cmd| game.handle(cmd)).collect()
+    }
+
+    #[test]
+    fn moves_and_takebacks() {
+        let mut game = Game::new();
+        let results = run(
+            &mut game,
+            &["new", "force", "usermove e2e4", "e7e5", "usermove Nf3"],
+        );
+        assert!(results.iter().all(Result::is_ok));
+        assert_eq!(game.moves.len(), 3);
+        assert_eq!(game.engine, None);
+        assert_eq!(
+            game.handle("usermove e7e5"),
+            Err(XboardError::IllegalMove("e7e5".into()))
+        );
+        assert_eq!(game.handle("remove"), Ok(Action::Position));
+        assert_eq!(game.moves.len(), 1);
+        assert_eq!(game.board.turn(), Colour::Black);
+        assert_eq!(
+            game.handle("remove"),
+            Err(XboardError::NotLegalNow("remove".into()))
+        );
+        assert_eq!(game.handle("go"), Ok(Action::Position));
+        assert!(game.engine_to_move());
+        assert_eq!(game.handle("ping 7"), Ok(Action::Reply("pong 7".into())));
+        assert_eq!(
+            game.handle("frobnicate"),
+            Err(XboardError::UnknownCommand("frobnicate".into()))
+        );
+    }
+
+    #[test]
+    fn time_controls() {
+        let mut game = Game::new();
+        let results = run(&mut game, &["level 40 5 0", "time 12000", "otim 9000"]);
+        assert!(results.iter().all(Result::is_ok));
+        assert_eq!(
+            game.time_control.search_limit(38),
+            SearchLimit::Dynamic {
+                our_clock: 120_000,
+                their_clock: 90_000,
+                our_inc: 0,
+                their_inc: 0,
+                moves_to_go: Some(2),
+            }
+        );
+        assert_eq!(game.handle("level 0 2:30 1.5"), Ok(Action::None));
+        assert_eq!(game.time_control.base, 150_000);
+        assert_eq!(game.time_control.increment, 1500);
+        assert_eq!(game.handle("st 10"), Ok(Action::None));
+        assert_eq!(game.time_control.search_limit(0), SearchLimit::Time(10_000));
+        assert!(game.handle("level 40 five 0").is_err());
+        // `new` clears the depth limit and the clocks.
+        let results = run(&mut game, &["level 0 1 0", "sd 4"]);
+        assert!(results.iter().all(Result::is_ok));
+        assert_eq!(game.time_control.search_limit(0), SearchLimit::Depth(4));
+        assert_eq!(game.handle("new"), Ok(Action::NewGame));
+        assert!(matches!(
+            game.time_control.search_limit(0),
+            SearchLimit::Dynamic {
+                our_clock: 60_000,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn game_results() {
+        let mut game = Game::new();
+        let results = run(&mut game, &["force", "f3", "e5", "g4"]);
+        assert!(results.iter().all(Result::is_ok));
+        assert_eq!(game.result(), None);
+        assert_eq!(game.handle("Qh4"), Ok(Action::Position));
+        assert_eq!(game.result(), Some("0-1 {Black mates}"));
+        assert_eq!(
+            game.handle("setboard 7k/8/6QK/8/8/8/8/8 b - - 0 1"),
+            Ok(Action::Position)
+        );
+        assert_eq!(game.result(), Some("1/2-1/2 {Stalemate}"));
+        assert_eq!(
+            game.handle("setboard not a fen"),
+            Err(XboardError::IllegalPosition)
+        );
+    }
+}